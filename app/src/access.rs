@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use proto::{
+    client,
+    proxy::{access_rule::Kind, AccessRule},
+};
+
+/// It prints the bans and the allows of a running proxy, or sets or removes one of them
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `ban`: The subject of the ban to set or remove
+/// * `allow`: The subject of the allow to set or remove, the rules are printed without either
+/// * `delete`: Whether the rule is removed instead of set
+/// * `ttl_secs`: How long the rule lasts, 0 for ever
+/// * `reason`: Why, shown to the banned players
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    ban: Option<String>,
+    allow: Option<String>,
+    delete: bool,
+    ttl_secs: u64,
+    reason: String,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let (kind, subject) = match (ban, allow) {
+        (Some(subject), _) => (Kind::Ban, subject),
+        (_, Some(subject)) => (Kind::Allow, subject),
+        (None, None) => {
+            let rules = client
+                .list_access_rules(())
+                .await
+                .map_err(|e| anyhow!("failed to list the access rules: {}", e.message()))?
+                .into_inner()
+                .rules;
+
+            for rule in rules {
+                println!("{}", describe(&rule));
+            }
+            return Ok(());
+        }
+    };
+
+    let rule = AccessRule {
+        kind: kind as i32,
+        subject,
+        reason,
+        ttl_secs,
+        ..Default::default()
+    };
+    if delete {
+        client
+            .delete_access_rule(rule.clone())
+            .await
+            .map_err(|e| anyhow!("failed to delete the access rule: {}", e.message()))?;
+        println!("removed the {} of {}", kind_name(rule.kind), rule.subject);
+        return Ok(());
+    }
+
+    let rule = client
+        .put_access_rule(rule)
+        .await
+        .map_err(|e| anyhow!("failed to put the access rule: {}", e.message()))?
+        .into_inner();
+    println!("{}", describe(&rule));
+
+    Ok(())
+}
+
+/// It returns the line describing an access rule
+///
+/// Arguments:
+///
+/// * `rule`: The access rule
+///
+/// Returns:
+///
+/// A String
+fn describe(rule: &AccessRule) -> String {
+    let until = match rule.expires_at_ms {
+        0 => "ever".to_string(),
+        expires_at_ms => expires_at_ms.to_string(),
+    };
+    let mut line = format!(
+        "{} {} {} until {}",
+        rule.created_at_ms,
+        kind_name(rule.kind),
+        rule.subject,
+        until
+    );
+    if !rule.reason.is_empty() {
+        line.push_str(&format!(": {}", rule.reason));
+    }
+    line
+}
+
+/// It returns the name of the kind of an access rule
+///
+/// Arguments:
+///
+/// * `kind`: The value of the `AccessRule.Kind` enum
+///
+/// Returns:
+///
+/// `ban` or `allow`
+fn kind_name(kind: i32) -> &'static str {
+    match Kind::from_i32(kind) {
+        Some(Kind::Allow) => "allow",
+        _ => "ban",
+    }
+}
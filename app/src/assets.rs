@@ -0,0 +1,90 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::StatusAsset};
+
+/// It prints the MOTDs and the favicons of a running proxy, or sets or removes the ones of a
+/// hostname
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname whose asset is set or removed, none to print the assets
+/// * `motd`: The MOTD template
+/// * `favicon`: The favicon, as a `data:image/png;base64,` URI
+/// * `delete`: Whether the asset is removed instead of set
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: Option<String>,
+    motd: Option<String>,
+    favicon: Option<String>,
+    delete: bool,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let hostname = match hostname {
+        Some(hostname) => hostname,
+        None => {
+            let assets = client
+                .list_status_assets(())
+                .await
+                .map_err(|e| anyhow!("failed to list the status assets: {}", e.message()))?
+                .into_inner()
+                .assets;
+
+            for asset in assets {
+                println!("{}", describe(&asset));
+            }
+            return Ok(());
+        }
+    };
+
+    let asset = StatusAsset {
+        hostname,
+        motd: motd.unwrap_or_default(),
+        favicon: favicon.unwrap_or_default(),
+    };
+    if delete {
+        client
+            .delete_status_asset(asset.clone())
+            .await
+            .map_err(|e| anyhow!("failed to delete the status asset: {}", e.message()))?;
+        println!("removed the status asset of {}", asset.hostname);
+        return Ok(());
+    }
+
+    let asset = client
+        .put_status_asset(asset)
+        .await
+        .map_err(|e| anyhow!("failed to put the status asset: {}", e.message()))?
+        .into_inner();
+    println!("{}", describe(&asset));
+
+    Ok(())
+}
+
+/// It returns the line describing a status asset, the favicon by its size
+///
+/// Arguments:
+///
+/// * `asset`: The status asset
+///
+/// Returns:
+///
+/// A String
+fn describe(asset: &StatusAsset) -> String {
+    let favicon = match asset.favicon.len() {
+        0 => "no favicon".to_string(),
+        len => format!("a favicon of {} bytes", len),
+    };
+    let motd = match asset.motd.as_str() {
+        "" => "the message of the proxy".to_string(),
+        motd => format!("{:?}", motd),
+    };
+
+    format!("{}: {}, {}", asset.hostname, motd, favicon)
+}
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::ListBackendRequest};
+
+/// It prints the backends of a running proxy, or the ones matching a label selector
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `selector`: The label selector, e.g. `env=prod,region=eu`, none for every backend
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(endpoint: String, token: Option<String>, selector: Option<String>) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let mut backends = client
+        .list_backend(ListBackendRequest {
+            selector: selector.unwrap_or_default(),
+        })
+        .await
+        .map_err(|e| anyhow!("failed to list the backends: {}", e.message()))?
+        .into_inner();
+
+    while let Some(backend) = backends
+        .message()
+        .await
+        .map_err(|e| anyhow!("failed to list the backends: {}", e.message()))?
+    {
+        let mut labels: Vec<_> = backend
+            .labels
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        labels.sort();
+        println!(
+            "{} version {} at {}:{} {}",
+            backend.hostname,
+            backend.version,
+            backend.redirect_ip,
+            backend.redirect_port,
+            labels.join(",")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::ClearBansRequest};
+
+/// It prints the addresses a running proxy banned, or lifts their bans
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `clear`: The address to unban, `all` for every address, none to print the bans
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(endpoint: String, token: Option<String>, clear: Option<String>) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    if let Some(ip) = clear {
+        let ip = match ip.as_str() {
+            "all" => String::new(),
+            _ => ip,
+        };
+        let cleared = client
+            .clear_bans(ClearBansRequest { ip })
+            .await
+            .map_err(|e| anyhow!("failed to clear the bans: {}", e.message()))?
+            .into_inner()
+            .cleared;
+        println!("lifted {} bans", cleared);
+        return Ok(());
+    }
+
+    let bans = client
+        .list_bans(())
+        .await
+        .map_err(|e| anyhow!("failed to list the bans: {}", e.message()))?
+        .into_inner()
+        .bans;
+
+    for ban in bans {
+        println!(
+            "{} {} until {}: {} strikes, the last one a {}",
+            ban.banned_at_ms, ban.ip, ban.expires_at_ms, ban.strikes, ban.offense
+        );
+    }
+
+    Ok(())
+}
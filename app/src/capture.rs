@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::CaptureRequest};
+
+/// It starts or stops capturing the connections of a hostname on a running proxy, or prints the
+/// hostnames captured
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname to capture, none to print the captures
+/// * `max_kib`: The first KiB of each direction recorded, 0 for the default
+/// * `connections`: The connections captured before the capture stops, 0 until it is stopped
+/// * `stop`: Whether the capture of the hostname is stopped rather than started
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: Option<String>,
+    max_kib: u32,
+    connections: u32,
+    stop: bool,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let Some(hostname) = hostname else {
+        let captures = client
+            .list_captures(())
+            .await
+            .map_err(|e| anyhow!("failed to list the captures: {}", e.message()))?
+            .into_inner()
+            .captures;
+        for capture in captures {
+            let connections = match capture.connections {
+                0 => "every connection".to_string(),
+                connections => format!("{} connections left", connections),
+            };
+            println!(
+                "{} {} KiB, {}",
+                capture.hostname, capture.max_kib, connections
+            );
+        }
+        return Ok(());
+    };
+
+    let request = CaptureRequest {
+        hostname: hostname.clone(),
+        max_kib,
+        connections,
+    };
+    if stop {
+        client
+            .stop_capture(request)
+            .await
+            .map_err(|e| anyhow!("failed to stop the capture: {}", e.message()))?;
+        println!("stopped capturing {}", hostname);
+    } else {
+        client
+            .start_capture(request)
+            .await
+            .map_err(|e| anyhow!("failed to start the capture: {}", e.message()))?;
+        println!("capturing {}", hostname);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,363 @@
+#[cfg(feature = "grpc")]
+use std::net::IpAddr;
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+#[cfg(feature = "grpc")]
+use config::secret::read_secret_file;
+use config::{LogFormat, ProxyConfig, Secret};
+#[cfg(feature = "grpc")]
+use importer::ImportFormat;
+
+/// The gRPC API of a local proxy, used by the client commands
+#[cfg(feature = "grpc")]
+const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:65535";
+
+/// A reverse proxy for Minecraft servers using gRPC for configuration
+#[derive(Debug, Parser)]
+#[command(name = "kubecraft-proxy", bin_name = "kubecraft-proxy", version, about)]
+pub struct Cli {
+    /// The TOML or YAML configuration file
+    #[arg(short, long, env = "CONFIG_PATH")]
+    pub config: Option<PathBuf>,
+
+    /// The address accepting the Minecraft clients, e.g. 0.0.0.0:25565
+    #[arg(long)]
+    pub proxy_bind: Option<SocketAddr>,
+
+    /// The address of the gRPC API configuring the proxy, e.g. 127.0.0.1:65535
+    #[arg(long)]
+    pub control_bind: Option<SocketAddr>,
+
+    /// The format of the log lines, `text` or `json`
+    #[arg(long)]
+    pub log_format: Option<LogFormat>,
+
+    /// The API token of the gRPC API, required by the proxy and sent by the client commands
+    #[arg(long, env = "LISTENER_TOKEN", hide_env_values = true, global = true)]
+    pub token: Option<String>,
+
+    /// The file holding the API token, e.g. a mounted Kubernetes Secret
+    #[arg(
+        long,
+        env = "LISTENER_TOKEN_FILE",
+        global = true,
+        conflicts_with = "token"
+    )]
+    pub token_file: Option<PathBuf>,
+
+    /// Check the configuration, then exit without starting the proxy
+    #[arg(long)]
+    pub validate_config: bool,
+
+    /// Run as a Windows service, started and stopped by the service control manager
+    #[cfg(all(windows, feature = "windows-service"))]
+    #[arg(long)]
+    pub service: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[cfg(feature = "grpc")]
+    /// Import the forced hosts of a BungeeCord or Velocity configuration into a running proxy
+    Import {
+        /// The format of the file, `bungeecord` or `velocity`
+        format: ImportFormat,
+        /// The BungeeCord `config.yml` or Velocity `velocity.toml`
+        file: PathBuf,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the configuration a running proxy uses, with the secrets redacted
+    DumpConfig {
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the latest kicks, routing misses, backend failures and bans of a running proxy
+    RecentEvents {
+        /// How many events to print at most, 0 for all of them
+        #[arg(long, default_value_t = 0)]
+        limit: u32,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the addresses a running proxy banned for misbehaving, or lift their bans
+    Bans {
+        /// The address to unban, `all` to unban every address
+        #[arg(long)]
+        clear: Option<String>,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the bans and the allows of a running proxy, or set or remove one of them
+    Access {
+        /// The address, CIDR block or username to ban
+        #[arg(long, conflicts_with = "allow")]
+        ban: Option<String>,
+        /// The address, CIDR block or username never banned
+        #[arg(long)]
+        allow: Option<String>,
+        /// Remove the ban or the allow instead of setting it
+        #[arg(long)]
+        delete: bool,
+        /// How long the rule lasts, in seconds, 0 for ever
+        #[arg(long, default_value_t = 0)]
+        ttl_secs: u64,
+        /// Why, shown to the banned players
+        #[arg(long, default_value = "")]
+        reason: String,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the MOTDs and the favicons the proxy answers the status pings with, or set or remove
+    /// the ones of a hostname
+    Assets {
+        /// The hostname whose MOTD and favicon are set or removed
+        #[arg(long)]
+        hostname: Option<String>,
+        /// The MOTD template, `{message}` is replaced with the message of the proxy and
+        /// `{hostname}` with the hostname
+        #[arg(long, requires = "hostname")]
+        motd: Option<String>,
+        /// The favicon, a 64x64 PNG as a `data:image/png;base64,` URI
+        #[arg(long, requires = "hostname")]
+        favicon: Option<String>,
+        /// Remove the MOTD and the favicon of the hostname instead of setting them
+        #[arg(long, requires = "hostname", conflicts_with_all = ["motd", "favicon"])]
+        delete: bool,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Turn the DDoS mode of a running proxy on or off, or print whether it is on
+    DdosMode {
+        /// Answer the status pings from the proxy, gate and throttle the logins harder, and drop
+        /// the unknown hostnames
+        #[arg(long, conflicts_with = "off")]
+        on: bool,
+        /// Go back to the normal mode
+        #[arg(long)]
+        off: bool,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Capture the connections of a hostname on a running proxy to files, or print the captures
+    Capture {
+        /// The hostname to capture, the captures are printed without it
+        #[arg(long)]
+        hostname: Option<String>,
+        /// The first KiB of each direction of a connection recorded, 0 for 64
+        #[arg(long, default_value_t = 0)]
+        max_kib: u32,
+        /// The connections captured before the capture stops, 0 until it is stopped
+        #[arg(long, default_value_t = 0)]
+        connections: u32,
+        /// Stop capturing the hostname instead
+        #[arg(long, requires = "hostname")]
+        stop: bool,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the backends of a running proxy, or the ones matching a label selector
+    Backends {
+        /// A label selector, e.g. `env=prod,region=eu`, `tier!=free` or `!canary`
+        #[arg(long)]
+        selector: Option<String>,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Print the players relayed to every version of the backends of a running proxy, the legacy
+    /// ones left on the previous address of an updated backend included
+    Sessions {
+        /// The hostname of the backend, every backend without it
+        #[arg(long)]
+        hostname: Option<String>,
+        /// A label selector of the backends, e.g. `env=prod,region=eu`
+        #[arg(long)]
+        selector: Option<String>,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Follow the records logged by a running proxy, like `kubectl logs -f` without access to its
+    /// pod
+    Logs {
+        /// The least severe level printed: error, warn, info, debug or trace, info without it
+        #[arg(long)]
+        level: Option<String>,
+        /// The target printed with its modules, e.g. `proxy`, every target without it
+        #[arg(long)]
+        target: Option<String>,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Follow the activity of the backends of a running proxy: active players, joins per minute
+    /// and bytes per second
+    Stats {
+        /// The hostname of the backend, every backend without it
+        #[arg(long)]
+        hostname: Option<String>,
+        /// The seconds between two snapshots, 5 without it
+        #[arg(long)]
+        interval_secs: Option<u32>,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Ping the stored target of a backend of a running proxy and print its status
+    Probe {
+        /// The hostname of the backend
+        #[arg(long)]
+        hostname: String,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Validate a route against a running proxy without storing it, e.g. in a CI pipeline
+    Validate {
+        /// The hostname of the route
+        #[arg(long)]
+        hostname: String,
+        /// The backend of the route, `host` or `host:port`
+        #[arg(long)]
+        target: String,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Move the players of a backend of a running proxy to another server with the transfer of
+    /// 1.20.5, e.g. before the backend shuts down
+    Transfer {
+        /// The hostname of the backend
+        #[arg(
+            long,
+            required_unless_present = "selector",
+            conflicts_with = "selector"
+        )]
+        hostname: Option<String>,
+        /// A label selector of the backends whose players move, e.g. `env=staging`
+        #[arg(long)]
+        selector: Option<String>,
+        /// The server the players join, `host` or `host:port`
+        #[arg(long)]
+        target: String,
+        /// The gRPC API of the proxy
+        #[arg(default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "grpc")]
+    /// Register the Minecraft server of this pod with a central proxy until the pod stops, as a
+    /// sidecar container
+    Sidecar {
+        /// The hostname the players join, the `kubecraft.cloud/hostname` annotation of the pod
+        /// without it
+        #[arg(long, env = "KUBECRAFT_HOSTNAME")]
+        hostname: Option<String>,
+        /// The annotations of the pod, mounted by the downward API
+        #[arg(
+            long,
+            env = "POD_ANNOTATIONS",
+            default_value = "/etc/podinfo/annotations"
+        )]
+        annotations: PathBuf,
+        /// The IP of the pod, from the downward API
+        #[arg(long, env = "POD_IP")]
+        pod_ip: IpAddr,
+        /// The port of the Minecraft server in the pod
+        #[arg(long, default_value_t = 25565)]
+        port: u16,
+        /// A label of the backend, `key=value`, repeated for several
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// The seconds between two checks of the registration, registered again when it is
+        /// missing, e.g. after the central proxy restarted
+        #[arg(long, default_value_t = 30)]
+        refresh_secs: u64,
+        /// The gRPC API of the central proxy
+        #[arg(env = "KUBECRAFT_PROXY_ENDPOINT", default_value = DEFAULT_ENDPOINT)]
+        endpoint: String,
+    },
+    #[cfg(feature = "kubernetes")]
+    /// Print the CustomResourceDefinition of the `MinecraftServer` resources reconciled by the operator
+    Crd,
+}
+
+impl Cli {
+    /// It loads the configuration of the proxy, with the command-line flags applied on top
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyConfig>, an error when the configuration can't be read or is invalid
+    pub fn load_config(&self) -> Result<ProxyConfig> {
+        let mut config = ProxyConfig::load(self.config.clone())?;
+        self.override_config(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// It applies the command-line flags on top of the configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration loaded from the file and the environment.
+    pub fn override_config(&self, config: &mut ProxyConfig) {
+        if let Some(addr) = self.proxy_bind {
+            config.proxy.host = addr.ip().to_string();
+            config.proxy.port = addr.port();
+        }
+        if let Some(addr) = self.control_bind {
+            config.listener.host = addr.ip().to_string();
+            config.listener.port = addr.port();
+        }
+        if let Some(format) = self.log_format {
+            config.log.format = format;
+        }
+        if let Some(file) = &self.token_file {
+            config.listener.token = Some(Secret::File { file: file.clone() });
+        }
+        if let Some(token) = &self.token {
+            config.listener.token = Some(Secret::Value(token.clone()));
+        }
+    }
+
+    /// It returns the API token the client commands send, if any
+    ///
+    /// Returns:
+    ///
+    /// A Result<Option<String>>
+    #[cfg(feature = "grpc")]
+    pub fn client_token(&self) -> Result<Option<String>> {
+        match (&self.token, &self.token_file) {
+            (Some(token), _) => Ok(Some(token.clone())),
+            (None, Some(file)) => read_secret_file(file).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::DdosModeState};
+
+/// It turns the DDoS mode of a running proxy on or off, or prints whether it is on
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `enabled`: Whether the proxy is put in DDoS mode, none to print the mode
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(endpoint: String, token: Option<String>, enabled: Option<bool>) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let state = match enabled {
+        Some(enabled) => client
+            .set_ddos_mode(DdosModeState { enabled })
+            .await
+            .map_err(|e| anyhow!("failed to set the DDoS mode: {}", e.message()))?,
+        None => client
+            .get_ddos_mode(())
+            .await
+            .map_err(|e| anyhow!("failed to get the DDoS mode: {}", e.message()))?,
+    };
+    match state.into_inner().enabled {
+        true => println!("DDoS mode on"),
+        false => println!("DDoS mode off"),
+    }
+
+    Ok(())
+}
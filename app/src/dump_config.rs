@@ -0,0 +1,29 @@
+use anyhow::{anyhow, Result};
+use proto::client;
+
+/// It prints the configuration a running proxy currently uses, with the secrets redacted
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(endpoint: String, token: Option<String>) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let dump = client
+        .get_config(())
+        .await
+        .map_err(|e| anyhow!("failed to get configuration: {}", e.message()))?
+        .into_inner();
+
+    if !dump.path.is_empty() {
+        println!("# loaded from {}", dump.path);
+    }
+    print!("{}", dump.content);
+
+    Ok(())
+}
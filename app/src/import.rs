@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use importer::ImportFormat;
+use proto::{
+    client,
+    proxy::{import_routes_request::Format, ImportRoutesRequest},
+};
+
+/// It reads a BungeeCord or Velocity configuration file and imports its forced hosts
+/// into a running proxy
+///
+/// Arguments:
+///
+/// * `format`: The format of the file
+/// * `path`: The path of the file
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    format: ImportFormat,
+    path: &Path,
+    endpoint: String,
+    token: Option<String>,
+) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+
+    let format = match format {
+        ImportFormat::BungeeCord => Format::Bungeecord,
+        ImportFormat::Velocity => Format::Velocity,
+    };
+
+    let mut client = client::connect(endpoint, token).await?;
+
+    let batch = client
+        .import_routes(ImportRoutesRequest {
+            format: format as i32,
+            content,
+        })
+        .await
+        .map_err(|e| anyhow!("failed to import routes: {}", e.message()))?
+        .into_inner();
+
+    for backend in batch.events.into_iter().filter_map(|event| event.backend) {
+        println!(
+            "imported {} -> {}:{} (version {})",
+            backend.hostname, backend.redirect_ip, backend.redirect_port, backend.version
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::LogsRequest};
+
+/// It prints the records logged by a running proxy as they are written, until the proxy stops
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `level`: The least severe level printed, `info` when none
+/// * `target`: The target printed with its modules, every target when none
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    level: Option<String>,
+    target: Option<String>,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let mut records = client
+        .stream_logs(LogsRequest {
+            level: level.unwrap_or_default(),
+            target: target.unwrap_or_default(),
+        })
+        .await
+        .map_err(|e| anyhow!("failed to stream the logs: {}", e.message()))?
+        .into_inner();
+
+    while let Some(record) = records
+        .message()
+        .await
+        .map_err(|e| anyhow!("the log stream failed: {}", e.message()))?
+    {
+        println!(
+            "[{} {:<5} {}] {}",
+            record.timestamp_ms,
+            record.level.to_uppercase(),
+            record.target,
+            record.message
+        );
+    }
+
+    Ok(())
+}
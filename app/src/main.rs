@@ -1,20 +1,564 @@
 use anyhow::Result;
-use std::env;
+use clap::Parser;
+use config::{LogFormat, ProxyConfig, RuntimeFlavor};
+use listener::event::Event;
+use shared::{
+    logs::{LogLevel, LogRecord},
+    metadata::PodMetadata,
+};
+use std::{env, future::Future, io::Write};
+use tokio::{
+    runtime::{Builder, Handle, Runtime},
+    sync::{broadcast, mpsc, oneshot},
+};
 
-use proxy::Proxy;
+#[cfg(feature = "kubernetes")]
+use kube::CustomResourceExt;
+#[cfg(feature = "kubernetes")]
+use operator::crd::MinecraftServer;
+use proxy::{access::ACCESS_TARGET, handoff, Proxy};
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    if env::var("RUST_LOG").is_err() {
-        env::set_var("RUST_LOG", "info");
+use crate::cli::{Cli, Command};
+
+#[cfg(feature = "grpc")]
+mod access;
+#[cfg(feature = "grpc")]
+mod assets;
+#[cfg(feature = "grpc")]
+mod backends;
+#[cfg(feature = "grpc")]
+mod bans;
+#[cfg(feature = "grpc")]
+mod capture;
+mod cli;
+#[cfg(feature = "grpc")]
+mod ddos_mode;
+#[cfg(feature = "grpc")]
+mod dump_config;
+#[cfg(feature = "grpc")]
+mod import;
+#[cfg(feature = "grpc")]
+mod logs;
+#[cfg(feature = "grpc")]
+mod probe;
+#[cfg(feature = "grpc")]
+mod recent_events;
+#[cfg(all(windows, feature = "windows-service"))]
+mod service;
+#[cfg(feature = "grpc")]
+mod sessions;
+#[cfg(feature = "grpc")]
+mod sidecar;
+#[cfg(feature = "grpc")]
+mod stats;
+#[cfg(feature = "grpc")]
+mod transfer;
+#[cfg(feature = "grpc")]
+mod validate;
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Some(command) = &cli.command {
+        return runtime(RuntimeFlavor::MultiThread)?.block_on(run_command(&cli, command));
+    }
+
+    #[cfg(all(windows, feature = "windows-service"))]
+    if cli.service {
+        return service::run();
+    }
+
+    let config = cli.load_config()?;
+
+    if cli.validate_config {
+        println!("configuration is valid");
+        return Ok(());
     }
-    env_logger::init();
 
+    run(config, shutdown_signal(), None)
+}
+
+/// It runs the proxy until it is shut down, on the runtimes of its configuration
+///
+/// Arguments:
+///
+/// * `config`: The configuration of the proxy, already validated.
+/// * `shutdown`: Resolves once the proxy must stop, the players are drained first.
+/// * `reloads`: The requests to reload the configuration of the service control manager, none
+///   outside of a Windows service.
+///
+/// Returns:
+///
+/// A Result<()>
+fn run(
+    config: ProxyConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    reloads: Option<mpsc::UnboundedReceiver<()>>,
+) -> Result<()> {
+    let logs = init_logger(&config);
+
+    // the players are relayed apart from the gRPC API and the events, on threads of their own
+    let data_plane = match config.runtime.data_plane_threads {
+        0 => None,
+        threads => Some(
+            Builder::new_multi_thread()
+                .worker_threads(threads)
+                .thread_name("data-plane")
+                .enable_all()
+                .build()?,
+        ),
+    };
+    let control_plane = runtime(config.runtime.control_plane)?;
+    control_plane.block_on(serve(
+        config,
+        data_plane.as_ref().map(|runtime| runtime.handle().clone()),
+        logs,
+        shutdown,
+        reloads,
+    ))?;
+
+    // the connections still open after the drain timeout don't hold the exit
+    if let Some(data_plane) = data_plane {
+        data_plane.shutdown_background();
+    }
+    Ok(())
+}
+
+/// It builds a runtime of the binary
+///
+/// Arguments:
+///
+/// * `flavor`: The kind of the runtime.
+///
+/// Returns:
+///
+/// A Result<Runtime>
+fn runtime(flavor: RuntimeFlavor) -> Result<Runtime> {
+    let mut builder = match flavor {
+        RuntimeFlavor::MultiThread => Builder::new_multi_thread(),
+        RuntimeFlavor::CurrentThread => Builder::new_current_thread(),
+    };
+    Ok(builder.enable_all().build()?)
+}
+
+/// It runs a command of the CLI instead of the proxy
+///
+/// Arguments:
+///
+/// * `cli`: The arguments of the binary.
+/// * `command`: The command to run.
+///
+/// Returns:
+///
+/// A Result<()>
+#[cfg_attr(not(feature = "grpc"), allow(unused_variables))]
+async fn run_command(cli: &Cli, command: &Command) -> Result<()> {
+    match command {
+        #[cfg(feature = "grpc")]
+        Command::Import {
+            format,
+            file,
+            endpoint,
+        } => import::run(*format, file, endpoint.clone(), cli.client_token()?).await,
+        #[cfg(feature = "grpc")]
+        Command::DumpConfig { endpoint } => {
+            dump_config::run(endpoint.clone(), cli.client_token()?).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::RecentEvents { limit, endpoint } => {
+            recent_events::run(endpoint.clone(), cli.client_token()?, *limit).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Bans { clear, endpoint } => {
+            bans::run(endpoint.clone(), cli.client_token()?, clear.clone()).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Access {
+            ban,
+            allow,
+            delete,
+            ttl_secs,
+            reason,
+            endpoint,
+        } => {
+            access::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                ban.clone(),
+                allow.clone(),
+                *delete,
+                *ttl_secs,
+                reason.clone(),
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Assets {
+            hostname,
+            motd,
+            favicon,
+            delete,
+            endpoint,
+        } => {
+            assets::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                motd.clone(),
+                favicon.clone(),
+                *delete,
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::DdosMode { on, off, endpoint } => {
+            let enabled = match (on, off) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            ddos_mode::run(endpoint.clone(), cli.client_token()?, enabled).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Capture {
+            hostname,
+            max_kib,
+            connections,
+            stop,
+            endpoint,
+        } => {
+            capture::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                *max_kib,
+                *connections,
+                *stop,
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Backends { selector, endpoint } => {
+            backends::run(endpoint.clone(), cli.client_token()?, selector.clone()).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Sessions {
+            hostname,
+            selector,
+            endpoint,
+        } => {
+            sessions::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                selector.clone(),
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Logs {
+            level,
+            target,
+            endpoint,
+        } => {
+            logs::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                level.clone(),
+                target.clone(),
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Stats {
+            hostname,
+            interval_secs,
+            endpoint,
+        } => {
+            stats::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                *interval_secs,
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Probe { hostname, endpoint } => {
+            probe::run(endpoint.clone(), cli.client_token()?, hostname.clone()).await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Validate {
+            hostname,
+            target,
+            endpoint,
+        } => {
+            validate::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                target.clone(),
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Transfer {
+            hostname,
+            selector,
+            target,
+            endpoint,
+        } => {
+            transfer::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                hostname.clone(),
+                selector.clone(),
+                target.clone(),
+            )
+            .await
+        }
+        #[cfg(feature = "grpc")]
+        Command::Sidecar {
+            hostname,
+            annotations,
+            pod_ip,
+            port,
+            labels,
+            refresh_secs,
+            endpoint,
+        } => {
+            let pod = sidecar::Pod::resolve(hostname.clone(), annotations, *pod_ip, *port, labels)?;
+            sidecar::run(
+                endpoint.clone(),
+                cli.client_token()?,
+                pod,
+                std::time::Duration::from_secs(*refresh_secs),
+                shutdown_signal(),
+            )
+            .await
+        }
+        #[cfg(feature = "kubernetes")]
+        Command::Crd => {
+            print!("{}", serde_yaml::to_string(&MinecraftServer::crd())?);
+            Ok(())
+        }
+        // every command is left out of this build
+        #[cfg(not(any(feature = "grpc", feature = "kubernetes")))]
+        _ => match *command {},
+    }
+}
+
+/// It runs the proxy until it is shut down
+///
+/// Arguments:
+///
+/// * `config`: The configuration of the proxy, already validated.
+/// * `data_plane`: The runtime relaying the players, the current one when none.
+/// * `logs`: The sender of the records logged, streamed by the gRPC API.
+/// * `shutdown`: Resolves once the proxy must stop.
+/// * `reloads`: The requests to reload the configuration, besides the gRPC API and the signals.
+///
+/// Returns:
+///
+/// A Result<()>
+async fn serve(
+    config: ProxyConfig,
+    data_plane: Option<Handle>,
+    logs: broadcast::Sender<LogRecord>,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    reloads: Option<mpsc::UnboundedReceiver<()>>,
+) -> Result<()> {
     log::info!(target: "kubecraft-proxy", "starting up");
 
-    let proxy = Proxy::new();
-    proxy.start().await?;
+    // a Ctrl-C or a SIGTERM lets the players leave before the proxy stops, like a drain
+    let mut builder = Proxy::builder(config)
+        .pod_metadata(PodMetadata::from_env())
+        .logs(logs)
+        .shutdown(shutdown);
+    // the socket stays open across the restarts when systemd passes it
+    if let Some(listener) = handoff::systemd_listeners()?.remove("proxy") {
+        builder = builder.proxy_listener(listener);
+    }
+    if let Some(data_plane) = data_plane {
+        builder = builder.data_plane(data_plane);
+    }
+    let proxy = builder.build()?;
+    if let Some(reloads) = reloads {
+        tokio::spawn(forward_reloads(reloads, proxy.events()));
+    }
+    proxy.start().await?.wait().await?;
 
     log::info!(target: "kubecraft-proxy", "shutting down");
     Ok(())
 }
+
+/// It reloads the configuration of the proxy on every request, like a `ReloadConfig` of the gRPC
+/// API
+///
+/// Arguments:
+///
+/// * `reloads`: The requests to reload the configuration.
+/// * `events`: The sender of the events of the proxy.
+async fn forward_reloads(mut reloads: mpsc::UnboundedReceiver<()>, events: mpsc::Sender<Event>) {
+    while reloads.recv().await.is_some() {
+        log::info!("reload requested, reloading configuration");
+        let (tx, rx) = oneshot::channel();
+        if events.send(Event::ReloadConfig(tx)).await.is_err() {
+            return;
+        }
+        if let Ok(Err(e)) = rx.await {
+            log::error!("failed to reload configuration: {:#}", e);
+        }
+    }
+}
+
+/// It resolves once the proxy must stop: on Ctrl-C, on the SIGTERM sent by systemd or Kubernetes
+/// on unix, or when the console closes or the system shuts down on Windows
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {},
+                _ = terminate.recv() => {},
+            }
+            return;
+        }
+    }
+
+    // Windows gives a few seconds to the processes of a closing console, the drain may be cut short
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows::{ctrl_close, ctrl_shutdown};
+
+        if let (Ok(mut close), Ok(mut shutdown)) = (ctrl_close(), ctrl_shutdown()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {},
+                _ = close.recv() => {},
+                _ = shutdown.recv() => {},
+            }
+            return;
+        }
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// It initializes the logger with the level and the format of the configuration
+///
+/// Arguments:
+///
+/// * `config`: The configuration of the proxy
+///
+/// Returns:
+///
+/// The sender of the records logged, to stream them through the gRPC API
+fn init_logger(config: &ProxyConfig) -> broadcast::Sender<LogRecord> {
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", &config.log.level);
+    }
+
+    // the pod, namespace and node of the downward API tell the replicas apart
+    let metadata = match config.kubernetes.metadata {
+        true => PodMetadata::from_env().labels(),
+        false => Vec::new(),
+    };
+
+    let mut builder = env_logger::Builder::from_default_env();
+    if config.log.format == LogFormat::Json {
+        builder.format(move |buf, record| {
+            let mut line = serde_json::json!({
+                "timestamp": buf.timestamp().to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            // the fields of an access log record are merged into the line
+            if record.target() == ACCESS_TARGET {
+                if let Ok(serde_json::Value::Object(fields)) =
+                    serde_json::from_str(line["message"].as_str().unwrap_or_default())
+                {
+                    line["message"] = serde_json::Value::from("connection ended");
+                    for (name, value) in fields {
+                        line[name] = value;
+                    }
+                }
+            }
+            for (name, value) in &metadata {
+                line[*name] = serde_json::Value::from(value.as_str());
+            }
+            writeln!(buf, "{}", line)
+        });
+    } else if let Some(pod) = metadata
+        .iter()
+        .find_map(|(name, value)| (*name == "pod").then(|| value.clone()))
+    {
+        builder.format(move |buf, record| {
+            writeln!(
+                buf,
+                "[{} {:<5} {} {}] {}",
+                buf.timestamp(),
+                record.level(),
+                pod,
+                record.target(),
+                record.args()
+            )
+        });
+    }
+
+    let (records, _) = broadcast::channel(config.channels.logs);
+    let logger = StreamedLogger {
+        inner: builder.build(),
+        records: records.clone(),
+    };
+    log::set_max_level(logger.inner.filter());
+    log::set_boxed_logger(Box::new(logger)).expect("the logger is initialized once");
+    records
+}
+
+/// A logger writing the records with env_logger, which also sends them to the subscribers of
+/// `StreamLogs`
+///
+/// Properties:
+///
+/// * `inner`: The logger writing the records.
+/// * `records`: The sender of the records, nothing is sent without subscribers.
+struct StreamedLogger {
+    inner: env_logger::Logger,
+    records: broadcast::Sender<LogRecord>,
+}
+
+impl log::Log for StreamedLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        self.inner.log(record);
+
+        if self.records.receiver_count() > 0 {
+            let level = match record.level() {
+                log::Level::Error => LogLevel::Error,
+                log::Level::Warn => LogLevel::Warn,
+                log::Level::Info => LogLevel::Info,
+                log::Level::Debug => LogLevel::Debug,
+                log::Level::Trace => LogLevel::Trace,
+            };
+            let record = LogRecord::now(
+                level,
+                record.target().to_string(),
+                record.args().to_string(),
+            );
+            let _ = self.records.send(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
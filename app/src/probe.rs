@@ -0,0 +1,35 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::ProbeRequest};
+
+/// It pings the stored target of a backend of a running proxy and prints its status, e.g. to
+/// check a route right after creating it
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname of the backend
+///
+/// Returns:
+///
+/// A Result<()>, an error when the backend didn't answer the ping
+pub async fn run(endpoint: String, token: Option<String>, hostname: String) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let probe = client
+        .probe_backend(ProbeRequest { hostname })
+        .await
+        .map_err(|e| anyhow!("failed to probe the backend: {}", e.message()))?
+        .into_inner();
+    if !probe.reachable {
+        return Err(anyhow!("{} is unreachable: {}", probe.target, probe.error));
+    }
+
+    println!(
+        "{} connected in {:.1} ms, pinged in {:.1} ms",
+        probe.target, probe.connect_ms, probe.latency_ms
+    );
+    println!("{}", probe.status);
+
+    Ok(())
+}
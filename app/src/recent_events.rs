@@ -0,0 +1,43 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::RecentEventsRequest};
+
+/// It prints the latest notable events of a running proxy, the oldest first
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `limit`: How many events to print at most, 0 for all of them
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(endpoint: String, token: Option<String>, limit: u32) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let events = client
+        .get_recent_events(RecentEventsRequest { limit })
+        .await
+        .map_err(|e| anyhow!("failed to get the recent events: {}", e.message()))?
+        .into_inner()
+        .events;
+
+    for event in events {
+        let backend = match event.backend.is_empty() {
+            true => String::new(),
+            false => format!(" -> {}", event.backend),
+        };
+        println!(
+            "{} {} {} {} {}{}: {}",
+            event.timestamp_ms,
+            event.kind,
+            event.connection_id,
+            event.client,
+            event.hostname,
+            backend,
+            event.message
+        );
+    }
+
+    Ok(())
+}
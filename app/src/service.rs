@@ -0,0 +1,141 @@
+use std::{ffi::OsString, time::Duration};
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::sync::mpsc;
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+};
+
+use crate::cli::Cli;
+
+/// The name of the service, as created with `sc.exe create`
+const SERVICE_NAME: &str = "kubecraft-proxy";
+
+/// The time the service control manager waits for the drain on top of its timeout
+const STOP_MARGIN: Duration = Duration::from_secs(5);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// It hands the process over to the service control manager, which runs the proxy until the
+/// service is stopped
+///
+/// Returns:
+///
+/// A Result<()>, an error when the process isn't started as a service
+pub fn run() -> Result<()> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)?;
+    Ok(())
+}
+
+/// It runs the proxy as the service, on a thread of the service control manager
+///
+/// Arguments:
+///
+/// * `_arguments`: The start parameters of the service, the proxy reads the arguments of the
+///   binary path instead.
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        log::error!("the service failed: {:#}", e);
+    }
+}
+
+/// It runs the proxy until the service is stopped
+///
+/// A stop, or the shutdown of the system, drains the players like a Ctrl-C. A `paramchange`
+/// control reloads the configuration like a Ctrl-Break.
+///
+/// Returns:
+///
+/// A Result<()>
+fn run_service() -> Result<()> {
+    let config = Cli::parse().load_config()?;
+    let drain_timeout = Duration::from_secs(config.health.drain_timeout_secs);
+
+    let (stops, mut stopped) = mpsc::unbounded_channel();
+    let (reloads, reloaded) = mpsc::unbounded_channel();
+    let status = service_control_handler::register(SERVICE_NAME, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            let _ = stops.send(());
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::ParamChange => {
+            let _ = reloads.send(());
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+    set_status(
+        status,
+        ServiceState::Running,
+        ServiceControlAccept::STOP
+            | ServiceControlAccept::SHUTDOWN
+            | ServiceControlAccept::PARAM_CHANGE,
+        Duration::ZERO,
+        ServiceExitCode::NO_ERROR,
+    )?;
+
+    let shutdown = async move {
+        let _ = stopped.recv().await;
+        // the manager waits for the players to leave instead of killing the process
+        let _ = set_status(
+            status,
+            ServiceState::StopPending,
+            ServiceControlAccept::empty(),
+            drain_timeout + STOP_MARGIN,
+            ServiceExitCode::NO_ERROR,
+        );
+    };
+    let result = crate::run(config, shutdown, Some(reloaded));
+
+    set_status(
+        status,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        Duration::ZERO,
+        match &result {
+            Ok(()) => ServiceExitCode::NO_ERROR,
+            Err(_) => ServiceExitCode::ServiceSpecific(1),
+        },
+    )?;
+    result
+}
+
+/// It reports the status of the service to the service control manager
+///
+/// Arguments:
+///
+/// * `status`: The handle of the service.
+/// * `state`: The state of the service.
+/// * `controls_accepted`: The controls the service handles in this state.
+/// * `wait_hint`: How long the manager waits for the next status of a pending state.
+/// * `exit_code`: The exit code of a stopped service.
+///
+/// Returns:
+///
+/// A Result<()>
+fn set_status(
+    status: ServiceStatusHandle,
+    state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    wait_hint: Duration,
+    exit_code: ServiceExitCode,
+) -> Result<()> {
+    status.set_service_status(ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    })?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::SessionsRequest};
+
+/// It prints the players relayed to every version of the backends of a running proxy
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname of the backend, none for every backend
+/// * `selector`: A label selector of the backends, none for every backend
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: Option<String>,
+    selector: Option<String>,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let sessions = client
+        .get_sessions(SessionsRequest {
+            hostname: hostname.unwrap_or_default(),
+            selector: selector.unwrap_or_default(),
+        })
+        .await
+        .map_err(|e| anyhow!("failed to get the sessions: {}", e.message()))?
+        .into_inner()
+        .sessions;
+
+    for sessions in sessions {
+        println!(
+            "{} version {} at {}: {} players{}",
+            sessions.hostname,
+            sessions.version,
+            sessions.backend,
+            sessions.active,
+            if sessions.legacy { ", legacy" } else { "" }
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::{collections::BTreeMap, fs, future::Future, net::IpAddr, path::Path, time::Duration};
+
+use anyhow::{anyhow, Result};
+use proto::{
+    client,
+    proxy::{Backend, ListBackendRequest},
+};
+use shared::metadata::PodMetadata;
+
+/// The annotation of the pod naming the hostname of its server, read when no hostname is given
+const HOSTNAME_ANNOTATION: &str = "kubecraft.cloud/hostname";
+
+/// The label of the backends the proxy routes to the current IP of their pod, set when the name
+/// and the namespace of the pod are known
+const POD_LABEL: &str = "kubecraft.cloud/pod";
+
+/// The delay before registering again after a failure, e.g. while the central proxy starts
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// The Minecraft server of the pod the sidecar runs next to
+///
+/// Properties:
+///
+/// * `hostname`: The hostname the players join.
+/// * `ip`: The IP of the pod.
+/// * `port`: The port of the server in the pod.
+/// * `labels`: The labels of the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pod {
+    pub hostname: String,
+    pub ip: IpAddr,
+    pub port: u16,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Pod {
+    /// It describes the server of the pod from the arguments and the downward API
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname the players join, the annotation of the pod when none.
+    /// * `annotations`: The annotations of the pod, mounted by the downward API.
+    /// * `ip`: The IP of the pod.
+    /// * `port`: The port of the server in the pod.
+    /// * `labels`: The labels of the backend, as `key=value`.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Pod>, an error without any hostname or with an invalid label
+    pub fn resolve(
+        hostname: Option<String>,
+        annotations: &Path,
+        ip: IpAddr,
+        port: u16,
+        labels: &[String],
+    ) -> Result<Self> {
+        let hostname = match hostname {
+            Some(hostname) => hostname,
+            None => fs::read_to_string(annotations)
+                .ok()
+                .and_then(|annotations| annotation(&annotations, HOSTNAME_ANNOTATION))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "no hostname given, and no {} annotation in {}",
+                        HOSTNAME_ANNOTATION,
+                        annotations.display()
+                    )
+                })?,
+        };
+
+        let mut pod_labels = BTreeMap::new();
+        let metadata = PodMetadata::from_env();
+        if let (Some(namespace), Some(name)) = (metadata.namespace, metadata.pod) {
+            pod_labels.insert(POD_LABEL.to_string(), format!("{}/{}", namespace, name));
+        }
+        for label in labels {
+            let (key, value) = label
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid label {}, expected key=value", label))?;
+            pod_labels.insert(key.to_string(), value.to_string());
+        }
+
+        Ok(Self {
+            hostname,
+            ip,
+            port,
+            labels: pod_labels,
+        })
+    }
+
+    /// It returns whether a stored backend routes to the server of the pod
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The backend stored by the central proxy.
+    ///
+    /// Returns:
+    ///
+    /// true if the backend has the address and the labels of the pod
+    fn is_registered_as(&self, backend: &Backend) -> bool {
+        backend.redirect_ip == self.ip.to_string()
+            && backend.redirect_port == u32::from(self.port)
+            && self
+                .labels
+                .iter()
+                .all(|(key, value)| backend.labels.get(key) == Some(value))
+    }
+}
+
+/// It registers the server of the pod with a central proxy until the pod stops, then
+/// deregisters it
+///
+/// The registration is checked at every interval and made again when it is missing, e.g. after
+/// the central proxy restarted, or when it routes elsewhere.
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the central proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `pod`: The server of the pod
+/// * `refresh`: The time between two checks of the registration
+/// * `shutdown`: Resolves once the pod stops
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    pod: Pod,
+    refresh: Duration,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    tokio::pin!(shutdown);
+
+    loop {
+        let delay = match register(&endpoint, &token, &pod).await {
+            Ok(()) => refresh,
+            Err(e) => {
+                eprintln!("failed to register {}: {:#}", pod.hostname, e);
+                RETRY_DELAY
+            }
+        };
+
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+
+    deregister(&endpoint, &token, &pod).await
+}
+
+/// It registers the server of the pod, unless the central proxy already routes to it
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the central proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `pod`: The server of the pod
+///
+/// Returns:
+///
+/// A Result<()>
+async fn register(endpoint: &str, token: &Option<String>, pod: &Pod) -> Result<()> {
+    let mut client = client::connect(endpoint.to_string(), token.clone()).await?;
+
+    let stored = find(&mut client, &pod.hostname).await?;
+    if stored
+        .as_ref()
+        .is_some_and(|stored| pod.is_registered_as(stored))
+    {
+        return Ok(());
+    }
+
+    // the settings of the stored backend are kept, e.g. its limits or its MOTD, along with the
+    // labels set by others, only its address and the labels of the pod change
+    let mut backend = stored.unwrap_or_else(|| Backend {
+        hostname: pod.hostname.clone(),
+        ..Default::default()
+    });
+    backend.redirect_ip = pod.ip.to_string();
+    backend.redirect_port = u32::from(pod.port);
+    backend.labels.extend(pod.labels.clone());
+    let backend = client
+        .put_backend(backend)
+        .await
+        .map_err(|e| anyhow!("failed to put the backend: {}", e.message()))?
+        .into_inner();
+
+    println!(
+        "registered {} to {}:{} (version {})",
+        backend.hostname, backend.redirect_ip, backend.redirect_port, backend.version
+    );
+    Ok(())
+}
+
+/// It deregisters the server of the pod, unless the central proxy routes the hostname elsewhere
+/// by now, e.g. to the pod replacing this one
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the central proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `pod`: The server of the pod
+///
+/// Returns:
+///
+/// A Result<()>
+async fn deregister(endpoint: &str, token: &Option<String>, pod: &Pod) -> Result<()> {
+    let mut client = client::connect(endpoint.to_string(), token.clone()).await?;
+
+    let Some(stored) = find(&mut client, &pod.hostname).await? else {
+        return Ok(());
+    };
+    if stored.redirect_ip != pod.ip.to_string() || stored.redirect_port != u32::from(pod.port) {
+        println!(
+            "{} routes to {}:{} by now, leaving it",
+            stored.hostname, stored.redirect_ip, stored.redirect_port
+        );
+        return Ok(());
+    }
+
+    client
+        .delete_backend(stored)
+        .await
+        .map_err(|e| anyhow!("failed to delete the backend: {}", e.message()))?;
+
+    println!("deregistered {}", pod.hostname);
+    Ok(())
+}
+
+/// It returns the backend of a hostname stored by the central proxy
+///
+/// Arguments:
+///
+/// * `client`: The client of the central proxy
+/// * `hostname`: The hostname of the backend
+///
+/// Returns:
+///
+/// A Result<Option<Backend>>, none when the hostname has no backend
+async fn find(client: &mut client::Client, hostname: &str) -> Result<Option<Backend>> {
+    let mut backends = client
+        .list_backend(ListBackendRequest::default())
+        .await
+        .map_err(|e| anyhow!("failed to list the backends: {}", e.message()))?
+        .into_inner();
+
+    while let Some(backend) = backends
+        .message()
+        .await
+        .map_err(|e| anyhow!("failed to list the backends: {}", e.message()))?
+    {
+        if backend.hostname.eq_ignore_ascii_case(hostname) {
+            return Ok(Some(backend));
+        }
+    }
+
+    Ok(None)
+}
+
+/// It returns the value of an annotation, from the annotations mounted by the downward API
+///
+/// Arguments:
+///
+/// * `annotations`: The content of the file, a `key="value"` line per annotation
+/// * `name`: The key of the annotation
+///
+/// Returns:
+///
+/// The unquoted value, none when the pod has no such annotation
+fn annotation(annotations: &str, name: &str) -> Option<String> {
+    annotations.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != name {
+            return None;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        Some(value.replace("\\\"", "\"").replace("\\\\", "\\"))
+    })
+}
@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use proto::{
+    client,
+    proxy::{HealthState, StatsRequest},
+};
+
+/// It prints the activity of the backends of a running proxy at every interval, until the proxy
+/// stops
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname of the backend, every backend when none
+/// * `interval_secs`: The seconds between two snapshots, 5 when none
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: Option<String>,
+    interval_secs: Option<u32>,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let mut snapshots = client
+        .watch_stats(StatsRequest {
+            interval_secs: interval_secs.unwrap_or_default(),
+            hostname: hostname.unwrap_or_default(),
+        })
+        .await
+        .map_err(|e| anyhow!("failed to watch the stats: {}", e.message()))?
+        .into_inner();
+
+    while let Some(snapshot) = snapshots
+        .message()
+        .await
+        .map_err(|e| anyhow!("the stats stream failed: {}", e.message()))?
+    {
+        for backend in snapshot.backends {
+            let health = backend.health.unwrap_or_default();
+            let state = match health.state() {
+                HealthState::Healthy => "healthy",
+                HealthState::Degraded => "degraded",
+                HealthState::Down => "down",
+            };
+            println!(
+                "[{}] {} active={} joins/min={:.1} in={:.0}B/s out={:.0}B/s health={} ({:.2}) \
+                 total: joins={} in={}B out={}B",
+                snapshot.timestamp_ms,
+                backend.hostname,
+                backend.active,
+                backend.joins_per_min,
+                backend.bytes_in_per_sec,
+                backend.bytes_out_per_sec,
+                state,
+                health.score,
+                backend.total_joins,
+                backend.total_bytes_in,
+                backend.total_bytes_out
+            );
+        }
+    }
+
+    Ok(())
+}
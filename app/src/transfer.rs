@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::TransferRequest};
+
+/// It moves the players of a backend of a running proxy, or of the backends matching a label
+/// selector, to another server, e.g. before the backend shuts down
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname of the backend, none with a selector
+/// * `selector`: The label selector of the backends, none with a hostname
+/// * `target`: The server the players join, `host` or `host:port`
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: Option<String>,
+    selector: Option<String>,
+    target: String,
+) -> Result<()> {
+    let mut client = client::connect(endpoint, token).await?;
+
+    let backends = match (&hostname, &selector) {
+        (Some(hostname), _) => hostname.clone(),
+        (None, selector) => format!(
+            "the backends matching {}",
+            selector.as_deref().unwrap_or_default()
+        ),
+    };
+    let players = client
+        .transfer_players(TransferRequest {
+            hostname: hostname.unwrap_or_default(),
+            target: target.clone(),
+            selector: selector.unwrap_or_default(),
+        })
+        .await
+        .map_err(|e| anyhow!("failed to transfer the players: {}", e.message()))?
+        .into_inner()
+        .players;
+    println!(
+        "transferring the {} players of {} to {}, the ones older than 1.20.5 stay",
+        players, backends, target
+    );
+
+    Ok(())
+}
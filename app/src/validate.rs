@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use proto::{client, proxy::Backend};
+
+/// The port of a target without one
+const DEFAULT_PORT: u32 = 25565;
+
+/// It validates a route against a running proxy without storing it, e.g. to lint the route
+/// changes of a CI pipeline
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+/// * `hostname`: The hostname of the route
+/// * `target`: The backend of the route, `host` or `host:port`
+///
+/// Returns:
+///
+/// A Result<()>, an error when the route is invalid
+pub async fn run(
+    endpoint: String,
+    token: Option<String>,
+    hostname: String,
+    target: String,
+) -> Result<()> {
+    let (redirect_ip, redirect_port) = match target.rsplit_once(':') {
+        Some((host, port)) => (
+            host.to_string(),
+            port.parse()
+                .map_err(|_| anyhow!("invalid port in target {}", target))?,
+        ),
+        None => (target, DEFAULT_PORT),
+    };
+
+    let mut client = client::connect(endpoint, token).await?;
+
+    let validation = client
+        .validate_backend(Backend {
+            hostname: hostname.clone(),
+            redirect_ip,
+            redirect_port,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| anyhow!("failed to validate the route: {}", e.message()))?
+        .into_inner();
+    for warning in &validation.warnings {
+        println!("warning: {}", warning);
+    }
+    for error in &validation.errors {
+        println!("error: {}", error);
+    }
+
+    match validation.valid {
+        true => Ok(()),
+        false => Err(anyhow!("the route of {} is invalid", hostname)),
+    }
+}
@@ -0,0 +1,1868 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fmt, fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use shared::{
+    bans::BanPolicy,
+    cidr::Cidr,
+    models::{backend::Backend, forwarding::ForwardingMode, schedule::MAX_SCHEDULE_MINS},
+    rate_limit::Rate,
+};
+
+pub use crate::{route::StaticRoute, secret::Secret};
+
+pub mod route;
+pub mod secret;
+
+/// The configuration of the proxy, read from a TOML or YAML file at startup
+///
+/// Every section is optional and falls back to its defaults, so an empty file is a valid
+/// configuration. Environment variables override the values of the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    pub proxy: MinecraftConfig,
+    pub listener: ListenerConfig,
+    pub metrics: MetricsConfig,
+    pub health: HealthConfig,
+    pub timeouts: TimeoutsConfig,
+    /// The options of the sockets to the clients and to the backends, e.g. the DSCP marking of
+    /// the game traffic on a managed network
+    pub sockets: SocketsConfig,
+    pub channels: ChannelsConfig,
+    pub limits: LimitsConfig,
+    pub messages: MessagesConfig,
+    /// What happens to the handshakes no backend matches, e.g. the direct joins of an IP
+    pub direct_ip: DirectIpConfig,
+    /// The answer to the handshakes no backend matches, with the `kick` policy of `direct_ip`
+    pub catch_all: CatchAllConfig,
+    /// When set, the proxy authenticates the players itself, for backends in offline mode
+    pub online_mode: Option<OnlineModeConfig>,
+    /// When set, only the clients which pinged the server list recently can log in
+    pub ping_gate: Option<PingGateConfig>,
+    /// When set, a client logging in again too soon is kicked
+    pub throttle: Option<ThrottleConfig>,
+    /// When set, the new connections to each backend are rate limited
+    pub connect_rate: Option<ConnectRateConfig>,
+    /// When set, the addresses of the misbehaving clients are banned for a while
+    pub bans: Option<BansConfig>,
+    /// The protective mode of the proxy under attack, also flipped through the API
+    pub ddos_mode: DdosModeConfig,
+    pub log: LogConfig,
+    pub capture: CaptureConfig,
+    /// How often the totals of the backends are checkpointed into the storage
+    pub stats: StatsConfig,
+    pub runtime: RuntimeConfig,
+    pub kubernetes: KubernetesConfig,
+    /// The backends declared in the configuration file, as `[[routes]]` tables
+    pub routes: Vec<StaticRoute>,
+    /// The peer proxies the routes are pulled from, as `[[federation]]` tables
+    pub federation: Vec<FederationPeer>,
+    /// The file the configuration was read from, reloads read it again
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Whether the environment variables override the file, reloads apply them again
+    #[serde(skip)]
+    pub from_env: bool,
+}
+
+/// The server accepting the Minecraft clients
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinecraftConfig {
+    pub host: String,
+    pub port: u16,
+    /// When set, another process can bind the same ports as every server of the proxy, e.g. the
+    /// new binary of an upgrade accepting the players while this one drains
+    pub reuse_port: bool,
+    /// The most connections handled at once, none for unlimited
+    pub max_connections: Option<usize>,
+    /// What happens to the connections over `max_connections`
+    pub overload: OverloadPolicy,
+    /// When set, a backend declared as `hostname:port` gets the handshakes of its hostname with
+    /// that port, before the backend of the hostname alone
+    pub route_by_port: bool,
+}
+
+/// How the proxy sheds the connections over its `max_connections`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverloadPolicy {
+    /// The connection is reset right after the accept, without reading anything
+    #[default]
+    Refuse,
+    /// The client is kicked with the `overloaded` message, or gets it as MOTD
+    Kick,
+}
+
+/// The gRPC listener used to configure the proxy
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenerConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: Option<TlsConfig>,
+    /// When set, requests must carry it as an `authorization: Bearer <token>` header
+    pub token: Option<Secret>,
+    /// The CIDR blocks of the clients allowed to connect, e.g. the pod network, every client
+    /// when empty
+    pub allowlist: Vec<String>,
+}
+
+/// The server exposing the Prometheus metrics
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub host: String,
+    pub port: u16,
+    /// When set, the metrics are also pushed to a StatsD or DogStatsD agent
+    pub statsd: Option<StatsdConfig>,
+    /// When set, the metrics are also pushed to an OpenTelemetry collector over OTLP
+    pub otlp: Option<OtlpConfig>,
+}
+
+/// The StatsD agent the metrics are pushed to, e.g. the Datadog agent of the node
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsdConfig {
+    /// The `host:port` of the agent, over UDP
+    pub addr: String,
+    /// The prefix of the metric names, none when empty
+    pub prefix: String,
+    /// How often the metrics are pushed, in seconds
+    pub interval_secs: u64,
+    /// Whether the labels are sent as DogStatsD tags, or appended to the metric names
+    pub format: StatsdFormat,
+}
+
+/// The OpenTelemetry collector the metrics are pushed to, over OTLP/HTTP with the JSON encoding
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OtlpConfig {
+    /// The URL the metrics are posted to, e.g. `http://otel-collector:4318/v1/metrics`
+    pub endpoint: String,
+    /// The headers of the requests, e.g. the API key of a hosted collector, their values given
+    /// inline or read from a file
+    pub headers: BTreeMap<String, Secret>,
+    /// The `service.name` attribute of the resource
+    pub service_name: String,
+    /// How often the metrics are pushed, in seconds
+    pub interval_secs: u64,
+}
+
+/// The flavor of the StatsD protocol spoken by the agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsdFormat {
+    Statsd,
+    #[default]
+    Dogstatsd,
+}
+
+/// The server answering the liveness and readiness probes on `/healthz` and `/readyz`, and the
+/// drain requests on `/drain`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    pub host: String,
+    pub port: u16,
+    /// How long a drain waits for the sessions to finish, in seconds, below the termination
+    /// grace period of the pod
+    pub drain_timeout_secs: u64,
+    /// The port answering a status ping to every connection, on the host of the health server,
+    /// for the load balancers checking the proxy over TCP only; none by default
+    pub ping_port: Option<u16>,
+}
+
+/// The certificate and key, in PEM format, used to serve the gRPC listener over TLS
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+    /// When set, clients must present a certificate signed by this authority
+    pub client_ca: Option<String>,
+}
+
+/// The timeouts applied to the client connections, in seconds
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub handshake_secs: u64,
+    pub connect_secs: u64,
+}
+
+/// The options of the sockets of the relayed connections, per direction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketsConfig {
+    /// The sockets accepted from the clients
+    pub client: SocketConfig,
+    /// The sockets dialed to the backends
+    pub backend: SocketConfig,
+}
+
+/// The options of a socket, the ones unset are left to the kernel
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketConfig {
+    /// The DSCP of the packets sent, from 0 to 63, written in the TOS byte of the IPv4 header.
+    /// The IPv6 connections are not marked
+    pub dscp: Option<u8>,
+    /// The size of the receive buffer, in bytes
+    pub recv_buffer_bytes: Option<usize>,
+    /// The size of the send buffer, in bytes
+    pub send_buffer_bytes: Option<usize>,
+}
+
+impl SocketConfig {
+    /// It returns the TOS byte of the packets sent, the DSCP shifted above the ECN bits
+    ///
+    /// Returns:
+    ///
+    /// The TOS byte, none when no DSCP is set
+    pub fn tos(&self) -> Option<u32> {
+        self.dscp.map(|dscp| u32::from(dscp) << 2)
+    }
+}
+
+/// The checkpoints of the cumulative totals of the backends into the storage, so the state
+/// snapshots carry them across the restarts
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatsConfig {
+    /// The seconds between two checkpoints
+    pub checkpoint_secs: u64,
+}
+
+/// The internal channels between the gRPC listener and the proxy
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelsConfig {
+    /// The number of requests waiting to be handled by the proxy
+    pub events: usize,
+    /// The number of messages buffered by every streaming response
+    pub streams: usize,
+    /// The number of changes a watcher can lag behind before it is disconnected
+    pub changes: usize,
+    /// How long a request waits for the proxy to answer, in seconds
+    pub response_timeout_secs: u64,
+    /// The number of tapped packets a subscriber can lag behind before it misses some
+    pub taps: usize,
+    /// The number of log records a `StreamLogs` subscriber can lag behind before it is
+    /// disconnected
+    pub logs: usize,
+}
+
+/// The limits on the backends stored by the proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_backends: Option<usize>,
+    pub max_backends_per_label: BTreeMap<String, usize>,
+    /// How long deleted backends can be restored, in seconds
+    pub tombstone_retention_secs: Option<u64>,
+}
+
+/// The messages shown to the players
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MessagesConfig {
+    /// The kick reason, or the MOTD, when no backend matches the hostname
+    pub backend_not_found: String,
+    /// The kick reason when a player wakes up a server scaled down to zero
+    pub backend_starting: String,
+    /// The MOTD of a server scaled down to zero
+    pub backend_sleeping: String,
+    /// The kick reason when the session server doesn't know the player, in online mode
+    pub authentication_failed: String,
+    /// The kick reason when the session server can't be reached, in online mode
+    pub authentication_unavailable: String,
+    /// The kick reason of a player logging in without pinging the server list first
+    pub ping_required: String,
+    /// The kick reason of a player logging in again too soon
+    pub throttled: String,
+    /// The MOTD the proxy answers in DDoS mode, for the backends without their own
+    pub ddos_mode: String,
+    /// The kick reason, or the MOTD, when the connections to the backend exceed their rate
+    pub backend_busy: String,
+    /// The kick reason, or the MOTD, when the proxy handles its `max_connections` already
+    pub overloaded: String,
+    /// The kick reason of a player whose version the backend doesn't support, `{versions}` is
+    /// replaced with the versions it does
+    pub unsupported_version: String,
+    /// The kick reason, or the MOTD, of a handshake no backend matches with the `hint` policy
+    pub direct_ip_hint: String,
+    /// The kick reason, or the MOTD, of a backend in a maintenance window without fallback
+    pub maintenance: String,
+    /// The kick reason of a player banned through the API, followed by the reason of its ban
+    pub banned: String,
+}
+
+/// The handling of the handshakes no backend matches, usually the players joining with the IP
+/// of the proxy instead of a hostname
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirectIpConfig {
+    pub policy: DirectIpPolicy,
+    /// The hostname of the backend the `route` policy sends the players to
+    pub backend: String,
+}
+
+/// The answer of the proxy to the handshakes no backend matches, instead of the
+/// `backend_not_found` message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CatchAllConfig {
+    /// The MOTD of the status pings, `messages.backend_not_found` when unset
+    pub motd: Option<String>,
+    /// The favicon of the status pings, a 64x64 PNG as a `data:image/png;base64,` URI
+    pub favicon: Option<String>,
+    /// The kick reason of the logins, which may span several lines, `messages.backend_not_found`
+    /// when unset
+    pub kick: Option<String>,
+    /// The hostnames listed below the kick reason, e.g. the addresses of the network
+    pub hostnames: Vec<String>,
+}
+
+/// What the proxy does with a handshake no backend matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectIpPolicy {
+    /// The client is kicked with the `backend_not_found` message, or gets it as MOTD
+    #[default]
+    Kick,
+    /// The client is relayed to the default `backend`, kicked when it doesn't exist
+    Route,
+    /// The client is kicked with the `direct_ip_hint` message, or gets it as MOTD
+    Hint,
+    /// The connection is closed without an answer
+    Drop,
+}
+
+/// The authentication of the players by the proxy, like an online-mode server does, so the
+/// backends can run in offline mode
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OnlineModeConfig {
+    /// The session server verifying the players, Mojang's by default
+    pub session_server: String,
+    /// Whether the session server checks that the player joined from the address of the client
+    pub prevent_proxy_connections: bool,
+    /// How long the session server is waited for, in seconds
+    pub timeout_secs: u64,
+}
+
+/// The gate admitting the logins of the clients which pinged the server list recently, as the
+/// real clients do and the join bots usually don't
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PingGateConfig {
+    /// How long after its status ping a client can log in, in seconds
+    pub window_secs: u64,
+}
+
+/// The minimum delay between the login attempts of an address, against the reconnect macros
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThrottleConfig {
+    /// The minimum delay between two login attempts of an address, in milliseconds
+    pub min_delay_ms: u64,
+    /// The addresses never throttled, e.g. a load balancer in front of the proxy
+    pub exempt: Vec<IpAddr>,
+}
+
+/// The emergency mode of the proxy under a DDoS attack
+///
+/// While it is on, the status pings are answered by the proxy without dialing the backends, the
+/// logins need a recent status ping and are throttled harder, and the handshakes no backend
+/// matches never reach the `direct_ip` backend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DdosModeConfig {
+    /// Whether the proxy starts in DDoS mode, a reload changing it flips the mode
+    pub enabled: bool,
+    /// The minimum delay between two login attempts of an address, in milliseconds, the
+    /// `throttle` one when it is longer
+    pub min_delay_ms: u64,
+    /// How long after its status ping a client can log in, in seconds
+    pub ping_window_secs: u64,
+}
+
+/// The token bucket limiting the new connections to each backend, so a server coming back online
+/// isn't joined by every waiting player at once
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectRateConfig {
+    /// The new connections to a backend allowed every second
+    pub per_sec: u32,
+    /// The new connections to a backend allowed at once
+    pub burst: u32,
+}
+
+/// The temporary bans of the clients failing their handshake, breaking the protocol or getting
+/// kicked over and over, fail2ban-style
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BansConfig {
+    /// The number of strikes within the window banning an address
+    pub max_strikes: usize,
+    /// How long a strike counts, in seconds
+    pub window_secs: u64,
+    /// How long an address is banned, in seconds
+    pub duration_secs: u64,
+    /// The addresses never banned, e.g. a load balancer in front of the proxy
+    pub exempt: Vec<IpAddr>,
+}
+
+/// The logging of the proxy
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// The default log filter, `RUST_LOG` takes precedence over it
+    pub level: String,
+    pub format: LogFormat,
+}
+
+/// The captures of the connections, started per hostname through the API for debugging
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// The directory the captures are written to, created when missing
+    pub directory: PathBuf,
+}
+
+/// The Tokio runtimes of the binary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// The worker threads of a runtime dedicated to relaying the players, 0 to share the runtime
+    /// of the control plane
+    pub data_plane_threads: usize,
+    /// The runtime of the gRPC API, the events and the other subsystems
+    pub control_plane: RuntimeFlavor,
+}
+
+/// The kind of a Tokio runtime
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeFlavor {
+    /// Worker threads as many as the CPUs
+    #[default]
+    MultiThread,
+    /// The thread of the binary only
+    CurrentThread,
+}
+
+/// The integration of the proxy with the Kubernetes cluster it runs in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KubernetesConfig {
+    /// Whether the `MinecraftServer` resources are reconciled into the routing table
+    pub operator: bool,
+    /// Whether the routes are discovered from the annotated Ingresses
+    pub ingress: bool,
+    /// Whether the routes are discovered from the annotated HTTPRoutes of the Gateway API
+    pub gateway: bool,
+    /// Whether the backends labeled with a pod follow the IP of the pod when it is rescheduled
+    pub pods: bool,
+    /// Whether the metrics and the logs are labeled with the pod, namespace and node of the proxy,
+    /// read from the `POD_NAME`, `POD_NAMESPACE` and `NODE_NAME` variables of the downward API
+    pub metadata: bool,
+    /// The namespace the resources are watched in, all the namespaces when unset
+    pub namespace: Option<String>,
+    /// When set, only the replica holding the Lease runs the controllers, all of them serve traffic
+    pub leader_election: Option<LeaderElectionConfig>,
+}
+
+/// A peer proxy whose routes are mirrored from its `WatchBackends` stream
+///
+/// An entry proxy pulls the routes of the proxies of every region, and redirects their players to
+/// the proxy of the region, which routes them to its backends.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FederationPeer {
+    /// The name of the peer, its routes are labeled with `federation/<name>`
+    pub name: String,
+    /// The gRPC API of the peer, e.g. `http://eu.example.com:65535`
+    pub endpoint: String,
+    /// The API token of the peer, inline or read from a file
+    pub token: Option<Secret>,
+    /// Only the hostnames starting with this prefix are mirrored
+    #[serde(default)]
+    pub prefix: String,
+    /// Only the backends carrying all these labels are mirrored
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// The `host:port` the mirrored routes are redirected to, keeping the hostname of the
+    /// players, e.g. the proxy of the region; the address of the backends when unset
+    pub redirect: Option<String>,
+}
+
+impl FederationPeer {
+    /// It parses the address the mirrored routes are redirected to
+    ///
+    /// Returns:
+    ///
+    /// A Result with the host and the port, none when the routes keep the address of the backends
+    pub fn redirect(&self) -> Result<Option<(String, u16)>> {
+        let redirect = match &self.redirect {
+            Some(redirect) => redirect,
+            None => return Ok(None),
+        };
+
+        let (host, port) = redirect
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("redirect {} must be a host:port", redirect))?;
+        match port.parse::<u16>() {
+            Ok(port) if port != 0 && !host.is_empty() => Ok(Some((host.to_string(), port))),
+            _ => Err(anyhow!("redirect {} must be a host:port", redirect)),
+        }
+    }
+}
+
+/// The Lease the replicas of the proxy compete for
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderElectionConfig {
+    pub lease_name: String,
+    /// The namespace of the Lease, the namespace of the Kubernetes client when unset
+    pub lease_namespace: Option<String>,
+    /// The identity of this replica, `POD_NAME` or `HOSTNAME` when unset
+    pub identity: Option<String>,
+    /// How long the Lease is held without being renewed, in seconds
+    pub lease_duration_secs: u64,
+    /// How often the Lease is renewed, or tried to be acquired, in seconds
+    pub renew_interval_secs: u64,
+}
+
+/// The format of the log lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+impl MinecraftConfig {
+    /// It returns the address to bind, in the `host:port` format
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Default for MinecraftConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: 25565,
+            reuse_port: false,
+            max_connections: None,
+            overload: OverloadPolicy::Refuse,
+            route_by_port: false,
+        }
+    }
+}
+
+impl ListenerConfig {
+    /// It returns the address to bind, in the `host:port` format
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: 65535,
+            tls: None,
+            token: None,
+            allowlist: Vec::new(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// It returns the address to bind, in the `host:port` format
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: 9090,
+            statsd: None,
+            otlp: None,
+        }
+    }
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://127.0.0.1:4318/v1/metrics".to_string(),
+            headers: BTreeMap::new(),
+            service_name: "kubecraft-proxy".to_string(),
+            interval_secs: 10,
+        }
+    }
+}
+
+impl Default for StatsdConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:8125".to_string(),
+            prefix: "kubecraft_proxy".to_string(),
+            interval_secs: 10,
+            format: StatsdFormat::default(),
+        }
+    }
+}
+
+impl HealthConfig {
+    /// It returns the address to bind, in the `host:port` format
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// It returns the address of the ping port, in the `host:port` format
+    ///
+    /// Returns:
+    ///
+    /// A String, none without a ping port
+    pub fn ping_addr(&self) -> Option<String> {
+        self.ping_port
+            .map(|ping_port| format!("{}:{}", self.host, ping_port))
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: 8080,
+            drain_timeout_secs: 25,
+            ping_port: None,
+        }
+    }
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            lease_name: "kubecraft-proxy".to_string(),
+            lease_namespace: None,
+            identity: None,
+            lease_duration_secs: 15,
+            renew_interval_secs: 5,
+        }
+    }
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            handshake_secs: 5,
+            connect_secs: 5,
+        }
+    }
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            checkpoint_secs: 60,
+        }
+    }
+}
+
+impl Default for ChannelsConfig {
+    fn default() -> Self {
+        Self {
+            events: 16,
+            streams: 4,
+            changes: 64,
+            response_timeout_secs: 10,
+            taps: 1024,
+            logs: 1024,
+        }
+    }
+}
+
+impl Default for MessagesConfig {
+    fn default() -> Self {
+        Self {
+            backend_not_found: "Backend not found".to_string(),
+            backend_starting: "The server is starting, please reconnect in a moment".to_string(),
+            backend_sleeping: "The server is sleeping, join to wake it up".to_string(),
+            authentication_failed: "Failed to verify username!".to_string(),
+            authentication_unavailable:
+                "Authentication servers are down. Please try again later, sorry!".to_string(),
+            ping_required: "Please refresh the server list and join again".to_string(),
+            throttled: "Connection throttled! Please wait before reconnecting.".to_string(),
+            ddos_mode: "A Minecraft Server".to_string(),
+            backend_busy: "The server is busy, please try again in a moment".to_string(),
+            overloaded: "The proxy is full, please try again in a moment".to_string(),
+            unsupported_version: "This server requires {versions}".to_string(),
+            direct_ip_hint: "Please join through the address of the server".to_string(),
+            maintenance: "This server is under maintenance, please come back later".to_string(),
+            banned: "You are banned from this server".to_string(),
+        }
+    }
+}
+
+impl CatchAllConfig {
+    /// It returns the kick reason of the logins, with the hostnames listed below it
+    ///
+    /// Arguments:
+    ///
+    /// * `default`: The reason when the catch-all sets none, the `backend_not_found` message.
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn kick_reason(&self, default: &str) -> String {
+        let reason = self.kick.as_deref().unwrap_or(default);
+        match self.hostnames.is_empty() {
+            true => reason.to_string(),
+            false => format!("{}\n\n{}", reason, self.hostnames.join("\n")),
+        }
+    }
+}
+
+impl Default for DirectIpConfig {
+    fn default() -> Self {
+        Self {
+            policy: DirectIpPolicy::Kick,
+            backend: String::new(),
+        }
+    }
+}
+
+impl Default for OnlineModeConfig {
+    fn default() -> Self {
+        Self {
+            session_server: "https://sessionserver.mojang.com".to_string(),
+            prevent_proxy_connections: false,
+            timeout_secs: 5,
+        }
+    }
+}
+
+impl Default for PingGateConfig {
+    fn default() -> Self {
+        Self { window_secs: 300 }
+    }
+}
+
+impl Default for DdosModeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_delay_ms: 10_000,
+            ping_window_secs: 120,
+        }
+    }
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            min_delay_ms: 4000,
+            exempt: Vec::new(),
+        }
+    }
+}
+
+impl ConnectRateConfig {
+    /// It returns the rate and the burst of the connections to a backend
+    ///
+    /// Returns:
+    ///
+    /// A Rate
+    pub fn rate(&self) -> Rate {
+        Rate {
+            per_sec: self.per_sec as f64,
+            burst: self.burst as f64,
+        }
+    }
+}
+
+impl Default for ConnectRateConfig {
+    fn default() -> Self {
+        Self {
+            per_sec: 20,
+            burst: 50,
+        }
+    }
+}
+
+impl BansConfig {
+    /// It returns when an address gets banned, and for how long
+    ///
+    /// Returns:
+    ///
+    /// A BanPolicy
+    pub fn policy(&self) -> BanPolicy {
+        BanPolicy {
+            max_strikes: self.max_strikes,
+            window: Duration::from_secs(self.window_secs),
+            duration: Duration::from_secs(self.duration_secs),
+        }
+    }
+}
+
+impl Default for BansConfig {
+    fn default() -> Self {
+        Self {
+            max_strikes: 10,
+            window_secs: 60,
+            duration_secs: 600,
+            exempt: Vec::new(),
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("/tmp/kubecraft-captures"),
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::default(),
+        }
+    }
+}
+
+/// The value replacing the secrets in a redacted configuration
+pub const REDACTED: &str = "<redacted>";
+
+/// The format of a configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// It guesses the format of a configuration file from its extension, defaulting to TOML
+    ///
+    /// Arguments:
+    ///
+    /// * `path`: The path of the configuration file.
+    ///
+    /// Returns:
+    ///
+    /// A Format
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Format::Yaml,
+            _ => Format::Toml,
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// It returns the static routes of the configuration as read-only backends
+    ///
+    /// Returns:
+    ///
+    /// A Vec<Backend>
+    pub fn static_backends(&self) -> Vec<Backend> {
+        self.routes.iter().map(StaticRoute::backend).collect()
+    }
+
+    /// It returns a copy of the configuration with the secrets replaced by `REDACTED`
+    ///
+    /// Returns:
+    ///
+    /// A ProxyConfig
+    pub fn redacted(&self) -> ProxyConfig {
+        let mut config = self.clone();
+        if let Some(tls) = config.listener.tls.as_mut() {
+            tls.key = REDACTED.to_string();
+        }
+        config.listener.token = config.listener.token.as_ref().map(Secret::redacted);
+        for peer in &mut config.federation {
+            peer.token = peer.token.as_ref().map(Secret::redacted);
+        }
+        if let Some(otlp) = config.metrics.otlp.as_mut() {
+            for value in otlp.headers.values_mut() {
+                *value = value.redacted();
+            }
+        }
+
+        config
+    }
+
+    /// It serializes the configuration in the TOML format of the configuration file
+    ///
+    /// Returns:
+    ///
+    /// A Result<String>
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|e| anyhow!("failed to serialize configuration: {}", e))
+    }
+
+    /// It tells whether the settings only applied at startup differ from another configuration
+    ///
+    /// The bind addresses, the TLS configuration, the channels, the logging and the Kubernetes
+    /// integration are read once, the other settings can be reloaded while the proxy is running.
+    ///
+    /// Arguments:
+    ///
+    /// * `other`: The configuration to compare with.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn requires_restart(&self, other: &ProxyConfig) -> bool {
+        self.proxy != other.proxy
+            || self.listener != other.listener
+            || self.metrics != other.metrics
+            || self.health != other.health
+            || self.channels != other.channels
+            || self.log != other.log
+            || self.runtime != other.runtime
+            || self.kubernetes != other.kubernetes
+            || self.federation != other.federation
+    }
+
+    /// It loads the configuration from a file, falling back to the `CONFIG_PATH` environment
+    /// variable, then applies the environment variable overrides
+    ///
+    /// Without any file, the defaults are used. The configuration must be validated before use.
+    ///
+    /// Arguments:
+    ///
+    /// * `path`: The path of the configuration file, if any.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyConfig>
+    pub fn load(path: Option<PathBuf>) -> Result<Self> {
+        let path = path.or_else(|| env::var_os("CONFIG_PATH").map(PathBuf::from));
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        config.override_with(|name| env::var(name).ok())?;
+        config.from_env = true;
+        Ok(config)
+    }
+
+    /// It reads the configuration again the way it was read, so a proxy embedded next to another
+    /// one never picks up the file or the environment variables of the other
+    ///
+    /// A configuration from `ProxyConfig::load` reads its file and the environment variables
+    /// again, one from `ProxyConfig::from_file` only its file. A configuration built in code has
+    /// nothing to read again.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyConfig>, not validated yet
+    pub fn reload(&self) -> Result<Self> {
+        if self.from_env {
+            return Self::load(self.path.clone());
+        }
+
+        match &self.path {
+            Some(path) => Self::from_file(path),
+            None => Err(anyhow!(
+                "the configuration wasn't read from a file, there is nothing to reload"
+            )),
+        }
+    }
+
+    /// It checks the configuration, reporting every invalid setting at once
+    ///
+    /// The hosts must be IP addresses, the ports must not be 0 nor shared by two servers, and the
+    /// timeouts, the tombstone retention and the ban settings must be positive.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        let servers = [
+            ("proxy", &self.proxy.host, self.proxy.port),
+            ("listener", &self.listener.host, self.listener.port),
+            ("metrics", &self.metrics.host, self.metrics.port),
+            ("health", &self.health.host, self.health.port),
+        ];
+
+        let mut binds = Vec::new();
+        for (name, host, port) in servers {
+            if port == 0 {
+                errors.push(format!("{}.port must be between 1 and 65535", name));
+            }
+
+            match host.parse::<IpAddr>() {
+                Ok(ip) => binds.push((name, ip, port)),
+                Err(_) => errors.push(format!("{}.host {} is not an IP address", name, host)),
+            }
+        }
+
+        if let Some(ping_port) = self.health.ping_port {
+            if ping_port == 0 {
+                errors.push("health.ping_port must be between 1 and 65535".to_string());
+            }
+            // the ping port binds the host of the health server
+            if let Some(&(_, ip, _)) = binds.iter().find(|(name, _, _)| *name == "health") {
+                binds.push(("health ping", ip, ping_port));
+            }
+        }
+
+        for (index, (name, ip, port)) in binds.iter().enumerate() {
+            for (other, other_ip, other_port) in &binds[index + 1..] {
+                let overlaps = ip == other_ip || ip.is_unspecified() || other_ip.is_unspecified();
+                if port == other_port && overlaps {
+                    errors.push(format!("{} and {} both bind port {}", name, other, port));
+                }
+            }
+        }
+
+        if let Some(statsd) = &self.metrics.statsd {
+            match statsd.addr.rsplit_once(':') {
+                Some((host, port))
+                    if !host.is_empty() && port.parse::<u16>().is_ok_and(|port| port != 0) => {}
+                _ => errors.push(format!(
+                    "metrics.statsd.addr {} must be a host:port",
+                    statsd.addr
+                )),
+            }
+            if statsd.interval_secs == 0 {
+                errors.push("metrics.statsd.interval_secs must be greater than 0".to_string());
+            }
+        }
+
+        if let Some(otlp) = &self.metrics.otlp {
+            if !otlp.endpoint.starts_with("https://") && !otlp.endpoint.starts_with("http://") {
+                errors.push(format!(
+                    "metrics.otlp.endpoint {} must be an http or https URL",
+                    otlp.endpoint
+                ));
+            }
+            if otlp.interval_secs == 0 {
+                errors.push("metrics.otlp.interval_secs must be greater than 0".to_string());
+            }
+            for (name, value) in &otlp.headers {
+                if let Err(e) = value.resolve() {
+                    errors.push(format!("metrics.otlp.headers.{}: {}", name, e));
+                }
+            }
+        }
+
+        if let Some(tls) = &self.listener.tls {
+            if tls.cert.is_empty() || tls.key.is_empty() {
+                errors.push("listener.tls requires both a cert and a key".to_string());
+            }
+        }
+
+        for block in &self.listener.allowlist {
+            if let Err(e) = block.parse::<Cidr>() {
+                errors.push(format!("listener.allowlist: {}", e));
+            }
+        }
+
+        if let Some(token) = &self.listener.token {
+            match token.resolve() {
+                Ok(token) if token.is_empty() => {
+                    errors.push("listener.token must not be empty".to_string())
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(format!("listener.token: {}", e)),
+            }
+        }
+
+        if self.proxy.max_connections == Some(0) {
+            errors.push(
+                "proxy.max_connections must be greater than 0, unset it for unlimited".to_string(),
+            );
+        }
+
+        if self.timeouts.handshake_secs == 0 {
+            errors.push("timeouts.handshake_secs must be greater than 0".to_string());
+        }
+        if self.timeouts.connect_secs == 0 {
+            errors.push("timeouts.connect_secs must be greater than 0".to_string());
+        }
+
+        let channels = [
+            ("events", self.channels.events),
+            ("streams", self.channels.streams),
+            ("changes", self.channels.changes),
+            ("taps", self.channels.taps),
+            ("logs", self.channels.logs),
+        ];
+        for (name, capacity) in channels {
+            if capacity == 0 {
+                errors.push(format!("channels.{} must be greater than 0", name));
+            }
+        }
+        if self.channels.response_timeout_secs == 0 {
+            errors.push("channels.response_timeout_secs must be greater than 0".to_string());
+        }
+        if self.stats.checkpoint_secs == 0 {
+            errors.push("stats.checkpoint_secs must be greater than 0".to_string());
+        }
+        if self.capture.directory.as_os_str().is_empty() {
+            errors.push("capture.directory must not be empty".to_string());
+        }
+
+        if self.limits.tombstone_retention_secs == Some(0) {
+            errors.push(
+                "limits.tombstone_retention_secs must be greater than 0, unset it to delete backends right away"
+                    .to_string(),
+            );
+        }
+        if self
+            .limits
+            .max_backends_per_label
+            .keys()
+            .any(|label| label.is_empty())
+        {
+            errors.push("limits.max_backends_per_label has an empty label".to_string());
+        }
+
+        if let Some(online_mode) = &self.online_mode {
+            let session_server = &online_mode.session_server;
+            if !session_server.starts_with("https://") && !session_server.starts_with("http://") {
+                errors.push(format!(
+                    "online_mode.session_server {} must be an http or https URL",
+                    session_server
+                ));
+            }
+            if online_mode.timeout_secs == 0 {
+                errors.push("online_mode.timeout_secs must be greater than 0".to_string());
+            }
+        }
+        if let Some(ping_gate) = &self.ping_gate {
+            if ping_gate.window_secs == 0 {
+                errors.push("ping_gate.window_secs must be greater than 0".to_string());
+            }
+        }
+        if let Some(throttle) = &self.throttle {
+            if throttle.min_delay_ms == 0 {
+                errors.push("throttle.min_delay_ms must be greater than 0".to_string());
+            }
+        }
+        for (name, socket) in [
+            ("client", &self.sockets.client),
+            ("backend", &self.sockets.backend),
+        ] {
+            if socket.dscp.is_some_and(|dscp| dscp > 63) {
+                errors.push(format!("sockets.{}.dscp must be between 0 and 63", name));
+            }
+            if socket.recv_buffer_bytes == Some(0) {
+                errors.push(format!(
+                    "sockets.{}.recv_buffer_bytes must be greater than 0",
+                    name
+                ));
+            }
+            if socket.send_buffer_bytes == Some(0) {
+                errors.push(format!(
+                    "sockets.{}.send_buffer_bytes must be greater than 0",
+                    name
+                ));
+            }
+        }
+        if self.ddos_mode.min_delay_ms == 0 {
+            errors.push("ddos_mode.min_delay_ms must be greater than 0".to_string());
+        }
+        if self.ddos_mode.ping_window_secs == 0 {
+            errors.push("ddos_mode.ping_window_secs must be greater than 0".to_string());
+        }
+        if let Some(connect_rate) = &self.connect_rate {
+            let settings = [
+                ("per_sec", connect_rate.per_sec),
+                ("burst", connect_rate.burst),
+            ];
+            for (name, value) in settings {
+                if value == 0 {
+                    errors.push(format!("connect_rate.{} must be greater than 0", name));
+                }
+            }
+        }
+        if let Some(bans) = &self.bans {
+            let settings = [
+                ("max_strikes", bans.max_strikes as u64),
+                ("window_secs", bans.window_secs),
+                ("duration_secs", bans.duration_secs),
+            ];
+            for (name, value) in settings {
+                if value == 0 {
+                    errors.push(format!("bans.{} must be greater than 0", name));
+                }
+            }
+        }
+
+        let mut hostnames = BTreeSet::new();
+        for route in &self.routes {
+            let hostname = route.hostname.to_lowercase();
+            if hostname.is_empty() {
+                errors.push("routes must have a hostname".to_string());
+            } else if !hostnames.insert(hostname) {
+                errors.push(format!("routes declare {} more than once", route.hostname));
+            }
+            if route.redirect_ip.is_empty() || route.redirect_port == 0 {
+                errors.push(format!(
+                    "route {} must have a redirect_ip and a redirect_port",
+                    route.hostname
+                ));
+            }
+            if route.min_protocol_version > 0
+                && route.max_protocol_version > 0
+                && route.min_protocol_version > route.max_protocol_version
+            {
+                errors.push(format!(
+                    "route {} has a min_protocol_version newer than its max_protocol_version",
+                    route.hostname
+                ));
+            }
+            if self.online_mode.is_some() && route.forwarding_mode != ForwardingMode::None {
+                errors.push(format!(
+                    "route {} has a {} forwarding_mode, which online_mode doesn't support",
+                    route.hostname, route.forwarding_mode
+                ));
+            }
+            for schedule in &route.schedules {
+                if schedule.duration_mins == 0 || schedule.duration_mins > MAX_SCHEDULE_MINS {
+                    errors.push(format!(
+                        "route {} has a schedule lasting {} minutes, it must last 1 to {}",
+                        route.hostname, schedule.duration_mins, MAX_SCHEDULE_MINS
+                    ));
+                }
+            }
+        }
+
+        if let Some(favicon) = &self.catch_all.favicon {
+            if !favicon.starts_with("data:image/png;base64,") {
+                errors.push("catch_all.favicon must be a data:image/png;base64, URI".to_string());
+            }
+        }
+
+        if self.direct_ip.policy == DirectIpPolicy::Route && self.direct_ip.backend.is_empty() {
+            errors.push("direct_ip.backend is required by the route policy".to_string());
+        }
+
+        if self.log.level.is_empty() {
+            errors.push("log.level must not be empty".to_string());
+        }
+
+        if self.kubernetes.namespace.as_deref() == Some("") {
+            errors.push(
+                "kubernetes.namespace must not be empty, unset it to watch all the namespaces"
+                    .to_string(),
+            );
+        }
+        if let Some(election) = &self.kubernetes.leader_election {
+            if election.lease_name.is_empty() {
+                errors.push("kubernetes.leader_election.lease_name must not be empty".to_string());
+            }
+            if election.renew_interval_secs == 0
+                || election.renew_interval_secs >= election.lease_duration_secs
+            {
+                errors.push(
+                    "kubernetes.leader_election.renew_interval_secs must be greater than 0 and less than lease_duration_secs"
+                        .to_string(),
+                );
+            }
+        }
+
+        let mut peers = BTreeSet::new();
+        for peer in &self.federation {
+            if peer.name.is_empty() {
+                errors.push("federation peers must have a name".to_string());
+            } else if !peers.insert(peer.name.as_str()) {
+                errors.push(format!("federation declares {} more than once", peer.name));
+            }
+            if peer.endpoint.is_empty() {
+                errors.push(format!("federation {} must have an endpoint", peer.name));
+            }
+            if let Err(e) = peer.redirect() {
+                errors.push(format!("federation {}: {}", peer.name, e));
+            }
+            if let Some(Err(e)) = peer.token.as_ref().map(Secret::resolve) {
+                errors.push(format!("federation {}.token: {}", peer.name, e));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("invalid configuration: {}", errors.join(", ")))
+        }
+    }
+
+    /// It reads a configuration file, its format is guessed from its extension
+    ///
+    /// Arguments:
+    ///
+    /// * `path`: The path of the configuration file.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyConfig>
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read configuration {}: {}", path.display(), e))?;
+
+        let mut config = Self::parse(&content, Format::from_path(path))
+            .map_err(|e| anyhow!("invalid configuration {}: {}", path.display(), e))?;
+        config.path = Some(path.to_path_buf());
+
+        Ok(config)
+    }
+
+    /// It parses a configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `content`: The content of the configuration file.
+    /// * `format`: The format of the content.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyConfig>
+    pub fn parse(content: &str, format: Format) -> Result<Self> {
+        match format {
+            Format::Toml => toml::from_str(content).map_err(|e| anyhow!(e)),
+            Format::Yaml => {
+                // an empty YAML document is `null`, not an empty mapping
+                if content.trim().is_empty() {
+                    return Ok(Self::default());
+                }
+                serde_yaml::from_str(content).map_err(|e| anyhow!(e))
+            }
+        }
+    }
+
+    /// It overrides the configuration with the environment variables
+    ///
+    /// `PROXY_PORT`, `LISTENER_PORT`, `METRICS_PORT`, `HEALTH_PORT`, `HANDSHAKE_TIMEOUT`,
+    /// `CONNECT_TIMEOUT`, `MAX_BACKENDS`, `MAX_BACKENDS_PER_LABEL`, `TOMBSTONE_RETENTION` and `LISTENER_TOKEN` are
+    /// supported. `LISTENER_TOKEN_FILE` reads the token from a file instead, e.g. a mounted
+    /// Kubernetes Secret.
+    /// `LISTENER_ALLOWLIST` is a comma separated list of CIDR blocks, e.g. `10.42.0.0/16,127.0.0.1`.
+    /// `DDOS_MODE=true` starts the proxy in DDoS mode.
+    /// `MAX_BACKENDS_PER_LABEL` is a comma separated list of `label=limit`, e.g. `tenant=10`
+    /// allows at most 10 backends for every value of the `tenant` label.
+    ///
+    /// Arguments:
+    ///
+    /// * `var`: A function returning the value of an environment variable, if it is set.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub fn override_with<F: Fn(&str) -> Option<String>>(&mut self, var: F) -> Result<()> {
+        if let Some(port) = parse_var(&var, "PROXY_PORT")? {
+            self.proxy.port = port;
+        }
+        if let Some(port) = parse_var(&var, "LISTENER_PORT")? {
+            self.listener.port = port;
+        }
+        if let Some(port) = parse_var(&var, "METRICS_PORT")? {
+            self.metrics.port = port;
+        }
+        if let Some(port) = parse_var(&var, "HEALTH_PORT")? {
+            self.health.port = port;
+        }
+        if let Some(timeout) = parse_var(&var, "HANDSHAKE_TIMEOUT")? {
+            self.timeouts.handshake_secs = timeout;
+        }
+        if let Some(timeout) = parse_var(&var, "CONNECT_TIMEOUT")? {
+            self.timeouts.connect_secs = timeout;
+        }
+        if let Some(max_backends) = parse_var(&var, "MAX_BACKENDS")? {
+            self.limits.max_backends = Some(max_backends);
+        }
+        if let Some(retention) = parse_var(&var, "TOMBSTONE_RETENTION")? {
+            self.limits.tombstone_retention_secs = Some(retention);
+        }
+        if let Some(file) = var("LISTENER_TOKEN_FILE") {
+            self.listener.token = Some(Secret::File { file: file.into() });
+        }
+        if let Some(token) = var("LISTENER_TOKEN") {
+            self.listener.token = Some(Secret::Value(token));
+        }
+        if let Some(enabled) = parse_var(&var, "DDOS_MODE")? {
+            self.ddos_mode.enabled = enabled;
+        }
+        if let Some(allowlist) = var("LISTENER_ALLOWLIST") {
+            self.listener.allowlist = allowlist
+                .split(',')
+                .map(str::trim)
+                .filter(|block| !block.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
+        let per_label = var("MAX_BACKENDS_PER_LABEL").unwrap_or_default();
+        for quota in per_label.split(',').filter(|quota| !quota.is_empty()) {
+            let (label, limit) = quota
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid MAX_BACKENDS_PER_LABEL entry {}", quota))?;
+            let limit = limit
+                .parse()
+                .map_err(|e| anyhow!("Invalid MAX_BACKENDS_PER_LABEL entry {}: {}", quota, e))?;
+
+            self.limits
+                .max_backends_per_label
+                .insert(label.trim().to_string(), limit);
+        }
+
+        Ok(())
+    }
+}
+
+/// It parses an environment variable, if it is set
+fn parse_var<F, T>(var: &F, name: &str) -> Result<Option<T>>
+where
+    F: Fn(&str) -> Option<String>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    var(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|e| anyhow!("Invalid {} {}: {}", name, value, e))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_fills_missing_sections_with_defaults() {
+        let config = ProxyConfig::parse(
+            r#"
+            [proxy]
+            port = 25566
+
+            [limits]
+            max_backends = 10
+            max_backends_per_label = { tenant = 2 }
+            "#,
+            Format::Toml,
+        )
+        .unwrap();
+
+        assert_eq!(config.proxy.addr(), "0.0.0.0:25566");
+        assert_eq!(config.listener.addr(), "0.0.0.0:65535");
+        assert_eq!(config.metrics.addr(), "0.0.0.0:9090");
+        assert_eq!(config.limits.max_backends, Some(10));
+        assert_eq!(config.limits.max_backends_per_label["tenant"], 2);
+        assert_eq!(config.timeouts, TimeoutsConfig::default());
+    }
+
+    #[test]
+    fn it_parses_yaml() {
+        let config = ProxyConfig::parse(
+            "listener:\n  port: 5000\n  tls:\n    cert: cert.pem\n    key: key.pem\nlog:\n  level: debug\n",
+            Format::Yaml,
+        )
+        .unwrap();
+
+        assert_eq!(config.listener.port, 5000);
+        assert_eq!(config.listener.tls.unwrap().cert, "cert.pem");
+        assert_eq!(config.log.level, "debug");
+        assert_eq!(config.log.format, LogFormat::Text);
+        assert_eq!(
+            ProxyConfig::parse("", Format::Yaml).unwrap(),
+            ProxyConfig::default()
+        );
+    }
+
+    #[test]
+    fn it_detects_settings_requiring_a_restart() {
+        let config = ProxyConfig::default();
+
+        let mut reloadable = config.clone();
+        reloadable.timeouts.handshake_secs = 1;
+        reloadable.messages.backend_not_found = "Nope".to_string();
+        assert!(!config.requires_restart(&reloadable));
+
+        let mut rebound = config.clone();
+        rebound.listener.port = 5000;
+        assert!(config.requires_restart(&rebound));
+
+        let mut split = ProxyConfig::parse(
+            "[runtime]\ndata_plane_threads = 4\ncontrol_plane = \"current_thread\"\n",
+            Format::Toml,
+        )
+        .unwrap();
+        assert_eq!(split.runtime.control_plane, RuntimeFlavor::CurrentThread);
+        assert!(config.requires_restart(&split));
+        split.runtime = RuntimeConfig::default();
+        assert!(!config.requires_restart(&split));
+    }
+
+    #[test]
+    fn it_validates_the_defaults() {
+        assert!(ProxyConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn it_reports_every_invalid_setting() {
+        let mut config = ProxyConfig::default();
+        config.proxy.host = "localhost".to_string();
+        config.proxy.max_connections = Some(0);
+        config.listener.port = 0;
+        config.metrics.port = 25565;
+        config.timeouts.connect_secs = 0;
+        config.metrics.statsd = Some(StatsdConfig {
+            addr: "datadog-agent".to_string(),
+            ..Default::default()
+        });
+        config.metrics.otlp = Some(OtlpConfig {
+            endpoint: "otel-collector:4318".to_string(),
+            interval_secs: 0,
+            ..Default::default()
+        });
+        config.bans = Some(BansConfig {
+            window_secs: 0,
+            ..Default::default()
+        });
+        config.online_mode = Some(OnlineModeConfig {
+            session_server: "sessionserver.mojang.com".to_string(),
+            ..Default::default()
+        });
+        config.ping_gate = Some(PingGateConfig { window_secs: 0 });
+        config.connect_rate = Some(ConnectRateConfig {
+            burst: 0,
+            ..Default::default()
+        });
+        config.throttle = Some(ThrottleConfig {
+            min_delay_ms: 0,
+            ..Default::default()
+        });
+        config.ddos_mode.ping_window_secs = 0;
+        config.sockets.client.dscp = Some(64);
+        config.capture.directory = PathBuf::new();
+        config.stats.checkpoint_secs = 0;
+        config.direct_ip.policy = DirectIpPolicy::Route;
+        config
+            .override_with(|name| {
+                (name == "LISTENER_ALLOWLIST").then(|| "10.42.0.0/16, pods".to_string())
+            })
+            .unwrap();
+        config.catch_all.favicon = Some("favicon.png".to_string());
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("proxy.host localhost is not an IP address"));
+        assert!(error.contains("listener.port must be between 1 and 65535"));
+        assert!(error.contains("proxy.max_connections must be greater than 0"));
+        assert!(error.contains("timeouts.connect_secs"));
+        assert!(error.contains("metrics.statsd.addr datadog-agent must be a host:port"));
+        assert!(error.contains("metrics.otlp.endpoint otel-collector:4318 must be an http"));
+        assert!(error.contains("metrics.otlp.interval_secs must be greater than 0"));
+        assert!(error.contains("bans.window_secs must be greater than 0"));
+        assert!(error.contains("online_mode.session_server sessionserver.mojang.com must be"));
+        assert!(error.contains("ping_gate.window_secs must be greater than 0"));
+        assert!(error.contains("throttle.min_delay_ms must be greater than 0"));
+        assert!(error.contains("ddos_mode.ping_window_secs must be greater than 0"));
+        assert!(error.contains("sockets.client.dscp must be between 0 and 63"));
+        assert!(error.contains("connect_rate.burst must be greater than 0"));
+        assert!(error.contains("capture.directory must not be empty"));
+        assert!(error.contains("stats.checkpoint_secs must be greater than 0"));
+        assert!(error.contains("direct_ip.backend is required by the route policy"));
+        assert!(error.contains("listener.allowlist: invalid CIDR block: pods"));
+        assert!(error.contains("catch_all.favicon must be a data:image/png;base64, URI"));
+        assert!(!error.contains("both bind"));
+
+        config.proxy.host = "127.0.0.1".to_string();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("proxy and metrics both bind port 25565"));
+
+        config.health.ping_port = Some(config.health.port);
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("health and health ping both bind port 8080"));
+    }
+
+    #[test]
+    fn it_parses_static_routes() {
+        let config = ProxyConfig::parse(
+            r#"
+            [[routes]]
+            hostname = "Lobby.example.com"
+            redirect_ip = "10.0.0.1"
+            forwarding_mode = "velocity"
+
+            [[routes]]
+            hostname = "lobby.example.com"
+            redirect_ip = "10.0.0.2"
+            redirect_port = 25566
+            min_protocol_version = 766
+            max_protocol_version = 765
+
+            [[routes.schedules]]
+            cron = "0 4 * * *"
+            duration_mins = 0
+            fallback = "limbo.example.com"
+            "#,
+            Format::Toml,
+        )
+        .unwrap();
+
+        let backends = config.static_backends();
+        assert_eq!(backends[0].hostname(), "lobby.example.com");
+        assert_eq!(backends[0].addr(), "10.0.0.1:25565");
+        assert_eq!(backends[0].forwarding_mode().as_str(), "velocity");
+        assert!(backends[0].read_only());
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("routes declare lobby.example.com more than once"));
+        assert!(error.contains(
+            "route lobby.example.com has a min_protocol_version newer than its max_protocol_version"
+        ));
+        assert_eq!(backends[1].min_protocol_version(), Some(766));
+        assert_eq!(backends[0].max_protocol_version(), None);
+        assert!(error.contains(
+            "route lobby.example.com has a schedule lasting 0 minutes, it must last 1 to 10080"
+        ));
+        assert_eq!(backends[1].schedules()[0].cron.to_string(), "0 4 * * *");
+        assert_eq!(
+            backends[1].schedules()[0].fallback(),
+            Some("limbo.example.com")
+        );
+
+        assert!(ProxyConfig::parse(
+            "[[routes]]\nhostname = \"a\"\nredirect_ip = \"b\"\nforwarding_mode = \"bogus\"",
+            Format::Toml
+        )
+        .is_err());
+        assert!(ProxyConfig::parse(
+            "[[routes]]\nhostname = \"a\"\nredirect_ip = \"b\"\nschedules = [{ cron = \"0 4 * *\", duration_mins = 5 }]",
+            Format::Toml
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn it_parses_federation_peers() {
+        let config = ProxyConfig::parse(
+            r#"
+            [[federation]]
+            name = "eu"
+            endpoint = "http://eu.example.com:65535"
+            labels = { public = "true" }
+            redirect = "eu.example.com:25565"
+
+            [[federation]]
+            name = "eu"
+            endpoint = "http://eu-2.example.com:65535"
+            redirect = "eu-2.example.com"
+            "#,
+            Format::Toml,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.federation[0].redirect().unwrap(),
+            Some(("eu.example.com".to_string(), 25565))
+        );
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("federation declares eu more than once"));
+        assert!(error.contains("redirect eu-2.example.com must be a host:port"));
+    }
+
+    #[test]
+    fn it_rejects_the_forwarding_modes_in_online_mode() {
+        let routes = r#"
+            [[routes]]
+            hostname = "lobby.example.com"
+            redirect_ip = "10.0.0.1"
+            forwarding_mode = "velocity"
+            "#;
+        assert!(ProxyConfig::parse(routes, Format::Toml)
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        let config =
+            ProxyConfig::parse(&format!("[online_mode]\n{}", routes), Format::Toml).unwrap();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains(
+            "route lobby.example.com has a velocity forwarding_mode, which online_mode doesn't support"
+        ));
+    }
+
+    #[test]
+    fn it_dumps_a_redacted_configuration() {
+        let mut config = ProxyConfig::parse(
+            r#"
+            [listener.tls]
+            cert = "cert.pem"
+            key = "key.pem"
+
+            [metrics.otlp]
+            endpoint = "https://otlp.example.com/v1/metrics"
+            headers = { x-api-key = "4p1-k3y" }
+
+            [[routes]]
+            hostname = "lobby.example.com"
+            redirect_ip = "10.0.0.1"
+            forwarding_mode = "legacy"
+            "#,
+            Format::Toml,
+        )
+        .unwrap();
+        config.limits.max_backends = Some(3);
+
+        let dump = config.redacted().to_toml().unwrap();
+        assert!(!dump.contains("key.pem"));
+        assert!(!dump.contains("4p1-k3y"));
+        assert_eq!(
+            config.redacted().metrics.otlp.unwrap().headers["x-api-key"],
+            Secret::Value(REDACTED.to_string())
+        );
+
+        let parsed = ProxyConfig::parse(&dump, Format::Toml).unwrap();
+        assert_eq!(parsed, config.redacted());
+    }
+
+    #[test]
+    fn it_reads_secrets_from_files() {
+        let path = env::temp_dir().join(format!("kubecraft-token-{}", std::process::id()));
+        fs::write(&path, "s3cr3t\n").unwrap();
+
+        let mut config = ProxyConfig::parse(
+            &format!(
+                "[listener]\ntoken = {{ file = {:?} }}",
+                path.display().to_string()
+            ),
+            Format::Toml,
+        )
+        .unwrap();
+        let token = config.listener.token.clone().unwrap();
+        assert_eq!(token.resolve().unwrap(), "s3cr3t");
+        assert!(config.validate().is_ok());
+
+        config
+            .override_with(|name| (name == "LISTENER_TOKEN").then(|| "inline".to_string()))
+            .unwrap();
+        let dump = config.redacted().to_toml().unwrap();
+        assert!(!dump.contains("inline"));
+        assert!(!format!("{:?}", config).contains("inline"));
+
+        fs::remove_file(&path).unwrap();
+        config.listener.token = Some(token);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn it_rejects_unknown_fields() {
+        assert!(ProxyConfig::parse("[proxy]\nprot = 1", Format::Toml).is_err());
+    }
+
+    #[test]
+    fn it_reloads_only_the_file_it_was_read_from() {
+        let path = env::temp_dir().join(format!("kubecraft-reload-{}.toml", std::process::id()));
+        fs::write(&path, "[proxy]\nport = 25570").unwrap();
+
+        let config = ProxyConfig::from_file(&path).unwrap();
+        fs::write(&path, "[proxy]\nport = 25571").unwrap();
+        let reloaded = config.reload().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded.proxy.port, 25571);
+        assert!(!reloaded.from_env);
+        assert!(ProxyConfig::default().reload().is_err());
+    }
+
+    #[test]
+    fn it_overrides_with_environment() {
+        let mut config = ProxyConfig::default();
+        config
+            .override_with(|name| match name {
+                "PROXY_PORT" => Some("25570".to_string()),
+                "MAX_BACKENDS_PER_LABEL" => Some("tenant=10,team=5".to_string()),
+                "DDOS_MODE" => Some("true".to_string()),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(config.proxy.port, 25570);
+        assert!(config.ddos_mode.enabled);
+        assert_eq!(config.limits.max_backends_per_label.len(), 2);
+
+        assert!(config
+            .override_with(|name| (name == "LISTENER_PORT").then(|| "70000".to_string()))
+            .is_err());
+    }
+}
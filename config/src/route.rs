@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shared::models::{
+    backend::Backend,
+    forwarding::ForwardingMode,
+    schedule::{Cron, Schedule},
+};
+
+/// A backend declared in the configuration file
+///
+/// Static routes are merged with the backends registered through the API, and can't be
+/// changed or deleted through it.
+///
+/// Properties:
+///
+/// * `hostname`: The hostname the players connect to.
+/// * `redirect_ip`: The address of the Minecraft server.
+/// * `redirect_port`: The port of the Minecraft server, 25565 by default.
+/// * `weight`: The relative share of the connections the backend receives.
+/// * `max_connections`: The maximum number of connections to the backend, `0` for unlimited.
+/// * `forwarding_mode`: How the real address of the client is forwarded to the backend.
+/// * `motd`: The message of the day to answer status pings with instead of the backend's.
+/// * `labels`: Free-form key/value pairs used to select and group backends.
+/// * `preserve_hostname`: Whether the handshake keeps the hostname the client connected with.
+/// * `tap`: Whether the packets relayed with the backend are sent to the taps of the proxy.
+/// * `min_protocol_version`: The oldest protocol version the backend supports, `0` for any.
+/// * `max_protocol_version`: The newest protocol version the backend supports, `0` for any.
+/// * `username_routes`: The hostnames of the backends some usernames are routed to instead.
+/// * `schedules`: The recurring maintenance windows of the backend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StaticRoute {
+    pub hostname: String,
+    pub redirect_ip: String,
+    #[serde(default = "default_port")]
+    pub redirect_port: u16,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub max_connections: u32,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_forwarding_mode",
+        serialize_with = "serialize_forwarding_mode"
+    )]
+    pub forwarding_mode: ForwardingMode,
+    pub motd: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub preserve_hostname: bool,
+    #[serde(default)]
+    pub tap: bool,
+    #[serde(default)]
+    pub min_protocol_version: u32,
+    #[serde(default)]
+    pub max_protocol_version: u32,
+    #[serde(default)]
+    pub username_routes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub schedules: Vec<StaticSchedule>,
+}
+
+/// A recurring maintenance window of a static route
+///
+/// Properties:
+///
+/// * `cron`: When the windows open, `minute hour day-of-month month day-of-week` in UTC.
+/// * `duration_mins`: How long the windows last, in minutes.
+/// * `fallback`: The hostname of the backend the players are sent to meanwhile, if any.
+/// * `motd`: The MOTD, and kick reason, served meanwhile instead of the maintenance message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StaticSchedule {
+    #[serde(
+        deserialize_with = "deserialize_cron",
+        serialize_with = "serialize_cron"
+    )]
+    pub cron: Cron,
+    pub duration_mins: u32,
+    pub fallback: Option<String>,
+    pub motd: Option<String>,
+}
+
+fn default_port() -> u16 {
+    25565
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// It parses a forwarding mode the same way the API does
+fn deserialize_forwarding_mode<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<ForwardingMode, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+fn serialize_forwarding_mode<S: Serializer>(
+    mode: &ForwardingMode,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(mode.as_str())
+}
+
+/// It parses a cron expression the same way the API does
+fn deserialize_cron<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cron, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+fn serialize_cron<S: Serializer>(cron: &Cron, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&cron.to_string())
+}
+
+impl StaticRoute {
+    /// It converts the route into a read-only backend
+    ///
+    /// Returns:
+    ///
+    /// A Backend
+    pub fn backend(&self) -> Backend {
+        Backend {
+            hostname: self.hostname.to_lowercase(),
+            redirect_ip: self.redirect_ip.clone(),
+            redirect_port: self.redirect_port,
+            weight: self.weight,
+            max_connections: self.max_connections,
+            forwarding_mode: self.forwarding_mode,
+            motd: self.motd.clone(),
+            labels: self.labels.clone(),
+            read_only: true,
+            preserve_hostname: self.preserve_hostname,
+            tap: self.tap,
+            min_protocol_version: self.min_protocol_version,
+            max_protocol_version: self.max_protocol_version,
+            username_routes: self.username_routes.clone(),
+            schedules: self
+                .schedules
+                .iter()
+                .map(|schedule| Schedule {
+                    cron: schedule.cron.clone(),
+                    duration_mins: schedule.duration_mins,
+                    fallback: schedule.fallback.clone(),
+                    motd: schedule.motd.clone(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
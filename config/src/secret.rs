@@ -0,0 +1,75 @@
+use std::{fmt, fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::REDACTED;
+
+/// A sensitive setting, given inline or read from a file
+///
+/// Reading it from a file allows mounting a Kubernetes Secret instead of writing the value in
+/// the configuration or in the environment.
+///
+/// Properties:
+///
+/// * `Value`: The secret itself, e.g. `token = "..."`.
+/// * `File`: The file holding the secret, e.g. `token = { file = "/run/secrets/token" }`.
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Value(String),
+    File { file: PathBuf },
+}
+
+impl Secret {
+    /// It returns the value of the secret, reading its file if needed
+    ///
+    /// The trailing newline of a file is dropped.
+    ///
+    /// Returns:
+    ///
+    /// A Result<String>
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            Secret::Value(value) => Ok(value.clone()),
+            Secret::File { file } => read_secret_file(file),
+        }
+    }
+
+    /// It returns a copy of the secret safe to display, a file is kept as its path is not secret
+    ///
+    /// Returns:
+    ///
+    /// A Secret
+    pub fn redacted(&self) -> Secret {
+        match self {
+            Secret::Value(_) => Secret::Value(REDACTED.to_string()),
+            Secret::File { file } => Secret::File { file: file.clone() },
+        }
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Value(_) => f.write_str(REDACTED),
+            Secret::File { file } => f.debug_struct("File").field("file", file).finish(),
+        }
+    }
+}
+
+/// It reads a file holding a secret, without its trailing newline
+///
+/// Arguments:
+///
+/// * `path`: The path of the file
+///
+/// Returns:
+///
+/// A Result<String>
+pub fn read_secret_file(path: &PathBuf) -> Result<String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read secret file {}: {}", path.display(), e))?;
+
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
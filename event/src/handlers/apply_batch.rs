@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use storage::{BackendChange, BackendStore};
+use tokio::sync::{oneshot, RwLock};
+
+pub struct ApplyBatchHandler {}
+
+impl ApplyBatchHandler {
+    /// It handles the `ApplyBatch` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `batch`: The puts and deletes to apply atomically.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        batch: Vec<BackendChange>,
+        tx: oneshot::Sender<Result<Vec<BackendChange>>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let result = storage.apply(batch).context("Failed to apply batch");
+
+        let _ = tx.send(result);
+    }
+}
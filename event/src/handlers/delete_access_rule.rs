@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use shared::models::access_rule::{AccessKind, AccessRule, AccessSubject};
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct DeleteAccessRuleHandler {}
+
+impl DeleteAccessRuleHandler {
+    /// It handles the `DeleteAccessRule` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the access rules
+    /// * `kind`: Whether the rule to remove is a ban or an allow.
+    /// * `subject`: The subject of the rule to remove.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        kind: AccessKind,
+        subject: AccessSubject,
+        tx: oneshot::Sender<Result<AccessRule>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let result = storage
+            .remove_access_rule(kind, &subject)
+            .context("Failed to delete access rule");
+
+        let _ = tx.send(result);
+    }
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use shared::models::backend::Backend;
-use storage::Storage;
-use tokio::sync::{oneshot, Mutex};
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
 
 pub struct DeleteBackendHandler {}
 
@@ -12,19 +12,19 @@ impl DeleteBackendHandler {
     ///
     /// Arguments:
     ///
-    /// * `storage`: Arc<Mutex<Storage>> - the storage object that holds all the backends
-    /// * `backend`: The backend to add to the storage.
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `backend`: The backend to delete from the storage, with its expected version.
     /// * `tx`: This is the channel that the client is listening on.
     pub async fn handle(
-        storage: Arc<Mutex<Storage>>,
+        storage: Arc<RwLock<dyn BackendStore>>,
         backend: Backend,
         tx: oneshot::Sender<Result<()>>,
     ) {
-        let mut storage = storage.lock().await;
+        let mut storage = storage.write().await;
 
         let result = storage
-            .remove_backend(&backend.hostname)
-            .map_err(|e| anyhow!("Failed to delete backend: {}", e));
+            .remove_backend(backend.hostname(), backend.version())
+            .context("Failed to delete backend");
 
         let _ = tx.send(result);
     }
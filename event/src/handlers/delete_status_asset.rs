@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use shared::models::status_asset::StatusAsset;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct DeleteStatusAssetHandler {}
+
+impl DeleteStatusAssetHandler {
+    /// It handles the `DeleteStatusAsset` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the status assets
+    /// * `hostname`: The hostname of the asset to remove.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        hostname: String,
+        tx: oneshot::Sender<Result<StatusAsset>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let result = storage
+            .remove_status_asset(&hostname)
+            .context("Failed to delete status asset");
+
+        let _ = tx.send(result);
+    }
+}
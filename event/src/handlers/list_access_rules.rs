@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::models::access_rule::AccessRule;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct ListAccessRulesHandler {}
+
+impl ListAccessRulesHandler {
+    /// It handles the `ListAccessRules` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the access rules
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        tx: oneshot::Sender<Result<Vec<AccessRule>>>,
+    ) {
+        let storage = storage.read().await;
+
+        let _ = tx.send(Ok(storage.access_rules()));
+    }
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use shared::models::backend::Backend;
-use storage::Storage;
-use tokio::sync::{oneshot, Mutex};
+use shared::models::{backend::Backend, selector::LabelSelector};
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
 
 pub struct ListBackendHandler {}
 
@@ -12,13 +12,17 @@ impl ListBackendHandler {
     ///
     /// Arguments:
     ///
-    /// * `storage`: Arc<Mutex<Storage>> - the storage object that holds all the backends
-    /// * `backend`: The backend to add to the storage.
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `selector`: The label selector of the backends, an empty one for every backend.
     /// * `tx`: This is the channel that the client is listening on.
-    pub async fn handle(storage: Arc<Mutex<Storage>>, tx: oneshot::Sender<Result<Vec<Backend>>>) {
-        let storage = storage.lock().await;
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        selector: LabelSelector,
+        tx: oneshot::Sender<Result<Vec<Backend>>>,
+    ) {
+        let storage = storage.read().await;
 
-        let backends = storage.get_backends().clone().into_values().collect();
+        let backends = storage.select_backends(&selector);
 
         let _ = tx.send(Ok(backends));
     }
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::models::status_asset::StatusAsset;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct ListStatusAssetsHandler {}
+
+impl ListStatusAssetsHandler {
+    /// It handles the `ListStatusAssets` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the status assets
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        tx: oneshot::Sender<Result<Vec<StatusAsset>>>,
+    ) {
+        let storage = storage.read().await;
+
+        let _ = tx.send(Ok(storage.status_assets()));
+    }
+}
@@ -1,3 +1,16 @@
+pub mod apply_batch;
+pub mod delete_access_rule;
 pub mod delete_backend;
+pub mod delete_status_asset;
+pub mod list_access_rules;
 pub mod list_backend;
+pub mod list_status_assets;
+pub mod probe_backend;
+pub mod put_access_rule;
 pub mod put_backend;
+pub mod put_status_asset;
+pub mod restore_backend;
+pub mod restore_state;
+pub mod snapshot_state;
+pub mod validate_backend;
+pub mod watch_backends;
@@ -0,0 +1,217 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use protocol::{
+    packets::{
+        clientbound::pong::Pong,
+        serverbound::{
+            handshake::{Handshake, NextState},
+            ping::Ping,
+        },
+    },
+    read_packet, read_string, read_var_int, write_packet, ProtocolError,
+};
+use shared::{models::backend::Backend, probe::Probe};
+use storage::{BackendStore, StorageError};
+use tokio::{
+    net::TcpStream,
+    sync::{oneshot, RwLock},
+    time::timeout_at,
+};
+
+/// How long a probe waits for a backend without a health check, the connection included
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum length of the status of a backend, in bytes, its favicon included
+const MAX_STATUS_SIZE: usize = 128 * 1024;
+
+/// The ID of the status request, and of the status answering it
+const STATUS_ID: i32 = 0;
+
+pub struct ProbeBackendHandler {}
+
+impl ProbeBackendHandler {
+    /// It handles the `ProbeBackend` event.
+    ///
+    /// The storage is only locked to look the backend up, not while it is probed.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `hostname`: The hostname of the backend to probe.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        hostname: String,
+        tx: oneshot::Sender<Result<Probe>>,
+    ) {
+        let backend = storage.read().await.get_backend(&hostname).cloned();
+
+        let result = match backend {
+            Some(backend) => Ok(probe(&backend).await),
+            None => Err(StorageError::NotFound(hostname)).context("Failed to probe backend"),
+        };
+
+        let _ = tx.send(result);
+    }
+}
+
+/// It pings the stored target of a backend as the server list does, up to the timeout of its
+/// health check
+///
+/// Arguments:
+///
+/// * `backend`: The backend to probe.
+///
+/// Returns:
+///
+/// The Probe, with the cause of the failure when the backend didn't answer
+async fn probe(backend: &Backend) -> Probe {
+    let wait = backend.health_check().map_or(PROBE_TIMEOUT, |check| {
+        Duration::from_secs(check.timeout_secs.into())
+    });
+    let deadline = tokio::time::Instant::now() + wait;
+    let mut probe = Probe {
+        target: backend.addr(),
+        ..Default::default()
+    };
+
+    let connecting = Instant::now();
+    let mut stream = match timeout_at(deadline, TcpStream::connect(&probe.target)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            probe.error = Some(format!("failed to connect: {}", e));
+            return probe;
+        }
+        Err(_) => {
+            probe.error = Some(format!("timed out connecting after {:?}", wait));
+            return probe;
+        }
+    };
+    probe.connect = Some(connecting.elapsed());
+
+    match timeout_at(deadline, ping(&mut stream, backend, &mut probe)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => probe.error = Some(format!("failed to ping: {:#}", e)),
+        Err(_) => probe.error = Some(format!("timed out pinging after {:?}", wait)),
+    }
+    probe
+}
+
+/// It asks a backend for its status then pings it, on a connection opened to it
+///
+/// Arguments:
+///
+/// * `stream`: The connection to the backend.
+/// * `backend`: The backend, for the handshake the proxy would write.
+/// * `probe`: The probe, given the status and the latency.
+///
+/// Returns:
+///
+/// A Result<()>
+async fn ping(stream: &mut TcpStream, backend: &Backend, probe: &mut Probe) -> Result<()> {
+    // the backend sees the handshake the proxy would forward
+    let hostname = match backend.preserve_hostname() {
+        true => backend.hostname(),
+        false => backend.redirect_ip(),
+    };
+    Handshake::new(
+        -1,
+        hostname.to_string(),
+        backend.redirect_port(),
+        NextState::Status,
+    )
+    .write(stream)
+    .await?;
+
+    write_packet(stream, &[STATUS_ID as u8]).await?;
+    let mut status = read_packet(stream, MAX_STATUS_SIZE).await?;
+    let id = read_var_int(&mut status).await?;
+    if id != STATUS_ID {
+        return Err(ProtocolError::InvalidPacketId {
+            packet: "status",
+            id,
+        }
+        .into());
+    }
+    probe.status = Some(read_string(&mut status, MAX_STATUS_SIZE).await?);
+
+    let payload = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64);
+    let pinging = Instant::now();
+    Ping::new(payload).write(stream).await?;
+    let pong = Pong::read(stream).await?;
+    if pong.payload() != payload {
+        return Err(anyhow!("the pong carries another payload"));
+    }
+    probe.latency = Some(pinging.elapsed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::write_string;
+    use storage::Storage;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// It answers a single status ping with a status, then echoes the ping
+    async fn fake_backend(listener: TcpListener) {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        read_packet(&mut socket, 1024).await.unwrap();
+        read_packet(&mut socket, 16).await.unwrap();
+
+        let mut status = vec![0];
+        write_string(&mut status, r#"{"description":{"text":"lobby"}}"#)
+            .await
+            .unwrap();
+        write_packet(&mut socket, &status).await.unwrap();
+        let ping = read_packet(&mut socket, 16).await.unwrap();
+        write_packet(&mut socket, ping.get_ref()).await.unwrap();
+    }
+
+    /// It probes a backend through the handler, as the proxy does on a `ProbeBackend` event
+    async fn probe_of(storage: &Arc<RwLock<Storage>>, hostname: &str) -> Result<Probe> {
+        let (tx, rx) = oneshot::channel();
+        ProbeBackendHandler::handle(storage.clone(), hostname.to_string(), tx).await;
+        rx.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_pings_the_stored_target() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(fake_backend(listener));
+
+        let storage = Arc::new(RwLock::new(Storage::new()));
+        for (hostname, port) in [("lobby.example.com", port), ("down.example.com", 1)] {
+            let backend = Backend::new(hostname.to_string(), "127.0.0.1".to_string(), port);
+            storage.write().await.add_backend(backend).unwrap();
+        }
+
+        let probe = probe_of(&storage, "lobby.example.com").await.unwrap();
+        assert!(probe.reachable(), "{:?}", probe.error);
+        assert_eq!(probe.target, format!("127.0.0.1:{}", port));
+        assert_eq!(
+            probe.status.as_deref(),
+            Some(r#"{"description":{"text":"lobby"}}"#)
+        );
+        assert!(probe.connect.is_some() && probe.latency.is_some());
+
+        let probe = probe_of(&storage, "down.example.com").await.unwrap();
+        assert!(probe.error.unwrap().starts_with("failed to connect"));
+        assert_eq!(probe.status, None);
+
+        let error = probe_of(&storage, "unknown.example.com").await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<StorageError>(),
+            Some(&StorageError::NotFound("unknown.example.com".to_string()))
+        );
+    }
+}
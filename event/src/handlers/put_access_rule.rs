@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::models::access_rule::AccessRule;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct PutAccessRuleHandler {}
+
+impl PutAccessRuleHandler {
+    /// It handles the `PutAccessRule` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the access rules
+    /// * `rule`: The rule to add, it replaces the one of the same kind and subject.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        rule: AccessRule,
+        tx: oneshot::Sender<Result<AccessRule>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let _ = tx.send(Ok(storage.put_access_rule(rule)));
+    }
+}
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{Context, Result};
 use shared::models::backend::Backend;
-use storage::Storage;
-use tokio::sync::{oneshot, Mutex};
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
 
 pub struct PutBackendHandler {}
 
@@ -12,19 +12,19 @@ impl PutBackendHandler {
     ///
     /// Arguments:
     ///
-    /// * `storage`: Arc<Mutex<Storage>> - the storage object that holds all the backends
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
     /// * `backend`: The backend to add to the storage.
     /// * `tx`: This is the channel that the client is listening on.
     pub async fn handle(
-        storage: Arc<Mutex<Storage>>,
+        storage: Arc<RwLock<dyn BackendStore>>,
         backend: Backend,
-        tx: oneshot::Sender<Result<()>>,
+        tx: oneshot::Sender<Result<Backend>>,
     ) {
-        let mut storage = storage.lock().await;
+        let mut storage = storage.write().await;
 
         let result = storage
             .add_backend(backend)
-            .map_err(|e| anyhow!(format!("Failed to add backend: {}", e)));
+            .context("Failed to add backend");
 
         let _ = tx.send(result);
     }
@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::models::status_asset::StatusAsset;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct PutStatusAssetHandler {}
+
+impl PutStatusAssetHandler {
+    /// It handles the `PutStatusAsset` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the status assets
+    /// * `asset`: The asset to add, it replaces the one of its hostname.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        asset: StatusAsset,
+        tx: oneshot::Sender<Result<StatusAsset>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let _ = tx.send(Ok(storage.put_status_asset(asset)));
+    }
+}
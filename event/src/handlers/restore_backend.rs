@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use shared::models::backend::Backend;
+use storage::BackendStore;
+use tokio::sync::{oneshot, RwLock};
+
+pub struct RestoreBackendHandler {}
+
+impl RestoreBackendHandler {
+    /// It handles the `RestoreBackend` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `hostname`: The hostname of the deleted backend to restore.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        hostname: String,
+        tx: oneshot::Sender<Result<Backend>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        let result = storage
+            .restore_backend(&hostname)
+            .context("Failed to restore backend");
+
+        let _ = tx.send(result);
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::stats::Stats;
+use storage::{BackendStore, Snapshot};
+use tokio::sync::{oneshot, RwLock};
+
+pub struct RestoreStateHandler {}
+
+impl RestoreStateHandler {
+    /// It handles the `RestoreState` event.
+    ///
+    /// The counting of the backends carries on from the totals of the snapshot.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `stats`: The counters of every backend.
+    /// * `snapshot`: The snapshot replacing the whole state of the storage.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        stats: Arc<Stats>,
+        snapshot: Snapshot,
+        tx: oneshot::Sender<Result<()>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        stats.restore(&snapshot.backend_totals);
+        storage.restore(snapshot);
+
+        let _ = tx.send(Ok(()));
+    }
+}
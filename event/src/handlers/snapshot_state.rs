@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::stats::Stats;
+use storage::{BackendStore, Snapshot};
+use tokio::sync::{oneshot, RwLock};
+
+pub struct SnapshotStateHandler {}
+
+impl SnapshotStateHandler {
+    /// It handles the `SnapshotState` event.
+    ///
+    /// The totals of the backends are checkpointed first, so the snapshot has the latest ones.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `stats`: The counters of every backend.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        stats: Arc<Stats>,
+        tx: oneshot::Sender<Result<Snapshot>>,
+    ) {
+        let mut storage = storage.write().await;
+
+        storage.checkpoint_totals(stats.cumulative_totals());
+
+        let _ = tx.send(Ok(storage.snapshot()));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use shared::{
+    models::backend::{check_hostname, Backend},
+    validation::Validation,
+};
+use storage::BackendStore;
+use tokio::{
+    net::lookup_host,
+    sync::{oneshot, RwLock},
+    time::timeout,
+};
+
+/// How long the address of a backend is resolved for
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct ValidateBackendHandler {}
+
+impl ValidateBackendHandler {
+    /// It handles the `ValidateBackend` event, a dry run of the `PutBackend` event.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `backend`: The backend to validate, left out of the storage.
+    /// * `route_by_port`: Whether the proxy routes the `hostname:port` of the handshakes.
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        backend: Backend,
+        route_by_port: bool,
+        tx: oneshot::Sender<Result<Validation>>,
+    ) {
+        let mut validation = Validation::default();
+
+        if let Err(e) = check_hostname(backend.hostname()) {
+            validation.errors.push(e);
+        }
+        if !route_by_port && backend.hostname().contains(':') {
+            validation.warnings.push(format!(
+                "{} matches no handshake, proxy.route_by_port is disabled",
+                backend.hostname()
+            ));
+        }
+        match backend.redirect_port() {
+            0 => validation
+                .errors
+                .push("redirect port must be greater than 0".to_string()),
+            _ => {
+                if let Err(e) = resolve(&backend).await {
+                    validation.errors.push(e);
+                }
+            }
+        }
+
+        let storage = storage.read().await;
+        if let Err(e) = storage.check_backend(&backend) {
+            validation.errors.push(e.to_string());
+        }
+        // the usernames routes and the fallbacks only work on a stored backend
+        let targets = backend
+            .username_routes()
+            .values()
+            .map(String::as_str)
+            .chain(backend.schedules().iter().filter_map(|s| s.fallback()));
+        for target in targets {
+            if target == backend.hostname() {
+                validation
+                    .warnings
+                    .push(format!("{} routes players to itself", target));
+            } else if storage.get_backend(target).is_none() {
+                validation.warnings.push(format!(
+                    "{} routes players to {}, which isn't stored",
+                    backend.hostname(),
+                    target
+                ));
+            }
+        }
+
+        let _ = tx.send(Ok(validation));
+    }
+}
+
+/// It resolves the redirect address of a backend, as the proxy does when it connects to it
+///
+/// Arguments:
+///
+/// * `backend`: The backend.
+///
+/// Returns:
+///
+/// A Result<(), String>, with the reason the address doesn't resolve
+async fn resolve(backend: &Backend) -> Result<(), String> {
+    match timeout(RESOLVE_TIMEOUT, lookup_host(backend.addr())).await {
+        Ok(Ok(mut addrs)) => match addrs.next() {
+            Some(_) => Ok(()),
+            None => Err(format!("{} resolves to no address", backend.redirect_ip())),
+        },
+        Ok(Err(e)) => Err(format!(
+            "failed to resolve {}: {}",
+            backend.redirect_ip(),
+            e
+        )),
+        Err(_) => Err(format!(
+            "timed out resolving {} after {:?}",
+            backend.redirect_ip(),
+            RESOLVE_TIMEOUT
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::models::schedule::Schedule;
+    use storage::Storage;
+
+    use super::*;
+
+    /// It validates a backend through the handler, as the proxy does on a `ValidateBackend`
+    /// event
+    async fn validate(storage: &Arc<RwLock<Storage>>, backend: Backend) -> Validation {
+        let (tx, rx) = oneshot::channel();
+        ValidateBackendHandler::handle(storage.clone(), backend, false, tx).await;
+        rx.await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn it_validates_a_backend_without_storing_it() {
+        let storage = Arc::new(RwLock::new(Storage::new()));
+        let lobby = Backend::new(
+            "lobby.example.com".to_string(),
+            "127.0.0.1".to_string(),
+            25565,
+        );
+
+        assert_eq!(
+            validate(&storage, lobby.clone()).await,
+            Validation::default()
+        );
+        assert!(storage.read().await.get_backends().is_empty());
+
+        let mut invalid = Backend::new(
+            "*.example.com:25566".to_string(),
+            "127.0.0.1".to_string(),
+            0,
+        );
+        invalid.version = 3;
+        invalid
+            .username_routes
+            .insert("Notch".to_string(), "staging.example.com".to_string());
+        invalid.schedules.push(Schedule {
+            cron: "0 4 * * *".parse().unwrap(),
+            duration_mins: 10,
+            fallback: Some("*.example.com:25566".to_string()),
+            motd: None,
+        });
+        let validation = validate(&storage, invalid).await;
+
+        assert!(!validation.is_valid());
+        assert_eq!(validation.errors.len(), 3, "{:?}", validation.errors);
+        assert!(validation.errors[0].starts_with("wildcard"));
+        assert_eq!(validation.errors[1], "redirect port must be greater than 0");
+        assert!(validation.errors[2].starts_with("version conflict"));
+        assert_eq!(validation.warnings.len(), 3, "{:?}", validation.warnings);
+    }
+}
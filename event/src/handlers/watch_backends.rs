@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use shared::models::backend::Backend;
+use storage::{BackendChange, BackendStore};
+use tokio::sync::{broadcast, oneshot, RwLock};
+
+pub struct WatchBackendsHandler {}
+
+impl WatchBackendsHandler {
+    /// It handles the `WatchBackends` event.
+    ///
+    /// The current backends are read under the same lock as the subscription, so the
+    /// watcher doesn't miss any change between the two.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: Arc<RwLock<dyn BackendStore>> - the storage object that holds all the backends
+    /// * `tx`: This is the channel that the client is listening on.
+    pub async fn handle(
+        storage: Arc<RwLock<dyn BackendStore>>,
+        tx: oneshot::Sender<Result<(Vec<Backend>, broadcast::Receiver<BackendChange>)>>,
+    ) {
+        let storage = storage.read().await;
+
+        let backends = storage.get_backends().clone().into_values().collect();
+        let changes = storage.subscribe();
+
+        let _ = tx.send(Ok((backends, changes)));
+    }
+}
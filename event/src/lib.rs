@@ -1,5 +1,16 @@
+use anyhow::{anyhow, Result};
 use proto::proxy::Backend;
 
+use shared::{
+    models::{
+        access_rule::{AccessKind, AccessRule},
+        forwarding::ForwardingMode,
+        health_check::HealthCheck,
+        schedule::{Schedule, MAX_SCHEDULE_MINS},
+        status_asset::StatusAsset,
+    },
+    stats::{Health, HealthState, Totals},
+};
 use tokio::sync::oneshot;
 
 pub mod handlers;
@@ -8,7 +19,7 @@ pub mod handlers;
 pub enum Event {
     // Backend events
     ListBackend(oneshot::Sender<Result<Vec<Backend>, tonic::Status>>),
-    PutBackend(Backend, oneshot::Sender<Result<(), tonic::Status>>),
+    PutBackend(Backend, oneshot::Sender<Result<Backend, tonic::Status>>),
     DeleteBackend(Backend, oneshot::Sender<Result<(), tonic::Status>>),
 }
 
@@ -20,13 +31,126 @@ pub enum Event {
 ///
 /// Returns:
 ///
-/// A proxy::backend::Backend struct
-pub fn proxy_backend_from_tonic(backend: Backend) -> shared::models::backend::Backend {
-    shared::models::backend::Backend::new(
-        backend.hostname,
-        backend.redirect_ip,
-        backend.redirect_port as u16,
-    )
+/// A Result<proxy::backend::Backend>, an error if a field holds an invalid value
+pub fn proxy_backend_from_tonic(backend: Backend) -> Result<shared::models::backend::Backend> {
+    let redirect_port = u16::try_from(backend.redirect_port)
+        .map_err(|_| anyhow!("invalid redirect port: {}", backend.redirect_port))?;
+    let forwarding_mode = forwarding_mode_from_tonic(backend.forwarding_mode)?;
+    if backend.min_protocol_version > 0
+        && backend.max_protocol_version > 0
+        && backend.min_protocol_version > backend.max_protocol_version
+    {
+        return Err(anyhow!(
+            "min_protocol_version {} is newer than max_protocol_version {}",
+            backend.min_protocol_version,
+            backend.max_protocol_version
+        ));
+    }
+
+    let schedules = backend
+        .schedules
+        .into_iter()
+        .map(|schedule| {
+            if schedule.duration_mins == 0 || schedule.duration_mins > MAX_SCHEDULE_MINS {
+                return Err(anyhow!(
+                    "invalid schedule duration: {} minutes",
+                    schedule.duration_mins
+                ));
+            }
+            Ok(Schedule {
+                cron: schedule.cron.parse().map_err(|e| anyhow!("{}", e))?,
+                duration_mins: schedule.duration_mins,
+                fallback: Some(schedule.fallback).filter(|fallback| !fallback.is_empty()),
+                motd: Some(schedule.motd).filter(|motd| !motd.is_empty()),
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(shared::models::backend::Backend {
+        hostname: backend.hostname,
+        redirect_ip: backend.redirect_ip,
+        redirect_port,
+        version: backend.version,
+        weight: backend.weight,
+        max_connections: backend.max_connections,
+        forwarding_mode,
+        motd: Some(backend.motd).filter(|motd| !motd.is_empty()),
+        health_check: backend.health_check.map(|health_check| HealthCheck {
+            interval_secs: health_check.interval_secs,
+            timeout_secs: health_check.timeout_secs,
+            unhealthy_threshold: health_check.unhealthy_threshold,
+        }),
+        labels: backend.labels.into_iter().collect(),
+        // only the configuration file declares read-only backends
+        read_only: false,
+        preserve_hostname: backend.preserve_hostname,
+        tap: backend.tap,
+        min_protocol_version: backend.min_protocol_version,
+        max_protocol_version: backend.max_protocol_version,
+        username_routes: backend.username_routes.into_iter().collect(),
+        schedules,
+    })
+}
+
+/// It converts the forwarding mode of a gRPC backend into the one of the proxy
+///
+/// Arguments:
+///
+/// * `mode`: The value of the `ForwardingMode` enum
+///
+/// Returns:
+///
+/// A Result<ForwardingMode>, an error for a value the enum doesn't define
+pub fn forwarding_mode_from_tonic(mode: i32) -> Result<ForwardingMode> {
+    let mode = proto::proxy::ForwardingMode::from_i32(mode)
+        .ok_or_else(|| anyhow!("unknown forwarding mode: {}", mode))?;
+
+    Ok(match mode {
+        proto::proxy::ForwardingMode::None => ForwardingMode::None,
+        proto::proxy::ForwardingMode::Legacy => ForwardingMode::Legacy,
+        proto::proxy::ForwardingMode::Velocity => ForwardingMode::Velocity,
+    })
+}
+
+/// It converts the forwarding mode of the proxy into the one of the gRPC API
+///
+/// Arguments:
+///
+/// * `mode`: The forwarding mode of a backend
+///
+/// Returns:
+///
+/// A proto::proxy::ForwardingMode
+pub fn tonic_forwarding_mode(mode: ForwardingMode) -> proto::proxy::ForwardingMode {
+    match mode {
+        ForwardingMode::None => proto::proxy::ForwardingMode::None,
+        ForwardingMode::Legacy => proto::proxy::ForwardingMode::Legacy,
+        ForwardingMode::Velocity => proto::proxy::ForwardingMode::Velocity,
+    }
+}
+
+/// It converts the health of a backend into the one of the gRPC API
+///
+/// Arguments:
+///
+/// * `health`: The health of a backend
+///
+/// Returns:
+///
+/// A proto::proxy::BackendHealth
+pub fn tonic_health(health: Health) -> proto::proxy::BackendHealth {
+    let state = match health.state {
+        HealthState::Healthy => proto::proxy::HealthState::Healthy,
+        HealthState::Degraded => proto::proxy::HealthState::Degraded,
+        HealthState::Down => proto::proxy::HealthState::Down,
+    };
+
+    proto::proxy::BackendHealth {
+        state: state.into(),
+        score: health.score,
+        consecutive_failures: health.consecutive_failures,
+        connect_ms: health.connect_ms,
+    }
 }
 
 /// It takes a `proxy::backend::Backend` and returns a `proto::proxy::Backend`
@@ -40,8 +164,328 @@ pub fn proxy_backend_from_tonic(backend: Backend) -> shared::models::backend::Ba
 /// A proto::proxy::Backend struct
 pub fn tonic_backend_from_proxy(backend: shared::models::backend::Backend) -> Backend {
     Backend {
-        hostname: backend.hostname().to_string(),
-        redirect_ip: backend.redirect_ip().to_string(),
         redirect_port: backend.redirect_port() as u32,
+        version: backend.version(),
+        weight: backend.weight(),
+        max_connections: backend.max_connections,
+        forwarding_mode: tonic_forwarding_mode(backend.forwarding_mode()) as i32,
+        motd: backend.motd.unwrap_or_default(),
+        health_check: backend
+            .health_check
+            .map(|health_check| proto::proxy::HealthCheck {
+                interval_secs: health_check.interval_secs,
+                timeout_secs: health_check.timeout_secs,
+                unhealthy_threshold: health_check.unhealthy_threshold,
+            }),
+        labels: backend.labels.into_iter().collect(),
+        read_only: backend.read_only,
+        preserve_hostname: backend.preserve_hostname,
+        tap: backend.tap,
+        min_protocol_version: backend.min_protocol_version,
+        max_protocol_version: backend.max_protocol_version,
+        username_routes: backend.username_routes.into_iter().collect(),
+        schedules: backend
+            .schedules
+            .into_iter()
+            .map(|schedule| proto::proxy::Schedule {
+                cron: schedule.cron.to_string(),
+                duration_mins: schedule.duration_mins,
+                fallback: schedule.fallback.unwrap_or_default(),
+                motd: schedule.motd.unwrap_or_default(),
+            })
+            .collect(),
+        hostname: backend.hostname,
+        redirect_ip: backend.redirect_ip,
+        // the health is the one of the running proxy, not of the stored backend
+        health: None,
+    }
+}
+
+/// It converts the kind of an access rule of the gRPC API into the one of the proxy
+///
+/// Arguments:
+///
+/// * `kind`: The value of the `AccessRule.Kind` enum
+///
+/// Returns:
+///
+/// A Result<AccessKind>, an error for a value the enum doesn't define
+pub fn access_kind_from_tonic(kind: i32) -> Result<AccessKind> {
+    let kind = proto::proxy::access_rule::Kind::from_i32(kind)
+        .ok_or_else(|| anyhow!("unknown access rule kind: {}", kind))?;
+
+    Ok(match kind {
+        proto::proxy::access_rule::Kind::Ban => AccessKind::Ban,
+        proto::proxy::access_rule::Kind::Allow => AccessKind::Allow,
+    })
+}
+
+/// It takes a `proto::proxy::AccessRule` and returns the access rule of the proxy
+///
+/// The `ttl_secs` of the rule is not read, its `expires_at_ms` is, 0 for a rule which never
+/// expires.
+///
+/// Arguments:
+///
+/// * `rule`: proto::proxy::AccessRule
+///
+/// Returns:
+///
+/// A Result<AccessRule>, an error for an unknown kind or a subject which is neither an address,
+/// a CIDR block nor a username
+pub fn proxy_access_rule_from_tonic(rule: proto::proxy::AccessRule) -> Result<AccessRule> {
+    Ok(AccessRule {
+        kind: access_kind_from_tonic(rule.kind)?,
+        subject: rule.subject.parse().map_err(|e: String| anyhow!(e))?,
+        reason: rule.reason,
+        created_at_ms: rule.created_at_ms,
+        expires_at_ms: Some(rule.expires_at_ms).filter(|expires_at_ms| *expires_at_ms > 0),
+    })
+}
+
+/// It takes an access rule of the proxy and returns a `proto::proxy::AccessRule`
+///
+/// Arguments:
+///
+/// * `rule`: AccessRule
+///
+/// Returns:
+///
+/// A proto::proxy::AccessRule, without `ttl_secs`
+pub fn tonic_access_rule_from_proxy(rule: AccessRule) -> proto::proxy::AccessRule {
+    let kind = match rule.kind {
+        AccessKind::Ban => proto::proxy::access_rule::Kind::Ban,
+        AccessKind::Allow => proto::proxy::access_rule::Kind::Allow,
+    };
+
+    proto::proxy::AccessRule {
+        kind: kind as i32,
+        subject: rule.subject.to_string(),
+        reason: rule.reason,
+        ttl_secs: 0,
+        created_at_ms: rule.created_at_ms,
+        expires_at_ms: rule.expires_at_ms.unwrap_or_default(),
+    }
+}
+
+/// It takes a `proto::proxy::StatusAsset` and returns the status asset of the proxy
+///
+/// Arguments:
+///
+/// * `asset`: proto::proxy::StatusAsset, its empty fields are unset
+///
+/// Returns:
+///
+/// A Result<StatusAsset>, an error when the asset can't be shown in the server list
+pub fn proxy_status_asset_from_tonic(asset: proto::proxy::StatusAsset) -> Result<StatusAsset> {
+    let asset = StatusAsset {
+        hostname: asset.hostname,
+        motd: Some(asset.motd).filter(|motd| !motd.is_empty()),
+        favicon: Some(asset.favicon).filter(|favicon| !favicon.is_empty()),
+    };
+    asset.validate().map_err(|e| anyhow!(e))?;
+
+    Ok(asset)
+}
+
+/// It takes a status asset of the proxy and returns a `proto::proxy::StatusAsset`
+///
+/// Arguments:
+///
+/// * `asset`: StatusAsset
+///
+/// Returns:
+///
+/// A proto::proxy::StatusAsset
+pub fn tonic_status_asset_from_proxy(asset: StatusAsset) -> proto::proxy::StatusAsset {
+    proto::proxy::StatusAsset {
+        hostname: asset.hostname,
+        motd: asset.motd.unwrap_or_default(),
+        favicon: asset.favicon.unwrap_or_default(),
+    }
+}
+
+/// It takes a `proto::proxy::BackendTotals` and returns the totals of a backend
+///
+/// Arguments:
+///
+/// * `totals`: proto::proxy::BackendTotals
+///
+/// Returns:
+///
+/// The hostname of the backend and its Totals
+pub fn proxy_backend_totals_from_tonic(totals: proto::proxy::BackendTotals) -> (String, Totals) {
+    (
+        totals.hostname,
+        Totals {
+            joins: totals.joins,
+            bytes_in: totals.bytes_in,
+            bytes_out: totals.bytes_out,
+        },
+    )
+}
+
+/// It takes the totals of a backend and returns a `proto::proxy::BackendTotals`
+///
+/// Arguments:
+///
+/// * `hostname`: The hostname of the backend
+/// * `totals`: Totals
+///
+/// Returns:
+///
+/// A proto::proxy::BackendTotals
+pub fn tonic_backend_totals_from_proxy(
+    hostname: String,
+    totals: Totals,
+) -> proto::proxy::BackendTotals {
+    proto::proxy::BackendTotals {
+        hostname,
+        joins: totals.joins,
+        bytes_in: totals.bytes_in,
+        bytes_out: totals.bytes_out,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_conversion_round_trip() {
+        let backend = Backend {
+            hostname: "game.example.com".to_string(),
+            redirect_ip: "192.168.1.10".to_string(),
+            redirect_port: 25565,
+            version: 3,
+            weight: 2,
+            max_connections: 100,
+            forwarding_mode: proto::proxy::ForwardingMode::Velocity as i32,
+            motd: "Hello".to_string(),
+            health_check: Some(proto::proxy::HealthCheck {
+                interval_secs: 5,
+                timeout_secs: 1,
+                unhealthy_threshold: 2,
+            }),
+            labels: [("env".to_string(), "prod".to_string())].into(),
+            read_only: false,
+            preserve_hostname: true,
+            tap: true,
+            min_protocol_version: 763,
+            max_protocol_version: 765,
+            username_routes: [("Notch".to_string(), "staging.example.com".to_string())].into(),
+            schedules: vec![proto::proxy::Schedule {
+                cron: "0 4 * * 1-5".to_string(),
+                duration_mins: 30,
+                fallback: "limbo.example.com".to_string(),
+                motd: String::new(),
+            }],
+            health: None,
+        };
+
+        let converted =
+            tonic_backend_from_proxy(proxy_backend_from_tonic(backend.clone()).unwrap());
+
+        assert_eq!(converted, backend);
+    }
+
+    #[test]
+    fn test_access_rule_conversion_round_trip() {
+        let rule = proto::proxy::AccessRule {
+            kind: proto::proxy::access_rule::Kind::Allow as i32,
+            subject: "10.0.0.0/8".to_string(),
+            reason: "the staff network".to_string(),
+            ttl_secs: 0,
+            created_at_ms: 1_700_000_000_000,
+            expires_at_ms: 0,
+        };
+
+        let converted =
+            tonic_access_rule_from_proxy(proxy_access_rule_from_tonic(rule.clone()).unwrap());
+
+        assert_eq!(converted, rule);
+        assert!(proxy_access_rule_from_tonic(proto::proxy::AccessRule {
+            subject: "not a subject".to_string(),
+            ..rule
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_status_asset_conversion_round_trip() {
+        let asset = proto::proxy::StatusAsset {
+            hostname: "lobby.example.com".to_string(),
+            motd: String::new(),
+            favicon: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+        };
+
+        let converted =
+            tonic_status_asset_from_proxy(proxy_status_asset_from_tonic(asset.clone()).unwrap());
+
+        assert_eq!(converted, asset);
+        assert!(proxy_status_asset_from_tonic(proto::proxy::StatusAsset {
+            favicon: String::new(),
+            ..asset
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_backend_conversion_unknown_forwarding_mode_err() {
+        let backend = Backend {
+            redirect_port: 25565,
+            forwarding_mode: 3,
+            ..Default::default()
+        };
+
+        assert!(proxy_backend_from_tonic(backend).is_err());
+    }
+
+    #[test]
+    fn test_backend_conversion_invalid_port_err() {
+        let backend = Backend {
+            redirect_port: 70000,
+            ..Default::default()
+        };
+
+        assert!(proxy_backend_from_tonic(backend).is_err());
+    }
+
+    #[test]
+    fn test_backend_conversion_inverted_protocol_versions_err() {
+        let backend = Backend {
+            redirect_port: 25565,
+            min_protocol_version: 766,
+            max_protocol_version: 765,
+            ..Default::default()
+        };
+
+        assert!(proxy_backend_from_tonic(backend).is_err());
+    }
+
+    #[test]
+    fn test_backend_conversion_invalid_schedule_err() {
+        let schedule = proto::proxy::Schedule {
+            cron: "0 4 * * *".to_string(),
+            duration_mins: 30,
+            ..Default::default()
+        };
+        let backend = |schedule| Backend {
+            redirect_port: 25565,
+            schedules: vec![schedule],
+            ..Default::default()
+        };
+
+        assert!(proxy_backend_from_tonic(backend(schedule.clone())).is_ok());
+        assert!(proxy_backend_from_tonic(backend(proto::proxy::Schedule {
+            cron: "0 25 * * *".to_string(),
+            ..schedule.clone()
+        }))
+        .is_err());
+        assert!(proxy_backend_from_tonic(backend(proto::proxy::Schedule {
+            duration_mins: 0,
+            ..schedule
+        }))
+        .is_err());
     }
 }
@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::FederationPeer;
+use event::proxy_backend_from_tonic;
+use futures::future::join_all;
+use listener::event::Event;
+use operator::routes::{self, Routes};
+use proto::{
+    client,
+    proxy::{backend_event::Type as BackendEventType, BackendEvent},
+};
+use shared::models::backend::Backend;
+use tokio::{sync::mpsc, time::sleep, time::timeout};
+
+/// The prefix of the sources of the mirrored backends, `federation/<peer>`
+pub const SOURCE_PREFIX: &str = "federation/";
+
+/// How long the changes of a peer are gathered before they are applied together
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// How long a lost peer waits before it is connected to again
+const RETRY: Duration = Duration::from_secs(5);
+
+/// It turns a backend of a peer into the route mirrored by this proxy
+///
+/// The backends the peer mirrors itself are skipped, so two proxies pulling from each other
+/// don't bounce their routes back and forth.
+///
+/// Arguments:
+///
+/// * `peer`: The peer the backend was pulled from.
+/// * `redirect`: The address the mirrored routes are redirected to, keeping their hostname.
+/// * `backend`: The backend of the peer.
+///
+/// Returns:
+///
+/// The mirrored backend, none when the filters of the peer exclude it
+pub fn mirror(
+    peer: &FederationPeer,
+    redirect: Option<&(String, u16)>,
+    backend: &Backend,
+) -> Option<Backend> {
+    if !backend.hostname().starts_with(&peer.prefix) {
+        return None;
+    }
+    if peer
+        .labels
+        .iter()
+        .any(|(key, value)| backend.labels().get(key) != Some(value))
+    {
+        return None;
+    }
+    if routes::source_of(backend).is_some_and(|source| source.starts_with(SOURCE_PREFIX)) {
+        return None;
+    }
+
+    let mut mirrored = backend.clone();
+    mirrored.version = 0;
+    // the peer taps its own connections, not the ones of this proxy
+    mirrored.tap = false;
+    if let Some((host, port)) = redirect {
+        mirrored.redirect_ip = host.clone();
+        mirrored.redirect_port = *port;
+        mirrored.preserve_hostname = true;
+    }
+    routes::own(&mut mirrored, &format!("{}{}", SOURCE_PREFIX, peer.name));
+    Some(mirrored)
+}
+
+/// The federation mirrors the routes of peer proxies into the routing table
+///
+/// Every peer is followed through its `WatchBackends` stream, and its backends are routed like
+/// the ones of the Kubernetes operator, so the quotas and the static routes apply to them too. A
+/// lost peer keeps its routes until it is reached again.
+///
+/// Properties:
+///
+/// * `peers`: The peer proxies the routes are pulled from.
+pub struct Federation {
+    peers: Vec<FederationPeer>,
+}
+
+impl Federation {
+    pub fn new(peers: Vec<FederationPeer>) -> Self {
+        Self { peers }
+    }
+
+    /// It follows every peer, and sends the changes of their routes to the event loop
+    ///
+    /// It returns right away when no peer is configured.
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: mpsc::Sender<Event>
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn start(&self, tx: mpsc::Sender<Event>) -> Result<()> {
+        let routes = Routes::new(tx);
+        join_all(self.peers.iter().map(|peer| follow(peer, &routes))).await;
+
+        Ok(())
+    }
+}
+
+/// It mirrors the routes of a peer, connecting to it again whenever it is lost
+async fn follow(peer: &FederationPeer, routes: &Routes) {
+    log::info!("Following the routes of {} at {}", peer.name, peer.endpoint);
+
+    loop {
+        if let Err(e) = watch(peer, routes).await {
+            log::warn!("lost the routes of {}: {:#}", peer.name, e);
+        }
+        sleep(RETRY).await;
+    }
+}
+
+/// It mirrors the routes of a peer until its stream fails
+///
+/// The stream starts with every backend of the peer, so the first sync also removes the routes
+/// the peer deleted while it couldn't be reached.
+async fn watch(peer: &FederationPeer, routes: &Routes) -> Result<()> {
+    let redirect = peer.redirect()?;
+    let token = peer
+        .token
+        .as_ref()
+        .map(|token| token.resolve())
+        .transpose()?;
+    let mut client = client::connect(peer.endpoint.clone(), token).await?;
+    let mut stream = client
+        .watch_backends(())
+        .await
+        .map_err(|e| anyhow!("failed to watch the backends: {}", e.message()))?
+        .into_inner();
+
+    let source = format!("{}{}", SOURCE_PREFIX, peer.name);
+    let mut backends: BTreeMap<String, Backend> = BTreeMap::new();
+    let mut dirty = true;
+
+    loop {
+        let message = match dirty {
+            true => match timeout(DEBOUNCE, stream.message()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    let wanted = backends
+                        .values()
+                        .filter_map(|backend| mirror(peer, redirect.as_ref(), backend))
+                        .collect();
+                    for outcome in routes.sync(&source, wanted).await? {
+                        if !outcome.ready {
+                            log::warn!("{} is not routed: {}", source, outcome.message);
+                        }
+                    }
+                    dirty = false;
+                    continue;
+                }
+            },
+            false => stream.message().await,
+        };
+
+        let event = message
+            .map_err(|e| anyhow!("the stream failed: {}", e.message()))?
+            .ok_or_else(|| anyhow!("the stream ended"))?;
+        apply(&mut backends, event)?;
+        dirty = true;
+    }
+}
+
+/// It applies a change of a peer to its mirrored backends
+fn apply(backends: &mut BTreeMap<String, Backend>, event: BackendEvent) -> Result<()> {
+    let event_type = BackendEventType::from_i32(event.r#type)
+        .ok_or_else(|| anyhow!("unknown event type {}", event.r#type))?;
+    let backend = event
+        .backend
+        .ok_or_else(|| anyhow!("the event has no backend"))?;
+
+    match event_type {
+        BackendEventType::Put => {
+            let backend = proxy_backend_from_tonic(backend)?;
+            backends.insert(backend.hostname().to_string(), backend);
+        }
+        BackendEventType::Delete => {
+            backends.remove(&backend.hostname);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer() -> FederationPeer {
+        FederationPeer {
+            name: "eu".to_string(),
+            endpoint: "http://eu.example.com:65535".to_string(),
+            token: None,
+            prefix: "eu-".to_string(),
+            labels: BTreeMap::from([("public".to_string(), "true".to_string())]),
+            redirect: Some("eu.example.com:25565".to_string()),
+        }
+    }
+
+    fn backend(hostname: &str) -> Backend {
+        let mut backend = Backend::new(hostname.to_string(), "10.0.0.12".to_string(), 25566);
+        backend.version = 7;
+        backend
+            .labels
+            .insert("public".to_string(), "true".to_string());
+        backend
+    }
+
+    #[test]
+    fn it_mirrors_the_filtered_backends_of_a_peer() {
+        let peer = peer();
+        let redirect = peer.redirect().unwrap();
+
+        let mut tapped = backend("eu-lobby.example.com");
+        tapped.tap = true;
+        let mirrored = mirror(&peer, redirect.as_ref(), &tapped).unwrap();
+        assert_eq!(mirrored.addr(), "eu.example.com:25565");
+        assert!(mirrored.preserve_hostname());
+        assert_eq!(mirrored.version(), 0);
+        assert!(!mirrored.tap());
+        assert_eq!(routes::source_of(&mirrored), Some("federation/eu"));
+
+        // without a redirect, the players go straight to the backend
+        let direct = mirror(&peer, None, &backend("eu-lobby.example.com")).unwrap();
+        assert_eq!(direct.addr(), "10.0.0.12:25566");
+        assert!(!direct.preserve_hostname());
+
+        assert_eq!(mirror(&peer, None, &backend("us-lobby.example.com")), None);
+        let mut private = backend("eu-admin.example.com");
+        private.labels.clear();
+        assert_eq!(mirror(&peer, None, &private), None);
+        // the routes the peer mirrors itself are not bounced back
+        assert_eq!(mirror(&peer, None, &mirrored), None);
+    }
+}
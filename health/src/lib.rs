@@ -0,0 +1,384 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use tokio::{
+    sync::{watch, Notify},
+    time::timeout,
+};
+
+mod ping;
+
+pub use ping::serve_pings;
+
+/// The state of the proxy reported to the liveness and readiness probes
+///
+/// The proxy is alive as soon as the health server answers, and ready once the storage is
+/// loaded and both the proxy and the gRPC listener are bound, until it starts draining.
+///
+/// Properties:
+///
+/// * `storage_loaded`: Whether the storage holds its initial backends.
+/// * `proxy_bound`: Whether the proxy accepts the Minecraft clients.
+/// * `listener_bound`: Whether the gRPC listener accepts requests.
+/// * `draining`: Whether the proxy stopped taking new players.
+/// * `sessions`: The number of open client connections.
+/// * `closed`: Notified when the last session is closed.
+/// * `changed`: Notified when a condition of the readiness changes.
+/// * `drain_timeout`: How long a drain waits for the sessions by default.
+#[derive(Debug)]
+pub struct Health {
+    storage_loaded: AtomicBool,
+    proxy_bound: AtomicBool,
+    listener_bound: AtomicBool,
+    draining: watch::Sender<bool>,
+    sessions: AtomicUsize,
+    closed: Notify,
+    changed: Notify,
+    drain_timeout: Duration,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(25))
+    }
+}
+
+impl Health {
+    pub fn new(drain_timeout: Duration) -> Self {
+        Self {
+            storage_loaded: AtomicBool::new(false),
+            proxy_bound: AtomicBool::new(false),
+            listener_bound: AtomicBool::new(false),
+            draining: watch::channel(false).0,
+            sessions: AtomicUsize::new(0),
+            closed: Notify::new(),
+            changed: Notify::new(),
+            drain_timeout,
+        }
+    }
+
+    pub fn set_storage_loaded(&self) {
+        self.storage_loaded.store(true, Ordering::Relaxed);
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_proxy_bound(&self) {
+        self.proxy_bound.store(true, Ordering::Relaxed);
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_listener_bound(&self) {
+        self.listener_bound.store(true, Ordering::Relaxed);
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_listener_unbound(&self) {
+        self.listener_bound.store(false, Ordering::Relaxed);
+        self.changed.notify_waiters();
+    }
+
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.send_replace(draining);
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// It waits until the proxy starts draining
+    pub async fn draining_started(&self) {
+        let mut draining = self.draining.subscribe();
+        while !*draining.borrow_and_update() {
+            if draining.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// It records a client connection, until the returned guard is dropped
+    ///
+    /// Returns:
+    ///
+    /// A Session
+    pub fn open_session(self: &Arc<Self>) -> Session {
+        self.sessions.fetch_add(1, Ordering::Relaxed);
+        Session {
+            health: self.clone(),
+        }
+    }
+
+    /// It returns the number of open client connections
+    pub fn sessions(&self) -> usize {
+        self.sessions.load(Ordering::Relaxed)
+    }
+
+    /// It marks the proxy as draining, then waits for the open sessions to finish
+    ///
+    /// The proxy stops accepting new connections and fails its readiness probe, so a pod about to
+    /// terminate lets its players leave before it is killed.
+    ///
+    /// Arguments:
+    ///
+    /// * `wait`: How long to wait for the sessions, the configured drain timeout when unset.
+    ///
+    /// Returns:
+    ///
+    /// The number of sessions still open when the wait ended
+    pub async fn drain(&self, wait: Option<Duration>) -> usize {
+        if !self.is_draining() {
+            log::info!("draining, {} sessions open", self.sessions());
+        }
+        self.set_draining(true);
+
+        let closed = async {
+            loop {
+                let notified = self.closed.notified();
+                if self.sessions() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        let _ = timeout(wait.unwrap_or(self.drain_timeout), closed).await;
+
+        self.sessions()
+    }
+
+    /// It waits until the proxy is ready to take traffic, forever once it drains
+    pub async fn ready(&self) {
+        loop {
+            let changed = self.changed.notified();
+            if self.unready_reasons().is_empty() {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// It returns why the proxy is not ready to take traffic
+    ///
+    /// Returns:
+    ///
+    /// The unmet conditions, empty when the proxy is ready
+    pub fn unready_reasons(&self) -> Vec<&'static str> {
+        let checks = [
+            (
+                self.storage_loaded.load(Ordering::Relaxed),
+                "storage not loaded",
+            ),
+            (self.proxy_bound.load(Ordering::Relaxed), "proxy not bound"),
+            (
+                self.listener_bound.load(Ordering::Relaxed),
+                "listener not bound",
+            ),
+            (!self.is_draining(), "draining"),
+        ];
+
+        checks
+            .into_iter()
+            .filter(|(ok, _)| !ok)
+            .map(|(_, reason)| reason)
+            .collect()
+    }
+}
+
+/// A client connection counted by the drain, released when dropped
+#[derive(Debug)]
+pub struct Session {
+    health: Arc<Health>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.health.sessions.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.health.closed.notify_waiters();
+        }
+    }
+}
+
+/// It serves the liveness probe on `/healthz`, the readiness probe on `/readyz`, and drains the
+/// proxy on `/drain`
+///
+/// Arguments:
+///
+/// * `addr`: The address to listen on
+/// * `health`: The state of the proxy
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn serve(addr: String, health: Arc<Health>) -> Result<()> {
+    serve_listener(bind(&addr)?, health).await
+}
+
+/// It binds the address of the health server, before it serves
+///
+/// Arguments:
+///
+/// * `addr`: The address to listen on, its port chosen by the system when 0
+///
+/// Returns:
+///
+/// A Result<TcpListener>
+pub fn bind(addr: &str) -> Result<TcpListener> {
+    let addr = SocketAddr::from_str(addr).map_err(|e| anyhow!("failed to parse address: {}", e))?;
+
+    TcpListener::bind(addr).map_err(|e| anyhow!("failed to bind health server to {}: {}", addr, e))
+}
+
+/// It serves the probes and the drain on a bound listener, see `serve`
+///
+/// Arguments:
+///
+/// * `listener`: The listener returned by `bind`
+/// * `health`: The state of the proxy
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn serve_listener(listener: TcpListener, health: Arc<Health>) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let health = health.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let health = health.clone();
+                async move { Ok::<_, Infallible>(handle(request, &health).await) }
+            }))
+        }
+    });
+
+    Server::from_tcp(listener)?
+        .serve(make_service)
+        .await
+        .map_err(|e| anyhow!("health server exited with error {}", e))
+}
+
+/// It answers an HTTP request made to the health server
+///
+/// Arguments:
+///
+/// * `request`: The HTTP request
+/// * `health`: The state of the proxy
+///
+/// Returns:
+///
+/// A Response<Body>
+async fn handle(request: Request<Body>, health: &Health) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+
+    // the preStop hooks of Kubernetes can only send GET requests
+    if request.uri().path() == "/drain"
+        && (request.method() == Method::GET || request.method() == Method::POST)
+    {
+        let wait = request
+            .uri()
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("timeout_secs="))
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs);
+
+        *response.body_mut() = match health.drain(wait).await {
+            0 => Body::from("drained"),
+            remaining => Body::from(format!("{} sessions still open", remaining)),
+        };
+        return response;
+    }
+
+    if request.method() != Method::GET {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+
+    match request.uri().path() {
+        "/healthz" => *response.body_mut() = Body::from("ok"),
+        "/readyz" => {
+            let reasons = health.unready_reasons();
+            if reasons.is_empty() {
+                *response.body_mut() = Body::from("ok");
+            } else {
+                *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+                *response.body_mut() = Body::from(format!("not ready: {}", reasons.join(", ")));
+            }
+        }
+        _ => *response.status_mut() = StatusCode::NOT_FOUND,
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(path: &str, health: &Health) -> Response<Body> {
+        handle(Request::get(path).body(Body::empty()).unwrap(), health).await
+    }
+
+    #[tokio::test]
+    async fn it_is_ready_once_everything_is_up_until_draining() {
+        let health = Health::default();
+        assert_eq!(get("/healthz", &health).await.status(), StatusCode::OK);
+
+        let response = get("/readyz", &health).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            body,
+            "not ready: storage not loaded, proxy not bound, listener not bound"
+        );
+
+        health.set_storage_loaded();
+        health.set_proxy_bound();
+        health.set_listener_bound();
+        assert_eq!(get("/readyz", &health).await.status(), StatusCode::OK);
+
+        // the listener crashed, until it is restarted
+        health.set_listener_unbound();
+        assert_eq!(health.unready_reasons(), vec!["listener not bound"]);
+        health.set_listener_bound();
+
+        health.set_draining(true);
+        assert_eq!(health.unready_reasons(), vec!["draining"]);
+        assert_eq!(
+            get("/readyz", &health).await.status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            get("/metrics", &health).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn it_drains_until_the_sessions_are_closed() {
+        let health = Arc::new(Health::default());
+        let first = health.open_session();
+        let second = health.open_session();
+
+        assert_eq!(health.drain(Some(Duration::from_millis(10))).await, 2);
+        assert!(health.is_draining());
+
+        drop(first);
+        let (remaining, ()) = tokio::join!(health.drain(Some(Duration::from_secs(5))), async {
+            drop(second)
+        });
+        assert_eq!(remaining, 0);
+    }
+}
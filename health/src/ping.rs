@@ -0,0 +1,157 @@
+use std::{net::TcpListener, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use protocol::packets::{
+    clientbound::{pong::Pong, status::Status},
+    serverbound::{handshake::Handshake, ping::Ping},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::TcpStream,
+    sync::Semaphore,
+    time::{sleep, timeout},
+};
+
+use crate::Health;
+
+/// The MOTD of the status answered on the ping port
+const MOTD: &str = "kubecraft-proxy";
+
+/// The time a check speaking the protocol has to send its handshake and its ping
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most connections answered at once, the ones over it are closed right away
+const MAX_PINGS: usize = 64;
+
+/// The time waited after a failed accept, e.g. while the process is out of file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// It answers a status ping to every connection of the ping port, while the proxy is ready
+///
+/// The network load balancers which only open a TCP connection, or send a canned handshake and
+/// expect a status back, check the proxy without reaching a backend. The connections are not
+/// counted as sessions nor in the metrics of the players, and the ones opened while the proxy
+/// isn't ready, e.g. once it drains, are closed without an answer so the checks fail.
+///
+/// The port answers `MAX_PINGS` connections at once, and keeps accepting after a failed accept.
+///
+/// Arguments:
+///
+/// * `listener`: The listener of the ping port, bound beforehand
+/// * `health`: The state of the proxy
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn serve_pings(listener: TcpListener, health: Arc<Health>) -> Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+
+    let pings = Arc::new(Semaphore::new(MAX_PINGS));
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                log::warn!("failed to accept a ping: {}", e);
+                sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        // the connections over the limit are dropped, closing them
+        let Ok(ping) = pings.clone().try_acquire_owned() else {
+            continue;
+        };
+        let health = health.clone();
+        tokio::spawn(async move {
+            let _ping = ping;
+            answer(stream, &health).await
+        });
+    }
+}
+
+/// It writes the status right away, then answers the ping of the checks speaking the protocol
+///
+/// Arguments:
+///
+/// * `stream`: The connection of the load balancer
+/// * `health`: The state of the proxy
+async fn answer(mut stream: TcpStream, health: &Health) {
+    if !health.unready_reasons().is_empty() {
+        return;
+    }
+
+    // the status doesn't wait for the request, a plain TCP check reads it without sending anything
+    if Status::from_error(MOTD.to_string())
+        .write_as_motd(&mut stream)
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let pinged = timeout(PING_TIMEOUT, async {
+        Handshake::read(&mut stream).await?;
+        let ping = Ping::read(&mut stream).await?;
+        Pong::new(&ping).write(&mut stream).await
+    })
+    .await;
+    if let Ok(Err(e)) = pinged {
+        log::trace!("no ping on the ping port: {}", e);
+    }
+
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::{
+        packets::serverbound::handshake::NextState, read_packet, read_string, read_var_int,
+        write_packet,
+    };
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_answers_the_pings_until_the_proxy_drains() {
+        let health = Arc::new(Health::default());
+        health.set_storage_loaded();
+        health.set_proxy_bound();
+        health.set_listener_bound();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_pings(listener, health.clone()));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut data = Vec::new();
+        Handshake::new(765, "lb".to_string(), addr.port(), NextState::Status).encode(&mut data);
+        stream.write_all(&data).await.unwrap();
+        write_packet(&mut stream, &[0]).await.unwrap();
+        Ping::new(42).write(&mut stream).await.unwrap();
+
+        let mut status = read_packet(&mut stream, 1024).await.unwrap();
+        assert_eq!(read_var_int(&mut status).await.unwrap(), 0);
+        let json = read_string(&mut status, 1024).await.unwrap();
+        assert!(json.contains(MOTD));
+        assert_eq!(Pong::read(&mut stream).await.unwrap().payload(), 42);
+
+        // the checks over the limit are closed, until one of the answered ones is done
+        let mut held = Vec::new();
+        for _ in 0..MAX_PINGS {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            read_packet(&mut stream, 1024).await.unwrap();
+            held.push(stream);
+        }
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut answer = Vec::new();
+        stream.read_to_end(&mut answer).await.unwrap();
+        assert!(answer.is_empty());
+        drop(held);
+
+        health.set_draining(true);
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut answer = Vec::new();
+        stream.read_to_end(&mut answer).await.unwrap();
+        assert!(answer.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::models::backend::Backend;
+
+/// The parts of a BungeeCord `config.yml` describing the routes
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[serde(default)]
+    servers: BTreeMap<String, Server>,
+    #[serde(default)]
+    listeners: Vec<Listener>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Server {
+    address: String,
+    motd: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Listener {
+    #[serde(default)]
+    forced_hosts: BTreeMap<String, String>,
+}
+
+/// It converts the forced hosts of a BungeeCord `config.yml` into backends
+///
+/// The MOTD of the servers is kept as the MOTD override of the backends.
+///
+/// Arguments:
+///
+/// * `content`: The content of the `config.yml`
+///
+/// Returns:
+///
+/// A Result<Vec<Backend>>
+pub fn import(content: &str) -> Result<Vec<Backend>> {
+    let config: Config = serde_yaml::from_str(content)
+        .map_err(|e| anyhow!("failed to parse BungeeCord configuration: {}", e))?;
+
+    let mut backends = Vec::new();
+    for (hostname, name) in config.listeners.iter().flat_map(|l| &l.forced_hosts) {
+        let server = config
+            .servers
+            .get(name)
+            .ok_or_else(|| anyhow!("forced host {} targets unknown server {}", hostname, name))?;
+
+        let mut backend = crate::backend(hostname, &server.address)?;
+        backend.motd = server.motd.clone();
+        backends.push(backend);
+    }
+
+    Ok(backends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import() {
+        let content = r#"
+servers:
+  lobby:
+    motd: '&1Just another BungeeCord - Forced Host'
+    address: localhost:25566
+    restricted: false
+listeners:
+- query_port: 25577
+  motd: '&1Another Bungee server'
+  priorities:
+  - lobby
+  forced_hosts:
+    pvp.md-5.net: lobby
+  host: 0.0.0.0:25577
+"#;
+
+        let backends = import(content).unwrap();
+
+        assert_eq!(backends.len(), 1);
+        assert_eq!(backends[0].hostname(), "pvp.md-5.net");
+        assert_eq!(backends[0].addr(), "localhost:25566");
+        assert_eq!(
+            backends[0].motd(),
+            Some("&1Just another BungeeCord - Forced Host")
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use shared::models::backend::Backend;
+
+pub mod bungeecord;
+pub mod velocity;
+
+/// The default port of a Minecraft server, used when an address has none
+const DEFAULT_PORT: u16 = 25565;
+
+/// The configuration formats routes can be imported from
+///
+/// Properties:
+///
+/// * `BungeeCord`: A BungeeCord `config.yml`.
+/// * `Velocity`: A Velocity `velocity.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    BungeeCord,
+    Velocity,
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bungeecord" | "bungee" => Ok(Self::BungeeCord),
+            "velocity" => Ok(Self::Velocity),
+            _ => Err(anyhow!("unknown import format: {}", s)),
+        }
+    }
+}
+
+/// It converts the forced hosts of a proxy configuration into backends
+///
+/// Arguments:
+///
+/// * `format`: The format of the configuration
+/// * `content`: The content of the configuration file
+///
+/// Returns:
+///
+/// A Result<Vec<Backend>>, one backend by forced host
+pub fn import(format: ImportFormat, content: &str) -> Result<Vec<Backend>> {
+    match format {
+        ImportFormat::BungeeCord => bungeecord::import(content),
+        ImportFormat::Velocity => velocity::import(content),
+    }
+}
+
+/// It converts a server address into a backend for a hostname
+///
+/// Arguments:
+///
+/// * `hostname`: The forced host routed to the server
+/// * `address`: The address of the server, `host[:port]`
+///
+/// Returns:
+///
+/// A Result<Backend>
+fn backend(hostname: &str, address: &str) -> Result<Backend> {
+    let (ip, port) = match address.rsplit_once(':') {
+        Some((ip, port)) => (
+            ip,
+            port.parse()
+                .map_err(|e| anyhow!("invalid port in address {}: {}", address, e))?,
+        ),
+        None => (address, DEFAULT_PORT),
+    };
+
+    Ok(Backend::new(
+        hostname.to_ascii_lowercase(),
+        ip.to_string(),
+        port,
+    ))
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::models::backend::Backend;
+
+/// The parts of a Velocity `velocity.toml` describing the routes
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[serde(default)]
+    servers: BTreeMap<String, toml::Value>,
+    #[serde(default, rename = "forced-hosts")]
+    forced_hosts: BTreeMap<String, Vec<String>>,
+}
+
+/// It converts the forced hosts of a Velocity `velocity.toml` into backends
+///
+/// Velocity tries the servers of a forced host in order, only the first one is imported.
+///
+/// Arguments:
+///
+/// * `content`: The content of the `velocity.toml`
+///
+/// Returns:
+///
+/// A Result<Vec<Backend>>
+pub fn import(content: &str) -> Result<Vec<Backend>> {
+    let config: Config = toml::from_str(content)
+        .map_err(|e| anyhow!("failed to parse Velocity configuration: {}", e))?;
+
+    let mut backends = Vec::new();
+    for (hostname, names) in &config.forced_hosts {
+        let name = names
+            .first()
+            .ok_or_else(|| anyhow!("forced host {} has no server", hostname))?;
+        let address = config
+            .servers
+            .get(name)
+            .and_then(|address| address.as_str())
+            .ok_or_else(|| anyhow!("forced host {} targets unknown server {}", hostname, name))?;
+
+        backends.push(crate::backend(hostname, address)?);
+    }
+
+    Ok(backends)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import() {
+        let content = r#"
+bind = "0.0.0.0:25577"
+
+[servers]
+lobby = "127.0.0.1:30066"
+factions = "127.0.0.1:30067"
+try = ["lobby"]
+
+[forced-hosts]
+"lobby.example.com" = ["lobby"]
+"factions.example.com" = ["factions", "lobby"]
+"#;
+
+        let backends = import(content).unwrap();
+
+        assert_eq!(backends.len(), 2);
+        assert_eq!(backends[0].hostname(), "factions.example.com");
+        assert_eq!(backends[0].addr(), "127.0.0.1:30067");
+        assert_eq!(backends[1].hostname(), "lobby.example.com");
+        assert_eq!(backends[1].addr(), "127.0.0.1:30066");
+    }
+}
@@ -0,0 +1,77 @@
+// the interceptor returns a `tonic::Status` as error
+#![allow(clippy::result_large_err)]
+
+use tonic::{service::Interceptor, Request, Status};
+
+/// It checks the API token of every gRPC request
+///
+/// Properties:
+///
+/// * `token`: The token requests must carry as `authorization: Bearer <token>`, none to accept
+///   every request.
+#[derive(Clone, Default)]
+pub struct TokenInterceptor {
+    token: Option<String>,
+}
+
+impl TokenInterceptor {
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+}
+
+impl Interceptor for TokenInterceptor {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let token = match &self.token {
+            Some(token) => token,
+            None => return Ok(request),
+        };
+
+        let bearer = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match bearer {
+            Some(bearer) if constant_time_eq(bearer.as_bytes(), token.as_bytes()) => Ok(request),
+            _ => Err(Status::unauthenticated("invalid or missing API token")),
+        }
+    }
+}
+
+/// It compares two byte strings without returning early, so the time taken doesn't tell how
+/// much of the token was guessed
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(authorization: Option<&str>) -> Request<()> {
+        let mut request = Request::new(());
+        if let Some(authorization) = authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.parse().unwrap());
+        }
+        request
+    }
+
+    #[test]
+    fn test_token_interceptor_checks_bearer() {
+        let mut interceptor = TokenInterceptor::new(Some("s3cr3t".to_string()));
+
+        assert!(interceptor.call(request(Some("Bearer s3cr3t"))).is_ok());
+        assert!(interceptor.call(request(Some("Bearer wrong"))).is_err());
+        assert!(interceptor.call(request(Some("s3cr3t"))).is_err());
+        assert!(interceptor.call(request(None)).is_err());
+    }
+
+    #[test]
+    fn test_token_interceptor_without_token_accepts_all() {
+        assert!(TokenInterceptor::default().call(request(None)).is_ok());
+    }
+}
@@ -1,10 +1,130 @@
-use shared::models::backend::Backend;
-use tokio::sync::oneshot;
+use std::sync::Arc;
+
+use config::ProxyConfig;
+use shared::{
+    models::{
+        access_rule::{AccessKind, AccessRule, AccessSubject},
+        backend::Backend,
+        selector::LabelSelector,
+        status_asset::StatusAsset,
+    },
+    probe::Probe,
+    validation::Validation,
+};
+use storage::{BackendChange, Snapshot};
+use tokio::sync::{broadcast, oneshot};
 
 /// Event is an enum that represents the different events that can be sent to the proxy
 #[derive(Debug)]
 pub enum Event {
-    ListBackends(oneshot::Sender<anyhow::Result<Vec<Backend>>>),
-    PutBackend(Backend, oneshot::Sender<anyhow::Result<()>>),
+    ListBackends(LabelSelector, oneshot::Sender<anyhow::Result<Vec<Backend>>>),
+    PutBackend(Backend, oneshot::Sender<anyhow::Result<Backend>>),
     DeleteBackend(Backend, oneshot::Sender<anyhow::Result<()>>),
+    RestoreBackend(String, oneshot::Sender<anyhow::Result<Backend>>),
+    ApplyBatch(
+        Vec<BackendChange>,
+        oneshot::Sender<anyhow::Result<Vec<BackendChange>>>,
+    ),
+    SnapshotState(oneshot::Sender<anyhow::Result<Snapshot>>),
+    RestoreState(Snapshot, oneshot::Sender<anyhow::Result<()>>),
+    WatchBackends(
+        oneshot::Sender<anyhow::Result<(Vec<Backend>, broadcast::Receiver<BackendChange>)>>,
+    ),
+    ReloadConfig(oneshot::Sender<anyhow::Result<()>>),
+    GetConfig(oneshot::Sender<anyhow::Result<Arc<ProxyConfig>>>),
+    ProbeBackend(String, oneshot::Sender<anyhow::Result<Probe>>),
+    ValidateBackend(Backend, oneshot::Sender<anyhow::Result<Validation>>),
+    ListAccessRules(oneshot::Sender<anyhow::Result<Vec<AccessRule>>>),
+    PutAccessRule(AccessRule, oneshot::Sender<anyhow::Result<AccessRule>>),
+    DeleteAccessRule(
+        AccessKind,
+        AccessSubject,
+        oneshot::Sender<anyhow::Result<AccessRule>>,
+    ),
+    ListStatusAssets(oneshot::Sender<anyhow::Result<Vec<StatusAsset>>>),
+    PutStatusAsset(StatusAsset, oneshot::Sender<anyhow::Result<StatusAsset>>),
+    DeleteStatusAsset(String, oneshot::Sender<anyhow::Result<StatusAsset>>),
+}
+
+impl Event {
+    /// It returns the name of the event, as shown in the logs
+    ///
+    /// Returns:
+    ///
+    /// A &'static str
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ListBackends(..) => "list backends",
+            Self::PutBackend(..) => "put backend",
+            Self::DeleteBackend(..) => "delete backend",
+            Self::RestoreBackend(..) => "restore backend",
+            Self::ApplyBatch(..) => "apply batch",
+            Self::SnapshotState(_) => "snapshot state",
+            Self::RestoreState(..) => "restore state",
+            Self::WatchBackends(_) => "watch backends",
+            Self::ReloadConfig(_) => "reload config",
+            Self::GetConfig(_) => "get config",
+            Self::ProbeBackend(..) => "probe backend",
+            Self::ValidateBackend(..) => "validate backend",
+            Self::ListAccessRules(_) => "list access rules",
+            Self::PutAccessRule(..) => "put access rule",
+            Self::DeleteAccessRule(..) => "delete access rule",
+            Self::ListStatusAssets(_) => "list status assets",
+            Self::PutStatusAsset(..) => "put status asset",
+            Self::DeleteStatusAsset(..) => "delete status asset",
+        }
+    }
+
+    /// It returns the backends, or the access rules, changed by the event
+    ///
+    /// The events changing the same backend or the same access rule are handled in the order they
+    /// were sent, the other ones concurrently.
+    ///
+    /// Returns:
+    ///
+    /// The scope of the event
+    pub fn scope(&self) -> Scope {
+        match self {
+            Self::PutBackend(backend, _) | Self::DeleteBackend(backend, _) => {
+                Scope::Hostnames(vec![backend.hostname().to_string()])
+            }
+            Self::RestoreBackend(hostname, _) | Self::DeleteStatusAsset(hostname, _) => {
+                Scope::Hostnames(vec![hostname.clone()])
+            }
+            Self::PutStatusAsset(asset, _) => Scope::Hostnames(vec![asset.hostname.clone()]),
+            Self::PutAccessRule(rule, _) => Scope::AccessRules(vec![rule.key()]),
+            Self::DeleteAccessRule(kind, subject, _) => {
+                Scope::AccessRules(vec![AccessRule::key_of(*kind, subject)])
+            }
+            Self::ApplyBatch(batch, _) => Scope::Hostnames(
+                batch
+                    .iter()
+                    .map(|change| change.backend().hostname().to_string())
+                    .collect(),
+            ),
+            Self::RestoreState(..) | Self::ReloadConfig(_) => Scope::All,
+            Self::ListBackends(..)
+            | Self::SnapshotState(_)
+            | Self::WatchBackends(_)
+            | Self::GetConfig(_)
+            | Self::ProbeBackend(..)
+            | Self::ValidateBackend(..)
+            | Self::ListAccessRules(_)
+            | Self::ListStatusAssets(_) => Scope::None,
+        }
+    }
+}
+
+/// The backends, or the access rules, changed by an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    /// The event changes no backend nor access rule.
+    None,
+    /// The event changes the backends of these hostnames.
+    Hostnames(Vec<String>),
+    /// The event changes the access rules of these keys, see `AccessRule::key`.
+    AccessRules(Vec<String>),
+    /// The event may change every backend, it waits for the events before it and the next ones
+    /// wait for it.
+    All,
 }
@@ -1,80 +1,413 @@
+// every gRPC handler returns a `tonic::Status` as error
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
 use async_trait::async_trait;
-use log::{debug, error, trace};
-use proto::proxy::{proxy_service_server::ProxyService, Backend};
-use tokio::sync::{mpsc, oneshot};
+use config::{ChannelsConfig, ProxyConfig};
+use event::{
+    access_kind_from_tonic, proxy_access_rule_from_tonic, proxy_backend_from_tonic,
+    proxy_backend_totals_from_tonic, proxy_status_asset_from_tonic, tonic_access_rule_from_proxy,
+    tonic_backend_from_proxy, tonic_backend_totals_from_proxy, tonic_health,
+    tonic_status_asset_from_proxy,
+};
+use health::Health;
+use importer::ImportFormat;
+use log::{debug, error, info, trace, warn};
+use metrics::channel::ChannelMetrics;
+use prost::Message;
+use proto::proxy::{
+    backend_event::Type as BackendEventType, import_routes_request::Format,
+    proxy_service_server::ProxyService, AccessRule, AccessRules, Backend, BackendBatch,
+    BackendEvent, BackendStats, Ban, Bans, CaptureRequest, Captures, ClearBansRequest,
+    ClearBansResult, ConfigDump, DdosModeState, DrainRequest, DrainResult, ImportRoutesRequest,
+    ListBackendRequest, LogRecord, LogsRequest, ProbeRequest, ProbeResult, RecentEvent,
+    RecentEvents, RecentEventsRequest, Sessions, SessionsRequest, StateBlob, StateSnapshot,
+    StatsRequest, StatsSnapshot, StatusAsset, StatusAssets, TransferRequest, TransferResult,
+    ValidationResult, VersionSessions,
+};
+use shared::{
+    bans,
+    capture::{self, CaptureSettings, DEFAULT_CAPTURE_BYTES, MAX_CAPTURE_BYTES},
+    ddos,
+    logs::{self, LogFilter, LogLevel},
+    models::{access_rule, selector::LabelSelector},
+    probe::Probe,
+    recent, sessions, stats,
+    validation::Validation,
+};
+use storage::{BackendChange, Snapshot, StorageError};
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+    time::timeout,
+};
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 use crate::event::Event;
 
+/// The time between two snapshots of `WatchStats`, when the request doesn't set it
+const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The longest time between two snapshots of `WatchStats`, in seconds
+const MAX_STATS_INTERVAL_SECS: u32 = 3600;
+
 /// It is the gRPC server that handles the requests concerning the proxy configuration
 ///
 /// Properties:
 ///
 /// * `sender`: This is a channel that will be used to send events to the proxy.
+/// * `channels`: The capacities of the streams and how long to wait for the proxy.
+/// * `metrics`: The metrics recording the saturated channels and the timeouts.
+/// * `health`: The state of the proxy, drained by `StartDrain`.
+/// * `recent`: The latest notable events of the connections, returned by `GetRecentEvents`.
+/// * `bans`: The banned addresses, listed by `ListBans` and lifted by `ClearBans`.
+/// * `captures`: The hostnames whose connections are captured, changed by `StartCapture` and
+///   `StopCapture`.
+/// * `sessions`: The players relayed to every version of the backends, returned by `GetSessions`.
+/// * `transfers`: The sender of the transfers of `TransferPlayers`.
+/// * `logs`: The sender of the records streamed by `StreamLogs`, none when the process doesn't
+///   stream them.
+/// * `stats`: The counters of every backend, streamed by `WatchStats`.
+/// * `ddos`: The DDoS mode of the proxy, flipped by `SetDdosMode`.
 pub struct ProxyListener {
     pub sender: mpsc::Sender<Event>,
+    pub channels: ChannelsConfig,
+    pub metrics: ChannelMetrics,
+    pub health: Arc<Health>,
+    pub recent: Arc<recent::RecentEvents>,
+    pub bans: Arc<bans::Bans>,
+    pub captures: Arc<capture::Captures>,
+    pub sessions: Arc<sessions::Sessions>,
+    pub transfers: broadcast::Sender<sessions::Transfer>,
+    pub logs: Option<broadcast::Sender<logs::LogRecord>>,
+    pub stats: Arc<stats::Stats>,
+    pub ddos: Arc<ddos::DdosMode>,
+}
+
+/// It converts an error returned by the proxy into the matching gRPC status
+///
+/// Arguments:
+///
+/// * `error`: The error returned by the proxy
+///
+/// Returns:
+///
+/// A `Status`
+fn status_from_error(error: &anyhow::Error) -> Status {
+    // the error of a batch is the one of its rejected change
+    match error
+        .downcast_ref::<StorageError>()
+        .map(StorageError::root_cause)
+    {
+        Some(StorageError::NotFound(_))
+        | Some(StorageError::AccessRuleNotFound(_))
+        | Some(StorageError::StatusAssetNotFound(_)) => Status::not_found(format!("{:#}", error)),
+        Some(StorageError::AlreadyExists(_)) => Status::already_exists(format!("{:#}", error)),
+        Some(StorageError::QuotaExceeded(_)) => Status::resource_exhausted(format!("{:#}", error)),
+        Some(StorageError::VersionConflict { .. }) => Status::aborted(format!("{:#}", error)),
+        Some(StorageError::ReadOnly(_)) => Status::failed_precondition(format!("{:#}", error)),
+        Some(StorageError::BatchRejected { .. }) | None => {
+            Status::internal("Internal server error")
+        }
+    }
+}
+
+/// It converts a gRPC backend into a backend of the proxy
+///
+/// Arguments:
+///
+/// * `backend`: The backend to convert
+///
+/// Returns:
+///
+/// A `Result<shared::models::backend::Backend, Status>`, invalid if a field holds an invalid value
+fn shared_backend(backend: Backend) -> Result<shared::models::backend::Backend, Status> {
+    proxy_backend_from_tonic(backend).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// It parses the label selector of a request
+///
+/// Arguments:
+///
+/// * `selector`: The selector, e.g. `env=prod,region=eu`, empty for every backend
+///
+/// Returns:
+///
+/// A `Result<LabelSelector, Status>`, invalid for an invalid selector
+fn parse_selector(selector: &str) -> Result<LabelSelector, Status> {
+    selector.parse().map_err(Status::invalid_argument)
+}
+
+/// It converts a gRPC access rule into an access rule of the proxy
+///
+/// Arguments:
+///
+/// * `rule`: The access rule to convert
+///
+/// Returns:
+///
+/// A `Result<shared::models::access_rule::AccessRule, Status>`, invalid for an unknown kind or
+/// an invalid subject
+fn shared_access_rule(rule: AccessRule) -> Result<shared::models::access_rule::AccessRule, Status> {
+    proxy_access_rule_from_tonic(rule).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// It converts a gRPC status asset into a status asset of the proxy
+///
+/// Arguments:
+///
+/// * `asset`: The status asset to convert
+///
+/// Returns:
+///
+/// A `Result<shared::models::status_asset::StatusAsset, Status>`, invalid when the asset can't be
+/// shown in the server list
+fn shared_status_asset(
+    asset: StatusAsset,
+) -> Result<shared::models::status_asset::StatusAsset, Status> {
+    proxy_status_asset_from_tonic(asset).map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// It converts a gRPC backend event into a change of the storage
+///
+/// Arguments:
+///
+/// * `event`: The event to convert
+///
+/// Returns:
+///
+/// A `Result<BackendChange, Status>`, invalid if the event has no backend or an unknown type
+fn backend_change(event: BackendEvent) -> Result<BackendChange, Status> {
+    let event_type = BackendEventType::from_i32(event.r#type)
+        .ok_or_else(|| Status::invalid_argument("unknown backend event type"))?;
+    let backend = event
+        .backend
+        .ok_or_else(|| Status::invalid_argument("missing backend in backend event"))
+        .and_then(shared_backend)?;
+
+    Ok(match event_type {
+        BackendEventType::Put => BackendChange::Put(backend),
+        BackendEventType::Delete => BackendChange::Delete(backend),
+    })
+}
+
+/// It converts a change of the storage into its gRPC representation
+///
+/// Arguments:
+///
+/// * `change`: The change to convert
+///
+/// Returns:
+///
+/// A `BackendEvent`
+fn proto_backend_event(change: BackendChange) -> BackendEvent {
+    let (event_type, backend) = match change {
+        BackendChange::Put(backend) => (BackendEventType::Put, backend),
+        BackendChange::Delete(backend) => (BackendEventType::Delete, backend),
+    };
+
+    BackendEvent {
+        r#type: event_type as i32,
+        backend: Some(tonic_backend_from_proxy(backend)),
+    }
+}
+
+/// It sends an item to a response stream, recording an overflow when the stream is full
+///
+/// Arguments:
+///
+/// * `tx`: The sender of the stream
+/// * `item`: The item to send
+/// * `metrics`: The metrics recording the overflow
+///
+/// Returns:
+///
+/// true if the item was sent, false if the client is gone
+async fn stream_send<T>(tx: &mpsc::Sender<T>, item: T, metrics: &ChannelMetrics) -> bool {
+    let item = match tx.try_send(item) {
+        Ok(()) => return true,
+        Err(TrySendError::Closed(_)) => return false,
+        Err(TrySendError::Full(item)) => item,
+    };
+
+    metrics.overflow("streams");
+    tx.send(item).await.is_ok()
+}
+
+impl ProxyListener {
+    /// It sends an event to the proxy
+    ///
+    /// When the event channel is full, an overflow is recorded before waiting for room in it.
+    ///
+    /// Arguments:
+    ///
+    /// * `name`: The name of the event, for the logs
+    /// * `event`: The event to send
+    ///
+    /// Returns:
+    ///
+    /// A `Result<(), Status>`
+    async fn send_event(&self, name: &str, event: Event) -> Result<(), Status> {
+        let event = match self.sender.try_send(event) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(event)) => {
+                warn!("event channel is full, waiting to send {} event", name);
+                self.metrics.overflow("events");
+                event
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!("failed to send {} event: channel closed", name);
+                return Err(Status::internal("Internal server error"));
+            }
+        };
+
+        self.sender.send(event).await.map_err(|e| {
+            error!("failed to send {} event: {}", name, e);
+            Status::internal("Internal server error")
+        })
+    }
+
+    /// It waits for the response of the proxy to an event, up to the response timeout
+    ///
+    /// Arguments:
+    ///
+    /// * `name`: The name of the event, for the logs and the metrics
+    /// * `rx`: The receiver of the response
+    ///
+    /// Returns:
+    ///
+    /// A `Result<T, Status>` with the response
+    async fn wait_response<T>(&self, name: &str, rx: oneshot::Receiver<T>) -> Result<T, Status> {
+        let response_timeout = Duration::from_secs(self.channels.response_timeout_secs);
+
+        match timeout(response_timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => {
+                error!("failed to receive {} response: {}", name, e);
+                Err(Status::internal("Internal server error"))
+            }
+            Err(_) => {
+                error!("timed out waiting for the {} response", name);
+                self.metrics.timeout(&name.replace(' ', "_"));
+                Err(Status::deadline_exceeded(format!(
+                    "the proxy did not answer the {} request in time",
+                    name
+                )))
+            }
+        }
+    }
+
+    /// It sends a message to the proxy to list the backends matching a label selector
+    ///
+    /// Arguments:
+    ///
+    /// * `selector`: The label selector, an empty one for every backend
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Vec<Backend>, Status>` with the selected backends
+    async fn list_backends(
+        &self,
+        selector: LabelSelector,
+    ) -> Result<Vec<shared::models::backend::Backend>, Status> {
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Vec<shared::models::backend::Backend>>>();
+
+        debug!("sending backend list request: {:?}", selector.to_string());
+        self.send_event("list backends", Event::ListBackends(selector, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("list backends", rx).await?.map_err(|e| {
+            error!("failed to list backends: {}", e);
+            Status::internal("Internal server error")
+        })
+    }
+
+    /// It sends a message to the proxy to apply a batch of changes and waits for the result
+    ///
+    /// Arguments:
+    ///
+    /// * `batch`: The changes to apply atomically
+    ///
+    /// Returns:
+    ///
+    /// A `Result<BackendBatch, Status>` with the applied changes and their new versions
+    async fn send_batch(&self, batch: Vec<BackendChange>) -> Result<BackendBatch, Status> {
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Vec<BackendChange>>>();
+
+        debug!("sending batch of {} changes", batch.len());
+        self.send_event("apply batch", Event::ApplyBatch(batch, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("apply batch", rx).await?.map_or_else(
+            |e| {
+                error!("failed to apply batch: {:#}", e);
+                Err(status_from_error(&e))
+            },
+            |changes| {
+                Ok(BackendBatch {
+                    events: changes.into_iter().map(proto_backend_event).collect(),
+                })
+            },
+        )
+    }
 }
 
 #[async_trait]
 impl ProxyService for ProxyListener {
     type ListBackendStream = ReceiverStream<Result<Backend, Status>>;
+    type WatchBackendsStream = ReceiverStream<Result<BackendEvent, Status>>;
+    type StreamLogsStream = ReceiverStream<Result<LogRecord, Status>>;
+    type WatchStatsStream = ReceiverStream<Result<StatsSnapshot, Status>>;
 
-    /// Tt sends a message to the proxy to list all backend configurations and returns the response
+    /// Tt sends a message to the proxy to list the backend configurations matching a label
+    /// selector, all of them without one, and returns the response
     ///
     /// Arguments:
     ///
-    /// * `request`: Request<()>
+    /// * `request`: Request<ListBackendRequest>
     ///
     /// Returns:
     ///
-    /// A `Response` with a `ReceiverStream` of `Backend`s.
+    /// A `Response` with a `ReceiverStream` of `Backend`s, invalid for an invalid selector
     async fn list_backend(
         &self,
-        request: Request<()>,
+        request: Request<ListBackendRequest>,
     ) -> Result<Response<Self::ListBackendStream>, Status> {
         trace!("received request: {:?}", request);
 
-        trace!("creating oneshot channel to communicate with the proxy");
-        let (tx, rx) = oneshot::channel::<anyhow::Result<Vec<shared::models::backend::Backend>>>();
-
-        debug!("sending backend list request");
-        self.sender
-            .send(Event::ListBackends(tx))
-            .await
-            .map_err(|e| {
-                error!("failed to send list backends event: {}", e);
-                Status::internal("Internal server error")
-            })?;
-
-        debug!("waiting for the response from the proxy");
-        let backends = rx
-            .await
-            .map_err(|e| {
-                error!("failed to receive list backends response: {}", e);
-                Status::internal("Internal server error")
-            })?
-            .map_err(|e| {
-                error!("failed to list backends: {}", e);
-                Status::internal("Internal server error")
-            })?;
+        let selector = parse_selector(&request.into_inner().selector)?;
+        let backends = self.list_backends(selector).await?;
 
         trace!("creating mpsc channel to stream backends");
-        let (tx, rx) = mpsc::channel::<Result<Backend, Status>>(4);
+        let (tx, rx) = mpsc::channel::<Result<Backend, Status>>(self.channels.streams);
+        let metrics = self.metrics.clone();
+        let stats = self.stats.clone();
 
         tokio::spawn(async move {
             debug!("streaming backends");
             for backend in backends {
-                tx.send(Ok(Backend {
-                    hostname: backend.hostname,
-                    redirect_ip: backend.redirect_ip,
-                    redirect_port: backend.redirect_port as u32,
-                }))
-                .await
-                .map_err(|e| {
-                    error!("failed to stream backend: {}", e);
-                })
-                .ok();
+                let health = stats.health(backend.hostname());
+                let backend = Backend {
+                    health: Some(tonic_health(health)),
+                    ..tonic_backend_from_proxy(backend)
+                };
+                if !stream_send(&tx, Ok(backend), &metrics).await {
+                    error!("failed to stream backend: client disconnected");
+                    return;
+                }
             }
         });
 
@@ -89,43 +422,29 @@ impl ProxyService for ProxyListener {
     ///
     /// Returns:
     ///
-    /// A `Result<Response<()>, Status>`
-    async fn put_backend(&self, request: Request<Backend>) -> Result<Response<()>, Status> {
+    /// A `Result<Response<Backend>, Status>` with the stored backend and its new version
+    async fn put_backend(&self, request: Request<Backend>) -> Result<Response<Backend>, Status> {
         trace!("received request: {:?}", request);
 
         trace!("creating oneshot channel to communicate with the proxy");
-        let (tx, rx) = oneshot::channel::<anyhow::Result<()>>();
+        let (tx, rx) = oneshot::channel::<anyhow::Result<shared::models::backend::Backend>>();
         let backend = request.into_inner();
 
         debug!("sending backend creation request: {:?}", backend);
-        self.sender
-            .send(Event::PutBackend(
-                shared::models::backend::Backend {
-                    hostname: backend.hostname,
-                    redirect_ip: backend.redirect_ip,
-                    redirect_port: backend.redirect_port as u16,
-                },
-                tx,
-            ))
-            .await
-            .map_err(|e| {
-                error!("failed to send put backend event: {}", e);
-                Status::internal("Internal server error")
-            })?;
+        self.send_event(
+            "put backend",
+            Event::PutBackend(shared_backend(backend)?, tx),
+        )
+        .await?;
 
         debug!("waiting for the response from the proxy");
-        rx.await
-            .map_err(|e| {
-                error!("failed to receive put backend response: {}", e);
-                Status::internal("Internal server error")
-            })?
-            .map_or_else(
-                |e| {
-                    error!("failed to put backend: {}", e);
-                    Err(Status::internal("Internal server error"))
-                },
-                |_| Ok(Response::new(())),
-            )
+        self.wait_response("put backend", rx).await?.map_or_else(
+            |e| {
+                error!("failed to put backend: {:#}", e);
+                Err(status_from_error(&e))
+            },
+            |backend| Ok(Response::new(tonic_backend_from_proxy(backend))),
+        )
     }
 
     /// It sends a message to the proxy to delete a backend configuration
@@ -145,33 +464,1302 @@ impl ProxyService for ProxyListener {
         let backend = request.into_inner();
 
         debug!("sending backend deletion request: {:?}", backend);
-        self.sender
-            .send(Event::DeleteBackend(
-                shared::models::backend::Backend {
-                    hostname: backend.hostname,
-                    redirect_ip: backend.redirect_ip,
-                    redirect_port: backend.redirect_port as u16,
-                },
-                tx,
-            ))
-            .await
-            .map_err(|e| {
-                error!("failed to send delete backend event: {}", e);
-                Status::internal("Internal server error")
-            })?;
+        self.send_event(
+            "delete backend",
+            Event::DeleteBackend(shared_backend(backend)?, tx),
+        )
+        .await?;
 
         debug!("waiting for the response from the proxy");
-        rx.await
-            .map_err(|e| {
-                error!("failed to receive delete backend response: {}", e);
-                Status::internal("Internal server error")
-            })?
+        self.wait_response("delete backend", rx).await?.map_or_else(
+            |e| {
+                error!("failed to delete backend: {:#}", e);
+                Err(status_from_error(&e))
+            },
+            |_| Ok(Response::new(())),
+        )
+    }
+
+    /// It sends a message to the proxy to restore a backend deleted during the tombstone
+    /// retention window
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: The request object that contains the hostname of the backend to restore.
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<Backend>, Status>` with the restored backend and its new version
+    async fn restore_backend(
+        &self,
+        request: Request<Backend>,
+    ) -> Result<Response<Backend>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<shared::models::backend::Backend>>();
+        let backend = request.into_inner();
+
+        debug!("sending backend restoration request: {:?}", backend);
+        self.send_event(
+            "restore backend",
+            Event::RestoreBackend(backend.hostname, tx),
+        )
+        .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("restore backend", rx)
+            .await?
             .map_or_else(
                 |e| {
-                    error!("failed to delete backend: {}", e);
-                    Err(Status::internal("Internal server error"))
+                    error!("failed to restore backend: {:#}", e);
+                    Err(status_from_error(&e))
                 },
-                |_| Ok(Response::new(())),
+                |backend| Ok(Response::new(tonic_backend_from_proxy(backend))),
             )
     }
+
+    /// It sends a message to the proxy to apply a batch of puts and deletes atomically
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<BackendBatch>
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<BackendBatch>, Status>` with the applied changes and their new versions
+    async fn apply_batch(
+        &self,
+        request: Request<BackendBatch>,
+    ) -> Result<Response<BackendBatch>, Status> {
+        trace!("received request: {:?}", request);
+
+        let batch = request
+            .into_inner()
+            .events
+            .into_iter()
+            .map(backend_change)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.send_batch(batch).await.map(Response::new)
+    }
+
+    /// It converts the forced hosts of a BungeeCord or Velocity configuration into backends,
+    /// then sends a message to the proxy to create them atomically
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<ImportRoutesRequest>
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<BackendBatch>, Status>` with the created backends and their versions
+    async fn import_routes(
+        &self,
+        request: Request<ImportRoutesRequest>,
+    ) -> Result<Response<BackendBatch>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        let format = match Format::from_i32(request.format) {
+            Some(Format::Bungeecord) => ImportFormat::BungeeCord,
+            Some(Format::Velocity) => ImportFormat::Velocity,
+            None => return Err(Status::invalid_argument("unknown import format")),
+        };
+
+        let backends = importer::import(format, &request.content).map_err(|e| {
+            error!("failed to import routes: {:#}", e);
+            Status::invalid_argument(format!("{:#}", e))
+        })?;
+
+        debug!("importing {} routes from {:?}", backends.len(), format);
+        let batch = backends.into_iter().map(BackendChange::Put).collect();
+
+        self.send_batch(batch).await.map(Response::new)
+    }
+
+    /// It sends a message to the proxy to snapshot its whole state and returns it as a blob
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<StateBlob>, Status>` with the encoded `StateSnapshot`
+    async fn snapshot_state(&self, request: Request<()>) -> Result<Response<StateBlob>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Snapshot>>();
+
+        debug!("sending state snapshot request");
+        self.send_event("snapshot state", Event::SnapshotState(tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        let snapshot = self
+            .wait_response("snapshot state", rx)
+            .await?
+            .map_err(|e| {
+                error!("failed to snapshot state: {:#}", e);
+                status_from_error(&e)
+            })?;
+
+        let state = StateSnapshot {
+            revision: snapshot.revision,
+            backends: snapshot
+                .backends
+                .into_iter()
+                .map(tonic_backend_from_proxy)
+                .collect(),
+            access_rules: snapshot
+                .access_rules
+                .into_iter()
+                .map(tonic_access_rule_from_proxy)
+                .collect(),
+            status_assets: snapshot
+                .status_assets
+                .into_iter()
+                .map(tonic_status_asset_from_proxy)
+                .collect(),
+            backend_totals: snapshot
+                .backend_totals
+                .into_iter()
+                .map(|(hostname, totals)| tonic_backend_totals_from_proxy(hostname, totals))
+                .collect(),
+        };
+
+        Ok(Response::new(StateBlob {
+            data: state.encode_to_vec(),
+        }))
+    }
+
+    /// It sends a message to the proxy to replace its whole state with a snapshot
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<StateBlob> with a blob returned by `snapshot_state`
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<()>, Status>`
+    async fn restore_state(&self, request: Request<StateBlob>) -> Result<Response<()>, Status> {
+        trace!("received request: {:?}", request);
+
+        let state = StateSnapshot::decode(request.into_inner().data.as_slice()).map_err(|e| {
+            error!("failed to decode state snapshot: {}", e);
+            Status::invalid_argument(format!("invalid state snapshot: {}", e))
+        })?;
+
+        let snapshot = Snapshot {
+            revision: state.revision,
+            backends: state
+                .backends
+                .into_iter()
+                .map(shared_backend)
+                .collect::<Result<_, _>>()?,
+            access_rules: state
+                .access_rules
+                .into_iter()
+                .map(shared_access_rule)
+                .collect::<Result<_, _>>()?,
+            status_assets: state
+                .status_assets
+                .into_iter()
+                .map(shared_status_asset)
+                .collect::<Result<_, _>>()?,
+            backend_totals: state
+                .backend_totals
+                .into_iter()
+                .map(proxy_backend_totals_from_tonic)
+                .collect(),
+        };
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<()>>();
+
+        debug!(
+            "sending state restore request with {} backends",
+            snapshot.backends.len()
+        );
+        self.send_event("restore state", Event::RestoreState(snapshot, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("restore state", rx).await?.map_or_else(
+            |e| {
+                error!("failed to restore state: {:#}", e);
+                Err(status_from_error(&e))
+            },
+            |_| Ok(Response::new(())),
+        )
+    }
+
+    /// It subscribes to the changes of the backend configurations and streams them
+    ///
+    /// The current backends are first streamed as `PUT` events, followed by every change
+    /// applied afterwards. If the watcher lags too far behind, the stream ends with a
+    /// `DATA_LOSS` status and the client is expected to watch again.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A `Response` with a `ReceiverStream` of `BackendEvent`s.
+    async fn watch_backends(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<Self::WatchBackendsStream>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel();
+
+        debug!("sending backend watch request");
+        self.send_event("watch backends", Event::WatchBackends(tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        let (backends, mut changes) =
+            self.wait_response("watch backends", rx)
+                .await?
+                .map_err(|e| {
+                    error!("failed to watch backends: {}", e);
+                    Status::internal("Internal server error")
+                })?;
+
+        trace!("creating mpsc channel to stream backend events");
+        let (tx, rx) = mpsc::channel::<Result<BackendEvent, Status>>(self.channels.streams);
+        let metrics = self.metrics.clone();
+
+        tokio::spawn(async move {
+            debug!("streaming backend events");
+            for backend in backends {
+                let event = proto_backend_event(BackendChange::Put(backend));
+                if !stream_send(&tx, Ok(event), &metrics).await {
+                    return;
+                }
+            }
+
+            loop {
+                let event = match changes.recv().await {
+                    Ok(change) => Ok(proto_backend_event(change)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        error!("backend watcher lagged behind by {} changes", skipped);
+                        metrics.overflow("changes");
+                        Err(Status::data_loss("watcher lagged behind, watch again"))
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                let is_err = event.is_err();
+                if !stream_send(&tx, event, &metrics).await || is_err {
+                    debug!("backend watcher disconnected");
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// It reloads the configuration file of the proxy
+    ///
+    /// The settings that can change at runtime are applied without dropping the live
+    /// connections, a configuration that fails to load is rejected and the current one is kept.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<()>, Status>
+    async fn reload_config(&self, request: Request<()>) -> Result<Response<()>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<()>>();
+
+        debug!("sending reload config request");
+        self.send_event("reload config", Event::ReloadConfig(tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("reload config", rx).await?.map_or_else(
+            |e| {
+                error!("failed to reload configuration: {:#}", e);
+                Err(Status::failed_precondition(format!(
+                    "failed to reload configuration: {:#}",
+                    e
+                )))
+            },
+            |_| Ok(Response::new(())),
+        )
+    }
+
+    /// It returns the configuration the proxy currently runs with, after the environment
+    /// overrides, the command-line flags and the reloads, with the secrets redacted
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<ConfigDump>, Status>
+    async fn get_config(&self, request: Request<()>) -> Result<Response<ConfigDump>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Arc<ProxyConfig>>>();
+
+        debug!("sending get config request");
+        self.send_event("get config", Event::GetConfig(tx)).await?;
+
+        debug!("waiting for the response from the proxy");
+        let config = self.wait_response("get config", rx).await?.map_err(|e| {
+            error!("failed to get configuration: {:#}", e);
+            Status::internal("Internal server error")
+        })?;
+
+        let content = config.redacted().to_toml().map_err(|e| {
+            error!("{:#}", e);
+            Status::internal("Internal server error")
+        })?;
+
+        Ok(Response::new(ConfigDump {
+            content,
+            path: config
+                .path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// It marks the proxy as draining, then waits for the open sessions to finish
+    ///
+    /// The proxy fails its readiness probe and stops accepting new connections, it is meant to be
+    /// called by the preStop hook of the pod. The wait doesn't go through the event loop, so it
+    /// isn't bounded by the response timeout of the channels.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<DrainRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<DrainResult>, Status>
+    async fn start_drain(
+        &self,
+        request: Request<DrainRequest>,
+    ) -> Result<Response<DrainResult>, Status> {
+        trace!("received request: {:?}", request);
+
+        let wait = match request.into_inner().timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        let remaining = self.health.drain(wait).await;
+        if remaining > 0 {
+            warn!("drain timed out with {} sessions still open", remaining);
+        }
+
+        Ok(Response::new(DrainResult {
+            remaining_sessions: remaining as u64,
+        }))
+    }
+
+    /// It returns the latest notable events of the connections, such as the kicks, the routing
+    /// misses and the backend failures
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<RecentEventsRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<RecentEvents>, Status>
+    async fn get_recent_events(
+        &self,
+        request: Request<RecentEventsRequest>,
+    ) -> Result<Response<RecentEvents>, Status> {
+        trace!("received request: {:?}", request);
+
+        let limit = match request.into_inner().limit {
+            0 => None,
+            limit => Some(limit as usize),
+        };
+        let events = self
+            .recent
+            .latest(limit)
+            .into_iter()
+            .map(|event| RecentEvent {
+                timestamp_ms: event.timestamp_ms,
+                kind: event.kind.as_str().to_string(),
+                connection_id: event.connection_id,
+                client: event.client,
+                hostname: event.hostname,
+                backend: event.backend.unwrap_or_default(),
+                message: event.message,
+            })
+            .collect();
+
+        Ok(Response::new(RecentEvents { events }))
+    }
+
+    /// It returns the addresses banned for misbehaving, with the offense which got them banned
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<Bans>, Status>
+    async fn list_bans(&self, request: Request<()>) -> Result<Response<Bans>, Status> {
+        trace!("received request: {:?}", request);
+
+        let bans = self
+            .bans
+            .list()
+            .into_iter()
+            .map(|ban| Ban {
+                ip: ban.ip.to_string(),
+                offense: ban.offense.as_str().to_string(),
+                strikes: ban.strikes as u32,
+                banned_at_ms: ban.banned_at_ms,
+                expires_at_ms: ban.expires_at_ms,
+            })
+            .collect();
+
+        Ok(Response::new(Bans { bans }))
+    }
+
+    /// It lifts the ban of an address, or of every address, and forgets their strikes
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<ClearBansRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<ClearBansResult>, Status>, invalid if the address is not an IP address
+    async fn clear_bans(
+        &self,
+        request: Request<ClearBansRequest>,
+    ) -> Result<Response<ClearBansResult>, Status> {
+        trace!("received request: {:?}", request);
+
+        let ip =
+            match request.into_inner().ip {
+                ip if ip.is_empty() => None,
+                ip => Some(ip.parse().map_err(|_| {
+                    Status::invalid_argument(format!("{} is not an IP address", ip))
+                })?),
+            };
+        let cleared = self.bans.clear(ip);
+        debug!("lifted {} bans", cleared);
+
+        Ok(Response::new(ClearBansResult {
+            cleared: cleared as u32,
+        }))
+    }
+
+    /// It sends a message to the proxy to list the bans and the allows set through the API
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<AccessRules>, Status>, without the expired rules
+    async fn list_access_rules(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<AccessRules>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<Vec<shared::models::access_rule::AccessRule>>>();
+
+        debug!("sending access rules list request");
+        self.send_event("list access rules", Event::ListAccessRules(tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("list access rules", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to list access rules: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |rules| {
+                    Ok(Response::new(AccessRules {
+                        rules: rules
+                            .into_iter()
+                            .map(tonic_access_rule_from_proxy)
+                            .collect(),
+                    }))
+                },
+            )
+    }
+
+    /// It sends a message to the proxy to ban or allow an address, a CIDR block or a username
+    ///
+    /// The rule replaces the one of the same kind and subject, and lasts `ttl_secs` from now.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<AccessRule>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<AccessRule>, Status> with the stored rule, invalid for an unknown kind
+    /// or an invalid subject
+    async fn put_access_rule(
+        &self,
+        request: Request<AccessRule>,
+    ) -> Result<Response<AccessRule>, Status> {
+        trace!("received request: {:?}", request);
+
+        let rule = request.into_inner();
+        let now_ms = access_rule::now_ms();
+        let expires_at_ms = match rule.ttl_secs {
+            0 => 0,
+            ttl_secs => now_ms.saturating_add(ttl_secs.saturating_mul(1000)),
+        };
+        let rule = shared_access_rule(AccessRule {
+            created_at_ms: now_ms,
+            expires_at_ms,
+            ..rule
+        })?;
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<shared::models::access_rule::AccessRule>>();
+
+        debug!("sending access rule put request: {:?}", rule);
+        self.send_event("put access rule", Event::PutAccessRule(rule, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("put access rule", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to put access rule: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |rule| Ok(Response::new(tonic_access_rule_from_proxy(rule))),
+            )
+    }
+
+    /// It sends a message to the proxy to remove the rule of a kind and a subject
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<AccessRule>, only its kind and its subject are read
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<()>, Status>, not found if no rule has this kind and this subject
+    async fn delete_access_rule(
+        &self,
+        request: Request<AccessRule>,
+    ) -> Result<Response<()>, Status> {
+        trace!("received request: {:?}", request);
+
+        let rule = request.into_inner();
+        let kind = access_kind_from_tonic(rule.kind)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let subject = rule.subject.parse().map_err(Status::invalid_argument)?;
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<shared::models::access_rule::AccessRule>>();
+
+        debug!(
+            "sending access rule deletion request: {:?} {}",
+            kind, subject
+        );
+        self.send_event(
+            "delete access rule",
+            Event::DeleteAccessRule(kind, subject, tx),
+        )
+        .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("delete access rule", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to delete access rule: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |_| Ok(Response::new(())),
+            )
+    }
+
+    /// It sends a message to the proxy to list the MOTDs and the favicons of the hostnames
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<StatusAssets>, Status>
+    async fn list_status_assets(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<StatusAssets>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<Vec<shared::models::status_asset::StatusAsset>>>();
+
+        debug!("sending status assets list request");
+        self.send_event("list status assets", Event::ListStatusAssets(tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("list status assets", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to list status assets: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |assets| {
+                    Ok(Response::new(StatusAssets {
+                        assets: assets
+                            .into_iter()
+                            .map(tonic_status_asset_from_proxy)
+                            .collect(),
+                    }))
+                },
+            )
+    }
+
+    /// It sends a message to the proxy to set the MOTD and the favicon of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<StatusAsset>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<StatusAsset>, Status> with the stored asset, invalid when it can't be
+    /// shown in the server list
+    async fn put_status_asset(
+        &self,
+        request: Request<StatusAsset>,
+    ) -> Result<Response<StatusAsset>, Status> {
+        // the favicons are too long for the logs
+        trace!(
+            "received request for the status asset of {}",
+            request.get_ref().hostname
+        );
+
+        let asset = shared_status_asset(request.into_inner())?;
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<shared::models::status_asset::StatusAsset>>();
+
+        debug!("sending status asset put request for {}", asset.hostname);
+        self.send_event("put status asset", Event::PutStatusAsset(asset, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("put status asset", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to put status asset: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |asset| Ok(Response::new(tonic_status_asset_from_proxy(asset))),
+            )
+    }
+
+    /// It sends a message to the proxy to remove the MOTD and the favicon of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<StatusAsset>, only its hostname is read
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<()>, Status>, not found if the hostname has no asset
+    async fn delete_status_asset(
+        &self,
+        request: Request<StatusAsset>,
+    ) -> Result<Response<()>, Status> {
+        trace!(
+            "received request for the status asset of {}",
+            request.get_ref().hostname
+        );
+
+        let hostname = request.into_inner().hostname;
+        if hostname.is_empty() {
+            return Err(Status::invalid_argument(
+                "the hostname of a status asset must be set",
+            ));
+        }
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) =
+            oneshot::channel::<anyhow::Result<shared::models::status_asset::StatusAsset>>();
+
+        debug!("sending status asset deletion request for {}", hostname);
+        self.send_event(
+            "delete status asset",
+            Event::DeleteStatusAsset(hostname, tx),
+        )
+        .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("delete status asset", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to delete status asset: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |_| Ok(Response::new(())),
+            )
+    }
+
+    /// It captures the next connections to a hostname into files, for debugging
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<CaptureRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<()>, Status>, invalid without a hostname or above 16 MiB per direction
+    async fn start_capture(
+        &self,
+        request: Request<CaptureRequest>,
+    ) -> Result<Response<()>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        if request.hostname.is_empty() {
+            return Err(Status::invalid_argument(
+                "the hostname of the capture is missing",
+            ));
+        }
+        let max_bytes = match request.max_kib as usize * 1024 {
+            0 => DEFAULT_CAPTURE_BYTES,
+            max_bytes if max_bytes > MAX_CAPTURE_BYTES => {
+                return Err(Status::invalid_argument(format!(
+                    "a capture records at most {} KiB of each direction",
+                    MAX_CAPTURE_BYTES / 1024
+                )))
+            }
+            max_bytes => max_bytes,
+        };
+        let settings = CaptureSettings {
+            max_bytes,
+            remaining: (request.connections > 0).then_some(request.connections),
+        };
+        self.captures.start(&request.hostname, settings);
+        debug!("capturing the connections to {}", request.hostname);
+
+        Ok(Response::new(()))
+    }
+
+    /// It stops the capture of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<CaptureRequest>, only its hostname is read
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<()>, Status>, not found if the hostname isn't captured
+    async fn stop_capture(&self, request: Request<CaptureRequest>) -> Result<Response<()>, Status> {
+        trace!("received request: {:?}", request);
+
+        let hostname = request.into_inner().hostname;
+        if !self.captures.stop(&hostname) {
+            return Err(Status::not_found(format!("{} isn't captured", hostname)));
+        }
+        debug!("stopped capturing the connections to {}", hostname);
+
+        Ok(Response::new(()))
+    }
+
+    /// It returns the running captures
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<Captures>, Status>
+    async fn list_captures(&self, request: Request<()>) -> Result<Response<Captures>, Status> {
+        trace!("received request: {:?}", request);
+
+        let captures = self
+            .captures
+            .list()
+            .into_iter()
+            .map(|(hostname, settings)| CaptureRequest {
+                hostname,
+                max_kib: (settings.max_bytes / 1024) as u32,
+                connections: settings.remaining.unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(Response::new(Captures { captures }))
+    }
+
+    /// It turns the DDoS mode of the proxy on or off, from the next connection
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<DdosModeState>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<DdosModeState>, Status>, the mode the proxy is now in
+    async fn set_ddos_mode(
+        &self,
+        request: Request<DdosModeState>,
+    ) -> Result<Response<DdosModeState>, Status> {
+        trace!("received request: {:?}", request);
+
+        let enabled = request.into_inner().enabled;
+        if self.ddos.set(enabled) {
+            match enabled {
+                true => warn!("DDoS mode turned on through the API"),
+                false => info!("DDoS mode turned off through the API"),
+            }
+        }
+
+        Ok(Response::new(DdosModeState { enabled }))
+    }
+
+    /// It returns whether the proxy is in DDoS mode
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<()>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<DdosModeState>, Status>
+    async fn get_ddos_mode(&self, request: Request<()>) -> Result<Response<DdosModeState>, Status> {
+        trace!("received request: {:?}", request);
+
+        Ok(Response::new(DdosModeState {
+            enabled: self.ddos.enabled(),
+        }))
+    }
+
+    /// It returns the players relayed to every version of the backends
+    ///
+    /// The players keep the backend they joined until they leave, so the versions older than
+    /// the current one of their backend, or of a deleted backend, are the legacy sessions an
+    /// update left on the previous address.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<SessionsRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<Sessions>, Status>
+    async fn get_sessions(
+        &self,
+        request: Request<SessionsRequest>,
+    ) -> Result<Response<Sessions>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        let hostname = request.hostname.to_lowercase();
+        let selector = parse_selector(&request.selector)?;
+        let selected = selector.is_empty();
+
+        let versions: HashMap<String, u64> = self
+            .list_backends(LabelSelector::default())
+            .await?
+            .into_iter()
+            .filter(|backend| selector.matches(backend.labels()))
+            .map(|backend| (backend.hostname().to_string(), backend.version()))
+            .collect();
+
+        // with a selector, the sessions of the deleted backends aren't selected anymore
+        let sessions = self
+            .sessions
+            .list()
+            .into_iter()
+            .filter(|sessions| hostname.is_empty() || sessions.hostname == hostname)
+            .filter(|sessions| selected || versions.contains_key(&sessions.hostname))
+            .map(|sessions| VersionSessions {
+                legacy: versions.get(&sessions.hostname) != Some(&sessions.version),
+                hostname: sessions.hostname,
+                version: sessions.version,
+                backend: sessions.backend,
+                active: sessions.active as u64,
+            })
+            .collect();
+
+        Ok(Response::new(Sessions { sessions }))
+    }
+
+    /// It moves the players of a backend to another server with the transfer packet of the
+    /// 1.20.5 protocol, e.g. before the backend shuts down
+    ///
+    /// Every relay to the backend writes the transfer after the packet it is relaying, then
+    /// closes the connection of its player. The players of the older versions stay.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<TransferRequest>
+    ///
+    /// Returns:
+    ///
+    /// A Result<Response<TransferResult>, Status>, invalid without a hostname or a valid target
+    async fn transfer_players(
+        &self,
+        request: Request<TransferRequest>,
+    ) -> Result<Response<TransferResult>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        let backend = request.hostname.to_lowercase();
+        let backends = match (backend.is_empty(), request.selector.is_empty()) {
+            (false, true) => vec![backend],
+            (true, false) => self
+                .list_backends(parse_selector(&request.selector)?)
+                .await?
+                .into_iter()
+                .map(|backend| backend.hostname().to_string())
+                .collect(),
+            (true, true) => {
+                return Err(Status::invalid_argument(
+                    "the hostname of the backend is missing",
+                ))
+            }
+            (false, false) => {
+                return Err(Status::invalid_argument(
+                    "either the hostname or the selector must be set, not both",
+                ))
+            }
+        };
+        let (hostname, port) = parse_target(&request.target).ok_or_else(|| {
+            Status::invalid_argument(format!("invalid transfer target: {}", request.target))
+        })?;
+
+        let mut players = 0;
+        for backend in backends {
+            let moved = self.sessions.players(&backend);
+            // without relays, nobody is subscribed and there is nobody to transfer
+            let _ = self.transfers.send(sessions::Transfer {
+                backend: backend.clone(),
+                hostname: hostname.clone(),
+                port,
+            });
+            debug!("transferring the {} players of {}", moved, backend);
+            players += moved;
+        }
+
+        Ok(Response::new(TransferResult {
+            players: players as u64,
+        }))
+    }
+
+    /// It streams the records logged by the proxy from now on, like `kubectl logs -f` without
+    /// access to the pod
+    ///
+    /// Only the records passing the log filter of the proxy are streamed. If the subscriber lags
+    /// more than `channels.logs` records behind, the stream ends with a `DATA_LOSS` status.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<LogsRequest>
+    ///
+    /// Returns:
+    ///
+    /// A `Response` with a `ReceiverStream` of `LogRecord`s, unavailable when the process doesn't
+    /// stream its logs
+    async fn stream_logs(
+        &self,
+        request: Request<LogsRequest>,
+    ) -> Result<Response<Self::StreamLogsStream>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        let filter = LogFilter {
+            level: match request.level.as_str() {
+                "" => LogLevel::Info,
+                level => level.parse().map_err(Status::invalid_argument)?,
+            },
+            target: (!request.target.is_empty()).then_some(request.target),
+        };
+        let mut records = self
+            .logs
+            .as_ref()
+            .ok_or_else(|| Status::unavailable("the logs of this proxy aren't streamed"))?
+            .subscribe();
+
+        let (tx, rx) = mpsc::channel::<Result<LogRecord, Status>>(self.channels.streams);
+        let metrics = self.metrics.clone();
+
+        tokio::spawn(async move {
+            // nothing is logged per record, it would be streamed back
+            loop {
+                let record = match records.recv().await {
+                    Ok(record) if filter.matches(&record) => Ok(LogRecord {
+                        timestamp_ms: record.timestamp_ms,
+                        level: record.level.to_string(),
+                        target: record.target,
+                        message: record.message,
+                    }),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => {
+                        metrics.overflow("logs");
+                        Err(Status::data_loss("the stream lagged behind, stream again"))
+                    }
+                    Err(RecvError::Closed) => return,
+                };
+
+                let is_err = record.is_err();
+                if !stream_send(&tx, record, &metrics).await || is_err {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// It streams snapshots of the activity of every backend, e.g. for a dashboard or an
+    /// autoscaler
+    ///
+    /// Every snapshot has the players relayed to each backend, the joins and the bytes relayed
+    /// since the previous snapshot, as rates, and the health of the backend.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<StatsRequest>
+    ///
+    /// Returns:
+    ///
+    /// A `Response` with a `ReceiverStream` of `StatsSnapshot`s, invalid above an interval of an
+    /// hour
+    async fn watch_stats(
+        &self,
+        request: Request<StatsRequest>,
+    ) -> Result<Response<Self::WatchStatsStream>, Status> {
+        trace!("received request: {:?}", request);
+
+        let request = request.into_inner();
+        let interval = match request.interval_secs {
+            0 => DEFAULT_STATS_INTERVAL,
+            secs if secs > MAX_STATS_INTERVAL_SECS => {
+                return Err(Status::invalid_argument(format!(
+                    "the snapshots are taken at most {} seconds apart",
+                    MAX_STATS_INTERVAL_SECS
+                )))
+            }
+            secs => Duration::from_secs(secs.into()),
+        };
+        let hostname = request.hostname.to_lowercase();
+
+        let (tx, rx) = mpsc::channel::<Result<StatsSnapshot, Status>>(self.channels.streams);
+        let metrics = self.metrics.clone();
+        let stats = self.stats.clone();
+        let sessions = self.sessions.clone();
+
+        tokio::spawn(async move {
+            debug!("streaming the stats every {:?}", interval);
+            let mut previous = stats.totals();
+            let mut taken = Instant::now();
+            let mut ticks = tokio::time::interval_at((taken + interval).into(), interval);
+
+            loop {
+                ticks.tick().await;
+                let totals = stats.totals();
+                let cumulative = stats.cumulative_totals();
+                let elapsed = taken.elapsed();
+                taken = Instant::now();
+
+                let backends = totals
+                    .iter()
+                    .filter(|(backend, _)| hostname.is_empty() || **backend == hostname)
+                    .map(|(backend, totals)| {
+                        let previous = previous.get(backend).copied().unwrap_or_default();
+                        let rates = totals.rates_since(&previous, elapsed);
+                        let cumulative = cumulative.get(backend).copied().unwrap_or_default();
+                        BackendStats {
+                            hostname: backend.clone(),
+                            active: sessions.players(backend) as u64,
+                            joins_per_min: rates.joins_per_min,
+                            bytes_in_per_sec: rates.bytes_in_per_sec,
+                            bytes_out_per_sec: rates.bytes_out_per_sec,
+                            health: Some(tonic_health(stats.health(backend))),
+                            total_joins: cumulative.joins,
+                            total_bytes_in: cumulative.bytes_in,
+                            total_bytes_out: cumulative.bytes_out,
+                        }
+                    })
+                    .collect();
+                previous = totals;
+
+                let snapshot = StatsSnapshot {
+                    timestamp_ms: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|elapsed| elapsed.as_millis() as u64)
+                        .unwrap_or_default(),
+                    backends,
+                };
+                if !stream_send(&tx, Ok(snapshot), &metrics).await {
+                    debug!("stats watcher disconnected");
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    /// It sends a message to the proxy to ping the stored target of a backend right now, e.g. to
+    /// check a route right after creating it
+    ///
+    /// A backend that doesn't answer isn't an error of the request: the result tells why.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<ProbeRequest>
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<ProbeResult>, Status>`, not found when no backend has the hostname
+    async fn probe_backend(
+        &self,
+        request: Request<ProbeRequest>,
+    ) -> Result<Response<ProbeResult>, Status> {
+        trace!("received request: {:?}", request);
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Probe>>();
+        let hostname = request.into_inner().hostname.to_lowercase();
+
+        debug!("sending backend probe request: {}", hostname);
+        self.send_event("probe backend", Event::ProbeBackend(hostname, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("probe backend", rx).await?.map_or_else(
+            |e| {
+                error!("failed to probe backend: {:#}", e);
+                Err(status_from_error(&e))
+            },
+            |probe| {
+                let millis = |elapsed: Option<Duration>| {
+                    elapsed.map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+                };
+                Ok(Response::new(ProbeResult {
+                    reachable: probe.reachable(),
+                    connect_ms: millis(probe.connect),
+                    latency_ms: millis(probe.latency),
+                    status: probe.status.unwrap_or_default(),
+                    error: probe.error.unwrap_or_default(),
+                    target: probe.target,
+                }))
+            },
+        )
+    }
+
+    /// It sends a message to the proxy to validate a backend as `PutBackend` would, without
+    /// storing it, e.g. to lint the route changes of a CI pipeline
+    ///
+    /// Besides the checks of `PutBackend`, the hostname must be valid, the redirect address must
+    /// resolve, and the rules routing the players to other backends are checked.
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: Request<Backend>
+    ///
+    /// Returns:
+    ///
+    /// A `Result<Response<ValidationResult>, Status>` with the errors and the warnings found
+    async fn validate_backend(
+        &self,
+        request: Request<Backend>,
+    ) -> Result<Response<ValidationResult>, Status> {
+        trace!("received request: {:?}", request);
+
+        // an invalid field is a finding as any other
+        let backend = match proxy_backend_from_tonic(request.into_inner()) {
+            Ok(backend) => backend,
+            Err(e) => {
+                return Ok(Response::new(ValidationResult {
+                    valid: false,
+                    errors: vec![e.to_string()],
+                    warnings: Vec::new(),
+                }))
+            }
+        };
+
+        trace!("creating oneshot channel to communicate with the proxy");
+        let (tx, rx) = oneshot::channel::<anyhow::Result<Validation>>();
+
+        debug!("sending backend validation request: {:?}", backend);
+        self.send_event("validate backend", Event::ValidateBackend(backend, tx))
+            .await?;
+
+        debug!("waiting for the response from the proxy");
+        self.wait_response("validate backend", rx)
+            .await?
+            .map_or_else(
+                |e| {
+                    error!("failed to validate backend: {:#}", e);
+                    Err(status_from_error(&e))
+                },
+                |validation| {
+                    Ok(Response::new(ValidationResult {
+                        valid: validation.is_valid(),
+                        errors: validation.errors,
+                        warnings: validation.warnings,
+                    }))
+                },
+            )
+    }
+}
+
+/// It splits the target of a transfer into its host and its port
+///
+/// Arguments:
+///
+/// * `target`: `host` or `host:port`.
+///
+/// Returns:
+///
+/// The host and the port, 25565 by default, none when the target is empty or its port invalid
+fn parse_target(target: &str) -> Option<(String, u16)> {
+    let (host, port) = match target.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok().filter(|port| *port > 0)?),
+        None => (target, 25565),
+    };
+    if host.is_empty() || host.len() > 255 {
+        return None;
+    }
+    Some((host.to_string(), port))
 }
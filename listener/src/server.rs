@@ -0,0 +1,222 @@
+use std::{fs, net::SocketAddr, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Ok};
+use config::{ChannelsConfig, ListenerConfig, TlsConfig};
+use health::Health;
+use log::{debug, error};
+use metrics::channel::ChannelMetrics;
+use proto::proxy::proxy_service_server::ProxyServiceServer;
+use shared::{
+    bans::Bans,
+    capture::Captures,
+    cidr::Cidr,
+    ddos::DdosMode,
+    logs::LogRecord,
+    recent::RecentEvents,
+    sessions::{Sessions, Transfer},
+    stats::Stats,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+};
+use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+
+use crate::{auth::TokenInterceptor, event::Event, listeners::proxy::ProxyListener};
+
+/// The state of the proxy the gRPC API reads and changes, besides the backends
+///
+/// Properties:
+///
+/// * `health`: The state of the proxy, drained by `StartDrain`.
+/// * `recent`: The latest notable events of the connections.
+/// * `bans`: The banned addresses.
+/// * `captures`: The hostnames whose connections are captured.
+/// * `sessions`: The players relayed to every version of the backends.
+/// * `transfers`: The sender of the transfers moving the players of a backend.
+/// * `logs`: The sender of the records logged by the process, none when they aren't streamed.
+/// * `stats`: The counters of every backend.
+/// * `ddos`: The DDoS mode of the proxy.
+#[derive(Debug, Clone)]
+pub struct ProxyState {
+    pub health: Arc<Health>,
+    pub recent: Arc<RecentEvents>,
+    pub bans: Arc<Bans>,
+    pub captures: Arc<Captures>,
+    pub sessions: Arc<Sessions>,
+    pub transfers: broadcast::Sender<Transfer>,
+    pub logs: Option<broadcast::Sender<LogRecord>>,
+    pub stats: Arc<Stats>,
+    pub ddos: Arc<DdosMode>,
+}
+
+pub struct Listener {
+    config: ListenerConfig,
+    channels: ChannelsConfig,
+    metrics: ChannelMetrics,
+    state: ProxyState,
+}
+
+impl Listener {
+    pub fn new(
+        config: ListenerConfig,
+        channels: ChannelsConfig,
+        metrics: ChannelMetrics,
+        state: ProxyState,
+    ) -> Self {
+        Self {
+            config,
+            channels,
+            metrics,
+            state,
+        }
+    }
+
+    /// It creates a gRPC server that listens on the address specified in the configuration, and sends
+    /// events to the event loop
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: mpsc::Sender<Event>
+    ///
+    /// Returns:
+    ///
+    /// A JoinHandle<()>
+    pub async fn start(&self, tx: mpsc::Sender<Event>) -> anyhow::Result<()> {
+        let incoming = self.bind().await?;
+        self.serve(incoming, tx).await
+    }
+
+    /// It binds the address specified in the configuration, so the readiness probe only passes
+    /// once requests are accepted
+    ///
+    /// Returns:
+    ///
+    /// A Result<TcpListener>, its port chosen by the system when the configured one is 0
+    pub async fn bind(&self) -> anyhow::Result<TcpListener> {
+        let addr = SocketAddr::from_str(&self.config.addr()).map_err(|e| {
+            error!("failed to parse address: {}", e);
+            anyhow!("failed to parse address: {}", e)
+        })?;
+
+        let incoming = TcpListener::bind(addr)
+            .await
+            .map_err(|e| anyhow!("failed to bind listener to {}: {}", addr, e))?;
+        self.state.health.set_listener_bound();
+
+        Ok(incoming)
+    }
+
+    /// It serves the gRPC API on a bound listener, and sends events to the event loop
+    ///
+    /// The connections of the clients outside of the `allowlist` are closed right after the
+    /// accept, before the TLS handshake and the token check.
+    ///
+    /// Arguments:
+    ///
+    /// * `incoming`: The listener returned by `Listener::bind`
+    /// * `tx`: mpsc::Sender<Event>
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn serve(
+        &self,
+        incoming: TcpListener,
+        tx: mpsc::Sender<Event>,
+    ) -> anyhow::Result<()> {
+        let state = self.state.clone();
+        let proxy_listener = ProxyListener {
+            sender: tx,
+            channels: self.channels.clone(),
+            metrics: self.metrics.clone(),
+            health: state.health,
+            recent: state.recent,
+            bans: state.bans,
+            captures: state.captures,
+            sessions: state.sessions,
+            transfers: state.transfers,
+            logs: state.logs,
+            stats: state.stats,
+            ddos: state.ddos,
+        };
+
+        let allowlist = self
+            .config
+            .allowlist
+            .iter()
+            .map(|block| block.parse::<Cidr>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("listener.allowlist: {}", e))?;
+        let incoming = TcpListenerStream::new(incoming).filter(move |stream| {
+            // the errors of the accept are left to the server
+            let Some(stream) = stream.as_ref().ok() else {
+                return true;
+            };
+            if allowlist.is_empty() {
+                return true;
+            }
+            match stream.peer_addr() {
+                Result::Ok(addr) if allowlist.iter().any(|block| block.contains(&addr.ip())) => {
+                    true
+                }
+                Result::Ok(addr) => {
+                    debug!(
+                        "closing the connection of {}, outside of the allowlist",
+                        addr
+                    );
+                    false
+                }
+                Err(_) => false,
+            }
+        });
+
+        let token = self
+            .config
+            .token
+            .as_ref()
+            .map(|token| token.resolve())
+            .transpose()?;
+
+        let mut server = Server::builder();
+        if let Some(tls) = &self.config.tls {
+            server = server
+                .tls_config(Self::tls_config(tls)?)
+                .map_err(|e| anyhow!("failed to configure TLS: {}", e))?;
+        }
+
+        server
+            .add_service(ProxyServiceServer::with_interceptor(
+                proxy_listener,
+                TokenInterceptor::new(token),
+            ))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(|e| anyhow!("server exited with error {}", e))?;
+
+        Ok(())
+    }
+
+    /// It reads the certificate, the key and the optional client authority of the listener
+    ///
+    /// Arguments:
+    ///
+    /// * `tls`: The paths of the PEM files.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ServerTlsConfig>
+    fn tls_config(tls: &TlsConfig) -> anyhow::Result<ServerTlsConfig> {
+        let read =
+            |path: &String| fs::read(path).map_err(|e| anyhow!("failed to read {}: {}", path, e));
+
+        let mut config =
+            ServerTlsConfig::new().identity(Identity::from_pem(read(&tls.cert)?, read(&tls.key)?));
+        if let Some(client_ca) = &tls.client_ca {
+            config = config.client_ca_root(Certificate::from_pem(read(client_ca)?));
+        }
+
+        Ok(config)
+    }
+}
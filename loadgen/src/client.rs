@@ -0,0 +1,221 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use protocol::{
+    encode_string, encode_var_int,
+    packets::serverbound::handshake::{Handshake, NextState},
+    read_packet, read_var_int, write_packet,
+};
+use tokio::{
+    io::AsyncReadExt,
+    net::TcpStream,
+    time::{sleep, timeout},
+};
+
+use crate::Cli;
+
+/// The maximum length of the packets read by the fake clients, a status may hold a favicon
+const MAX_PACKET_SIZE: usize = 64 * 1024;
+
+/// What a fake client does on each of its connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scenario {
+    /// The handshake, the status request and the ping, as the server list does
+    Status,
+    /// The handshake, the login start, then the packets of `--packets` while the answers of the
+    /// backend are drained
+    Login,
+}
+
+/// The connections of the fake clients, from the command line
+///
+/// Properties:
+///
+/// * `target`: The address of the proxy.
+/// * `hostname`: The hostname of the handshakes.
+/// * `scenario`: What every connection does.
+/// * `protocol`: The protocol version of the handshakes.
+/// * `packets`: The packets sent after the login start.
+/// * `packet_size`: The size of these packets, in bytes.
+/// * `packet_interval`: The delay between two of these packets.
+/// * `timeout`: How long the connect and each answer of the proxy are waited for.
+#[derive(Debug)]
+pub struct FakeClient {
+    target: SocketAddr,
+    hostname: String,
+    scenario: Scenario,
+    protocol: i32,
+    packets: u32,
+    packet_size: usize,
+    packet_interval: Duration,
+    timeout: Duration,
+}
+
+impl From<&Cli> for FakeClient {
+    fn from(cli: &Cli) -> Self {
+        Self {
+            target: cli.target,
+            hostname: cli.hostname.clone(),
+            scenario: cli.scenario,
+            protocol: cli.protocol,
+            packets: cli.packets,
+            packet_size: cli.packet_size,
+            packet_interval: Duration::from_millis(cli.packet_interval_ms),
+            timeout: Duration::from_secs(cli.timeout_secs),
+        }
+    }
+}
+
+impl FakeClient {
+    /// It runs a connection of a client, from its connect until it is done or closed
+    ///
+    /// Arguments:
+    ///
+    /// * `client`: The index of the client, in its username.
+    /// * `connection`: The number of connections of the client before this one, in its username.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the time between the connect and the first answer of the proxy
+    pub async fn run(&self, client: usize, connection: u64) -> Result<Duration> {
+        let started = Instant::now();
+        let mut stream = self
+            .within("connect", TcpStream::connect(self.target))
+            .await??;
+        stream.set_nodelay(true)?;
+
+        let next_state = match self.scenario {
+            Scenario::Status => NextState::Status,
+            Scenario::Login => NextState::Login,
+        };
+        Handshake::new(
+            self.protocol,
+            self.hostname.clone(),
+            self.target.port(),
+            next_state,
+        )
+        .write(&mut stream)
+        .await?;
+
+        match self.scenario {
+            Scenario::Status => self.status(stream, started).await,
+            Scenario::Login => {
+                // the usernames are at most 16 characters
+                let username = format!("lg{}_{}", client, connection % 100_000);
+                self.login(stream, &username, started).await
+            }
+        }
+    }
+
+    /// It asks for the status, then pings the server, after a `Status` handshake
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The connection, right after its handshake.
+    /// * `started`: When the client connected.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the time until the status was read
+    async fn status(&self, mut stream: TcpStream, started: Instant) -> Result<Duration> {
+        write_packet(&mut stream, &[0]).await?;
+        let mut status = self
+            .within("status", read_packet(&mut stream, MAX_PACKET_SIZE))
+            .await??;
+        let answered = started.elapsed();
+        match read_var_int(&mut status).await? {
+            0 => {}
+            id => return Err(anyhow!("unexpected status packet id: {}", id)),
+        }
+
+        let mut ping = vec![1];
+        ping.extend_from_slice(&(answered.as_millis() as i64).to_be_bytes());
+        write_packet(&mut stream, &ping).await?;
+        let pong = self
+            .within("pong", read_packet(&mut stream, MAX_PACKET_SIZE))
+            .await??;
+        if pong.get_ref() != &ping {
+            return Err(anyhow!("the pong doesn't carry the payload of the ping"));
+        }
+
+        Ok(answered)
+    }
+
+    /// It sends the login start, then the packets of the player, after a `Login` handshake
+    ///
+    /// The packets are filler ones, sent until the backend closes the connection, so the relay
+    /// is measured against a backend tolerating them or disconnecting the player right away.
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The connection, right after its handshake.
+    /// * `username`: The username of the login start.
+    /// * `started`: When the client connected.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the time until the first answer of the backend
+    async fn login(&self, stream: TcpStream, username: &str, started: Instant) -> Result<Duration> {
+        let (mut reader, mut writer) = stream.into_split();
+
+        let mut login_start = Vec::new();
+        encode_var_int(&mut login_start, 0);
+        encode_string(&mut login_start, username);
+        match self.protocol {
+            ..=758 => {}
+            // no chat signing key, and no UUID from 1.19.1
+            759..=760 => login_start.extend_from_slice(&[0, 0]),
+            // no UUID
+            761..=763 => login_start.push(0),
+            _ => login_start.extend_from_slice(&[0; 16]),
+        }
+        write_packet(&mut writer, &login_start).await?;
+
+        let mut buf = vec![0; 4096];
+        let read = self.within("login", reader.read(&mut buf)).await??;
+        let answered = started.elapsed();
+        if read == 0 {
+            return Err(anyhow!("closed before the first answer of the backend"));
+        }
+
+        // the answers of the backend are drained while the player talks
+        let drain = tokio::spawn(async move {
+            while matches!(reader.read(&mut buf).await, Ok(read) if read > 0) {}
+        });
+        let mut packet = vec![0; self.packet_size.max(1)];
+        packet[0] = 0x7f;
+        for _ in 0..self.packets {
+            if drain.is_finished() {
+                break;
+            }
+            sleep(self.packet_interval).await;
+            if write_packet(&mut writer, &packet).await.is_err() {
+                break;
+            }
+        }
+        drain.abort();
+
+        Ok(answered)
+    }
+
+    /// It waits for a step of a connection, for at most the timeout of the client
+    ///
+    /// Arguments:
+    ///
+    /// * `step`: The name of the step, in the error of a timeout.
+    /// * `future`: The step.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the output of the step
+    async fn within<F: Future>(&self, step: &str, future: F) -> Result<F::Output> {
+        timeout(self.timeout, future)
+            .await
+            .map_err(|_| anyhow!("{} timed out", step))
+    }
+}
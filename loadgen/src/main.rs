@@ -0,0 +1,148 @@
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use clap::Parser;
+use tokio::time::{interval, sleep_until, MissedTickBehavior};
+
+use crate::{
+    client::{FakeClient, Scenario},
+    report::Report,
+};
+
+mod client;
+mod report;
+
+/// A load generator opening thousands of fake Minecraft clients against the proxy at once, to
+/// measure its capacity without external tooling
+#[derive(Debug, Parser)]
+#[command(name = "loadgen", bin_name = "loadgen", version, about)]
+pub struct Cli {
+    /// The address of the proxy
+    #[arg(long, env = "LOADGEN_TARGET", default_value = "127.0.0.1:25565")]
+    pub target: SocketAddr,
+
+    /// The hostname of the handshakes
+    #[arg(long, env = "LOADGEN_HOSTNAME", default_value = "localhost")]
+    pub hostname: String,
+
+    /// What every client does on each of its connections
+    #[arg(long, env = "LOADGEN_SCENARIO", value_enum, default_value_t = Scenario::Status)]
+    pub scenario: Scenario,
+
+    /// The number of clients connected at once, each reconnecting once its connection ends
+    #[arg(long, env = "LOADGEN_CLIENTS", default_value_t = 1000)]
+    pub clients: usize,
+
+    /// How long the clients keep reconnecting once they all started, in seconds
+    #[arg(long, env = "LOADGEN_DURATION_SECS", default_value_t = 30)]
+    pub duration_secs: u64,
+
+    /// How long the clients take to start, evenly spread, in seconds, so the proxy isn't hit by a
+    /// single burst of connects
+    #[arg(long, env = "LOADGEN_RAMP_UP_SECS", default_value_t = 5)]
+    pub ramp_up_secs: u64,
+
+    /// The protocol version of the handshakes, it decides the fields of the login starts
+    #[arg(long, env = "LOADGEN_PROTOCOL", default_value_t = 765)]
+    pub protocol: i32,
+
+    /// The packets a player sends after its login start, with the `login` scenario
+    #[arg(long, env = "LOADGEN_PACKETS", default_value_t = 100)]
+    pub packets: u32,
+
+    /// The size of these packets, in bytes
+    #[arg(long, env = "LOADGEN_PACKET_SIZE", default_value_t = 64)]
+    pub packet_size: usize,
+
+    /// The delay between two of these packets, in milliseconds
+    #[arg(long, env = "LOADGEN_PACKET_INTERVAL_MS", default_value_t = 50)]
+    pub packet_interval_ms: u64,
+
+    /// How long a client waits for the connect and for each answer of the proxy, in seconds
+    #[arg(long, env = "LOADGEN_TIMEOUT_SECS", default_value_t = 10)]
+    pub timeout_secs: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    log::info!(
+        "starting {} {:?} clients against {} ({}) over {}s, for {}s",
+        cli.clients,
+        cli.scenario,
+        cli.target,
+        cli.hostname,
+        cli.ramp_up_secs,
+        cli.duration_secs
+    );
+
+    let started = Instant::now();
+    let ramp_up = Duration::from_secs(cli.ramp_up_secs);
+    let deadline = started + ramp_up + Duration::from_secs(cli.duration_secs);
+    let report = Arc::new(Report::default());
+    let client = Arc::new(FakeClient::from(&cli));
+
+    let progress = tokio::spawn(log_progress(report.clone()));
+    let mut clients = Vec::with_capacity(cli.clients);
+    for index in 0..cli.clients {
+        let start = started + ramp_up.mul_f64(index as f64 / cli.clients as f64);
+        let report = report.clone();
+        let client = client.clone();
+        clients.push(tokio::spawn(async move {
+            sleep_until(start.into()).await;
+            let mut connections = 0;
+            while Instant::now() < deadline {
+                report.opened();
+                match client.run(index, connections).await {
+                    Ok(latency) => report.succeeded(latency),
+                    Err(e) => {
+                        log::debug!("connection of client {} failed: {:#}", index, e);
+                        report.failed(&e);
+                    }
+                }
+                connections += 1;
+            }
+        }));
+    }
+
+    for client in clients {
+        client.await?;
+    }
+    progress.abort();
+    report.log_summary(started.elapsed());
+
+    Ok(())
+}
+
+/// It logs the connections of the last second, every second
+///
+/// Arguments:
+///
+/// * `report`: The outcome of the connections so far.
+async fn log_progress(report: Arc<Report>) {
+    let mut ticks = interval(Duration::from_secs(1));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticks.tick().await;
+
+    let (mut succeeded, mut failed) = report.totals();
+    loop {
+        ticks.tick().await;
+        let (now_succeeded, now_failed) = report.totals();
+        log::info!(
+            "{} connections/s, {} failed/s, {} open",
+            now_succeeded - succeeded,
+            now_failed - failed,
+            report.open()
+        );
+        (succeeded, failed) = (now_succeeded, now_failed);
+    }
+}
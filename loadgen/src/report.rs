@@ -0,0 +1,118 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// The outcome of the connections of the fake clients
+///
+/// Properties:
+///
+/// * `opened`: The connections opened so far.
+/// * `succeeded`: The connections done without error.
+/// * `failed`: The connections which failed.
+/// * `latencies`: The time until the first answer of every connection done without error.
+/// * `errors`: The number of connections which failed, by error.
+#[derive(Debug, Default)]
+pub struct Report {
+    opened: AtomicU64,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    latencies: Mutex<Vec<Duration>>,
+    errors: Mutex<HashMap<String, u64>>,
+}
+
+impl Report {
+    /// It records a connection being opened
+    pub fn opened(&self) {
+        self.opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// It records a connection done without error
+    ///
+    /// Arguments:
+    ///
+    /// * `latency`: The time until the first answer of the proxy.
+    pub fn succeeded(&self, latency: Duration) {
+        self.succeeded.fetch_add(1, Ordering::Relaxed);
+        self.latencies.lock().unwrap().push(latency);
+    }
+
+    /// It records a connection which failed
+    ///
+    /// Arguments:
+    ///
+    /// * `error`: The error of the connection.
+    pub fn failed(&self, error: &anyhow::Error) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        *self
+            .errors
+            .lock()
+            .unwrap()
+            .entry(error.to_string())
+            .or_default() += 1;
+    }
+
+    /// It returns the connections done so far
+    ///
+    /// Returns:
+    ///
+    /// The connections done without error, and the failed ones
+    pub fn totals(&self) -> (u64, u64) {
+        (
+            self.succeeded.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// It returns the connections opened and not done yet
+    ///
+    /// Returns:
+    ///
+    /// A u64
+    pub fn open(&self) -> u64 {
+        let (succeeded, failed) = self.totals();
+        self.opened
+            .load(Ordering::Relaxed)
+            .saturating_sub(succeeded + failed)
+    }
+
+    /// It logs the rate of the connections, the percentiles of their latency and their errors
+    ///
+    /// Arguments:
+    ///
+    /// * `elapsed`: How long the clients ran.
+    pub fn log_summary(&self, elapsed: Duration) {
+        let (succeeded, failed) = self.totals();
+        log::info!(
+            "{} connections in {:.1}s, {:.0}/s, {} failed",
+            succeeded + failed,
+            elapsed.as_secs_f64(),
+            (succeeded + failed) as f64 / elapsed.as_secs_f64(),
+            failed
+        );
+
+        let mut latencies = self.latencies.lock().unwrap();
+        latencies.sort_unstable();
+        if !latencies.is_empty() {
+            let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+            log::info!(
+                "time to first answer: p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+                percentile(50),
+                percentile(90),
+                percentile(99),
+                percentile(100)
+            );
+        }
+
+        let mut errors: Vec<_> = self.errors.lock().unwrap().drain().collect();
+        errors.sort_by_key(|(_, count)| Reverse(*count));
+        for (error, count) in errors {
+            log::warn!("{} connections failed: {}", count, error);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use prometheus::{IntCounterVec, Opts, Registry};
+
+/// The metrics recorded by the internal channels between the listener and the proxy
+///
+/// Properties:
+///
+/// * `overflows`: The number of sends that found a full channel and had to wait, by channel
+///   (`events`, `streams` or `changes`).
+/// * `timeouts`: The number of requests the proxy didn't answer in time, by event.
+#[derive(Debug, Clone)]
+pub struct ChannelMetrics {
+    overflows: IntCounterVec,
+    timeouts: IntCounterVec,
+}
+
+impl Default for ChannelMetrics {
+    fn default() -> Self {
+        Self {
+            overflows: IntCounterVec::new(
+                Opts::new(
+                    "channel_overflows_total",
+                    "Number of sends that found a full channel, by channel",
+                ),
+                &["channel"],
+            )
+            .expect("valid channel_overflows_total metric"),
+            timeouts: IntCounterVec::new(
+                Opts::new(
+                    "listener_response_timeouts_total",
+                    "Number of requests the proxy didn't answer in time, by event",
+                ),
+                &["event"],
+            )
+            .expect("valid listener_response_timeouts_total metric"),
+        }
+    }
+}
+
+impl ChannelMetrics {
+    /// It registers the metrics into a registry
+    ///
+    /// Arguments:
+    ///
+    /// * `registry`: The registry to register the metrics into
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.overflows.clone()))?;
+        registry.register(Box::new(self.timeouts.clone()))?;
+        Ok(())
+    }
+
+    /// It records a send that found a full channel
+    ///
+    /// Arguments:
+    ///
+    /// * `channel`: The name of the channel
+    pub fn overflow(&self, channel: &str) {
+        self.overflows.with_label_values(&[channel]).inc();
+    }
+
+    /// It records a request the proxy didn't answer in time
+    ///
+    /// Arguments:
+    ///
+    /// * `event`: The name of the event
+    pub fn timeout(&self, event: &str) {
+        self.timeouts.with_label_values(&[event]).inc();
+    }
+}
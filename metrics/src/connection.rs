@@ -0,0 +1,595 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use prometheus::{
+    exponential_buckets, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec, Opts, Registry,
+};
+
+/// The backend label of the connections whose hostname matches no backend
+pub const UNKNOWN_BACKEND: &str = "unknown";
+
+/// The hostname label of the connections closed before their handshake was read
+pub const NO_HOSTNAME: &str = "none";
+
+/// The most requested hostnames, other than the ones of the backends, counted under their own
+/// label, the next ones are counted as `other`, so the clients choosing the hostnames can't grow
+/// the series forever
+const MAX_UNKNOWN_HOSTNAMES: usize = 256;
+
+/// The states of the backends, from their health score
+const HEALTH_STATES: [&str; 3] = ["healthy", "degraded", "down"];
+
+/// The hostname label of the requested hostnames over `MAX_UNKNOWN_HOSTNAMES`
+const OTHER_HOSTNAME: &str = "other";
+
+/// The metrics recorded by the connections before they are relayed
+///
+/// The metrics of the data plane are split by `hostname`, the one requested by the client, and by
+/// `backend`, the hostname of its route. The requested hostnames are chosen by the clients: the
+/// ones of a backend keep their name, at most `MAX_UNKNOWN_HOSTNAMES` others get a label of their
+/// own, e.g. the subdomains of a wildcard backend, and the next ones are counted as `other`.
+///
+/// Properties:
+///
+/// * `connections`: The number of connections closed, by close reason.
+/// * `bytes`: The number of bytes relayed, by direction.
+/// * `session_durations`: How long the relayed connections lasted, in seconds, by backend.
+/// * `session_bytes`: The bytes relayed by the connections in both directions, by backend.
+/// * `health_scores`: The health score of the backends, from 0 to 1, by backend.
+/// * `health_states`: 1 for the state of the backends, 0 for the others, by backend and state.
+/// * `requests`: The number of handshakes read, by next state, so the status pings refreshing the
+///   server lists are told apart from the players joining.
+/// * `handshakes`: The time to read and parse the handshake of the client, in seconds.
+/// * `connects`: The time to open the TCP connection to the backend, in seconds.
+/// * `setups`: The time from the accept until the handshake is forwarded to the backend, in
+///   seconds.
+/// * `malformed`: The number of connections closed because their handshake broke the protocol.
+/// * `bans`: The number of addresses banned for misbehaving, by offense.
+/// * `banned`: The number of connections of banned addresses, dropped right after the accept.
+/// * `authentications`: The number of players authenticated in online mode, by result.
+/// * `cold_logins`: The number of players kicked for logging in without a recent status ping.
+/// * `throttled`: The number of players kicked for logging in again too soon.
+/// * `classified`: The number of connections the classifier judged, by verdict.
+/// * `rate_limited`: The number of connections kicked because the connections to their backend
+///   exceeded their rate, by backend.
+/// * `panicked`: The number of connections whose task panicked.
+/// * `overloaded`: The number of connections shed over the `max_connections` of the proxy, by
+///   policy.
+/// * `unknown_hostnames`: The number of handshakes no backend matches, by requested hostname.
+/// * `unknown_labels`: The requested hostnames, other than the ones of the backends, with a label
+///   of their own.
+#[derive(Debug, Clone)]
+pub struct ConnectionMetrics {
+    connections: IntCounterVec,
+    bytes: IntCounterVec,
+    session_durations: HistogramVec,
+    session_bytes: HistogramVec,
+    health_scores: GaugeVec,
+    health_states: IntGaugeVec,
+    requests: IntCounterVec,
+    handshakes: HistogramVec,
+    connects: HistogramVec,
+    setups: HistogramVec,
+    malformed: IntCounter,
+    bans: IntCounterVec,
+    banned: IntCounter,
+    authentications: IntCounterVec,
+    cold_logins: IntCounter,
+    throttled: IntCounter,
+    classified: IntCounterVec,
+    rate_limited: IntCounterVec,
+    panicked: IntCounter,
+    overloaded: IntCounterVec,
+    unknown_hostnames: IntCounterVec,
+    unknown_labels: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Default for ConnectionMetrics {
+    fn default() -> Self {
+        let histogram = |name: &str, help: &str| {
+            HistogramVec::new(
+                HistogramOpts::new(name, help).buckets(vec![
+                    0.000_5, 0.001, 0.002_5, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+                    5.0, 10.0,
+                ]),
+                &["hostname", "backend"],
+            )
+            .unwrap_or_else(|e| panic!("valid {} metric: {}", name, e))
+        };
+
+        Self {
+            connections: IntCounterVec::new(
+                Opts::new(
+                    "connections_total",
+                    "Number of connections closed, by hostname, backend and close reason",
+                ),
+                &["hostname", "backend", "reason"],
+            )
+            .expect("valid connections_total metric"),
+            bytes: IntCounterVec::new(
+                Opts::new(
+                    "relayed_bytes_total",
+                    "Number of bytes relayed when the connections closed, by hostname, backend and direction",
+                ),
+                &["hostname", "backend", "direction"],
+            )
+            .expect("valid relayed_bytes_total metric"),
+            session_durations: HistogramVec::new(
+                HistogramOpts::new(
+                    "session_duration_seconds",
+                    "How long the relayed connections lasted in seconds, by backend",
+                )
+                .buckets(vec![
+                    1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0,
+                    28800.0, 86400.0,
+                ]),
+                &["backend"],
+            )
+            .expect("valid session_duration_seconds metric"),
+            session_bytes: HistogramVec::new(
+                HistogramOpts::new(
+                    "session_bytes",
+                    "Bytes relayed by the connections in both directions, by backend",
+                )
+                // from 1 KiB to 1 GiB
+                .buckets(exponential_buckets(1024.0, 4.0, 11).expect("valid buckets")),
+                &["backend"],
+            )
+            .expect("valid session_bytes metric"),
+            health_scores: GaugeVec::new(
+                Opts::new(
+                    "backend_health_score",
+                    "Health score of the backends from their connects, from 0 to 1, by backend",
+                ),
+                &["backend"],
+            )
+            .expect("valid backend_health_score metric"),
+            health_states: IntGaugeVec::new(
+                Opts::new(
+                    "backend_health_state",
+                    "1 for the health state of the backends, 0 for the others, by backend and state",
+                ),
+                &["backend", "state"],
+            )
+            .expect("valid backend_health_state metric"),
+            requests: IntCounterVec::new(
+                Opts::new(
+                    "handshakes_total",
+                    "Number of handshakes read, by hostname, backend and next state",
+                ),
+                &["hostname", "backend", "next_state"],
+            )
+            .expect("valid handshakes_total metric"),
+            handshakes: histogram(
+                "connection_handshake_duration_seconds",
+                "Time to read and parse the handshake of the client in seconds, by hostname and backend",
+            ),
+            connects: histogram(
+                "backend_connect_duration_seconds",
+                "Time to open the TCP connection to the backend in seconds, by hostname and backend",
+            ),
+            setups: histogram(
+                "connection_setup_duration_seconds",
+                "Time from the accept until the connection is relayed in seconds, by hostname and backend",
+            ),
+            malformed: IntCounter::new(
+                "malformed_handshakes_total",
+                "Number of connections closed because their handshake broke the protocol",
+            )
+            .expect("valid malformed_handshakes_total metric"),
+            bans: IntCounterVec::new(
+                Opts::new(
+                    "bans_total",
+                    "Number of addresses banned for misbehaving, by offense",
+                ),
+                &["offense"],
+            )
+            .expect("valid bans_total metric"),
+            banned: IntCounter::new(
+                "banned_connections_total",
+                "Number of connections of banned addresses dropped after the accept",
+            )
+            .expect("valid banned_connections_total metric"),
+            authentications: IntCounterVec::new(
+                Opts::new(
+                    "authentications_total",
+                    "Number of players authenticated in online mode, by result",
+                ),
+                &["result"],
+            )
+            .expect("valid authentications_total metric"),
+            cold_logins: IntCounter::new(
+                "cold_logins_total",
+                "Number of players kicked for logging in without a recent status ping",
+            )
+            .expect("valid cold_logins_total metric"),
+            throttled: IntCounter::new(
+                "throttled_logins_total",
+                "Number of players kicked for logging in again too soon",
+            )
+            .expect("valid throttled_logins_total metric"),
+            classified: IntCounterVec::new(
+                Opts::new(
+                    "classified_connections_total",
+                    "Number of connections judged by the classifier, by verdict",
+                ),
+                &["verdict"],
+            )
+            .expect("valid classified_connections_total metric"),
+            rate_limited: IntCounterVec::new(
+                Opts::new(
+                    "rate_limited_connections_total",
+                    "Number of connections kicked because their backend exceeded its connect rate, by hostname and backend",
+                ),
+                &["hostname", "backend"],
+            )
+            .expect("valid rate_limited_connections_total metric"),
+            panicked: IntCounter::new(
+                "connection_panics_total",
+                "Number of connections whose task panicked",
+            )
+            .expect("valid connection_panics_total metric"),
+            overloaded: IntCounterVec::new(
+                Opts::new(
+                    "overloaded_connections_total",
+                    "Number of connections shed over the max connections of the proxy, by policy",
+                ),
+                &["policy"],
+            )
+            .expect("valid overloaded_connections_total metric"),
+            unknown_hostnames: IntCounterVec::new(
+                Opts::new(
+                    "unknown_hostnames_total",
+                    "Number of handshakes no backend matches, by requested hostname",
+                ),
+                &["hostname"],
+            )
+            .expect("valid unknown_hostnames_total metric"),
+            unknown_labels: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+}
+
+impl ConnectionMetrics {
+    /// It registers the metrics into a registry
+    ///
+    /// Arguments:
+    ///
+    /// * `registry`: The registry to register the metrics into
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.connections.clone()))?;
+        registry.register(Box::new(self.bytes.clone()))?;
+        registry.register(Box::new(self.session_durations.clone()))?;
+        registry.register(Box::new(self.session_bytes.clone()))?;
+        registry.register(Box::new(self.health_scores.clone()))?;
+        registry.register(Box::new(self.health_states.clone()))?;
+        registry.register(Box::new(self.requests.clone()))?;
+        registry.register(Box::new(self.handshakes.clone()))?;
+        registry.register(Box::new(self.connects.clone()))?;
+        registry.register(Box::new(self.setups.clone()))?;
+        registry.register(Box::new(self.malformed.clone()))?;
+        registry.register(Box::new(self.bans.clone()))?;
+        registry.register(Box::new(self.banned.clone()))?;
+        registry.register(Box::new(self.authentications.clone()))?;
+        registry.register(Box::new(self.cold_logins.clone()))?;
+        registry.register(Box::new(self.throttled.clone()))?;
+        registry.register(Box::new(self.classified.clone()))?;
+        registry.register(Box::new(self.rate_limited.clone()))?;
+        registry.register(Box::new(self.panicked.clone()))?;
+        registry.register(Box::new(self.overloaded.clone()))?;
+        registry.register(Box::new(self.unknown_hostnames.clone()))?;
+        Ok(())
+    }
+
+    /// It records a closed connection, and the bytes it relayed
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client, none when the handshake wasn't read
+    /// * `backend`: The hostname of the backend, none when no backend matches
+    /// * `reason`: Why the connection was closed, e.g. `closed`, `backend_busy` or `error`
+    /// * `bytes_in`: The bytes relayed from the client to the backend
+    /// * `bytes_out`: The bytes relayed from the backend to the client
+    pub fn closed(
+        &self,
+        hostname: Option<&str>,
+        backend: Option<&str>,
+        reason: &str,
+        bytes_in: u64,
+        bytes_out: u64,
+    ) {
+        let backend = backend.unwrap_or(UNKNOWN_BACKEND);
+        let hostname = match hostname {
+            Some(hostname) => self.hostname_label(hostname, backend),
+            None => NO_HOSTNAME,
+        };
+        self.connections
+            .with_label_values(&[hostname, backend, reason])
+            .inc();
+        if bytes_in > 0 {
+            self.bytes
+                .with_label_values(&[hostname, backend, "in"])
+                .inc_by(bytes_in);
+        }
+        if bytes_out > 0 {
+            self.bytes
+                .with_label_values(&[hostname, backend, "out"])
+                .inc_by(bytes_out);
+        }
+    }
+
+    /// It records a connection relayed to a backend once it is closed
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The hostname of the backend
+    /// * `duration`: How long the connection lasted
+    /// * `bytes`: The bytes relayed in both directions
+    pub fn session(&self, backend: &str, duration: Duration, bytes: u64) {
+        self.session_durations
+            .with_label_values(&[backend])
+            .observe(duration.as_secs_f64());
+        self.session_bytes
+            .with_label_values(&[backend])
+            .observe(bytes as f64);
+    }
+
+    /// It records the health of a backend, after a connect to it
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The hostname of the backend
+    /// * `state`: `healthy`, `degraded` or `down`
+    /// * `score`: The health score, from 0 to 1
+    pub fn health(&self, backend: &str, state: &str, score: f64) {
+        self.health_scores.with_label_values(&[backend]).set(score);
+        for name in HEALTH_STATES {
+            self.health_states
+                .with_label_values(&[backend, name])
+                .set(i64::from(name == state));
+        }
+    }
+
+    /// It records a handshake, and the time to read and parse it
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client
+    /// * `backend`: The hostname of the backend, `UNKNOWN_BACKEND` when none matches
+    /// * `next_state`: `status` for a status ping, `login` or `transfer` for a player joining
+    /// * `duration`: The time to read the handshake
+    pub fn handshake(&self, hostname: &str, backend: &str, next_state: &str, duration: Duration) {
+        let hostname = self.hostname_label(hostname, backend);
+        self.requests
+            .with_label_values(&[hostname, backend, next_state])
+            .inc();
+        self.handshakes
+            .with_label_values(&[hostname, backend])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// It records the time to open the TCP connection to a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client
+    /// * `backend`: The hostname of the backend
+    /// * `duration`: The time to connect
+    pub fn connect(&self, hostname: &str, backend: &str, duration: Duration) {
+        self.connects
+            .with_label_values(&[self.hostname_label(hostname, backend), backend])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// It records the time from the accept until a connection is relayed
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client
+    /// * `backend`: The hostname of the backend
+    /// * `duration`: The time to set up the connection
+    pub fn setup(&self, hostname: &str, backend: &str, duration: Duration) {
+        self.setups
+            .with_label_values(&[self.hostname_label(hostname, backend), backend])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// It records a connection closed because its handshake broke the protocol
+    pub fn malformed_handshake(&self) {
+        self.malformed.inc();
+    }
+
+    /// It records an address banned for misbehaving
+    ///
+    /// Arguments:
+    ///
+    /// * `offense`: The offense of the strike which got the address banned
+    pub fn ban(&self, offense: &str) {
+        self.bans.with_label_values(&[offense]).inc();
+    }
+
+    /// It records a connection of a banned address, dropped after the accept
+    pub fn banned_connection(&self) {
+        self.banned.inc();
+    }
+
+    /// It records a connection whose task panicked
+    pub fn panicked_connection(&self) {
+        self.panicked.inc();
+    }
+
+    /// It records a connection shed over the `max_connections` of the proxy
+    ///
+    /// Arguments:
+    ///
+    /// * `policy`: `refuse` when the connection was reset, `kick` when the client was kicked
+    pub fn overloaded_connection(&self, policy: &str) {
+        self.overloaded.with_label_values(&[policy]).inc();
+    }
+
+    /// It records the authentication of a player in online mode
+    ///
+    /// Arguments:
+    ///
+    /// * `result`: `success`, `failure` when the player couldn't be verified, or `unavailable`
+    ///   when the session server couldn't be reached
+    pub fn authentication(&self, result: &str) {
+        self.authentications.with_label_values(&[result]).inc();
+    }
+
+    /// It records a player kicked for logging in without a recent status ping
+    pub fn cold_login(&self) {
+        self.cold_logins.inc();
+    }
+
+    /// It records a player kicked for logging in again too soon
+    pub fn throttled_login(&self) {
+        self.throttled.inc();
+    }
+
+    /// It records the verdict of the classifier on a connection
+    ///
+    /// Arguments:
+    ///
+    /// * `verdict`: `allow`, `flag` or `reject`.
+    pub fn classified(&self, verdict: &str) {
+        self.classified.with_label_values(&[verdict]).inc();
+    }
+
+    /// It records a connection kicked because the connections to its backend exceeded their rate
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client
+    /// * `backend`: The hostname of the backend
+    pub fn rate_limited(&self, hostname: &str, backend: &str) {
+        self.rate_limited
+            .with_label_values(&[self.hostname_label(hostname, backend), backend])
+            .inc();
+    }
+
+    /// It records a handshake no backend matches
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client, counted as `other` once
+    ///   `MAX_UNKNOWN_HOSTNAMES` other hostnames have a label
+    pub fn unknown_hostname(&self, hostname: &str) {
+        self.unknown_hostnames
+            .with_label_values(&[self.hostname_label(hostname, UNKNOWN_BACKEND)])
+            .inc();
+    }
+
+    /// It returns the label of a requested hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname requested by the client
+    /// * `backend`: The hostname of its backend, `UNKNOWN_BACKEND` when none matches
+    ///
+    /// Returns:
+    ///
+    /// The hostname when it is the one of its backend, or one of the first
+    /// `MAX_UNKNOWN_HOSTNAMES` other hostnames, `other` for the next ones
+    fn hostname_label<'a>(&self, hostname: &'a str, backend: &str) -> &'a str {
+        if hostname == backend {
+            return hostname;
+        }
+        let mut labels = self
+            .unknown_labels
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match labels.contains(hostname)
+            || (labels.len() < MAX_UNKNOWN_HOSTNAMES && labels.insert(hostname.to_string()))
+        {
+            true => hostname,
+            false => OTHER_HOSTNAME,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_limits_the_labels_of_the_requested_hostnames() {
+        let metrics = ConnectionMetrics::default();
+        for i in 0..MAX_UNKNOWN_HOSTNAMES {
+            let hostname = format!("player{}.example.com", i);
+            assert_eq!(metrics.hostname_label(&hostname, UNKNOWN_BACKEND), hostname);
+        }
+
+        assert_eq!(
+            metrics.hostname_label("player0.example.com", "lobby.example.com"),
+            "player0.example.com"
+        );
+        assert_eq!(
+            metrics.hostname_label("bot.example.com", UNKNOWN_BACKEND),
+            OTHER_HOSTNAME
+        );
+        // the hostnames of the backends always keep their label
+        assert_eq!(
+            metrics.hostname_label("lobby.example.com", "lobby.example.com"),
+            "lobby.example.com"
+        );
+
+        metrics.closed(
+            Some("lobby.example.com"),
+            Some("lobby.example.com"),
+            "closed",
+            11,
+            22,
+        );
+        metrics.closed(None, None, "malformed_handshake", 0, 0);
+        metrics.handshake(
+            "lobby.example.com",
+            "lobby.example.com",
+            "status",
+            Duration::from_millis(1),
+        );
+        metrics.session("lobby.example.com", Duration::from_secs(90), 4096);
+        let sessions = metrics
+            .session_bytes
+            .with_label_values(&["lobby.example.com"]);
+        assert_eq!(sessions.get_sample_count(), 1);
+        assert_eq!(sessions.get_sample_sum(), 4096.0);
+        assert_eq!(
+            metrics
+                .requests
+                .with_label_values(&["lobby.example.com", "lobby.example.com", "status"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .connections
+                .with_label_values(&["lobby.example.com", "lobby.example.com", "closed"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .bytes
+                .with_label_values(&["lobby.example.com", "lobby.example.com", "out"])
+                .get(),
+            22
+        );
+        assert_eq!(
+            metrics
+                .connections
+                .with_label_values(&[NO_HOSTNAME, UNKNOWN_BACKEND, "malformed_handshake"])
+                .get(),
+            1
+        );
+    }
+}
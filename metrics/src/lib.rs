@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use prometheus::{proto::MetricFamily, Encoder, Registry, TextEncoder};
+
+use crate::{channel::ChannelMetrics, connection::ConnectionMetrics, storage::StorageMetrics};
+
+pub mod channel;
+pub mod connection;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+pub mod storage;
+
+#[cfg(feature = "server")]
+pub use server::{bind, serve, serve_listener};
+
+/// The metrics of the proxy, exported in the Prometheus text format
+///
+/// Every subsystem records into its own set of metrics, all registered into the
+/// same registry.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    storage: StorageMetrics,
+    channels: ChannelMetrics,
+    connections: ConnectionMetrics,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new().expect("metrics are registered into a fresh registry")
+    }
+}
+
+impl Metrics {
+    /// Creates a new instance of the `Metrics` struct with all the metrics registered
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub fn new() -> Result<Self> {
+        Self::with_labels(HashMap::new())
+    }
+
+    /// Creates a new instance of the `Metrics` struct, with labels added to every metric
+    ///
+    /// Arguments:
+    ///
+    /// * `labels`: The constant labels of every metric, e.g. the pod of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub fn with_labels(labels: HashMap<String, String>) -> Result<Self> {
+        let registry =
+            Registry::new_custom(None, Some(labels).filter(|labels| !labels.is_empty()))?;
+
+        let storage = StorageMetrics::default();
+        storage.register(&registry)?;
+
+        let channels = ChannelMetrics::default();
+        channels.register(&registry)?;
+
+        let connections = ConnectionMetrics::default();
+        connections.register(&registry)?;
+
+        Ok(Self {
+            registry,
+            storage,
+            channels,
+            connections,
+        })
+    }
+
+    /// It returns the metrics recorded by the storage
+    ///
+    /// Returns:
+    ///
+    /// A StorageMetrics
+    pub fn storage(&self) -> StorageMetrics {
+        self.storage.clone()
+    }
+
+    /// It returns the metrics recorded by the internal channels
+    ///
+    /// Returns:
+    ///
+    /// A ChannelMetrics
+    pub fn channels(&self) -> ChannelMetrics {
+        self.channels.clone()
+    }
+
+    /// It returns the metrics recorded by the connections
+    ///
+    /// Returns:
+    ///
+    /// A ConnectionMetrics
+    pub fn connections(&self) -> ConnectionMetrics {
+        self.connections.clone()
+    }
+
+    /// It gathers all the metrics, for the exporters
+    ///
+    /// Returns:
+    ///
+    /// A Vec<MetricFamily>
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+
+    /// It encodes all the metrics in the Prometheus text format
+    ///
+    /// Returns:
+    ///
+    /// A Result<String>
+    pub fn encode(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8(buffer)?)
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use config::OtlpConfig;
+use hyper::{client::HttpConnector, header::CONTENT_TYPE, Body, Client, Method, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use prometheus::proto::{LabelPair, MetricFamily, MetricType};
+use serde_json::{json, Value};
+use tokio::time::interval;
+
+use crate::Metrics;
+
+/// The name of the instrumentation scope of the metrics
+const SCOPE: &str = "kubecraft-proxy";
+
+/// The cumulative aggregation temporality: the values are totals since the start time
+const CUMULATIVE: u8 = 2;
+
+/// It turns the metrics of the registry into the body of an OTLP export request
+///
+/// The counters and the histograms are sent as cumulative sums and histograms since the proxy
+/// started, so the collector needs no state to accept them, the gauges as their value. The
+/// 64-bit integers are encoded as strings, as the JSON encoding of OTLP requires.
+///
+/// Arguments:
+///
+/// * `families`: The metrics gathered from the registry.
+/// * `service_name`: The `service.name` attribute of the resource.
+/// * `start`: When the proxy started, in nanoseconds since the Unix epoch.
+/// * `now`: When the metrics were gathered, in nanoseconds since the Unix epoch.
+///
+/// Returns:
+///
+/// The `ExportMetricsServiceRequest`, in JSON
+pub fn payload(families: &[MetricFamily], service_name: &str, start: u128, now: u128) -> Value {
+    let (start, now) = (start.to_string(), now.to_string());
+    let metrics: Vec<Value> = families
+        .iter()
+        .filter_map(|family| {
+            let points = family.get_metric().iter().map(|metric| {
+                let mut point = json!({
+                    "attributes": attributes(metric.get_label()),
+                    "startTimeUnixNano": start,
+                    "timeUnixNano": now,
+                });
+                match family.get_field_type() {
+                    MetricType::COUNTER => {
+                        point["asDouble"] = json!(metric.get_counter().get_value())
+                    }
+                    MetricType::GAUGE => point["asDouble"] = json!(metric.get_gauge().get_value()),
+                    _ => {
+                        let histogram = metric.get_histogram();
+                        // the Prometheus buckets count the samples below their bound, the OTLP ones
+                        // the samples between their bound and the previous one
+                        let mut counts = Vec::new();
+                        let mut below = 0;
+                        for bucket in histogram.get_bucket() {
+                            counts.push((bucket.get_cumulative_count() - below).to_string());
+                            below = bucket.get_cumulative_count();
+                        }
+                        counts.push((histogram.get_sample_count() - below).to_string());
+                        let bounds: Vec<f64> = histogram
+                            .get_bucket()
+                            .iter()
+                            .map(|bucket| bucket.get_upper_bound())
+                            .collect();
+
+                        point["count"] = json!(histogram.get_sample_count().to_string());
+                        point["sum"] = json!(histogram.get_sample_sum());
+                        point["bucketCounts"] = json!(counts);
+                        point["explicitBounds"] = json!(bounds);
+                    }
+                }
+                point
+            });
+            let points: Vec<Value> = points.collect();
+
+            let data = match family.get_field_type() {
+                MetricType::COUNTER => json!({
+                    "sum": {
+                        "dataPoints": points,
+                        "aggregationTemporality": CUMULATIVE,
+                        "isMonotonic": true,
+                    }
+                }),
+                MetricType::GAUGE => json!({ "gauge": { "dataPoints": points } }),
+                MetricType::HISTOGRAM => json!({
+                    "histogram": {
+                        "dataPoints": points,
+                        "aggregationTemporality": CUMULATIVE,
+                    }
+                }),
+                MetricType::SUMMARY | MetricType::UNTYPED => return None,
+            };
+            let mut metric = json!({
+                "name": family.get_name(),
+                "description": family.get_help(),
+            });
+            metric.as_object_mut()?.extend(data.as_object()?.clone());
+            Some(metric)
+        })
+        .collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": { "stringValue": service_name },
+                }],
+            },
+            "scopeMetrics": [{
+                "scope": { "name": SCOPE },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+/// It turns the labels of a metric into OTLP attributes
+fn attributes(labels: &[LabelPair]) -> Vec<Value> {
+    labels
+        .iter()
+        .map(|label| {
+            json!({
+                "key": label.get_name(),
+                "value": { "stringValue": label.get_value() },
+            })
+        })
+        .collect()
+}
+
+/// It returns a time in nanoseconds since the Unix epoch
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default()
+}
+
+/// It pushes the metrics to an OpenTelemetry collector at every interval
+///
+/// It returns right away when no collector is configured. A push failing, e.g. because the
+/// collector can't be reached, is logged and tried again at the next interval.
+///
+/// Arguments:
+///
+/// * `config`: The collector, if any.
+/// * `metrics`: The metrics to push.
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn push(config: Option<OtlpConfig>, metrics: Arc<Metrics>) -> Result<()> {
+    let config = match config {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    log::info!(
+        "Pushing metrics to the OTLP collector at {}",
+        config.endpoint
+    );
+    let https = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = Client::builder().build(https);
+
+    let start = unix_nanos(SystemTime::now());
+    let mut ticks = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticks.tick().await;
+
+        let body = payload(
+            &metrics.gather(),
+            &config.service_name,
+            start,
+            unix_nanos(SystemTime::now()),
+        );
+        if let Err(e) = send(&client, &config, body).await {
+            log::warn!("failed to push the metrics to {}: {}", config.endpoint, e);
+        }
+    }
+}
+
+/// It posts an export request to the collector
+async fn send(
+    client: &Client<HttpsConnector<HttpConnector>>,
+    config: &OtlpConfig,
+    body: Value,
+) -> Result<()> {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(&config.endpoint)
+        .header(CONTENT_TYPE, "application/json");
+    for (name, value) in &config.headers {
+        request = request.header(name, value.resolve()?);
+    }
+    let request = request.body(Body::from(body.to_string()))?;
+
+    let response = client.request(request).await?;
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(anyhow!("the collector answered {}", response.status())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+
+    use super::*;
+
+    #[test]
+    fn it_encodes_the_metrics_as_an_export_request() {
+        let registry = Registry::new();
+        let lookups =
+            IntCounterVec::new(Opts::new("lookups_total", "lookups"), &["result"]).unwrap();
+        let connects = HistogramVec::new(
+            HistogramOpts::new("connect_seconds", "connects").buckets(vec![0.1, 1.0]),
+            &["backend"],
+        )
+        .unwrap();
+        registry.register(Box::new(lookups.clone())).unwrap();
+        registry.register(Box::new(connects.clone())).unwrap();
+
+        lookups.with_label_values(&["hit"]).inc_by(3);
+        let connect = connects.with_label_values(&["lobby.example.com"]);
+        connect.observe(0.05);
+        connect.observe(0.5);
+        connect.observe(2.0);
+
+        let payload = payload(&registry.gather(), "proxy", 1, 2);
+        let scope = &payload["resourceMetrics"][0];
+        assert_eq!(
+            scope["resource"]["attributes"][0]["value"]["stringValue"],
+            "proxy"
+        );
+
+        let metrics = &scope["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "connect_seconds");
+        assert_eq!(
+            metrics[0]["histogram"]["dataPoints"][0],
+            json!({
+                "attributes": [{
+                    "key": "backend",
+                    "value": { "stringValue": "lobby.example.com" },
+                }],
+                "startTimeUnixNano": "1",
+                "timeUnixNano": "2",
+                "count": "3",
+                "sum": 2.55,
+                "bucketCounts": ["1", "1", "1"],
+                "explicitBounds": [0.1, 1.0],
+            })
+        );
+        assert_eq!(metrics[1]["name"], "lookups_total");
+        assert_eq!(metrics[1]["sum"]["isMonotonic"], true);
+        assert_eq!(metrics[1]["sum"]["dataPoints"][0]["asDouble"], 3.0);
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+
+use crate::Metrics;
+
+/// It serves the metrics over HTTP on `/metrics`
+///
+/// Arguments:
+///
+/// * `addr`: The address to listen on
+/// * `metrics`: The metrics to serve
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn serve(addr: String, metrics: Arc<Metrics>) -> Result<()> {
+    serve_listener(bind(&addr)?, metrics).await
+}
+
+/// It binds the address of the metrics server, before it serves
+///
+/// Arguments:
+///
+/// * `addr`: The address to listen on, its port chosen by the system when 0
+///
+/// Returns:
+///
+/// A Result<TcpListener>
+pub fn bind(addr: &str) -> Result<TcpListener> {
+    let addr = SocketAddr::from_str(addr).map_err(|e| anyhow!("failed to parse address: {}", e))?;
+
+    TcpListener::bind(addr).map_err(|e| anyhow!("failed to bind metrics server to {}: {}", addr, e))
+}
+
+/// It serves the metrics on a bound listener, see `serve`
+///
+/// Arguments:
+///
+/// * `listener`: The listener returned by `bind`
+/// * `metrics`: The metrics to serve
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn serve_listener(listener: TcpListener, metrics: Arc<Metrics>) -> Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let metrics = metrics.clone();
+                async move { Ok::<_, Infallible>(handle(request, &metrics)) }
+            }))
+        }
+    });
+
+    Server::from_tcp(listener)?
+        .serve(make_service)
+        .await
+        .map_err(|e| anyhow!("metrics server exited with error {}", e))
+}
+
+/// It answers an HTTP request made to the metrics server
+///
+/// Arguments:
+///
+/// * `request`: The HTTP request
+/// * `metrics`: The metrics to serve
+///
+/// Returns:
+///
+/// A Response<Body>
+fn handle(request: Request<Body>, metrics: &Metrics) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+
+    match metrics.encode() {
+        Ok(body) => {
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+            );
+            *response.body_mut() = Body::from(body);
+        }
+        Err(e) => {
+            log::error!("failed to encode metrics: {}", e);
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+
+    response
+}
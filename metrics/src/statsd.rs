@@ -0,0 +1,227 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{StatsdConfig, StatsdFormat};
+use prometheus::proto::{MetricFamily, MetricType};
+use tokio::{net::UdpSocket, time::interval};
+
+use crate::Metrics;
+
+/// The maximum size of a datagram, below the MTU of most networks
+const MAX_DATAGRAM: usize = 1432;
+
+/// It turns the metrics of the registry into StatsD lines
+///
+/// The counters, and the count and sum of the histograms, are sent as the increase since the
+/// previous push, the gauges as their value.
+///
+/// Arguments:
+///
+/// * `families`: The metrics gathered from the registry.
+/// * `prefix`: The prefix of the metric names, none when empty.
+/// * `format`: Whether the labels are sent as DogStatsD tags or appended to the names.
+/// * `previous`: The values of the counters at the previous push, updated in place.
+///
+/// Returns:
+///
+/// The lines to send, one per metric
+pub fn lines(
+    families: &[MetricFamily],
+    prefix: &str,
+    format: StatsdFormat,
+    previous: &mut HashMap<String, f64>,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for family in families {
+        let name = match prefix.is_empty() {
+            true => family.get_name().to_string(),
+            false => format!("{}.{}", prefix, family.get_name()),
+        };
+
+        for metric in family.get_metric() {
+            let labels: Vec<(&str, &str)> = metric
+                .get_label()
+                .iter()
+                .map(|label| (label.get_name(), label.get_value()))
+                .collect();
+
+            let mut line = |suffix: &str, value: f64, kind: &str| {
+                let name = format!("{}{}", name, suffix);
+                let line = match format {
+                    StatsdFormat::Statsd => {
+                        let mut name = name;
+                        for (_, value) in &labels {
+                            name.push('.');
+                            name.push_str(&sanitize(value));
+                        }
+                        format!("{}:{}|{}", name, value, kind)
+                    }
+                    StatsdFormat::Dogstatsd if labels.is_empty() => {
+                        format!("{}:{}|{}", name, value, kind)
+                    }
+                    StatsdFormat::Dogstatsd => {
+                        let tags: Vec<String> = labels
+                            .iter()
+                            .map(|(key, value)| format!("{}:{}", key, sanitize(value)))
+                            .collect();
+                        format!("{}:{}|{}|#{}", name, value, kind, tags.join(","))
+                    }
+                };
+                lines.push(line);
+            };
+            let mut delta = |suffix: &str, value: f64| {
+                let key = format!("{}{}{:?}", name, suffix, labels);
+                let last = previous.insert(key, value).unwrap_or_default();
+                // a counter going backwards was reset, e.g. by a restart
+                let increase = match value >= last {
+                    true => value - last,
+                    false => value,
+                };
+                (increase > 0.0).then_some(increase)
+            };
+
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    if let Some(increase) = delta("", metric.get_counter().get_value()) {
+                        line("", increase, "c");
+                    }
+                }
+                MetricType::GAUGE => line("", metric.get_gauge().get_value(), "g"),
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let count = delta(".count", histogram.get_sample_count() as f64);
+                    let sum = delta(".sum", histogram.get_sample_sum());
+                    if let Some(count) = count {
+                        line(".count", count, "c");
+                    }
+                    if let Some(sum) = sum {
+                        line(".sum", sum, "c");
+                    }
+                }
+                MetricType::SUMMARY | MetricType::UNTYPED => {}
+            }
+        }
+    }
+
+    lines
+}
+
+/// It replaces the characters of a label value which have a meaning in the StatsD protocol
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | ',' | '.' | ' ' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// It pushes the metrics to a StatsD agent at every interval
+///
+/// It returns right away when no agent is configured. A push failing, e.g. because the agent
+/// can't be resolved, is logged and tried again at the next interval.
+///
+/// Arguments:
+///
+/// * `config`: The StatsD agent, if any.
+/// * `metrics`: The metrics to push.
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn push(config: Option<StatsdConfig>, metrics: Arc<Metrics>) -> Result<()> {
+    let config = match config {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    log::info!("Pushing metrics to the StatsD agent at {}", config.addr);
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| anyhow!("failed to bind the StatsD socket: {}", e))?;
+
+    let mut previous = HashMap::new();
+    let mut ticks = interval(Duration::from_secs(config.interval_secs));
+    loop {
+        ticks.tick().await;
+
+        let lines = lines(
+            &metrics.gather(),
+            &config.prefix,
+            config.format,
+            &mut previous,
+        );
+        if let Err(e) = send(&socket, &config.addr, &lines).await {
+            log::warn!("failed to push the metrics to {}: {}", config.addr, e);
+        }
+    }
+}
+
+/// It sends the lines to the agent, packed into as few datagrams as possible
+async fn send(socket: &UdpSocket, addr: &str, lines: &[String]) -> Result<()> {
+    let mut datagram = String::new();
+    for line in lines {
+        if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM {
+            socket.send_to(datagram.as_bytes(), addr).await?;
+            datagram.clear();
+        }
+        if !datagram.is_empty() {
+            datagram.push('\n');
+        }
+        datagram.push_str(line);
+    }
+    if !datagram.is_empty() {
+        socket.send_to(datagram.as_bytes(), addr).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry};
+
+    use super::*;
+
+    #[test]
+    fn it_pushes_the_increase_of_the_counters() {
+        let registry = Registry::new();
+        let lookups =
+            IntCounterVec::new(Opts::new("lookups_total", "lookups"), &["result"]).unwrap();
+        let sessions = IntGauge::new("sessions", "sessions").unwrap();
+        let connects = HistogramVec::new(
+            HistogramOpts::new("connect_seconds", "connects"),
+            &["backend"],
+        )
+        .unwrap();
+        registry.register(Box::new(lookups.clone())).unwrap();
+        registry.register(Box::new(sessions.clone())).unwrap();
+        registry.register(Box::new(connects.clone())).unwrap();
+
+        lookups.with_label_values(&["hit"]).inc_by(3);
+        sessions.set(2);
+        connects
+            .with_label_values(&["lobby.example.com"])
+            .observe(0.5);
+
+        let mut previous = HashMap::new();
+        let mut push = |format| lines(&registry.gather(), "proxy", format, &mut previous);
+        assert_eq!(
+            push(StatsdFormat::Dogstatsd),
+            vec![
+                "proxy.connect_seconds.count:1|c|#backend:lobby_example_com",
+                "proxy.connect_seconds.sum:0.5|c|#backend:lobby_example_com",
+                "proxy.lookups_total:3|c|#result:hit",
+                "proxy.sessions:2|g",
+            ]
+        );
+
+        lookups.with_label_values(&["hit"]).inc();
+        assert_eq!(
+            push(StatsdFormat::Statsd),
+            vec!["proxy.lookups_total.hit:1|c", "proxy.sessions:2|g"]
+        );
+    }
+}
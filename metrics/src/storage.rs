@@ -0,0 +1,97 @@
+use anyhow::Result;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+
+/// The metrics recorded by the storage
+///
+/// Properties:
+///
+/// * `operations`: The number of operations, by operation and result (`ok` or `error`).
+/// * `durations`: The duration of the operations in seconds, by operation.
+/// * `lookups`: The number of backend lookups, by result (`hit` or `miss`).
+#[derive(Debug, Clone)]
+pub struct StorageMetrics {
+    operations: IntCounterVec,
+    durations: HistogramVec,
+    lookups: IntCounterVec,
+}
+
+impl Default for StorageMetrics {
+    fn default() -> Self {
+        Self {
+            operations: IntCounterVec::new(
+                Opts::new(
+                    "storage_operations_total",
+                    "Number of storage operations by operation and result",
+                ),
+                &["operation", "result"],
+            )
+            .expect("valid storage_operations_total metric"),
+            durations: HistogramVec::new(
+                HistogramOpts::new(
+                    "storage_operation_duration_seconds",
+                    "Duration of the storage operations in seconds",
+                )
+                .buckets(vec![
+                    0.000_01, 0.000_05, 0.000_1, 0.000_5, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5,
+                ]),
+                &["operation"],
+            )
+            .expect("valid storage_operation_duration_seconds metric"),
+            lookups: IntCounterVec::new(
+                Opts::new(
+                    "storage_lookups_total",
+                    "Number of backend lookups by result (hit or miss)",
+                ),
+                &["result"],
+            )
+            .expect("valid storage_lookups_total metric"),
+        }
+    }
+}
+
+impl StorageMetrics {
+    /// It registers the metrics into a registry
+    ///
+    /// Arguments:
+    ///
+    /// * `registry`: The registry to register the metrics into
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub fn register(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.operations.clone()))?;
+        registry.register(Box::new(self.durations.clone()))?;
+        registry.register(Box::new(self.lookups.clone()))?;
+        Ok(())
+    }
+
+    /// It records an operation, its duration and whether it succeeded
+    ///
+    /// Arguments:
+    ///
+    /// * `operation`: The name of the operation (`get`, `put`, `delete`, `list`, ...)
+    /// * `seconds`: The duration of the operation in seconds
+    /// * `ok`: Whether the operation succeeded
+    pub fn observe(&self, operation: &str, seconds: f64, ok: bool) {
+        let result = if ok { "ok" } else { "error" };
+
+        self.operations
+            .with_label_values(&[operation, result])
+            .inc();
+        self.durations
+            .with_label_values(&[operation])
+            .observe(seconds);
+    }
+
+    /// It records the result of a backend lookup
+    ///
+    /// Arguments:
+    ///
+    /// * `hit`: Whether a backend was found
+    pub fn lookup(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+
+        self.lookups.with_label_values(&[result]).inc();
+    }
+}
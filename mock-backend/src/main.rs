@@ -0,0 +1,73 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use tokio::net::TcpListener;
+
+use crate::server::MockServer;
+
+mod server;
+
+/// A fake Minecraft server answering the status pings and the logins, to try the proxy without a
+/// real server
+#[derive(Debug, Parser)]
+#[command(name = "mock-backend", bin_name = "mock-backend", version, about)]
+pub struct Cli {
+    /// The address accepting the connections of the proxy
+    #[arg(long, env = "MOCK_BIND", default_value = "0.0.0.0:25566")]
+    pub bind: SocketAddr,
+
+    /// The description of the server in the server list
+    #[arg(long, env = "MOCK_MOTD", default_value = "A mock Minecraft server")]
+    pub motd: String,
+
+    /// The name of the version shown in the server list
+    #[arg(long, env = "MOCK_VERSION_NAME", default_value = "mock")]
+    pub version_name: String,
+
+    /// The protocol version answered, the one of the client when unset
+    #[arg(long, env = "MOCK_PROTOCOL")]
+    pub protocol: Option<i32>,
+
+    /// The maximum number of players shown in the server list
+    #[arg(long, env = "MOCK_MAX_PLAYERS", default_value_t = 20)]
+    pub max_players: u32,
+
+    /// The number of online players shown in the server list
+    #[arg(long, env = "MOCK_ONLINE_PLAYERS", default_value_t = 0)]
+    pub online_players: u32,
+
+    /// The message the players logging in are disconnected with, `{username}` and `{hostname}`
+    /// are replaced
+    #[arg(
+        long,
+        env = "MOCK_LOGIN_MESSAGE",
+        default_value = "Logged in to the mock backend as {username} through {hostname}"
+    )]
+    pub login_message: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    let listener = TcpListener::bind(cli.bind)
+        .await
+        .map_err(|e| anyhow!("failed to bind {}: {}", cli.bind, e))?;
+    log::info!("mock backend listening on {}", cli.bind);
+
+    let server = Arc::new(MockServer::from(cli));
+    loop {
+        let (socket, client) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(socket).await {
+                log::warn!("connection of {} failed: {:#}", client, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::ErrorKind;
+
+use anyhow::{anyhow, Result};
+use protocol::{
+    packets::serverbound::{
+        handshake::{Handshake, NextState},
+        login_start::LoginStart,
+    },
+    read_packet, read_var_int, write_packet, write_string, write_var_int, ProtocolError,
+};
+use serde_json::json;
+use tokio::net::TcpStream;
+
+use crate::Cli;
+
+/// The maximum length of the status packets, in bytes
+const MAX_STATUS_PACKET_SIZE: usize = 64;
+
+/// What the mock server answers, from the command line
+///
+/// Properties:
+///
+/// * `motd`: The description of the server in the server list.
+/// * `version_name`: The name of the version shown in the server list.
+/// * `protocol`: The protocol version answered, the one of the client when none.
+/// * `max_players`: The maximum number of players shown in the server list.
+/// * `online_players`: The number of online players shown in the server list.
+/// * `login_message`: The message the players logging in are disconnected with.
+#[derive(Debug)]
+pub struct MockServer {
+    motd: String,
+    version_name: String,
+    protocol: Option<i32>,
+    max_players: u32,
+    online_players: u32,
+    login_message: String,
+}
+
+impl From<Cli> for MockServer {
+    fn from(cli: Cli) -> Self {
+        Self {
+            motd: cli.motd,
+            version_name: cli.version_name,
+            protocol: cli.protocol,
+            max_players: cli.max_players,
+            online_players: cli.online_players,
+            login_message: cli.login_message,
+        }
+    }
+}
+
+impl MockServer {
+    /// It serves a connection, from its handshake until it is closed
+    ///
+    /// A status ping gets the status and its pong, a player logging in is disconnected with the
+    /// login message once its login start is read.
+    ///
+    /// Arguments:
+    ///
+    /// * `socket`: The connection of the proxy or of the client.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn serve(&self, mut socket: TcpStream) -> Result<()> {
+        let handshake = Handshake::read(&mut socket).await?;
+        log::info!(
+            "handshake for {}:{} with protocol {}, next state {:?}",
+            handshake.hostname(),
+            handshake.port(),
+            handshake.version(),
+            handshake.next_state()
+        );
+
+        match handshake.next_state() {
+            NextState::Status => self.status(&mut socket, handshake.version()).await,
+            NextState::Login | NextState::Transfer => {
+                let login_start = LoginStart::read(&mut socket).await?;
+                log::info!("{} logged in", login_start.username());
+
+                let message = self
+                    .login_message
+                    .replace("{username}", login_start.username())
+                    .replace("{hostname}", &handshake.hostname());
+                write_string_packet(&mut socket, &json!({ "text": message }).to_string()).await
+            }
+        }
+    }
+
+    /// It answers the status request and the ping of a client
+    ///
+    /// Arguments:
+    ///
+    /// * `socket`: The connection, right after its handshake.
+    /// * `version`: The protocol version of the handshake.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    async fn status(&self, socket: &mut TcpStream, version: i32) -> Result<()> {
+        loop {
+            let mut packet = match read_packet(socket, MAX_STATUS_PACKET_SIZE).await {
+                Ok(packet) => packet,
+                // the client may close the connection once it got the status, without a ping
+                Err(ProtocolError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            match read_var_int(&mut packet).await? {
+                0 => {
+                    let status = json!({
+                        "version": {
+                            "name": self.version_name,
+                            "protocol": self.protocol.unwrap_or(version),
+                        },
+                        "players": {
+                            "max": self.max_players,
+                            "online": self.online_players,
+                            "sample": [],
+                        },
+                        "description": { "text": self.motd },
+                    });
+                    write_string_packet(socket, &status.to_string()).await?;
+                }
+                // the pong carries the payload of the ping back
+                1 => return Ok(write_packet(socket, packet.get_ref()).await?),
+                id => return Err(anyhow!("unexpected status packet id: {}", id)),
+            }
+        }
+    }
+}
+
+/// It writes a packet of id 0 whose only field is a string, as the status and the disconnect are
+///
+/// Arguments:
+///
+/// * `socket`: The connection to write to.
+/// * `string`: The JSON of the packet.
+///
+/// Returns:
+///
+/// A Result<()>
+async fn write_string_packet(socket: &mut TcpStream, string: &str) -> Result<()> {
+    let mut data = Vec::new();
+    write_var_int(&mut data, 0).await?;
+    write_string(&mut data, string).await?;
+    Ok(write_packet(socket, &data).await?)
+}
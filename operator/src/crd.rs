@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
+use kube::{CustomResource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use shared::models::backend::Backend;
+
+use crate::routes;
+
+/// A Minecraft server routed by the proxy, served by a Kubernetes Service
+#[derive(CustomResource, Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "kubecraft.cloud",
+    version = "v1alpha1",
+    kind = "MinecraftServer",
+    namespaced,
+    status = "MinecraftServerStatus",
+    shortname = "mcs",
+    printcolumn = r#"{"name":"Hostname","type":"string","jsonPath":".spec.hostname"}"#,
+    printcolumn = r#"{"name":"Ready","type":"string","jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct MinecraftServerSpec {
+    /// The hostname the players connect to
+    pub hostname: String,
+    /// The Service of the Minecraft server, in the namespace of the resource
+    pub service: ServiceRef,
+    /// The relative share of the connections the backend receives, 1 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    /// The maximum number of connections to the backend, unlimited by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    /// How the real address of the client is forwarded: `none`, `legacy` or `velocity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarding_mode: Option<String>,
+    /// The message of the day to answer status pings with instead of the backend's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motd: Option<String>,
+    /// Free-form key/value pairs used to select and group backends
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// The workload scaled down to zero when nobody plays, and back up when a player joins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<ScaleSpec>,
+}
+
+/// The workload running a Minecraft server, in the namespace of the resource
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScaleSpec {
+    /// The kind of the workload, `Deployment` or `StatefulSet`
+    pub kind: WorkloadKind,
+    /// The name of the workload
+    pub name: String,
+    /// How long the server runs without any player before it is scaled down, 600 by default
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+}
+
+/// The kinds of workloads that can be scaled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum WorkloadKind {
+    Deployment,
+    StatefulSet,
+}
+
+fn default_idle_timeout() -> u64 {
+    600
+}
+
+/// The Service a `MinecraftServer` is routed to
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceRef {
+    /// The name of the Service
+    pub name: String,
+    /// The port of the Service, 25565 by default
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_port() -> u16 {
+    25565
+}
+
+/// The state of a `MinecraftServer`, written back by the operator
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MinecraftServerStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+impl MinecraftServer {
+    /// It returns the `<namespace>/<name>` identifying the resource in the backend labels
+    ///
+    /// Returns:
+    ///
+    /// A String
+    pub fn source(&self) -> String {
+        format!(
+            "{}/{}",
+            self.namespace().unwrap_or_default(),
+            self.name_any()
+        )
+    }
+
+    /// It converts the resource into a backend routed to the address of its Service
+    ///
+    /// Arguments:
+    ///
+    /// * `redirect_ip`: The cluster IP of the Service.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Backend>
+    pub fn backend(&self, redirect_ip: String) -> Result<Backend> {
+        let spec = &self.spec;
+        if spec.hostname.is_empty() {
+            return Err(anyhow!("spec.hostname must not be empty"));
+        }
+        if spec.service.port == 0 {
+            return Err(anyhow!("spec.service.port must be between 1 and 65535"));
+        }
+
+        let forwarding_mode = spec
+            .forwarding_mode
+            .as_deref()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|e| anyhow!("spec.forwardingMode: {}", e))?;
+
+        let mut backend = Backend {
+            hostname: spec.hostname.to_lowercase(),
+            redirect_ip,
+            redirect_port: spec.service.port,
+            weight: spec.weight.unwrap_or(1),
+            max_connections: spec.max_connections.unwrap_or_default(),
+            forwarding_mode,
+            motd: spec.motd.clone(),
+            labels: spec.labels.clone(),
+            ..Default::default()
+        };
+        routes::own(&mut backend, &self.source());
+
+        Ok(backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kube::api::ObjectMeta;
+    use shared::models::forwarding::ForwardingMode;
+
+    use crate::routes::is_owned_by;
+
+    use super::*;
+
+    fn server(hostname: &str, forwarding_mode: Option<&str>) -> MinecraftServer {
+        MinecraftServer {
+            metadata: ObjectMeta {
+                name: Some("lobby".to_string()),
+                namespace: Some("games".to_string()),
+                ..Default::default()
+            },
+            spec: MinecraftServerSpec {
+                hostname: hostname.to_string(),
+                service: ServiceRef {
+                    name: "lobby".to_string(),
+                    port: 25566,
+                },
+                weight: None,
+                max_connections: Some(20),
+                forwarding_mode: forwarding_mode.map(str::to_string),
+                motd: None,
+                labels: BTreeMap::from([("tenant".to_string(), "acme".to_string())]),
+                scale: None,
+            },
+            status: None,
+        }
+    }
+
+    #[test]
+    fn it_converts_a_server_into_an_owned_backend() {
+        let backend = server("Lobby.Example.com", Some("velocity"))
+            .backend("10.0.0.12".to_string())
+            .unwrap();
+
+        assert_eq!(backend.hostname(), "lobby.example.com");
+        assert_eq!(backend.addr(), "10.0.0.12:25566");
+        assert_eq!(backend.weight(), 1);
+        assert_eq!(backend.max_connections(), Some(20));
+        assert_eq!(backend.forwarding_mode(), ForwardingMode::Velocity);
+        assert_eq!(backend.labels().get("tenant").unwrap(), "acme");
+        assert!(is_owned_by(&backend, "games/lobby"));
+        assert!(!is_owned_by(&backend, "games/survival"));
+        assert!(!backend.read_only());
+    }
+
+    #[test]
+    fn it_rejects_an_invalid_spec() {
+        assert!(server("", None).backend("10.0.0.12".to_string()).is_err());
+        assert!(server("lobby.example.com", Some("waterfall"))
+            .backend("10.0.0.12".to_string())
+            .is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
+
+use anyhow::Result;
+use futures::StreamExt;
+use k8s_openapi::api::networking::v1::Ingress;
+use kube::{
+    api::{ApiResource, DynamicObject, GroupVersionKind},
+    runtime::{watcher, WatchStreamExt},
+    Api, Client, Resource, ResourceExt,
+};
+use serde::de::DeserializeOwned;
+use shared::models::backend::Backend;
+
+use crate::{
+    routes::{self, Routes},
+    services,
+};
+
+/// The annotation opting an Ingress or an HTTPRoute in the routing table of the proxy
+///
+/// Its value is the `<service>[:<port>]` serving the Minecraft server, or `true` to use the
+/// backend of the object itself.
+pub const BACKEND_ANNOTATION: &str = "kubecraft.cloud/backend";
+
+/// The Service a discovered hostname is routed to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Target {
+    pub service: String,
+    pub port: u16,
+}
+
+/// The hostnames read from an object and their targets, or why the object is ignored
+pub type Discovered = std::result::Result<Vec<(String, Target)>, String>;
+
+/// A kind of object the routes are discovered from
+///
+/// Properties:
+///
+/// * `kind`: The lowercase kind, the prefix of the sources of its backends.
+/// * `routes`: Reads the hostnames of an object and their targets, none when it isn't annotated.
+pub struct Discovery<K> {
+    kind: &'static str,
+    routes: fn(&K) -> Discovered,
+}
+
+/// It returns the discovery of the annotated Ingresses
+pub fn ingresses() -> Discovery<Ingress> {
+    Discovery {
+        kind: "ingress",
+        routes: ingress_routes,
+    }
+}
+
+/// It returns the discovery of the annotated HTTPRoutes of the Gateway API
+pub fn http_routes() -> Discovery<DynamicObject> {
+    Discovery {
+        kind: "httproute",
+        routes: http_route_routes,
+    }
+}
+
+/// It returns the API resource of the HTTPRoutes, which are not part of the core API
+pub fn http_route_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("gateway.networking.k8s.io", "v1", "HTTPRoute"),
+        "httproutes",
+    )
+}
+
+impl<K> Discovery<K>
+where
+    K: Resource + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+{
+    /// It watches the objects, and keeps the backends of the annotated ones in sync with them
+    ///
+    /// Arguments:
+    ///
+    /// * `api`: The API of the objects to watch.
+    /// * `client`: The client resolving the Services.
+    /// * `routes`: The routing table of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn watch(&self, api: Api<K>, client: Client, routes: Routes) -> Result<()> {
+        let mut events = watcher(api, watcher::Config::default())
+            .default_backoff()
+            .boxed();
+
+        while let Some(event) = events.next().await {
+            let result = match event {
+                Ok(watcher::Event::Applied(object)) => self.sync(&object, &client, &routes).await,
+                Ok(watcher::Event::Deleted(object)) => routes.remove(&self.source(&object)).await,
+                Ok(watcher::Event::Restarted(objects)) => {
+                    self.resync(&objects, &client, &routes).await
+                }
+                Err(e) => {
+                    log::warn!("failed to watch the {} objects: {}", self.kind, e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = result {
+                log::warn!("failed to discover the {} routes: {:#}", self.kind, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It returns the source of the backends of an object, `<kind>/<namespace>/<name>`
+    fn source(&self, object: &K) -> String {
+        format!(
+            "{}/{}/{}",
+            self.kind,
+            object.namespace().unwrap_or_default(),
+            object.name_any()
+        )
+    }
+
+    /// It syncs every listed object, then removes the backends of the objects that are gone
+    async fn resync(&self, objects: &[K], client: &Client, routes: &Routes) -> Result<()> {
+        for object in objects {
+            self.sync(object, client, routes).await?;
+        }
+
+        let sources: BTreeSet<String> = objects.iter().map(|object| self.source(object)).collect();
+        let prefix = format!("{}/", self.kind);
+        let stale: BTreeSet<String> = routes
+            .list()
+            .await?
+            .iter()
+            .filter_map(routes::source_of)
+            .filter(|source| source.starts_with(&prefix) && !sources.contains(*source))
+            .map(str::to_string)
+            .collect();
+
+        for source in stale {
+            routes.remove(&source).await?;
+        }
+
+        Ok(())
+    }
+
+    /// It routes the hostnames of an object to their Services
+    async fn sync(&self, object: &K, client: &Client, routes: &Routes) -> Result<()> {
+        let source = self.source(object);
+        let namespace = object.namespace().unwrap_or_default();
+
+        let discovered = match (self.routes)(object) {
+            Ok(discovered) => discovered,
+            Err(e) => {
+                log::warn!("ignoring {}: {}", source, e);
+                Vec::new()
+            }
+        };
+
+        let mut addresses = BTreeMap::new();
+        let mut wanted = Vec::new();
+        for (hostname, target) in discovered {
+            let key = (target.service.clone(), target.port);
+            if !addresses.contains_key(&key) {
+                let address =
+                    services::resolve(client, &namespace, &target.service, target.port).await?;
+                addresses.insert(key.clone(), address);
+            }
+
+            match &addresses[&key] {
+                // the connections to a headless Service are balanced by its DNS name
+                Ok(address) => {
+                    let mut backend = Backend::new(hostname, address.host.clone(), target.port);
+                    routes::own(&mut backend, &source);
+                    wanted.push(backend);
+                }
+                Err(outcome) => {
+                    log::warn!("{} can't route {}: {}", source, hostname, outcome.message)
+                }
+            }
+        }
+
+        for outcome in routes.sync(&source, wanted).await? {
+            if !outcome.ready {
+                log::warn!("{} is not routed: {}", source, outcome.message);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// It parses the `kubecraft.cloud/backend` annotation
+///
+/// Arguments:
+///
+/// * `value`: The value of the annotation.
+///
+/// Returns:
+///
+/// The target of the annotation, none when the backend of the object is used
+fn parse_annotation(value: &str) -> std::result::Result<Option<Target>, String> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("true") {
+        return Ok(None);
+    }
+
+    let (service, port) = match value.split_once(':') {
+        Some((service, port)) => (
+            service,
+            port.parse()
+                .map_err(|e| format!("invalid port in {} {}: {}", BACKEND_ANNOTATION, value, e))?,
+        ),
+        None => (value, 25565),
+    };
+    if service.is_empty() || port == 0 {
+        return Err(format!("invalid {} {}", BACKEND_ANNOTATION, value));
+    }
+
+    Ok(Some(Target {
+        service: service.to_string(),
+        port,
+    }))
+}
+
+/// It keeps the hostnames the proxy can route, wildcards aren't supported
+fn routable(hostname: &str) -> Option<String> {
+    (!hostname.is_empty() && !hostname.contains('*')).then(|| hostname.to_lowercase())
+}
+
+/// It reads the hostnames of the rules of an annotated Ingress and their targets
+///
+/// Without a target in the annotation, a rule is routed to the Service of its first path, or to
+/// the default backend of the Ingress. Only numbered Service ports are supported.
+///
+/// Arguments:
+///
+/// * `ingress`: The Ingress.
+///
+/// Returns:
+///
+/// The hostnames and their targets, empty when the Ingress isn't annotated
+pub fn ingress_routes(ingress: &Ingress) -> Discovered {
+    let annotation = match ingress.annotations().get(BACKEND_ANNOTATION) {
+        Some(annotation) => parse_annotation(annotation)?,
+        None => return Ok(Vec::new()),
+    };
+    let spec = match &ingress.spec {
+        Some(spec) => spec,
+        None => return Ok(Vec::new()),
+    };
+
+    let service_target = |backend: &k8s_openapi::api::networking::v1::IngressBackend| {
+        backend.service.as_ref().and_then(|service| {
+            let port = service.port.as_ref()?.number?;
+            Some(Target {
+                service: service.name.clone(),
+                port: u16::try_from(port).ok()?,
+            })
+        })
+    };
+    let default_target = spec.default_backend.as_ref().and_then(service_target);
+
+    let mut routes = Vec::new();
+    for rule in spec.rules.iter().flatten() {
+        let hostname = match rule.host.as_deref().and_then(routable) {
+            Some(hostname) => hostname,
+            None => continue,
+        };
+
+        let target = annotation
+            .clone()
+            .or_else(|| {
+                rule.http
+                    .as_ref()
+                    .and_then(|http| http.paths.first())
+                    .and_then(|path| service_target(&path.backend))
+            })
+            .or_else(|| default_target.clone())
+            .ok_or_else(|| format!("rule {} has no numbered service port", hostname))?;
+
+        routes.push((hostname, target));
+    }
+
+    Ok(routes)
+}
+
+/// It reads the hostnames of an annotated HTTPRoute and their target
+///
+/// Without a target in the annotation, the hostnames are routed to the first backend reference
+/// of the first rule.
+///
+/// Arguments:
+///
+/// * `route`: The HTTPRoute.
+///
+/// Returns:
+///
+/// The hostnames and their target, empty when the HTTPRoute isn't annotated
+pub fn http_route_routes(route: &DynamicObject) -> Discovered {
+    let annotation = match route.annotations().get(BACKEND_ANNOTATION) {
+        Some(annotation) => parse_annotation(annotation)?,
+        None => return Ok(Vec::new()),
+    };
+    let spec = &route.data["spec"];
+
+    let target = match annotation {
+        Some(target) => target,
+        None => {
+            let backend = &spec["rules"][0]["backendRefs"][0];
+            let service = backend["name"].as_str().unwrap_or_default();
+            let port = backend["port"]
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .unwrap_or_default();
+            if service.is_empty() || port == 0 {
+                return Err("the first rule has no backend reference with a port".to_string());
+            }
+            Target {
+                service: service.to_string(),
+                port,
+            }
+        }
+    };
+
+    Ok(spec["hostnames"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|hostname| hostname.as_str().and_then(routable))
+        .map(|hostname| (hostname, target.clone()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn target(service: &str, port: u16) -> Target {
+        Target {
+            service: service.to_string(),
+            port,
+        }
+    }
+
+    #[test]
+    fn it_parses_the_backend_annotation() {
+        assert_eq!(parse_annotation("true"), Ok(None));
+        assert_eq!(parse_annotation("lobby"), Ok(Some(target("lobby", 25565))));
+        assert_eq!(
+            parse_annotation("lobby:25566"),
+            Ok(Some(target("lobby", 25566)))
+        );
+        assert!(parse_annotation("lobby:minecraft").is_err());
+        assert!(parse_annotation(":25565").is_err());
+    }
+
+    #[test]
+    fn it_reads_the_routes_of_an_annotated_ingress() {
+        let ingress: Ingress = serde_json::from_value(json!({
+            "metadata": {
+                "name": "lobby",
+                "namespace": "games",
+                "annotations": { "kubecraft.cloud/backend": "true" }
+            },
+            "spec": {
+                "defaultBackend": { "service": { "name": "fallback", "port": { "number": 25565 } } },
+                "rules": [
+                    {
+                        "host": "Lobby.example.com",
+                        "http": { "paths": [{
+                            "path": "/",
+                            "pathType": "Prefix",
+                            "backend": { "service": { "name": "lobby", "port": { "number": 25566 } } }
+                        }] }
+                    },
+                    { "host": "survival.example.com" },
+                    { "host": "*.example.com" }
+                ]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            ingress_routes(&ingress),
+            Ok(vec![
+                ("lobby.example.com".to_string(), target("lobby", 25566)),
+                (
+                    "survival.example.com".to_string(),
+                    target("fallback", 25565)
+                ),
+            ])
+        );
+
+        let mut ingress = ingress;
+        ingress.metadata.annotations = None;
+        assert_eq!(ingress_routes(&ingress), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn it_reads_the_routes_of_an_annotated_http_route() {
+        let route: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "gateway.networking.k8s.io/v1",
+            "kind": "HTTPRoute",
+            "metadata": {
+                "name": "lobby",
+                "namespace": "games",
+                "annotations": { "kubecraft.cloud/backend": "minecraft:25566" }
+            },
+            "spec": {
+                "hostnames": ["lobby.example.com", "hub.example.com"],
+                "rules": [{ "backendRefs": [{ "name": "web", "port": 80 }] }]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            http_route_routes(&route),
+            Ok(vec![
+                ("lobby.example.com".to_string(), target("minecraft", 25566)),
+                ("hub.example.com".to_string(), target("minecraft", 25566)),
+            ])
+        );
+    }
+}
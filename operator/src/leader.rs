@@ -0,0 +1,235 @@
+use std::{env, time::Duration};
+
+use anyhow::Result;
+use config::LeaderElectionConfig;
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+    chrono::{DateTime, Utc},
+};
+use kube::{
+    api::{ObjectMeta, PostParams},
+    Api, Client,
+};
+use tokio::{sync::watch, time::sleep};
+
+/// What a replica does with the Lease it observed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The replica holds the Lease and renews it
+    Renew,
+    /// The Lease is free or expired, the replica takes it
+    Acquire,
+    /// Another replica holds the Lease
+    Follow(String),
+}
+
+/// It decides what a replica does with a Lease
+///
+/// Arguments:
+///
+/// * `spec`: The observed Lease, none when it doesn't exist.
+/// * `identity`: The identity of the replica.
+/// * `now`: The current time.
+///
+/// Returns:
+///
+/// A Decision
+pub fn decide(spec: Option<&LeaseSpec>, identity: &str, now: DateTime<Utc>) -> Decision {
+    let spec = match spec {
+        Some(spec) => spec,
+        None => return Decision::Acquire,
+    };
+
+    match spec.holder_identity.as_deref() {
+        None | Some("") => Decision::Acquire,
+        Some(holder) if holder == identity => Decision::Renew,
+        Some(holder) => {
+            let renewed = spec.renew_time.as_ref().or(spec.acquire_time.as_ref());
+            let duration = k8s_openapi::chrono::Duration::seconds(
+                spec.lease_duration_seconds.unwrap_or_default().into(),
+            );
+            match renewed {
+                Some(MicroTime(renewed)) if *renewed + duration > now => {
+                    Decision::Follow(holder.to_string())
+                }
+                _ => Decision::Acquire,
+            }
+        }
+    }
+}
+
+/// It competes for a Lease with the other replicas of the proxy
+///
+/// Properties:
+///
+/// * `api`: The API of the Leases, in the namespace of the Lease.
+/// * `name`: The name of the Lease.
+/// * `identity`: The identity of this replica, written in the Lease it holds.
+/// * `lease_duration`: How long the Lease is held without being renewed.
+/// * `renew_interval`: How often the Lease is renewed, or tried to be acquired.
+pub struct LeaderElector {
+    api: Api<Lease>,
+    name: String,
+    identity: String,
+    lease_duration: Duration,
+    renew_interval: Duration,
+}
+
+impl LeaderElector {
+    pub fn new(client: Client, config: &LeaderElectionConfig) -> Self {
+        let namespace = config
+            .lease_namespace
+            .clone()
+            .unwrap_or_else(|| client.default_namespace().to_string());
+        let identity = config
+            .identity
+            .clone()
+            .or_else(|| env::var("POD_NAME").ok())
+            .or_else(|| env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| format!("kubecraft-proxy-{}", std::process::id()));
+
+        Self {
+            api: Api::namespaced(client, &namespace),
+            name: config.lease_name.clone(),
+            identity,
+            lease_duration: Duration::from_secs(config.lease_duration_secs),
+            renew_interval: Duration::from_secs(config.renew_interval_secs),
+        }
+    }
+
+    /// It keeps acquiring or renewing the Lease, and publishes whether this replica leads
+    ///
+    /// A replica failing to reach the Kubernetes API steps down right away, so two replicas never
+    /// run the controllers for longer than the duration of the Lease.
+    ///
+    /// Arguments:
+    ///
+    /// * `leader`: Publishes whether this replica holds the Lease.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn run(&self, leader: watch::Sender<bool>) -> Result<()> {
+        loop {
+            let leading = match self.try_acquire_or_renew().await {
+                Ok(leading) => leading,
+                Err(e) => {
+                    log::warn!("failed to renew the lease {}: {}", self.name, e);
+                    false
+                }
+            };
+
+            if leader.send_replace(leading) != leading {
+                match leading {
+                    true => log::info!("{} acquired the lease {}", self.identity, self.name),
+                    false => log::info!("{} lost the lease {}", self.identity, self.name),
+                }
+            }
+
+            sleep(self.renew_interval).await;
+        }
+    }
+
+    /// It acquires or renews the Lease when this replica can hold it
+    ///
+    /// The Lease is replaced at the version it was read at, so two replicas racing for it can't
+    /// both win.
+    ///
+    /// Returns:
+    ///
+    /// A Result<bool> telling whether this replica holds the Lease
+    async fn try_acquire_or_renew(&self) -> Result<bool> {
+        let now = Utc::now();
+        let current = self.api.get_opt(&self.name).await?;
+
+        let mut lease = match decide(
+            current.as_ref().and_then(|lease| lease.spec.as_ref()),
+            &self.identity,
+            now,
+        ) {
+            Decision::Follow(_) => return Ok(false),
+            Decision::Renew => {
+                let mut lease = current.unwrap_or_default();
+                let spec = lease.spec.get_or_insert_with(Default::default);
+                spec.renew_time = Some(MicroTime(now));
+                lease
+            }
+            Decision::Acquire => {
+                let mut lease = current.unwrap_or_else(|| Lease {
+                    metadata: ObjectMeta {
+                        name: Some(self.name.clone()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                let spec = lease.spec.get_or_insert_with(Default::default);
+                spec.holder_identity = Some(self.identity.clone());
+                spec.lease_duration_seconds = Some(self.lease_duration.as_secs() as i32);
+                spec.acquire_time = Some(MicroTime(now));
+                spec.renew_time = Some(MicroTime(now));
+                spec.lease_transitions = Some(spec.lease_transitions.unwrap_or(-1) + 1);
+                lease
+            }
+        };
+
+        let result = match lease.metadata.resource_version {
+            Some(_) => {
+                self.api
+                    .replace(&self.name, &PostParams::default(), &lease)
+                    .await
+            }
+            None => {
+                lease.metadata.name = Some(self.name.clone());
+                self.api.create(&PostParams::default(), &lease).await
+            }
+        };
+
+        match result {
+            Ok(_) => Ok(true),
+            // another replica changed the Lease since it was read
+            Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease(holder: &str, renewed_secs_ago: i64) -> LeaseSpec {
+        LeaseSpec {
+            holder_identity: Some(holder.to_string()),
+            lease_duration_seconds: Some(15),
+            renew_time: Some(MicroTime(
+                Utc::now() - k8s_openapi::chrono::Duration::seconds(renewed_secs_ago),
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_decides_who_holds_the_lease() {
+        let now = Utc::now();
+
+        assert_eq!(decide(None, "proxy-0", now), Decision::Acquire);
+        assert_eq!(
+            decide(Some(&LeaseSpec::default()), "proxy-0", now),
+            Decision::Acquire
+        );
+        assert_eq!(
+            decide(Some(&lease("proxy-0", 2)), "proxy-0", now),
+            Decision::Renew
+        );
+        assert_eq!(
+            decide(Some(&lease("proxy-1", 2)), "proxy-0", now),
+            Decision::Follow("proxy-1".to_string())
+        );
+        // the holder stopped renewing the lease
+        assert_eq!(
+            decide(Some(&lease("proxy-1", 30)), "proxy-0", now),
+            Decision::Acquire
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use config::KubernetesConfig;
+use futures::{try_join, StreamExt};
+use k8s_openapi::api::{
+    core::v1::{Pod, Service},
+    discovery::v1::EndpointSlice,
+    networking::v1::Ingress,
+};
+use kube::{
+    api::DynamicObject,
+    runtime::{
+        reflector::{ObjectRef, Store},
+        watcher, Controller,
+    },
+    Api, Client, ResourceExt,
+};
+use listener::event::Event;
+use shared::{activity::Activity, endpoints::Endpoints};
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    crd::MinecraftServer, leader::LeaderElector, reconcile::Context, routes::Routes, scaler::Scaler,
+};
+
+pub mod crd;
+pub mod discovery;
+pub mod leader;
+pub mod pods;
+pub mod reconcile;
+pub mod routes;
+pub mod scaler;
+pub mod services;
+pub mod status;
+
+/// The operator reconciles the Kubernetes objects describing Minecraft servers into the routing
+/// table
+///
+/// The `MinecraftServer` resources, the annotated Ingresses and the annotated HTTPRoutes are
+/// routed to the cluster IP of their Service, and the connections to the headless Service of a
+/// `MinecraftServer` are balanced across its pods. The backends are changed through the same
+/// events as the gRPC API, so the quotas and the static routes apply to them too. With leader
+/// election, only the replica holding the Lease runs the controllers. The workloads of the
+/// `MinecraftServer` resources can be scaled down to zero while nobody plays on them.
+pub struct Operator {
+    config: KubernetesConfig,
+    activity: Arc<Activity>,
+    endpoints: Arc<Endpoints>,
+}
+
+impl Operator {
+    pub fn new(
+        config: KubernetesConfig,
+        activity: Arc<Activity>,
+        endpoints: Arc<Endpoints>,
+    ) -> Self {
+        Self {
+            config,
+            activity,
+            endpoints,
+        }
+    }
+
+    /// It watches the enabled kinds of objects, and sends the changes of the backends to the event
+    /// loop
+    ///
+    /// It returns right away when the Kubernetes integration is disabled.
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: mpsc::Sender<Event>
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn start(&self, tx: mpsc::Sender<Event>) -> Result<()> {
+        if !(self.config.operator || self.config.ingress || self.config.gateway || self.config.pods)
+        {
+            return Ok(());
+        }
+
+        let client = Client::try_default().await.map_err(|e| {
+            log::error!("failed to connect to the Kubernetes API: {}", e);
+            anyhow!("failed to connect to the Kubernetes API: {}", e)
+        })?;
+        let routes = Routes::new(tx);
+
+        log::info!(
+            "Starting operator in {}",
+            self.config.namespace.as_deref().unwrap_or("all namespaces")
+        );
+
+        match &self.config.leader_election {
+            Some(election) => {
+                let elector = LeaderElector::new(client.clone(), election);
+                let (leader, leading) = watch::channel(false);
+                try_join!(elector.run(leader), self.lead(leading, client, routes))?;
+            }
+            None => self.run_controllers(client, routes).await?,
+        }
+
+        Ok(())
+    }
+
+    /// It runs the controllers while this replica holds the Lease, and stops them when it loses it
+    ///
+    /// Arguments:
+    ///
+    /// * `leading`: Tells whether this replica holds the Lease.
+    /// * `client`: The client of the Kubernetes API.
+    /// * `routes`: The routing table of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    async fn lead(
+        &self,
+        mut leading: watch::Receiver<bool>,
+        client: Client,
+        routes: Routes,
+    ) -> Result<()> {
+        loop {
+            while !*leading.borrow() {
+                leading.changed().await?;
+            }
+
+            log::info!("leading, starting the controllers");
+            tokio::select! {
+                result = self.run_controllers(client.clone(), routes.clone()) => return result,
+                _ = async {
+                    while *leading.borrow() {
+                        if leading.changed().await.is_err() {
+                            break;
+                        }
+                    }
+                } => log::info!("not leading anymore, stopping the controllers"),
+            }
+        }
+    }
+
+    /// It runs the enabled controllers
+    async fn run_controllers(&self, client: Client, routes: Routes) -> Result<()> {
+        try_join!(
+            self.reconcile_servers(client.clone(), routes.clone()),
+            self.discover_ingresses(client.clone(), routes.clone()),
+            self.discover_http_routes(client.clone(), routes.clone()),
+            self.follow_pods(client, routes)
+        )?;
+
+        Ok(())
+    }
+
+    /// It returns the API of a kind of object, in the watched namespace
+    fn api<K>(&self, client: Client) -> Api<K>
+    where
+        K: kube::Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+        K::DynamicType: Default,
+    {
+        match &self.config.namespace {
+            Some(namespace) => Api::namespaced(client, namespace),
+            None => Api::all(client),
+        }
+    }
+
+    /// It reconciles the `MinecraftServer` resources, and the ones routed to a changed Service
+    async fn reconcile_servers(&self, client: Client, routes: Routes) -> Result<()> {
+        if !self.config.operator {
+            return Ok(());
+        }
+
+        let controller = Controller::new(
+            self.api::<MinecraftServer>(client.clone()),
+            watcher::Config::default(),
+        );
+        let store = controller.store();
+        let slices_store = store.clone();
+        let services = self.api::<Service>(client.clone());
+        let slices = self.api::<EndpointSlice>(client.clone());
+        let scaler = Arc::new(Scaler::new(client.clone(), self.activity.clone()));
+        let context = Arc::new(Context::new(
+            client,
+            routes,
+            scaler.clone(),
+            self.endpoints.clone(),
+        ));
+
+        let controller = controller
+            // a change of a Service reconciles the servers routed to it
+            .watches(services, watcher::Config::default(), move |service| {
+                routed_to(&store, service.namespace(), &service.name_any())
+            })
+            // and so does a change of the pods of a headless Service
+            .watches(slices, watcher::Config::default(), move |slice| match slice
+                .labels()
+                .get(services::SERVICE_NAME_LABEL)
+            {
+                Some(service) => routed_to(&slices_store, slice.namespace(), service),
+                None => Vec::new(),
+            })
+            .run(reconcile::reconcile, reconcile::error_policy, context)
+            .for_each(|result| async move {
+                match result {
+                    Ok((server, _)) => log::debug!("reconciled MinecraftServer {}", server),
+                    Err(e) => log::warn!("failed to reconcile MinecraftServer: {}", e),
+                }
+            });
+
+        tokio::select! {
+            _ = controller => Ok(()),
+            result = scaler.run() => result,
+        }
+    }
+
+    /// It discovers the routes of the annotated Ingresses
+    async fn discover_ingresses(&self, client: Client, routes: Routes) -> Result<()> {
+        if !self.config.ingress {
+            return Ok(());
+        }
+
+        let api = self.api::<Ingress>(client.clone());
+        discovery::ingresses().watch(api, client, routes).await
+    }
+
+    /// It discovers the routes of the annotated HTTPRoutes
+    async fn discover_http_routes(&self, client: Client, routes: Routes) -> Result<()> {
+        if !self.config.gateway {
+            return Ok(());
+        }
+
+        let resource = discovery::http_route_resource();
+        let api = match &self.config.namespace {
+            Some(namespace) => {
+                Api::<DynamicObject>::namespaced_with(client.clone(), namespace, &resource)
+            }
+            None => Api::<DynamicObject>::all_with(client.clone(), &resource),
+        };
+        discovery::http_routes().watch(api, client, routes).await
+    }
+
+    /// It follows the IP of the pods the labeled backends are routed to
+    async fn follow_pods(&self, client: Client, routes: Routes) -> Result<()> {
+        if !self.config.pods {
+            return Ok(());
+        }
+
+        pods::watch(self.api::<Pod>(client), routes).await
+    }
+}
+
+/// It returns the `MinecraftServer` resources routed to a Service
+///
+/// Arguments:
+///
+/// * `store`: The watched `MinecraftServer` resources.
+/// * `namespace`: The namespace of the Service.
+/// * `service`: The name of the Service.
+///
+/// Returns:
+///
+/// The references of the resources to reconcile
+fn routed_to(
+    store: &Store<MinecraftServer>,
+    namespace: Option<String>,
+    service: &str,
+) -> Vec<ObjectRef<MinecraftServer>> {
+    store
+        .state()
+        .into_iter()
+        .filter(|server| server.namespace() == namespace && server.spec.service.name == service)
+        .map(|server| ObjectRef::from_obj(server.as_ref()))
+        .collect()
+}
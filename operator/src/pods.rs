@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    runtime::{watcher, WatchStreamExt},
+    Api, ResourceExt,
+};
+use shared::models::backend::Backend;
+
+use crate::routes::Routes;
+
+/// The label naming the pod serving a backend, `<namespace>/<name>`
+///
+/// The redirect IP of a backend carrying it follows the IP of the pod, e.g. when a pod of a
+/// StatefulSet is rescheduled on another node.
+pub const POD_LABEL: &str = "kubecraft.cloud/pod";
+
+/// It returns the pod serving a backend
+///
+/// Arguments:
+///
+/// * `backend`: The stored backend.
+///
+/// Returns:
+///
+/// The namespace and the name of the pod, none when the backend isn't labeled with one
+pub fn pod_of(backend: &Backend) -> Option<(&str, &str)> {
+    backend
+        .labels()
+        .get(POD_LABEL)?
+        .split_once('/')
+        .filter(|(namespace, name)| !namespace.is_empty() && !name.is_empty())
+}
+
+/// It returns the backends served by a pod which are not routed to its IP anymore
+///
+/// Arguments:
+///
+/// * `backends`: The stored backends.
+/// * `namespace`: The namespace of the pod.
+/// * `name`: The name of the pod.
+/// * `pod_ip`: The current IP of the pod.
+///
+/// Returns:
+///
+/// The backends routed to the new IP of the pod, at their stored version
+pub fn retarget(backends: &[Backend], namespace: &str, name: &str, pod_ip: &str) -> Vec<Backend> {
+    backends
+        .iter()
+        .filter(|backend| !backend.read_only())
+        .filter(|backend| pod_of(backend) == Some((namespace, name)))
+        .filter(|backend| backend.redirect_ip() != pod_ip)
+        .map(|backend| {
+            let mut backend = backend.clone();
+            backend.redirect_ip = pod_ip.to_string();
+            backend
+        })
+        .collect()
+}
+
+/// It watches the pods, and routes the backends labeled with a pod to its IP when it changes
+///
+/// Arguments:
+///
+/// * `api`: The API of the pods to watch.
+/// * `routes`: The routing table of the proxy.
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn watch(api: Api<Pod>, routes: Routes) -> Result<()> {
+    let mut events = watcher(api, watcher::Config::default())
+        .default_backoff()
+        .boxed();
+    // the IPs already followed, so the routing table is only read when one changes
+    let mut known: HashMap<(String, String), String> = HashMap::new();
+
+    while let Some(event) = events.next().await {
+        let pods = match event {
+            Ok(watcher::Event::Applied(pod)) => vec![pod],
+            Ok(watcher::Event::Deleted(pod)) => {
+                known.remove(&(pod.namespace().unwrap_or_default(), pod.name_any()));
+                continue;
+            }
+            Ok(watcher::Event::Restarted(pods)) => {
+                known.clear();
+                pods
+            }
+            Err(e) => {
+                log::warn!("failed to watch the pods: {}", e);
+                continue;
+            }
+        };
+
+        let changed: Vec<(String, String, String)> = pods
+            .iter()
+            .filter_map(|pod| {
+                let pod_ip = pod.status.as_ref()?.pod_ip.clone()?;
+                let key = (pod.namespace().unwrap_or_default(), pod.name_any());
+                match known.get(&key) == Some(&pod_ip) {
+                    true => None,
+                    false => {
+                        known.insert(key.clone(), pod_ip.clone());
+                        Some((key.0, key.1, pod_ip))
+                    }
+                }
+            })
+            .collect();
+
+        if let Err(e) = follow(&changed, &routes).await {
+            log::warn!("failed to follow the pods: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// It routes the backends of the pods whose IP changed to their new IP
+async fn follow(changed: &[(String, String, String)], routes: &Routes) -> Result<()> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let backends = routes.list().await?;
+    for (namespace, name, pod_ip) in changed {
+        for backend in retarget(&backends, namespace, name, pod_ip) {
+            log::info!(
+                "routing {} to {}, the new IP of the pod {}/{}",
+                backend.hostname(),
+                pod_ip,
+                namespace,
+                name
+            );
+            routes.put(backend).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(hostname: &str, redirect_ip: &str, pod: Option<&str>) -> Backend {
+        let mut backend = Backend::new(hostname.to_string(), redirect_ip.to_string(), 25565);
+        backend.version = 3;
+        if let Some(pod) = pod {
+            backend
+                .labels
+                .insert(POD_LABEL.to_string(), pod.to_string());
+        }
+        backend
+    }
+
+    #[test]
+    fn it_retargets_the_backends_of_a_rescheduled_pod() {
+        let mut pinned = backend("static.example.com", "10.0.0.1", Some("games/lobby-0"));
+        pinned.read_only = true;
+        let backends = vec![
+            backend("lobby.example.com", "10.0.0.1", Some("games/lobby-0")),
+            backend("moved.example.com", "10.0.0.9", Some("games/lobby-0")),
+            backend("other.example.com", "10.0.0.1", Some("games/lobby-1")),
+            backend("plain.example.com", "10.0.0.1", None),
+            backend("invalid.example.com", "10.0.0.1", Some("lobby-0")),
+            pinned,
+        ];
+
+        let retargeted = retarget(&backends, "games", "lobby-0", "10.0.0.9");
+        assert_eq!(retargeted.len(), 1);
+        assert_eq!(retargeted[0].hostname(), "lobby.example.com");
+        assert_eq!(retargeted[0].redirect_ip(), "10.0.0.9");
+        assert_eq!(retargeted[0].version(), 3);
+    }
+}
@@ -0,0 +1,225 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use kube::{
+    api::{Patch, PatchParams},
+    runtime::{
+        controller::Action,
+        finalizer::{finalizer, Event as Finalizer},
+    },
+    Api, Client, ResourceExt,
+};
+use serde_json::json;
+use shared::endpoints::Endpoints;
+
+use crate::{
+    crd::MinecraftServer,
+    routes::Routes,
+    scaler::Scaler,
+    services,
+    status::{next_status, Outcome},
+};
+
+/// The finalizer removing the route of a deleted `MinecraftServer`
+pub const FINALIZER: &str = "kubecraft.cloud/route";
+
+/// How often the resources are reconciled again, to repair the routes the API changed
+const RESYNC: Duration = Duration::from_secs(300);
+/// How long a failed reconciliation waits before it is retried
+const RETRY: Duration = Duration::from_secs(15);
+
+/// The error of a reconciliation, retried after a delay
+#[derive(Debug)]
+pub struct ReconcileError(String);
+
+impl fmt::Display for ReconcileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ReconcileError {}
+
+impl From<anyhow::Error> for ReconcileError {
+    fn from(e: anyhow::Error) -> Self {
+        Self(format!("{:#}", e))
+    }
+}
+
+impl From<kube::Error> for ReconcileError {
+    fn from(e: kube::Error) -> Self {
+        Self(e.to_string())
+    }
+}
+
+/// The state shared by the reconciliations
+///
+/// Properties:
+///
+/// * `client`: The client of the Kubernetes API.
+/// * `routes`: The routing table of the proxy.
+/// * `scaler`: Scales the workloads of the routed servers on demand.
+/// * `endpoints`: The pods the connections to the headless Services are balanced across.
+pub struct Context {
+    client: Client,
+    routes: Routes,
+    scaler: Arc<Scaler>,
+    endpoints: Arc<Endpoints>,
+}
+
+impl Context {
+    pub fn new(
+        client: Client,
+        routes: Routes,
+        scaler: Arc<Scaler>,
+        endpoints: Arc<Endpoints>,
+    ) -> Self {
+        Self {
+            client,
+            routes,
+            scaler,
+            endpoints,
+        }
+    }
+
+    /// It routes the hostname of a resource to its Service, then writes the result back to its
+    /// status
+    ///
+    /// Arguments:
+    ///
+    /// * `server`: The resource to reconcile.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Action, ReconcileError>
+    async fn apply(&self, server: Arc<MinecraftServer>) -> Result<Action, ReconcileError> {
+        let outcome = self.route(&server).await?;
+        if !outcome.ready {
+            log::warn!(
+                "MinecraftServer {} is not routed: {}",
+                server.source(),
+                outcome.message
+            );
+        }
+
+        match &server.spec.scale {
+            Some(scale) if outcome.ready => self.scaler.register(
+                &server.spec.hostname,
+                &server.source(),
+                &server.namespace().unwrap_or_default(),
+                scale,
+            ),
+            _ => self.scaler.unregister(&server.source()),
+        }
+
+        let status = next_status(server.status.as_ref(), &outcome, server.metadata.generation);
+        if server.status.as_ref() != Some(&status) {
+            let api: Api<MinecraftServer> =
+                Api::namespaced(self.client.clone(), &server.namespace().unwrap_or_default());
+            api.patch_status(
+                &server.name_any(),
+                &PatchParams::default(),
+                &Patch::Merge(json!({ "status": status })),
+            )
+            .await?;
+        }
+
+        Ok(Action::requeue(RESYNC))
+    }
+
+    /// It stores the backend of a resource, replacing the one it was routed to before
+    ///
+    /// Arguments:
+    ///
+    /// * `server`: The resource to route.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Outcome, ReconcileError>, errors are only returned when the reconciliation must be
+    /// retried
+    async fn route(&self, server: &MinecraftServer) -> Result<Outcome, ReconcileError> {
+        let source = server.source();
+        let namespace = server.namespace().unwrap_or_default();
+
+        let address = match services::resolve(
+            &self.client,
+            &namespace,
+            &server.spec.service.name,
+            server.spec.service.port,
+        )
+        .await?
+        {
+            Ok(address) => address,
+            Err(outcome) => {
+                self.routes.remove(&source).await?;
+                self.endpoints.remove(&server.spec.hostname.to_lowercase());
+                return Ok(outcome);
+            }
+        };
+
+        let backend = match server.backend(address.host) {
+            Ok(backend) => backend,
+            Err(e) => return Ok(Outcome::failed("InvalidSpec", e.to_string())),
+        };
+        let hostname = backend.hostname().to_string();
+
+        let outcome = self.routes.sync(&source, vec![backend]).await?.remove(0);
+        match address.endpoints {
+            // a hostname routed by another source keeps its endpoints
+            Some(endpoints) if outcome.ready => self.endpoints.set(&hostname, endpoints),
+            Some(_) => {}
+            None => self.endpoints.remove(&hostname),
+        }
+        Ok(outcome)
+    }
+}
+
+/// It reconciles a `MinecraftServer`, removing its route once it is deleted
+///
+/// Arguments:
+///
+/// * `server`: The resource to reconcile.
+/// * `context`: The state shared by the reconciliations.
+///
+/// Returns:
+///
+/// A Result<Action, ReconcileError>
+pub async fn reconcile(
+    server: Arc<MinecraftServer>,
+    context: Arc<Context>,
+) -> Result<Action, ReconcileError> {
+    let api: Api<MinecraftServer> = Api::namespaced(
+        context.client.clone(),
+        &server.namespace().unwrap_or_default(),
+    );
+
+    finalizer(&api, FINALIZER, server, |event| async {
+        match event {
+            Finalizer::Apply(server) => context.apply(server).await,
+            Finalizer::Cleanup(server) => {
+                context.routes.remove(&server.source()).await?;
+                context.scaler.unregister(&server.source());
+                context
+                    .endpoints
+                    .remove(&server.spec.hostname.to_lowercase());
+                log::info!("removed the route of MinecraftServer {}", server.source());
+                Ok(Action::await_change())
+            }
+        }
+    })
+    .await
+    .map_err(|e| ReconcileError(e.to_string()))
+}
+
+/// It retries a failed reconciliation after a delay
+pub fn error_policy(
+    server: Arc<MinecraftServer>,
+    error: &ReconcileError,
+    _context: Arc<Context>,
+) -> Action {
+    log::warn!(
+        "failed to reconcile MinecraftServer {}: {}",
+        server.source(),
+        error
+    );
+    Action::requeue(RETRY)
+}
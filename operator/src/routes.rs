@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+use listener::event::Event;
+use shared::models::{backend::Backend, selector::LabelSelector};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::status::Outcome;
+
+/// The label set on every backend reconciled by the operator
+pub const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+/// The value of the `app.kubernetes.io/managed-by` label
+pub const MANAGED_BY: &str = "kubecraft-proxy";
+/// The label holding the source of a backend, e.g. `<namespace>/<name>` for a `MinecraftServer`
+pub const SOURCE_LABEL: &str = "kubecraft.cloud/source";
+
+/// It marks a backend as reconciled from a Kubernetes object
+///
+/// Arguments:
+///
+/// * `backend`: The backend to mark.
+/// * `source`: The object the backend is reconciled from.
+pub fn own(backend: &mut Backend, source: &str) {
+    backend
+        .labels
+        .insert(MANAGED_BY_LABEL.to_string(), MANAGED_BY.to_string());
+    backend
+        .labels
+        .insert(SOURCE_LABEL.to_string(), source.to_string());
+}
+
+/// It returns the source of a backend reconciled by the operator
+///
+/// Arguments:
+///
+/// * `backend`: The stored backend.
+///
+/// Returns:
+///
+/// The source of the backend, none when it isn't managed by the operator
+pub fn source_of(backend: &Backend) -> Option<&str> {
+    if backend.labels().get(MANAGED_BY_LABEL).map(String::as_str) != Some(MANAGED_BY) {
+        return None;
+    }
+    backend.labels().get(SOURCE_LABEL).map(String::as_str)
+}
+
+/// It tells whether a backend was reconciled from the given source
+///
+/// Arguments:
+///
+/// * `backend`: The stored backend.
+/// * `source`: The object the backend is reconciled from.
+///
+/// Returns:
+///
+/// A bool
+pub fn is_owned_by(backend: &Backend, source: &str) -> bool {
+    source_of(backend) == Some(source)
+}
+
+/// The routing table of the proxy, changed through its event loop like the gRPC API does
+///
+/// Properties:
+///
+/// * `sender`: The channel of the event loop of the proxy.
+#[derive(Debug, Clone)]
+pub struct Routes {
+    sender: mpsc::Sender<Event>,
+}
+
+impl Routes {
+    pub fn new(sender: mpsc::Sender<Event>) -> Self {
+        Self { sender }
+    }
+
+    /// It sends an event to the proxy and waits for its response
+    ///
+    /// Arguments:
+    ///
+    /// * `event`: Builds the event from the channel of the response.
+    ///
+    /// Returns:
+    ///
+    /// A Result<T>
+    async fn request<T>(
+        &self,
+        event: impl FnOnce(oneshot::Sender<Result<T>>) -> Event,
+    ) -> Result<T> {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(event(tx))
+            .await
+            .map_err(|_| anyhow!("the proxy stopped handling events"))?;
+
+        rx.await
+            .map_err(|_| anyhow!("the proxy dropped the request"))?
+    }
+
+    /// It returns the backends stored by the proxy
+    ///
+    /// Returns:
+    ///
+    /// A Result<Vec<Backend>>
+    pub async fn list(&self) -> Result<Vec<Backend>> {
+        self.request(|tx| Event::ListBackends(LabelSelector::default(), tx))
+            .await
+    }
+
+    /// It replaces the backends of a source with the given ones
+    ///
+    /// The backends of the source that aren't wanted anymore are deleted, and the unchanged
+    /// ones are left as is. A hostname routed by another source, or by the API, is not taken over.
+    ///
+    /// Arguments:
+    ///
+    /// * `source`: The object the backends are reconciled from.
+    /// * `wanted`: The backends of the source, already owned by it.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Vec<Outcome>> with the outcome of every wanted backend, in order
+    pub async fn sync(&self, source: &str, wanted: Vec<Backend>) -> Result<Vec<Outcome>> {
+        let stored = self.list().await?;
+
+        let hostnames: BTreeSet<&str> = wanted.iter().map(|backend| backend.hostname()).collect();
+        for stale in stored.iter().filter(|backend| {
+            is_owned_by(backend, source) && !hostnames.contains(backend.hostname())
+        }) {
+            self.delete(stale.clone()).await?;
+        }
+
+        let mut outcomes = Vec::with_capacity(wanted.len());
+        for mut backend in wanted {
+            let message = format!("routing {} to {}", backend.hostname(), backend.addr());
+
+            if let Some(current) = stored
+                .iter()
+                .find(|current| current.hostname() == backend.hostname())
+            {
+                if !is_owned_by(current, source) {
+                    outcomes.push(Outcome::failed(
+                        "HostnameConflict",
+                        format!("hostname {} is already routed", backend.hostname()),
+                    ));
+                    continue;
+                }
+                backend.version = current.version();
+                if *current == backend {
+                    outcomes.push(Outcome::ready(message));
+                    continue;
+                }
+            }
+
+            outcomes.push(
+                match self.request(|tx| Event::PutBackend(backend, tx)).await {
+                    Ok(_) => Outcome::ready(message),
+                    Err(e) => Outcome::failed("Rejected", format!("{:#}", e)),
+                },
+            );
+        }
+
+        Ok(outcomes)
+    }
+
+    /// It stores a backend, at the version it was listed at
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The changed backend.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn put(&self, backend: Backend) -> Result<()> {
+        self.request(|tx| Event::PutBackend(backend, tx))
+            .await
+            .map(|_| ())
+    }
+
+    /// It removes the backends reconciled from a source
+    ///
+    /// Arguments:
+    ///
+    /// * `source`: The object the backends are reconciled from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn remove(&self, source: &str) -> Result<()> {
+        self.sync(source, Vec::new()).await.map(|_| ())
+    }
+
+    /// It deletes a stored backend, at its stored version
+    async fn delete(&self, backend: Backend) -> Result<()> {
+        self.request(|tx| Event::DeleteBackend(backend, tx)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_tells_the_source_of_a_backend() {
+        let mut backend = Backend::new(
+            "lobby.example.com".to_string(),
+            "10.0.0.12".to_string(),
+            25565,
+        );
+        assert_eq!(source_of(&backend), None);
+
+        own(&mut backend, "ingress/games/lobby");
+        assert_eq!(source_of(&backend), Some("ingress/games/lobby"));
+        assert!(is_owned_by(&backend, "ingress/games/lobby"));
+        assert!(!is_owned_by(&backend, "games/lobby"));
+
+        // a label set through the API doesn't make the operator take a backend over
+        backend.labels.remove(MANAGED_BY_LABEL);
+        assert_eq!(source_of(&backend), None);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use kube::{
+    api::{Patch, PatchParams},
+    Api, Client,
+};
+use serde_json::json;
+use shared::activity::Activity;
+use tokio::time::interval;
+
+use crate::crd::{ScaleSpec, WorkloadKind};
+
+/// How often the servers asked to be woken up are scaled up
+const TICK: Duration = Duration::from_secs(1);
+/// How many ticks pass between two reads of the workloads
+const OBSERVE_EVERY: u64 = 5;
+
+/// It tells whether a running server has been idle long enough to be scaled down
+///
+/// Arguments:
+///
+/// * `active`: The number of players connected to the server.
+/// * `last_seen`: When a player was last connected, none when no player ever joined.
+/// * `last_change`: When the server was last scaled, or started being watched.
+/// * `idle_timeout`: How long the server runs without any player.
+/// * `now`: The current time.
+///
+/// Returns:
+///
+/// A bool
+pub fn is_idle(
+    active: usize,
+    last_seen: Option<Instant>,
+    last_change: Instant,
+    idle_timeout: Duration,
+    now: Instant,
+) -> bool {
+    let since = last_seen.map_or(last_change, |seen| seen.max(last_change));
+    active == 0 && now.saturating_duration_since(since) >= idle_timeout
+}
+
+/// A workload scaled on demand
+///
+/// Properties:
+///
+/// * `source`: The resource the workload is declared by.
+/// * `namespace`: The namespace of the workload.
+/// * `spec`: The workload and its idle timeout.
+/// * `last_change`: When the workload was last scaled, or started being watched.
+#[derive(Debug, Clone)]
+struct Target {
+    source: String,
+    namespace: String,
+    spec: ScaleSpec,
+    last_change: Instant,
+}
+
+/// The scaler runs the servers of the routed hostnames only while players use them
+///
+/// A workload without any player for its idle timeout is scaled down to zero replicas, and its
+/// hostname is marked as sleeping. The proxy then asks for it to be woken up when a player joins,
+/// and the workload is scaled back up to one replica.
+///
+/// Properties:
+///
+/// * `client`: The client of the Kubernetes API.
+/// * `activity`: The connections of the proxy, and the sleeping hostnames.
+/// * `targets`: The workloads scaled on demand, by hostname.
+pub struct Scaler {
+    client: Client,
+    activity: Arc<Activity>,
+    targets: Mutex<BTreeMap<String, Target>>,
+}
+
+impl Scaler {
+    pub fn new(client: Client, activity: Arc<Activity>) -> Self {
+        Self {
+            client,
+            activity,
+            targets: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// It scales the workload of a hostname on demand, replacing the one it was scaling before
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The routed hostname.
+    /// * `source`: The resource the workload is declared by.
+    /// * `namespace`: The namespace of the workload.
+    /// * `spec`: The workload and its idle timeout.
+    pub fn register(&self, hostname: &str, source: &str, namespace: &str, spec: &ScaleSpec) {
+        let mut targets = self.targets.lock().unwrap();
+        if let Some(target) = targets.get_mut(hostname) {
+            if target.source == source && target.namespace == namespace {
+                target.spec = spec.clone();
+                return;
+            }
+        }
+
+        targets.insert(
+            hostname.to_string(),
+            Target {
+                source: source.to_string(),
+                namespace: namespace.to_string(),
+                spec: spec.clone(),
+                last_change: Instant::now(),
+            },
+        );
+    }
+
+    /// It stops scaling the workloads declared by a resource, and wakes their hostnames up
+    ///
+    /// Arguments:
+    ///
+    /// * `source`: The resource the workloads are declared by.
+    pub fn unregister(&self, source: &str) {
+        let mut targets = self.targets.lock().unwrap();
+        targets.retain(|hostname, target| {
+            if target.source != source {
+                return true;
+            }
+            self.activity.set_sleeping(hostname, false);
+            false
+        });
+    }
+
+    /// It wakes the servers players joined up, and puts the idle ones to sleep
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn run(&self) -> Result<()> {
+        let mut ticker = interval(TICK);
+        let mut ticks: u64 = 0;
+
+        loop {
+            ticker.tick().await;
+
+            for hostname in self.activity.take_wakeups() {
+                if let Err(e) = self.wake(&hostname).await {
+                    log::warn!("failed to wake the server of {} up: {}", hostname, e);
+                }
+            }
+
+            if ticks.is_multiple_of(OBSERVE_EVERY) {
+                let targets = self.targets.lock().unwrap().clone();
+                for (hostname, target) in targets {
+                    if let Err(e) = self.observe(&hostname, &target).await {
+                        log::warn!("failed to read the workload of {}: {}", hostname, e);
+                    }
+                }
+            }
+            ticks = ticks.wrapping_add(1);
+        }
+    }
+
+    /// It scales the workload of a sleeping hostname up to one replica
+    async fn wake(&self, hostname: &str) -> Result<()> {
+        let target = match self.targets.lock().unwrap().get(hostname) {
+            Some(target) => target.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some((0, _)) = self.replicas(&target).await? {
+            self.scale(hostname, &target, 1).await?;
+            log::info!("woke the server of {} up", hostname);
+        }
+
+        Ok(())
+    }
+
+    /// It updates whether a hostname is sleeping, and scales its workload down once it is idle
+    async fn observe(&self, hostname: &str, target: &Target) -> Result<()> {
+        let (replicas, ready) = match self.replicas(target).await? {
+            Some(replicas) => replicas,
+            None => {
+                log::warn!(
+                    "{} {}/{} of {} not found",
+                    kind_name(target.spec.kind),
+                    target.namespace,
+                    target.spec.name,
+                    hostname
+                );
+                self.activity.set_sleeping(hostname, false);
+                return Ok(());
+            }
+        };
+
+        if replicas > 0
+            && is_idle(
+                self.activity.active(hostname),
+                self.activity.last_seen(hostname),
+                target.last_change,
+                Duration::from_secs(target.spec.idle_timeout_secs),
+                Instant::now(),
+            )
+        {
+            self.scale(hostname, target, 0).await?;
+            self.activity.set_sleeping(hostname, true);
+            log::info!("put the idle server of {} to sleep", hostname);
+            return Ok(());
+        }
+
+        self.activity
+            .set_sleeping(hostname, replicas == 0 || ready == 0);
+        Ok(())
+    }
+
+    /// It returns the wanted and the ready replicas of a workload, none when it doesn't exist
+    async fn replicas(&self, target: &Target) -> kube::Result<Option<(i32, i32)>> {
+        let client = self.client.clone();
+        Ok(match target.spec.kind {
+            WorkloadKind::Deployment => Api::<Deployment>::namespaced(client, &target.namespace)
+                .get_opt(&target.spec.name)
+                .await?
+                .map(|deployment| {
+                    (
+                        deployment.spec.and_then(|spec| spec.replicas).unwrap_or(1),
+                        deployment
+                            .status
+                            .and_then(|status| status.ready_replicas)
+                            .unwrap_or_default(),
+                    )
+                }),
+            WorkloadKind::StatefulSet => Api::<StatefulSet>::namespaced(client, &target.namespace)
+                .get_opt(&target.spec.name)
+                .await?
+                .map(|statefulset| {
+                    (
+                        statefulset.spec.and_then(|spec| spec.replicas).unwrap_or(1),
+                        statefulset
+                            .status
+                            .and_then(|status| status.ready_replicas)
+                            .unwrap_or_default(),
+                    )
+                }),
+        })
+    }
+
+    /// It sets the replicas of a workload through its scale subresource
+    async fn scale(&self, hostname: &str, target: &Target, replicas: i32) -> kube::Result<()> {
+        let client = self.client.clone();
+        let params = PatchParams::default();
+        let patch = Patch::Merge(json!({ "spec": { "replicas": replicas } }));
+        match target.spec.kind {
+            WorkloadKind::Deployment => {
+                Api::<Deployment>::namespaced(client, &target.namespace)
+                    .patch_scale(&target.spec.name, &params, &patch)
+                    .await?;
+            }
+            WorkloadKind::StatefulSet => {
+                Api::<StatefulSet>::namespaced(client, &target.namespace)
+                    .patch_scale(&target.spec.name, &params, &patch)
+                    .await?;
+            }
+        }
+
+        if let Some(target) = self.targets.lock().unwrap().get_mut(hostname) {
+            target.last_change = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+fn kind_name(kind: WorkloadKind) -> &'static str {
+    match kind {
+        WorkloadKind::Deployment => "Deployment",
+        WorkloadKind::StatefulSet => "StatefulSet",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_scales_down_servers_idle_for_their_timeout() {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(600);
+        let before = |secs| now - Duration::from_secs(secs);
+
+        // nobody joined since the server was scaled up
+        assert!(is_idle(0, None, before(600), timeout, now));
+        assert!(!is_idle(0, None, before(60), timeout, now));
+        // the last player left a minute ago
+        assert!(!is_idle(0, Some(before(60)), before(3600), timeout, now));
+        // a player left before the server was scaled up again
+        assert!(!is_idle(0, Some(before(3600)), before(60), timeout, now));
+        // a player is still connected
+        assert!(!is_idle(1, Some(before(3600)), before(3600), timeout, now));
+    }
+}
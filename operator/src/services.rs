@@ -0,0 +1,174 @@
+use k8s_openapi::api::{core::v1::Service, discovery::v1::EndpointSlice};
+use kube::{api::ListParams, Api, Client};
+
+use crate::status::Outcome;
+
+/// The label linking an EndpointSlice to its Service
+pub const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+
+/// The address a Service is routed to
+///
+/// Properties:
+///
+/// * `host`: The cluster IP of the Service, or its DNS name when it is headless.
+/// * `endpoints`: The `ip:port` addresses of the ready pods of a headless Service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub host: String,
+    pub endpoints: Option<Vec<String>>,
+}
+
+/// It resolves the address of a Service, the one its backends are routed to
+///
+/// A Service with a cluster IP is routed to it, and a headless Service to its DNS name, along with
+/// the addresses of its ready pods the connections are balanced across.
+///
+/// Arguments:
+///
+/// * `client`: The client of the Kubernetes API.
+/// * `namespace`: The namespace of the Service.
+/// * `name`: The name of the Service.
+/// * `port`: The port of the Service the backends are routed to.
+///
+/// Returns:
+///
+/// A Result with the address, or the outcome explaining why the Service can't be routed to
+pub async fn resolve(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    port: u16,
+) -> kube::Result<Result<Address, Outcome>> {
+    let services: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let spec = match services.get_opt(name).await? {
+        Some(service) => service.spec.unwrap_or_default(),
+        None => {
+            return Ok(Err(Outcome::failed(
+                "ServiceNotFound",
+                format!("service {}/{} not found", namespace, name),
+            )))
+        }
+    };
+
+    match spec.cluster_ip.as_deref() {
+        Some("None") => {
+            let port_name = spec
+                .ports
+                .unwrap_or_default()
+                .into_iter()
+                .find(|service_port| service_port.port == i32::from(port))
+                .and_then(|service_port| service_port.name);
+
+            let slices: Api<EndpointSlice> = Api::namespaced(client.clone(), namespace);
+            let slices = slices
+                .list(&ListParams::default().labels(&format!("{}={}", SERVICE_NAME_LABEL, name)))
+                .await?;
+
+            Ok(Ok(Address {
+                host: format!("{}.{}.svc", name, namespace),
+                endpoints: Some(endpoint_addrs(&slices.items, port_name.as_deref(), port)),
+            }))
+        }
+        Some(cluster_ip) if !cluster_ip.is_empty() => Ok(Ok(Address {
+            host: cluster_ip.to_string(),
+            endpoints: None,
+        })),
+        _ => Ok(Err(Outcome::failed(
+            "ServiceHasNoClusterIP",
+            format!("service {}/{} has no cluster IP", namespace, name),
+        ))),
+    }
+}
+
+/// It returns the addresses of the ready pods listed by the EndpointSlices of a Service
+///
+/// Arguments:
+///
+/// * `slices`: The EndpointSlices of the Service.
+/// * `port_name`: The name of the port of the Service, the pods may listen on another number.
+/// * `port`: The port used when the EndpointSlices don't list it.
+///
+/// Returns:
+///
+/// The sorted `ip:port` addresses
+pub fn endpoint_addrs(slices: &[EndpointSlice], port_name: Option<&str>, port: u16) -> Vec<String> {
+    let mut addrs: Vec<String> = slices
+        .iter()
+        .filter(|slice| slice.address_type != "FQDN")
+        .flat_map(|slice| {
+            let port = slice
+                .ports
+                .iter()
+                .flatten()
+                .find(|slice_port| {
+                    slice_port.name.as_deref().unwrap_or_default() == port_name.unwrap_or_default()
+                })
+                .and_then(|slice_port| slice_port.port)
+                .unwrap_or(i32::from(port));
+
+            slice
+                .endpoints
+                .iter()
+                .filter(|endpoint| {
+                    endpoint
+                        .conditions
+                        .as_ref()
+                        .and_then(|conditions| conditions.ready)
+                        != Some(false)
+                })
+                .flat_map(|endpoint| endpoint.addresses.iter())
+                .map(move |address| match address.contains(':') {
+                    true => format!("[{}]:{}", address, port),
+                    false => format!("{}:{}", address, port),
+                })
+        })
+        .collect();
+
+    addrs.sort();
+    addrs.dedup();
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::discovery::v1::{Endpoint, EndpointConditions, EndpointPort};
+
+    use super::*;
+
+    fn endpoint(address: &str, ready: bool) -> Endpoint {
+        Endpoint {
+            addresses: vec![address.to_string()],
+            conditions: Some(EndpointConditions {
+                ready: Some(ready),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_lists_the_ready_endpoints_of_a_service() {
+        let slices = vec![
+            EndpointSlice {
+                address_type: "IPv4".to_string(),
+                endpoints: vec![endpoint("10.0.0.2", true), endpoint("10.0.0.3", false)],
+                ports: Some(vec![EndpointPort {
+                    name: Some("minecraft".to_string()),
+                    port: Some(25566),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            },
+            EndpointSlice {
+                address_type: "IPv6".to_string(),
+                endpoints: vec![endpoint("fd00::1", true)],
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            endpoint_addrs(&slices, Some("minecraft"), 25565),
+            vec!["10.0.0.2:25566".to_string(), "[fd00::1]:25565".to_string()]
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use k8s_openapi::{
+    apimachinery::pkg::apis::meta::v1::{Condition, Time},
+    chrono::Utc,
+};
+
+use crate::crd::MinecraftServerStatus;
+
+/// The type of the condition written back to the `MinecraftServer` resources
+pub const READY: &str = "Ready";
+
+/// The result of the reconciliation of a `MinecraftServer`
+///
+/// Properties:
+///
+/// * `ready`: Whether the proxy routes the hostname of the resource.
+/// * `reason`: A CamelCase reason, e.g. `ServiceNotFound`.
+/// * `message`: A human readable description of the result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub ready: bool,
+    pub reason: &'static str,
+    pub message: String,
+}
+
+impl Outcome {
+    pub fn ready(message: String) -> Self {
+        Self {
+            ready: true,
+            reason: "Routed",
+            message,
+        }
+    }
+
+    pub fn failed(reason: &'static str, message: String) -> Self {
+        Self {
+            ready: false,
+            reason,
+            message,
+        }
+    }
+}
+
+/// It builds the status of a resource from the result of its reconciliation
+///
+/// The transition time of the `Ready` condition is kept while its status doesn't change.
+///
+/// Arguments:
+///
+/// * `current`: The status currently written on the resource.
+/// * `outcome`: The result of the reconciliation.
+/// * `generation`: The generation of the reconciled resource.
+///
+/// Returns:
+///
+/// A MinecraftServerStatus
+pub fn next_status(
+    current: Option<&MinecraftServerStatus>,
+    outcome: &Outcome,
+    generation: Option<i64>,
+) -> MinecraftServerStatus {
+    let status = if outcome.ready { "True" } else { "False" };
+    let previous = current.and_then(|current| {
+        current
+            .conditions
+            .iter()
+            .find(|condition| condition.type_ == READY)
+    });
+
+    let last_transition_time = previous
+        .filter(|previous| previous.status == status)
+        .map(|previous| previous.last_transition_time.clone())
+        .unwrap_or_else(|| Time(Utc::now()));
+
+    MinecraftServerStatus {
+        observed_generation: generation,
+        conditions: vec![Condition {
+            type_: READY.to_string(),
+            status: status.to_string(),
+            reason: outcome.reason.to_string(),
+            message: outcome.message.clone(),
+            observed_generation: generation,
+            last_transition_time,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_transition_time_while_the_status_is_unchanged() {
+        let routed = next_status(None, &Outcome::ready("routed".to_string()), Some(1));
+        assert_eq!(routed.conditions[0].status, "True");
+
+        let again = next_status(
+            Some(&routed),
+            &Outcome::ready("routed".to_string()),
+            Some(1),
+        );
+        assert_eq!(again, routed);
+
+        let failed = next_status(
+            Some(&routed),
+            &Outcome::failed("ServiceNotFound", "missing".to_string()),
+            Some(2),
+        );
+        assert_eq!(failed.conditions[0].status, "False");
+        assert_eq!(failed.conditions[0].reason, "ServiceNotFound");
+        assert_eq!(failed.observed_generation, Some(2));
+    }
+}
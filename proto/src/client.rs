@@ -0,0 +1,56 @@
+use crate::proxy::proxy_service_client::ProxyServiceClient;
+use anyhow::{anyhow, Result};
+use tonic::{
+    metadata::{Ascii, MetadataValue},
+    service::{interceptor::InterceptedService, Interceptor},
+    transport::{Channel, Endpoint},
+    Request, Status,
+};
+
+/// A client of the gRPC API of the proxy, sending the API token with every request
+pub type Client = ProxyServiceClient<InterceptedService<Channel, BearerToken>>;
+
+/// It adds the `authorization: Bearer <token>` header to every request, when a token is set
+#[derive(Clone)]
+pub struct BearerToken {
+    authorization: Option<MetadataValue<Ascii>>,
+}
+
+impl Interceptor for BearerToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(authorization) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// It connects to the gRPC API of a proxy
+///
+/// Arguments:
+///
+/// * `endpoint`: The gRPC API of the proxy
+/// * `token`: The API token of the proxy, if it requires one
+///
+/// Returns:
+///
+/// A Result<Client>
+pub async fn connect(endpoint: String, token: Option<String>) -> Result<Client> {
+    let authorization = token
+        .map(|token| format!("Bearer {}", token).parse())
+        .transpose()
+        .map_err(|_| anyhow!("the API token must be printable ASCII"))?;
+
+    let channel = Endpoint::from_shared(endpoint.clone())
+        .map_err(|e| anyhow!("invalid endpoint {}: {}", endpoint, e))?
+        .connect()
+        .await
+        .map_err(|e| anyhow!("failed to connect to {}: {}", endpoint, e))?;
+
+    Ok(ProxyServiceClient::with_interceptor(
+        channel,
+        BearerToken { authorization },
+    ))
+}
@@ -1,3 +1,5 @@
+pub mod client;
+
 pub mod proxy {
     #![allow(clippy::all)]
     tonic::include_proto!("proxy");
@@ -0,0 +1,70 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use protocol::{
+    decode_string, decode_var_int, encode_string, encode_var_int,
+    packets::serverbound::handshake::Handshake,
+};
+
+/// A handshake of a 1.20.4 client for `play.example.com:25565`, without its length
+const HANDSHAKE: &[u8] = b"\x00\xfd\x05\x10play.example.com\x63\xdd\x02";
+
+fn var_int(c: &mut Criterion) {
+    let values = [0, 127, 25565, 2147483647, -1];
+    let mut encoded = Vec::new();
+    for value in values {
+        encode_var_int(&mut encoded, value);
+    }
+
+    c.bench_function("encode_var_int", |b| {
+        let mut buf = Vec::with_capacity(32);
+        b.iter(|| {
+            buf.clear();
+            for value in values {
+                encode_var_int(&mut buf, black_box(value));
+            }
+        })
+    });
+    c.bench_function("decode_var_int", |b| {
+        b.iter(|| {
+            let mut offset = 0;
+            while offset < encoded.len() {
+                let (_, read) = decode_var_int(black_box(&encoded[offset..])).unwrap();
+                offset += read;
+            }
+        })
+    });
+}
+
+fn string(c: &mut Criterion) {
+    let hostname = "play.example.com\0FML3\0";
+    let mut encoded = Vec::new();
+    encode_string(&mut encoded, hostname);
+
+    c.bench_function("encode_string", |b| {
+        let mut buf = Vec::with_capacity(64);
+        b.iter(|| {
+            buf.clear();
+            encode_string(&mut buf, black_box(hostname));
+        })
+    });
+    c.bench_function("decode_string", |b| {
+        b.iter(|| decode_string(black_box(&encoded), 255 * 4).unwrap())
+    });
+}
+
+fn handshake(c: &mut Criterion) {
+    let handshake = Handshake::decode(HANDSHAKE).unwrap();
+
+    c.bench_function("encode_handshake", |b| {
+        let mut buf = Vec::with_capacity(64);
+        b.iter(|| {
+            buf.clear();
+            black_box(&handshake).encode(&mut buf);
+        })
+    });
+    c.bench_function("decode_handshake", |b| {
+        b.iter(|| Handshake::decode(black_box(HANDSHAKE)).unwrap())
+    });
+}
+
+criterion_group!(benches, var_int, string, handshake);
+criterion_main!(benches);
@@ -0,0 +1,134 @@
+use cfb8::cipher::{inout::InOutBuf, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use sha1::{Digest, Sha1};
+
+use crate::{ProtocolError, Result};
+
+/// It encrypts the packets sent to a client once encryption is enabled
+pub type Encryptor = cfb8::Encryptor<aes::Aes128>;
+
+/// It decrypts the packets received from a client once encryption is enabled
+pub type Decryptor = cfb8::Decryptor<aes::Aes128>;
+
+/// It creates the ciphers of a connection from the shared secret sent by the client
+///
+/// The protocol uses AES/CFB8, with the shared secret as both the key and the IV.
+///
+/// Arguments:
+///
+/// * `shared_secret`: The decrypted shared secret of the client, 16 bytes.
+///
+/// Returns:
+///
+/// A Result with the encryptor and the decryptor
+pub fn ciphers(shared_secret: &[u8]) -> Result<(Encryptor, Decryptor)> {
+    let invalid = |_| ProtocolError::InvalidSharedSecret(shared_secret.len());
+
+    Ok((
+        Encryptor::new_from_slices(shared_secret, shared_secret).map_err(invalid)?,
+        Decryptor::new_from_slices(shared_secret, shared_secret).map_err(invalid)?,
+    ))
+}
+
+/// It encrypts bytes in place, the cipher carries on from the previous bytes
+///
+/// Arguments:
+///
+/// * `encryptor`: The encryptor of the connection.
+/// * `data`: The bytes to encrypt.
+pub fn encrypt(encryptor: &mut Encryptor, data: &mut [u8]) {
+    let (blocks, _) = InOutBuf::from(data).into_chunks();
+    encryptor.encrypt_blocks_inout_mut(blocks);
+}
+
+/// It decrypts bytes in place, the cipher carries on from the previous bytes
+///
+/// Arguments:
+///
+/// * `decryptor`: The decryptor of the connection.
+/// * `data`: The bytes to decrypt.
+pub fn decrypt(decryptor: &mut Decryptor, data: &mut [u8]) {
+    let (blocks, _) = InOutBuf::from(data).into_chunks();
+    decryptor.decrypt_blocks_inout_mut(blocks);
+}
+
+/// It computes the server hash sent to the session server, Minecraft's own hex digest of SHA-1
+///
+/// The digest is read as a signed two's complement number, so it is printed with a `-` when
+/// negative and without leading zeros.
+///
+/// Arguments:
+///
+/// * `server_id`: The server ID of the encryption request, empty since 1.7.
+/// * `shared_secret`: The decrypted shared secret of the client.
+/// * `public_key`: The public key of the encryption request, DER encoded.
+///
+/// Returns:
+///
+/// A String
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let mut digest: [u8; 20] = Sha1::new()
+        .chain_update(server_id.as_bytes())
+        .chain_update(shared_secret)
+        .chain_update(public_key)
+        .finalize()
+        .into();
+
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // two's complement: invert every bit, then add one
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+            *byte = value;
+            carry = overflow;
+        }
+    }
+
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let hex = hex.trim_start_matches('0');
+    match negative {
+        true => format!("-{}", hex),
+        false => hex.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_hash() {
+        // from https://wiki.vg/Protocol_Encryption#Sample_Code
+        assert_eq!(
+            server_hash("Notch", &[], &[]),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            server_hash("simon", &[], &[]),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let secret = [7u8; 16];
+        let (mut encryptor, _) = ciphers(&secret).unwrap();
+        let (_, mut decryptor) = ciphers(&secret).unwrap();
+
+        let mut data = b"Hello, world".to_vec();
+        encrypt(&mut encryptor, &mut data[..5]);
+        encrypt(&mut encryptor, &mut data[5..]);
+        assert_ne!(data, b"Hello, world");
+
+        decrypt(&mut decryptor, &mut data);
+        assert_eq!(data, b"Hello, world");
+        assert!(matches!(
+            ciphers(&[7u8; 15]),
+            Err(ProtocolError::InvalidSharedSecret(15))
+        ));
+    }
+}
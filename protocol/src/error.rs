@@ -0,0 +1,78 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::state::ConnectionState;
+
+/// The result of the reads and the writes of the protocol
+pub type Result<T, E = ProtocolError> = std::result::Result<T, E>;
+
+/// Errors returned by the reads and the writes of the protocol
+///
+/// Every error but `Io` and `UnexpectedState` is a client breaking the protocol: the connection is closed without
+/// further reading, they are counted as malformed packets.
+///
+/// Properties:
+///
+/// * `PacketTooLarge`: The declared length of the packet is above the maximum of its kind.
+/// * `InvalidLength`: The declared length of the packet is zero or negative.
+/// * `VarIntTooLong`: A variable length integer takes more than 5 bytes.
+/// * `StringTooLong`: The declared length of a string is above the maximum of its field.
+/// * `NegativeStringLength`: The declared length of a string is negative.
+/// * `ArrayTooLong`: The declared length of a byte array is above the maximum of its field.
+/// * `NegativeArrayLength`: The declared length of a byte array is negative.
+/// * `InvalidPacketId`: The ID of the packet is not the one expected at this point.
+/// * `InvalidNextState`: The next state of the handshake is not status, login or transfer.
+/// * `InvalidHostname`: The hostname of the handshake is longer than 255 characters, or holds
+///   control characters.
+/// * `Truncated`: The packet ends before its last field.
+/// * `InvalidSharedSecret`: The shared secret of the client is not an AES-128 key.
+/// * `UnexpectedState`: The packet doesn't belong to the state of the connection, the proxy
+///   mixed up the states rather than the client breaking the protocol.
+/// * `Io`: The stream failed or was closed, the client didn't break the protocol.
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("packet of {length} bytes is larger than the maximum of {max} bytes")]
+    PacketTooLarge { length: i32, max: usize },
+    #[error("invalid packet length {0}")]
+    InvalidLength(i32),
+    #[error("VarInt is longer than 5 bytes")]
+    VarIntTooLong,
+    #[error("string of {length} bytes is longer than the maximum of {max} bytes")]
+    StringTooLong { length: i32, max: usize },
+    #[error("negative string length {0}")]
+    NegativeStringLength(i32),
+    #[error("byte array of {length} bytes is longer than the maximum of {max} bytes")]
+    ArrayTooLong { length: i32, max: usize },
+    #[error("negative byte array length {0}")]
+    NegativeArrayLength(i32),
+    #[error("invalid {packet} packet id: {id}")]
+    InvalidPacketId { packet: &'static str, id: i32 },
+    #[error("invalid next state {0}")]
+    InvalidNextState(i32),
+    #[error("invalid hostname: {0}")]
+    InvalidHostname(&'static str),
+    #[error("packet ends before its last field")]
+    Truncated,
+    #[error("invalid shared secret of {0} bytes")]
+    InvalidSharedSecret(usize),
+    #[error("{packet} packet in the {state} state")]
+    UnexpectedState {
+        packet: &'static str,
+        state: ConnectionState,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl ProtocolError {
+    /// It tells whether the error is a client breaking the protocol, rather than the stream
+    /// failing
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_malformed(&self) -> bool {
+        !matches!(self, Self::Io(_) | Self::UnexpectedState { .. })
+    }
+}
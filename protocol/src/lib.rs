@@ -1,7 +1,18 @@
-use anyhow::{anyhow, Result};
+use std::io::{self, Cursor, IoSlice};
+
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+pub use crate::error::{ProtocolError, Result};
+
+pub mod encryption;
+pub mod error;
 pub mod packets;
+pub mod pool;
+pub mod state;
+pub mod version;
+
+/// The longest frame the protocol allows, 3 bytes of VarInt length
+pub const MAX_FRAME_SIZE: usize = (1 << 21) - 1;
 
 /// It reads a variable length integer from a stream
 ///
@@ -26,18 +37,105 @@ where
         result |= value << (7 * num_read);
         num_read += 1;
 
-        if num_read > 5 {
-            return Err(anyhow!("VarInt too big!"));
-        }
-
         if (read & 0b1000_0000) == 0 {
             break;
         }
+
+        // the fifth byte is the last one, it can't announce another
+        if num_read >= 5 {
+            return Err(ProtocolError::VarIntTooLong);
+        }
     }
 
     Ok(result)
 }
 
+/// It decodes a variable length integer from the start of a buffer
+///
+/// Arguments:
+///
+/// * `buf`: The buffer to decode from
+///
+/// Returns:
+///
+/// A Result with the value and the number of bytes it took, a `ProtocolError::Truncated` when
+/// the buffer ends before the value
+pub fn decode_var_int(buf: &[u8]) -> Result<(i32, usize)> {
+    let mut result: i32 = 0;
+
+    for (num_read, read) in buf.iter().enumerate() {
+        result |= ((read & 0b0111_1111) as i32) << (7 * num_read);
+        if (read & 0b1000_0000) == 0 {
+            return Ok((result, num_read + 1));
+        }
+
+        // the fifth byte is the last one, it can't announce another
+        if num_read >= 4 {
+            return Err(ProtocolError::VarIntTooLong);
+        }
+    }
+
+    Err(ProtocolError::Truncated)
+}
+
+/// It encodes a variable length integer at the end of a buffer
+///
+/// Arguments:
+///
+/// * `buf`: The buffer to encode into
+/// * `value`: The value to encode
+pub fn encode_var_int(buf: &mut Vec<u8>, value: i32) {
+    let (encoded, size) = encode_var_int_on_stack(value);
+    buf.extend_from_slice(&encoded[..size]);
+}
+
+/// It encodes a variable length integer into a buffer on the stack, e.g. the length of a packet
+///
+/// Arguments:
+///
+/// * `value`: The value to encode
+///
+/// Returns:
+///
+/// The buffer, and the number of its bytes holding the value
+fn encode_var_int_on_stack(value: i32) -> ([u8; 5], usize) {
+    let mut encoded = [0; 5];
+    // the value is shifted as unsigned, so the negative ones take 5 bytes
+    let mut value = value as u32;
+    let mut size = 0;
+    loop {
+        let temp = (value & 0b0111_1111) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            encoded[size] = temp;
+            return (encoded, size + 1);
+        }
+        encoded[size] = temp | 0b1000_0000;
+        size += 1;
+    }
+}
+
+/// It returns the number of bytes a variable length integer is encoded into, so the length of a
+/// packet can be written before its fields are encoded
+///
+/// Arguments:
+///
+/// * `value`: The value to encode
+///
+/// Returns:
+///
+/// The size, between 1 and 5 bytes
+pub fn var_int_size(value: i32) -> usize {
+    match value as u32 {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        0x4000..=0x1f_ffff => 3,
+        0x20_0000..=0xfff_ffff => 4,
+        _ => 5,
+    }
+}
+
 /// It writes a variable length integer to a stream
 ///
 /// Arguments:
@@ -48,39 +146,43 @@ where
 /// Returns:
 ///
 /// A Result<()>
-pub async fn write_var_int<T>(stream: &mut T, mut value: i32) -> Result<()>
+pub async fn write_var_int<T>(stream: &mut T, value: i32) -> Result<()>
 where
     T: AsyncWriteExt + std::marker::Unpin,
 {
-    loop {
-        let mut temp: i16 = (value & 0b0111_1111) as i16;
-        value >>= 7;
-
-        if value != 0 {
-            temp |= 0b1000_0000;
-        }
-
-        stream.write_i8(temp as i8).await?;
-        if value == 0 {
-            break Ok(());
-        }
-    }
+    let (encoded, size) = encode_var_int_on_stack(value);
+    stream.write_all(&encoded[..size]).await?;
+    Ok(())
 }
 
 /// It reads a string from a stream
 ///
+/// The length is declared by the client, so a negative length, or one above `max_length`, is
+/// rejected with a `ProtocolError` before anything is allocated.
+///
 /// Arguments:
 ///
 /// * `stream`: The stream to read from.
+/// * `max_length`: The maximum length of the string, in bytes.
 ///
 /// Returns:
 ///
 /// A Result<String>
-pub async fn read_string<T>(stream: &mut T) -> Result<String>
+pub async fn read_string<T>(stream: &mut T, max_length: usize) -> Result<String>
 where
     T: AsyncReadExt + std::marker::Unpin,
 {
     let length = read_var_int(stream).await?;
+    if length < 0 {
+        return Err(ProtocolError::NegativeStringLength(length));
+    }
+    if length as usize > max_length {
+        return Err(ProtocolError::StringTooLong {
+            length,
+            max: max_length,
+        });
+    }
+
     let mut buf = vec![0u8; length as usize];
 
     stream.read_exact(&mut buf).await?;
@@ -88,6 +190,48 @@ where
     Ok(String::from_utf8_lossy(&buf).to_string())
 }
 
+/// It decodes a string, prefixed with its length, from the start of a buffer
+///
+/// The length is declared by the client, so a negative length, or one above `max_length`, is
+/// rejected with a `ProtocolError` before anything is copied.
+///
+/// Arguments:
+///
+/// * `buf`: The buffer to decode from.
+/// * `max_length`: The maximum length of the string, in bytes.
+///
+/// Returns:
+///
+/// A Result with the string and the number of bytes it took
+pub fn decode_string(buf: &[u8], max_length: usize) -> Result<(String, usize)> {
+    let (length, prefix) = decode_var_int(buf)?;
+    if length < 0 {
+        return Err(ProtocolError::NegativeStringLength(length));
+    }
+    if length as usize > max_length {
+        return Err(ProtocolError::StringTooLong {
+            length,
+            max: max_length,
+        });
+    }
+
+    let end = prefix + length as usize;
+    let bytes = buf.get(prefix..end).ok_or(ProtocolError::Truncated)?;
+
+    Ok((String::from_utf8_lossy(bytes).to_string(), end))
+}
+
+/// It encodes a string, prefixed with its length, at the end of a buffer
+///
+/// Arguments:
+///
+/// * `buf`: The buffer to encode into.
+/// * `string`: The string to encode.
+pub fn encode_string(buf: &mut Vec<u8>, string: &str) {
+    encode_var_int(buf, string.len() as i32);
+    buf.extend_from_slice(string.as_bytes());
+}
+
 /// It writes a string to a stream
 ///
 /// Arguments:
@@ -102,8 +246,181 @@ pub async fn write_string<T>(stream: &mut T, string: &str) -> Result<()>
 where
     T: AsyncWriteExt + std::marker::Unpin,
 {
-    write_var_int(stream, string.len() as i32).await?;
-    stream.write_all(string.as_bytes()).await?;
+    let mut buf = Vec::with_capacity(5 + string.len());
+    encode_string(&mut buf, string);
+    stream.write_all(&buf).await?;
+    Ok(())
+}
+
+/// It reads a byte array, prefixed with its length, from a stream
+///
+/// The length is declared by the client, so a negative length, or one above `max_length`, is
+/// rejected with a `ProtocolError` before anything is allocated.
+///
+/// Arguments:
+///
+/// * `stream`: The stream to read from.
+/// * `max_length`: The maximum length of the array, in bytes.
+///
+/// Returns:
+///
+/// A Result<Vec<u8>>
+pub async fn read_byte_array<T>(stream: &mut T, max_length: usize) -> Result<Vec<u8>>
+where
+    T: AsyncReadExt + std::marker::Unpin,
+{
+    let length = read_var_int(stream).await?;
+    if length < 0 {
+        return Err(ProtocolError::NegativeArrayLength(length));
+    }
+    if length as usize > max_length {
+        return Err(ProtocolError::ArrayTooLong {
+            length,
+            max: max_length,
+        });
+    }
+
+    let mut buf = vec![0u8; length as usize];
+    stream.read_exact(&mut buf).await?;
+
+    Ok(buf)
+}
+
+/// It writes a byte array, prefixed with its length, to a stream
+///
+/// Arguments:
+///
+/// * `stream`: The stream to write to.
+/// * `bytes`: The bytes to write to the stream.
+///
+/// Returns:
+///
+/// Result<()>
+pub async fn write_byte_array<T>(stream: &mut T, bytes: &[u8]) -> Result<()>
+where
+    T: AsyncWriteExt + std::marker::Unpin,
+{
+    write_var_int(stream, bytes.len() as i32).await?;
+    stream.write_all(bytes).await?;
+    Ok(())
+}
+
+/// It reads an uncompressed packet, prefixed with its length, from a stream
+///
+/// The length is declared by the client, so a length below 1, or one above `max_size`, is
+/// rejected with a `ProtocolError` before anything is allocated.
+///
+/// Arguments:
+///
+/// * `stream`: The stream to read from.
+/// * `max_size`: The maximum length of the packet, in bytes.
+///
+/// Returns:
+///
+/// A Result with the ID and the fields of the packet, to be read from the cursor
+pub async fn read_packet<T>(stream: &mut T, max_size: usize) -> Result<Cursor<Vec<u8>>>
+where
+    T: AsyncReadExt + std::marker::Unpin,
+{
+    let mut data = Vec::new();
+    read_packet_into(stream, max_size, &mut data).await?;
+
+    Ok(Cursor::new(data))
+}
+
+/// It reads an uncompressed packet, prefixed with its length, from a stream into a buffer, e.g.
+/// one of the `pool`
+///
+/// The length is checked like `read_packet` does, before the buffer grows.
+///
+/// Arguments:
+///
+/// * `stream`: The stream to read from.
+/// * `max_size`: The maximum length of the packet, in bytes.
+/// * `buf`: The buffer getting the ID and the fields of the packet, instead of its content.
+///
+/// Returns:
+///
+/// A Result<()>
+pub async fn read_packet_into<T>(stream: &mut T, max_size: usize, buf: &mut Vec<u8>) -> Result<()>
+where
+    T: AsyncReadExt + std::marker::Unpin,
+{
+    let size = read_var_int(stream).await?;
+    if size <= 0 {
+        return Err(ProtocolError::InvalidLength(size));
+    }
+    if size as usize > max_size {
+        return Err(ProtocolError::PacketTooLarge {
+            length: size,
+            max: max_size,
+        });
+    }
+
+    buf.clear();
+    buf.resize(size as usize, 0);
+    stream.read_exact(buf).await?;
+
+    Ok(())
+}
+
+/// It writes an uncompressed packet, prefixed with its length, to a stream
+///
+/// A body longer than `MAX_FRAME_SIZE` is rejected before anything is written.
+///
+/// Arguments:
+///
+/// * `stream`: The stream to write to.
+/// * `data`: The ID and the fields of the packet.
+///
+/// Returns:
+///
+/// Result<()>
+pub async fn write_packet<T>(stream: &mut T, data: &[u8]) -> Result<()>
+where
+    T: AsyncWriteExt + std::marker::Unpin,
+{
+    if data.len() > MAX_FRAME_SIZE {
+        return Err(ProtocolError::PacketTooLarge {
+            length: i32::try_from(data.len()).unwrap_or(i32::MAX),
+            max: MAX_FRAME_SIZE,
+        });
+    }
+
+    let (length, size) = encode_var_int_on_stack(data.len() as i32);
+    write_all_vectored(stream, &length[..size], data).await
+}
+
+/// It writes the length of a packet and its body to a stream with vectored writes, so both
+/// usually go out in a single syscall instead of one each
+///
+/// Arguments:
+///
+/// * `stream`: The stream to write to.
+/// * `header`: The encoded length of the packet.
+/// * `body`: The ID and the fields of the packet.
+///
+/// Returns:
+///
+/// Result<()>
+pub async fn write_all_vectored<T>(stream: &mut T, mut header: &[u8], mut body: &[u8]) -> Result<()>
+where
+    T: AsyncWriteExt + std::marker::Unpin,
+{
+    while !header.is_empty() || !body.is_empty() {
+        let written = stream
+            .write_vectored(&[IoSlice::new(header), IoSlice::new(body)])
+            .await?;
+        if written == 0 {
+            return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+        }
+
+        // a short write may stop anywhere, in the header or in the body
+        let from_header = written.min(header.len());
+        header = &header[from_header..];
+        body = &body[written - from_header..];
+    }
+
     Ok(())
 }
 
@@ -191,8 +508,8 @@ mod tests {
     #[tokio::test]
     async fn test_read_var_int_too_long_err() {
         let mut stream = &b"\xff\xff\xff\xff\xcf"[..];
-        let err = super::read_var_int(&mut stream).await.is_err();
-        assert!(err);
+        let err = super::read_var_int(&mut stream).await.unwrap_err();
+        assert!(matches!(err, super::ProtocolError::VarIntTooLong));
     }
 
     #[tokio::test]
@@ -226,17 +543,132 @@ mod tests {
     #[tokio::test]
     async fn test_read_string_hello_world() {
         let mut stream = &b"\x0cHello, world"[..];
-        let result = super::read_string(&mut stream).await.unwrap();
+        let result = super::read_string(&mut stream, 255).await.unwrap();
         assert_eq!(result, "Hello, world");
     }
 
     #[tokio::test]
     async fn test_read_string_hello_world_to_short() {
         let mut stream = &b"\x0bHello, world"[..];
-        let result = super::read_string(&mut stream).await.unwrap();
+        let result = super::read_string(&mut stream, 255).await.unwrap();
         assert_eq!(result, "Hello, worl");
     }
 
+    #[tokio::test]
+    async fn test_read_string_too_long_err() {
+        let mut stream = &b"\x0cHello, world"[..];
+        let err = super::read_string(&mut stream, 5).await.unwrap_err();
+        assert!(matches!(
+            err,
+            super::ProtocolError::StringTooLong { length: 12, max: 5 }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_string_negative_length_err() {
+        let mut stream = &b"\xff\xff\xff\xff\x0f"[..];
+        let err = super::read_string(&mut stream, 255).await.unwrap_err();
+        assert!(matches!(
+            err,
+            super::ProtocolError::NegativeStringLength(-1)
+        ));
+    }
+
+    #[test]
+    fn test_decode_encode_var_int() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            255,
+            25565,
+            2097151,
+            2147483647,
+            -1,
+            -2147483648,
+        ] {
+            let mut buf = Vec::new();
+            super::encode_var_int(&mut buf, value);
+            assert_eq!(super::decode_var_int(&buf).unwrap(), (value, buf.len()));
+        }
+
+        assert!(matches!(
+            super::decode_var_int(b"\xff\xff\xff\xff\xcf"),
+            Err(super::ProtocolError::VarIntTooLong)
+        ));
+        assert!(matches!(
+            super::decode_var_int(b"\xdd\xc7"),
+            Err(super::ProtocolError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_var_int_size() {
+        for value in [0, 1, 127, 128, 16383, 16384, 2097151, 2097152, i32::MAX, -1] {
+            let mut buf = Vec::new();
+            super::encode_var_int(&mut buf, value);
+            assert_eq!(super::var_int_size(value), buf.len(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_decode_encode_string() {
+        let mut buf = Vec::new();
+        super::encode_string(&mut buf, "Hello, world");
+        assert_eq!(buf, b"\x0cHello, world");
+        assert_eq!(
+            super::decode_string(&buf, 255).unwrap(),
+            ("Hello, world".to_string(), 13)
+        );
+
+        let err = super::decode_string(&buf, 5).unwrap_err();
+        assert!(matches!(
+            err,
+            super::ProtocolError::StringTooLong { length: 12, max: 5 }
+        ));
+        assert!(super::decode_string(&buf[..10], 255).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_packet_in_short_writes() {
+        let data = vec![7; 300];
+        let (mut writer, mut reader) = tokio::io::duplex(3);
+
+        let (written, read) = tokio::join!(super::write_packet(&mut writer, &data), async {
+            let mut read = vec![0; 302];
+            tokio::io::AsyncReadExt::read_exact(&mut reader, &mut read)
+                .await
+                .map(|_| read)
+        });
+        written.unwrap();
+        let read = read.unwrap();
+        assert_eq!(&read[..2], b"\xac\x02");
+        assert_eq!(&read[2..], &data[..]);
+    }
+
+    #[tokio::test]
+    async fn test_write_packet_too_large_err() {
+        let mut stream = Vec::new();
+        super::write_packet(&mut stream, &vec![0; super::MAX_FRAME_SIZE])
+            .await
+            .unwrap();
+        assert_eq!(&stream[..3], b"\xff\xff\x7f");
+
+        let mut stream = Vec::new();
+        let error = super::write_packet(&mut stream, &vec![0; super::MAX_FRAME_SIZE + 1])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            super::ProtocolError::PacketTooLarge {
+                length: 2097152,
+                max: super::MAX_FRAME_SIZE
+            }
+        ));
+        assert!(stream.is_empty());
+    }
+
     #[tokio::test]
     async fn test_write_string_hello_world() {
         let mut stream = Vec::new();
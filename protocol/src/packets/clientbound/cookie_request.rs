@@ -0,0 +1,111 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    packets::clientbound::store_cookie::MAX_COOKIE_KEY_SIZE, read_packet, read_string,
+    read_var_int, write_packet, write_string, write_var_int, ProtocolError, Result,
+};
+
+/// The ID of the cookie request, in the login state
+pub const COOKIE_REQUEST_ID: i32 = 5;
+
+/// `CookieRequest` asks the client for a cookie stored by a server, e.g. the one that transferred
+/// it
+///
+/// See [here](https://wiki.vg/Protocol#Cookie_Request_.28login.29) for more information.
+///
+/// Properties:
+///
+/// * `key`: The identifier of the cookie, e.g. `kubecraft:session`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieRequest {
+    key: String,
+}
+
+impl CookieRequest {
+    /// Creates a new instance of the `CookieRequest` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The identifier of the cookie.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+
+    /// It reads the cookie request from a stream, e.g. the one of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_COOKIE_KEY_SIZE + 8).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != COOKIE_REQUEST_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "cookie request",
+                id,
+            });
+        }
+        let key = read_string(&mut data, MAX_COOKIE_KEY_SIZE).await?;
+
+        Ok(Self { key })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(10 + self.key.len());
+        write_var_int(&mut data, COOKIE_REQUEST_ID).await?;
+        write_string(&mut data, &self.key).await?;
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the identifier of the cookie
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x13\x05\x11kubecraft:session";
+        let mut stream = &packet[..];
+
+        let request = CookieRequest::read(&mut stream).await.unwrap();
+        assert_eq!(request.key(), "kubecraft:session");
+
+        let mut written = Vec::new();
+        request.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+    }
+}
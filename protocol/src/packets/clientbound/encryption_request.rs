@@ -0,0 +1,58 @@
+use tokio::io::AsyncWriteExt;
+
+use crate::{write_byte_array, write_packet, write_string, write_var_int, Result};
+
+/// The protocol version of 1.20.5, whose encryption request tells whether the client must
+/// authenticate
+const SHOULD_AUTHENTICATE_VERSION: i32 = 766;
+
+/// `EncryptionRequest` asks the client to enable encryption, and to authenticate with the
+/// session server.
+///
+/// See [here](https://wiki.vg/Protocol#Encryption_Request) for more information.
+///
+/// Properties:
+///
+/// * `public_key`: The public key of the server, DER encoded.
+/// * `verify_token`: Random bytes the client must send back, encrypted with the public key.
+#[derive(Debug)]
+pub struct EncryptionRequest {
+    public_key: Vec<u8>,
+    verify_token: Vec<u8>,
+}
+
+impl EncryptionRequest {
+    pub fn new(public_key: Vec<u8>, verify_token: Vec<u8>) -> Self {
+        Self {
+            public_key,
+            verify_token,
+        }
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    /// * `version`: The protocol version of the client, from its handshake.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T, version: i32) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::new();
+        write_var_int(&mut data, 1).await?;
+        // the server ID is empty since 1.7
+        write_string(&mut data, "").await?;
+        write_byte_array(&mut data, &self.public_key).await?;
+        write_byte_array(&mut data, &self.verify_token).await?;
+        if version >= SHOULD_AUTHENTICATE_VERSION {
+            data.push(1);
+        }
+
+        write_packet(stream, &data).await
+    }
+}
@@ -0,0 +1,157 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    read_packet, read_string, read_var_int, write_packet, write_string, write_var_int,
+    ProtocolError, Result,
+};
+
+/// The maximum length of a login plugin message, in bytes: its data may take up to 1 MiB
+pub const MAX_LOGIN_PLUGIN_SIZE: usize = 1024 * 1024 + 1024;
+
+/// The maximum length of the channel of a login plugin message, in bytes: 32767 characters of up
+/// to 4 bytes
+pub const MAX_CHANNEL_SIZE: usize = 32767 * 4;
+
+/// The ID of the login plugin request, in the login state
+const LOGIN_PLUGIN_REQUEST_ID: i32 = 4;
+
+/// `LoginPluginRequest` asks the client for custom data during the login, e.g. the forwarding
+/// data of a Velocity backend
+///
+/// See [here](https://wiki.vg/Protocol#Login_Plugin_Request) for more information.
+///
+/// Properties:
+///
+/// * `message_id`: The ID the response of the client must carry back.
+/// * `channel`: The channel of the plugin, e.g. `velocity:player_info`.
+/// * `data`: The data of the request, whose format is the one of the channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginPluginRequest {
+    message_id: i32,
+    channel: String,
+    data: Vec<u8>,
+}
+
+impl LoginPluginRequest {
+    /// Creates a new instance of the `LoginPluginRequest` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `message_id`: The ID the response of the client must carry back.
+    /// * `channel`: The channel of the plugin.
+    /// * `data`: The data of the request.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(message_id: i32, channel: String, data: Vec<u8>) -> Self {
+        Self {
+            message_id,
+            channel,
+            data,
+        }
+    }
+
+    /// It reads the login plugin request from a stream, e.g. the one of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_LOGIN_PLUGIN_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != LOGIN_PLUGIN_REQUEST_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "login plugin request",
+                id,
+            });
+        }
+        let message_id = read_var_int(&mut data).await?;
+        let channel = read_string(&mut data, MAX_CHANNEL_SIZE).await?;
+        let mut payload = Vec::new();
+        data.read_to_end(&mut payload).await?;
+
+        Ok(Self {
+            message_id,
+            channel,
+            data: payload,
+        })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(10 + self.channel.len() + self.data.len());
+        write_var_int(&mut data, LOGIN_PLUGIN_REQUEST_ID).await?;
+        write_var_int(&mut data, self.message_id).await?;
+        write_string(&mut data, &self.channel).await?;
+        data.extend_from_slice(&self.data);
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the ID the response of the client must carry back
+    ///
+    /// Returns:
+    ///
+    /// An i32
+    pub fn message_id(&self) -> i32 {
+        self.message_id
+    }
+
+    /// It returns the channel of the plugin
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// It returns the data of the request
+    ///
+    /// Returns:
+    ///
+    /// A &[u8]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x18\x04\x2a\x14velocity:player_info\x04";
+        let mut stream = &packet[..];
+
+        let request = LoginPluginRequest::read(&mut stream).await.unwrap();
+        assert_eq!(request.message_id(), 42);
+        assert_eq!(request.channel(), "velocity:player_info");
+        assert_eq!(request.data(), b"\x04");
+
+        let mut written = Vec::new();
+        request.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+    }
+}
@@ -1 +1,7 @@
+pub mod cookie_request;
+pub mod encryption_request;
+pub mod login_plugin_request;
+pub mod pong;
 pub mod status;
+pub mod store_cookie;
+pub mod transfer;
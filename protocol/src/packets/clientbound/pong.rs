@@ -0,0 +1,95 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    packets::serverbound::ping::Ping, read_packet, read_var_int, write_packet, write_var_int,
+    ProtocolError, Result,
+};
+
+/// The ID of the pong, in the status state
+const PONG_ID: i32 = 1;
+
+/// `Pong` answers the ping of a client with its payload, the client shows the time it took as
+/// the latency of the server
+///
+/// See [here](https://wiki.vg/Protocol#Pong_Response_.28status.29) for more information.
+///
+/// Properties:
+///
+/// * `payload`: The payload of the ping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pong {
+    payload: i64,
+}
+
+impl Pong {
+    /// Creates a new instance of the `Pong` struct, answering a ping
+    ///
+    /// Arguments:
+    ///
+    /// * `ping`: The ping of the client.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(ping: &Ping) -> Self {
+        Self {
+            payload: ping.payload(),
+        }
+    }
+
+    /// It reads the pong from a stream, e.g. the one of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, 16).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != PONG_ID {
+            return Err(ProtocolError::InvalidPacketId { packet: "pong", id });
+        }
+        let payload = data
+            .read_i64()
+            .await
+            .map_err(|_| ProtocolError::Truncated)?;
+
+        Ok(Self { payload })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(9);
+        write_var_int(&mut data, PONG_ID).await?;
+        data.extend_from_slice(&self.payload.to_be_bytes());
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the payload of the ping
+    ///
+    /// Returns:
+    ///
+    /// An i64
+    pub fn payload(&self) -> i64 {
+        self.payload
+    }
+}
@@ -1,12 +1,19 @@
-use anyhow::Result;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
 
-use crate::{write_string, write_var_int};
+use crate::{encode_string, encode_var_int, pool, var_int_size, Result};
 
+/// The status written by the proxy itself, as the MOTD of a status ping or the disconnect of a
+/// login
+///
+/// Properties:
+///
+/// * `error`: The text shown to the player.
+/// * `favicon`: The favicon of the server list, a `data:image/png;base64,` URI, only written in
+///   the MOTD.
 #[derive(Debug, Default)]
 pub struct Status {
     error: Option<String>,
+    favicon: Option<String>,
 }
 
 impl Status {
@@ -29,7 +36,24 @@ impl Status {
     ///
     /// A new instance of the struct.
     pub fn from_error(error: String) -> Self {
-        Self { error: Some(error) }
+        Self {
+            error: Some(error),
+            favicon: None,
+        }
+    }
+
+    /// It sets the favicon shown next to the MOTD in the server list
+    ///
+    /// Arguments:
+    ///
+    /// * `favicon`: A 64x64 PNG, as a `data:image/png;base64,` URI.
+    ///
+    /// Returns:
+    ///
+    /// The status with the favicon
+    pub fn with_favicon(mut self, favicon: String) -> Self {
+        self.favicon = Some(favicon);
+        self
     }
 
     /// It writes the status packet to a stream as a response to a handshake
@@ -42,16 +66,13 @@ impl Status {
     /// Returns:
     ///
     /// A Result<()>
-    pub async fn write_as_text(&self, stream: &mut TcpStream) -> Result<()> {
+    pub async fn write_as_text<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
         let error = self.error.clone().unwrap(); // todo(iverly): handle error
 
-        let mut data = Vec::new();
-        write_var_int(&mut data, 0).await?;
-        write_string(&mut data, format!("{{\"text\": \"{}\"}}", error).as_str()).await?;
-
-        write_var_int(stream, data.len() as i32).await?;
-        stream.write_all(&data).await?;
-        Ok(())
+        write_response(stream, &format!("{{\"text\": \"{}\"}}", escape(&error))).await
     }
 
     /// It writes the status packet to a stream as a response to a handshake
@@ -64,14 +85,15 @@ impl Status {
     /// Returns:
     ///
     /// A Result<()>
-    pub async fn write_as_motd(&self, stream: &mut TcpStream) -> Result<()> {
+    pub async fn write_as_motd<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
         let error = self.error.clone().unwrap(); // todo(iverly): handle error
 
-        let mut data = Vec::new();
-        write_var_int(&mut data, 0).await?;
-        write_string(
-            &mut data,
-            format!(
+        write_response(
+            stream,
+            &format!(
                 "{{
                     \"version\": {{
                         \"name\": \"\",
@@ -84,16 +106,94 @@ impl Status {
                     }},
                     \"description\": {{
                         \"text\": \"{}\"
-                    }}
+                    }}{}
                 }}",
-                error
-            )
-            .as_str(),
+                escape(&error),
+                match &self.favicon {
+                    Some(favicon) => format!(",\n\"favicon\": \"{}\"", escape(favicon)),
+                    None => String::new(),
+                }
+            ),
         )
-        .await?;
+        .await
+    }
+}
 
-        write_var_int(stream, data.len() as i32).await?;
-        stream.write_all(&data).await?;
-        Ok(())
+/// It writes a status response holding a JSON text to a stream
+///
+/// The length of the packet is computed from the text first, so the packet is encoded once in a
+/// buffer of the pool and written in a single write.
+///
+/// Arguments:
+///
+/// * `stream`: The stream to write to.
+/// * `json`: The JSON text of the response.
+///
+/// Returns:
+///
+/// A Result<()>
+async fn write_response<T>(stream: &mut T, json: &str) -> Result<()>
+where
+    T: AsyncWriteExt + std::marker::Unpin,
+{
+    let length = 1 + var_int_size(json.len() as i32) + json.len();
+
+    let mut data = pool::buffer();
+    data.reserve(var_int_size(length as i32) + length);
+    encode_var_int(&mut data, length as i32);
+    encode_var_int(&mut data, 0);
+    encode_string(&mut data, json);
+    stream.write_all(&data).await?;
+
+    Ok(())
+}
+
+/// It escapes a text for a JSON string, so the messages can hold quotes and span several lines
+///
+/// Arguments:
+///
+/// * `text`: The text to escape.
+///
+/// Returns:
+///
+/// The escaped text, without the surrounding quotes
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{read_packet, read_string, read_var_int};
+
+    #[tokio::test]
+    async fn it_writes_a_multi_line_motd_with_a_favicon() {
+        let status = Status::from_error("Unknown \"server\"\nTry play.example.com".to_string())
+            .with_favicon("data:image/png;base64,iVBORw0KGgo=".to_string());
+
+        let mut data = Vec::new();
+        status.write_as_motd(&mut data).await.unwrap();
+        let mut packet = read_packet(&mut data.as_slice(), 4096).await.unwrap();
+        assert_eq!(read_var_int(&mut packet).await.unwrap(), 0);
+        let json = read_string(&mut packet, 4096).await.unwrap();
+        assert!(json.contains(r#""text": "Unknown \"server\"\nTry play.example.com""#));
+        assert!(json.contains(r#""favicon": "data:image/png;base64,iVBORw0KGgo=""#));
+
+        // the disconnect of a login has no favicon
+        let mut data = Vec::new();
+        status.write_as_text(&mut data).await.unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("favicon"));
     }
 }
@@ -0,0 +1,152 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    read_byte_array, read_packet, read_string, read_var_int, write_byte_array, write_packet,
+    write_string, write_var_int, ProtocolError, Result,
+};
+
+/// The maximum length of the data of a cookie, in bytes
+pub const MAX_COOKIE_SIZE: usize = 5120;
+
+/// The maximum length of the identifier of a cookie, in bytes: 32767 characters of up to 4 bytes
+pub const MAX_COOKIE_KEY_SIZE: usize = 32767 * 4;
+
+/// The maximum length of a packet carrying a cookie, in bytes: its identifier, its data and their
+/// lengths
+pub const MAX_COOKIE_PACKET_SIZE: usize = MAX_COOKIE_KEY_SIZE + MAX_COOKIE_SIZE + 16;
+
+/// The ID of the store cookie, in the configuration state of the 1.20.5 protocol and after
+///
+/// The ID in the play state changes with the versions, the proxy only stores cookies in the
+/// configuration state.
+pub const STORE_COOKIE_ID: i32 = 0x0a;
+
+/// `StoreCookie` stores a cookie on the client, which it keeps across the transfers
+///
+/// See [here](https://wiki.vg/Protocol#Store_Cookie_.28configuration.29) for more information.
+///
+/// Properties:
+///
+/// * `key`: The identifier of the cookie, e.g. `kubecraft:session`.
+/// * `data`: The data of the cookie, at most `MAX_COOKIE_SIZE` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreCookie {
+    key: String,
+    data: Vec<u8>,
+}
+
+impl StoreCookie {
+    /// Creates a new instance of the `StoreCookie` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The identifier of the cookie.
+    /// * `data`: The data of the cookie.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(key: String, data: Vec<u8>) -> Self {
+        Self { key, data }
+    }
+
+    /// It reads the store cookie from a stream, e.g. the one of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_COOKIE_PACKET_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != STORE_COOKIE_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "store cookie",
+                id,
+            });
+        }
+        let key = read_string(&mut data, MAX_COOKIE_KEY_SIZE).await?;
+        let payload = read_byte_array(&mut data, MAX_COOKIE_SIZE).await?;
+
+        Ok(Self { key, data: payload })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(15 + self.key.len() + self.data.len());
+        write_var_int(&mut data, STORE_COOKIE_ID).await?;
+        write_string(&mut data, &self.key).await?;
+        write_byte_array(&mut data, &self.data).await?;
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the identifier of the cookie
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// It returns the data of the cookie
+    ///
+    /// Returns:
+    ///
+    /// A &[u8]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x17\x0a\x11kubecraft:session\x03abc";
+        let mut stream = &packet[..];
+
+        let cookie = StoreCookie::read(&mut stream).await.unwrap();
+        assert_eq!(cookie.key(), "kubecraft:session");
+        assert_eq!(cookie.data(), b"abc");
+
+        let mut written = Vec::new();
+        cookie.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+
+        // a cookie can't hold more than 5 KiB
+        let mut written = Vec::new();
+        StoreCookie::new(
+            "kubecraft:session".to_string(),
+            vec![0; MAX_COOKIE_SIZE + 1],
+        )
+        .write(&mut written)
+        .await
+        .unwrap();
+        assert!(matches!(
+            StoreCookie::read(&mut &written[..]).await,
+            Err(ProtocolError::ArrayTooLong { .. })
+        ));
+    }
+}
@@ -0,0 +1,200 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    encode_string, encode_var_int, packets::serverbound::handshake::MAX_HOSTNAME_SIZE, read_packet,
+    read_string, read_var_int, write_packet, write_string, write_var_int, ProtocolError, Result,
+};
+
+/// The maximum length of a transfer packet, in bytes: a hostname and a port
+const MAX_TRANSFER_SIZE: usize = MAX_HOSTNAME_SIZE + 16;
+
+/// The ID of the transfer, in the configuration state of the 1.20.5 protocol and after
+///
+/// The ID in the play state changes with the versions, see `play_ids`.
+pub const TRANSFER_ID: i32 = 0x0b;
+
+/// The protocol version of 1.20.5, the first one with the transfers
+pub const TRANSFER_VERSION: i32 = 766;
+
+/// The IDs of the start configuration and of the transfer in the play state, by protocol version
+///
+/// The newer versions are left out until their IDs are checked, their players are only
+/// transferred in the configuration state.
+const PLAY_IDS: &[(i32, i32, i32)] = &[
+    (766, 0x69, 0x73),
+    (767, 0x69, 0x73),
+    (768, 0x70, 0x7a),
+    (769, 0x70, 0x7a),
+];
+
+/// It returns the IDs of the clientbound packets of the play state a relay looks for to transfer
+/// a player
+///
+/// Arguments:
+///
+/// * `version`: The protocol version of the client.
+///
+/// Returns:
+///
+/// The IDs of the start configuration and of the transfer, none when they aren't known
+pub fn play_ids(version: i32) -> Option<(i32, i32)> {
+    PLAY_IDS
+        .iter()
+        .find(|(known, _, _)| *known == version)
+        .map(|(_, start_configuration, transfer)| (*start_configuration, *transfer))
+}
+
+/// `Transfer` sends the client to another server, which it joins with a handshake whose next
+/// state is `Transfer`
+///
+/// See [here](https://wiki.vg/Protocol#Transfer_.28configuration.29) for more information.
+///
+/// Properties:
+///
+/// * `hostname`: The hostname of the server the client joins.
+/// * `port`: The port of the server the client joins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    hostname: String,
+    port: u16,
+}
+
+impl Transfer {
+    /// Creates a new instance of the `Transfer` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the server the client joins.
+    /// * `port`: The port of the server the client joins.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(hostname: String, port: u16) -> Self {
+        Self { hostname, port }
+    }
+
+    /// It reads the transfer from a stream, e.g. the one of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_TRANSFER_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != TRANSFER_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "transfer",
+                id,
+            });
+        }
+        let hostname = read_string(&mut data, MAX_HOSTNAME_SIZE).await?;
+        // the port is a VarInt here, unlike in the handshake
+        let port = read_var_int(&mut data).await?;
+
+        Ok(Self {
+            hostname,
+            port: port as u16,
+        })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(15 + self.hostname.len());
+        write_var_int(&mut data, TRANSFER_ID).await?;
+        write_string(&mut data, &self.hostname).await?;
+        write_var_int(&mut data, self.port as i32).await?;
+
+        write_packet(stream, &data).await
+    }
+
+    /// It encodes the packet as a frame of a relayed connection, e.g. to write it between two
+    /// packets of the backend
+    ///
+    /// Arguments:
+    ///
+    /// * `id`: The ID of the transfer in the state of the connection.
+    /// * `compressed`: Whether the backend enabled the compression, the packet is then written
+    ///   with a data length of 0: uncompressed.
+    ///
+    /// Returns:
+    ///
+    /// The frame, with its lengths
+    pub fn encode(&self, id: i32, compressed: bool) -> Vec<u8> {
+        let mut data = Vec::with_capacity(16 + self.hostname.len());
+        if compressed {
+            encode_var_int(&mut data, 0);
+        }
+        encode_var_int(&mut data, id);
+        encode_string(&mut data, &self.hostname);
+        encode_var_int(&mut data, self.port as i32);
+
+        let mut frame = Vec::with_capacity(3 + data.len());
+        encode_var_int(&mut frame, data.len() as i32);
+        frame.extend_from_slice(&data);
+        frame
+    }
+
+    /// It returns the hostname of the server the client joins
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// It returns the port of the server the client joins
+    ///
+    /// Returns:
+    ///
+    /// The port number
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x15\x0b\x10play.example.com\xdd\xc7\x01";
+        let mut stream = &packet[..];
+
+        let transfer = Transfer::read(&mut stream).await.unwrap();
+        assert_eq!(transfer.hostname(), "play.example.com");
+        assert_eq!(transfer.port(), 25565);
+
+        let mut written = Vec::new();
+        transfer.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+        assert_eq!(transfer.encode(TRANSFER_ID, false), packet);
+        assert_eq!(
+            transfer.encode(0x73, true),
+            b"\x16\x00\x73\x10play.example.com\xdd\xc7\x01"
+        );
+        assert_eq!(play_ids(766), Some((0x69, 0x73)));
+        assert_eq!(play_ids(765), None);
+    }
+}
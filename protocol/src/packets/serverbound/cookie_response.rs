@@ -0,0 +1,143 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    packets::clientbound::store_cookie::{
+        MAX_COOKIE_KEY_SIZE, MAX_COOKIE_PACKET_SIZE, MAX_COOKIE_SIZE,
+    },
+    read_byte_array, read_packet, read_string, read_var_int, write_byte_array, write_packet,
+    write_string, write_var_int, ProtocolError, Result,
+};
+
+/// The ID of the cookie response, in the login state
+pub const COOKIE_RESPONSE_ID: i32 = 4;
+
+/// `CookieResponse` is the answer of the client to a cookie request
+///
+/// See [here](https://wiki.vg/Protocol#Cookie_Response_.28login.29) for more information.
+///
+/// Properties:
+///
+/// * `key`: The identifier of the cookie requested.
+/// * `data`: The data of the cookie, none when the client has no such cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieResponse {
+    key: String,
+    data: Option<Vec<u8>>,
+}
+
+impl CookieResponse {
+    /// Creates a new instance of the `CookieResponse` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The identifier of the cookie requested.
+    /// * `data`: The data of the cookie, none when there is no such cookie.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(key: String, data: Option<Vec<u8>>) -> Self {
+        Self { key, data }
+    }
+
+    /// It reads the cookie response from a stream, e.g. the one of a client
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_COOKIE_PACKET_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != COOKIE_RESPONSE_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "cookie response",
+                id,
+            });
+        }
+        let key = read_string(&mut data, MAX_COOKIE_KEY_SIZE).await?;
+        let payload = match data.read_u8().await? != 0 {
+            true => Some(read_byte_array(&mut data, MAX_COOKIE_SIZE).await?),
+            false => None,
+        };
+
+        Ok(Self { key, data: payload })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let payload = self.data.as_deref();
+        let mut data = Vec::with_capacity(16 + self.key.len() + payload.map_or(0, <[u8]>::len));
+        write_var_int(&mut data, COOKIE_RESPONSE_ID).await?;
+        write_string(&mut data, &self.key).await?;
+        data.push(payload.is_some() as u8);
+        if let Some(payload) = payload {
+            write_byte_array(&mut data, payload).await?;
+        }
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the identifier of the cookie requested
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// It returns the data of the cookie
+    ///
+    /// Returns:
+    ///
+    /// The data, none when the client has no such cookie
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x18\x04\x11kubecraft:session\x01\x03abc";
+        let mut stream = &packet[..];
+
+        let response = CookieResponse::read(&mut stream).await.unwrap();
+        assert_eq!(response.key(), "kubecraft:session");
+        assert_eq!(response.data(), Some(&b"abc"[..]));
+
+        let mut written = Vec::new();
+        response.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+
+        // a client without the cookie answers without data
+        let mut stream = &b"\x14\x04\x11kubecraft:session\x00"[..];
+        let response = CookieResponse::read(&mut stream).await.unwrap();
+        assert_eq!(
+            response,
+            CookieResponse::new("kubecraft:session".to_string(), None)
+        );
+    }
+}
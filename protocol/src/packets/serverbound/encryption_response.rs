@@ -0,0 +1,104 @@
+use tokio::io::AsyncReadExt;
+
+use crate::{read_byte_array, read_packet, read_var_int, ProtocolError, Result};
+
+/// The maximum length of an encryption response packet, in bytes
+pub const MAX_ENCRYPTION_RESPONSE_SIZE: usize = 1024;
+
+/// The maximum length of the encrypted shared secret and verify token, for a 1024-bit key
+const MAX_ENCRYPTED_SIZE: usize = 128;
+
+/// The protocol versions of 1.19 to 1.19.2, whose clients may sign a salt instead of sending the
+/// verify token back
+const SIGNED_SALT_VERSIONS: [i32; 2] = [759, 760];
+
+/// `EncryptionResponse` is the answer of the client to the encryption request of the server.
+///
+/// See [here](https://wiki.vg/Protocol#Encryption_Response) for more information.
+///
+/// Properties:
+///
+/// * `shared_secret`: The shared secret of the client, encrypted with the public key.
+/// * `verify_token`: The verify token of the request, encrypted with the public key; none when
+///   a 1.19 client signed a salt instead.
+#[derive(Debug)]
+pub struct EncryptionResponse {
+    shared_secret: Vec<u8>,
+    verify_token: Option<Vec<u8>>,
+}
+
+impl EncryptionResponse {
+    /// It reads the encryption response packet from a stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    /// * `version`: The protocol version of the client, from its handshake.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T, version: i32) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_ENCRYPTION_RESPONSE_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != 1 {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "encryption response",
+                id,
+            });
+        }
+
+        let shared_secret = read_byte_array(&mut data, MAX_ENCRYPTED_SIZE).await?;
+        let has_verify_token =
+            !SIGNED_SALT_VERSIONS.contains(&version) || data.read_u8().await? != 0;
+        let verify_token = match has_verify_token {
+            true => Some(read_byte_array(&mut data, MAX_ENCRYPTED_SIZE).await?),
+            false => None,
+        };
+
+        Ok(Self {
+            shared_secret,
+            verify_token,
+        })
+    }
+
+    /// It returns the shared secret, encrypted with the public key of the server
+    ///
+    /// Returns:
+    ///
+    /// A &[u8]
+    pub fn shared_secret(&self) -> &[u8] {
+        &self.shared_secret
+    }
+
+    /// It returns the verify token, encrypted with the public key of the server
+    ///
+    /// Returns:
+    ///
+    /// The token, none when a 1.19 client signed a salt instead
+    pub fn verify_token(&self) -> Option<&[u8]> {
+        self.verify_token.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read() {
+        let mut stream = &b"\x07\x01\x02\xaa\xbb\x02\xcc\xdd"[..];
+        let response = EncryptionResponse::read(&mut stream, 765).await.unwrap();
+        assert_eq!(response.shared_secret(), b"\xaa\xbb");
+        assert_eq!(response.verify_token(), Some(&b"\xcc\xdd"[..]));
+
+        // a 1.19 client signing a salt, whose signature is left unread
+        let mut stream = &b"\x0d\x01\x02\xaa\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x01"[..];
+        let response = EncryptionResponse::read(&mut stream, 759).await.unwrap();
+        assert_eq!(response.verify_token(), None);
+    }
+}
@@ -1,10 +1,24 @@
-use std::io::Cursor;
-
-use anyhow::{anyhow, Result};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use crate::{read_string, read_var_int, write_string, write_var_int};
+use crate::{
+    decode_string, decode_var_int, encode_string, encode_var_int, pool, read_packet_into,
+    var_int_size, ProtocolError, Result,
+};
+
+/// The maximum length of a handshake packet, in bytes
+///
+/// A handshake holds a hostname of at most 255 characters, the suffixes added by the modded
+/// clients fit in the rest, so the length declared by a client is checked before allocating.
+pub const MAX_HANDSHAKE_SIZE: usize = 1024;
+
+/// The maximum length of the hostname of a handshake, in characters, as the protocol allows
+pub const MAX_HOSTNAME_LENGTH: usize = 255;
+
+/// The maximum length of the hostname of a handshake, in bytes
+///
+/// The protocol allows 255 characters, each of them taking up to 4 bytes in UTF-8.
+pub const MAX_HOSTNAME_SIZE: usize = MAX_HOSTNAME_LENGTH * 4;
 
 /// `Handshake` is a struct that contains a version, a host, a port, and a next state.
 ///
@@ -25,8 +39,33 @@ pub struct Handshake {
 }
 
 impl Handshake {
+    /// Creates a new instance of the `Handshake` struct, e.g. to ping a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `version`: The version of the protocol, -1 for a ping not knowing it.
+    /// * `hostname`: The hostname of the server.
+    /// * `port`: The port of the server.
+    /// * `next_state`: What the client connects for.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(version: i32, hostname: String, port: u16, next_state: NextState) -> Self {
+        Self {
+            version,
+            hostname,
+            port,
+            next_state,
+        }
+    }
+
     /// It reads the handshake packet from a stream and returns a `Handshake` struct
     ///
+    /// A packet declaring more than `MAX_HANDSHAKE_SIZE` bytes, or a length below 1, is rejected
+    /// with a `ProtocolError` before anything is allocated. The packet is read into a buffer of
+    /// the pool.
+    ///
     /// Arguments:
     ///
     /// * `stream`: The stream to read from.
@@ -38,30 +77,74 @@ impl Handshake {
     where
         T: AsyncReadExt + std::marker::Unpin,
     {
-        let size = read_var_int(stream).await?;
-
-        let mut data = vec![0u8; size as usize];
-        stream.read_exact(&mut data).await?;
-        let mut data = Cursor::new(data);
+        let mut data = pool::buffer();
+        read_packet_into(stream, MAX_HANDSHAKE_SIZE, &mut data).await?;
+        Self::decode(&data)
+    }
 
-        let id = read_var_int(&mut data).await?;
+    /// It decodes the handshake packet from its ID and its fields, without its length
+    ///
+    /// Arguments:
+    ///
+    /// * `data`: The ID and the fields of the packet.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let (id, mut offset) = decode_var_int(data)?;
         if id != 0 {
-            return Err(anyhow!("invalid handshake packet id: {}", id));
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "handshake",
+                id,
+            });
         }
 
-        let version = read_var_int(&mut data).await?;
-        let hostname = read_string(&mut data).await?;
-        let port = data.read_u16().await?;
-        let next_state = NextState::from_i32(read_var_int(&mut data).await?)?;
+        let (version, read) = decode_var_int(&data[offset..])?;
+        offset += read;
+        let (hostname, read) = decode_string(&data[offset..], MAX_HOSTNAME_SIZE)?;
+        offset += read;
+        let port = data
+            .get(offset..offset + 2)
+            .map(|port| u16::from_be_bytes([port[0], port[1]]))
+            .ok_or(ProtocolError::Truncated)?;
+        offset += 2;
+        let (next_state, _) = decode_var_int(&data[offset..])?;
+        check_hostname(&hostname)?;
 
         Ok(Self {
             version,
             hostname,
             port,
-            next_state,
+            next_state: NextState::from_i32(next_state)?,
         })
     }
 
+    /// It encodes the packet, prefixed with its length, at the end of a buffer
+    ///
+    /// The length is computed from the fields first, so the packet is encoded in place rather
+    /// than in a buffer of its own copied after its length.
+    ///
+    /// Arguments:
+    ///
+    /// * `buf`: The buffer to encode into.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let length = 1
+            + var_int_size(self.version)
+            + var_int_size(self.hostname.len() as i32)
+            + self.hostname.len()
+            + 2
+            + var_int_size(self.next_state.to_i32());
+
+        buf.reserve(var_int_size(length as i32) + length);
+        encode_var_int(buf, length as i32);
+        encode_var_int(buf, 0);
+        encode_var_int(buf, self.version);
+        encode_string(buf, &self.hostname);
+        buf.extend_from_slice(&self.port.to_be_bytes());
+        encode_var_int(buf, self.next_state.to_i32());
+    }
+
     /// It writes the packet to the stream
     ///
     /// Arguments:
@@ -72,15 +155,9 @@ impl Handshake {
     ///
     /// A Result<()>
     pub async fn write(&self, stream: &mut TcpStream) -> Result<()> {
-        let mut data = Vec::new();
-        write_var_int(&mut data, 0).await?;
-        write_var_int(&mut data, self.version).await?;
-        write_string(&mut data, &self.hostname).await?;
-        data.append(&mut self.port.to_be_bytes().to_vec());
-        write_var_int(&mut data, self.next_state.to_i32()).await?;
-
-        write_var_int(stream, data.len() as i32).await?;
-        stream.write_all(&data).await?;
+        let mut buf = pool::buffer();
+        self.encode(&mut buf);
+        stream.write_all(&buf).await?;
 
         Ok(())
     }
@@ -131,8 +208,34 @@ impl Handshake {
     }
 }
 
+/// It checks the hostname of a handshake before it is logged, counted or routed
+///
+/// The modded clients and the legacy forwarding append fields to the hostname, each after a NUL
+/// character, e.g. `play.example.com\0FML3\0`: they are left as is, only the hostname before them
+/// is checked.
+///
+/// Arguments:
+///
+/// * `hostname`: The hostname of the handshake, with its appended fields.
+///
+/// Returns:
+///
+/// A Result<()>, an `InvalidHostname` error when the hostname is longer than
+/// `MAX_HOSTNAME_LENGTH` characters or holds control characters
+fn check_hostname(hostname: &str) -> Result<()> {
+    let hostname = hostname.split('\0').next().unwrap_or_default();
+    if hostname.chars().count() > MAX_HOSTNAME_LENGTH {
+        return Err(ProtocolError::InvalidHostname("longer than 255 characters"));
+    }
+    if hostname.chars().any(char::is_control) {
+        return Err(ProtocolError::InvalidHostname("control characters"));
+    }
+
+    Ok(())
+}
+
 /// `NextState` is an enum that contains the next state of the game.
-/// It can be either `Status` or `Login`.
+/// It can be either `Status`, `Login` or `Transfer`.
 ///
 /// See [here](https://wiki.vg/Protocol#Serverbound) for more information.
 ///
@@ -140,10 +243,12 @@ impl Handshake {
 ///
 /// * `Status`: The next state is the status state.
 /// * `Login`: The next state is the login state.
+/// * `Transfer`: The next state is the login state, the client was transferred by a server.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum NextState {
     Status,
     Login,
+    Transfer,
 }
 
 impl NextState {
@@ -160,10 +265,24 @@ impl NextState {
         Ok(match num {
             1 => Self::Status,
             2 => Self::Login,
-            _ => return Err(anyhow!("Cannot convert {} to NextState", num)),
+            3 => Self::Transfer,
+            _ => return Err(ProtocolError::InvalidNextState(num)),
         })
     }
 
+    /// It returns the name of the next state, as logged and counted by the proxy
+    ///
+    /// Returns:
+    ///
+    /// `status`, `login` or `transfer`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::Login => "login",
+            Self::Transfer => "transfer",
+        }
+    }
+
     /// It converts a `NextState` to an i32
     ///
     /// Returns:
@@ -173,8 +292,18 @@ impl NextState {
         match self {
             Self::Status => 1,
             Self::Login => 2,
+            Self::Transfer => 3,
         }
     }
+
+    /// It tells whether the client joins the server, after a login or a transfer
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn joins(self) -> bool {
+        matches!(self, Self::Login | Self::Transfer)
+    }
 }
 
 #[cfg(test)]
@@ -191,5 +320,68 @@ mod tests {
         assert_eq!(handshake.hostname(), "localhost");
         assert_eq!(handshake.port(), 25565);
         assert_eq!(handshake.next_state(), NextState::Status);
+
+        // a client transferred by a server joins with the next state 3
+        let mut stream = &b"\x10\x00\xfe\x05\x09localhost\x63\xdd\x03"[..];
+        let handshake = Handshake::read(&mut stream).await.unwrap();
+        assert_eq!(handshake.next_state(), NextState::Transfer);
+        assert!(handshake.next_state().joins());
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        let packet = b"\x0f\x00\x6e\x09\x6c\x6f\x63\x61\x6c\x68\x6f\x73\x74\x63\xdd\x01";
+
+        let handshake = Handshake::decode(&packet[1..]).unwrap();
+        let mut buf = Vec::new();
+        handshake.encode(&mut buf);
+
+        assert_eq!(buf, packet);
+        assert!(matches!(
+            Handshake::decode(&packet[1..12]),
+            Err(ProtocolError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_decode_invalid_hostname_err() {
+        let decode = |hostname: &str| {
+            let handshake = Handshake::new(765, hostname.to_string(), 25565, NextState::Login);
+            let mut buf = Vec::new();
+            handshake.encode(&mut buf);
+            let (_, read) = decode_var_int(&buf).unwrap();
+            Handshake::decode(&buf[read..])
+        };
+
+        assert!(decode("play.example.com\0FML3\0").is_ok());
+        let error = decode("play.example.com\r\nX-Injected: 1").unwrap_err();
+        assert!(matches!(error, ProtocolError::InvalidHostname(_)));
+        assert!(error.is_malformed());
+        assert!(decode(&"a".repeat(MAX_HOSTNAME_LENGTH)).is_ok());
+        assert!(matches!(
+            decode(&"a".repeat(MAX_HOSTNAME_LENGTH + 1)),
+            Err(ProtocolError::InvalidHostname(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_oversized_err() {
+        // declares 2 MiB, only the length is read
+        let mut stream = &b"\x80\x80\x80\x01\x00"[..];
+
+        let error = Handshake::read(&mut stream).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            ProtocolError::PacketTooLarge {
+                length: 2097152,
+                max: MAX_HANDSHAKE_SIZE
+            }
+        ));
+        assert_eq!(stream, &b"\x00"[..]);
+
+        let mut stream = &b"\xff\xff\xff\xff\x0f"[..];
+        let error = Handshake::read(&mut stream).await.unwrap_err();
+        assert!(matches!(error, ProtocolError::InvalidLength(-1)));
     }
 }
@@ -0,0 +1,145 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    packets::clientbound::login_plugin_request::MAX_LOGIN_PLUGIN_SIZE, read_packet, read_var_int,
+    write_packet, write_var_int, ProtocolError, Result,
+};
+
+/// The ID of the login plugin response, in the login state
+const LOGIN_PLUGIN_RESPONSE_ID: i32 = 2;
+
+/// `LoginPluginResponse` is the answer of the client to a login plugin request
+///
+/// See [here](https://wiki.vg/Protocol#Login_Plugin_Response) for more information.
+///
+/// Properties:
+///
+/// * `message_id`: The ID of the request it answers.
+/// * `data`: The data of the response, none when the client doesn't know the channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginPluginResponse {
+    message_id: i32,
+    data: Option<Vec<u8>>,
+}
+
+impl LoginPluginResponse {
+    /// Creates a new instance of the `LoginPluginResponse` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `message_id`: The ID of the request it answers.
+    /// * `data`: The data of the response, none when the channel is unknown.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(message_id: i32, data: Option<Vec<u8>>) -> Self {
+        Self { message_id, data }
+    }
+
+    /// It reads the login plugin response from a stream, e.g. the one of a client
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_LOGIN_PLUGIN_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != LOGIN_PLUGIN_RESPONSE_ID {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "login plugin response",
+                id,
+            });
+        }
+        let message_id = read_var_int(&mut data).await?;
+        let successful = data.read_u8().await? != 0;
+        let payload = match successful {
+            true => {
+                let mut payload = Vec::new();
+                data.read_to_end(&mut payload).await?;
+                Some(payload)
+            }
+            false => None,
+        };
+
+        Ok(Self {
+            message_id,
+            data: payload,
+        })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let payload = self.data.as_deref();
+        let mut data = Vec::with_capacity(11 + payload.map_or(0, <[u8]>::len));
+        write_var_int(&mut data, LOGIN_PLUGIN_RESPONSE_ID).await?;
+        write_var_int(&mut data, self.message_id).await?;
+        data.push(payload.is_some() as u8);
+        if let Some(payload) = payload {
+            data.extend_from_slice(payload);
+        }
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the ID of the request it answers
+    ///
+    /// Returns:
+    ///
+    /// An i32
+    pub fn message_id(&self) -> i32 {
+        self.message_id
+    }
+
+    /// It returns the data of the response
+    ///
+    /// Returns:
+    ///
+    /// The data, none when the client doesn't know the channel
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x05\x02\x2a\x01\xaa\xbb";
+        let mut stream = &packet[..];
+
+        let response = LoginPluginResponse::read(&mut stream).await.unwrap();
+        assert_eq!(response.message_id(), 42);
+        assert_eq!(response.data(), Some(&b"\xaa\xbb"[..]));
+
+        let mut written = Vec::new();
+        response.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+
+        // a client without the plugin answers without data
+        let mut stream = &b"\x03\x02\x2a\x00"[..];
+        let response = LoginPluginResponse::read(&mut stream).await.unwrap();
+        assert_eq!(response, LoginPluginResponse::new(42, None));
+    }
+}
@@ -0,0 +1,151 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{read_packet, read_string, read_var_int, write_packet, ProtocolError, Result};
+
+/// The maximum length of a login start packet, in bytes
+///
+/// Besides the username, the 1.19 clients send their chat signing key and its signature.
+pub const MAX_LOGIN_START_SIZE: usize = 8 * 1024;
+
+/// The maximum length of a username, in bytes: 16 characters of up to 4 bytes
+const MAX_USERNAME_SIZE: usize = 16 * 4;
+
+/// The bit set in the protocol versions of the snapshots, whose fields aren't checked
+const SNAPSHOT_VERSION: i32 = 1 << 30;
+
+/// `LoginStart` is the first packet of the login, it carries the username of the player.
+///
+/// See [here](https://wiki.vg/Protocol#Login_Start) for more information.
+///
+/// Properties:
+///
+/// * `username`: The username of the player.
+/// * `data`: The whole packet, forwarded to the backend as is since its other fields vary with the
+///   protocol version.
+/// * `fields`: The offset of the fields after the username in `data`.
+#[derive(Debug)]
+pub struct LoginStart {
+    username: String,
+    data: Vec<u8>,
+    fields: usize,
+}
+
+impl LoginStart {
+    /// It reads the login start packet from a stream and returns a `LoginStart` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_LOGIN_START_SIZE).await?;
+
+        let id = read_var_int(&mut data).await?;
+        if id != 0 {
+            return Err(ProtocolError::InvalidPacketId {
+                packet: "login start",
+                id,
+            });
+        }
+        let username = read_string(&mut data, MAX_USERNAME_SIZE).await?;
+        let fields = data.position() as usize;
+
+        Ok(Self {
+            username,
+            data: data.into_inner(),
+            fields,
+        })
+    }
+
+    /// It writes the packet to the stream, as it was read
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        write_packet(stream, &self.data).await
+    }
+
+    /// It returns the username of the player
+    ///
+    /// Returns:
+    ///
+    /// A &str
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// It tells whether the fields after the username have the shape the declared protocol
+    /// version gives them, a client lying about its version usually sends the ones of another
+    ///
+    /// Arguments:
+    ///
+    /// * `version`: The protocol version of the handshake.
+    ///
+    /// Returns:
+    ///
+    /// true if the fields fit the version, or the version is a snapshot
+    pub fn fits_version(&self, version: i32) -> bool {
+        let fields = &self.data[self.fields..];
+        match version {
+            ..=758 => fields.is_empty(),
+            // the optional chat signing key, then an optional UUID from 1.19.1
+            759..=760 => matches!(fields, [0 | 1, ..]),
+            // an optional UUID
+            761..=763 => matches!(fields, [0]) || matches!(fields, [1, ..] if fields.len() == 17),
+            764..SNAPSHOT_VERSION => fields.len() == 16,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        // a 1.20.2 login start, with the UUID of the player after its username
+        let packet =
+            b"\x17\x00\x05Notch\x06\x9a\x5e\x8d\x28\x47\x4a\x78\x9d\x3c\x4e\xd1\x7c\xa2\x1f\x1b";
+        let mut stream = &packet[..];
+
+        let login_start = LoginStart::read(&mut stream).await.unwrap();
+        assert_eq!(login_start.username(), "Notch");
+
+        let mut written = Vec::new();
+        login_start.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+    }
+
+    #[tokio::test]
+    async fn test_fits_version() {
+        let packet =
+            b"\x17\x00\x05Notch\x06\x9a\x5e\x8d\x28\x47\x4a\x78\x9d\x3c\x4e\xd1\x7c\xa2\x1f\x1b";
+        let login_start = LoginStart::read(&mut &packet[..]).await.unwrap();
+        assert!(login_start.fits_version(767));
+        assert!(!login_start.fits_version(47));
+        assert!(!login_start.fits_version(762));
+
+        // a 1.8 login start, the username alone
+        let login_start = LoginStart::read(&mut &b"\x07\x00\x05Notch"[..])
+            .await
+            .unwrap();
+        assert!(login_start.fits_version(47));
+        assert!(!login_start.fits_version(767));
+        assert!(login_start.fits_version(SNAPSHOT_VERSION | 200));
+    }
+}
@@ -1 +1,6 @@
+pub mod cookie_response;
+pub mod encryption_response;
 pub mod handshake;
+pub mod login_plugin_response;
+pub mod login_start;
+pub mod ping;
@@ -0,0 +1,125 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{read_packet, read_var_int, write_packet, write_var_int, ProtocolError, Result};
+
+/// The maximum length of a status request or a ping, in bytes
+const MAX_PING_SIZE: usize = 16;
+
+/// The ID of the status request, in the status state
+const STATUS_REQUEST_ID: i32 = 0;
+
+/// The ID of the ping, in the status state
+const PING_ID: i32 = 1;
+
+/// `Ping` is sent by the client once it got the status, to measure the latency shown in the
+/// server list
+///
+/// See [here](https://wiki.vg/Protocol#Ping_Request_.28status.29) for more information.
+///
+/// Properties:
+///
+/// * `payload`: The number the pong must carry back, usually a timestamp of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ping {
+    payload: i64,
+}
+
+impl Ping {
+    /// Creates a new instance of the `Ping` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `payload`: The number the pong must carry back.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(payload: i64) -> Self {
+        Self { payload }
+    }
+
+    /// It reads the ping from a stream, e.g. the one of a client
+    ///
+    /// The status request sent before it is skipped, so the ping can be read right after a
+    /// status written without waiting for the request.
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to read from.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn read<T>(stream: &mut T) -> Result<Self>
+    where
+        T: AsyncReadExt + std::marker::Unpin,
+    {
+        let mut data = read_packet(stream, MAX_PING_SIZE).await?;
+
+        let mut id = read_var_int(&mut data).await?;
+        if id == STATUS_REQUEST_ID {
+            data = read_packet(stream, MAX_PING_SIZE).await?;
+            id = read_var_int(&mut data).await?;
+        }
+        if id != PING_ID {
+            return Err(ProtocolError::InvalidPacketId { packet: "ping", id });
+        }
+        let payload = data
+            .read_i64()
+            .await
+            .map_err(|_| ProtocolError::Truncated)?;
+
+        Ok(Self { payload })
+    }
+
+    /// It writes the packet to the stream
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream to write to.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write<T>(&self, stream: &mut T) -> Result<()>
+    where
+        T: AsyncWriteExt + std::marker::Unpin,
+    {
+        let mut data = Vec::with_capacity(9);
+        write_var_int(&mut data, PING_ID).await?;
+        data.extend_from_slice(&self.payload.to_be_bytes());
+
+        write_packet(stream, &data).await
+    }
+
+    /// It returns the number the pong must carry back
+    ///
+    /// Returns:
+    ///
+    /// An i64
+    pub fn payload(&self) -> i64 {
+        self.payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let packet = b"\x09\x01\x00\x00\x01\x8f\x4a\x6b\x2c\x10";
+        let mut stream = &packet[..];
+
+        let ping = Ping::read(&mut stream).await.unwrap();
+        assert_eq!(ping.payload(), 0x018f_4a6b_2c10);
+
+        let mut written = Vec::new();
+        ping.write(&mut written).await.unwrap();
+        assert_eq!(written, packet);
+
+        // the status request before it is skipped
+        let mut stream = &b"\x01\x00\x09\x01\x00\x00\x01\x8f\x4a\x6b\x2c\x10"[..];
+        assert_eq!(Ping::read(&mut stream).await.unwrap(), ping);
+    }
+}
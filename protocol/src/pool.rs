@@ -0,0 +1,155 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// The most buffers kept for reuse by the pool of the packets
+const MAX_POOLED_BUFFERS: usize = 1024;
+
+/// The largest buffer kept for reuse, in bytes, the larger ones are freed
+///
+/// The handshakes, the login starts and the kicks fit, the rare larger packets don't hold their
+/// memory forever.
+const MAX_POOLED_CAPACITY: usize = 4096;
+
+/// The pool of the buffers of the packets read and written before the connections are relayed
+static PACKETS: BufferPool = BufferPool::new(MAX_POOLED_BUFFERS, MAX_POOLED_CAPACITY);
+
+/// It takes a buffer from the pool of the packets, so a join storm reuses the buffers of the
+/// previous handshakes instead of allocating new ones
+///
+/// Returns:
+///
+/// An empty PooledBuffer, back in the pool once dropped
+pub fn buffer() -> PooledBuffer<'static> {
+    PACKETS.get()
+}
+
+/// A pool of byte buffers, reused across the connections
+///
+/// Properties:
+///
+/// * `buffers`: The buffers waiting to be reused, empty.
+/// * `max_buffers`: The most buffers kept, the next ones returned are freed.
+/// * `max_capacity`: The largest buffer kept, in bytes.
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    max_capacity: usize,
+}
+
+impl BufferPool {
+    /// Creates a new instance of the `BufferPool` struct, empty
+    ///
+    /// Arguments:
+    ///
+    /// * `max_buffers`: The most buffers kept.
+    /// * `max_capacity`: The largest buffer kept, in bytes.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub const fn new(max_buffers: usize, max_capacity: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            max_buffers,
+            max_capacity,
+        }
+    }
+
+    /// It takes a buffer from the pool, a new one when the pool is empty
+    ///
+    /// Returns:
+    ///
+    /// An empty PooledBuffer, back in the pool once dropped
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buf = self
+            .buffers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop()
+            .unwrap_or_default();
+        PooledBuffer { buf, pool: self }
+    }
+
+    /// It keeps a buffer for reuse, unless the pool is full or the buffer too large
+    ///
+    /// Arguments:
+    ///
+    /// * `buf`: The buffer, cleared before it is kept.
+    fn put(&self, mut buf: Vec<u8>) {
+        if buf.capacity() == 0 || buf.capacity() > self.max_capacity {
+            return;
+        }
+        buf.clear();
+
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < self.max_buffers {
+            buffers.push(buf);
+        }
+    }
+}
+
+/// A buffer taken from a pool, which gets it back once dropped
+///
+/// Properties:
+///
+/// * `buf`: The buffer.
+/// * `pool`: The pool the buffer goes back to.
+#[derive(Debug)]
+pub struct PooledBuffer<'a> {
+    buf: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.buf));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reuses_the_buffers() {
+        let pool = BufferPool::new(1, 64);
+
+        let mut buf = pool.get();
+        buf.extend_from_slice(b"a handshake");
+        let allocated = buf.as_ptr();
+        drop(buf);
+
+        let buf = pool.get();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), allocated);
+
+        // a second buffer doesn't fit, a large one isn't kept
+        let mut other = pool.get();
+        other.reserve(8);
+        drop(other);
+        drop(buf);
+        assert_eq!(pool.buffers.lock().unwrap().len(), 1);
+
+        let mut large = pool.get();
+        large.reserve(128);
+        drop(large);
+        assert!(pool.buffers.lock().unwrap().is_empty());
+    }
+}
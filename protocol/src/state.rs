@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::{packets::serverbound::handshake::NextState, ProtocolError, Result};
+
+/// `ConnectionState` is the state of a connection, which tells the packets it can carry
+///
+/// A connection starts with its handshake, which moves it to the status or the login state. A
+/// login ends in the play state, where the proxy only relays the bytes.
+///
+/// See [here](https://wiki.vg/Protocol#Definitions) for more information.
+///
+/// Properties:
+///
+/// * `Handshaking`: The connection waits for its handshake.
+/// * `Status`: The client pings the server list.
+/// * `Login`: The client joins, after a login or a transfer handshake.
+/// * `Play`: The player joined, the connection is relayed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ConnectionState {
+    #[default]
+    Handshaking,
+    Status,
+    Login,
+    Play,
+}
+
+impl ConnectionState {
+    /// It returns the state a handshake moves the connection to
+    ///
+    /// Arguments:
+    ///
+    /// * `next_state`: The next state of the handshake.
+    ///
+    /// Returns:
+    ///
+    /// The state after the handshake
+    pub fn after_handshake(next_state: NextState) -> Self {
+        match next_state {
+            NextState::Status => Self::Status,
+            NextState::Login | NextState::Transfer => Self::Login,
+        }
+    }
+
+    /// It checks that a packet may be read or written in the state
+    ///
+    /// Arguments:
+    ///
+    /// * `expected`: The state of the packet.
+    /// * `packet`: The name of the packet, for the error.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>, an `UnexpectedState` error when the connection is in another state
+    pub fn expect(self, expected: Self, packet: &'static str) -> Result<()> {
+        match self == expected {
+            true => Ok(()),
+            false => Err(ProtocolError::UnexpectedState {
+                packet,
+                state: self,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Handshaking => "handshaking",
+            Self::Status => "status",
+            Self::Login => "login",
+            Self::Play => "play",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_moves_to_the_state_of_the_handshake() {
+        let state = ConnectionState::default();
+        assert!(state
+            .expect(ConnectionState::Handshaking, "handshake")
+            .is_ok());
+
+        let state = ConnectionState::after_handshake(NextState::Transfer);
+        assert_eq!(state, ConnectionState::Login);
+        assert!(state.expect(ConnectionState::Login, "login start").is_ok());
+
+        let error = ConnectionState::after_handshake(NextState::Status)
+            .expect(ConnectionState::Login, "login start")
+            .unwrap_err();
+        assert!(!error.is_malformed());
+        assert_eq!(error.to_string(), "login start packet in the status state");
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+/// The releases of every protocol version since 1.8, the first and the last release speaking it
+///
+/// The snapshots and the versions before 1.8 are left out, the proxy shows their number instead.
+const RELEASES: &[(i32, &str, &str)] = &[
+    (47, "1.8", "1.8.9"),
+    (107, "1.9", "1.9"),
+    (108, "1.9.1", "1.9.1"),
+    (109, "1.9.2", "1.9.2"),
+    (110, "1.9.3", "1.9.4"),
+    (210, "1.10", "1.10.2"),
+    (315, "1.11", "1.11"),
+    (316, "1.11.1", "1.11.2"),
+    (335, "1.12", "1.12"),
+    (338, "1.12.1", "1.12.1"),
+    (340, "1.12.2", "1.12.2"),
+    (393, "1.13", "1.13"),
+    (401, "1.13.1", "1.13.1"),
+    (404, "1.13.2", "1.13.2"),
+    (477, "1.14", "1.14"),
+    (480, "1.14.1", "1.14.1"),
+    (485, "1.14.2", "1.14.2"),
+    (490, "1.14.3", "1.14.3"),
+    (498, "1.14.4", "1.14.4"),
+    (573, "1.15", "1.15"),
+    (575, "1.15.1", "1.15.1"),
+    (578, "1.15.2", "1.15.2"),
+    (735, "1.16", "1.16"),
+    (736, "1.16.1", "1.16.1"),
+    (751, "1.16.2", "1.16.2"),
+    (753, "1.16.3", "1.16.3"),
+    (754, "1.16.4", "1.16.5"),
+    (755, "1.17", "1.17"),
+    (756, "1.17.1", "1.17.1"),
+    (757, "1.18", "1.18.1"),
+    (758, "1.18.2", "1.18.2"),
+    (759, "1.19", "1.19"),
+    (760, "1.19.1", "1.19.2"),
+    (761, "1.19.3", "1.19.3"),
+    (762, "1.19.4", "1.19.4"),
+    (763, "1.20", "1.20.1"),
+    (764, "1.20.2", "1.20.2"),
+    (765, "1.20.3", "1.20.4"),
+    (766, "1.20.5", "1.20.6"),
+    (767, "1.21", "1.21.1"),
+    (768, "1.21.2", "1.21.3"),
+    (769, "1.21.4", "1.21.4"),
+    (770, "1.21.5", "1.21.5"),
+    (771, "1.21.6", "1.21.6"),
+    (772, "1.21.7", "1.21.8"),
+];
+
+/// A range of protocol versions a backend supports, described with the Minecraft releases
+/// speaking them, e.g. for the kick of a client outside of it
+///
+/// Properties:
+///
+/// * `min`: The oldest protocol version, none when unbounded.
+/// * `max`: The newest protocol version, none when unbounded.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct VersionRange {
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl VersionRange {
+    /// It returns whether a protocol version is in the range
+    ///
+    /// Arguments:
+    ///
+    /// * `version`: The protocol version of a handshake.
+    ///
+    /// Returns:
+    ///
+    /// true if the version is neither older than `min` nor newer than `max`
+    pub fn contains(&self, version: i32) -> bool {
+        self.min.is_none_or(|min| version >= min) && self.max.is_none_or(|max| version <= max)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let first = |version: i32| match release(version) {
+            Some((first, _)) => first.to_string(),
+            None => format!("protocol {}", version),
+        };
+        let last = |version: i32| match release(version) {
+            Some((_, last)) => last.to_string(),
+            None => format!("protocol {}", version),
+        };
+
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if min == max => match release(min) {
+                Some((first, last)) if first == last => write!(f, "{}", first),
+                Some((first, last)) => write!(f, "{} to {}", first, last),
+                None => write!(f, "protocol {}", min),
+            },
+            (Some(min), Some(max)) => write!(f, "{} to {}", first(min), last(max)),
+            (Some(min), None) => write!(f, "{} or newer", first(min)),
+            (None, Some(max)) => write!(f, "{} or older", last(max)),
+            (None, None) => write!(f, "any version"),
+        }
+    }
+}
+
+/// It returns the Minecraft releases speaking a protocol version
+///
+/// Arguments:
+///
+/// * `version`: The protocol version.
+///
+/// Returns:
+///
+/// The first and the last release, none for a snapshot or an unknown version
+pub fn release(version: i32) -> Option<(&'static str, &'static str)> {
+    RELEASES
+        .binary_search_by_key(&version, |(protocol, _, _)| *protocol)
+        .ok()
+        .map(|index| (RELEASES[index].1, RELEASES[index].2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_describes_the_releases_of_a_range() {
+        let range = |min, max| VersionRange { min, max };
+
+        assert_eq!(range(Some(765), Some(765)).to_string(), "1.20.3 to 1.20.4");
+        assert_eq!(range(Some(764), Some(764)).to_string(), "1.20.2");
+        assert_eq!(range(Some(763), Some(765)).to_string(), "1.20 to 1.20.4");
+        assert_eq!(range(Some(766), None).to_string(), "1.20.5 or newer");
+        assert_eq!(range(None, Some(754)).to_string(), "1.16.5 or older");
+        assert_eq!(
+            range(Some(1000), None).to_string(),
+            "protocol 1000 or newer"
+        );
+
+        assert!(range(Some(763), Some(765)).contains(764));
+        assert!(!range(Some(763), Some(765)).contains(766));
+        assert!(range(None, None).contains(5));
+    }
+
+    #[test]
+    fn it_lists_the_releases_in_order() {
+        assert!(RELEASES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -0,0 +1,336 @@
+use std::{net::SocketAddr, time::Duration};
+
+use protocol::packets::serverbound::handshake::NextState;
+use serde::Serialize;
+use shared::{
+    bans::{Ban, Offense},
+    recent::{RecentEvent, RecentEventKind},
+};
+use ulid::Ulid;
+
+/// The target of the access log, one line per connection when it ends
+///
+/// The JSON logs merge the fields of the record into the line, and `RUST_LOG=access=off` turns
+/// the access log off.
+pub const ACCESS_TARGET: &str = "access";
+
+/// Why a connection ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseReason {
+    /// The client or the backend closed the relayed connection.
+    Closed,
+    /// The handshake of the client broke the protocol, e.g. it declared an oversized packet.
+    MalformedHandshake,
+    /// No backend matches the hostname, the client was kicked.
+    BackendNotFound,
+    /// The backend is scaled down and the client asked for its status.
+    BackendSleeping,
+    /// The backend is scaled down and the player logging in woke it up.
+    BackendStarting,
+    /// The new connections to the backend exceeded their rate, the client was kicked.
+    BackendBusy,
+    /// The backend couldn't be connected to, or refused the handshake.
+    BackendFailed,
+    /// The player couldn't be authenticated in online mode, it was kicked.
+    AuthenticationFailed,
+    /// The player logged in without pinging the server list first, it was kicked.
+    PingRequired,
+    /// The player logged in again too soon after its previous attempt, it was kicked.
+    Throttled,
+    /// The backend doesn't support the protocol version of the player, it was kicked.
+    UnsupportedVersion,
+    /// The backend is in a maintenance window without fallback, the client was kicked.
+    Maintenance,
+    /// The proxy is in DDoS mode, it answered the status ping without dialing the backend.
+    DdosMode,
+    /// The classifier rejected the connection as a likely bot, the client was kicked.
+    Rejected,
+    /// The username of the player is banned through the API, it was kicked.
+    Banned,
+    /// The connection failed, the error says why.
+    Error,
+}
+
+impl CloseReason {
+    /// It returns the name of the reason, as written in the access log and the metrics
+    ///
+    /// Returns:
+    ///
+    /// The name, in snake case
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::MalformedHandshake => "malformed_handshake",
+            Self::BackendNotFound => "backend_not_found",
+            Self::BackendSleeping => "backend_sleeping",
+            Self::BackendStarting => "backend_starting",
+            Self::BackendBusy => "backend_busy",
+            Self::BackendFailed => "backend_failed",
+            Self::AuthenticationFailed => "authentication_failed",
+            Self::PingRequired => "ping_required",
+            Self::Throttled => "throttled",
+            Self::UnsupportedVersion => "unsupported_version",
+            Self::Maintenance => "maintenance",
+            Self::DdosMode => "ddos_mode",
+            Self::Rejected => "rejected",
+            Self::Banned => "banned",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// The access log record of a connection, filled in as the connection goes
+///
+/// Properties:
+///
+/// * `id`: The ID of the connection.
+/// * `client`: The address of the client.
+/// * `hostname`: The hostname of the handshake, none until it is read.
+/// * `username`: The username of the player, once authenticated in online mode.
+/// * `route`: The hostname of the backend the connection was routed to.
+/// * `backend`: The address of the backend the connection was relayed to.
+/// * `protocol_version`: The protocol version of the handshake.
+/// * `next_state`: `status` or `login`, from the handshake.
+/// * `duration_ms`: How long the connection lasted.
+/// * `bytes_in`: The bytes relayed from the client to the backend.
+/// * `bytes_out`: The bytes relayed from the backend to the client.
+/// * `reason`: Why the connection ended.
+/// * `error`: The error which ended the connection, with the `error` reason.
+/// * `flag`: Why the classifier flagged the connection, none when it didn't.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessRecord {
+    pub id: String,
+    pub client: String,
+    pub hostname: Option<String>,
+    pub username: Option<String>,
+    pub route: Option<String>,
+    pub backend: Option<String>,
+    pub protocol_version: Option<i32>,
+    pub next_state: Option<&'static str>,
+    pub duration_ms: u128,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub reason: CloseReason,
+    pub error: Option<String>,
+    pub flag: Option<String>,
+}
+
+impl AccessRecord {
+    pub fn new(id: Ulid, client: SocketAddr) -> Self {
+        Self {
+            id: id.to_string(),
+            client: client.to_string(),
+            hostname: None,
+            username: None,
+            route: None,
+            backend: None,
+            protocol_version: None,
+            next_state: None,
+            duration_ms: 0,
+            bytes_in: 0,
+            bytes_out: 0,
+            reason: CloseReason::Closed,
+            error: None,
+            flag: None,
+        }
+    }
+
+    /// It records the next state of the handshake
+    ///
+    /// Arguments:
+    ///
+    /// * `next_state`: The next state requested by the client.
+    pub fn set_next_state(&mut self, next_state: NextState) {
+        self.next_state = Some(next_state.as_str());
+    }
+
+    /// It returns the notable event of the connection, kept for `GetRecentEvents`
+    ///
+    /// Returns:
+    ///
+    /// The kick, the failed authentication, the routing miss or the backend failure, none for the
+    /// other connections
+    pub fn recent_event(&self) -> Option<RecentEvent> {
+        let (kind, message) = match self.reason {
+            CloseReason::BackendNotFound => (
+                RecentEventKind::RoutingMiss,
+                "no backend matches the hostname".to_string(),
+            ),
+            CloseReason::BackendSleeping => {
+                (RecentEventKind::Kick, "the backend is sleeping".to_string())
+            }
+            CloseReason::BackendStarting => {
+                (RecentEventKind::Kick, "the backend is starting".to_string())
+            }
+            CloseReason::AuthenticationFailed => (
+                RecentEventKind::Kick,
+                format!(
+                    "failed to authenticate: {}",
+                    self.error.as_deref().unwrap_or("unknown player")
+                ),
+            ),
+            CloseReason::PingRequired => (
+                RecentEventKind::Kick,
+                "logged in without a recent status ping".to_string(),
+            ),
+            CloseReason::Throttled => (
+                RecentEventKind::Kick,
+                "logged in again too soon".to_string(),
+            ),
+            CloseReason::UnsupportedVersion => (
+                RecentEventKind::Kick,
+                format!(
+                    "the backend doesn't support the protocol version {}",
+                    self.protocol_version.unwrap_or_default()
+                ),
+            ),
+            CloseReason::Maintenance => (
+                RecentEventKind::Kick,
+                "the backend is under maintenance".to_string(),
+            ),
+            CloseReason::BackendBusy => (
+                RecentEventKind::Kick,
+                "the connections to the backend exceeded their rate".to_string(),
+            ),
+            CloseReason::Rejected => (
+                RecentEventKind::Kick,
+                format!(
+                    "rejected by the classifier: {}",
+                    self.error.as_deref().unwrap_or("likely a bot")
+                ),
+            ),
+            CloseReason::Banned => (
+                RecentEventKind::Kick,
+                format!(
+                    "the username is banned: {}",
+                    self.error.as_deref().unwrap_or("no reason")
+                ),
+            ),
+            CloseReason::BackendFailed => (
+                RecentEventKind::BackendFailure,
+                self.error.clone().unwrap_or_default(),
+            ),
+            // every status ping gets one under attack, they would flood the events
+            CloseReason::Closed
+            | CloseReason::MalformedHandshake
+            | CloseReason::DdosMode
+            | CloseReason::Error => return None,
+        };
+
+        Some(RecentEvent::now(
+            kind,
+            self.id.clone(),
+            self.client.clone(),
+            self.hostname.clone().unwrap_or_default(),
+            self.backend.clone(),
+            message,
+        ))
+    }
+
+    /// It returns the misbehavior of the client, counted as a strike against its address
+    ///
+    /// Returns:
+    ///
+    /// The failed or malformed handshake, or the kick of a player logging in, none for the other
+    /// connections
+    pub fn offense(&self) -> Option<Offense> {
+        match self.reason {
+            CloseReason::MalformedHandshake => Some(Offense::MalformedPacket),
+            // the connection failed before its handshake was read, e.g. it timed out
+            CloseReason::Error if self.protocol_version.is_none() => {
+                Some(Offense::HandshakeFailure)
+            }
+            CloseReason::BackendNotFound
+            | CloseReason::BackendStarting
+            | CloseReason::AuthenticationFailed
+            | CloseReason::PingRequired
+            | CloseReason::Throttled
+            | CloseReason::Rejected
+                if self.next_state == Some("login") =>
+            {
+                Some(Offense::Kick)
+            }
+            _ => None,
+        }
+    }
+
+    /// It returns the event recording the ban of the client, kept for `GetRecentEvents`
+    ///
+    /// Arguments:
+    ///
+    /// * `ban`: The ban the connection got its client.
+    ///
+    /// Returns:
+    ///
+    /// A RecentEvent
+    pub fn ban_event(&self, ban: &Ban) -> RecentEvent {
+        RecentEvent::now(
+            RecentEventKind::Ban,
+            self.id.clone(),
+            self.client.clone(),
+            self.hostname.clone().unwrap_or_default(),
+            self.backend.clone(),
+            format!(
+                "banned for {} seconds after {} strikes, the last one a {}",
+                (ban.expires_at_ms - ban.banned_at_ms) / 1000,
+                ban.strikes,
+                ban.offense.as_str()
+            ),
+        )
+    }
+
+    /// It logs the record once the connection ended
+    ///
+    /// Arguments:
+    ///
+    /// * `duration`: How long the connection lasted.
+    pub fn log(mut self, duration: Duration) {
+        self.duration_ms = duration.as_millis();
+        match serde_json::to_string(&self) {
+            Ok(line) => tracing::info!(target: ACCESS_TARGET, "{}", line),
+            Err(e) => tracing::error!(id = %self.id, "failed to serialize the access log: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_serializes_the_access_record() {
+        let mut record = AccessRecord::new(
+            Ulid::from_string("01HX5Z3Q8K2M7RZ4T9V6C1B0NA").unwrap(),
+            "10.0.0.7:51712".parse().unwrap(),
+        );
+        record.hostname = Some("lobby.example.com".to_string());
+        record.set_next_state(NextState::Status);
+        record.reason = CloseReason::BackendNotFound;
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "id": "01HX5Z3Q8K2M7RZ4T9V6C1B0NA",
+                "client": "10.0.0.7:51712",
+                "hostname": "lobby.example.com",
+                "username": null,
+                "route": null,
+                "backend": null,
+                "protocol_version": null,
+                "next_state": "status",
+                "duration_ms": 0,
+                "bytes_in": 0,
+                "bytes_out": 0,
+                "reason": "backend_not_found",
+                "error": null,
+                "flag": null,
+            })
+        );
+
+        // a server list ping of an unknown hostname is not a kick
+        assert_eq!(record.offense(), None);
+        record.set_next_state(NextState::Login);
+        assert_eq!(record.offense(), Some(Offense::Kick));
+    }
+}
@@ -0,0 +1,499 @@
+use std::{collections::HashMap, fmt, future::Future, net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use config::{LimitsConfig, ProxyConfig};
+use health::Health;
+use listener::event::Event;
+use metrics::Metrics;
+use shared::{
+    activity::Activity, bans::Bans, capture::Captures, ddos::DdosMode, endpoints::Endpoints,
+    logs::LogRecord, metadata::PodMetadata, pings::Pings, rate_limit::ConnectRateLimits,
+    recent::RecentEvents, sessions::Sessions, stats::Stats, throttle::Throttle,
+};
+use storage::{BackendStore, Storage};
+use tokio::{
+    runtime::Handle,
+    sync::{
+        broadcast,
+        mpsc::{self, Receiver, Sender},
+        RwLock,
+    },
+};
+
+use crate::{
+    fingerprint::Classifier,
+    frames::FrameObserver,
+    hook::{ErrorHook, ErrorHooks},
+    Proxy, ShutdownSignal,
+};
+
+/// The transfers the relays haven't read yet, a few since they come from an operator
+const TRANSFER_CHANNEL_SIZE: usize = 16;
+
+/// It builds a proxy from what the binary reads from its environment, so another binary or a
+/// test can embed the proxy with its own addresses, backends and shutdown signal
+///
+/// Properties:
+///
+/// * `config`: The configuration of the proxy, already validated.
+/// * `storage`: The store of the backends, a new `Storage` when unset.
+/// * `metrics`: The metrics of the proxy, new ones labeled with `metadata` and `labels` when
+///   unset.
+/// * `metadata`: The pod labeling the metrics, none when unset.
+/// * `labels`: The labels added to the metrics, e.g. to tell the proxies of a process apart.
+/// * `shutdown`: The future resolving when the proxy must drain and stop, never when unset.
+/// * `hooks`: The hooks reporting the unexpected errors and the panics.
+/// * `control_plane`: Whether the gRPC API is served, never without the `grpc` feature.
+/// * `inherited`: The socket accepting the Minecraft clients, bound by a previous process.
+/// * `events`: The channel of the events handled by the proxy, a new one when unset.
+/// * `data_plane`: The runtime relaying the players, the one starting the proxy when unset.
+/// * `observer`: The observer of the relayed packets, none when unset.
+/// * `classifier`: The classifier of the connections, none when unset.
+/// * `logs`: The sender of the records logged by the process, `StreamLogs` is unavailable when
+///   unset.
+pub struct ProxyBuilder {
+    config: ProxyConfig,
+    storage: Option<Arc<RwLock<dyn BackendStore>>>,
+    metrics: Option<Metrics>,
+    metadata: Option<PodMetadata>,
+    labels: HashMap<String, String>,
+    shutdown: Option<ShutdownSignal>,
+    hooks: ErrorHooks,
+    control_plane: bool,
+    inherited: Option<std::net::TcpListener>,
+    events: Option<(Sender<Event>, Receiver<Event>)>,
+    data_plane: Option<Handle>,
+    observer: Option<Arc<dyn FrameObserver>>,
+    classifier: Option<Arc<dyn Classifier>>,
+    logs: Option<broadcast::Sender<LogRecord>>,
+}
+
+impl fmt::Debug for ProxyBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyBuilder")
+            .field("config", &self.config)
+            .field("metadata", &self.metadata)
+            .field("labels", &self.labels)
+            .field("shutdown", &self.shutdown.is_some())
+            .field("control_plane", &self.control_plane)
+            .field("data_plane", &self.data_plane.is_some())
+            .field("observer", &self.observer.is_some())
+            .field("classifier", &self.classifier.is_some())
+            .field("logs", &self.logs.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProxyBuilder {
+    /// It starts building a proxy from its configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration of the proxy, already validated.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(config: ProxyConfig) -> Self {
+        Self {
+            config,
+            storage: None,
+            metrics: None,
+            metadata: None,
+            labels: HashMap::new(),
+            shutdown: None,
+            hooks: ErrorHooks::default(),
+            control_plane: cfg!(feature = "grpc"),
+            inherited: None,
+            events: None,
+            data_plane: None,
+            observer: None,
+            classifier: None,
+            logs: None,
+        }
+    }
+
+    /// It binds the Minecraft clients to an address, instead of the one of the `proxy`
+    /// configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address accepting the Minecraft clients.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the address
+    pub fn proxy_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.proxy.host = addr.ip().to_string();
+        self.config.proxy.port = addr.port();
+        self
+    }
+
+    /// It binds the gRPC API to an address, instead of the one of the `listener` configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address of the gRPC API.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the address
+    pub fn listener_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.listener.host = addr.ip().to_string();
+        self.config.listener.port = addr.port();
+        self
+    }
+
+    /// It binds the metrics server to an address, instead of the one of the `metrics`
+    /// configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address of the metrics server.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the address
+    pub fn metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.metrics.host = addr.ip().to_string();
+        self.config.metrics.port = addr.port();
+        self
+    }
+
+    /// It binds the health server to an address, instead of the one of the `health`
+    /// configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address of the health server.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the address
+    pub fn health_addr(mut self, addr: SocketAddr) -> Self {
+        self.config.health.host = addr.ip().to_string();
+        self.config.health.port = addr.port();
+        self
+    }
+
+    /// It accepts the Minecraft clients on a socket bound beforehand, e.g. inherited from the
+    /// previous process of an upgrade with `handoff::systemd_listeners`, instead of binding the
+    /// address of the `proxy` configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `listener`: The listening socket.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the socket
+    pub fn proxy_listener(mut self, listener: std::net::TcpListener) -> Self {
+        self.inherited = Some(listener);
+        self
+    }
+
+    /// It stores the backends in another store than a new `Storage`, e.g. one filled beforehand
+    /// with the backends of a test, or one kept outside of the process
+    ///
+    /// The limits and the static routes of the configuration are applied to it. The operations of
+    /// a `Storage` are recorded into the metrics it was created with, see `ProxyBuilder::metrics`.
+    ///
+    /// Arguments:
+    ///
+    /// * `storage`: The store of the backends.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the store
+    pub fn storage<S: BackendStore + 'static>(mut self, storage: S) -> Self {
+        self.storage = Some(Arc::new(RwLock::new(storage)));
+        self
+    }
+
+    /// It limits the backends stored, instead of the `limits` of the configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `limits`: The quotas and the tombstone retention of the backends.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the limits
+    pub fn limits(mut self, limits: LimitsConfig) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    /// It records the proxy into metrics created beforehand, e.g. to create the storage with
+    /// `Storage::with_metrics(metrics.storage())`
+    ///
+    /// Arguments:
+    ///
+    /// * `metrics`: The metrics of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the metrics
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// It labels the metrics with the pod the proxy runs in, when the `kubernetes.metadata`
+    /// configuration is set
+    ///
+    /// Arguments:
+    ///
+    /// * `metadata`: The pod the proxy runs in, e.g. `PodMetadata::from_env()`.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the metadata
+    pub fn pod_metadata(mut self, metadata: PodMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// It adds labels to every metric of the proxy, e.g. `entrypoint="staging"` when a process
+    /// runs several proxies on the same metrics pipeline
+    ///
+    /// They are ignored when the metrics are given with `ProxyBuilder::metrics`.
+    ///
+    /// Arguments:
+    ///
+    /// * `labels`: The names and the values of the labels.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the labels
+    pub fn metrics_labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+
+    /// It stops the proxy once a signal resolves: it stops accepting the connections, waits for
+    /// the open ones until the drain timeout, and the task of `Proxy::start` ends
+    ///
+    /// Arguments:
+    ///
+    /// * `signal`: The future resolving when the proxy must stop.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the signal
+    pub fn shutdown<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown = Some(Box::pin(signal));
+        self
+    }
+
+    /// It registers a hook reporting the unexpected errors and the panics of the proxy, see
+    /// `Proxy::with_error_hook`
+    ///
+    /// Arguments:
+    ///
+    /// * `hook`: The hook getting the reports.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the hook
+    pub fn error_hook(mut self, hook: Arc<dyn ErrorHook>) -> Self {
+        self.hooks.add(hook);
+        self
+    }
+
+    /// It runs the proxy without its gRPC API, e.g. when another control plane sends the events
+    /// through `Proxy::events`, or none at all and the backends are only the ones of the storage
+    ///
+    /// The `listener` configuration is then ignored.
+    ///
+    /// Returns:
+    ///
+    /// The builder without the gRPC API
+    pub fn without_listener(mut self) -> Self {
+        self.control_plane = false;
+        self
+    }
+
+    /// It handles the events of a channel created beforehand, whose sender is handed to another
+    /// control plane, instead of a new channel of `channels.events` events
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: The sender of the channel, given to the gRPC API, the operator and the federation.
+    /// * `rx`: The receiver of the channel.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the channel
+    pub fn event_channel(mut self, tx: Sender<Event>, rx: Receiver<Event>) -> Self {
+        self.events = Some((tx, rx));
+        self
+    }
+
+    /// It relays the players on another runtime than the one starting the proxy, so the gRPC API,
+    /// the events and the other subsystems never delay their packets
+    ///
+    /// The accept loop and every connection run on that runtime, the rest stays on the runtime
+    /// starting the proxy. The runtime must outlive the proxy, whose accept loop stops on it shortly
+    /// after the proxy is shut down.
+    ///
+    /// Arguments:
+    ///
+    /// * `runtime`: The handle of the runtime, with its IO and time drivers enabled.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the runtime
+    pub fn data_plane(mut self, runtime: Handle) -> Self {
+        self.data_plane = Some(runtime);
+        self
+    }
+
+    /// It hands every packet relayed by the proxy to an observer, e.g. a packet tap
+    ///
+    /// The connections are then split into packets as they are relayed, following the
+    /// compression enabled by the backend, instead of being copied as raw bytes. The compressed
+    /// packets are handed over without their ID.
+    ///
+    /// Arguments:
+    ///
+    /// * `observer`: The observer of the packets.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the observer
+    pub fn frame_observer(mut self, observer: Arc<dyn FrameObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// It hands the fingerprint of every connection to a classifier, flagging or rejecting the
+    /// likely bots before their backend is dialed
+    ///
+    /// The login start of the players is then read by the proxy, to time it and to check it
+    /// against the declared protocol version, before it is forwarded to the backend.
+    ///
+    /// Arguments:
+    ///
+    /// * `classifier`: The classifier of the connections.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the classifier
+    pub fn classifier(mut self, classifier: Arc<dyn Classifier>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// It streams the records logged by the process through the `StreamLogs` RPC, e.g. from a
+    /// logger which also sends them to a broadcast channel
+    ///
+    /// Arguments:
+    ///
+    /// * `logs`: The sender of the records, subscribed by every stream.
+    ///
+    /// Returns:
+    ///
+    /// The builder with the logs
+    pub fn logs(mut self, logs: broadcast::Sender<LogRecord>) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// It builds the proxy, which is started with `Proxy::start`
+    ///
+    /// Returns:
+    ///
+    /// A Result<Proxy>, an error when a label of the metrics is invalid
+    pub fn build(self) -> Result<Proxy> {
+        let config = self.config;
+
+        let metrics = match self.metrics {
+            Some(metrics) => metrics,
+            None => {
+                let mut labels: HashMap<String, String> =
+                    match (&self.metadata, config.kubernetes.metadata) {
+                        (Some(metadata), true) => metadata
+                            .labels()
+                            .into_iter()
+                            .map(|(name, value)| (name.to_string(), value))
+                            .collect(),
+                        _ => Default::default(),
+                    };
+                labels.extend(self.labels);
+                Metrics::with_labels(labels)?
+            }
+        };
+        let metrics = Arc::new(metrics);
+
+        let mut storage = self.storage.unwrap_or_else(|| {
+            Arc::new(RwLock::new(Storage::with_capacity(
+                config.channels.changes,
+                metrics.storage(),
+            )))
+        });
+        let store = Arc::get_mut(&mut storage)
+            .expect("only the builder holds the store")
+            .get_mut();
+        store.set_limits(&config.limits);
+        store.set_static_routes(config.static_backends());
+        // the connections read the routes the storage publishes, never its lock
+        let routes = store.routing_table();
+        let access = store.access_list();
+        let assets = store.status_assets_handle();
+        // the counting carries on from the totals of a storage filled beforehand
+        let stats = Stats::default();
+        stats.restore(&store.backend_totals());
+
+        let health = Arc::new(Health::new(Duration::from_secs(
+            config.health.drain_timeout_secs,
+        )));
+        health.set_storage_loaded();
+
+        let (tx, rx) = self
+            .events
+            .unwrap_or_else(|| mpsc::channel(config.channels.events));
+        let (taps, _) = broadcast::channel(config.channels.taps);
+        let (transfers, _) = broadcast::channel(TRANSFER_CHANNEL_SIZE);
+        let ddos = Arc::new(DdosMode::new(config.ddos_mode.enabled));
+
+        Ok(Proxy {
+            config: Arc::new(ArcSwap::from_pointee(config)),
+            storage,
+            routes,
+            access,
+            assets,
+            metrics,
+            health,
+            activity: Arc::new(Activity::default()),
+            endpoints: Arc::new(Endpoints::default()),
+            recent: Arc::new(RecentEvents::default()),
+            bans: Arc::new(Bans::default()),
+            pings: Arc::new(Pings::default()),
+            throttle: Arc::new(Throttle::default()),
+            connect_rates: Arc::new(ConnectRateLimits::default()),
+            hooks: self.hooks,
+            shutdown: std::sync::Mutex::new(self.shutdown),
+            control_plane: self.control_plane,
+            inherited: self.inherited,
+            events: tx,
+            received: Some(rx),
+            data_plane: self.data_plane,
+            observer: self.observer,
+            classifier: self.classifier,
+            // only the gRPC API streams the logs
+            #[cfg(feature = "grpc")]
+            logs: self.logs,
+            taps,
+            captures: Arc::new(Captures::default()),
+            sessions: Arc::new(Sessions::default()),
+            transfers,
+            stats: Arc::new(stats),
+            ddos,
+        })
+    }
+}
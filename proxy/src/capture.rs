@@ -0,0 +1,250 @@
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use ulid::Ulid;
+
+use crate::frames::Direction;
+
+/// The first bytes of a capture file, with the version of its format
+const MAGIC: &[u8; 8] = b"KCCAP01\n";
+
+/// A capture of a connection, started through the API to diagnose a protocol incompatibility
+/// between a client and a backend
+///
+/// The first bytes of both directions are recorded, unencrypted, and written to a file once the
+/// connection ends. The file starts with a header, then holds the records in the order they were
+/// read:
+///
+/// * header: `KCCAP01\n`, the start as microseconds since the Unix epoch (u64), the length of
+///   the hostname (u8) and the hostname, and the 16 bytes of the ID of the connection.
+/// * record: the direction (u8, 0 serverbound and 1 clientbound), the microseconds since the
+///   start (u64), the length of the bytes (u32) and the bytes.
+///
+/// Every integer is big-endian.
+///
+/// Properties:
+///
+/// * `connection`: The ID of the connection, the one of its access log record.
+/// * `hostname`: The hostname of the backend.
+/// * `directory`: The directory the file is written to.
+/// * `max_bytes`: The first bytes of each direction recorded.
+/// * `started`: When the relay started, the records are timed from it.
+/// * `started_at`: When the relay started, for the header and the name of the file.
+#[derive(Debug)]
+pub(crate) struct Capture {
+    connection: Ulid,
+    hostname: String,
+    directory: PathBuf,
+    max_bytes: usize,
+    started: Instant,
+    started_at: SystemTime,
+}
+
+/// The bytes of one direction of a captured connection
+///
+/// Properties:
+///
+/// * `direction`: The way the bytes travel.
+/// * `started`: When the relay started.
+/// * `remaining`: The bytes left to record.
+/// * `records`: The bytes recorded, with the time since the start they were read at.
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    direction: Direction,
+    started: Instant,
+    remaining: usize,
+    records: Vec<(Duration, Vec<u8>)>,
+}
+
+impl Capture {
+    /// Creates a new instance of the `Capture` struct, starting now
+    ///
+    /// Arguments:
+    ///
+    /// * `connection`: The ID of the connection.
+    /// * `hostname`: The hostname of the backend.
+    /// * `directory`: The directory the file is written to.
+    /// * `max_bytes`: The first bytes of each direction recorded.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub(crate) fn new(
+        connection: Ulid,
+        hostname: &str,
+        directory: PathBuf,
+        max_bytes: usize,
+    ) -> Self {
+        Self {
+            connection,
+            hostname: hostname.to_string(),
+            directory,
+            max_bytes,
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+        }
+    }
+
+    /// It returns a recorder of one direction of the connection
+    ///
+    /// Arguments:
+    ///
+    /// * `direction`: The way the bytes travel.
+    ///
+    /// Returns:
+    ///
+    /// A Recorder
+    pub(crate) fn recorder(&self, direction: Direction) -> Recorder {
+        Recorder {
+            direction,
+            started: self.started,
+            remaining: self.max_bytes,
+            records: Vec::new(),
+        }
+    }
+
+    /// It writes the capture to a new file of its directory, created when missing
+    ///
+    /// Arguments:
+    ///
+    /// * `recorders`: The recorders of both directions.
+    ///
+    /// Returns:
+    ///
+    /// An io::Result with the path of the file
+    pub(crate) async fn write(self, recorders: [Recorder; 2]) -> io::Result<PathBuf> {
+        let since_epoch = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // the hostnames are DNS names, anything else is kept out of the path
+        let hostname: String = self
+            .hostname
+            .chars()
+            .map(
+                |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    true => c,
+                    false => '_',
+                },
+            )
+            .collect();
+        let path = self.directory.join(format!(
+            "{}-{}-{}.kccap",
+            since_epoch.as_millis(),
+            hostname,
+            self.connection
+        ));
+        let data = self.encode(since_epoch, recorders);
+
+        let written = path.clone();
+        tokio::task::spawn_blocking(move || {
+            std::fs::create_dir_all(&self.directory)?;
+            std::fs::write(&written, data)
+        })
+        .await??;
+        Ok(path)
+    }
+
+    /// It encodes the capture in the format of its files
+    ///
+    /// Arguments:
+    ///
+    /// * `since_epoch`: The start, since the Unix epoch.
+    /// * `recorders`: The recorders of both directions.
+    ///
+    /// Returns:
+    ///
+    /// The bytes of the file
+    fn encode(&self, since_epoch: Duration, recorders: [Recorder; 2]) -> Vec<u8> {
+        let mut records: Vec<_> = recorders
+            .into_iter()
+            .flat_map(|recorder| {
+                let direction = recorder.direction;
+                recorder
+                    .records
+                    .into_iter()
+                    .map(move |(offset, bytes)| (offset, direction, bytes))
+            })
+            .collect();
+        records.sort_by_key(|(offset, _, _)| *offset);
+
+        let hostname = &self.hostname.as_bytes()[..self.hostname.len().min(255)];
+        let mut data = Vec::with_capacity(
+            MAGIC.len()
+                + 25
+                + hostname.len()
+                + records.iter().map(|r| 13 + r.2.len()).sum::<usize>(),
+        );
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&(since_epoch.as_micros() as u64).to_be_bytes());
+        data.push(hostname.len() as u8);
+        data.extend_from_slice(hostname);
+        data.extend_from_slice(&self.connection.to_bytes());
+        for (offset, direction, bytes) in records {
+            data.push(match direction {
+                Direction::Serverbound => 0,
+                Direction::Clientbound => 1,
+            });
+            data.extend_from_slice(&(offset.as_micros() as u64).to_be_bytes());
+            data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            data.extend_from_slice(&bytes);
+        }
+        data
+    }
+}
+
+impl Recorder {
+    /// It records the bytes just read, until the maximum of the capture
+    ///
+    /// Arguments:
+    ///
+    /// * `bytes`: The bytes read, unencrypted.
+    pub(crate) fn record(&mut self, bytes: &[u8]) {
+        let recorded = bytes.len().min(self.remaining);
+        if recorded == 0 {
+            return;
+        }
+        self.remaining -= recorded;
+        self.records
+            .push((self.started.elapsed(), bytes[..recorded].to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_the_first_bytes_of_both_directions() {
+        let connection = Ulid::new();
+        let capture = Capture::new(connection, "lobby.example.com", PathBuf::new(), 4);
+        let mut serverbound = capture.recorder(Direction::Serverbound);
+        let mut clientbound = capture.recorder(Direction::Clientbound);
+        serverbound.record(b"abc");
+        clientbound.record(b"hello");
+        serverbound.record(b"def");
+
+        let data = capture.encode(Duration::from_micros(42), [serverbound, clientbound]);
+        let (header, records) = data.split_at(8 + 8 + 1 + 17 + 16);
+        assert_eq!(&header[..8], MAGIC);
+        assert_eq!(header[8..16], 42u64.to_be_bytes());
+        assert_eq!(&header[16..34], b"\x11lobby.example.com");
+        assert_eq!(header[34..], connection.to_bytes());
+
+        // the records are in the order they were read, cut at the maximum
+        fn record(data: &[u8]) -> (u8, &[u8], &[u8]) {
+            let length = u32::from_be_bytes(data[9..13].try_into().unwrap()) as usize;
+            (data[0], &data[13..13 + length], &data[13 + length..])
+        }
+        let (direction, bytes, records) = record(records);
+        assert_eq!((direction, bytes), (0, &b"abc"[..]));
+        let (direction, bytes, records) = record(records);
+        assert_eq!((direction, bytes), (1, &b"hell"[..]));
+        let (direction, bytes, records) = record(records);
+        assert_eq!((direction, bytes), (0, &b"d"[..]));
+        assert!(records.is_empty());
+    }
+}
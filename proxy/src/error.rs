@@ -0,0 +1,95 @@
+use std::io;
+
+use protocol::ProtocolError;
+use shared::models::forwarding::ForwardingMode;
+use thiserror::Error;
+
+use crate::online_mode::AuthenticationError;
+
+/// Errors ending a connection of the proxy before it was closed
+///
+/// The kicks are not errors: a client kicked by the proxy is closed with the reason of the kick.
+///
+/// Properties:
+///
+/// * `ClientStream`: The stream of the client failed outside of a packet.
+/// * `HandshakeTimeout`: The client didn't send its handshake in time.
+/// * `Handshake`: The handshake of the client couldn't be read.
+/// * `LoginStartTimeout`: The player didn't send its login start in time, for a username rule.
+/// * `LoginStart`: The login start of the player couldn't be read, for a username rule.
+/// * `Kick`: The kick couldn't be written to the client.
+/// * `Authentication`: The player couldn't be authenticated, without being rejected.
+/// * `ForwardingUnsupported`: The backend expects a forwarded identity, which online mode doesn't
+///   forward.
+/// * `ConnectTimeout`: The backend didn't accept the connection in time.
+/// * `Connect`: The backend refused the connection.
+/// * `ServerStream`: The stream of the backend failed outside of a packet.
+/// * `Forward`: A packet of the client couldn't be forwarded to the backend.
+/// * `Relay`: The relay between the client and the backend failed.
+#[derive(Debug, Error)]
+pub enum ConnectionError {
+    #[error("the client stream failed")]
+    ClientStream(#[from] io::Error),
+    #[error("timed out reading the handshake packet")]
+    HandshakeTimeout,
+    #[error("failed to read the handshake packet")]
+    Handshake(#[source] ProtocolError),
+    #[error("timed out reading the login start packet")]
+    LoginStartTimeout,
+    #[error("failed to read the login start packet")]
+    LoginStart(#[source] ProtocolError),
+    #[error("failed to kick the client")]
+    Kick(#[source] ProtocolError),
+    #[error("failed to authenticate the player")]
+    Authentication(#[source] AuthenticationError),
+    #[error("the {mode} forwarding of {backend} is not supported in online mode")]
+    ForwardingUnsupported {
+        backend: String,
+        mode: ForwardingMode,
+    },
+    #[error("failed to connect to {backend}: timed out")]
+    ConnectTimeout { backend: String },
+    #[error("failed to connect to {backend}")]
+    Connect {
+        backend: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("the server stream for {backend} failed")]
+    ServerStream {
+        backend: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("failed to write the {packet} packet to {backend}")]
+    Forward {
+        packet: &'static str,
+        backend: String,
+        #[source]
+        source: ProtocolError,
+    },
+    #[error("failed to copy the streams with {backend}")]
+    Relay {
+        backend: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl ConnectionError {
+    /// It tells whether the backend failed before the connection was relayed, an expected failure
+    /// kept in the recent events rather than reported
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_backend_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::ConnectTimeout { .. }
+                | Self::Connect { .. }
+                | Self::ServerStream { .. }
+                | Self::Forward { .. }
+        )
+    }
+}
@@ -0,0 +1,78 @@
+use std::{net::IpAddr, time::Duration};
+
+use protocol::packets::serverbound::handshake::NextState;
+use ulid::Ulid;
+
+/// What a client did before the proxy dials its backend, for a classifier to tell the bots
+/// from the players
+///
+/// Properties:
+///
+/// * `connection`: The ID of the connection, the one of its access log record.
+/// * `client`: The address of the client.
+/// * `hostname`: The hostname of the handshake.
+/// * `protocol_version`: The protocol version the handshake declared.
+/// * `next_state`: Whether the client pings the status or logs in.
+/// * `handshake_delay`: The time between the accept and the end of the handshake.
+/// * `login_delay`: The time between the handshake and the login start, none for the status
+///   pings.
+/// * `username`: The username of the login start, none for the status pings.
+/// * `fits_version`: Whether the login start has the fields of the declared protocol version,
+///   none for the status pings. The bots often declare a version they don't speak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint<'a> {
+    pub connection: Ulid,
+    pub client: IpAddr,
+    pub hostname: &'a str,
+    pub protocol_version: i32,
+    pub next_state: NextState,
+    pub handshake_delay: Duration,
+    pub login_delay: Option<Duration>,
+    pub username: Option<&'a str>,
+    pub fits_version: Option<bool>,
+}
+
+/// What the classifier makes of a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The connection goes on.
+    Allow,
+    /// The connection goes on, its access log record and the metrics carry the reason.
+    Flag(String),
+    /// The client is kicked with the reason, before the backend is dialed.
+    Reject(String),
+}
+
+impl Verdict {
+    /// It returns the name of the verdict, as labeled in the metrics
+    ///
+    /// Returns:
+    ///
+    /// The name, in snake case
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Flag(_) => "flag",
+            Self::Reject(_) => "reject",
+        }
+    }
+}
+
+/// A classifier of the connections, flagging or rejecting the likely bots from their
+/// fingerprint
+///
+/// It is called by every connection once its handshake, and the login start of a login, are
+/// read, before the session server and the backend are reached, so it should decide right away
+/// rather than block.
+pub trait Classifier: Send + Sync {
+    /// It classifies a connection
+    ///
+    /// Arguments:
+    ///
+    /// * `fingerprint`: What the client did so far.
+    ///
+    /// Returns:
+    ///
+    /// The verdict
+    fn classify(&self, fingerprint: &Fingerprint<'_>) -> Verdict;
+}
@@ -0,0 +1,295 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc,
+    },
+};
+
+use protocol::{decode_var_int, ProtocolError, MAX_FRAME_SIZE};
+use ulid::Ulid;
+
+/// The ID of the set compression packet, clientbound in the login state
+pub(crate) const SET_COMPRESSION_ID: i32 = 3;
+
+/// The ID of the login success packet, clientbound in the login state
+const LOGIN_SUCCESS_ID: i32 = 2;
+
+/// The way a frame travels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the client to the backend.
+    Serverbound,
+    /// From the backend to the client.
+    Clientbound,
+}
+
+/// A packet relayed between a client and its backend, split from the stream
+///
+/// Properties:
+///
+/// * `direction`: The way the packet travels.
+/// * `login`: Whether the packet belongs to the login, before the login success of the backend.
+/// * `id`: The ID of the packet, none when it is compressed.
+/// * `compressed`: Whether the packet is compressed, its data is then the zlib stream.
+/// * `data`: The ID and the fields of the packet, without its lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub direction: Direction,
+    pub login: bool,
+    pub id: Option<i32>,
+    pub compressed: bool,
+    pub data: &'a [u8],
+}
+
+/// An observer of the packets relayed by the proxy, e.g. a packet tap
+///
+/// It is called from the relay of the connections, for every packet, so it should hand the
+/// packet over rather than block.
+pub trait FrameObserver: Send + Sync {
+    /// It observes a packet relayed by the proxy
+    ///
+    /// Arguments:
+    ///
+    /// * `connection`: The ID of the connection, the one of its access log record.
+    /// * `frame`: The packet.
+    fn observe(&self, connection: Ulid, frame: &Frame<'_>);
+}
+
+/// The framing of a connection, shared by both of its directions: the backend enables the
+/// compression of both, and ends the login of both
+///
+/// Properties:
+///
+/// * `threshold`: The compression threshold set by the backend, negative until it is set.
+/// * `logged_in`: Whether the backend sent its login success.
+#[derive(Debug)]
+pub struct Framing {
+    threshold: AtomicI32,
+    logged_in: AtomicBool,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self {
+            threshold: AtomicI32::new(-1),
+            logged_in: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Framing {
+    /// It returns the compression threshold set by the backend
+    ///
+    /// Returns:
+    ///
+    /// The threshold in bytes, none while the packets are uncompressed
+    pub fn compression_threshold(&self) -> Option<usize> {
+        usize::try_from(self.threshold.load(Ordering::Acquire)).ok()
+    }
+}
+
+/// It splits one direction of a relayed connection into packets, following the compression set
+/// by the backend
+///
+/// The bytes are relayed as they are read, the decoder only looks at them: a stream it can't
+/// frame, e.g. one declaring a packet over the maximum, is left alone for the rest of the
+/// connection instead of being cut.
+///
+/// Properties:
+///
+/// * `direction`: The way the packets travel.
+/// * `framing`: The framing shared with the other direction.
+/// * `buf`: The bytes of the packet being read, and of the ones after it.
+/// * `broken`: Whether the stream couldn't be framed, nothing is decoded anymore.
+pub struct FrameDecoder {
+    direction: Direction,
+    framing: Arc<Framing>,
+    buf: Vec<u8>,
+    broken: bool,
+}
+
+impl fmt::Debug for FrameDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameDecoder")
+            .field("direction", &self.direction)
+            .field("framing", &self.framing)
+            .field("buffered", &self.buf.len())
+            .field("broken", &self.broken)
+            .finish()
+    }
+}
+
+impl FrameDecoder {
+    /// Creates a new instance of the `FrameDecoder` struct, at the start of a packet
+    ///
+    /// Arguments:
+    ///
+    /// * `direction`: The way the packets travel.
+    /// * `framing`: The framing shared with the other direction.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(direction: Direction, framing: Arc<Framing>) -> Self {
+        Self {
+            direction,
+            framing,
+            buf: Vec::new(),
+            broken: false,
+        }
+    }
+
+    /// It reads the relayed bytes, and hands every packet they complete to the observer
+    ///
+    /// Arguments:
+    ///
+    /// * `bytes`: The bytes relayed, unencrypted.
+    /// * `observe`: It gets every complete packet.
+    pub fn feed(&mut self, bytes: &[u8], mut observe: impl FnMut(&Frame<'_>)) {
+        if self.broken {
+            return;
+        }
+        self.buf.extend_from_slice(bytes);
+
+        let mut offset = 0;
+        loop {
+            let (length, prefix) = match decode_var_int(&self.buf[offset..]) {
+                Ok(length) => length,
+                Err(ProtocolError::Truncated) => break,
+                Err(_) => return self.give_up(),
+            };
+            let length = match usize::try_from(length) {
+                Ok(length) if length > 0 && length <= MAX_FRAME_SIZE => length,
+                _ => return self.give_up(),
+            };
+            let start = offset + prefix;
+            let Some(packet) = self.buf.get(start..start + length) else {
+                break;
+            };
+
+            match self.decode(packet) {
+                Some(frame) => {
+                    observe(&frame);
+                    self.follow(&frame);
+                }
+                None => return self.give_up(),
+            }
+            offset = start + length;
+        }
+        self.buf.drain(..offset);
+    }
+
+    /// It decodes a packet, without its length
+    ///
+    /// Arguments:
+    ///
+    /// * `packet`: The packet, in the format of the current compression.
+    ///
+    /// Returns:
+    ///
+    /// The frame, none when the packet is malformed
+    fn decode<'a>(&self, packet: &'a [u8]) -> Option<Frame<'a>> {
+        let (compressed, data) = match self.framing.compression_threshold() {
+            Some(_) => {
+                let (data_length, read) = decode_var_int(packet).ok()?;
+                (data_length != 0, &packet[read..])
+            }
+            None => (false, packet),
+        };
+        let id = match compressed {
+            true => None,
+            false => Some(decode_var_int(data).ok()?.0),
+        };
+
+        Some(Frame {
+            direction: self.direction,
+            login: !self.framing.logged_in.load(Ordering::Acquire),
+            id,
+            compressed,
+            data,
+        })
+    }
+
+    /// It follows the packets of the backend changing the framing: the set compression and the
+    /// login success
+    ///
+    /// Arguments:
+    ///
+    /// * `frame`: The packet just decoded.
+    fn follow(&self, frame: &Frame<'_>) {
+        if self.direction != Direction::Clientbound || !frame.login {
+            return;
+        }
+        match frame.id {
+            Some(SET_COMPRESSION_ID) => {
+                let threshold = decode_var_int(frame.data)
+                    .and_then(|(_, read)| decode_var_int(&frame.data[read..]));
+                if let Ok((threshold, _)) = threshold {
+                    self.framing.threshold.store(threshold, Ordering::Release);
+                }
+            }
+            Some(LOGIN_SUCCESS_ID) => self.framing.logged_in.store(true, Ordering::Release),
+            _ => {}
+        }
+    }
+
+    /// It stops decoding a stream which can't be framed
+    fn give_up(&mut self) {
+        tracing::debug!(direction = ?self.direction, "the relayed stream can't be framed");
+        self.broken = true;
+        self.buf = Vec::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// It decodes the frames of bytes fed in chunks of a size
+    fn decode(decoder: &mut FrameDecoder, bytes: &[u8], chunk: usize) -> Vec<(Option<i32>, bool)> {
+        let mut frames = Vec::new();
+        for chunk in bytes.chunks(chunk) {
+            decoder.feed(chunk, |frame| frames.push((frame.id, frame.compressed)));
+        }
+        frames
+    }
+
+    #[test]
+    fn it_follows_the_compression_set_by_the_backend() {
+        let framing = Arc::new(Framing::default());
+        let mut clientbound = FrameDecoder::new(Direction::Clientbound, framing.clone());
+        let mut serverbound = FrameDecoder::new(Direction::Serverbound, framing.clone());
+
+        // set compression to 256, a login success below it, then a compressed packet
+        let stream = b"\x03\x03\x80\x02\x04\x00\x02\xaa\xbb\x04\x90\x03\x78\x9c";
+        assert_eq!(
+            decode(&mut clientbound, stream, 1),
+            vec![(Some(3), false), (Some(2), false), (None, true)]
+        );
+        assert_eq!(framing.compression_threshold(), Some(256));
+
+        // the client compresses too, and is past the login
+        let mut login = true;
+        serverbound.feed(b"\x03\x00\x03\x01", |frame| {
+            assert_eq!(frame.id, Some(3));
+            login = frame.login;
+        });
+        assert!(!login);
+    }
+
+    #[test]
+    fn it_leaves_a_stream_it_cannot_frame() {
+        let mut decoder = FrameDecoder::new(Direction::Serverbound, Arc::new(Framing::default()));
+
+        assert_eq!(
+            decode(
+                &mut decoder,
+                b"\x02\x00\x01\xff\xff\xff\xff\xff\x02\x00\x01",
+                64
+            ),
+            vec![(Some(0), false)]
+        );
+        assert!(decode(&mut decoder, b"\x02\x00\x01", 64).is_empty());
+    }
+}
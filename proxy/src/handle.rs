@@ -0,0 +1,113 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use health::Health;
+use tokio::{sync::oneshot, task::JoinHandle};
+
+/// The addresses the servers of a started proxy are bound to, with the ports chosen by the system
+/// for the ones configured with port 0
+///
+/// Properties:
+///
+/// * `proxy`: The address accepting the Minecraft clients.
+/// * `listener`: The address of the gRPC API, none when the proxy runs without it.
+/// * `metrics`: The address of the metrics server, none when the proxy is built without it.
+/// * `health`: The address of the health server.
+/// * `ping`: The address of the ping port, none when it is unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundAddrs {
+    pub proxy: SocketAddr,
+    pub listener: Option<SocketAddr>,
+    pub metrics: Option<SocketAddr>,
+    pub health: SocketAddr,
+    pub ping: Option<SocketAddr>,
+}
+
+/// A started proxy, running in the background until it is shut down
+///
+/// Dropping the handle doesn't stop the proxy.
+///
+/// Properties:
+///
+/// * `addrs`: The addresses the servers are bound to.
+/// * `health`: The readiness of the proxy.
+/// * `stop`: Asks the proxy to drain and stop.
+/// * `task`: The task running the subsystems of the proxy.
+#[derive(Debug)]
+pub struct ProxyHandle {
+    addrs: BoundAddrs,
+    health: Arc<Health>,
+    stop: Option<oneshot::Sender<()>>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ProxyHandle {
+    /// Creates a new instance of the `ProxyHandle` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `addrs`: The addresses the servers are bound to.
+    /// * `health`: The readiness of the proxy.
+    /// * `stop`: Asks the proxy to drain and stop.
+    /// * `task`: The task running the subsystems of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub(crate) fn new(
+        addrs: BoundAddrs,
+        health: Arc<Health>,
+        stop: oneshot::Sender<()>,
+        task: JoinHandle<Result<()>>,
+    ) -> Self {
+        Self {
+            addrs,
+            health,
+            stop: Some(stop),
+            task,
+        }
+    }
+
+    /// It returns the addresses the servers are bound to
+    ///
+    /// Returns:
+    ///
+    /// A BoundAddrs
+    pub fn addrs(&self) -> BoundAddrs {
+        self.addrs
+    }
+
+    /// It waits until the proxy is ready to take traffic, as its readiness probe tells
+    pub async fn wait_ready(&self) {
+        self.health.ready().await
+    }
+
+    /// It stops the proxy: it stops accepting the connections, waits for the open ones until the
+    /// drain timeout, then stops its subsystems
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>, an error when the proxy failed before it was stopped
+    pub async fn shutdown(mut self) -> Result<()> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        self.wait().await
+    }
+
+    /// It waits until the proxy stops, after its shutdown signal or once every subsystem exited
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>, an error when the proxy failed
+    pub async fn wait(self) -> Result<()> {
+        self.task
+            .await
+            .map_err(|e| anyhow!("the proxy task failed: {}", e))?
+    }
+
+    /// It stops the proxy right away, without draining its connections
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+}
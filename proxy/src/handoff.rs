@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, TcpListener},
+};
+
+use anyhow::{anyhow, Result};
+use socket2::{Domain, Protocol, Socket, Type};
+
+/// The first file descriptor passed by systemd, the next ones follow it
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// The length of the queue of the connections not accepted yet
+const BACKLOG: i32 = 1024;
+
+/// It binds an address of a server of the proxy
+///
+/// With `reuse_port`, the socket is bound with `SO_REUSEPORT`, so the next process of an upgrade
+/// binds the same port and accepts the players while this one drains.
+///
+/// Arguments:
+///
+/// * `addr`: The address to bind, in the `host:port` format.
+/// * `reuse_port`: Whether another process can bind the same port.
+///
+/// Returns:
+///
+/// A Result<TcpListener>, in non-blocking mode
+pub fn bind(addr: &str, reuse_port: bool) -> Result<TcpListener> {
+    let parsed = addr
+        .parse()
+        .map_err(|e| anyhow!("invalid address {}: {}", addr, e))?;
+    bind_socket(parsed, reuse_port).map_err(|e| anyhow!("failed to bind {}: {}", addr, e))
+}
+
+/// It binds a socket, shared with the other processes with `reuse_port`
+///
+/// Arguments:
+///
+/// * `addr`: The address to bind.
+/// * `reuse_port`: Whether another process can bind the same port.
+///
+/// Returns:
+///
+/// An io::Result<TcpListener>, in non-blocking mode
+fn bind_socket(addr: SocketAddr, reuse_port: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    if reuse_port {
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(unix))]
+        log::warn!("reuse_port is only supported on unix, the port can't be shared");
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(BACKLOG)?;
+
+    Ok(socket.into())
+}
+
+/// It takes the listening sockets passed by systemd socket activation, by the name of their
+/// `FileDescriptorName=`, e.g. `proxy`
+///
+/// The sockets stay open across the restarts of the service, so the players connecting while
+/// the binary is swapped wait in the queue of the socket instead of being refused. The
+/// sockets are only taken once, they belong to the caller afterwards.
+///
+/// Returns:
+///
+/// A Result<HashMap<String, TcpListener>>, empty when the process wasn't socket activated
+#[cfg(unix)]
+pub fn systemd_listeners() -> Result<HashMap<String, TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    let fds = listen_fds(|name| std::env::var(name).ok(), std::process::id())?;
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+
+    Ok(fds
+        .into_iter()
+        .map(|(fd, name)| {
+            // SAFETY: systemd passed these descriptors to this process, nothing else owns them
+            let listener = unsafe { TcpListener::from_raw_fd(fd) };
+            (name, listener)
+        })
+        .collect())
+}
+
+/// It takes the listening sockets passed by systemd socket activation, only on unix
+///
+/// Returns:
+///
+/// A Result<HashMap<String, TcpListener>>, always empty on this platform
+#[cfg(not(unix))]
+pub fn systemd_listeners() -> Result<HashMap<String, TcpListener>> {
+    Ok(HashMap::new())
+}
+
+/// It reads the descriptors and the names of the sockets passed by systemd
+///
+/// An unnamed socket is named `unknown` like systemd does, except the first one which is the
+/// one of the Minecraft clients, so a single socket needs no name.
+///
+/// Arguments:
+///
+/// * `var`: A function returning the value of an environment variable, if it is set.
+/// * `pid`: The ID of this process, the sockets were passed to another one when it differs.
+///
+/// Returns:
+///
+/// A Result<Vec<(i32, String)>>
+#[cfg(unix)]
+fn listen_fds<F: Fn(&str) -> Option<String>>(var: F, pid: u32) -> Result<Vec<(i32, String)>> {
+    match var("LISTEN_PID").map(|listen_pid| listen_pid.parse::<u32>()) {
+        Some(Ok(listen_pid)) if listen_pid == pid => {}
+        Some(Err(e)) => return Err(anyhow!("Invalid LISTEN_PID: {}", e)),
+        _ => return Ok(Vec::new()),
+    }
+
+    let count: i32 = var("LISTEN_FDS")
+        .unwrap_or_default()
+        .parse()
+        .map_err(|e| anyhow!("Invalid LISTEN_FDS: {}", e))?;
+    let names = var("LISTEN_FDNAMES").unwrap_or_default();
+    let mut names = names.split(':').filter(|name| !name.is_empty());
+
+    Ok((0..count)
+        .map(|index| {
+            let name = match (names.next(), index) {
+                (Some(name), _) => name.to_string(),
+                (None, 0) => "proxy".to_string(),
+                (None, _) => "unknown".to_string(),
+            };
+            (LISTEN_FDS_START + index, name)
+        })
+        .collect())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_the_sockets_passed_to_this_process() {
+        let var = |name: &str| match name {
+            "LISTEN_PID" => Some("42".to_string()),
+            "LISTEN_FDS" => Some("2".to_string()),
+            "LISTEN_FDNAMES" => Some("proxy:listener".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            listen_fds(var, 42).unwrap(),
+            vec![(3, "proxy".to_string()), (4, "listener".to_string())]
+        );
+        assert!(listen_fds(var, 43).unwrap().is_empty());
+        assert_eq!(
+            listen_fds(
+                |name| (name != "LISTEN_FDNAMES").then(|| var(name)).flatten(),
+                42
+            )
+            .unwrap(),
+            vec![(3, "proxy".to_string()), (4, "unknown".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_shares_the_port_with_reuse_port() {
+        let first = bind("127.0.0.1:0", true).unwrap();
+        let addr = first.local_addr().unwrap().to_string();
+
+        assert!(bind(&addr, true).is_ok());
+        assert!(bind(&addr, false).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    any::Any, collections::BTreeMap, fmt, future::Future, panic::AssertUnwindSafe, sync::Arc,
+};
+
+use anyhow::anyhow;
+use futures::FutureExt;
+
+/// Where an unexpected error happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorSource {
+    /// The task serving a connection of a client.
+    Connection,
+    /// The task handling an event of the gRPC API or of the Kubernetes integration.
+    EventHandler,
+    /// A subsystem of the proxy, such as the listener or the operator, which exited.
+    Subsystem,
+}
+
+/// What was going on when an unexpected error happened
+///
+/// Properties:
+///
+/// * `source`: Where the error happened.
+/// * `panicked`: Whether the task panicked, the error then holds the panic message.
+/// * `fields`: What the task was doing, e.g. the `connection_id` and the `hostname` of a
+///   connection, or the `event` being handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub source: ErrorSource,
+    pub panicked: bool,
+    pub fields: BTreeMap<&'static str, String>,
+}
+
+impl ErrorContext {
+    pub fn new(source: ErrorSource) -> Self {
+        Self {
+            source,
+            panicked: false,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// It adds a field to the context
+    ///
+    /// Arguments:
+    ///
+    /// * `name`: The name of the field.
+    /// * `value`: Its value.
+    ///
+    /// Returns:
+    ///
+    /// The context with the field
+    pub fn with(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.fields.insert(name, value.to_string());
+        self
+    }
+}
+
+/// A hook invoked on the unexpected errors and the panics of the proxy
+///
+/// It lets the integrators send them to Sentry or to their own alerting. It is called from the
+/// tasks of the proxy, so it should hand the report over rather than block.
+pub trait ErrorHook: Send + Sync {
+    /// It reports an unexpected error
+    ///
+    /// Arguments:
+    ///
+    /// * `error`: The error, or the panic message when the task panicked.
+    /// * `context`: What was going on.
+    fn report(&self, error: &anyhow::Error, context: &ErrorContext);
+}
+
+/// The error hooks registered on the proxy, each of them gets every report
+#[derive(Clone, Default)]
+pub struct ErrorHooks {
+    hooks: Vec<Arc<dyn ErrorHook>>,
+}
+
+impl fmt::Debug for ErrorHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorHooks")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl ErrorHooks {
+    /// It registers a hook
+    ///
+    /// Arguments:
+    ///
+    /// * `hook`: The hook getting the reports.
+    pub fn add(&mut self, hook: Arc<dyn ErrorHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// It reports an unexpected error to every hook
+    ///
+    /// Arguments:
+    ///
+    /// * `error`: The error.
+    /// * `context`: What was going on.
+    pub fn report(&self, error: &anyhow::Error, context: &ErrorContext) {
+        for hook in &self.hooks {
+            hook.report(error, context);
+        }
+    }
+
+    /// It runs a task, and reports it when it panics
+    ///
+    /// The panic is caught, so the hooks get the report before the task ends.
+    ///
+    /// Arguments:
+    ///
+    /// * `task`: The task to run.
+    /// * `context`: What the task does.
+    pub async fn catch_panics<F>(&self, task: F, context: impl FnOnce() -> ErrorContext)
+    where
+        F: Future<Output = ()>,
+    {
+        if let Err(panic) = AssertUnwindSafe(task).catch_unwind().await {
+            let mut context = context();
+            context.panicked = true;
+            let error = anyhow!("panicked: {}", panic_message(panic.as_ref()));
+
+            log::error!("{:?} task {}: {:?}", context.source, error, context.fields);
+            self.report(&error, &context);
+        }
+    }
+}
+
+/// It returns the message a task panicked with
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, ErrorContext)>>);
+
+    impl ErrorHook for Recorder {
+        fn report(&self, error: &anyhow::Error, context: &ErrorContext) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((error.to_string(), context.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_reports_the_panics_of_a_task() {
+        let recorder = Arc::new(Recorder::default());
+        let mut hooks = ErrorHooks::default();
+        hooks.add(recorder.clone());
+
+        let context = || ErrorContext::new(ErrorSource::EventHandler).with("event", "put backend");
+        hooks.catch_panics(async {}, context).await;
+        hooks
+            .catch_panics(async { panic!("the storage is gone") }, context)
+            .await;
+
+        let reports = recorder.0.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].0, "panicked: the storage is gone");
+        assert!(reports[0].1.panicked);
+        assert_eq!(reports[0].1.fields["event"], "put backend");
+    }
+}
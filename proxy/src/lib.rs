@@ -1,22 +1,269 @@
-use std::{env, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::{pending, Future},
+    io,
+    net::IpAddr,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
 
-use anyhow::{anyhow, Ok, Result};
+use anyhow::{anyhow, Result};
+use arc_swap::ArcSwap;
+use config::{DirectIpPolicy, OnlineModeConfig, OverloadPolicy, ProxyConfig};
 use event::handlers::{
-    delete_backend::DeleteBackendHandler, list_backend::ListBackendHandler,
-    put_backend::PutBackendHandler,
+    apply_batch::ApplyBatchHandler, delete_access_rule::DeleteAccessRuleHandler,
+    delete_backend::DeleteBackendHandler, delete_status_asset::DeleteStatusAssetHandler,
+    list_access_rules::ListAccessRulesHandler, list_backend::ListBackendHandler,
+    list_status_assets::ListStatusAssetsHandler, probe_backend::ProbeBackendHandler,
+    put_access_rule::PutAccessRuleHandler, put_backend::PutBackendHandler,
+    put_status_asset::PutStatusAssetHandler, restore_backend::RestoreBackendHandler,
+    restore_state::RestoreStateHandler, snapshot_state::SnapshotStateHandler,
+    validate_backend::ValidateBackendHandler, watch_backends::WatchBackendsHandler,
 };
-use listener::{event::Event, Listener};
+use futures::FutureExt;
+use health::Health;
+use listener::event::Event;
 use log::debug;
-use storage::Storage;
+use metrics::{
+    connection::{ConnectionMetrics, UNKNOWN_BACKEND},
+    Metrics,
+};
+use protocol::{
+    packets::{
+        clientbound::transfer::TRANSFER_VERSION,
+        serverbound::{handshake::NextState, login_start::LoginStart},
+    },
+    version::VersionRange,
+};
+use shared::{
+    activity::Activity,
+    bans::Bans,
+    capture::Captures,
+    ddos::DdosMode,
+    endpoints::Endpoints,
+    metadata::PodMetadata,
+    models::{access_rule, backend::Backend, forwarding::ForwardingMode},
+    pings::Pings,
+    rate_limit::ConnectRateLimits,
+    recent::RecentEvents,
+    sessions::{Sessions, Transfer},
+    stats::{BackendCounters, Stats},
+    throttle::Throttle,
+};
+use storage::{AccessHandle, AssetsHandle, BackendStore, RoutingHandle};
 use tokio::{
     join,
-    net::TcpListener,
-    sync::{mpsc::Receiver, Mutex},
+    net::{TcpListener, TcpStream},
+    runtime::Handle,
+    sync::{
+        broadcast,
+        mpsc::{Receiver, Sender},
+        oneshot, OnceCell, OwnedSemaphorePermit, RwLock, Semaphore,
+    },
+    task::{JoinError, JoinHandle, JoinSet},
+    time::{sleep_until, timeout},
 };
 
-use crate::stream::Stream;
+use tracing::Instrument;
+use ulid::Ulid;
+
+use crate::{
+    access::{AccessRecord, CloseReason},
+    builder::ProxyBuilder,
+    capture::Capture,
+    error::ConnectionError,
+    fingerprint::{Classifier, Fingerprint, Verdict},
+    frames::{Direction, Frame, FrameDecoder, FrameObserver, Framing},
+    handle::{BoundAddrs, ProxyHandle},
+    hook::{panic_message, ErrorContext, ErrorHook, ErrorHooks, ErrorSource},
+    online_mode::{AuthenticationError, Authenticator},
+    reload::Reloader,
+    sequencer::Sequencer,
+    stream::{Counted, Stream},
+    subsystems::ControlPlane,
+    supervisor::Supervisor,
+    tap::{Tap, TapEvent},
+    transfer::{PacketCursor, Transfers},
+};
 
+pub mod access;
+pub mod builder;
+mod capture;
+pub mod error;
+pub mod fingerprint;
+pub mod frames;
+pub mod handle;
+pub mod handoff;
+pub mod hook;
+pub mod online_mode;
+pub mod reload;
+mod sequencer;
 pub mod stream;
+pub mod subsystems;
+pub mod supervisor;
+pub mod tap;
+mod transfer;
+
+/// The most clients kicked at once for the overload of the proxy, the next ones are reset
+const MAX_OVERLOAD_KICKS: usize = 256;
+
+/// The time waited after a failed accept, e.g. while the process is out of file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A future resolving when the proxy must drain and stop
+type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The end of the task of a connection: the panic it raised, with what the connection was
+type ConnectionResult = std::result::Result<(), (anyhow::Error, ErrorContext)>;
+
+/// A task aborted once it isn't awaited anymore, e.g. when it runs on another runtime than the
+/// future waiting for it
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// What the connections share, cloned into the task of each of them
+///
+/// Properties:
+///
+/// * `routes`: The routing table published by the storage, read without locking.
+/// * `access`: The bans and the allows published by the storage, read without locking.
+/// * `assets`: The MOTDs and the favicons of the hostnames published by the storage.
+/// * `activity`: The players connected to every hostname, and the sleeping hostnames.
+/// * `endpoints`: The addresses the connections to some hostnames are balanced across.
+/// * `metrics`: The latencies of the connections before they are relayed.
+/// * `recent`: The latest notable events of the connections.
+/// * `bans`: The addresses banned for misbehaving, and the strikes against the others.
+/// * `pings`: The last status ping of the clients, admitting their logins through the ping gate.
+/// * `throttle`: The last login attempt of the clients, throttling the reconnect spam.
+/// * `connect_rates`: The token buckets limiting the new connections to each backend.
+/// * `authenticator`: The key pair authenticating the players in online mode, generated on the
+///   first login.
+/// * `hooks`: The hooks reporting the unexpected errors of the connections.
+/// * `classifier`: The classifier of the connections, none when they aren't classified.
+/// * `observer`: The observer of the relayed packets, the connections are relayed without
+///   framing them when none.
+/// * `taps`: The sender of the packets relayed with the tapped backends.
+/// * `captures`: The hostnames whose connections are captured for debugging.
+/// * `sessions`: The players relayed to every backend, by the version of the backend they joined.
+/// * `transfers`: The sender of the transfers moving the players of a backend to another server.
+/// * `stats`: The counters of every backend, the relays add to them.
+/// * `ddos`: Whether the proxy is in DDoS mode, read by every new connection.
+#[derive(Clone)]
+struct ConnectionContext {
+    routes: RoutingHandle,
+    access: AccessHandle,
+    assets: AssetsHandle,
+    activity: Arc<Activity>,
+    endpoints: Arc<Endpoints>,
+    metrics: ConnectionMetrics,
+    recent: Arc<RecentEvents>,
+    bans: Arc<Bans>,
+    pings: Arc<Pings>,
+    throttle: Arc<Throttle>,
+    connect_rates: Arc<ConnectRateLimits>,
+    authenticator: Arc<OnceCell<Authenticator>>,
+    hooks: ErrorHooks,
+    classifier: Option<Arc<dyn Classifier>>,
+    observer: Option<Arc<dyn FrameObserver>>,
+    taps: broadcast::Sender<TapEvent>,
+    captures: Arc<Captures>,
+    sessions: Arc<Sessions>,
+    transfers: broadcast::Sender<Transfer>,
+    stats: Arc<Stats>,
+    ddos: Arc<DdosMode>,
+}
+
+/// What a connection needs of its backend, copied out of the routing table so the table isn't
+/// held while the connection waits
+///
+/// Properties:
+///
+/// * `hostname`: The hostname of the backend, its route.
+/// * `version`: The version of the backend, the relays keep the backend of their version.
+/// * `addr`: The address of the backend.
+/// * `redirect_ip`: The host of the address, written in the forwarded handshake.
+/// * `preserve_hostname`: Whether the handshake keeps the hostname the client connected with.
+/// * `forwarding_mode`: How the backend expects the identity of the players to be forwarded.
+/// * `tap`: Whether the packets relayed with the backend are tapped.
+/// * `versions`: The protocol versions the backend supports.
+/// * `usernames`: The backends of the players with some usernames.
+/// * `maintenance`: The kick reason, or the MOTD, of a backend in a maintenance window without
+///   fallback.
+/// * `motd`: The MOTD of the backend, the proxy answers the status pings with in DDoS mode.
+/// * `unhealthy_threshold`: The consecutive failed connects after which the backend is down.
+struct Route {
+    hostname: String,
+    version: u64,
+    addr: String,
+    redirect_ip: String,
+    preserve_hostname: bool,
+    forwarding_mode: ForwardingMode,
+    tap: bool,
+    versions: VersionRange,
+    usernames: BTreeMap<String, String>,
+    maintenance: Option<String>,
+    motd: Option<String>,
+    unhealthy_threshold: u32,
+}
+
+impl Route {
+    /// Creates a new instance of the `Route` struct, from a backend of the routing table
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The backend.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    fn new(backend: &Backend) -> Self {
+        Self {
+            hostname: backend.hostname().to_string(),
+            version: backend.version(),
+            addr: backend.addr(),
+            redirect_ip: backend.redirect_ip().to_string(),
+            preserve_hostname: backend.preserve_hostname(),
+            forwarding_mode: backend.forwarding_mode(),
+            tap: backend.tap(),
+            versions: VersionRange {
+                min: backend.min_protocol_version(),
+                max: backend.max_protocol_version(),
+            },
+            usernames: backend.username_routes().clone(),
+            maintenance: None,
+            motd: backend.motd().map(str::to_string),
+            unhealthy_threshold: backend
+                .health_check()
+                .cloned()
+                .unwrap_or_default()
+                .unhealthy_threshold,
+        }
+    }
+}
+
+/// What the relay of a connection does besides copying its bytes
+///
+/// Properties:
+///
+/// * `tap`: The hostname of the backend when it is tapped.
+/// * `capture`: The capture of the connection, when its hostname is captured.
+/// * `transfers`: The transfers of the connection and the cursor of the packets of its client,
+///   when the client can be transferred.
+/// * `counters`: The counters of the backend, the relayed bytes are added to.
+struct Relay<'a> {
+    tap: Option<&'a str>,
+    capture: Option<Capture>,
+    transfers: Option<(Transfers, PacketCursor)>,
+    counters: Arc<BackendCounters>,
+}
 
 /// The proxy is responsible for accepting connections from the client and
 /// forwarding them to the correct server.
@@ -28,174 +275,1541 @@ pub mod stream;
 ///
 /// The proxy is responsible for keeping track of the server's state and
 /// forwarding packets to the correct client.
-#[derive(Debug, Default)]
 pub struct Proxy {
-    storage: Arc<Mutex<Storage>>,
+    config: Arc<ArcSwap<ProxyConfig>>,
+    storage: Arc<RwLock<dyn BackendStore>>,
+    routes: RoutingHandle,
+    access: AccessHandle,
+    assets: AssetsHandle,
+    metrics: Arc<Metrics>,
+    health: Arc<Health>,
+    activity: Arc<Activity>,
+    endpoints: Arc<Endpoints>,
+    recent: Arc<RecentEvents>,
+    bans: Arc<Bans>,
+    pings: Arc<Pings>,
+    throttle: Arc<Throttle>,
+    connect_rates: Arc<ConnectRateLimits>,
+    hooks: ErrorHooks,
+    shutdown: Mutex<Option<ShutdownSignal>>,
+    control_plane: bool,
+    inherited: Option<std::net::TcpListener>,
+    events: Sender<Event>,
+    received: Option<Receiver<Event>>,
+    data_plane: Option<Handle>,
+    observer: Option<Arc<dyn FrameObserver>>,
+    classifier: Option<Arc<dyn Classifier>>,
+    taps: broadcast::Sender<TapEvent>,
+    captures: Arc<Captures>,
+    sessions: Arc<Sessions>,
+    transfers: broadcast::Sender<Transfer>,
+    #[cfg(feature = "grpc")]
+    logs: Option<broadcast::Sender<shared::logs::LogRecord>>,
+    stats: Arc<Stats>,
+    ddos: Arc<DdosMode>,
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("config", &self.config)
+            .field("storage", &self.storage)
+            .field("health", &self.health)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Proxy {
+    fn default() -> Self {
+        Self::new(ProxyConfig::default())
+    }
 }
 
 impl Proxy {
-    /// Creates a new instance of the `Proxy` struct
+    /// Creates a new instance of the `Proxy` struct, its metrics labeled with the pod of the
+    /// downward API when the `kubernetes.metadata` configuration is set
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration of the proxy, already validated.
     ///
     /// Returns:
     ///
     /// A new instance of the struct.
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(config: ProxyConfig) -> Self {
+        Self::builder(config)
+            .pod_metadata(PodMetadata::from_env())
+            .build()
+            .expect("the pod labels are valid metric labels")
     }
 
-    /// It listens for incoming connections on the port specified by the `PROXY_PORT` environment
-    /// variable, and spawns a new task to handle each connection
+    /// It starts building a proxy, with its addresses, its backends and its shutdown signal given
+    /// rather than read from the environment
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration of the proxy, already validated.
     ///
     /// Returns:
     ///
-    /// A Result<()>
-    pub async fn start(&self) -> Result<()> {
-        let proxy_port = env::var("PROXY_PORT").unwrap_or_else(|_| "25565".to_string());
-        let proxy_addr = format!("0.0.0.0:{}", proxy_port);
+    /// A ProxyBuilder
+    pub fn builder(config: ProxyConfig) -> ProxyBuilder {
+        ProxyBuilder::new(config)
+    }
 
-        log::info!("Starting proxy on {}", proxy_addr);
-        let tcp_listener = TcpListener::bind(proxy_addr.clone())
-            .await
-            .map_err(|e| anyhow!("Failed to bind proxy to {}: {}", proxy_addr, e))?;
+    /// It registers a hook reporting the unexpected errors and the panics of the proxy, e.g. to
+    /// Sentry
+    ///
+    /// Arguments:
+    ///
+    /// * `hook`: The hook getting the reports.
+    ///
+    /// Returns:
+    ///
+    /// The proxy with the hook
+    pub fn with_error_hook(mut self, hook: Arc<dyn ErrorHook>) -> Self {
+        self.hooks.add(hook);
+        self
+    }
+
+    /// It returns the storage of the backends, so an integrator can read it or change it outside of
+    /// the events
+    ///
+    /// Returns:
+    ///
+    /// An Arc<RwLock<dyn BackendStore>>
+    pub fn storage(&self) -> Arc<RwLock<dyn BackendStore>> {
+        self.storage.clone()
+    }
+
+    /// It returns the sender of the events the proxy handles, so another control plane than the
+    /// gRPC API can change the backends or reload the configuration
+    ///
+    /// Returns:
+    ///
+    /// A Sender<Event>
+    pub fn events(&self) -> Sender<Event> {
+        self.events.clone()
+    }
 
-        let listener_port = env::var("LISTENER_PORT").unwrap_or_else(|_| "65535".to_string());
-        let listener_addr = format!("0.0.0.0:{}", listener_port);
+    /// It subscribes to the packets relayed with the backends whose `tap` is set, e.g. for a
+    /// debugging tool
+    ///
+    /// A subscriber lagging more than `channels.taps` packets behind misses the oldest ones.
+    ///
+    /// Returns:
+    ///
+    /// A broadcast::Receiver<TapEvent>
+    pub fn taps(&self) -> broadcast::Receiver<TapEvent> {
+        self.taps.subscribe()
+    }
 
-        log::info!("Starting listener on {}", listener_addr);
-        let listener = Listener::new(listener_addr);
+    /// It returns the hostnames whose connections are captured, so an integrator can start a
+    /// capture outside of the gRPC API
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Captures>
+    pub fn captures(&self) -> Arc<Captures> {
+        self.captures.clone()
+    }
 
-        // Start listener and pass it a channel to send events to the proxy
-        let (tx, rx) = tokio::sync::mpsc::channel::<Event>(16);
+    /// It returns the players relayed to every version of the backends, so an integrator can
+    /// tell when an update of a backend has no players left on its previous address
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Sessions>
+    pub fn sessions(&self) -> Arc<Sessions> {
+        self.sessions.clone()
+    }
+
+    /// It returns the DDoS mode of the proxy, so an integrator can flip it outside of the gRPC
+    /// API, e.g. from an alert
+    ///
+    /// Returns:
+    ///
+    /// An Arc<DdosMode>
+    pub fn ddos_mode(&self) -> Arc<DdosMode> {
+        self.ddos.clone()
+    }
+
+    /// It returns the sender of the transfers, so an integrator can move the players of a backend
+    /// to another server outside of the gRPC API
+    ///
+    /// Only the players of 1.20.5 and after are transferred, the older ones stay on the backend.
+    ///
+    /// Returns:
+    ///
+    /// A broadcast::Sender<Transfer>
+    pub fn transfers(&self) -> broadcast::Sender<Transfer> {
+        self.transfers.clone()
+    }
+
+    /// It returns the counters of every backend, so an integrator can compute its own
+    /// statistics outside of the gRPC API
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Stats>
+    pub fn stats(&self) -> Arc<Stats> {
+        self.stats.clone()
+    }
+
+    /// It binds the servers of the proxy, then runs them in the background: it listens for incoming
+    /// connections on the address of the `proxy` configuration, and spawns a new task to handle
+    /// each connection
+    ///
+    /// The proxy runs until it is shut down, through the handle or the signal given to
+    /// `ProxyBuilder::shutdown`, or until every subsystem exited.
+    ///
+    /// Returns:
+    ///
+    /// A Result<ProxyHandle>, an error when a server can't be bound
+    pub async fn start(mut self) -> Result<ProxyHandle> {
+        let config = self.config.load_full();
+        let proxy_addr = config.proxy.addr();
+        // every server shares its port with the next process of an upgrade
+        let reuse_port = config.proxy.reuse_port;
+
+        let tcp_listener = match self.inherited.take() {
+            Some(inherited) => {
+                log::info!(
+                    "Accepting on the inherited socket {}",
+                    inherited.local_addr()?
+                );
+                inherited.set_nonblocking(true)?;
+                inherited
+            }
+            None => {
+                log::info!("Starting proxy on {}", proxy_addr);
+                handoff::bind(&proxy_addr, reuse_port)?
+            }
+        };
+        // registered with the runtime relaying the players once it runs there
+        self.health.set_proxy_bound();
+
+        let control_plane: Option<ControlPlane> = match self.control_plane {
+            #[cfg(feature = "grpc")]
+            true => {
+                log::info!("Starting listener on {}", config.listener.addr());
+                let listener = listener::Listener::new(
+                    config.listener.clone(),
+                    config.channels.clone(),
+                    self.metrics.channels(),
+                    listener::ProxyState {
+                        health: self.health.clone(),
+                        recent: self.recent.clone(),
+                        bans: self.bans.clone(),
+                        captures: self.captures.clone(),
+                        sessions: self.sessions.clone(),
+                        transfers: self.transfers.clone(),
+                        logs: self.logs.clone(),
+                        stats: self.stats.clone(),
+                        ddos: self.ddos.clone(),
+                    },
+                );
+                let control_listener = handoff::bind(&config.listener.addr(), reuse_port)?;
+                let control_listener = TcpListener::from_std(control_listener)?;
+                self.health.set_listener_bound();
+                Some((listener, control_listener))
+            }
+            // another control plane sends the events, nothing is left to wait for
+            _ => {
+                self.health.set_listener_bound();
+                None
+            }
+        };
+
+        let metrics_listener = match cfg!(feature = "metrics-server") {
+            true => {
+                let metrics_addr = config.metrics.addr();
+                log::info!("Starting metrics server on {}", metrics_addr);
+                Some(handoff::bind(&metrics_addr, reuse_port)?)
+            }
+            false => None,
+        };
+
+        let health_addr = config.health.addr();
+        log::info!("Starting health server on {}", health_addr);
+        let health_listener = handoff::bind(&health_addr, reuse_port)?;
+
+        let ping_listener = match config.health.ping_addr() {
+            Some(ping_addr) => {
+                log::info!("Answering the status pings on {}", ping_addr);
+                Some(handoff::bind(&ping_addr, reuse_port)?)
+            }
+            None => None,
+        };
+
+        let addrs = BoundAddrs {
+            proxy: tcp_listener.local_addr()?,
+            listener: control_plane
+                .as_ref()
+                .map(|(_, control_listener)| control_listener.local_addr())
+                .transpose()?,
+            metrics: metrics_listener
+                .as_ref()
+                .map(|metrics_listener| metrics_listener.local_addr())
+                .transpose()?,
+            health: health_listener.local_addr()?,
+            ping: ping_listener
+                .as_ref()
+                .map(|ping_listener| ping_listener.local_addr())
+                .transpose()?,
+        };
+
+        let received = self
+            .received
+            .take()
+            .ok_or_else(|| anyhow!("the proxy was already started"))?;
+        let (stop, stopped) = oneshot::channel();
+        let signal = self.shutdown.lock().unwrap().take();
+        let health = self.health.clone();
+        let task = tokio::spawn(async move {
+            let requested = async {
+                let signal = async {
+                    match signal {
+                        Some(signal) => signal.await,
+                        None => pending().await,
+                    }
+                };
+                // a dropped handle doesn't stop the proxy
+                let stopped = async {
+                    if stopped.await.is_err() {
+                        pending::<()>().await
+                    }
+                };
+                tokio::select! {
+                    _ = signal => {},
+                    _ = stopped => {},
+                }
+            };
+
+            let run = self.run(
+                tcp_listener,
+                control_plane,
+                received,
+                metrics_listener,
+                health_listener,
+                ping_listener,
+            );
+            tokio::pin!(run);
+
+            tokio::select! {
+                result = &mut run => return result,
+                _ = requested => log::info!("shutdown requested"),
+            }
+
+            // the subsystems keep running while the sessions drain, dropping them aborts the
+            // connections left
+            tokio::select! {
+                result = &mut run => result,
+                remaining = self.health.drain(None) => {
+                    if remaining > 0 {
+                        log::warn!("stopping with {} sessions still open", remaining);
+                    }
+                    Ok(())
+                }
+            }
+        });
+
+        Ok(ProxyHandle::new(addrs, health, stop, task))
+    }
+
+    /// It runs the subsystems of the proxy on the bound servers, until every one of them exited
+    ///
+    /// Arguments:
+    ///
+    /// * `tcp_listener`: The listener accepting the Minecraft clients, not registered with a
+    ///   runtime yet.
+    /// * `control_plane`: The gRPC API and its listener, none when another control plane sends
+    ///   the events.
+    /// * `received`: The events to handle.
+    /// * `metrics_listener`: The listener of the metrics server, none when the proxy is built
+    ///   without it.
+    /// * `health_listener`: The listener of the health server.
+    /// * `ping_listener`: The listener of the ping port, none when it is unset.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    async fn run(
+        &self,
+        tcp_listener: std::net::TcpListener,
+        control_plane: Option<ControlPlane>,
+        received: Receiver<Event>,
+        metrics_listener: Option<std::net::TcpListener>,
+        health_listener: std::net::TcpListener,
+        ping_listener: Option<std::net::TcpListener>,
+    ) -> Result<()> {
+        let config = self.config.load_full();
+        let reloader = Reloader::new(self.config.clone(), self.storage.clone(), self.ddos.clone());
+
+        if let Some(retention) = config.limits.tombstone_retention_secs {
+            log::info!("Keeping deleted backends for {} seconds", retention);
+        }
+
+        // the listener, the operator and the federation send their events to the proxy
+        let tx = self.events.clone();
+        // the restarts of the event handler keep receiving from the same channel
+        let events = tokio::sync::Mutex::new(received);
+
+        // the listener and the event handler are restarted when they crash
+        let supervisor = Supervisor::new(self.hooks.clone());
 
         // Create the joins that will run in parallel
         let results = join!(
-            Self::handle_connections(tcp_listener, self.storage.clone()),
-            Self::handle_listener_events(rx, self.storage.clone()),
-            listener.start(tx)
+            self.relay(
+                tcp_listener,
+                ConnectionContext {
+                    routes: self.routes.clone(),
+                    access: self.access.clone(),
+                    assets: self.assets.clone(),
+                    activity: self.activity.clone(),
+                    endpoints: self.endpoints.clone(),
+                    metrics: self.metrics.connections(),
+                    recent: self.recent.clone(),
+                    bans: self.bans.clone(),
+                    pings: self.pings.clone(),
+                    throttle: self.throttle.clone(),
+                    connect_rates: self.connect_rates.clone(),
+                    authenticator: Arc::new(OnceCell::new()),
+                    hooks: self.hooks.clone(),
+                    classifier: self.classifier.clone(),
+                    observer: self.observer.clone(),
+                    taps: self.taps.clone(),
+                    captures: self.captures.clone(),
+                    sessions: self.sessions.clone(),
+                    transfers: self.transfers.clone(),
+                    stats: self.stats.clone(),
+                    ddos: self.ddos.clone(),
+                },
+            ),
+            supervisor.supervise("listener event handler", || {
+                Self::handle_listener_events(
+                    &events,
+                    self.storage.clone(),
+                    self.stats.clone(),
+                    reloader.clone(),
+                    self.hooks.clone(),
+                )
+            }),
+            self.serve_control_plane(&supervisor, control_plane, tx.clone()),
+            self.serve_metrics(metrics_listener),
+            health::serve_listener(health_listener, self.health.clone()),
+            reloader.watch_signals(),
+            self.run_operator(tx.clone()),
+            self.run_federation(tx.clone()),
+            self.push_statsd(),
+            self.push_otlp(),
+            self.checkpoint_stats(),
+            self.serve_pings(ping_listener)
         );
 
         results
             .0
-            .unwrap_or_else(|e| log::error!("proxy connection handler exited with error: {}", e));
+            .unwrap_or_else(|e| self.exited("proxy connection handler", e));
+        results.2.unwrap_or_else(|e| self.exited("listener", e));
+        results
+            .3
+            .unwrap_or_else(|e| self.exited("metrics server", e));
         results
-            .1
-            .unwrap_or_else(|e| log::error!("listener event handler exited with error: {}", e));
+            .4
+            .unwrap_or_else(|e| self.exited("health server", e));
         results
-            .2
-            .unwrap_or_else(|e| log::error!("listener exited with error: {}", e));
+            .5
+            .unwrap_or_else(|e| self.exited("signal handler", e));
+        results.6.unwrap_or_else(|e| self.exited("operator", e));
+        results.7.unwrap_or_else(|e| self.exited("federation", e));
+        results
+            .8
+            .unwrap_or_else(|e| self.exited("statsd exporter", e));
+        results
+            .9
+            .unwrap_or_else(|e| self.exited("otlp exporter", e));
+        results
+            .10
+            .unwrap_or_else(|e| self.exited("stats checkpoints", e));
+        results.11.unwrap_or_else(|e| self.exited("ping port", e));
 
         Ok(())
     }
 
+    /// It logs a subsystem of the proxy which exited with an error, and reports it to the hooks
+    ///
+    /// Arguments:
+    ///
+    /// * `subsystem`: The name of the subsystem.
+    /// * `error`: The error it exited with.
+    fn exited(&self, subsystem: &str, error: anyhow::Error) {
+        log::error!("{} exited with error: {}", subsystem, error);
+        self.hooks.report(
+            &error,
+            &ErrorContext::new(ErrorSource::Subsystem).with("subsystem", subsystem),
+        );
+    }
+
+    /// It accepts the Minecraft clients and relays their connections, on the runtime of the data
+    /// plane when one is set and on the current one otherwise
+    ///
+    /// Arguments:
+    ///
+    /// * `listener`: The listener accepting the client connections, registered with the runtime
+    ///   running the accept loop.
+    /// * `context`: What the connections share, cloned into each of them.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    async fn relay(
+        &self,
+        listener: std::net::TcpListener,
+        context: ConnectionContext,
+    ) -> Result<()> {
+        let config = self.config.clone();
+        let health = self.health.clone();
+        let accept = async move {
+            let listener = TcpListener::from_std(listener)?;
+            Self::handle_connections(listener, context, config, health).await
+        };
+
+        match &self.data_plane {
+            Some(runtime) => {
+                // the accept loop stops with the proxy, even when it runs on another runtime
+                let mut task = AbortOnDrop(runtime.spawn(accept));
+                (&mut task.0)
+                    .await
+                    .map_err(|e| anyhow!("the data plane task failed: {}", e))?
+            }
+            None => accept.await,
+        }
+    }
+
     /// It reads the handshake packet from the client, connects to the server, and then forwards all
     /// data between the client and the server
     ///
     /// Arguments:
     ///
-    /// * `socket`: The socket that the client connected to.
-    /// * `remote_addr`: The address of the client that connected to the proxy
+    /// * `listener`: The listener accepting the client connections.
+    /// * `context`: What the connections share, cloned into each of them.
+    /// * `config`: The configuration, read again by every connection to pick up reloads.
+    /// * `health`: Counts the open sessions, and stops the accept loop once the proxy drains.
     ///
     /// Returns:
     ///
     /// A Result<()>
-    async fn handle_connections(listener: TcpListener, storage: Arc<Mutex<Storage>>) -> Result<()> {
+    async fn handle_connections(
+        listener: TcpListener,
+        context: ConnectionContext,
+        config: Arc<ArcSwap<ProxyConfig>>,
+        health: Arc<Health>,
+    ) -> Result<()> {
+        // the connections stop with the accept loop, which reaps them as they close
+        let mut tasks = JoinSet::new();
+        // a connection holds a ticket until it closes, the ones without are shed
+        let tickets = config
+            .load()
+            .proxy
+            .max_connections
+            .map(|max| Arc::new(Semaphore::new(max)));
+        let kicks = Arc::new(Semaphore::new(MAX_OVERLOAD_KICKS));
+        // the next accept waits until then after a failed one
+        let mut resume = Instant::now();
         loop {
-            let (socket, remote_addr) = listener.accept().await?;
-            log::debug!("serving incoming connection from {}", remote_addr);
+            let (socket, remote_addr) = tokio::select! {
+                accepted = async {
+                    sleep_until(resume.into()).await;
+                    listener.accept().await
+                } => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // e.g. out of file descriptors, the open connections are still reaped
+                        log::warn!("failed to accept a connection: {}", e);
+                        resume = Instant::now() + ACCEPT_BACKOFF;
+                        continue;
+                    }
+                },
+                Some(joined) = tasks.join_next() => {
+                    Self::reap(joined, &context);
+                    continue;
+                }
+                _ = health.draining_started() => {
+                    log::info!("draining, not accepting new connections anymore");
+                    break;
+                }
+            };
+            let config = config.load_full();
+            if Self::is_banned(&config, &context, remote_addr.ip()) {
+                tracing::debug!(client = %remote_addr, "dropping the connection of a banned address");
+                context.metrics.banned_connection();
+                continue;
+            }
+            let ticket = match tickets.clone().map(Semaphore::try_acquire_owned) {
+                Some(Ok(ticket)) => Some(ticket),
+                Some(Err(_)) => {
+                    Self::shed(socket, config, &context, &kicks, &mut tasks);
+                    continue;
+                }
+                None => None,
+            };
 
-            let storage = storage.clone();
+            // the ID tells the lines of a connection apart from the ones of the others
+            let id = Ulid::new();
+            let span = tracing::debug_span!("connection", %id, client = %remote_addr);
 
-            // Handle connection in parallel
-            tokio::spawn(async move {
-                let mut client_stream = Stream::wrap(socket);
-                client_stream.configure().map_err(|e| {
-                    let err_msg = format!(
-                        "failed to configure client stream for {}: {}",
-                        remote_addr, e
-                    );
-                    log::error!("{}", err_msg);
-                    anyhow!(err_msg)
-                })?;
+            let context = context.clone();
+            let session = health.open_session();
 
-                let mut handshake = client_stream.read_handshake().await.map_err(|e| {
-                    let err_msg = format!(
-                        "failed to read handshake packet from client {}: {}",
-                        remote_addr, e
+            let task = async move {
+                let _session = session;
+                let _ticket = ticket;
+                let started = Instant::now();
+                let mut record = AccessRecord::new(id, remote_addr);
+                let result = Self::handle_connection(
+                    id,
+                    socket,
+                    &mut record,
+                    config.clone(),
+                    &context,
+                    started,
+                )
+                .await;
+                match result {
+                    Ok(()) => tracing::debug!(%id, "connection closed"),
+                    Err(e) => {
+                        let backend_failure = e.is_backend_failure();
+                        let e = anyhow::Error::new(e);
+                        tracing::error!(%id, "connection failed: {:#}", e);
+                        // the backend failures are expected, they are kept in the recent events
+                        if record.reason == CloseReason::Closed && backend_failure {
+                            record.reason = CloseReason::BackendFailed;
+                        } else if record.reason == CloseReason::Closed {
+                            record.reason = CloseReason::Error;
+                            let error_context = ErrorContext::new(ErrorSource::Connection)
+                                .with("connection_id", id)
+                                .with("client", remote_addr)
+                                .with("hostname", record.hostname.clone().unwrap_or_default());
+                            context.hooks.report(&e, &error_context);
+                        }
+                        record.error = Some(format!("{:#}", e));
+                    }
+                }
+                if let Some(event) = record.recent_event() {
+                    context.recent.push(event);
+                }
+                Self::strike(&config, &context, &record, remote_addr.ip());
+                context.metrics.closed(
+                    record.hostname.as_deref(),
+                    record.route.as_deref(),
+                    record.reason.as_str(),
+                    record.bytes_in,
+                    record.bytes_out,
+                );
+                // only the connections which reached their backend are sessions
+                if let (Some(route), Some(_)) = (&record.route, &record.backend) {
+                    context.metrics.session(
+                        route,
+                        started.elapsed(),
+                        record.bytes_in + record.bytes_out,
                     );
-                    log::error!("{}", err_msg);
-                    anyhow!(err_msg)
-                })?;
+                }
+                record.log(started.elapsed());
+            };
 
-                log::debug!(
-                    "client {} trying to connect to {}",
-                    remote_addr,
-                    handshake.hostname()
-                );
+            // Handle connection in parallel
+            tasks.spawn(
+                async move {
+                    AssertUnwindSafe(task)
+                        .catch_unwind()
+                        .await
+                        .map_err(|panic| {
+                            let mut context = ErrorContext::new(ErrorSource::Connection)
+                                .with("connection_id", id)
+                                .with("client", remote_addr);
+                            context.panicked = true;
+                            (
+                                anyhow!("panicked: {}", panic_message(panic.as_ref())),
+                                context,
+                            )
+                        })
+                }
+                .instrument(span),
+            );
+        }
+
+        // the new players are refused rather than left in the backlog until the proxy stops
+        drop(listener);
+
+        // the drain waits for the sessions, the ones left after its timeout are aborted with the
+        // proxy
+        while let Some(joined) = tasks.join_next().await {
+            Self::reap(joined, &context);
+        }
+        Ok(())
+    }
+
+    /// It sheds a connection over the `max_connections` of the proxy, as its `overload` policy
+    /// says
+    ///
+    /// The kicks are limited too, the connections over them are reset like with the `refuse`
+    /// policy.
+    ///
+    /// Arguments:
+    ///
+    /// * `socket`: The connection of the client.
+    /// * `config`: The configuration, with the policy and the `overloaded` message.
+    /// * `context`: What the connections share.
+    /// * `kicks`: The tickets of the clients being kicked.
+    /// * `tasks`: The tasks of the connections, the kick runs in one of them.
+    fn shed(
+        socket: TcpStream,
+        config: Arc<ProxyConfig>,
+        context: &ConnectionContext,
+        kicks: &Arc<Semaphore>,
+        tasks: &mut JoinSet<ConnectionResult>,
+    ) {
+        let kick = match config.proxy.overload {
+            OverloadPolicy::Kick => kicks.clone().try_acquire_owned().ok(),
+            OverloadPolicy::Refuse => None,
+        };
+        let Some(kick) = kick else {
+            tracing::debug!("refusing a connection, the proxy is overloaded");
+            context.metrics.overloaded_connection("refuse");
+            // a reset tells the client right away, and frees the socket without a TIME_WAIT
+            let _ = socket.set_linger(Some(Duration::ZERO));
+            return;
+        };
+
+        context.metrics.overloaded_connection("kick");
+        tasks.spawn(async move {
+            if let Err(e) = Self::kick_overloaded(socket, &config, kick).await {
+                tracing::debug!("failed to kick an overloaded connection: {}", e);
+            }
+            Ok(())
+        });
+    }
+
+    /// It kicks a client because the proxy handles its `max_connections` already
+    ///
+    /// Arguments:
+    ///
+    /// * `socket`: The connection of the client.
+    /// * `config`: The configuration, with the `overloaded` message.
+    /// * `_kick`: The ticket of the kick, released once the client is kicked.
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), ConnectionError>
+    async fn kick_overloaded(
+        socket: TcpStream,
+        config: &ProxyConfig,
+        _kick: OwnedSemaphorePermit,
+    ) -> Result<(), ConnectionError> {
+        let mut client_stream = Stream::wrap(socket);
+        let handshake_timeout = Duration::from_secs(config.timeouts.handshake_secs);
+        // the handshake tells the kick apart from a status
+        timeout(handshake_timeout, client_stream.read_handshake())
+            .await
+            .map_err(|_| ConnectionError::HandshakeTimeout)?
+            .map_err(ConnectionError::Handshake)?;
+        client_stream
+            .kick_backend_not_found(config.messages.overloaded.clone())
+            .await
+            .map_err(ConnectionError::Kick)
+    }
+
+    /// It counts and reports a connection whose task panicked, once its task is joined
+    ///
+    /// Arguments:
+    ///
+    /// * `joined`: The result of the task, with the panic and what the connection was.
+    /// * `context`: What the connections share.
+    fn reap(joined: Result<ConnectionResult, JoinError>, context: &ConnectionContext) {
+        let (error, error_context) = match joined {
+            Ok(Ok(())) => return,
+            Ok(Err(panic)) => panic,
+            // the tasks are only aborted with the proxy
+            Err(e) if e.is_cancelled() => return,
+            Err(e) => {
+                let mut error_context = ErrorContext::new(ErrorSource::Connection);
+                error_context.panicked = true;
+                (anyhow!("panicked: {}", e), error_context)
+            }
+        };
+
+        context.metrics.panicked_connection();
+        log::error!(
+            "{:?} task {}: {:?}",
+            error_context.source,
+            error,
+            error_context.fields
+        );
+        context.hooks.report(&error, &error_context);
+    }
+
+    /// It tells whether the connections of an address are dropped right after the accept
+    ///
+    /// The allowed addresses never are, the ones banned through the API always are, whatever the
+    /// `bans` section.
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration, without bans when its `bans` section is unset.
+    /// * `context`: What the connections share.
+    /// * `ip`: The address of the client.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    fn is_banned(config: &ProxyConfig, context: &ConnectionContext, ip: IpAddr) -> bool {
+        let access = context.access.load();
+        let now_ms = access_rule::now_ms();
+        if access.is_allowed(&ip, None, now_ms) {
+            return false;
+        }
+        if access.ban(&ip, None, now_ms).is_some() {
+            return true;
+        }
+
+        match &config.bans {
+            Some(bans) => !bans.exempt.contains(&ip) && context.bans.is_banned(ip),
+            None => false,
+        }
+    }
+
+    /// It kicks the client with a message of the proxy, a status ping gets it through the MOTD
+    /// template of the hostname, with its favicon, when they were uploaded
+    ///
+    /// Arguments:
+    ///
+    /// * `client_stream`: The connection of the client.
+    /// * `context`: What the connections share.
+    /// * `hostname`: The hostname of the backend.
+    /// * `next_state`: The next state of the handshake, only the status pings get the asset.
+    /// * `message`: The message of the proxy.
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), ConnectionError>
+    async fn kick_with_asset(
+        client_stream: &mut Stream,
+        context: &ConnectionContext,
+        hostname: &str,
+        next_state: NextState,
+        message: String,
+    ) -> Result<(), ConnectionError> {
+        let assets = context.assets.load();
+        let (message, favicon) = match (next_state, assets.get(hostname)) {
+            (NextState::Status, Some(asset)) => (asset.motd(&message), asset.favicon.clone()),
+            _ => (message, None),
+        };
 
-                let (backend_addr, backend_host) = match storage
-                    .lock()
+        client_stream
+            .kick_with_favicon(message, favicon)
+            .await
+            .map_err(ConnectionError::Kick)
+    }
+
+    /// It counts the misbehavior of a client as a strike against its address, and bans the
+    /// address once it got too many of them
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration, without bans when its `bans` section is unset.
+    /// * `context`: What the connections share.
+    /// * `record`: The access log record of the connection which ended.
+    /// * `ip`: The address of the client.
+    fn strike(
+        config: &ProxyConfig,
+        context: &ConnectionContext,
+        record: &AccessRecord,
+        ip: IpAddr,
+    ) {
+        let (bans, offense) = match (&config.bans, record.offense()) {
+            (Some(bans), Some(offense)) if !bans.exempt.contains(&ip) => (bans, offense),
+            _ => return,
+        };
+        if context
+            .access
+            .load()
+            .is_allowed(&ip, None, access_rule::now_ms())
+        {
+            return;
+        }
+
+        if let Some(ban) = context.bans.strike(ip, offense, &bans.policy()) {
+            tracing::warn!(
+                id = %record.id,
+                client = %ip,
+                "banned for {} seconds after {} strikes, the last one a {}",
+                bans.duration_secs,
+                ban.strikes,
+                offense.as_str()
+            );
+            context.metrics.ban(offense.as_str());
+            context.recent.push(record.ban_event(&ban));
+        }
+    }
+
+    /// It serves a connection accepted by the proxy, from its handshake until it is closed
+    ///
+    /// Arguments:
+    ///
+    /// * `id`: The ID of the connection, carried by every line it logs.
+    /// * `socket`: The connection of the client.
+    /// * `record`: The access log record of the connection, filled in as it goes.
+    /// * `config`: The configuration when the connection was accepted.
+    /// * `context`: What the connections share.
+    /// * `started`: When the connection was accepted.
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), ConnectionError>, an error when the connection failed before it was closed
+    async fn handle_connection(
+        id: Ulid,
+        socket: TcpStream,
+        record: &mut AccessRecord,
+        config: Arc<ProxyConfig>,
+        context: &ConnectionContext,
+        started: Instant,
+    ) -> Result<(), ConnectionError> {
+        let ConnectionContext {
+            routes,
+            activity,
+            endpoints,
+            metrics,
+            ..
+        } = context;
+        let handshake_timeout = Duration::from_secs(config.timeouts.handshake_secs);
+        let connect_timeout = Duration::from_secs(config.timeouts.connect_secs);
+
+        let mut client_stream = Stream::wrap(socket);
+        client_stream.configure(&config.sockets.client)?;
+
+        let reading = Instant::now();
+        let handshake = timeout(handshake_timeout, client_stream.read_handshake())
+            .await
+            .map_err(|_| ConnectionError::HandshakeTimeout)?;
+        let mut handshake = match handshake {
+            Ok(handshake) => handshake,
+            // a client breaking the protocol is closed right away, without reading further
+            Err(e) if e.is_malformed() => {
+                tracing::debug!(%id, "malformed handshake: {}", e);
+                metrics.malformed_handshake();
+                record.reason = CloseReason::MalformedHandshake;
+                return Ok(());
+            }
+            Err(e) => return Err(ConnectionError::Handshake(e)),
+        };
+        let handshake_duration = reading.elapsed();
+        let handshake_delay = started.elapsed();
+        let handshaked = Instant::now();
+
+        let hostname = handshake.hostname();
+        record.hostname = Some(hostname.clone());
+        record.protocol_version = Some(handshake.version());
+        record.set_next_state(handshake.next_state());
+        tracing::debug!(%id, %hostname, next_state = ?handshake.next_state(), "read handshake");
+
+        // the mode is read once, so a flip during the handshake doesn't mix both
+        let ddos_mode = context.ddos.enabled().then_some(&config.ddos_mode);
+
+        // the handshakes no backend matches, e.g. of an IP, may go to the default backend
+        let (backend, rerouted) = {
+            let routes = routes.load();
+            let backend = match config.proxy.route_by_port {
+                true => routes.get_backend_on_port(hostname.as_str(), handshake.port()),
+                false => routes.get_backend(hostname.as_str()),
+            };
+            let (backend, rerouted) = match backend {
+                Some(backend) => (Some(backend), false),
+                None if config.direct_ip.policy == DirectIpPolicy::Route && ddos_mode.is_none() => {
+                    (routes.get_backend(&config.direct_ip.backend), true)
+                }
+                None => (None, true),
+            };
+            // a backend in a maintenance window hands its players over to its fallback
+            let schedule = backend.and_then(|backend| backend.maintenance(SystemTime::now()));
+            let fallback = schedule
+                .and_then(|schedule| schedule.fallback())
+                .and_then(|fallback| routes.get_backend(fallback));
+            match (backend, schedule, fallback) {
+                (_, _, Some(fallback)) => (Some(Route::new(fallback)), true),
+                (Some(backend), Some(schedule), None) => {
+                    let maintenance = schedule.motd().unwrap_or(&config.messages.maintenance);
+                    let route = Route {
+                        maintenance: Some(maintenance.to_string()),
+                        ..Route::new(backend)
+                    };
+                    (Some(route), rerouted)
+                }
+                (backend, _, None) => (backend.map(Route::new), rerouted),
+            }
+        };
+        metrics.handshake(
+            &hostname,
+            backend
+                .as_ref()
+                .map_or(UNKNOWN_BACKEND, |backend| &backend.hostname),
+            handshake.next_state().as_str(),
+            handshake_duration,
+        );
+
+        let Route {
+            hostname: mut route,
+            version: mut backend_version,
+            addr: mut backend_addr,
+            redirect_ip: mut backend_host,
+            mut preserve_hostname,
+            mut forwarding_mode,
+            tap: mut tapped,
+            mut versions,
+            usernames,
+            maintenance,
+            motd,
+            mut unhealthy_threshold,
+        } = match backend {
+            Some(backend) => backend,
+            None => {
+                tracing::debug!(%id, %hostname, "no backend matches the hostname");
+                metrics.unknown_hostname(&hostname);
+                record.reason = CloseReason::BackendNotFound;
+                let catch_all = &config.catch_all;
+                let (message, favicon) = match config.direct_ip.policy {
+                    DirectIpPolicy::Drop => return Ok(()),
+                    DirectIpPolicy::Hint => (config.messages.direct_ip_hint.clone(), None),
+                    DirectIpPolicy::Kick | DirectIpPolicy::Route => match handshake.next_state() {
+                        NextState::Status => (
+                            catch_all
+                                .motd
+                                .clone()
+                                .unwrap_or_else(|| config.messages.backend_not_found.clone()),
+                            catch_all.favicon.clone(),
+                        ),
+                        NextState::Login | NextState::Transfer => (
+                            catch_all.kick_reason(&config.messages.backend_not_found),
+                            None,
+                        ),
+                    },
+                };
+                client_stream
+                    .kick_with_favicon(message, favicon)
                     .await
-                    .get_backend(handshake.hostname().as_str())
-                {
-                    Some(backend) => (backend.addr(), backend.redirect_ip().to_string()),
-                    None => {
-                        client_stream
-                            .kick_backend_not_found(handshake.next_state())
-                            .await
-                            .map_err(|e| {
-                                let err_msg =
-                                    format!("failed to kick client {}: {}", remote_addr, e);
-                                log::error!("{}", err_msg);
-                                anyhow!(err_msg)
-                            })?;
-                        return Err(anyhow!(
-                            "failed to handle connection, unable to find hostname: {}",
-                            handshake.hostname()
-                        ));
-                    }
+                    .map_err(ConnectionError::Kick)?;
+                return Ok(());
+            }
+        };
+        // the default and the fallback backends are woken up, counted and balanced under their own
+        // hostname
+        record.route = Some(route.clone());
+        let requested = hostname.clone();
+        let mut hostname = match rerouted {
+            true => route.clone(),
+            false => hostname,
+        };
+
+        if let Some(message) = maintenance {
+            tracing::debug!(%id, %hostname, "the backend is under maintenance");
+            let next_state = handshake.next_state();
+            Self::kick_with_asset(&mut client_stream, context, &route, next_state, message).await?;
+            record.reason = CloseReason::Maintenance;
+            return Ok(());
+        }
+
+        // the player is told the versions of the backend, rather than the generic error of the
+        // backend once connected to it, the status pings still show the backend's own version
+        if handshake.next_state().joins() && !versions.contains(handshake.version()) {
+            return Self::kick_unsupported_version(&mut client_stream, &config, versions, record)
+                .await;
+        }
+
+        // a macro reconnecting in a loop is kicked until it waits, the status pings are exempt, the
+        // DDoS mode throttles every address harder
+        let throttle = match (&config.throttle, ddos_mode) {
+            (throttle, Some(ddos_mode)) => Some((
+                throttle
+                    .as_ref()
+                    .map_or(0, |throttle| throttle.min_delay_ms)
+                    .max(ddos_mode.min_delay_ms),
+                throttle
+                    .as_ref()
+                    .map_or(&[][..], |throttle| &throttle.exempt),
+            )),
+            (Some(throttle), None) => Some((throttle.min_delay_ms, &throttle.exempt[..])),
+            (None, None) => None,
+        };
+        if let (Some((min_delay_ms, exempt)), NextState::Login | NextState::Transfer) =
+            (throttle, handshake.next_state())
+        {
+            let ip = client_stream.peer_addr()?.ip();
+            let min_delay = Duration::from_millis(min_delay_ms);
+            if !exempt.contains(&ip) && !context.throttle.attempt(ip, min_delay) {
+                tracing::debug!(%id, %hostname, "login attempt throttled");
+                metrics.throttled_login();
+                client_stream
+                    .kick_backend_not_found(config.messages.throttled.clone())
+                    .await
+                    .map_err(ConnectionError::Kick)?;
+                record.reason = CloseReason::Throttled;
+                return Ok(());
+            }
+        }
+
+        // the real clients ping the server list before the player joins, the join bots usually
+        // don't, the DDoS mode gates every login
+        let ping_window = match ddos_mode {
+            Some(ddos_mode) => Some(ddos_mode.ping_window_secs),
+            None => config
+                .ping_gate
+                .as_ref()
+                .map(|ping_gate| ping_gate.window_secs),
+        };
+        if let Some(window) = ping_window {
+            let ip = client_stream.peer_addr()?.ip();
+            let window = Duration::from_secs(window);
+            match handshake.next_state() {
+                NextState::Status => context.pings.ping(ip, window),
+                NextState::Login | NextState::Transfer if !context.pings.pinged(ip, window) => {
+                    tracing::debug!(%id, %hostname, "login without a recent status ping");
+                    metrics.cold_login();
+                    client_stream
+                        .kick_backend_not_found(config.messages.ping_required.clone())
+                        .await
+                        .map_err(ConnectionError::Kick)?;
+                    record.reason = CloseReason::PingRequired;
+                    return Ok(());
+                }
+                NextState::Login | NextState::Transfer => {}
+            }
+        }
+
+        // under attack, the proxy answers the status pings itself, only the logins reach a backend
+        if ddos_mode.is_some() && handshake.next_state() == NextState::Status {
+            tracing::debug!(%id, %hostname, "status ping answered in DDoS mode");
+            let message = motd.unwrap_or_else(|| config.messages.ddos_mode.clone());
+            Self::kick_with_asset(
+                &mut client_stream,
+                context,
+                &route,
+                NextState::Status,
+                message,
+            )
+            .await?;
+            record.reason = CloseReason::DdosMode;
+            return Ok(());
+        }
+
+        // the login start is read by the proxy when the authentication, a username rule, a
+        // username ban or the classifier needs it, then forwarded to the backend
+        let access = context.access.load_full();
+        let reads_login_start = config.online_mode.is_some()
+            || !usernames.is_empty()
+            || access.has_username_bans()
+            || context.classifier.is_some();
+        let login_start = match handshake.next_state().joins() && reads_login_start {
+            true => {
+                let login_start = timeout(handshake_timeout, client_stream.read_login_start())
+                    .await
+                    .map_err(|_| ConnectionError::LoginStartTimeout)?
+                    .map_err(ConnectionError::LoginStart)?;
+                Some(login_start)
+            }
+            false => None,
+        };
+        let login_delay = handshaked.elapsed();
+
+        // the players banned through the API are kicked with the reason of their ban
+        if let Some(login_start) = &login_start {
+            let client = client_stream.peer_addr()?.ip();
+            let now_ms = access_rule::now_ms();
+            if let Some(ban) = access.ban(&client, Some(login_start.username()), now_ms) {
+                tracing::debug!(%id, %hostname, username = %login_start.username(), "banned username kicked");
+                let message = match ban.reason.as_str() {
+                    "" => config.messages.banned.clone(),
+                    reason => format!("{}\n{}", config.messages.banned, reason),
                 };
+                client_stream
+                    .kick_backend_not_found(message)
+                    .await
+                    .map_err(ConnectionError::Kick)?;
+                record.reason = CloseReason::Banned;
+                record.error = Some(ban.reason.clone()).filter(|reason| !reason.is_empty());
+                return Ok(());
+            }
+        }
 
-                log::debug!("forwarding client packets to {}", backend_addr);
+        // the likely bots are flagged or kicked before the session server and the backend are
+        // reached
+        if let Some(classifier) = &context.classifier {
+            let fingerprint = Fingerprint {
+                connection: id,
+                client: client_stream.peer_addr()?.ip(),
+                hostname: &requested,
+                protocol_version: handshake.version(),
+                next_state: handshake.next_state(),
+                handshake_delay,
+                login_delay: login_start.as_ref().map(|_| login_delay),
+                username: login_start
+                    .as_ref()
+                    .map(|login_start| login_start.username()),
+                fits_version: login_start
+                    .as_ref()
+                    .map(|login_start| login_start.fits_version(handshake.version())),
+            };
+            let verdict = classifier.classify(&fingerprint);
+            metrics.classified(verdict.as_str());
+            match verdict {
+                Verdict::Allow => {}
+                Verdict::Flag(reason) => {
+                    tracing::debug!(%id, %hostname, %reason, "connection flagged by the classifier");
+                    record.flag = Some(reason);
+                }
+                Verdict::Reject(reason) => {
+                    tracing::debug!(%id, %hostname, %reason, "connection rejected by the classifier");
+                    client_stream
+                        .kick_backend_not_found(reason.clone())
+                        .await
+                        .map_err(ConnectionError::Kick)?;
+                    record.reason = CloseReason::Rejected;
+                    record.error = Some(reason);
+                    return Ok(());
+                }
+            }
+        }
 
-                let mut server_stream = Stream::from(&backend_addr).await?;
-                server_stream.configure().map_err(|e| {
-                    let err_msg = format!(
-                        "failed to configure server stream for {}: {}",
-                        backend_addr, e
-                    );
-                    log::error!("{}", err_msg);
-                    anyhow!(err_msg)
+        // in online mode, the player is authenticated before it can wake up or reach the backend
+        let login_start = match (&config.online_mode, login_start) {
+            (Some(online_mode), Some(login_start)) => {
+                let version = handshake.version();
+                let authenticated = Self::authenticate(
+                    &mut client_stream,
+                    version,
+                    login_start,
+                    &config,
+                    online_mode,
+                    record,
+                    context,
+                )
+                .await?;
+                match authenticated {
+                    Some(login_start) => Some(login_start),
+                    None => return Ok(()),
+                }
+            }
+            (_, login_start) => login_start,
+        };
+
+        // the players of a username rule go to another backend, e.g. the staff to a staging server
+        let rule = login_start.as_ref().and_then(|login_start| {
+            usernames
+                .iter()
+                .find(|(username, _)| username.eq_ignore_ascii_case(login_start.username()))
+        });
+        if let Some((username, target)) = rule {
+            match routes.load().get_backend(target).map(Route::new) {
+                Some(username_route) => {
+                    tracing::debug!(%id, %username, backend = %target, "routing the player by its username");
+                    Route {
+                        hostname: route,
+                        version: backend_version,
+                        addr: backend_addr,
+                        redirect_ip: backend_host,
+                        preserve_hostname,
+                        forwarding_mode,
+                        tap: tapped,
+                        versions,
+                        unhealthy_threshold,
+                        ..
+                    } = username_route;
+                    hostname = route.clone();
+                    record.route = Some(route.clone());
+                    if !versions.contains(handshake.version()) {
+                        return Self::kick_unsupported_version(
+                            &mut client_stream,
+                            &config,
+                            versions,
+                            record,
+                        )
+                        .await;
+                    }
+                }
+                None => {
+                    tracing::debug!(%id, %username, backend = %target, "the backend of the username rule doesn't exist");
+                }
+            }
+        }
+
+        // the players authenticated by the proxy join with their offline identity, no backend can
+        // expect a forwarded one
+        if config.online_mode.is_some()
+            && login_start.is_some()
+            && forwarding_mode != ForwardingMode::None
+        {
+            let _ = client_stream
+                .kick_backend_not_found(config.messages.authentication_unavailable.clone())
+                .await;
+            return Err(ConnectionError::ForwardingUnsupported {
+                backend: route,
+                mode: forwarding_mode,
+            });
+        }
+
+        // the server of the hostname is scaled down, a player logging in wakes it up
+        if activity.is_sleeping(&hostname) {
+            let message = match handshake.next_state() {
+                NextState::Login | NextState::Transfer => {
+                    tracing::info!(%id, %hostname, "waking up the backend");
+                    activity.wake(&hostname);
+                    record.reason = CloseReason::BackendStarting;
+                    config.messages.backend_starting.clone()
+                }
+                NextState::Status => {
+                    record.reason = CloseReason::BackendSleeping;
+                    config.messages.backend_sleeping.clone()
+                }
+            };
+            let next_state = handshake.next_state();
+            Self::kick_with_asset(&mut client_stream, context, &route, next_state, message).await?;
+            return Ok(());
+        }
+        // a backend coming back online isn't joined by every waiting player at once
+        if let Some(connect_rate) = &config.connect_rate {
+            if !context.connect_rates.acquire(&route, &connect_rate.rate()) {
+                tracing::debug!(%id, backend = %route, "the connections to the backend exceed their rate");
+                metrics.rate_limited(&requested, &route);
+                let message = config.messages.backend_busy.clone();
+                let next_state = handshake.next_state();
+                Self::kick_with_asset(&mut client_stream, context, &route, next_state, message)
+                    .await?;
+                record.reason = CloseReason::BackendBusy;
+                return Ok(());
+            }
+        }
+        let _connection = handshake
+            .next_state()
+            .joins()
+            .then(|| activity.connect(&hostname));
+        // the player keeps the version of the backend it joined until it leaves, an update of the
+        // backend only moves the next players
+        let _session = handshake.next_state().joins().then(|| {
+            context
+                .sessions
+                .open(&route, backend_version, &backend_addr)
+        });
+        let counters = context.stats.counters(&route);
+        if handshake.next_state().joins() {
+            counters.join();
+        }
+
+        // the hostname of a headless Service is balanced across its pods
+        let backend_addr = endpoints.pick(&hostname).unwrap_or(backend_addr);
+
+        record.backend = Some(backend_addr.clone());
+        tracing::debug!(%id, backend = %backend_addr, "forwarding client packets");
+
+        let connecting = Instant::now();
+        let connected = timeout(connect_timeout, Stream::from(&backend_addr))
+            .await
+            .map_err(|_| ConnectionError::ConnectTimeout {
+                backend: backend_addr.clone(),
+            })
+            .and_then(|connected| {
+                connected.map_err(|source| ConnectionError::Connect {
+                    backend: backend_addr.clone(),
+                    source,
+                })
+            });
+        // the connects are the passive health checks of the backend
+        match &connected {
+            Ok(_) => counters.connected(connecting.elapsed(), unhealthy_threshold),
+            Err(_) => counters.failed(unhealthy_threshold),
+        }
+        let health = counters.health();
+        metrics.health(&route, health.state.as_str(), health.score);
+        let mut server_stream = connected?;
+        metrics.connect(&requested, &route, connecting.elapsed());
+        server_stream
+            .configure(&config.sockets.backend)
+            .map_err(|source| ConnectionError::ServerStream {
+                backend: backend_addr.clone(),
+                source,
+            })?;
+
+        // rewrite handshake packet to use the backend's IP, unless the backend routes
+        // by hostname too
+        if !preserve_hostname {
+            handshake.set_hostname(backend_host);
+        }
+        server_stream
+            .write_handshake(&handshake)
+            .await
+            .map_err(|source| ConnectionError::Forward {
+                packet: "handshake",
+                backend: backend_addr.clone(),
+                source,
+            })?;
+        if let Some(login_start) = &login_start {
+            server_stream
+                .write_login_start(login_start)
+                .await
+                .map_err(|source| ConnectionError::Forward {
+                    packet: "login start",
+                    backend: backend_addr.clone(),
+                    source,
+                })?;
+        }
+        metrics.setup(&requested, &route, started.elapsed());
+
+        let relay = Relay {
+            tap: tapped.then_some(route.as_str()),
+            capture: context.captures.take(&route).map(|max_bytes| {
+                Capture::new(id, &route, config.capture.directory.clone(), max_bytes)
+            }),
+            // the players of 1.20.5 and after can be moved to another server, e.g. before their
+            // backend shuts down
+            transfers: (handshake.next_state().joins() && handshake.version() >= TRANSFER_VERSION)
+                .then(|| {
+                    Transfers::new(&route, handshake.version(), context.transfers.subscribe())
+                }),
+            counters,
+        };
+        let (bytes_in, bytes_out) =
+            Self::copy_streams(id, client_stream, server_stream, relay, context)
+                .await
+                .map_err(|source| ConnectionError::Relay {
+                    backend: backend_addr.clone(),
+                    source,
                 })?;
+        record.bytes_in = bytes_in;
+        record.bytes_out = bytes_out;
+
+        Ok(())
+    }
 
-                // rewrite handshake packet to use the backend's IP
-                handshake.set_hostname(backend_host);
-                server_stream
-                    .write_handshake(&handshake)
+    /// It kicks a player whose protocol version the backend doesn't support
+    ///
+    /// Arguments:
+    ///
+    /// * `client_stream`: The stream of the client, right after its handshake or its login start.
+    /// * `config`: The configuration, for the kick message.
+    /// * `versions`: The protocol versions the backend supports, named in the kick.
+    /// * `record`: The access log record of the connection.
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), ConnectionError>, an error when the kick can't be written
+    async fn kick_unsupported_version(
+        client_stream: &mut Stream,
+        config: &ProxyConfig,
+        versions: VersionRange,
+        record: &mut AccessRecord,
+    ) -> Result<(), ConnectionError> {
+        tracing::debug!(id = %record.id, version = ?record.protocol_version, "unsupported version");
+        client_stream
+            .kick_backend_not_found(
+                config
+                    .messages
+                    .unsupported_version
+                    .replace("{versions}", &versions.to_string()),
+            )
+            .await
+            .map_err(ConnectionError::Kick)?;
+        record.reason = CloseReason::UnsupportedVersion;
+        Ok(())
+    }
+
+    /// It authenticates a player logging in with the session server, and encrypts its connection
+    ///
+    /// A player the session server doesn't know is kicked, as well as every player when the session
+    /// server can't be reached.
+    ///
+    /// Arguments:
+    ///
+    /// * `client_stream`: The stream of the client, right after its login start.
+    /// * `version`: The protocol version of the handshake.
+    /// * `login_start`: The login start of the player.
+    /// * `config`: The configuration, for the kick messages.
+    /// * `online_mode`: The session server.
+    /// * `record`: The access log record of the connection.
+    /// * `context`: What the connections share.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the login start to forward to the backend, none when the player was kicked
+    async fn authenticate(
+        client_stream: &mut Stream,
+        version: i32,
+        login_start: LoginStart,
+        config: &ProxyConfig,
+        online_mode: &OnlineModeConfig,
+        record: &mut AccessRecord,
+        context: &ConnectionContext,
+    ) -> Result<Option<LoginStart>, ConnectionError> {
+        let authenticator = context
+            .authenticator
+            .get_or_try_init(|| async {
+                tokio::task::spawn_blocking(Authenticator::new)
                     .await
-                    .map_err(|e| {
-                        let err_msg = format!(
-                            "failed to write handshake packet to server {}: {}",
-                            backend_addr, e
-                        );
-                        log::error!("{}", err_msg);
-                        anyhow!(err_msg)
-                    })?;
-
-                Self::copy_streams(client_stream, server_stream)
+                    .map_err(|e| AuthenticationError::KeyPair(e.to_string()))?
+            })
+            .await
+            .map_err(ConnectionError::Authentication)?;
+
+        let (login_start, server_hash) = match authenticator
+            .encrypt(client_stream, version, login_start)
+            .await
+        {
+            Ok(encrypted) => encrypted,
+            Err(error) if error.is_rejected() => {
+                tracing::debug!(id = %record.id, "failed to authenticate: {}", error);
+                context.metrics.authentication("failure");
+                record.reason = CloseReason::AuthenticationFailed;
+                record.error = Some(error.to_string());
+                // the client enabled its encryption once it answered, it can't read the kick then
+                if let AuthenticationError::UnsupportedVersion(_) = error {
+                    client_stream
+                        .kick_backend_not_found(config.messages.authentication_failed.clone())
+                        .await
+                        .map_err(ConnectionError::Kick)?;
+                }
+                return Ok(None);
+            }
+            Err(error) => return Err(ConnectionError::Authentication(error)),
+        };
+
+        let ip = client_stream.peer_addr()?.ip();
+        let username = login_start.username();
+        match authenticator
+            .has_joined(online_mode, username, &server_hash, ip)
+            .await
+        {
+            Ok(Some(profile)) => {
+                tracing::debug!(id = %record.id, username = %profile.name, uuid = %profile.id, "authenticated");
+                context.metrics.authentication("success");
+                record.username = Some(profile.name);
+                Ok(Some(login_start))
+            }
+            Ok(None) => {
+                tracing::debug!(id = %record.id, %username, "the session server doesn't know the player");
+                context.metrics.authentication("failure");
+                record.reason = CloseReason::AuthenticationFailed;
+                record.error = Some(format!("the session server doesn't know {}", username));
+                client_stream
+                    .kick_backend_not_found(config.messages.authentication_failed.clone())
                     .await
-                    .map_err(|e| {
-                        let err_msg = format!(
-                            "failed to copy streams between client {} and server {}: {}",
-                            remote_addr, backend_addr, e
-                        );
-                        log::error!("{}", err_msg);
-                        anyhow!(err_msg)
-                    })?;
-
-                log::debug!("connection closed from {}", remote_addr);
-
-                Ok(())
-            });
+                    .map_err(ConnectionError::Kick)?;
+                Ok(None)
+            }
+            Err(error) => {
+                if error.is_unavailable() {
+                    context.metrics.authentication("unavailable");
+                }
+                let _ = client_stream
+                    .kick_backend_not_found(config.messages.authentication_unavailable.clone())
+                    .await;
+                Err(ConnectionError::Authentication(error))
+            }
         }
     }
 
@@ -203,50 +1817,165 @@ impl Proxy {
     ///
     /// Arguments:
     ///
+    /// * `id`: The ID of the connection.
     /// * `client_stream`: The stream that the client is connected to.
     /// * `server_stream`: The stream to the server.
+    /// * `relay`: What the relay does besides copying the bytes.
+    /// * `context`: What the connections share, with the observer and the taps of the packets.
     ///
     /// Returns:
     ///
-    /// A future that resolves to a Result<()>
-    async fn copy_streams(client_stream: Stream, server_stream: Stream) -> Result<()> {
-        let mut client_tcp_stream = client_stream.tcp_stream();
-        let mut server_tcp_stream = server_stream.tcp_stream();
+    /// The bytes copied from the client to the server, and from the server to the client
+    async fn copy_streams(
+        id: Ulid,
+        client_stream: Stream,
+        server_stream: Stream,
+        relay: Relay<'_>,
+        context: &ConnectionContext,
+    ) -> io::Result<(u64, u64)> {
+        let Relay {
+            tap,
+            capture,
+            transfers,
+            counters,
+        } = relay;
+        let (client_tcp_stream, ciphers) = client_stream.into_parts();
+        let (server_tcp_stream, _) = server_stream.into_parts();
 
-        tokio::io::copy_bidirectional(&mut client_tcp_stream, &mut server_tcp_stream)
-            .await
-            .map_err(|e| anyhow!("failed to copy data between client and server: {}", e))?;
+        // the packets are only split when something looks at them
+        let observer = context.observer.as_deref();
+        let tap = tap.map(|backend| Tap::new(&context.taps, backend, observer));
+        let observer = match &tap {
+            Some(tap) => Some(tap as &dyn FrameObserver),
+            None => observer,
+        };
+        if observer.is_none() && capture.is_none() && transfers.is_none() {
+            return match ciphers {
+                Some(ciphers) => {
+                    stream::copy_encrypted(client_tcp_stream, server_tcp_stream, ciphers, &counters)
+                        .await
+                }
+                None => {
+                    let mut client = Counted::new(client_tcp_stream, counters.bytes_in());
+                    let mut server = Counted::new(server_tcp_stream, counters.bytes_out());
+                    tokio::io::copy_bidirectional(&mut client, &mut server).await
+                }
+            };
+        }
 
-        Ok(())
+        let framing = Arc::new(Framing::default());
+        let (transfers, mut serverbound_cursor) = transfers.unzip();
+        let (mut serverbound, mut clientbound) = match observer {
+            Some(_) => (
+                Some(FrameDecoder::new(Direction::Serverbound, framing.clone())),
+                Some(FrameDecoder::new(Direction::Clientbound, framing)),
+            ),
+            None => (None, None),
+        };
+        let (mut serverbound_capture, mut clientbound_capture) = capture
+            .as_ref()
+            .map(|capture| {
+                (
+                    capture.recorder(Direction::Serverbound),
+                    capture.recorder(Direction::Clientbound),
+                )
+            })
+            .unzip();
+        let observe = |frame: &Frame<'_>| {
+            if let Some(observer) = observer {
+                observer.observe(id, frame);
+            }
+        };
+
+        let copied = stream::copy_inspected(
+            client_tcp_stream,
+            server_tcp_stream,
+            ciphers,
+            |data| {
+                if let Some(cursor) = &mut serverbound_cursor {
+                    cursor.advance(data, false);
+                }
+                if let Some(decoder) = &mut serverbound {
+                    decoder.feed(data, observe);
+                }
+                if let Some(recorder) = &mut serverbound_capture {
+                    recorder.record(data);
+                }
+            },
+            |data| {
+                if let Some(decoder) = &mut clientbound {
+                    decoder.feed(data, observe);
+                }
+                if let Some(recorder) = &mut clientbound_capture {
+                    recorder.record(data);
+                }
+            },
+            transfers,
+            &counters,
+        )
+        .await;
+
+        // the capture is written even when the relay failed, it is what is being diagnosed
+        if let (Some(capture), Some(serverbound), Some(clientbound)) =
+            (capture, serverbound_capture, clientbound_capture)
+        {
+            match capture.write([serverbound, clientbound]).await {
+                Ok(path) => log::info!("captured the connection {} to {}", id, path.display()),
+                Err(error) => log::warn!("couldn't write the capture of {}: {}", id, error),
+            }
+        }
+        copied
     }
 
     /// The function `handle_listener_events` handles events received from a channel by spawning async
     /// tasks to handle different types of events.
     ///
+    /// The tasks of the events changing the same backend run one after the other, in the order the
+    /// events were received, so a put followed by a delete of a hostname can't be swapped. The
+    /// other events don't wait for them.
+    ///
     /// Arguments:
     ///
-    /// * `rx`: A `Receiver<Event>` which is used to receive events from some event source.
-    /// * `storage`: `storage` is an `Arc<Mutex<Storage>>` which is a shared mutable state that is
-    /// protected by a mutex. It allows multiple threads to access and modify the `Storage` struct
-    /// concurrently.
+    /// * `events`: The `Receiver<Event>` which is used to receive events from some event source,
+    ///   kept locked while the handler runs and handed over to it again after a restart.
+    /// * `storage`: `storage` is an `Arc<RwLock<dyn BackendStore>>` which is a shared mutable state that is
+    ///   protected by a read-write lock. Control-plane events take the write lock only when they
+    ///   change the storage, so connections looking up their backend are not serialized behind them.
+    /// * `stats`: The counters of every backend, carrying on from the totals of a `RestoreState`.
+    /// * `reloader`: The reloader applying a new configuration on a `ReloadConfig` event.
+    /// * `hooks`: The hooks reporting the panics of the handlers.
     ///
     /// Returns:
     ///
     /// a `Result<()>`.
     async fn handle_listener_events(
-        mut rx: Receiver<Event>,
-        storage: Arc<Mutex<Storage>>,
+        events: &tokio::sync::Mutex<Receiver<Event>>,
+        storage: Arc<RwLock<dyn BackendStore>>,
+        stats: Arc<Stats>,
+        reloader: Reloader,
+        hooks: ErrorHooks,
     ) -> Result<()> {
+        let mut rx = events.lock().await;
+        let mut sequencer = Sequencer::default();
         loop {
-            let event = rx.recv().await.ok_or(anyhow!("failed to receive event"))?;
+            let Some(event) = rx.recv().await else {
+                log::info!("every sender of events exited, not handling events anymore");
+                return Ok(());
+            };
             debug!("handling event: {:?}", event);
 
             let storage = storage.clone();
+            let stats = stats.clone();
+            let reloader = reloader.clone();
+            let hooks = hooks.clone();
+            let name = event.name();
+            let mut turn = sequencer.turn(&event.scope());
 
-            tokio::spawn(async move {
+            let task = async move {
+                turn.wait().await;
                 match event {
-                    Event::ListBackends(tx) => {
-                        ListBackendHandler::handle(storage, tx).await;
+                    Event::ListBackends(selector, tx) => {
+                        ListBackendHandler::handle(storage, selector, tx).await;
                     }
                     Event::PutBackend(backend, tx) => {
                         PutBackendHandler::handle(storage, backend, tx).await;
@@ -254,8 +1983,58 @@ impl Proxy {
                     Event::DeleteBackend(backend, tx) => {
                         DeleteBackendHandler::handle(storage, backend, tx).await;
                     }
+                    Event::RestoreBackend(hostname, tx) => {
+                        RestoreBackendHandler::handle(storage, hostname, tx).await;
+                    }
+                    Event::ApplyBatch(batch, tx) => {
+                        ApplyBatchHandler::handle(storage, batch, tx).await;
+                    }
+                    Event::SnapshotState(tx) => {
+                        SnapshotStateHandler::handle(storage, stats, tx).await;
+                    }
+                    Event::RestoreState(snapshot, tx) => {
+                        RestoreStateHandler::handle(storage, stats, snapshot, tx).await;
+                    }
+                    Event::WatchBackends(tx) => {
+                        WatchBackendsHandler::handle(storage, tx).await;
+                    }
+                    Event::ReloadConfig(tx) => {
+                        let _ = tx.send(reloader.reload().await);
+                    }
+                    Event::GetConfig(tx) => {
+                        let _ = tx.send(Ok(reloader.config()));
+                    }
+                    Event::ProbeBackend(hostname, tx) => {
+                        ProbeBackendHandler::handle(storage, hostname, tx).await;
+                    }
+                    Event::ValidateBackend(backend, tx) => {
+                        let route_by_port = reloader.config().proxy.route_by_port;
+                        ValidateBackendHandler::handle(storage, backend, route_by_port, tx).await;
+                    }
+                    Event::ListAccessRules(tx) => {
+                        ListAccessRulesHandler::handle(storage, tx).await;
+                    }
+                    Event::PutAccessRule(rule, tx) => {
+                        PutAccessRuleHandler::handle(storage, rule, tx).await;
+                    }
+                    Event::DeleteAccessRule(kind, subject, tx) => {
+                        DeleteAccessRuleHandler::handle(storage, kind, subject, tx).await;
+                    }
+                    Event::ListStatusAssets(tx) => {
+                        ListStatusAssetsHandler::handle(storage, tx).await;
+                    }
+                    Event::PutStatusAsset(asset, tx) => {
+                        PutStatusAssetHandler::handle(storage, asset, tx).await;
+                    }
+                    Event::DeleteStatusAsset(hostname, tx) => {
+                        DeleteStatusAssetHandler::handle(storage, hostname, tx).await;
+                    }
                 }
-                Ok(())
+            };
+
+            tokio::spawn(async move {
+                let context = || ErrorContext::new(ErrorSource::EventHandler).with("event", name);
+                hooks.catch_panics(task, context).await
             });
         }
     }
@@ -0,0 +1,261 @@
+use std::{fmt, net::IpAddr, time::Duration};
+
+use config::OnlineModeConfig;
+use hyper::{body, client::HttpConnector, Client, StatusCode};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use protocol::{
+    encryption,
+    packets::{
+        clientbound::encryption_request::EncryptionRequest, serverbound::login_start::LoginStart,
+    },
+    ProtocolError,
+};
+use rand::RngCore;
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::time::timeout;
+
+use crate::stream::Stream;
+
+/// The protocol versions the proxy authenticates, from 1.8
+pub const MIN_PROTOCOL_VERSION: i32 = 47;
+
+/// The protocol versions of 1.19 to 1.19.2, whose clients sign a salt with their chat key instead
+/// of sending the verify token back, which the proxy doesn't check
+pub const SIGNED_SALT_VERSIONS: [i32; 2] = [759, 760];
+
+/// The profile of a player, as returned by the session server
+///
+/// Properties:
+///
+/// * `id`: The UUID of the player, without dashes.
+/// * `name`: The username of the player, with its case.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+}
+
+/// Errors returned when a player can't be authenticated
+///
+/// Properties:
+///
+/// * `UnsupportedVersion`: The protocol version of the client is not authenticated by the proxy.
+/// * `VerifyTokenMismatch`: The client didn't send the verify token of the request back.
+/// * `Protocol`: The client broke the protocol, or its stream failed, while enabling the
+///   encryption.
+/// * `KeyPair`: The key pair of the proxy couldn't be generated.
+/// * `InvalidSessionServer`: The URL of the session server is invalid.
+/// * `SessionServerTimeout`: The session server didn't answer in time.
+/// * `SessionServer`: The session server couldn't be reached.
+/// * `SessionServerStatus`: The session server answered with an unexpected status.
+/// * `InvalidProfile`: The session server answered with an invalid profile.
+#[derive(Debug, Error)]
+pub enum AuthenticationError {
+    #[error("protocol version {0} is not supported in online mode")]
+    UnsupportedVersion(i32),
+    #[error("the verify token doesn't match")]
+    VerifyTokenMismatch,
+    #[error("failed to enable the encryption")]
+    Protocol(#[from] ProtocolError),
+    #[error("failed to generate the key pair: {0}")]
+    KeyPair(String),
+    #[error("invalid session server URL {0}")]
+    InvalidSessionServer(String),
+    #[error("the session server timed out")]
+    SessionServerTimeout,
+    #[error("failed to reach the session server")]
+    SessionServer(#[from] hyper::Error),
+    #[error("the session server answered {0}")]
+    SessionServerStatus(StatusCode),
+    #[error("invalid profile returned by the session server")]
+    InvalidProfile(#[from] serde_json::Error),
+}
+
+impl AuthenticationError {
+    /// It tells whether the player was rejected, rather than the proxy failing to authenticate it
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_rejected(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsupportedVersion(_) | Self::VerifyTokenMismatch
+        )
+    }
+
+    /// It tells whether the session server failed, so no player can be authenticated
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidSessionServer(_)
+                | Self::SessionServerTimeout
+                | Self::SessionServer(_)
+                | Self::SessionServerStatus(_)
+                | Self::InvalidProfile(_)
+        )
+    }
+}
+
+/// It authenticates the players with the session server, like an online-mode server does
+///
+/// The proxy sends its public key to the client, which encrypts a shared secret with it and
+/// tells the session server it joins the server identified by the hash of both. The proxy then
+/// asks the session server whether the player joined, and encrypts the connection with the client.
+///
+/// Properties:
+///
+/// * `key`: The key pair of the proxy, generated at startup.
+/// * `public_key`: The public key, DER encoded as sent to the clients.
+/// * `client`: The HTTP client of the session server.
+pub struct Authenticator {
+    key: RsaPrivateKey,
+    public_key: Vec<u8>,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator").finish_non_exhaustive()
+    }
+}
+
+impl Authenticator {
+    /// It generates the key pair of the proxy, which takes a while
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self, AuthenticationError>
+    pub fn new() -> Result<Self, AuthenticationError> {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024)
+            .map_err(|e| AuthenticationError::KeyPair(e.to_string()))?;
+        let public_key = key
+            .to_public_key()
+            .to_public_key_der()
+            .map_err(|e| AuthenticationError::KeyPair(e.to_string()))?
+            .into_vec();
+
+        let https = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Self {
+            key,
+            public_key,
+            client: Client::builder().build(https),
+        })
+    }
+
+    /// It enables the encryption of the connection with a client logging in
+    ///
+    /// It sends the encryption request answering the login start of the client, and reads the
+    /// encryption response. Once it returns, the stream encrypts what it writes.
+    ///
+    /// Arguments:
+    ///
+    /// * `stream`: The stream of the client, right after its login start.
+    /// * `version`: The protocol version of the handshake.
+    /// * `login_start`: The login start read from the client.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the login start of the client, forwarded to the backend, and the server hash
+    /// sent to the session server
+    pub async fn encrypt(
+        &self,
+        stream: &mut Stream,
+        version: i32,
+        login_start: LoginStart,
+    ) -> Result<(LoginStart, String), AuthenticationError> {
+        if version < MIN_PROTOCOL_VERSION || SIGNED_SALT_VERSIONS.contains(&version) {
+            return Err(AuthenticationError::UnsupportedVersion(version));
+        }
+
+        let mut verify_token = vec![0u8; 4];
+        rand::thread_rng().fill_bytes(&mut verify_token);
+        let request = EncryptionRequest::new(self.public_key.clone(), verify_token.clone());
+        stream.write_encryption_request(&request, version).await?;
+
+        let response = stream.read_encryption_response(version).await?;
+        let decrypt = |data: &[u8]| {
+            self.key
+                .decrypt(Pkcs1v15Encrypt, data)
+                .map_err(|_| AuthenticationError::VerifyTokenMismatch)
+        };
+        match response.verify_token() {
+            Some(token) if decrypt(token)? == verify_token => {}
+            _ => return Err(AuthenticationError::VerifyTokenMismatch),
+        }
+        let shared_secret = decrypt(response.shared_secret())?;
+
+        let (encryptor, decryptor) = encryption::ciphers(&shared_secret)?;
+        stream.enable_encryption(encryptor, decryptor);
+
+        let server_hash = encryption::server_hash("", &shared_secret, &self.public_key);
+        Ok((login_start, server_hash))
+    }
+
+    /// It asks the session server whether a player joined the proxy
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The session server, and whether it checks the address of the player.
+    /// * `username`: The username of the login start.
+    /// * `server_hash`: The hash of the shared secret and of the public key.
+    /// * `ip`: The address of the client.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the profile of the player, none when the session server doesn't know it, an
+    /// error when the session server can't be reached
+    pub async fn has_joined(
+        &self,
+        config: &OnlineModeConfig,
+        username: &str,
+        server_hash: &str,
+        ip: IpAddr,
+    ) -> Result<Option<Profile>, AuthenticationError> {
+        // the names of the accounts only use these characters, so none needs escaping
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if username.is_empty() || !username.chars().all(valid) {
+            return Ok(None);
+        }
+
+        let mut url = format!(
+            "{}/session/minecraft/hasJoined?username={}&serverId={}",
+            config.session_server.trim_end_matches('/'),
+            username,
+            server_hash
+        );
+        if config.prevent_proxy_connections {
+            url.push_str(&format!("&ip={}", ip));
+        }
+        let uri = url
+            .parse()
+            .map_err(|_| AuthenticationError::InvalidSessionServer(url.clone()))?;
+
+        let request = async {
+            let response = self.client.get(uri).await?;
+            let status = response.status();
+            let body = body::to_bytes(response.into_body()).await?;
+            Ok::<_, hyper::Error>((status, body))
+        };
+        let (status, body) = timeout(Duration::from_secs(config.timeout_secs), request)
+            .await
+            .map_err(|_| AuthenticationError::SessionServerTimeout)??;
+
+        match status {
+            StatusCode::OK => Ok(Some(serde_json::from_slice(&body)?)),
+            StatusCode::NO_CONTENT => Ok(None),
+            status => Err(AuthenticationError::SessionServerStatus(status)),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use config::ProxyConfig;
+use shared::ddos::DdosMode;
+use storage::BackendStore;
+use tokio::sync::RwLock;
+
+/// It applies a new configuration to a running proxy, without dropping the live connections
+///
+/// The timeouts and messages are read by every new connection, and the limits and the static
+/// routes are applied to the storage. A change of `ddos_mode.enabled` flips the DDoS mode, which
+/// is otherwise left as the API set it. The bind addresses, the TLS configuration, the channels,
+/// the logging, the runtimes and the Kubernetes integration require a restart.
+///
+/// Properties:
+///
+/// * `config`: The configuration read by the connection handlers.
+/// * `storage`: The storage the limits are applied to.
+/// * `ddos`: The DDoS mode of the proxy.
+#[derive(Debug, Clone)]
+pub struct Reloader {
+    config: Arc<ArcSwap<ProxyConfig>>,
+    storage: Arc<RwLock<dyn BackendStore>>,
+    ddos: Arc<DdosMode>,
+}
+
+impl Reloader {
+    pub fn new(
+        config: Arc<ArcSwap<ProxyConfig>>,
+        storage: Arc<RwLock<dyn BackendStore>>,
+        ddos: Arc<DdosMode>,
+    ) -> Self {
+        Self {
+            config,
+            storage,
+            ddos,
+        }
+    }
+
+    /// It returns the configuration the proxy currently runs with
+    ///
+    /// Returns:
+    ///
+    /// An Arc<ProxyConfig>
+    pub fn config(&self) -> Arc<ProxyConfig> {
+        self.config.load_full()
+    }
+
+    /// It loads the configuration again and applies it
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn reload(&self) -> Result<()> {
+        let config = self.config.load().reload()?;
+        config.validate()?;
+        self.apply(config).await;
+
+        log::info!("configuration reloaded");
+        Ok(())
+    }
+
+    /// It applies a configuration to the storage, then publishes it to the connection handlers
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration to apply.
+    pub async fn apply(&self, mut config: ProxyConfig) {
+        // keep the settings in effect, so the configuration reflects what the proxy runs with
+        let current = self.config.load();
+        if current.requires_restart(&config) {
+            log::warn!(
+                "the bind addresses, TLS, channels, log, runtime, kubernetes and federation settings are only applied on restart"
+            );
+            config.proxy = current.proxy.clone();
+            config.listener = current.listener.clone();
+            config.metrics = current.metrics.clone();
+            config.health = current.health.clone();
+            config.channels = current.channels.clone();
+            config.log = current.log.clone();
+            config.runtime = current.runtime.clone();
+            config.kubernetes = current.kubernetes.clone();
+            config.federation = current.federation.clone();
+        }
+
+        {
+            let mut storage = self.storage.write().await;
+            storage.set_limits(&config.limits);
+            storage.set_static_routes(config.static_backends());
+        }
+        if config.ddos_mode.enabled != current.ddos_mode.enabled
+            && self.ddos.set(config.ddos_mode.enabled)
+        {
+            match config.ddos_mode.enabled {
+                true => log::warn!("DDoS mode turned on by the configuration"),
+                false => log::info!("DDoS mode turned off by the configuration"),
+            }
+        }
+
+        self.config.store(Arc::new(config));
+    }
+
+    /// It reloads the configuration every time the process receives a SIGHUP
+    ///
+    /// A configuration that fails to load is logged and the current one is kept.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(unix)]
+    pub async fn watch_signals(&self) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = signal(SignalKind::hangup())?;
+        while hangups.recv().await.is_some() {
+            log::info!("received SIGHUP, reloading configuration");
+            if let Err(e) = self.reload().await {
+                log::error!("failed to reload configuration: {:#}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It reloads the configuration every time the console sends a Ctrl-Break, the SIGHUP of
+    /// Windows
+    ///
+    /// A configuration that fails to load is logged and the current one is kept.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(windows)]
+    pub async fn watch_signals(&self) -> Result<()> {
+        use tokio::signal::windows::ctrl_break;
+
+        let mut breaks = ctrl_break()?;
+        while breaks.recv().await.is_some() {
+            log::info!("received Ctrl-Break, reloading configuration");
+            if let Err(e) = self.reload().await {
+                log::error!("failed to reload configuration: {:#}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It waits forever, the configuration is only reloaded through the gRPC API on this platform
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(any(unix, windows)))]
+    pub async fn watch_signals(&self) -> Result<()> {
+        std::future::pending().await
+    }
+}
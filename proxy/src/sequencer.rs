@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use listener::event::Scope;
+use tokio::sync::watch;
+
+/// It orders the handlers of the control-plane events, so the events changing the same backend
+/// or the same access rule are handled in the order they were received while the other ones run
+/// concurrently
+///
+/// Every handler changing a backend holds a `Turn`, the next handler changing the same backend
+/// waits for the turn to be dropped before it starts.
+///
+/// Properties:
+///
+/// * `last`: The turn of the last handler of every hostname and access rule, until it is done.
+/// * `barrier`: The turn of the last handler which may change every backend, until it is done.
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    last: HashMap<Key, watch::Receiver<()>>,
+    barrier: Option<watch::Receiver<()>>,
+}
+
+/// What the handlers of the events take turns on, a hostname and an access rule never share a key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Hostname(String),
+    AccessRule(String),
+}
+
+impl Sequencer {
+    /// It takes the turn of an event, behind the handlers of the events before it changing the
+    /// same backends
+    ///
+    /// Arguments:
+    ///
+    /// * `scope`: The backends changed by the event.
+    ///
+    /// Returns:
+    ///
+    /// The turn to wait for before handling the event, and to drop once it is handled
+    pub fn turn(&mut self, scope: &Scope) -> Turn {
+        // the handlers already done are forgotten, so the map only holds the running ones
+        self.last.retain(|_, done| done.has_changed().is_ok());
+        if let Some(barrier) = &self.barrier {
+            if barrier.has_changed().is_err() {
+                self.barrier = None;
+            }
+        }
+
+        let (tx, rx) = watch::channel(());
+        let mut before = Vec::new();
+        let keys: Vec<Key> = match scope {
+            Scope::None => return Turn { before, _done: tx },
+            Scope::Hostnames(hostnames) => hostnames.iter().cloned().map(Key::Hostname).collect(),
+            Scope::AccessRules(rules) => rules.iter().cloned().map(Key::AccessRule).collect(),
+            Scope::All => {
+                before.extend(self.last.drain().map(|(_, done)| done));
+                if let Some(barrier) = self.barrier.replace(rx) {
+                    before.push(barrier);
+                }
+                return Turn { before, _done: tx };
+            }
+        };
+        for key in keys {
+            // a batch may change a hostname twice, it doesn't wait for itself
+            match self.last.insert(key, rx.clone()) {
+                Some(done) if !done.same_channel(&rx) => before.push(done),
+                _ => {}
+            }
+        }
+
+        before.extend(self.barrier.clone());
+        Turn { before, _done: tx }
+    }
+}
+
+/// The turn of the handler of an event, see `Sequencer::turn`
+///
+/// Properties:
+///
+/// * `before`: The turns of the handlers to wait for.
+/// * `_done`: Dropped once the event is handled, or its handler panicked, to let the next
+///   handlers start.
+#[derive(Debug)]
+pub struct Turn {
+    before: Vec<watch::Receiver<()>>,
+    _done: watch::Sender<()>,
+}
+
+impl Turn {
+    /// It waits for the handlers before this one to be done
+    pub async fn wait(&mut self) {
+        // a turn is only forgotten once done, so the wait can be cancelled and resumed
+        while let Some(done) = self.before.last_mut() {
+            // nothing is ever sent, the wait ends once the sender is dropped
+            let _ = done.changed().await;
+            self.before.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn hostnames(hostnames: &[&str]) -> Scope {
+        Scope::Hostnames(hostnames.iter().map(|h| h.to_string()).collect())
+    }
+
+    async fn is_waiting(turn: &mut Turn) -> bool {
+        tokio::time::timeout(Duration::from_millis(20), turn.wait())
+            .await
+            .is_err()
+    }
+
+    #[tokio::test]
+    async fn it_orders_the_events_of_the_same_hostname() {
+        let mut sequencer = Sequencer::default();
+        let put = sequencer.turn(&hostnames(&["a.example.com"]));
+        let mut delete = sequencer.turn(&hostnames(&["a.example.com"]));
+        let mut other = sequencer.turn(&hostnames(&["b.example.com"]));
+        let mut list = sequencer.turn(&Scope::None);
+
+        assert!(!is_waiting(&mut other).await);
+        assert!(!is_waiting(&mut list).await);
+        assert!(is_waiting(&mut delete).await);
+        drop(put);
+        assert!(!is_waiting(&mut delete).await);
+    }
+
+    #[tokio::test]
+    async fn it_orders_the_events_of_the_same_access_rule() {
+        let mut sequencer = Sequencer::default();
+        let rule = |key: &str| Scope::AccessRules(vec![key.to_string()]);
+        let put = sequencer.turn(&rule("ban ip 10.0.0.1/32"));
+        let mut delete = sequencer.turn(&rule("ban ip 10.0.0.1/32"));
+        let mut allow = sequencer.turn(&rule("allow ip 10.0.0.1/32"));
+        // a hostname named like the rule is another key
+        let mut backend = sequencer.turn(&hostnames(&["ban ip 10.0.0.1/32"]));
+
+        assert!(!is_waiting(&mut allow).await);
+        assert!(!is_waiting(&mut backend).await);
+        assert!(is_waiting(&mut delete).await);
+        drop(put);
+        assert!(!is_waiting(&mut delete).await);
+    }
+
+    #[tokio::test]
+    async fn it_orders_the_events_of_every_hostname_around_a_restore() {
+        let mut sequencer = Sequencer::default();
+        let put = sequencer.turn(&hostnames(&["a.example.com"]));
+        let mut restore = sequencer.turn(&Scope::All);
+        let mut batch = sequencer.turn(&hostnames(&["b.example.com", "b.example.com"]));
+
+        assert!(is_waiting(&mut restore).await);
+        drop(put);
+        assert!(!is_waiting(&mut restore).await);
+        assert!(is_waiting(&mut batch).await);
+        drop(restore);
+        assert!(!is_waiting(&mut batch).await);
+
+        drop(batch);
+        sequencer.turn(&Scope::None);
+        assert!(sequencer.last.is_empty());
+        assert!(sequencer.barrier.is_none());
+    }
+}
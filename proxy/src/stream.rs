@@ -1,18 +1,63 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use anyhow::{anyhow, Result};
-use protocol::packets::{
-    clientbound,
-    serverbound::{self, handshake::NextState},
+use config::SocketConfig;
+use protocol::{
+    encryption::{self, Decryptor, Encryptor},
+    packets::{
+        clientbound::{encryption_request::EncryptionRequest, pong::Pong, status::Status},
+        serverbound::{
+            self, encryption_response::EncryptionResponse, login_start::LoginStart, ping::Ping,
+        },
+    },
+    pool,
+    state::ConnectionState,
+    ProtocolError, Result,
 };
+use shared::stats::BackendCounters;
+use socket2::SockRef;
 use tokio::{
-    io::AsyncWriteExt,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
     net::{TcpStream, ToSocketAddrs},
+    time::timeout,
 };
 
-#[derive(Debug)]
+use crate::transfer::Transfers;
+
+/// The time a client has to ping once the proxy wrote its status
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The size of the buffers relaying an encrypted or an observed connection
+const RELAY_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The ciphers of a connection whose encryption is enabled
+pub type Ciphers = (Encryptor, Decryptor);
+
+/// A TCP stream, encrypted once the proxy authenticated the player in online mode
+///
+/// It follows the state of the connection, its reads and writes fail with an `UnexpectedState`
+/// error when their packet doesn't belong to it.
 pub struct Stream {
     tcp_stream: TcpStream,
+    ciphers: Option<Ciphers>,
+    state: ConnectionState,
+}
+
+impl Debug for Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Stream")
+            .field("tcp_stream", &self.tcp_stream)
+            .field("encrypted", &self.ciphers.is_some())
+            .field("state", &self.state)
+            .finish()
+    }
 }
 
 impl Stream {
@@ -26,7 +71,11 @@ impl Stream {
     ///
     /// A new instance of the `TcpStreamWrapper` struct.
     pub fn wrap(tcp_stream: TcpStream) -> Self {
-        Self { tcp_stream }
+        Self {
+            tcp_stream,
+            ciphers: None,
+            state: ConnectionState::Handshaking,
+        }
     }
 
     /// It connects to a server, and returns a `TcpStream` wrapped in a `Stream` that can be used to
@@ -38,11 +87,9 @@ impl Stream {
     ///
     /// Returns:
     ///
-    /// A `Result<Self>`
-    pub async fn from<A: ToSocketAddrs + Debug + Clone>(server_addr: A) -> Result<Self> {
-        let tcp_stream = TcpStream::connect(server_addr.clone())
-            .await
-            .map_err(|e| anyhow!("Failed to connect to {:?}: {}", server_addr, e))?;
+    /// A `io::Result<Self>`
+    pub async fn from<A: ToSocketAddrs>(server_addr: A) -> io::Result<Self> {
+        let tcp_stream = TcpStream::connect(server_addr).await?;
 
         Ok(Self::wrap(tcp_stream))
     }
@@ -50,24 +97,87 @@ impl Stream {
     /// Configure the TCP stream.
     ///
     /// The first thing we do is call `set_nodelay` on the stream. This is a method that comes from the
-    /// `TcpStream` type. It returns a `Result` that we can use to check if the call succeeded
+    /// `TcpStream` type. It returns a `Result` that we can use to check if the call succeeded. The
+    /// options of the direction of the socket are applied next, the IPv6 sockets are not marked.
+    ///
+    /// Arguments:
+    ///
+    /// * `options`: The options of the sockets of this direction.
     ///
     /// Returns:
     ///
-    /// A Result<()>
-    pub fn configure(&self) -> Result<()> {
-        self.tcp_stream
-            .set_nodelay(true)
-            .map_err(|e| anyhow!("Failed to set nodelay on stream: {}", e))
+    /// A io::Result<()>
+    pub fn configure(&self, options: &SocketConfig) -> io::Result<()> {
+        self.tcp_stream.set_nodelay(true)?;
+
+        let socket = SockRef::from(&self.tcp_stream);
+        if let Some(tos) = options.tos() {
+            if self.tcp_stream.local_addr()?.is_ipv4() {
+                socket.set_tos(tos)?;
+            }
+        }
+        if let Some(size) = options.recv_buffer_bytes {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = options.send_buffer_bytes {
+            socket.set_send_buffer_size(size)?;
+        }
+        Ok(())
+    }
+
+    /// It returns the address of the peer
+    ///
+    /// Returns:
+    ///
+    /// A io::Result<SocketAddr>
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp_stream.peer_addr()
+    }
+
+    /// It returns the state of the connection
+    ///
+    /// Returns:
+    ///
+    /// A ConnectionState
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// It returns the tcp stream, and its ciphers when the encryption is enabled, once the
+    /// connection is relayed in the play state
+    ///
+    /// Returns:
+    ///
+    /// A (TcpStream, Option<Ciphers>)
+    pub fn into_parts(self) -> (TcpStream, Option<Ciphers>) {
+        (self.tcp_stream, self.ciphers)
+    }
+
+    /// It enables the encryption, everything written and read from now on is encrypted
+    ///
+    /// Arguments:
+    ///
+    /// * `encryptor`: The cipher of what is written.
+    /// * `decryptor`: The cipher of what is read.
+    pub fn enable_encryption(&mut self, encryptor: Encryptor, decryptor: Decryptor) {
+        self.ciphers = Some((encryptor, decryptor));
     }
 
-    /// It returns the tcp stream
+    /// It writes bytes to the stream, encrypted when the encryption is enabled
+    ///
+    /// Arguments:
+    ///
+    /// * `data`: The bytes to write, encrypted in place.
     ///
     /// Returns:
     ///
-    /// A TcpStream
-    pub fn tcp_stream(self) -> TcpStream {
-        self.tcp_stream
+    /// A Result<()>
+    async fn write_all(&mut self, data: &mut [u8]) -> Result<()> {
+        if let Some((encryptor, _)) = &mut self.ciphers {
+            encryption::encrypt(encryptor, data);
+        }
+        self.tcp_stream.write_all(data).await?;
+        Ok(())
     }
 
     /// It reads a handshake from the stream
@@ -76,7 +186,11 @@ impl Stream {
     ///
     /// A Result<Handshake>
     pub async fn read_handshake(&mut self) -> Result<serverbound::handshake::Handshake> {
-        serverbound::handshake::Handshake::read(&mut self.tcp_stream).await
+        self.state
+            .expect(ConnectionState::Handshaking, "handshake")?;
+        let handshake = serverbound::handshake::Handshake::read(&mut self.tcp_stream).await?;
+        self.state = ConnectionState::after_handshake(handshake.next_state());
+        Ok(handshake)
     }
 
     /// It writes a handshake to the stream
@@ -92,42 +206,381 @@ impl Stream {
         &mut self,
         handshake: &serverbound::handshake::Handshake,
     ) -> Result<()> {
-        handshake.write(&mut self.tcp_stream).await
+        self.state
+            .expect(ConnectionState::Handshaking, "handshake")?;
+        handshake.write(&mut self.tcp_stream).await?;
+        self.state = ConnectionState::after_handshake(handshake.next_state());
+        Ok(())
+    }
+
+    /// It reads the login start of a client, before the encryption is enabled
+    ///
+    /// Returns:
+    ///
+    /// A Result<LoginStart>
+    pub async fn read_login_start(&mut self) -> Result<LoginStart> {
+        self.state.expect(ConnectionState::Login, "login start")?;
+        LoginStart::read(&mut self.tcp_stream).await
     }
 
-    /// It kicks the user with the message "Backend not found"
+    /// It writes the login start of a client to a backend
     ///
     /// Arguments:
     ///
-    /// * `next_state`: Next state of the handshake
+    /// * `login_start`: The login start read from the client.
     ///
     /// Returns:
     ///
     /// A Result<()>
-    pub async fn kick_backend_not_found(&mut self, next_state: NextState) -> Result<()> {
-        // todo(iverly): get the message from the env for each next_state
-        self.kick("Backend not found".to_string(), next_state).await
+    pub async fn write_login_start(&mut self, login_start: &LoginStart) -> Result<()> {
+        self.state.expect(ConnectionState::Login, "login start")?;
+        let mut data = pool::buffer();
+        login_start.write(&mut *data).await?;
+        self.write_all(&mut data).await
+    }
+
+    /// It asks the client to enable the encryption
+    ///
+    /// Arguments:
+    ///
+    /// * `request`: The public key and the verify token of the proxy.
+    /// * `version`: The protocol version of the client.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn write_encryption_request(
+        &mut self,
+        request: &EncryptionRequest,
+        version: i32,
+    ) -> Result<()> {
+        self.state
+            .expect(ConnectionState::Login, "encryption request")?;
+        request.write(&mut self.tcp_stream, version).await
+    }
+
+    /// It reads the answer of the client to the encryption request
+    ///
+    /// Arguments:
+    ///
+    /// * `version`: The protocol version of the client.
+    ///
+    /// Returns:
+    ///
+    /// A Result<EncryptionResponse>
+    pub async fn read_encryption_response(&mut self, version: i32) -> Result<EncryptionResponse> {
+        self.state
+            .expect(ConnectionState::Login, "encryption response")?;
+        EncryptionResponse::read(&mut self.tcp_stream, version).await
+    }
+
+    /// It kicks the user because no backend matches the hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `message`: The message shown to the user
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn kick_backend_not_found(&mut self, message: String) -> Result<()> {
+        self.kick(Status::from_error(message)).await
+    }
+
+    /// It kicks the user with a favicon next to the MOTD of a status ping, e.g. the one of the
+    /// catch-all when no backend matches the hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `message`: The message shown to the user
+    /// * `favicon`: The favicon of the server list, a `data:image/png;base64,` URI
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn kick_with_favicon(
+        &mut self,
+        message: String,
+        favicon: Option<String>,
+    ) -> Result<()> {
+        let status = Status::from_error(message);
+        match favicon {
+            Some(favicon) => self.kick(status.with_favicon(favicon)).await,
+            None => self.kick(status).await,
+        }
+    }
+
+    /// It answers the ping of a client once its status is written, the clients not pinging within
+    /// `PING_TIMEOUT` are left without a pong
+    async fn answer_ping(&mut self) {
+        let ping = match timeout(PING_TIMEOUT, Ping::read(&mut self.tcp_stream)).await {
+            Ok(Ok(ping)) => ping,
+            Ok(Err(e)) if e.is_malformed() => {
+                tracing::debug!(error = %e, "malformed ping after the status");
+                return;
+            }
+            _ => return,
+        };
+        let _ = Pong::new(&ping).write(&mut self.tcp_stream).await;
     }
 
     /// It kicks the user with the reason, then shuts down the TCP stream
     ///
+    /// The reason is the MOTD of a status ping, and the disconnect of a login. A status is
+    /// followed by the pong of the ping of the client, so the server list shows a latency rather
+    /// than a failed ping.
+    ///
     /// Arguments:
     ///
-    /// * `reason`: The reason for the kick.
-    /// * `next_state`: The next state the client will be in.
+    /// * `status`: The reason for the kick.
     ///
     /// Returns:
     ///
     /// Result<()>
-    async fn kick(&mut self, reason: String, next_state: NextState) -> Result<()> {
-        let status = clientbound::status::Status::from_error(reason);
-
-        match next_state {
-            NextState::Login => status.write_as_text(&mut self.tcp_stream).await,
-            NextState::Status => status.write_as_motd(&mut self.tcp_stream).await,
+    async fn kick(&mut self, status: Status) -> Result<()> {
+        let mut data = pool::buffer();
+        match self.state {
+            ConnectionState::Login => status.write_as_text(&mut *data).await,
+            ConnectionState::Status => status.write_as_motd(&mut *data).await,
+            state => Err(ProtocolError::UnexpectedState {
+                packet: "disconnect",
+                state,
+            }),
         }?;
+        self.write_all(&mut data).await?;
+        if self.state == ConnectionState::Status {
+            self.answer_ping().await;
+        }
 
         self.tcp_stream.shutdown().await?;
         Ok(())
     }
 }
+
+/// It relays the bytes of an encrypted client with a backend until both sides are closed
+///
+/// Arguments:
+///
+/// * `client`: The connection of the client.
+/// * `server`: The connection of the backend.
+/// * `ciphers`: The ciphers of the client.
+/// * `counters`: The counters of the backend, the bytes are added to as they are relayed.
+///
+/// Returns:
+///
+/// The bytes copied from the client to the server, and from the server to the client
+pub async fn copy_encrypted(
+    client: TcpStream,
+    server: TcpStream,
+    (mut encryptor, mut decryptor): Ciphers,
+    counters: &BackendCounters,
+) -> io::Result<(u64, u64)> {
+    let (client_read, mut client_write) = client.into_split();
+    let (server_read, mut server_write) = server.into_split();
+    let mut client_read = Counted::new(client_read, counters.bytes_in());
+    let mut server_read = Counted::new(server_read, counters.bytes_out());
+
+    tokio::try_join!(
+        relay(&mut client_read, &mut server_write, |data| {
+            encryption::decrypt(&mut decryptor, data)
+        }),
+        relay(&mut server_read, &mut client_write, |data| {
+            encryption::encrypt(&mut encryptor, data)
+        })
+    )
+}
+
+/// It relays the bytes of a client with a backend until both sides are closed, and hands the
+/// unencrypted bytes of both directions to inspectors, e.g. a packet observer or a capture
+///
+/// Arguments:
+///
+/// * `client`: The connection of the client.
+/// * `server`: The connection of the backend.
+/// * `ciphers`: The ciphers of the client, none when its connection isn't encrypted.
+/// * `serverbound`: It gets the bytes of the client, unencrypted.
+/// * `clientbound`: It gets the bytes of the backend.
+/// * `transfers`: The transfers of the connection, none when its client can't be transferred.
+/// * `counters`: The counters of the backend, the bytes are added to as they are relayed.
+///
+/// Returns:
+///
+/// The bytes copied from the client to the server, and from the server to the client
+pub(crate) async fn copy_inspected(
+    client: TcpStream,
+    server: TcpStream,
+    ciphers: Option<Ciphers>,
+    mut serverbound: impl FnMut(&[u8]),
+    mut clientbound: impl FnMut(&[u8]),
+    transfers: Option<Transfers>,
+    counters: &BackendCounters,
+) -> io::Result<(u64, u64)> {
+    let (client_read, mut client_write) = client.into_split();
+    let (server_read, mut server_write) = server.into_split();
+    let mut client_read = Counted::new(client_read, counters.bytes_in());
+    let mut server_read = Counted::new(server_read, counters.bytes_out());
+    let (mut encryptor, mut decryptor) = ciphers.unzip();
+
+    tokio::try_join!(
+        relay(&mut client_read, &mut server_write, |data| {
+            if let Some(decryptor) = &mut decryptor {
+                encryption::decrypt(decryptor, data);
+            }
+            serverbound(data);
+        }),
+        async {
+            let cipher = |data: &mut [u8]| {
+                clientbound(data);
+                if let Some(encryptor) = &mut encryptor {
+                    encryption::encrypt(encryptor, data);
+                }
+            };
+            match transfers {
+                Some(transfers) => {
+                    relay_transferable(&mut server_read, &mut client_write, cipher, transfers).await
+                }
+                None => relay(&mut server_read, &mut client_write, cipher).await,
+            }
+        }
+    )
+}
+
+/// It copies the bytes of a reader to a writer through a cipher, then shuts the writer down
+async fn relay<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    mut cipher: impl FnMut(&mut [u8]),
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; RELAY_BUFFER_SIZE];
+    let mut copied = 0;
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            writer.shutdown().await?;
+            return Ok(copied);
+        }
+
+        cipher(&mut buf[..read]);
+        writer.write_all(&buf[..read]).await?;
+        copied += read as u64;
+    }
+}
+
+/// It copies the bytes of a backend to its client through a cipher, until a transfer of the
+/// backend is requested: the packet being relayed is finished, the transfer is written after it,
+/// then the writer is shut down
+async fn relay_transferable<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    mut cipher: impl FnMut(&mut [u8]),
+    mut transfers: Transfers,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; RELAY_BUFFER_SIZE];
+    let mut copied = 0;
+    let mut requested = None;
+    loop {
+        let read = tokio::select! {
+            read = reader.read(&mut buf) => Some(read?),
+            transfer = transfers.requested(), if requested.is_none() => {
+                requested = Some(transfer);
+                None
+            }
+        };
+        if read == Some(0) {
+            writer.shutdown().await?;
+            return Ok(copied);
+        }
+        let read = read.unwrap_or(0);
+
+        let mut offset = 0;
+        loop {
+            // a transfer waits for the end of the packet being relayed, and for a state the
+            // client can be transferred in
+            if let Some(mut packet) = requested.as_ref().and_then(|t| transfers.packet(t)) {
+                cipher(&mut packet);
+                writer.write_all(&packet).await?;
+                writer.shutdown().await?;
+                return Ok(copied);
+            }
+            if offset == read {
+                break;
+            }
+            let relayed = transfers.advance(&buf[offset..read], requested.is_some());
+            let data = &mut buf[offset..offset + relayed];
+            cipher(data);
+            writer.write_all(data).await?;
+            copied += relayed as u64;
+            offset += relayed;
+        }
+    }
+}
+
+/// A stream adding the bytes read from it to a counter as they are read, e.g. the bytes relayed
+/// to a backend for its statistics
+///
+/// Properties:
+///
+/// * `inner`: The stream.
+/// * `bytes`: The counter.
+pub struct Counted<'a, T> {
+    inner: T,
+    bytes: &'a AtomicU64,
+}
+
+impl<'a, T> Counted<'a, T> {
+    /// Creates a new instance of the `Counted` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `inner`: The stream.
+    /// * `bytes`: The counter of the bytes read.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(inner: T, bytes: &'a AtomicU64) -> Self {
+        Self { inner, bytes }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Counted<'_, T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - filled;
+        if read > 0 {
+            self.bytes.fetch_add(read as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Counted<'_, T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use listener::event::Event;
+use tokio::sync::mpsc::Sender;
+
+use crate::{supervisor::Supervisor, Proxy};
+
+/// The gRPC API, with the socket it serves on
+#[cfg(feature = "grpc")]
+pub(crate) type ControlPlane = (listener::Listener, tokio::net::TcpListener);
+
+/// Nothing to serve, the proxy is built without the gRPC API
+#[cfg(not(feature = "grpc"))]
+pub(crate) type ControlPlane = (std::convert::Infallible, tokio::net::TcpListener);
+
+impl Proxy {
+    /// It serves the gRPC API, restarted when it crashes, unless another control plane sends the
+    /// events
+    ///
+    /// Arguments:
+    ///
+    /// * `supervisor`: Restarts the listener when it crashes.
+    /// * `control_plane`: The gRPC API and its listener, none when another control plane sends
+    ///   the events.
+    /// * `tx`: The sender of the events of the gRPC API.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(feature = "grpc")]
+    pub(crate) async fn serve_control_plane(
+        &self,
+        supervisor: &Supervisor,
+        control_plane: Option<ControlPlane>,
+        tx: Sender<Event>,
+    ) -> Result<()> {
+        let Some((listener, control_listener)) = control_plane else {
+            return Ok(());
+        };
+        let listener_addr = control_listener.local_addr()?;
+        let mut control_listener = Some(control_listener);
+
+        supervisor
+            .supervise("listener", || {
+                let incoming = control_listener.take();
+                let (listener, health, tx) = (&listener, &self.health, tx.clone());
+                async move {
+                    // a restart binds the address of the first start again, its port included
+                    let incoming = match incoming {
+                        Some(incoming) => incoming,
+                        None => {
+                            let incoming = tokio::net::TcpListener::bind(listener_addr)
+                                .await
+                                .map_err(|e| {
+                                    anyhow::anyhow!(
+                                        "failed to bind listener to {}: {}",
+                                        listener_addr,
+                                        e
+                                    )
+                                })?;
+                            health.set_listener_bound();
+                            incoming
+                        }
+                    };
+                    let result = listener.serve(incoming, tx).await;
+                    health.set_listener_unbound();
+                    result
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// It has no gRPC API to serve, the proxy is built without the `grpc` feature
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(feature = "grpc"))]
+    pub(crate) async fn serve_control_plane(
+        &self,
+        _supervisor: &Supervisor,
+        _control_plane: Option<ControlPlane>,
+        _tx: Sender<Event>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// It serves the metrics over HTTP, when the proxy is built with the `metrics-server` feature
+    ///
+    /// Arguments:
+    ///
+    /// * `listener`: The listener of the metrics server, none without the feature.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub(crate) async fn serve_metrics(
+        &self,
+        listener: Option<std::net::TcpListener>,
+    ) -> Result<()> {
+        match listener {
+            #[cfg(feature = "metrics-server")]
+            Some(listener) => metrics::serve_listener(listener, self.metrics.clone()).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// It answers the status pings of the load balancers when the `health.ping_port` configuration
+    /// is set
+    ///
+    /// Arguments:
+    ///
+    /// * `listener`: The listener of the ping port, none when it is unset.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub(crate) async fn serve_pings(&self, listener: Option<std::net::TcpListener>) -> Result<()> {
+        match listener {
+            Some(listener) => health::serve_pings(listener, self.health.clone()).await,
+            None => Ok(()),
+        }
+    }
+
+    /// It pushes the metrics to a StatsD agent when the `metrics.statsd` configuration is set
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(feature = "statsd")]
+    pub(crate) async fn push_statsd(&self) -> Result<()> {
+        let config = self.config.load_full();
+        metrics::statsd::push(config.metrics.statsd.clone(), self.metrics.clone()).await
+    }
+
+    /// It warns the `metrics.statsd` configuration is ignored, the proxy is built without the
+    /// `statsd` feature
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(feature = "statsd"))]
+    pub(crate) async fn push_statsd(&self) -> Result<()> {
+        if self.config.load().metrics.statsd.is_some() {
+            log::warn!("the statsd configuration is ignored, the proxy is built without statsd");
+        }
+        Ok(())
+    }
+
+    /// It pushes the metrics to an OpenTelemetry collector when the `metrics.otlp` configuration
+    /// is set
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(feature = "otlp")]
+    pub(crate) async fn push_otlp(&self) -> Result<()> {
+        let config = self.config.load_full();
+        metrics::otlp::push(config.metrics.otlp.clone(), self.metrics.clone()).await
+    }
+
+    /// It warns the `metrics.otlp` configuration is ignored, the proxy is built without the
+    /// `otlp` feature
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(feature = "otlp"))]
+    pub(crate) async fn push_otlp(&self) -> Result<()> {
+        if self.config.load().metrics.otlp.is_some() {
+            log::warn!("the otlp configuration is ignored, the proxy is built without otlp");
+        }
+        Ok(())
+    }
+
+    /// It checkpoints the cumulative totals of the backends into the storage every
+    /// `stats.checkpoint_secs`, so the state snapshots carry them across the restarts
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub(crate) async fn checkpoint_stats(&self) -> Result<()> {
+        loop {
+            let interval = Duration::from_secs(self.config.load().stats.checkpoint_secs);
+            tokio::time::sleep(interval).await;
+
+            // the lock is taken first so a restore can't slip between the totals and the write
+            let mut storage = self.storage.write().await;
+            storage.checkpoint_totals(self.stats.cumulative_totals());
+        }
+    }
+
+    /// It runs the Kubernetes integrations enabled by the `kubernetes` configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: The sender of the events of the controllers.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(feature = "kubernetes")]
+    pub(crate) async fn run_operator(&self, tx: Sender<Event>) -> Result<()> {
+        let operator = operator::Operator::new(
+            self.config.load().kubernetes.clone(),
+            self.activity.clone(),
+            self.endpoints.clone(),
+        );
+        operator.start(tx).await
+    }
+
+    /// It warns the Kubernetes integrations are ignored, the proxy is built without the
+    /// `kubernetes` feature
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: The sender of the events of the controllers.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(feature = "kubernetes"))]
+    pub(crate) async fn run_operator(&self, _tx: Sender<Event>) -> Result<()> {
+        let config = self.config.load();
+        let kubernetes = &config.kubernetes;
+        if kubernetes.operator || kubernetes.ingress || kubernetes.gateway || kubernetes.pods {
+            log::warn!(
+                "the kubernetes configuration is ignored, the proxy is built without kubernetes"
+            );
+        }
+        Ok(())
+    }
+
+    /// It mirrors the routes of the peers of the `federation` configuration
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: The sender of the events of the mirrored routes.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(feature = "federation")]
+    pub(crate) async fn run_federation(&self, tx: Sender<Event>) -> Result<()> {
+        let federation = federation::Federation::new(self.config.load().federation.clone());
+        federation.start(tx).await
+    }
+
+    /// It warns the peers are ignored, the proxy is built without the `federation` feature
+    ///
+    /// Arguments:
+    ///
+    /// * `tx`: The sender of the events of the mirrored routes.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    #[cfg(not(feature = "federation"))]
+    pub(crate) async fn run_federation(&self, _tx: Sender<Event>) -> Result<()> {
+        if !self.config.load().federation.is_empty() {
+            log::warn!("the federation peers are ignored, the proxy is built without federation");
+        }
+        Ok(())
+    }
+}
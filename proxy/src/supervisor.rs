@@ -0,0 +1,156 @@
+use std::{future::Future, panic::AssertUnwindSafe, time::Duration};
+
+use anyhow::{anyhow, Result};
+use futures::FutureExt;
+use tokio::time::{sleep, Instant};
+
+use crate::hook::{panic_message, ErrorContext, ErrorHooks, ErrorSource};
+
+/// The delay before the first restart of a crashed subsystem
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The longest delay between two restarts of a crashed subsystem
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// It restarts the subsystems of the proxy which crash, waiting longer after each crash, so one
+/// of them failing doesn't leave the proxy half-alive
+///
+/// Properties:
+///
+/// * `hooks`: The hooks getting a report for every crash.
+/// * `initial_backoff`: The delay before the first restart, doubled after each crash.
+/// * `max_backoff`: The longest delay between two restarts. A subsystem which ran this long
+///   before crashing is restarted after the initial delay again.
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    hooks: ErrorHooks,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Supervisor {
+    /// Creates a new instance of the `Supervisor` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `hooks`: The hooks getting a report for every crash.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(hooks: ErrorHooks) -> Self {
+        Self {
+            hooks,
+            initial_backoff: INITIAL_BACKOFF,
+            max_backoff: MAX_BACKOFF,
+        }
+    }
+
+    /// It sets the delays between the restarts, instead of 500ms doubled up to 30s
+    ///
+    /// Arguments:
+    ///
+    /// * `initial`: The delay before the first restart.
+    /// * `max`: The longest delay between two restarts.
+    ///
+    /// Returns:
+    ///
+    /// The supervisor with the delays
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// It runs a subsystem until it exits without error, and starts it again whenever it returns
+    /// an error or panics
+    ///
+    /// Arguments:
+    ///
+    /// * `subsystem`: The name of the subsystem, e.g. `listener`.
+    /// * `start`: It starts the subsystem, once and then after each crash.
+    pub async fn supervise<F, Fut>(&self, subsystem: &'static str, mut start: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut backoff = self.initial_backoff;
+        loop {
+            let started = Instant::now();
+            let (error, panicked) = match AssertUnwindSafe(start()).catch_unwind().await {
+                Ok(Ok(())) => return,
+                Ok(Err(e)) => (e, false),
+                Err(panic) => (anyhow!("panicked: {}", panic_message(panic.as_ref())), true),
+            };
+
+            if started.elapsed() >= self.max_backoff {
+                backoff = self.initial_backoff;
+            }
+            log::error!(
+                "{} exited with error: {}, restarting in {:?}",
+                subsystem,
+                error,
+                backoff
+            );
+            let mut context =
+                ErrorContext::new(ErrorSource::Subsystem).with("subsystem", subsystem);
+            context.panicked = panicked;
+            self.hooks.report(&error, &context);
+
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use super::*;
+    use crate::hook::ErrorHook;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, ErrorContext)>>);
+
+    impl ErrorHook for Recorder {
+        fn report(&self, error: &anyhow::Error, context: &ErrorContext) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((error.to_string(), context.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_restarts_a_subsystem_until_it_exits_without_error() {
+        let recorder = Arc::new(Recorder::default());
+        let mut hooks = ErrorHooks::default();
+        hooks.add(recorder.clone());
+        let supervisor =
+            Supervisor::new(hooks).with_backoff(Duration::from_millis(1), Duration::from_millis(4));
+
+        let starts = AtomicUsize::new(0);
+        supervisor
+            .supervise("listener", || async {
+                match starts.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(anyhow!("the port is gone")),
+                    1 => panic!("the listener is gone"),
+                    _ => Ok(()),
+                }
+            })
+            .await;
+
+        assert_eq!(starts.load(Ordering::Relaxed), 3);
+        let reports = recorder.0.lock().unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].0, "the port is gone");
+        assert!(!reports[0].1.panicked);
+        assert_eq!(reports[1].0, "panicked: the listener is gone");
+        assert!(reports[1].1.panicked);
+        assert_eq!(reports[1].1.fields["subsystem"], "listener");
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use ulid::Ulid;
+
+use crate::frames::{Direction, Frame, FrameObserver};
+
+/// A packet relayed to or from a tapped backend, see `Proxy::taps`
+///
+/// Properties:
+///
+/// * `connection`: The ID of the connection, the one of its access log record.
+/// * `backend`: The hostname of the backend.
+/// * `direction`: The way the packet travels.
+/// * `login`: Whether the packet belongs to the login.
+/// * `id`: The ID of the packet, none when it is compressed.
+/// * `size`: The size of the packet, without its lengths, compressed or not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapEvent {
+    pub connection: Ulid,
+    pub backend: Arc<str>,
+    pub direction: Direction,
+    pub login: bool,
+    pub id: Option<i32>,
+    pub size: usize,
+}
+
+/// The observer of the connections to a tapped backend, which sends their packets to the
+/// subscribers of the taps
+///
+/// Properties:
+///
+/// * `events`: The sender of the taps, without subscribers the packets are dropped.
+/// * `backend`: The hostname of the backend.
+/// * `observer`: The observer of the proxy, which still gets the packets.
+pub(crate) struct Tap<'a> {
+    events: &'a broadcast::Sender<TapEvent>,
+    backend: Arc<str>,
+    observer: Option<&'a dyn FrameObserver>,
+}
+
+impl<'a> Tap<'a> {
+    /// Creates a new instance of the `Tap` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `events`: The sender of the taps.
+    /// * `backend`: The hostname of the backend.
+    /// * `observer`: The observer of the proxy, if any.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub(crate) fn new(
+        events: &'a broadcast::Sender<TapEvent>,
+        backend: &str,
+        observer: Option<&'a dyn FrameObserver>,
+    ) -> Self {
+        Self {
+            events,
+            backend: backend.into(),
+            observer,
+        }
+    }
+}
+
+impl FrameObserver for Tap<'_> {
+    fn observe(&self, connection: Ulid, frame: &Frame<'_>) {
+        if let Some(observer) = self.observer {
+            observer.observe(connection, frame);
+        }
+        // a send only fails without subscribers, nobody is tapping then
+        let _ = self.events.send(TapEvent {
+            connection,
+            backend: self.backend.clone(),
+            direction: frame.direction,
+            login: frame.login,
+            id: frame.id,
+            size: frame.data.len(),
+        });
+    }
+}
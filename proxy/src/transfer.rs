@@ -0,0 +1,341 @@
+use std::sync::{
+    atomic::{AtomicI32, AtomicU8, Ordering},
+    Arc,
+};
+
+use protocol::{
+    decode_var_int,
+    packets::clientbound::transfer::{self, TRANSFER_ID},
+    ProtocolError, MAX_FRAME_SIZE,
+};
+use shared::sessions::Transfer;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::frames::{Direction, SET_COMPRESSION_ID};
+
+/// The ID of the encryption request, clientbound in the login state: the backend encrypts the
+/// connection itself
+const ENCRYPTION_REQUEST_ID: i32 = 1;
+
+/// The ID of the login acknowledged, serverbound in the login state
+const LOGIN_ACKNOWLEDGED_ID: i32 = 3;
+
+/// The ID of the finish configuration, clientbound in the configuration state
+const FINISH_CONFIGURATION_ID: i32 = 3;
+
+/// The bytes of a packet the cursor keeps: its data length, its ID and a VarInt field
+const HEAD_SIZE: usize = 15;
+
+/// The state of a relayed connection, as far as the transfers are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Login,
+    Configuration,
+    Play,
+    /// The stream can't be followed, e.g. the backend encrypts it: its players stay.
+    Opaque,
+}
+
+/// What both directions of a relayed connection learn about it
+///
+/// Properties:
+///
+/// * `version`: The protocol version of the client.
+/// * `phase`: The state of the connection, a `Phase`.
+/// * `threshold`: The compression threshold set by the backend, negative until it is set.
+#[derive(Debug)]
+struct Progress {
+    version: i32,
+    phase: AtomicU8,
+    threshold: AtomicI32,
+}
+
+impl Progress {
+    fn phase(&self) -> Phase {
+        match self.phase.load(Ordering::Acquire) {
+            0 => Phase::Login,
+            1 => Phase::Configuration,
+            2 => Phase::Play,
+            _ => Phase::Opaque,
+        }
+    }
+
+    fn set_phase(&self, phase: Phase) {
+        self.phase.store(phase as u8, Ordering::Release);
+    }
+
+    fn compressed(&self) -> bool {
+        self.threshold.load(Ordering::Acquire) >= 0
+    }
+}
+
+/// It follows the packets of one direction of a relayed connection without keeping them, to tell
+/// where a packet ends and which state the connection is in
+///
+/// Properties:
+///
+/// * `direction`: The way the packets travel.
+/// * `progress`: What is shared with the other direction.
+/// * `length`: The bytes read of the length of the next packet.
+/// * `remaining`: The bytes of the current packet left to read, 0 between two packets.
+/// * `head`: The first bytes of the current packet, enough for its ID and its first field.
+#[derive(Debug)]
+pub(crate) struct PacketCursor {
+    direction: Direction,
+    progress: Arc<Progress>,
+    length: Vec<u8>,
+    remaining: usize,
+    head: Vec<u8>,
+}
+
+impl PacketCursor {
+    fn new(direction: Direction, progress: Arc<Progress>) -> Self {
+        Self {
+            direction,
+            progress,
+            length: Vec::with_capacity(3),
+            remaining: 0,
+            head: Vec::with_capacity(HEAD_SIZE),
+        }
+    }
+
+    /// It reads the relayed bytes, up to the end of the first packet they complete when asked to
+    ///
+    /// Arguments:
+    ///
+    /// * `bytes`: The bytes relayed, unencrypted.
+    /// * `stop_at_boundary`: Whether to stop at the end of a packet.
+    ///
+    /// Returns:
+    ///
+    /// The number of bytes read
+    pub(crate) fn advance(&mut self, bytes: &[u8], stop_at_boundary: bool) -> usize {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            if self.progress.phase() == Phase::Opaque {
+                return bytes.len();
+            }
+            if self.remaining == 0 {
+                self.length.push(bytes[offset]);
+                offset += 1;
+                match decode_var_int(&self.length) {
+                    Ok((length, _)) if length > 0 && length as usize <= MAX_FRAME_SIZE => {
+                        self.remaining = length as usize;
+                        self.length.clear();
+                        self.head.clear();
+                    }
+                    Err(ProtocolError::Truncated) if self.length.len() < 3 => {}
+                    _ => {
+                        tracing::debug!(
+                            direction = ?self.direction,
+                            "the relayed stream can't be followed"
+                        );
+                        self.progress.set_phase(Phase::Opaque);
+                    }
+                }
+                continue;
+            }
+
+            let taken = self.remaining.min(bytes.len() - offset);
+            let kept = (HEAD_SIZE - self.head.len()).min(taken);
+            self.head.extend_from_slice(&bytes[offset..offset + kept]);
+            self.remaining -= taken;
+            offset += taken;
+            if self.remaining == 0 {
+                self.follow();
+                if stop_at_boundary {
+                    return offset;
+                }
+            }
+        }
+        offset
+    }
+
+    /// It follows the packets changing the state or the framing of the connection
+    fn follow(&self) {
+        let mut data = &self.head[..];
+        if self.progress.compressed() {
+            match decode_var_int(data) {
+                // the packets changing the state are too short to be compressed
+                Ok((0, read)) => data = &data[read..],
+                _ => return,
+            }
+        }
+        let Ok((id, read)) = decode_var_int(data) else {
+            return;
+        };
+
+        match (self.direction, self.progress.phase()) {
+            (Direction::Clientbound, Phase::Login) if id == SET_COMPRESSION_ID => {
+                if let Ok((threshold, _)) = decode_var_int(&data[read..]) {
+                    self.progress.threshold.store(threshold, Ordering::Release);
+                }
+            }
+            (Direction::Clientbound, Phase::Login) if id == ENCRYPTION_REQUEST_ID => {
+                self.progress.set_phase(Phase::Opaque)
+            }
+            (Direction::Serverbound, Phase::Login) if id == LOGIN_ACKNOWLEDGED_ID => {
+                self.progress.set_phase(Phase::Configuration)
+            }
+            (Direction::Clientbound, Phase::Configuration) if id == FINISH_CONFIGURATION_ID => {
+                self.progress.set_phase(Phase::Play)
+            }
+            (Direction::Clientbound, Phase::Play)
+                if transfer::play_ids(self.progress.version)
+                    .is_some_and(|(start_configuration, _)| start_configuration == id) =>
+            {
+                self.progress.set_phase(Phase::Configuration)
+            }
+            _ => {}
+        }
+    }
+
+    /// It encodes the transfer packet of a request, when it can be written now
+    ///
+    /// Arguments:
+    ///
+    /// * `transfer`: The transfer requested.
+    ///
+    /// Returns:
+    ///
+    /// The frame of the packet, none in the middle of a packet, or in a state without a known
+    /// ID of the transfer
+    pub(crate) fn transfer(&self, transfer: &Transfer) -> Option<Vec<u8>> {
+        if self.remaining != 0 || !self.length.is_empty() {
+            return None;
+        }
+        let id = match self.progress.phase() {
+            Phase::Configuration => TRANSFER_ID,
+            Phase::Play => transfer::play_ids(self.progress.version)?.1,
+            Phase::Login | Phase::Opaque => return None,
+        };
+        let packet = transfer::Transfer::new(transfer.hostname.clone(), transfer.port);
+        Some(packet.encode(id, self.progress.compressed()))
+    }
+}
+
+/// The transfers of a relayed connection: the requests of its backend, and where the packets of
+/// the backend end, so a transfer is written between two of them
+///
+/// Properties:
+///
+/// * `backend`: The hostname of the backend of the connection.
+/// * `requests`: The transfers requested for every backend.
+/// * `clientbound`: The cursor of the packets of the backend.
+#[derive(Debug)]
+pub(crate) struct Transfers {
+    backend: String,
+    requests: broadcast::Receiver<Transfer>,
+    clientbound: PacketCursor,
+}
+
+impl Transfers {
+    /// Creates a new instance of the `Transfers` struct, at the start of the login
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The hostname of the backend of the connection.
+    /// * `version`: The protocol version of the client.
+    /// * `requests`: The transfers requested for every backend.
+    ///
+    /// Returns:
+    ///
+    /// The transfers, and the cursor of the packets of the client, which the serverbound relay
+    /// advances
+    pub(crate) fn new(
+        backend: &str,
+        version: i32,
+        requests: broadcast::Receiver<Transfer>,
+    ) -> (Self, PacketCursor) {
+        let progress = Arc::new(Progress {
+            version,
+            phase: AtomicU8::new(Phase::Login as u8),
+            threshold: AtomicI32::new(-1),
+        });
+        let transfers = Self {
+            backend: backend.to_string(),
+            requests,
+            clientbound: PacketCursor::new(Direction::Clientbound, progress.clone()),
+        };
+        (
+            transfers,
+            PacketCursor::new(Direction::Serverbound, progress),
+        )
+    }
+
+    /// It waits for a transfer of the backend of the connection
+    ///
+    /// Returns:
+    ///
+    /// The transfer, it never returns once the proxy stopped
+    pub(crate) async fn requested(&mut self) -> Transfer {
+        loop {
+            match self.requests.recv().await {
+                Ok(transfer) if transfer.backend == self.backend => return transfer,
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => std::future::pending().await,
+            }
+        }
+    }
+
+    /// It reads the relayed bytes of the backend, see `PacketCursor::advance`
+    pub(crate) fn advance(&mut self, bytes: &[u8], stop_at_boundary: bool) -> usize {
+        self.clientbound.advance(bytes, stop_at_boundary)
+    }
+
+    /// It encodes the transfer packet of a request, see `PacketCursor::transfer`
+    pub(crate) fn packet(&self, transfer: &Transfer) -> Option<Vec<u8>> {
+        self.clientbound.transfer(transfer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_writes_the_transfers_between_two_packets() {
+        let (_, requests) = broadcast::channel(1);
+        let (mut transfers, mut serverbound) = Transfers::new("lobby.example.com", 766, requests);
+        let transfer = Transfer {
+            backend: "lobby.example.com".to_string(),
+            hostname: "play.example.com".to_string(),
+            port: 25565,
+        };
+
+        // set compression to 256 then a login success, the players aren't transferred in the login
+        assert_eq!(
+            transfers.advance(b"\x03\x03\x80\x02\x04\x00\x02\xaa\xbb", false),
+            9
+        );
+        assert_eq!(transfers.packet(&transfer), None);
+
+        // the client acknowledges the login, then the backend sends half of a packet
+        assert_eq!(serverbound.advance(b"\x02\x00\x03", false), 3);
+        assert_eq!(transfers.advance(b"\x04\x00\x07", true), 3);
+        assert_eq!(transfers.packet(&transfer), None);
+        assert_eq!(transfers.advance(b"\xaa\xbb\x02\x00\x03", true), 2);
+        assert_eq!(
+            transfers.packet(&transfer).unwrap(),
+            b"\x16\x00\x0b\x10play.example.com\xdd\xc7\x01"
+        );
+
+        // the finish configuration moves the connection to the play state
+        assert_eq!(transfers.advance(b"\x02\x00\x03", true), 3);
+        assert_eq!(transfers.packet(&transfer).unwrap()[2], 0x73);
+    }
+
+    #[test]
+    fn it_leaves_the_connections_encrypted_by_the_backend() {
+        let (_, requests) = broadcast::channel(1);
+        let (mut transfers, _) = Transfers::new("lobby.example.com", 766, requests);
+        let transfer = Transfer {
+            backend: "lobby.example.com".to_string(),
+            hostname: "play.example.com".to_string(),
+            port: 25565,
+        };
+
+        assert_eq!(transfers.advance(b"\x02\x01\x00\x8f\x12\x34", false), 6);
+        assert_eq!(transfers.packet(&transfer), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// The players connected to every hostname, and the hostnames whose server is asleep
+///
+/// The proxy records the connections, and the Kubernetes integration scales the servers of the
+/// idle hostnames down, then back up when a player wakes them up.
+#[derive(Debug, Default)]
+pub struct Activity {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    hostnames: HashMap<String, Connections>,
+    sleeping: HashSet<String>,
+    wakeups: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+struct Connections {
+    active: usize,
+    last_seen: Instant,
+}
+
+impl Activity {
+    /// It records a player connected to a hostname, until the returned guard is dropped
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname the player connected to.
+    ///
+    /// Returns:
+    ///
+    /// A Connection
+    pub fn connect(self: &Arc<Self>, hostname: &str) -> Connection {
+        let mut inner = self.inner.lock().unwrap();
+        let connections = inner
+            .hostnames
+            .entry(hostname.to_string())
+            .or_insert_with(|| Connections {
+                active: 0,
+                last_seen: Instant::now(),
+            });
+        connections.active += 1;
+        connections.last_seen = Instant::now();
+
+        Connection {
+            activity: self.clone(),
+            hostname: hostname.to_string(),
+        }
+    }
+
+    /// It returns the number of players connected to a hostname
+    pub fn active(&self, hostname: &str) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .hostnames
+            .get(hostname)
+            .map(|connections| connections.active)
+            .unwrap_or_default()
+    }
+
+    /// It returns when a player was last connected to a hostname
+    pub fn last_seen(&self, hostname: &str) -> Option<Instant> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .hostnames
+            .get(hostname)
+            .map(|connections| connections.last_seen)
+    }
+
+    /// It tells whether the server of a hostname is asleep
+    pub fn is_sleeping(&self, hostname: &str) -> bool {
+        self.inner.lock().unwrap().sleeping.contains(hostname)
+    }
+
+    /// It marks the server of a hostname as asleep or awake
+    pub fn set_sleeping(&self, hostname: &str, sleeping: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if sleeping {
+            inner.sleeping.insert(hostname.to_string());
+        } else {
+            inner.sleeping.remove(hostname);
+            inner.wakeups.remove(hostname);
+        }
+    }
+
+    /// It asks for the server of a sleeping hostname to be woken up
+    pub fn wake(&self, hostname: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sleeping.contains(hostname) {
+            inner.wakeups.insert(hostname.to_string());
+        }
+    }
+
+    /// It returns the hostnames asked to be woken up since the last call
+    pub fn take_wakeups(&self) -> BTreeSet<String> {
+        std::mem::take(&mut self.inner.lock().unwrap().wakeups)
+    }
+}
+
+/// A player connected to a hostname, released when dropped
+#[derive(Debug)]
+pub struct Connection {
+    activity: Arc<Activity>,
+    hostname: String,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let mut inner = self.activity.inner.lock().unwrap();
+        if let Some(connections) = inner.hostnames.get_mut(&self.hostname) {
+            connections.active = connections.active.saturating_sub(1);
+            connections.last_seen = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_the_connections_of_a_hostname() {
+        let activity = Arc::new(Activity::default());
+        assert_eq!(activity.last_seen("lobby.example.com"), None);
+
+        let first = activity.connect("lobby.example.com");
+        let second = activity.connect("lobby.example.com");
+        assert_eq!(activity.active("lobby.example.com"), 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(activity.active("lobby.example.com"), 0);
+        assert!(activity.last_seen("lobby.example.com").is_some());
+    }
+
+    #[test]
+    fn it_only_wakes_up_sleeping_hostnames() {
+        let activity = Activity::default();
+        activity.wake("lobby.example.com");
+        assert!(activity.take_wakeups().is_empty());
+
+        activity.set_sleeping("lobby.example.com", true);
+        activity.wake("lobby.example.com");
+        assert_eq!(
+            activity.take_wakeups(),
+            BTreeSet::from(["lobby.example.com".to_string()])
+        );
+        assert!(activity.take_wakeups().is_empty());
+    }
+}
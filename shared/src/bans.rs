@@ -0,0 +1,291 @@
+use std::{
+    collections::VecDeque,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::expiring::ExpiringMap;
+
+/// The most addresses tracked, the least recently struck ones are forgotten past it
+const MAX_TRACKED: usize = 65_536;
+
+/// A misbehavior of a client, counted as a strike against its address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offense {
+    /// The client didn't send its handshake in time, or closed the connection before.
+    HandshakeFailure,
+    /// The handshake of the client broke the protocol.
+    MalformedPacket,
+    /// The player was kicked while logging in, e.g. because no backend matches the hostname.
+    Kick,
+}
+
+impl Offense {
+    /// It returns the name of the offense, as exposed by the API
+    ///
+    /// Returns:
+    ///
+    /// `handshake_failure`, `malformed_packet` or `kick`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HandshakeFailure => "handshake_failure",
+            Self::MalformedPacket => "malformed_packet",
+            Self::Kick => "kick",
+        }
+    }
+}
+
+/// When an address gets banned, and for how long
+///
+/// Properties:
+///
+/// * `max_strikes`: The number of strikes within the window banning the address.
+/// * `window`: How long a strike counts.
+/// * `duration`: How long the address is banned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BanPolicy {
+    pub max_strikes: usize,
+    pub window: Duration,
+    pub duration: Duration,
+}
+
+/// A temporary ban of an address
+///
+/// Properties:
+///
+/// * `ip`: The banned address.
+/// * `offense`: The offense of the last strike.
+/// * `strikes`: The number of strikes which got the address banned.
+/// * `banned_at_ms`: When it was banned, in milliseconds since the Unix epoch.
+/// * `expires_at_ms`: When the ban expires, in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ban {
+    pub ip: IpAddr,
+    pub offense: Offense,
+    pub strikes: usize,
+    pub banned_at_ms: u64,
+    pub expires_at_ms: u64,
+}
+
+/// The strikes and the ban of an address
+#[derive(Debug, Default)]
+struct Offender {
+    strikes: VecDeque<Instant>,
+    ban: Option<(Instant, Ban)>,
+}
+
+impl Offender {
+    /// It tells whether the address is banned
+    fn is_banned(&self, now: Instant) -> bool {
+        self.ban.as_ref().is_some_and(|(expires, _)| *expires > now)
+    }
+
+    /// It returns when the address can be forgotten, once its ban and its strikes expired
+    fn expires(&self, window: Duration, now: Instant) -> Instant {
+        let banned = self.ban.as_ref().map(|(expires, _)| *expires);
+        let struck = self.strikes.back().map(|strike| *strike + window);
+        banned.max(struck).unwrap_or(now)
+    }
+}
+
+/// The addresses banned for misbehaving, fail2ban-style
+///
+/// Every offense of a client is a strike against its address, and an address getting too many
+/// strikes within the window is banned for a while, so the floods of bots are dropped right after
+/// the accept.
+#[derive(Debug)]
+pub struct Bans {
+    offenders: Mutex<ExpiringMap<IpAddr, Offender>>,
+}
+
+impl Default for Bans {
+    fn default() -> Self {
+        Self {
+            offenders: Mutex::new(ExpiringMap::new(MAX_TRACKED)),
+        }
+    }
+}
+
+impl Bans {
+    /// It tells whether an address is banned
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        self.is_banned_at(ip, Instant::now())
+    }
+
+    fn is_banned_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let offenders = self.offenders.lock().unwrap();
+        offenders
+            .get(&ip, now)
+            .is_some_and(|offender| offender.is_banned(now))
+    }
+
+    /// It records a strike against an address, and bans it once it got too many of them
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    /// * `offense`: What the client did.
+    /// * `policy`: When the address gets banned, and for how long.
+    ///
+    /// Returns:
+    ///
+    /// The ban when this strike got the address banned
+    pub fn strike(&self, ip: IpAddr, offense: Offense, policy: &BanPolicy) -> Option<Ban> {
+        self.strike_at(ip, offense, policy, Instant::now())
+    }
+
+    fn strike_at(
+        &self,
+        ip: IpAddr,
+        offense: Offense,
+        policy: &BanPolicy,
+        now: Instant,
+    ) -> Option<Ban> {
+        let mut offenders = self.offenders.lock().unwrap();
+        let mut offender = offenders.remove(&ip, now).unwrap_or_default();
+        let ban = Self::count(&mut offender, ip, offense, policy, now);
+        let expires = offender.expires(policy.window, now);
+        offenders.insert(ip, offender, expires, now);
+
+        ban
+    }
+
+    /// It counts a strike against an offender, see `Bans::strike`
+    fn count(
+        offender: &mut Offender,
+        ip: IpAddr,
+        offense: Offense,
+        policy: &BanPolicy,
+        now: Instant,
+    ) -> Option<Ban> {
+        if offender.is_banned(now) {
+            return None;
+        }
+
+        while offender
+            .strikes
+            .front()
+            .is_some_and(|strike| now.duration_since(*strike) >= policy.window)
+        {
+            offender.strikes.pop_front();
+        }
+        offender.strikes.push_back(now);
+        if offender.strikes.len() < policy.max_strikes {
+            return None;
+        }
+
+        let banned_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let ban = Ban {
+            ip,
+            offense,
+            strikes: offender.strikes.len(),
+            banned_at_ms,
+            expires_at_ms: banned_at_ms + policy.duration.as_millis() as u64,
+        };
+        offender.strikes.clear();
+        offender.ban = Some((now + policy.duration, ban.clone()));
+
+        Some(ban)
+    }
+
+    /// It returns the active bans, the oldest first
+    ///
+    /// Returns:
+    ///
+    /// A Vec<Ban>
+    pub fn list(&self) -> Vec<Ban> {
+        let now = Instant::now();
+        let offenders = self.offenders.lock().unwrap();
+        let mut bans: Vec<Ban> = offenders
+            .values(now)
+            .filter(|offender| offender.is_banned(now))
+            .filter_map(|offender| offender.ban.as_ref())
+            .map(|(_, ban)| ban.clone())
+            .collect();
+        bans.sort_by_key(|ban| ban.banned_at_ms);
+
+        bans
+    }
+
+    /// It lifts the ban and forgets the strikes of an address, or of every address
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address to clear, none for all of them.
+    ///
+    /// Returns:
+    ///
+    /// The number of bans lifted
+    pub fn clear(&self, ip: Option<IpAddr>) -> usize {
+        let now = Instant::now();
+        let mut offenders = self.offenders.lock().unwrap();
+
+        match ip {
+            Some(ip) => offenders
+                .remove(&ip, now)
+                .map_or(0, |offender| offender.is_banned(now) as usize),
+            None => {
+                let lifted = offenders
+                    .values(now)
+                    .filter(|offender| offender.is_banned(now))
+                    .count();
+                offenders.clear();
+                lifted
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_bans_an_address_after_too_many_strikes() {
+        let bans = Bans::default();
+        let policy = BanPolicy {
+            max_strikes: 3,
+            window: Duration::from_secs(60),
+            duration: Duration::from_secs(600),
+        };
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+
+        // the first strike left the window before the third one
+        assert!(bans.strike_at(ip, Offense::Kick, &policy, start).is_none());
+        let later = start + Duration::from_secs(61);
+        assert!(bans.strike_at(ip, Offense::Kick, &policy, later).is_none());
+        assert!(bans
+            .strike_at(ip, Offense::MalformedPacket, &policy, later)
+            .is_none());
+        assert!(!bans.is_banned_at(ip, later));
+
+        let ban = bans
+            .strike_at(ip, Offense::MalformedPacket, &policy, later)
+            .unwrap();
+        assert_eq!(ban.offense, Offense::MalformedPacket);
+        assert_eq!(ban.strikes, 3);
+        assert!(bans.is_banned_at(ip, later + Duration::from_secs(599)));
+        assert!(!bans.is_banned_at("203.0.113.8".parse().unwrap(), later));
+        assert!(!bans.is_banned_at(ip, later + Duration::from_secs(600)));
+
+        for _ in 0..3 {
+            bans.strike(ip, Offense::HandshakeFailure, &policy);
+        }
+        assert_eq!(bans.list().len(), 1);
+        assert_eq!(bans.clear(Some(ip)), 1);
+        assert!(bans.list().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// The bytes of each direction recorded by default, when a capture doesn't set them
+pub const DEFAULT_CAPTURE_BYTES: usize = 64 * 1024;
+
+/// The most bytes of each direction a capture can record
+pub const MAX_CAPTURE_BYTES: usize = 16 * 1024 * 1024;
+
+/// What a capture of a hostname records
+///
+/// Properties:
+///
+/// * `max_bytes`: The first bytes of each direction of a connection recorded.
+/// * `remaining`: The connections captured before the capture stops, none for every connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureSettings {
+    pub max_bytes: usize,
+    pub remaining: Option<u32>,
+}
+
+/// The hostnames whose connections are captured for debugging, started and stopped through the
+/// API
+///
+/// The captures are rare, so the connections skip the lock while none runs.
+#[derive(Debug, Default)]
+pub struct Captures {
+    active: AtomicBool,
+    hostnames: Mutex<HashMap<String, CaptureSettings>>,
+}
+
+impl Captures {
+    /// It captures the next connections to a hostname, replacing its previous capture
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    /// * `settings`: What is recorded, and for how many connections.
+    pub fn start(&self, hostname: &str, settings: CaptureSettings) {
+        let mut hostnames = self.hostnames.lock().unwrap();
+        hostnames.insert(hostname.to_lowercase(), settings);
+        self.active.store(true, Ordering::Release);
+    }
+
+    /// It stops the capture of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    ///
+    /// Returns:
+    ///
+    /// A bool, false when the hostname wasn't captured
+    pub fn stop(&self, hostname: &str) -> bool {
+        let mut hostnames = self.hostnames.lock().unwrap();
+        let stopped = hostnames.remove(&hostname.to_lowercase()).is_some();
+        self.active.store(!hostnames.is_empty(), Ordering::Release);
+        stopped
+    }
+
+    /// It tells whether a new connection to a hostname is captured, counting it against the
+    /// connections of the capture
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    ///
+    /// Returns:
+    ///
+    /// The bytes of each direction to record, none when the connection isn't captured
+    pub fn take(&self, hostname: &str) -> Option<usize> {
+        if !self.active.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let mut hostnames = self.hostnames.lock().unwrap();
+        let settings = hostnames.get_mut(hostname)?;
+        let max_bytes = settings.max_bytes;
+        match &mut settings.remaining {
+            Some(1) => {
+                hostnames.remove(hostname);
+                self.active.store(!hostnames.is_empty(), Ordering::Release);
+            }
+            Some(remaining) => *remaining -= 1,
+            None => {}
+        }
+        Some(max_bytes)
+    }
+
+    /// It returns the running captures
+    ///
+    /// Returns:
+    ///
+    /// The hostnames and their settings, sorted by hostname
+    pub fn list(&self) -> Vec<(String, CaptureSettings)> {
+        let hostnames = self.hostnames.lock().unwrap();
+        let mut captures: Vec<_> = hostnames
+            .iter()
+            .map(|(hostname, settings)| (hostname.clone(), *settings))
+            .collect();
+        captures.sort_by(|a, b| a.0.cmp(&b.0));
+        captures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_captures_the_next_connections_of_a_hostname() {
+        let captures = Captures::default();
+        assert_eq!(captures.take("lobby.example.com"), None);
+
+        let settings = CaptureSettings {
+            max_bytes: 1024,
+            remaining: Some(2),
+        };
+        captures.start("Lobby.Example.com", settings);
+        assert_eq!(captures.take("game.example.com"), None);
+        assert_eq!(captures.take("lobby.example.com"), Some(1024));
+        assert_eq!(captures.take("lobby.example.com"), Some(1024));
+        // the capture stops after its connections
+        assert_eq!(captures.take("lobby.example.com"), None);
+        assert!(captures.list().is_empty());
+
+        captures.start(
+            "lobby.example.com",
+            CaptureSettings {
+                remaining: None,
+                ..settings
+            },
+        );
+        assert_eq!(captures.take("lobby.example.com"), Some(1024));
+        assert!(captures.stop("lobby.example.com"));
+        assert!(!captures.stop("lobby.example.com"));
+        assert_eq!(captures.take("lobby.example.com"), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+/// A block of IP addresses, e.g. `10.0.0.0/8` or `fd00::/8`
+///
+/// A bare address is a block of its own, `/32` or `/128`. The IPv4 addresses mapped into IPv6,
+/// as a dual-stack socket sees the IPv4 clients, match the IPv4 blocks.
+///
+/// Properties:
+///
+/// * `network`: The first address of the block.
+/// * `prefix`: The number of leading bits the addresses of the block share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// It returns whether an address is in the block
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address.
+    ///
+    /// Returns:
+    ///
+    /// true if its first `prefix` bits are the ones of the network
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid CIDR block: {}", s);
+        let (network, prefix) = match s.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = network.trim().parse().map_err(|_| invalid())?;
+        let max = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_the_addresses_of_the_block() {
+        let pods: Cidr = "10.42.0.0/16".parse().unwrap();
+        assert!(pods.contains(&"10.42.7.1".parse().unwrap()));
+        assert!(!pods.contains(&"10.43.0.1".parse().unwrap()));
+        // an IPv4 client seen by a dual-stack socket
+        assert!(pods.contains(&"::ffff:10.42.0.9".parse().unwrap()));
+        assert!(!pods.contains(&"fd00::1".parse().unwrap()));
+
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(&"203.0.113.7".parse().unwrap()));
+
+        let host: Cidr = "fd00::1".parse().unwrap();
+        assert_eq!(host.to_string(), "fd00::1/128");
+        assert!(host.contains(&"fd00::1".parse().unwrap()));
+        assert!(!host.contains(&"fd00::2".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("pods".parse::<Cidr>().is_err());
+    }
+}
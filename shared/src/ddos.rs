@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The emergency mode of the proxy under a DDoS attack, flipped through the API or the
+/// configuration
+///
+/// It is read by every new connection, so a change applies to the next handshake. The
+/// connections already relayed are kept.
+#[derive(Debug, Default)]
+pub struct DdosMode {
+    enabled: AtomicBool,
+}
+
+impl DdosMode {
+    /// Creates a new instance of the `DdosMode` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `enabled`: Whether the proxy starts in DDoS mode.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+        }
+    }
+
+    /// It tells whether the proxy is in DDoS mode
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// It turns the DDoS mode on or off
+    ///
+    /// Arguments:
+    ///
+    /// * `enabled`: Whether the proxy is in DDoS mode.
+    ///
+    /// Returns:
+    ///
+    /// A bool, whether the mode changed
+    pub fn set(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::AcqRel) != enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_flips_the_mode() {
+        let mode = DdosMode::default();
+        assert!(!mode.enabled());
+
+        assert!(mode.set(true));
+        assert!(mode.enabled());
+        assert!(!mode.set(true));
+
+        assert!(mode.set(false));
+        assert!(!mode.enabled());
+        assert!(DdosMode::new(true).enabled());
+    }
+}
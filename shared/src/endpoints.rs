@@ -0,0 +1,103 @@
+use std::{collections::HashMap, sync::Mutex};
+
+/// The addresses the connections to a hostname are balanced across, instead of its backend
+///
+/// The Kubernetes integration fills it with the pods of the headless Services, and the proxy picks
+/// one of them for every connection, in turn.
+#[derive(Debug, Default)]
+pub struct Endpoints {
+    pools: Mutex<HashMap<String, Pool>>,
+}
+
+#[derive(Debug)]
+struct Pool {
+    addrs: Vec<String>,
+    next: usize,
+}
+
+impl Endpoints {
+    /// It replaces the addresses of a hostname, an empty list removes them
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The routed hostname.
+    /// * `addrs`: The `ip:port` addresses serving the hostname.
+    pub fn set(&self, hostname: &str, addrs: Vec<String>) {
+        let mut pools = self.pools.lock().unwrap();
+        if addrs.is_empty() {
+            pools.remove(hostname);
+            return;
+        }
+
+        match pools.get_mut(hostname) {
+            Some(pool) if pool.addrs == addrs => {}
+            Some(pool) => {
+                pool.addrs = addrs;
+                pool.next %= pool.addrs.len();
+            }
+            None => {
+                pools.insert(hostname.to_string(), Pool { addrs, next: 0 });
+            }
+        }
+    }
+
+    /// It removes the addresses of a hostname, its connections go to its backend again
+    pub fn remove(&self, hostname: &str) {
+        self.pools.lock().unwrap().remove(hostname);
+    }
+
+    /// It picks the address the next connection to a hostname goes to, in turn
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname the player connected to.
+    ///
+    /// Returns:
+    ///
+    /// The address, none when the hostname isn't balanced across endpoints
+    pub fn pick(&self, hostname: &str) -> Option<String> {
+        let mut pools = self.pools.lock().unwrap();
+        let pool = pools.get_mut(hostname)?;
+        let addr = pool.addrs[pool.next].clone();
+        pool.next = (pool.next + 1) % pool.addrs.len();
+        Some(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_balances_the_connections_across_the_endpoints() {
+        let endpoints = Endpoints::default();
+        assert_eq!(endpoints.pick("lobby.example.com"), None);
+
+        endpoints.set(
+            "lobby.example.com",
+            vec!["10.0.0.1:25565".to_string(), "10.0.0.2:25565".to_string()],
+        );
+        assert_eq!(
+            endpoints.pick("lobby.example.com").as_deref(),
+            Some("10.0.0.1:25565")
+        );
+        assert_eq!(
+            endpoints.pick("lobby.example.com").as_deref(),
+            Some("10.0.0.2:25565")
+        );
+        assert_eq!(
+            endpoints.pick("lobby.example.com").as_deref(),
+            Some("10.0.0.1:25565")
+        );
+
+        // a pod went away
+        endpoints.set("lobby.example.com", vec!["10.0.0.2:25565".to_string()]);
+        assert_eq!(
+            endpoints.pick("lobby.example.com").as_deref(),
+            Some("10.0.0.2:25565")
+        );
+
+        endpoints.set("lobby.example.com", Vec::new());
+        assert_eq!(endpoints.pick("lobby.example.com"), None);
+    }
+}
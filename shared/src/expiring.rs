@@ -0,0 +1,274 @@
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    time::Instant,
+};
+
+/// A map of the recent state of many keys, e.g. of the addresses of the clients, bounded in size
+///
+/// Every entry expires at the time it was inserted with, and the expired ones are forgotten. Once
+/// the map is full, inserting a new key forgets the least recently inserted one, so a flood of new
+/// keys keeps the memory bounded and every call is O(1) amortised.
+///
+/// Properties:
+///
+/// * `entries`: The value of every key, with its expiry and its generation.
+/// * `order`: The keys from the least to the most recently inserted, with their generation; the
+///   entries inserted again since, or removed, are skipped.
+/// * `capacity`: The most entries kept.
+/// * `generation`: The generation of the next insert.
+#[derive(Debug)]
+pub struct ExpiringMap<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    order: VecDeque<(K, u64)>,
+    capacity: usize,
+    generation: u64,
+}
+
+/// The value of a key in an `ExpiringMap`
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    expires: Instant,
+    generation: u64,
+}
+
+impl<K, V> ExpiringMap<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Creates a new instance of the `ExpiringMap` struct
+    ///
+    /// Arguments:
+    ///
+    /// * `capacity`: The most entries kept, at least one.
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+            generation: 0,
+        }
+    }
+
+    /// It returns the value of a key, unless it expired
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The key of the entry.
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// An Option<&V>
+    pub fn get<Q>(&self, key: &Q, now: Instant) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries
+            .get(key)
+            .filter(|entry| entry.expires > now)
+            .map(|entry| &entry.value)
+    }
+
+    /// It returns the value of a key to change it in place, unless it expired
+    ///
+    /// The change keeps the expiry of the entry, insert the value again to extend it.
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The key of the entry.
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// An Option<&mut V>
+    pub fn get_mut<Q>(&mut self, key: &Q, now: Instant) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries
+            .get_mut(key)
+            .filter(|entry| entry.expires > now)
+            .map(|entry| &mut entry.value)
+    }
+
+    /// It sets the value of a key until it expires, as the most recently inserted one
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The key of the entry.
+    /// * `value`: The value of the key.
+    /// * `expires`: When the entry is forgotten.
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// The previous value of the key, unless it expired
+    pub fn insert(&mut self, key: K, value: V, expires: Instant, now: Instant) -> Option<V> {
+        if !self.entries.contains_key(&key) {
+            self.make_room(now);
+        }
+
+        let generation = self.generation;
+        self.generation += 1;
+        self.order.push_back((key.clone(), generation));
+        let previous = self.entries.insert(
+            key,
+            Entry {
+                value,
+                expires,
+                generation,
+            },
+        );
+
+        // the keys inserted again leave their previous position behind, dropped once they would
+        // outnumber the entries
+        if self.order.len() > 2 * self.capacity {
+            let entries = &self.entries;
+            self.order.retain(|(key, generation)| {
+                entries
+                    .get(key)
+                    .is_some_and(|entry| entry.generation == *generation)
+            });
+        }
+
+        previous
+            .filter(|entry| entry.expires > now)
+            .map(|entry| entry.value)
+    }
+
+    /// It removes the entry of a key
+    ///
+    /// Arguments:
+    ///
+    /// * `key`: The key of the entry.
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// The value of the key, unless it expired
+    pub fn remove<Q>(&mut self, key: &Q, now: Instant) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries
+            .remove(key)
+            .filter(|entry| entry.expires > now)
+            .map(|entry| entry.value)
+    }
+
+    /// It returns the values which didn't expire, in no particular order
+    ///
+    /// Arguments:
+    ///
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// An iterator over the values
+    pub fn values(&self, now: Instant) -> impl Iterator<Item = &V> {
+        self.entries
+            .values()
+            .filter(move |entry| entry.expires > now)
+            .map(|entry| &entry.value)
+    }
+
+    /// It forgets every entry
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// It returns the number of entries, the expired ones not forgotten yet included
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// It returns whether the map has no entry
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// It forgets the least recently inserted entries which expired, then the least recently
+    /// inserted one when the map is still full
+    ///
+    /// Arguments:
+    ///
+    /// * `now`: The current time.
+    fn make_room(&mut self, now: Instant) {
+        while let Some((key, generation)) = self.order.front() {
+            let entry = match self.entries.get(key) {
+                Some(entry) if entry.generation == *generation => entry,
+                // inserted again since, or removed
+                _ => {
+                    self.order.pop_front();
+                    continue;
+                }
+            };
+            if entry.expires > now && self.entries.len() < self.capacity {
+                return;
+            }
+
+            let (key, _) = self.order.pop_front().expect("the front of the order");
+            self.entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn it_forgets_the_expired_entries() {
+        let mut map = ExpiringMap::new(16);
+        let start = Instant::now();
+        let expires = start + Duration::from_secs(60);
+
+        assert_eq!(map.insert("a", 1, expires, start), None);
+        assert_eq!(map.get("a", start + Duration::from_secs(59)), Some(&1));
+        assert_eq!(map.get("a", expires), None);
+        assert_eq!(map.get_mut("a", expires), None);
+        assert_eq!(map.insert("a", 2, expires, expires), None);
+        assert_eq!(map.insert("a", 3, expires, start), Some(2));
+        assert_eq!(map.remove("a", start), Some(3));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn it_stays_bounded_under_a_flood_of_new_keys() {
+        let mut map = ExpiringMap::new(4);
+        let start = Instant::now();
+        let expires = start + Duration::from_secs(60);
+
+        for key in 0..4 {
+            map.insert(key, key, expires, start);
+        }
+        // the key inserted again is the most recent one, the next oldest is forgotten
+        map.insert(0, 0, expires, start);
+        map.insert(4, 4, expires, start);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get(&1, start), None);
+        assert_eq!(map.get(&0, start), Some(&0));
+
+        for key in 5..100_000 {
+            map.insert(key, key, expires, start);
+            map.insert(key, key, expires, start);
+        }
+        assert_eq!(map.len(), 4);
+        assert!(map.order.len() <= 2 * map.capacity);
+        assert_eq!(map.values(start).count(), 4);
+        assert_eq!(map.values(expires).count(), 0);
+    }
+}
@@ -1 +1,18 @@
+pub mod activity;
+pub mod bans;
+pub mod capture;
+pub mod cidr;
+pub mod ddos;
+pub mod endpoints;
+pub mod expiring;
+pub mod logs;
+pub mod metadata;
 pub mod models;
+pub mod pings;
+pub mod probe;
+pub mod rate_limit;
+pub mod recent;
+pub mod sessions;
+pub mod stats;
+pub mod throttle;
+pub mod validation;
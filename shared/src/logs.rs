@@ -0,0 +1,143 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The level of a log record, the most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// It returns the name of the level, as exposed by the API
+    ///
+    /// Returns:
+    ///
+    /// `error`, `warn`, `info`, `debug` or `trace`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(format!("invalid log level: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A record logged by the proxy, streamed to the API as it is written
+///
+/// Properties:
+///
+/// * `timestamp_ms`: When it was logged, in milliseconds since the Unix epoch.
+/// * `level`: The level of the record.
+/// * `target`: The module, or the target, logging it, e.g. `proxy::access`.
+/// * `message`: The message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    /// It creates a record logged now
+    pub fn now(level: LogLevel, target: String, message: String) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        Self {
+            timestamp_ms,
+            level,
+            target,
+            message,
+        }
+    }
+}
+
+/// The records a subscriber of the logs is interested in
+///
+/// Properties:
+///
+/// * `level`: The least severe level streamed.
+/// * `target`: The prefix of the targets streamed, e.g. `proxy` for `proxy::access`, every
+///   target when none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    pub level: LogLevel,
+    pub target: Option<String>,
+}
+
+impl LogFilter {
+    /// It returns whether a record passes the filter
+    ///
+    /// Arguments:
+    ///
+    /// * `record`: The record.
+    ///
+    /// Returns:
+    ///
+    /// true if the record is as severe as the level, and its target is the one of the filter or
+    /// one of its modules
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        let target = match &self.target {
+            Some(target) => match record.target.strip_prefix(target.as_str()) {
+                Some(rest) => rest.is_empty() || rest.starts_with("::"),
+                None => false,
+            },
+            None => true,
+        };
+        target && record.level <= self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_filters_the_records_by_level_and_target() {
+        let filter = LogFilter {
+            level: "warn".parse().unwrap(),
+            target: Some("proxy".to_string()),
+        };
+        let record = |level, target: &str| LogRecord::now(level, target.to_string(), String::new());
+
+        assert!(filter.matches(&record(LogLevel::Error, "proxy")));
+        assert!(filter.matches(&record(LogLevel::Warn, "proxy::access")));
+        assert!(!filter.matches(&record(LogLevel::Info, "proxy")));
+        assert!(!filter.matches(&record(LogLevel::Warn, "proxy_protocol")));
+        assert!(!filter.matches(&record(LogLevel::Warn, "listener")));
+        assert!("verbose".parse::<LogLevel>().is_err());
+    }
+}
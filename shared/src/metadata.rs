@@ -0,0 +1,74 @@
+use std::env;
+
+/// The pod the proxy runs in, as exposed by the downward API of Kubernetes
+///
+/// The metrics and the logs are labeled with it, so the replicas can be told apart in the
+/// dashboards.
+///
+/// Properties:
+///
+/// * `pod`: The name of the pod, from `POD_NAME`.
+/// * `namespace`: The namespace of the pod, from `POD_NAMESPACE`.
+/// * `node`: The node the pod is scheduled on, from `NODE_NAME`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PodMetadata {
+    pub pod: Option<String>,
+    pub namespace: Option<String>,
+    pub node: Option<String>,
+}
+
+impl PodMetadata {
+    /// It reads the metadata from the environment variables set by the downward API
+    ///
+    /// Returns:
+    ///
+    /// A PodMetadata, with the unset or empty variables left out
+    pub fn from_env() -> Self {
+        let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            pod: var("POD_NAME"),
+            namespace: var("POD_NAMESPACE"),
+            node: var("NODE_NAME"),
+        }
+    }
+
+    /// It returns the labels describing the pod
+    ///
+    /// Returns:
+    ///
+    /// The `pod`, `namespace` and `node` labels which are known
+    pub fn labels(&self) -> Vec<(&'static str, String)> {
+        [
+            ("pod", &self.pod),
+            ("namespace", &self.namespace),
+            ("node", &self.node),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.clone().map(|value| (name, value)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_leaves_the_unknown_metadata_out_of_the_labels() {
+        let metadata = PodMetadata {
+            pod: Some("kubecraft-proxy-0".to_string()),
+            namespace: None,
+            node: Some("node-a".to_string()),
+        };
+
+        assert_eq!(
+            metadata.labels(),
+            vec![
+                ("pod", "kubecraft-proxy-0".to_string()),
+                ("node", "node-a".to_string())
+            ]
+        );
+        assert!(PodMetadata::default().labels().is_empty());
+    }
+}
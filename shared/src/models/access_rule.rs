@@ -0,0 +1,207 @@
+use std::{
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::cidr::Cidr;
+
+/// Whether an access rule bans or allows its subject
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccessKind {
+    /// The connections of the subject are dropped, or kicked for a username.
+    Ban,
+    /// The subject is never banned, neither by a rule nor for misbehaving.
+    Allow,
+}
+
+impl AccessKind {
+    /// It returns the name of the kind, as shown by the API
+    ///
+    /// Returns:
+    ///
+    /// `ban` or `allow`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ban => "ban",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+/// The clients an access rule applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessSubject {
+    /// The clients of an address, or of a block of addresses.
+    Ip(Cidr),
+    /// The players logging in with a username, whatever its case.
+    Username(String),
+}
+
+impl AccessSubject {
+    /// It tells whether the subject is a client
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    /// * `username`: The username of the login start, none until it is read.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn matches(&self, ip: &IpAddr, username: Option<&str>) -> bool {
+        match self {
+            Self::Ip(cidr) => cidr.contains(ip),
+            Self::Username(subject) => {
+                username.is_some_and(|username| subject.eq_ignore_ascii_case(username))
+            }
+        }
+    }
+}
+
+impl FromStr for AccessSubject {
+    type Err = String;
+
+    /// It parses an address or a CIDR block, or else a username of at most 16 characters
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(cidr) = s.parse::<Cidr>() {
+            return Ok(Self::Ip(cidr));
+        }
+
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        match s.len() {
+            1..=16 if s.chars().all(valid) => Ok(Self::Username(s.to_string())),
+            _ => Err(format!(
+                "{} is neither an IP address, a CIDR block nor a username",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AccessSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(cidr) => write!(f, "{}", cidr),
+            Self::Username(username) => write!(f, "{}", username),
+        }
+    }
+}
+
+/// A ban or an allow of an address or of a username, set through the API
+///
+/// Properties:
+///
+/// * `kind`: Whether the subject is banned or allowed.
+/// * `subject`: The clients the rule applies to.
+/// * `reason`: Why, shown to the banned players.
+/// * `created_at_ms`: When the rule was set, in milliseconds since the Unix epoch.
+/// * `expires_at_ms`: When the rule expires, in milliseconds since the Unix epoch, none for a
+///   rule which never expires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRule {
+    pub kind: AccessKind,
+    pub subject: AccessSubject,
+    pub reason: String,
+    pub created_at_ms: u64,
+    pub expires_at_ms: Option<u64>,
+}
+
+impl AccessRule {
+    /// It returns the key of the rule, a rule replaces the one with the same key
+    ///
+    /// Returns:
+    ///
+    /// The kind and the subject, the usernames lowercased
+    pub fn key(&self) -> String {
+        Self::key_of(self.kind, &self.subject)
+    }
+
+    /// It returns the key of the rule of a kind and a subject
+    ///
+    /// Arguments:
+    ///
+    /// * `kind`: Whether the subject is banned or allowed.
+    /// * `subject`: The clients the rule applies to.
+    ///
+    /// Returns:
+    ///
+    /// The key, see `AccessRule::key`
+    pub fn key_of(kind: AccessKind, subject: &AccessSubject) -> String {
+        match subject {
+            AccessSubject::Ip(cidr) => format!("{} ip {}", kind.as_str(), cidr),
+            AccessSubject::Username(username) => {
+                format!("{} username {}", kind.as_str(), username.to_lowercase())
+            }
+        }
+    }
+
+    /// It tells whether the rule expired
+    ///
+    /// Arguments:
+    ///
+    /// * `now_ms`: The current time, in milliseconds since the Unix epoch.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.expires_at_ms
+            .is_some_and(|expires_at_ms| expires_at_ms <= now_ms)
+    }
+}
+
+/// It returns the current time, in milliseconds since the Unix epoch, as the access rules expire
+///
+/// Returns:
+///
+/// A u64
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_subjects() {
+        let ip: IpAddr = "10.1.2.3".parse().unwrap();
+        let block: AccessSubject = "10.1.0.0/16".parse().unwrap();
+        assert!(block.matches(&ip, None));
+        assert!(!block.matches(&"10.2.0.1".parse().unwrap(), None));
+
+        let username: AccessSubject = "Notch".parse().unwrap();
+        assert_eq!(username, AccessSubject::Username("Notch".to_string()));
+        assert!(username.matches(&ip, Some("notch")));
+        assert!(!username.matches(&ip, None));
+
+        assert!("not a username".parse::<AccessSubject>().is_err());
+        assert!("a_very_long_username".parse::<AccessSubject>().is_err());
+    }
+
+    #[test]
+    fn it_keys_the_rules_by_kind_and_subject() {
+        let rule = AccessRule {
+            kind: AccessKind::Ban,
+            subject: AccessSubject::Username("Notch".to_string()),
+            reason: String::new(),
+            created_at_ms: 1_000,
+            expires_at_ms: Some(2_000),
+        };
+        assert_eq!(
+            rule.key(),
+            AccessRule::key_of(
+                AccessKind::Ban,
+                &AccessSubject::Username("NOTCH".to_string())
+            )
+        );
+        assert!(!rule.is_expired(1_999));
+        assert!(rule.is_expired(2_000));
+    }
+}
@@ -1,14 +1,50 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use super::{forwarding::ForwardingMode, health_check::HealthCheck, schedule::Schedule};
+
 /// A backend is a Minecraft server that the proxy can connect to.
 ///
 /// Properties:
 ///
 /// * `host`: The hostname of the backend server.
 /// * `port`: The port that the backend server is listening on.
-#[derive(Debug, Clone)]
+/// * `version`: The resource version of the backend, bumped by the storage on every change.
+/// * `weight`: The relative share of the connections the backend receives.
+/// * `max_connections`: The maximum number of connections to the backend, `0` for unlimited.
+/// * `forwarding_mode`: How the real address of the client is forwarded to the backend.
+/// * `motd`: The message of the day to answer status pings with instead of the backend's.
+/// * `health_check`: The settings of the active health checks, none to disable them.
+/// * `labels`: Free-form key/value pairs used to select and group backends.
+/// * `read_only`: Whether the backend is a static route of the configuration file, which
+///   can't be changed through the API.
+/// * `preserve_hostname`: Whether the handshake keeps the hostname the client connected with,
+///   instead of the redirect address, e.g. when the backend is another proxy.
+/// * `tap`: Whether the packets relayed with the backend are sent to the taps of the proxy, for
+///   debugging.
+/// * `min_protocol_version`: The oldest protocol version the backend supports, `0` for any.
+/// * `max_protocol_version`: The newest protocol version the backend supports, `0` for any.
+/// * `username_routes`: The hostnames of the backends the players with some usernames are routed
+///   to instead, e.g. the staff to a staging server.
+/// * `schedules`: The recurring maintenance windows of the backend, e.g. its nightly restart.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Backend {
     pub hostname: String,
     pub redirect_ip: String,
     pub redirect_port: u16,
+    pub version: u64,
+    pub weight: u32,
+    pub max_connections: u32,
+    pub forwarding_mode: ForwardingMode,
+    pub motd: Option<String>,
+    pub health_check: Option<HealthCheck>,
+    pub labels: BTreeMap<String, String>,
+    pub read_only: bool,
+    pub preserve_hostname: bool,
+    pub tap: bool,
+    pub min_protocol_version: u32,
+    pub max_protocol_version: u32,
+    pub username_routes: BTreeMap<String, String>,
+    pub schedules: Vec<Schedule>,
 }
 
 impl Backend {
@@ -27,6 +63,8 @@ impl Backend {
             hostname,
             redirect_ip,
             redirect_port,
+            weight: 1,
+            ..Default::default()
         }
     }
 
@@ -57,6 +95,149 @@ impl Backend {
         self.redirect_port
     }
 
+    /// It returns the resource version of the backend
+    ///
+    /// A version of `0` means the backend has never been stored.
+    ///
+    /// Returns:
+    ///
+    /// The resource version of the backend
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// It returns the weight of the backend, never less than 1
+    ///
+    /// Returns:
+    ///
+    /// The weight of the backend
+    pub fn weight(&self) -> u32 {
+        self.weight.max(1)
+    }
+
+    /// It returns the maximum number of connections to the backend
+    ///
+    /// Returns:
+    ///
+    /// The maximum number of connections, none when unlimited
+    pub fn max_connections(&self) -> Option<u32> {
+        (self.max_connections > 0).then_some(self.max_connections)
+    }
+
+    /// It returns the forwarding mode of the backend
+    ///
+    /// Returns:
+    ///
+    /// The forwarding mode of the backend
+    pub fn forwarding_mode(&self) -> ForwardingMode {
+        self.forwarding_mode
+    }
+
+    /// It returns the message of the day overriding the backend's
+    ///
+    /// Returns:
+    ///
+    /// The message of the day, if any
+    pub fn motd(&self) -> Option<&str> {
+        self.motd.as_deref()
+    }
+
+    /// It returns the settings of the active health checks
+    ///
+    /// Returns:
+    ///
+    /// The health check settings, if enabled
+    pub fn health_check(&self) -> Option<&HealthCheck> {
+        self.health_check.as_ref()
+    }
+
+    /// It returns the labels of the backend
+    ///
+    /// Returns:
+    ///
+    /// The labels of the backend
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// It returns whether the backend is a read-only static route
+    ///
+    /// Returns:
+    ///
+    /// true if the backend comes from the configuration file
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// It returns whether the handshake keeps the hostname the client connected with
+    ///
+    /// Returns:
+    ///
+    /// true if the hostname isn't rewritten to the redirect address
+    pub fn preserve_hostname(&self) -> bool {
+        self.preserve_hostname
+    }
+
+    /// It returns whether the packets relayed with the backend are tapped
+    ///
+    /// Returns:
+    ///
+    /// true if the connections to the backend are split into packets sent to the taps
+    pub fn tap(&self) -> bool {
+        self.tap
+    }
+
+    /// It returns the oldest protocol version the backend supports
+    ///
+    /// Returns:
+    ///
+    /// The protocol version, none when the older clients aren't kicked
+    pub fn min_protocol_version(&self) -> Option<i32> {
+        protocol_version(self.min_protocol_version)
+    }
+
+    /// It returns the newest protocol version the backend supports
+    ///
+    /// Returns:
+    ///
+    /// The protocol version, none when the newer clients aren't kicked
+    pub fn max_protocol_version(&self) -> Option<i32> {
+        protocol_version(self.max_protocol_version)
+    }
+
+    /// It returns the backends the players with some usernames are routed to instead
+    ///
+    /// Returns:
+    ///
+    /// The hostnames of the backends, by username
+    pub fn username_routes(&self) -> &BTreeMap<String, String> {
+        &self.username_routes
+    }
+
+    /// It returns the recurring maintenance windows of the backend
+    ///
+    /// Returns:
+    ///
+    /// The schedules of the windows
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    /// It returns the schedule of the maintenance window the backend is in
+    ///
+    /// Arguments:
+    ///
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// The first schedule with an open window, none outside of the windows
+    pub fn maintenance(&self, now: SystemTime) -> Option<&Schedule> {
+        self.schedules
+            .iter()
+            .find(|schedule| schedule.is_active(now))
+    }
+
     /// It returns the address of the backend
     ///
     /// Returns:
@@ -66,3 +247,88 @@ impl Backend {
         self.redirect_ip.clone() + ":" + &self.redirect_port.to_string()
     }
 }
+
+/// The longest hostname, in characters, as in DNS
+const MAX_HOSTNAME_LENGTH: usize = 253;
+
+/// The longest label of a hostname, in characters, as in DNS
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// It checks the syntax of the hostname of a backend, a DNS name or an IP, followed by the port
+/// of the handshakes it routes for `proxy.route_by_port`
+///
+/// The routes match the exact hostname of the handshakes, a wildcard is rejected.
+///
+/// Arguments:
+///
+/// * `hostname`: The hostname of the backend.
+///
+/// Returns:
+///
+/// A Result<(), String>, with the reason the hostname is invalid
+pub fn check_hostname(hostname: &str) -> Result<(), String> {
+    let (host, port) = match hostname.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (hostname, None),
+    };
+    if let Some(port) = port {
+        if !matches!(port.parse::<u16>(), Ok(port) if port > 0) {
+            return Err(format!("invalid port in hostname {}", hostname));
+        }
+    }
+    if host.is_empty() || host.len() > MAX_HOSTNAME_LENGTH {
+        return Err(format!(
+            "hostname must be 1 to {} characters long",
+            MAX_HOSTNAME_LENGTH
+        ));
+    }
+
+    for label in host.strip_suffix('.').unwrap_or(host).split('.') {
+        if label == "*" {
+            return Err(format!(
+                "wildcard hostname {} isn't supported, the routes match the exact hostname",
+                hostname
+            ));
+        }
+        let valid = !label.is_empty()
+            && label.len() <= MAX_LABEL_LENGTH
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(format!(
+                "invalid label {:?} in hostname {}",
+                label, hostname
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// It converts a protocol version bound of a backend into the type of the handshakes
+fn protocol_version(version: u32) -> Option<i32> {
+    (version > 0).then(|| i32::try_from(version).unwrap_or(i32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checks_the_syntax_of_the_hostnames() {
+        assert_eq!(check_hostname("lobby.example.com"), Ok(()));
+        assert_eq!(check_hostname("lobby.example.com:25566"), Ok(()));
+        assert_eq!(check_hostname("10.0.0.1"), Ok(()));
+        assert_eq!(check_hostname("localhost"), Ok(()));
+
+        assert!(check_hostname("").is_err());
+        assert!(check_hostname("lobby.example.com:0").is_err());
+        assert!(check_hostname("lobby..example.com").is_err());
+        assert!(check_hostname("-lobby.example.com").is_err());
+        assert!(check_hostname("lobby_1.example.com").is_err());
+        assert!(check_hostname(&"a".repeat(64)).is_err());
+        assert!(check_hostname("*.example.com")
+            .unwrap_err()
+            .starts_with("wildcard"));
+    }
+}
@@ -0,0 +1,51 @@
+use std::{fmt, str::FromStr};
+
+/// How the proxy forwards the real address of the client to a backend
+///
+/// Properties:
+///
+/// * `None`: The backend only sees the address of the proxy.
+/// * `Legacy`: BungeeCord style forwarding, appended to the handshake hostname.
+/// * `Velocity`: Velocity modern forwarding, negotiated with a login plugin message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ForwardingMode {
+    #[default]
+    None,
+    Legacy,
+    Velocity,
+}
+
+impl ForwardingMode {
+    /// It returns the name of the forwarding mode
+    ///
+    /// Returns:
+    ///
+    /// The name of the forwarding mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Legacy => "legacy",
+            Self::Velocity => "velocity",
+        }
+    }
+}
+
+impl FromStr for ForwardingMode {
+    type Err = String;
+
+    /// It parses a forwarding mode from its name, an empty name is `None`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "legacy" | "bungeecord" => Ok(Self::Legacy),
+            "velocity" | "modern" => Ok(Self::Velocity),
+            _ => Err(format!("unknown forwarding mode: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ForwardingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,23 @@
+/// The settings of the active health checks of a backend
+///
+/// Properties:
+///
+/// * `interval_secs`: The number of seconds between two checks.
+/// * `timeout_secs`: The number of seconds before a check is considered failed.
+/// * `unhealthy_threshold`: The number of consecutive failed checks before the backend is down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub interval_secs: u32,
+    pub timeout_secs: u32,
+    pub unhealthy_threshold: u32,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            interval_secs: 10,
+            timeout_secs: 2,
+            unhealthy_threshold: 3,
+        }
+    }
+}
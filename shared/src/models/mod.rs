@@ -1 +1,7 @@
+pub mod access_rule;
 pub mod backend;
+pub mod forwarding;
+pub mod health_check;
+pub mod schedule;
+pub mod selector;
+pub mod status_asset;
@@ -0,0 +1,348 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The longest maintenance window, in minutes: a week
+pub const MAX_SCHEDULE_MINS: u32 = 7 * 24 * 60;
+
+/// The number of minutes in a day
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+/// A recurring maintenance window of a backend, e.g. its nightly restart
+///
+/// While a window is open, the players are sent to the fallback backend, or kicked with the
+/// maintenance MOTD, which the status pings get too.
+///
+/// Properties:
+///
+/// * `cron`: When the windows open.
+/// * `duration_mins`: How long the windows last, in minutes.
+/// * `fallback`: The hostname of the backend the players are sent to meanwhile, if any.
+/// * `motd`: The MOTD, and kick reason, served meanwhile instead of the proxy's default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub cron: Cron,
+    pub duration_mins: u32,
+    pub fallback: Option<String>,
+    pub motd: Option<String>,
+}
+
+impl Schedule {
+    /// It returns whether a window of the schedule is open
+    ///
+    /// Arguments:
+    ///
+    /// * `now`: The current time.
+    ///
+    /// Returns:
+    ///
+    /// true if a window opened less than `duration_mins` ago
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        let minute = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() / 60);
+        let duration = u64::from(self.duration_mins.min(MAX_SCHEDULE_MINS));
+        if duration == 0 {
+            return false;
+        }
+        let earliest = minute.saturating_sub(duration - 1);
+        self.cron
+            .latest(minute)
+            .is_some_and(|opened| opened >= earliest)
+    }
+
+    /// It returns the hostname of the fallback backend
+    ///
+    /// Returns:
+    ///
+    /// The hostname, none when the maintenance MOTD is served
+    pub fn fallback(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
+
+    /// It returns the MOTD served while a window is open
+    ///
+    /// Returns:
+    ///
+    /// The MOTD, none for the proxy's default
+    pub fn motd(&self) -> Option<&str> {
+        self.motd.as_deref()
+    }
+}
+
+/// A cron expression, `minute hour day-of-month month day-of-week`, evaluated in UTC
+///
+/// The fields take `*`, values, ranges such as `1-5`, steps such as `*/15` and lists of them. As
+/// in cron, a day matches either of the day fields when both are restricted, and Sunday is `0`
+/// or `7`.
+///
+/// Properties:
+///
+/// * `expression`: The expression, as written.
+/// * `minutes`: The matching minutes, one bit each.
+/// * `hours`: The matching hours.
+/// * `days`: The matching days of the month.
+/// * `months`: The matching months.
+/// * `weekdays`: The matching days of the week, Sunday first.
+/// * `any_day`: Whether the day of the month is unrestricted.
+/// * `any_weekday`: Whether the day of the week is unrestricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// It returns whether a minute matches the expression
+    ///
+    /// Arguments:
+    ///
+    /// * `minute`: The number of minutes since the unix epoch.
+    ///
+    /// Returns:
+    ///
+    /// true if the windows open at that minute
+    pub fn matches(&self, minute: u64) -> bool {
+        let minute_of_day = minute % MINUTES_PER_DAY;
+        self.matches_day(minute / MINUTES_PER_DAY)
+            && self.minutes & 1 << (minute_of_day % 60) != 0
+            && self.hours & 1 << (minute_of_day / 60) != 0
+    }
+
+    /// It returns the latest minute matching the expression, at or before a minute
+    ///
+    /// The days are walked back from the minute, up to the longest maintenance window, and the
+    /// latest matching hour and minute of a matching day are read from the fields, so a handshake
+    /// doesn't check every minute of the window.
+    ///
+    /// Arguments:
+    ///
+    /// * `minute`: The number of minutes since the unix epoch.
+    ///
+    /// Returns:
+    ///
+    /// The number of minutes since the unix epoch of the latest match, none within the longest
+    /// maintenance window
+    pub fn latest(&self, minute: u64) -> Option<u64> {
+        let today = minute / MINUTES_PER_DAY;
+        let days = u64::from(MAX_SCHEDULE_MINS) / MINUTES_PER_DAY + 1;
+
+        (0..=days.min(today))
+            .find_map(|ago| {
+                let day = today - ago;
+                let until = match ago {
+                    0 => minute % MINUTES_PER_DAY,
+                    _ => MINUTES_PER_DAY - 1,
+                };
+                self.matches_day(day)
+                    .then(|| self.latest_of_day(until))
+                    .flatten()
+                    .map(|minute_of_day| day * MINUTES_PER_DAY + minute_of_day)
+            })
+            .filter(|latest| minute - latest <= u64::from(MAX_SCHEDULE_MINS))
+    }
+
+    /// It returns whether a day matches the day fields and the month field of the expression
+    ///
+    /// Arguments:
+    ///
+    /// * `days`: The number of days since the unix epoch.
+    fn matches_day(&self, days: u64) -> bool {
+        let (month, day) = month_and_day(days);
+        // the epoch was a Thursday
+        let weekday = (days + 4) % 7;
+
+        let day = match (self.any_day, self.any_weekday) {
+            (false, false) => self.days & 1 << day != 0 || self.weekdays & 1 << weekday != 0,
+            _ => self.days & 1 << day != 0 && self.weekdays & 1 << weekday != 0,
+        };
+        day && self.months & 1 << month != 0
+    }
+
+    /// It returns the latest minute of a day matching the hour and minute fields, at or before a
+    /// minute of the day
+    ///
+    /// Arguments:
+    ///
+    /// * `until`: The latest minute of the day.
+    fn latest_of_day(&self, until: u64) -> Option<u64> {
+        let (hour, minute) = (until / 60, until % 60);
+        if self.hours & 1 << hour != 0 {
+            if let Some(minute) = highest(self.minutes, minute) {
+                return Some(hour * 60 + minute);
+            }
+        }
+        let hour = highest(self.hours, hour.checked_sub(1)?)?;
+        Some(hour * 60 + highest(self.minutes, 59)?)
+    }
+}
+
+/// It returns the highest bit set at or below a position
+fn highest(bits: u64, at_most: u64) -> Option<u64> {
+    let bits = match at_most {
+        63.. => bits,
+        _ => bits & ((1 << (at_most + 1)) - 1),
+    };
+    bits.checked_ilog2().map(u64::from)
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    /// It parses the five fields of a cron expression
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("invalid cron expression, expected 5 fields: {}", s));
+        };
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+        if weekday_bits & 1 << 7 != 0 {
+            weekday_bits = weekday_bits & !(1 << 7) | 1;
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// It parses a field of a cron expression into a bit per matching value
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let invalid = || format!("invalid cron field: {}", field);
+    let value = |value: &str| value.parse::<u32>().map_err(|_| invalid());
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, value(step)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (value(start)?, value(end)?),
+            // a value with a step repeats until the end of the range, e.g. `5/15`
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+/// It converts a number of days since the unix epoch into its month and its day of the month
+fn month_and_day(days: u64) -> (u64, u64) {
+    // the years start in March, so that the leap day is the last day of the year
+    let day_of_era = (days + 719_468) % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    match month < 10 {
+        true => (month + 3, day),
+        false => (month - 9, day),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// The minute 2024 started at, a Monday
+    const NEW_YEAR: u64 = 28_401_120;
+
+    /// It returns the time of a minute of 2024, counted from its start
+    fn at(minute: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs((NEW_YEAR + minute) * 60)
+    }
+
+    #[test]
+    fn it_matches_the_fields_of_the_expression() {
+        let nightly: Cron = "30 4 * * *".parse().unwrap();
+        assert!(nightly.matches(NEW_YEAR + 4 * 60 + 30));
+        assert!(!nightly.matches(NEW_YEAR + 4 * 60 + 31));
+
+        // the weekdays at 6, or the first of the month
+        let cron: Cron = "0 6 1 * 1-5".parse().unwrap();
+        let day = 24 * 60;
+        assert!(cron.matches(NEW_YEAR + 6 * 60));
+        assert!(cron.matches(NEW_YEAR + 4 * day + 6 * 60));
+        assert!(!cron.matches(NEW_YEAR + 5 * day + 6 * 60));
+
+        // Sundays in February 2024, a leap year
+        let cron: Cron = "*/15 * * 2 7".parse().unwrap();
+        assert!(cron.matches(NEW_YEAR + 34 * day + 45));
+        assert!(!cron.matches(NEW_YEAR + 34 * day + 46));
+        assert!(!cron.matches(NEW_YEAR + 6 * day));
+        assert_eq!(cron.to_string(), "*/15 * * 2 7");
+
+        assert!("* * *".parse::<Cron>().is_err());
+        assert!("60 * * * *".parse::<Cron>().is_err());
+        assert!("*/0 * * * *".parse::<Cron>().is_err());
+        assert!("5-1 * * * *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn it_opens_the_windows_for_their_duration() {
+        let schedule = Schedule {
+            cron: "50 23 * * *".parse().unwrap(),
+            duration_mins: 20,
+            fallback: None,
+            motd: None,
+        };
+
+        assert!(!schedule.is_active(at(23 * 60 + 49)));
+        assert!(schedule.is_active(at(23 * 60 + 50)));
+        // the window runs over midnight
+        assert!(schedule.is_active(at(24 * 60 + 9)));
+        assert!(!schedule.is_active(at(24 * 60 + 10)));
+    }
+
+    #[test]
+    fn it_finds_the_latest_match_like_a_scan_of_every_minute() {
+        for expression in [
+            "50 23 * * *",
+            "*/15 6-8 1 * 1-5",
+            "0 0 29 2 *",
+            "5,55 */6 * 3 0",
+        ] {
+            let cron: Cron = expression.parse().unwrap();
+            // every 37 minutes, from the end of February over the first week of March
+            for minute in (58 * 24 * 60..68 * 24 * 60).step_by(37) {
+                let minute = NEW_YEAR + minute;
+                let scanned = (0..=u64::from(MAX_SCHEDULE_MINS))
+                    .map(|ago| minute - ago)
+                    .find(|minute| cron.matches(*minute));
+                assert_eq!(cron.latest(minute), scanned, "{} at {}", expression, minute);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// A requirement of a label selector on the labels of a backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Requirement {
+    /// `key=value` or `key==value`, the label is set to the value.
+    Equals(String, String),
+    /// `key!=value`, the label is unset or set to another value.
+    NotEquals(String, String),
+    /// `key`, the label is set.
+    Exists(String),
+    /// `!key`, the label is unset.
+    Absent(String),
+}
+
+impl Requirement {
+    /// It tells whether labels fulfill the requirement
+    ///
+    /// Arguments:
+    ///
+    /// * `labels`: The labels of a backend.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Self::Equals(key, value) => labels.get(key) == Some(value),
+            Self::NotEquals(key, value) => labels.get(key) != Some(value),
+            Self::Exists(key) => labels.contains_key(key),
+            Self::Absent(key) => !labels.contains_key(key),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equals(key, value) => write!(f, "{}={}", key, value),
+            Self::NotEquals(key, value) => write!(f, "{}!={}", key, value),
+            Self::Exists(key) => write!(f, "{}", key),
+            Self::Absent(key) => write!(f, "!{}", key),
+        }
+    }
+}
+
+/// A label selector of the backends, in the equality-based syntax of Kubernetes, e.g.
+/// `env=prod,region=eu`
+///
+/// Properties:
+///
+/// * `requirements`: The requirements a backend fulfills all of, none to select every backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+impl LabelSelector {
+    /// It tells whether the selector selects every backend
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    /// It tells whether labels fulfill every requirement of the selector
+    ///
+    /// Arguments:
+    ///
+    /// * `labels`: The labels of a backend.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| requirement.matches(labels))
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = String;
+
+    /// It parses comma-separated requirements, an empty string selects every backend
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = |key: &str| {
+            let key = key.trim();
+            let valid = |c: char| c.is_ascii_alphanumeric() || "-_./".contains(c);
+            match !key.is_empty() && key.chars().all(valid) {
+                true => Ok(key.to_string()),
+                false => Err(format!("invalid label key in the selector: {:?}", key)),
+            }
+        };
+
+        let requirements = s
+            .split(',')
+            .map(str::trim)
+            .filter(|requirement| !requirement.is_empty())
+            .map(|requirement| {
+                if let Some((k, value)) = requirement.split_once("!=") {
+                    Ok(Requirement::NotEquals(key(k)?, value.trim().to_string()))
+                } else if let Some((k, value)) = requirement
+                    .split_once("==")
+                    .or_else(|| requirement.split_once('='))
+                {
+                    Ok(Requirement::Equals(key(k)?, value.trim().to_string()))
+                } else if let Some(k) = requirement.strip_prefix('!') {
+                    Ok(Requirement::Absent(key(k)?))
+                } else {
+                    Ok(Requirement::Exists(key(requirement)?))
+                }
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self { requirements })
+    }
+}
+
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements: Vec<_> = self.requirements.iter().map(|r| r.to_string()).collect();
+        write!(f, "{}", requirements.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn it_selects_the_backends_by_their_labels() {
+        let selector: LabelSelector = "env=prod, region==eu,!canary,tier!=free,team"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            selector.to_string(),
+            "env=prod,region=eu,!canary,tier!=free,team"
+        );
+
+        let prod = labels(&[("env", "prod"), ("region", "eu"), ("team", "a")]);
+        assert!(selector.matches(&prod));
+        let mut canary = prod.clone();
+        canary.insert("canary".to_string(), "true".to_string());
+        assert!(!selector.matches(&canary));
+        let mut free = prod.clone();
+        free.insert("tier".to_string(), "free".to_string());
+        assert!(!selector.matches(&free));
+        assert!(!selector.matches(&labels(&[("env", "prod"), ("region", "us")])));
+
+        let every: LabelSelector = "".parse().unwrap();
+        assert!(every.is_empty());
+        assert!(every.matches(&BTreeMap::new()));
+    }
+
+    #[test]
+    fn it_rejects_the_invalid_selectors() {
+        assert!("=prod".parse::<LabelSelector>().is_err());
+        assert!("env in (prod)".parse::<LabelSelector>().is_err());
+        assert!("!".parse::<LabelSelector>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+/// The prefix of the favicons, a PNG inlined as a data URI
+pub const FAVICON_PREFIX: &str = "data:image/png;base64,";
+
+/// The longest favicon, in bytes, the clients ignore a status longer than 32 KiB
+pub const MAX_FAVICON_BYTES: usize = 24 * 1024;
+
+/// The longest MOTD template, in bytes
+pub const MAX_MOTD_BYTES: usize = 1024;
+
+/// The MOTD and the favicon of a hostname, uploaded through the API and shown whenever the
+/// proxy answers a status ping itself
+///
+/// Properties:
+///
+/// * `hostname`: The hostname of the backend the asset belongs to.
+/// * `motd`: The MOTD template, `{message}` is replaced with the message of the proxy, e.g. the
+///   `maintenance` one, and `{hostname}` with the hostname. None to keep the message as is.
+/// * `favicon`: The favicon shown next to the MOTD, a 64x64 PNG as a `data:image/png;base64,`
+///   URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusAsset {
+    pub hostname: String,
+    pub motd: Option<String>,
+    pub favicon: Option<String>,
+}
+
+impl StatusAsset {
+    /// It checks the asset can be shown in the server list
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), String>, the error tells which field is invalid
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hostname.is_empty() {
+            return Err("the hostname of a status asset must be set".to_string());
+        }
+        if self.motd.is_none() && self.favicon.is_none() {
+            return Err(format!(
+                "the status asset of {} has neither a MOTD nor a favicon",
+                self.hostname
+            ));
+        }
+        if let Some(motd) = &self.motd {
+            if motd.len() > MAX_MOTD_BYTES {
+                return Err(format!(
+                    "the MOTD of {} exceeds {} bytes",
+                    self.hostname, MAX_MOTD_BYTES
+                ));
+            }
+        }
+        if let Some(favicon) = &self.favicon {
+            if !favicon.starts_with(FAVICON_PREFIX) {
+                return Err(format!(
+                    "the favicon of {} must be a {} URI",
+                    self.hostname, FAVICON_PREFIX
+                ));
+            }
+            if favicon.len() > MAX_FAVICON_BYTES {
+                return Err(format!(
+                    "the favicon of {} exceeds {} bytes",
+                    self.hostname, MAX_FAVICON_BYTES
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It returns the MOTD of a status the proxy answers itself
+    ///
+    /// Arguments:
+    ///
+    /// * `message`: The message the proxy would show without the asset.
+    ///
+    /// Returns:
+    ///
+    /// The template with its placeholders replaced, the message without a template
+    pub fn motd(&self, message: &str) -> String {
+        match &self.motd {
+            Some(template) => template
+                .replace("{message}", message)
+                .replace("{hostname}", &self.hostname),
+            None => message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(motd: Option<&str>, favicon: Option<&str>) -> StatusAsset {
+        StatusAsset {
+            hostname: "lobby.example.com".to_string(),
+            motd: motd.map(str::to_string),
+            favicon: favicon.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn it_fills_the_motd_template() {
+        let template = asset(Some("§6{hostname}§r\n{message}"), None);
+        assert_eq!(
+            template.motd("The server is sleeping"),
+            "§6lobby.example.com§r\nThe server is sleeping"
+        );
+        assert_eq!(
+            asset(None, Some("data:image/png;base64,iVBORw0KGgo=")).motd("Sleeping"),
+            "Sleeping"
+        );
+    }
+
+    #[test]
+    fn it_validates_the_assets() {
+        assert!(asset(
+            Some("{message}"),
+            Some("data:image/png;base64,iVBORw0KGgo=")
+        )
+        .validate()
+        .is_ok());
+        assert!(asset(None, None).validate().is_err());
+        assert!(asset(None, Some("favicon.png")).validate().is_err());
+        let favicon = format!("{}{}", FAVICON_PREFIX, "A".repeat(MAX_FAVICON_BYTES));
+        assert!(asset(None, Some(&favicon)).validate().is_err());
+        assert!(asset(Some(&"a".repeat(MAX_MOTD_BYTES + 1)), None)
+            .validate()
+            .is_err());
+    }
+}
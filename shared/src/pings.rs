@@ -0,0 +1,83 @@
+use std::{
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::expiring::ExpiringMap;
+
+/// The most addresses tracked, the least recently pinging ones are forgotten past it
+const MAX_TRACKED: usize = 65_536;
+
+/// The last status ping of the clients, for the gate admitting the logins
+///
+/// A real client pings the server list before the player joins, a join bot usually logs in
+/// straight away, so a login without a recent ping from its address is likely a bot.
+#[derive(Debug)]
+pub struct Pings {
+    last: Mutex<ExpiringMap<IpAddr, Instant>>,
+}
+
+impl Default for Pings {
+    fn default() -> Self {
+        Self {
+            last: Mutex::new(ExpiringMap::new(MAX_TRACKED)),
+        }
+    }
+}
+
+impl Pings {
+    /// It records the status ping of a client
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    /// * `window`: How long a ping admits the logins, the ping is forgotten after it.
+    pub fn ping(&self, ip: IpAddr, window: Duration) {
+        self.ping_at(ip, window, Instant::now())
+    }
+
+    fn ping_at(&self, ip: IpAddr, window: Duration, now: Instant) {
+        let mut last = self.last.lock().unwrap();
+        last.insert(ip, now, now + window, now);
+    }
+
+    /// It tells whether a client pinged the server list within the window
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    /// * `window`: How long a ping admits the logins.
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn pinged(&self, ip: IpAddr, window: Duration) -> bool {
+        self.pinged_at(ip, window, Instant::now())
+    }
+
+    fn pinged_at(&self, ip: IpAddr, window: Duration, now: Instant) -> bool {
+        let last = self.last.lock().unwrap();
+        last.get(&ip, now)
+            .is_some_and(|pinged| now.duration_since(*pinged) < window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_admits_the_clients_which_pinged_within_the_window() {
+        let pings = Pings::default();
+        let window = Duration::from_secs(300);
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(!pings.pinged_at(ip, window, start));
+        pings.ping_at(ip, window, start);
+        assert!(pings.pinged_at(ip, window, start + Duration::from_secs(299)));
+        assert!(!pings.pinged_at("203.0.113.8".parse().unwrap(), window, start));
+        assert!(!pings.pinged_at(ip, window, start + Duration::from_secs(300)));
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+/// The result of a status ping of a backend, run on demand to check its route
+///
+/// Properties:
+///
+/// * `target`: The address probed, the stored target of the backend.
+/// * `connect`: How long the TCP connection took to open, none when it failed.
+/// * `latency`: The round trip of the ping, once the status was answered.
+/// * `status`: The JSON of the status answered by the backend.
+/// * `error`: Why the probe failed, none when the backend answered the ping.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Probe {
+    pub target: String,
+    pub connect: Option<Duration>,
+    pub latency: Option<Duration>,
+    pub status: Option<String>,
+    pub error: Option<String>,
+}
+
+impl Probe {
+    /// It returns whether the backend answered the status ping
+    ///
+    /// Returns:
+    ///
+    /// true if the probe didn't fail
+    pub fn reachable(&self) -> bool {
+        self.error.is_none()
+    }
+}
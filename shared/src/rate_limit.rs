@@ -0,0 +1,111 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::expiring::ExpiringMap;
+
+/// The most backends tracked, the least recently joined ones are forgotten past it
+const MAX_TRACKED: usize = 4096;
+
+/// The token bucket of a backend
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// The rate of the new connections to a backend, and how many can be opened at once
+///
+/// Properties:
+///
+/// * `per_sec`: The connections refilled every second.
+/// * `burst`: The connections opened at once, when the bucket is full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub per_sec: f64,
+    pub burst: f64,
+}
+
+/// The token buckets limiting the new connections to each backend
+///
+/// A popular server coming back online is joined by every player waiting for it at once, the
+/// buckets spread these connections over a few seconds instead.
+#[derive(Debug)]
+pub struct ConnectRateLimits {
+    buckets: Mutex<ExpiringMap<String, Bucket>>,
+}
+
+impl Default for ConnectRateLimits {
+    fn default() -> Self {
+        Self {
+            buckets: Mutex::new(ExpiringMap::new(MAX_TRACKED)),
+        }
+    }
+}
+
+impl ConnectRateLimits {
+    /// It takes a token from the bucket of a backend, when there is one left
+    ///
+    /// Arguments:
+    ///
+    /// * `backend`: The hostname of the backend.
+    /// * `rate`: The rate and the burst of the connections to a backend.
+    ///
+    /// Returns:
+    ///
+    /// A bool, false when the connection exceeds the rate
+    pub fn acquire(&self, backend: &str, rate: &Rate) -> bool {
+        self.acquire_at(backend, rate, Instant::now())
+    }
+
+    fn acquire_at(&self, backend: &str, rate: &Rate, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap();
+        // a bucket is forgotten once full again, a new one starts full
+        let mut bucket = buckets.remove(backend, now).unwrap_or(Bucket {
+            tokens: rate.burst,
+            refilled: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate.per_sec).min(rate.burst);
+        bucket.refilled = now;
+
+        let acquired = bucket.tokens >= 1.0;
+        if acquired {
+            bucket.tokens -= 1.0;
+        }
+        let full = Duration::try_from_secs_f64((rate.burst - bucket.tokens) / rate.per_sec)
+            .unwrap_or_default();
+        buckets.insert(backend.to_string(), bucket, now + full, now);
+
+        acquired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn it_limits_the_connections_to_a_backend() {
+        let limits = ConnectRateLimits::default();
+        let rate = Rate {
+            per_sec: 2.0,
+            burst: 3.0,
+        };
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limits.acquire_at("lobby.example.com", &rate, start));
+        }
+        assert!(!limits.acquire_at("lobby.example.com", &rate, start));
+        assert!(limits.acquire_at("survival.example.com", &rate, start));
+
+        // half a second refills one connection
+        let later = start + Duration::from_millis(500);
+        assert!(limits.acquire_at("lobby.example.com", &rate, later));
+        assert!(!limits.acquire_at("lobby.example.com", &rate, later));
+    }
+}
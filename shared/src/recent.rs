@@ -0,0 +1,180 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How many events are kept by default
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// The kind of a notable event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecentEventKind {
+    /// The client was kicked, e.g. because its backend is scaled down.
+    Kick,
+    /// No backend matches the hostname requested by the client.
+    RoutingMiss,
+    /// The backend couldn't be connected to, or refused the handshake.
+    BackendFailure,
+    /// The address of the client was banned for misbehaving.
+    Ban,
+}
+
+impl RecentEventKind {
+    /// It returns the name of the kind, as exposed by the API
+    ///
+    /// Returns:
+    ///
+    /// `kick`, `routing_miss`, `backend_failure` or `ban`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Kick => "kick",
+            Self::RoutingMiss => "routing_miss",
+            Self::BackendFailure => "backend_failure",
+            Self::Ban => "ban",
+        }
+    }
+}
+
+/// A notable event of a connection
+///
+/// Properties:
+///
+/// * `timestamp_ms`: When it happened, in milliseconds since the Unix epoch.
+/// * `kind`: What happened.
+/// * `connection_id`: The ID of the connection.
+/// * `client`: The address of the client.
+/// * `hostname`: The hostname requested by the client.
+/// * `backend`: The address of the backend, when one was chosen.
+/// * `message`: What happened, in words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentEvent {
+    pub timestamp_ms: u64,
+    pub kind: RecentEventKind,
+    pub connection_id: String,
+    pub client: String,
+    pub hostname: String,
+    pub backend: Option<String>,
+    pub message: String,
+}
+
+impl RecentEvent {
+    /// It creates an event which happened now
+    pub fn now(
+        kind: RecentEventKind,
+        connection_id: String,
+        client: String,
+        hostname: String,
+        backend: Option<String>,
+        message: String,
+    ) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        Self {
+            timestamp_ms,
+            kind,
+            connection_id,
+            client,
+            hostname,
+            backend,
+            message,
+        }
+    }
+}
+
+/// The latest notable events of the connections, kept in memory
+///
+/// Once full, every new event pushes the oldest one out, so the operators get an instant picture
+/// of what went wrong lately without access to the logs.
+#[derive(Debug)]
+pub struct RecentEvents {
+    capacity: usize,
+    events: Mutex<VecDeque<RecentEvent>>,
+}
+
+impl Default for RecentEvents {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RecentEvents {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// It records an event, dropping the oldest one when the buffer is full
+    ///
+    /// Arguments:
+    ///
+    /// * `event`: The event which happened.
+    pub fn push(&self, event: RecentEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// It returns the latest events, the oldest first
+    ///
+    /// Arguments:
+    ///
+    /// * `limit`: How many events to return at most, none for all of them.
+    ///
+    /// Returns:
+    ///
+    /// A Vec<RecentEvent>
+    pub fn latest(&self, limit: Option<usize>) -> Vec<RecentEvent> {
+        let events = self.events.lock().unwrap();
+        let skipped = limit.map_or(0, |limit| events.len().saturating_sub(limit));
+
+        events.iter().skip(skipped).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(hostname: &str) -> RecentEvent {
+        RecentEvent::now(
+            RecentEventKind::RoutingMiss,
+            "01HX5Z3Q8K2M7RZ4T9V6C1B0NA".to_string(),
+            "10.0.0.7:51712".to_string(),
+            hostname.to_string(),
+            None,
+            "no backend matches the hostname".to_string(),
+        )
+    }
+
+    #[test]
+    fn it_keeps_the_latest_events() {
+        let recent = RecentEvents::new(2);
+        for hostname in ["a.example.com", "b.example.com", "c.example.com"] {
+            recent.push(event(hostname));
+        }
+
+        let hostnames = |events: Vec<RecentEvent>| {
+            events
+                .into_iter()
+                .map(|event| event.hostname)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            hostnames(recent.latest(None)),
+            vec!["b.example.com", "c.example.com"]
+        );
+        assert_eq!(hostnames(recent.latest(Some(1))), vec!["c.example.com"]);
+    }
+}
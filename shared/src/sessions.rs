@@ -0,0 +1,179 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// The players relayed to every backend, by the version of the backend they joined
+///
+/// An update of a backend only changes where its new players go, the players relayed before
+/// keep the backend they joined until they leave. The sessions of the older versions tell how
+/// many of them an update left on the previous backend.
+#[derive(Debug, Default)]
+pub struct Sessions {
+    inner: Mutex<BTreeMap<(String, u64), Relayed>>,
+}
+
+#[derive(Debug)]
+struct Relayed {
+    backend: String,
+    active: usize,
+}
+
+/// The players relayed to a version of a backend
+///
+/// Properties:
+///
+/// * `hostname`: The hostname of the backend.
+/// * `version`: The version of the backend the players joined.
+/// * `backend`: The address of the backend at that version.
+/// * `active`: The number of players still relayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSessions {
+    pub hostname: String,
+    pub version: u64,
+    pub backend: String,
+    pub active: usize,
+}
+
+/// A request moving the players of a backend to another server, e.g. before the backend shuts
+/// down, with the transfer packet of the 1.20.5 protocol
+///
+/// Properties:
+///
+/// * `backend`: The hostname of the backend whose players move.
+/// * `hostname`: The hostname of the server the players join.
+/// * `port`: The port of the server the players join.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub backend: String,
+    pub hostname: String,
+    pub port: u16,
+}
+
+impl Sessions {
+    /// It records a player relayed to a version of a backend, until the returned guard is dropped
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    /// * `version`: The version of the backend when the player joined.
+    /// * `backend`: The address of the backend at that version.
+    ///
+    /// Returns:
+    ///
+    /// A Session
+    pub fn open(self: &Arc<Self>, hostname: &str, version: u64, backend: &str) -> Session {
+        let key = (hostname.to_string(), version);
+        self.inner
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Relayed {
+                backend: backend.to_string(),
+                active: 0,
+            })
+            .active += 1;
+
+        Session {
+            sessions: self.clone(),
+            key,
+        }
+    }
+
+    /// It returns the players relayed to every version of the backends
+    ///
+    /// Returns:
+    ///
+    /// The sessions of the versions with players, by hostname then version
+    pub fn list(&self) -> Vec<VersionSessions> {
+        self.inner
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((hostname, version), relayed)| VersionSessions {
+                hostname: hostname.clone(),
+                version: *version,
+                backend: relayed.backend.clone(),
+                active: relayed.active,
+            })
+            .collect()
+    }
+
+    /// It returns the number of players relayed to a backend, whatever the version they joined
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    ///
+    /// Returns:
+    ///
+    /// The number of players
+    pub fn players(&self, hostname: &str) -> usize {
+        self.inner
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((relayed, _), _)| relayed == hostname)
+            .map(|(_, relayed)| relayed.active)
+            .sum()
+    }
+}
+
+/// A player relayed to a version of a backend, released when dropped
+#[derive(Debug)]
+pub struct Session {
+    sessions: Arc<Sessions>,
+    key: (String, u64),
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let mut inner = self.sessions.inner.lock().unwrap();
+        if let Some(relayed) = inner.get_mut(&self.key) {
+            relayed.active -= 1;
+            if relayed.active == 0 {
+                inner.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_the_sessions_of_every_version() {
+        let sessions = Arc::new(Sessions::default());
+        let legacy = sessions.open("lobby.example.com", 1, "10.0.0.1:25565");
+        let current = sessions.open("lobby.example.com", 2, "10.0.0.2:25565");
+        let other = sessions.open("lobby.example.com", 2, "10.0.0.2:25565");
+
+        assert_eq!(
+            sessions.list(),
+            vec![
+                VersionSessions {
+                    hostname: "lobby.example.com".to_string(),
+                    version: 1,
+                    backend: "10.0.0.1:25565".to_string(),
+                    active: 1,
+                },
+                VersionSessions {
+                    hostname: "lobby.example.com".to_string(),
+                    version: 2,
+                    backend: "10.0.0.2:25565".to_string(),
+                    active: 2,
+                },
+            ]
+        );
+
+        assert_eq!(sessions.players("lobby.example.com"), 3);
+        assert_eq!(sessions.players("survival.example.com"), 0);
+
+        drop(legacy);
+        drop(current);
+        assert_eq!(sessions.list()[0].active, 1);
+        drop(other);
+        assert!(sessions.list().is_empty());
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::models::health_check::HealthCheck;
+
+/// The weight of the latest connect in the moving averages of the health of a backend
+const SMOOTHING: f64 = 0.2;
+
+/// The average time to connect to a backend above which its score drops, in milliseconds
+const SLOW_CONNECT_MS: f64 = 250.0;
+
+/// The score under which a backend is degraded
+const DEGRADED_SCORE: f64 = 0.8;
+
+/// The counters of every backend, which the relays add to as they go, for the statistics streamed
+/// to the dashboards and the autoscalers
+///
+/// The cumulative totals carry on from the ones restored from the storage, see `Stats::restore`,
+/// so the usage reports aren't reset when the proxy restarts.
+#[derive(Debug, Default)]
+pub struct Stats {
+    inner: Mutex<BTreeMap<String, Arc<BackendCounters>>>,
+}
+
+/// The counters of a backend, since the proxy started
+///
+/// Properties:
+///
+/// * `joins`: The players who joined the backend.
+/// * `bytes_in`: The bytes relayed from the clients to the backend.
+/// * `bytes_out`: The bytes relayed from the backend to the clients.
+/// * `connects`: The outcomes of the connections opened to the backend.
+/// * `baseline`: The totals restored from the storage, and the counters when they were.
+#[derive(Debug, Default)]
+pub struct BackendCounters {
+    joins: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    connects: Mutex<Connects>,
+    baseline: Mutex<Baseline>,
+}
+
+/// The totals a backend carries on from
+///
+/// Properties:
+///
+/// * `restored`: The cumulative totals restored from the storage.
+/// * `counted`: The values of the counters when they were restored.
+#[derive(Debug, Default)]
+struct Baseline {
+    restored: Totals,
+    counted: Totals,
+}
+
+/// The outcomes of the connections opened to a backend, its passive health checks
+///
+/// Properties:
+///
+/// * `availability`: The moving average of the connects which succeeded, from 0 to 1.
+/// * `connect_ms`: The moving average of the time to connect, in milliseconds.
+/// * `consecutive_failures`: The connects which failed since the last one which succeeded.
+/// * `unhealthy_threshold`: The consecutive failures after which the backend is down.
+#[derive(Debug)]
+struct Connects {
+    availability: f64,
+    connect_ms: f64,
+    consecutive_failures: u32,
+    unhealthy_threshold: u32,
+}
+
+impl Default for Connects {
+    fn default() -> Self {
+        Self {
+            availability: 1.0,
+            connect_ms: 0.0,
+            consecutive_failures: 0,
+            unhealthy_threshold: HealthCheck::default().unhealthy_threshold,
+        }
+    }
+}
+
+/// The state of a backend, from its health score
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HealthState {
+    /// The connects succeed, quickly enough. A backend without connects yet is healthy.
+    #[default]
+    Healthy,
+    /// Some connects fail, or they are slow.
+    Degraded,
+    /// The latest connects all failed, `unhealthy_threshold` of them at least.
+    Down,
+}
+
+impl HealthState {
+    /// It returns the name of the state, as exposed by the API
+    ///
+    /// Returns:
+    ///
+    /// `healthy`, `degraded` or `down`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Healthy => "healthy",
+            Self::Degraded => "degraded",
+            Self::Down => "down",
+        }
+    }
+}
+
+/// The health of a backend, from the outcomes of the connections opened to it
+///
+/// Properties:
+///
+/// * `state`: The state, from the score and the consecutive failures.
+/// * `score`: From 0, down, to 1: the share of the recent connects which succeeded, lowered when
+///   they take over 250 ms on average.
+/// * `consecutive_failures`: The connects which failed since the last one which succeeded.
+/// * `connect_ms`: The average time to connect to the backend, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub state: HealthState,
+    pub score: f64,
+    pub consecutive_failures: u32,
+    pub connect_ms: f64,
+}
+
+impl BackendCounters {
+    /// It counts a player joining the backend
+    pub fn join(&self) {
+        self.joins.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// It records a connection opened to the backend
+    ///
+    /// Arguments:
+    ///
+    /// * `latency`: The time to connect.
+    /// * `unhealthy_threshold`: The consecutive failures after which the backend is down, from
+    ///   its health check settings.
+    pub fn connected(&self, latency: Duration, unhealthy_threshold: u32) {
+        let mut connects = self.connects.lock().unwrap();
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        connects.availability += SMOOTHING * (1.0 - connects.availability);
+        connects.connect_ms = match connects.connect_ms {
+            0.0 => latency_ms,
+            average => average + SMOOTHING * (latency_ms - average),
+        };
+        connects.consecutive_failures = 0;
+        connects.unhealthy_threshold = unhealthy_threshold;
+    }
+
+    /// It records a connection to the backend which couldn't be opened
+    ///
+    /// Arguments:
+    ///
+    /// * `unhealthy_threshold`: The consecutive failures after which the backend is down, from
+    ///   its health check settings.
+    pub fn failed(&self, unhealthy_threshold: u32) {
+        let mut connects = self.connects.lock().unwrap();
+        connects.availability -= SMOOTHING * connects.availability;
+        connects.consecutive_failures = connects.consecutive_failures.saturating_add(1);
+        connects.unhealthy_threshold = unhealthy_threshold;
+    }
+
+    /// It returns the health of the backend
+    ///
+    /// Returns:
+    ///
+    /// A Health, healthy until a connect fails or is slow
+    pub fn health(&self) -> Health {
+        let connects = self.connects.lock().unwrap();
+        let speed = match connects.connect_ms > SLOW_CONNECT_MS {
+            true => SLOW_CONNECT_MS / connects.connect_ms,
+            false => 1.0,
+        };
+        let down = connects.consecutive_failures >= connects.unhealthy_threshold.max(1);
+        let score = match down {
+            true => 0.0,
+            false => connects.availability * speed,
+        };
+        let state = match score {
+            _ if down => HealthState::Down,
+            score if score < DEGRADED_SCORE => HealthState::Degraded,
+            _ => HealthState::Healthy,
+        };
+
+        Health {
+            state,
+            score,
+            consecutive_failures: connects.consecutive_failures,
+            connect_ms: connects.connect_ms,
+        }
+    }
+
+    /// It returns the counter of the bytes relayed from the clients to the backend
+    ///
+    /// Returns:
+    ///
+    /// A &AtomicU64
+    pub fn bytes_in(&self) -> &AtomicU64 {
+        &self.bytes_in
+    }
+
+    /// It returns the counter of the bytes relayed from the backend to the clients
+    ///
+    /// Returns:
+    ///
+    /// A &AtomicU64
+    pub fn bytes_out(&self) -> &AtomicU64 {
+        &self.bytes_out
+    }
+
+    /// It returns the values of the counters, since the proxy started
+    ///
+    /// Returns:
+    ///
+    /// The Totals
+    fn counted(&self) -> Totals {
+        Totals {
+            joins: self.joins.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+        }
+    }
+
+    /// It returns the totals of the backend, the restored ones included
+    ///
+    /// Returns:
+    ///
+    /// The restored totals plus what was counted since they were restored
+    fn cumulative(&self) -> Totals {
+        let baseline = self.baseline.lock().unwrap();
+        let counted = self.counted();
+        let carry_on = |restored: u64, now: u64, then: u64| restored + now.saturating_sub(then);
+
+        Totals {
+            joins: carry_on(
+                baseline.restored.joins,
+                counted.joins,
+                baseline.counted.joins,
+            ),
+            bytes_in: carry_on(
+                baseline.restored.bytes_in,
+                counted.bytes_in,
+                baseline.counted.bytes_in,
+            ),
+            bytes_out: carry_on(
+                baseline.restored.bytes_out,
+                counted.bytes_out,
+                baseline.counted.bytes_out,
+            ),
+        }
+    }
+
+    /// It makes the cumulative totals of the backend carry on from restored ones
+    ///
+    /// Arguments:
+    ///
+    /// * `restored`: The cumulative totals, zeros to start over.
+    fn restore(&self, restored: Totals) {
+        *self.baseline.lock().unwrap() = Baseline {
+            restored,
+            counted: self.counted(),
+        };
+    }
+}
+
+/// The values of the counters of a backend at some point
+///
+/// Properties:
+///
+/// * `joins`: The players who joined the backend.
+/// * `bytes_in`: The bytes relayed from the clients to the backend.
+/// * `bytes_out`: The bytes relayed from the backend to the clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub joins: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// The activity of a backend between two totals
+///
+/// Properties:
+///
+/// * `joins_per_min`: The players joining the backend, per minute.
+/// * `bytes_in_per_sec`: The bytes relayed from the clients to the backend, per second.
+/// * `bytes_out_per_sec`: The bytes relayed from the backend to the clients, per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rates {
+    pub joins_per_min: f64,
+    pub bytes_in_per_sec: f64,
+    pub bytes_out_per_sec: f64,
+}
+
+impl Totals {
+    /// It returns the activity of the backend since previous totals
+    ///
+    /// Arguments:
+    ///
+    /// * `previous`: The totals taken earlier, zeros for a backend without any.
+    /// * `elapsed`: The time between both totals.
+    ///
+    /// Returns:
+    ///
+    /// The rates, zeros when no time elapsed
+    pub fn rates_since(&self, previous: &Totals, elapsed: Duration) -> Rates {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return Rates::default();
+        }
+        let per_sec = |now: u64, before: u64| now.saturating_sub(before) as f64 / secs;
+
+        Rates {
+            joins_per_min: per_sec(self.joins, previous.joins) * 60.0,
+            bytes_in_per_sec: per_sec(self.bytes_in, previous.bytes_in),
+            bytes_out_per_sec: per_sec(self.bytes_out, previous.bytes_out),
+        }
+    }
+}
+
+impl Stats {
+    /// It returns the counters of a backend, created on its first connection
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    ///
+    /// Returns:
+    ///
+    /// An Arc<BackendCounters>
+    pub fn counters(&self, hostname: &str) -> Arc<BackendCounters> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entry(hostname.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// It returns the health of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname`: The hostname of the backend.
+    ///
+    /// Returns:
+    ///
+    /// The health, healthy for a backend without connections yet
+    pub fn health(&self, hostname: &str) -> Health {
+        self.inner.lock().unwrap().get(hostname).map_or_else(
+            || BackendCounters::default().health(),
+            |counters| counters.health(),
+        )
+    }
+
+    /// It returns the values of the counters of every backend, since the proxy started
+    ///
+    /// Returns:
+    ///
+    /// The totals, by hostname
+    pub fn totals(&self) -> BTreeMap<String, Totals> {
+        self.inner
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(hostname, counters)| (hostname.clone(), counters.counted()))
+            .collect()
+    }
+
+    /// It returns the cumulative totals of every backend, the restored ones included, as
+    /// checkpointed into the storage
+    ///
+    /// Returns:
+    ///
+    /// The totals, by hostname
+    pub fn cumulative_totals(&self) -> BTreeMap<String, Totals> {
+        self.inner
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(hostname, counters)| (hostname.clone(), counters.cumulative()))
+            .collect()
+    }
+
+    /// It replaces the cumulative totals of every backend with the ones restored from the
+    /// storage, the counting carries on from them
+    ///
+    /// The backends without restored totals start over from zero, the rates aren't affected.
+    ///
+    /// Arguments:
+    ///
+    /// * `restored`: The cumulative totals, by hostname.
+    pub fn restore(&self, restored: &BTreeMap<String, Totals>) {
+        let mut inner = self.inner.lock().unwrap();
+        for (hostname, totals) in restored {
+            inner.entry(hostname.clone()).or_default().restore(*totals);
+        }
+        for (hostname, counters) in inner.iter() {
+            if !restored.contains_key(hostname) {
+                counters.restore(Totals::default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_turns_the_totals_into_rates() {
+        let stats = Stats::default();
+        let lobby = stats.counters("lobby.example.com");
+        let previous = stats.totals();
+
+        lobby.join();
+        lobby.join();
+        lobby.bytes_in().fetch_add(1000, Ordering::Relaxed);
+        lobby.bytes_out().fetch_add(8000, Ordering::Relaxed);
+        let totals = stats.totals();
+
+        assert_eq!(
+            totals["lobby.example.com"]
+                .rates_since(&previous["lobby.example.com"], Duration::from_secs(2)),
+            Rates {
+                joins_per_min: 60.0,
+                bytes_in_per_sec: 500.0,
+                bytes_out_per_sec: 4000.0,
+            }
+        );
+        assert_eq!(
+            totals["lobby.example.com"].rates_since(&Totals::default(), Duration::ZERO),
+            Rates::default()
+        );
+    }
+
+    #[test]
+    fn it_carries_on_from_the_restored_totals() {
+        let stats = Stats::default();
+        let lobby = stats.counters("lobby.example.com");
+        lobby.join();
+        lobby.bytes_in().fetch_add(100, Ordering::Relaxed);
+
+        let restored = Totals {
+            joins: 10,
+            bytes_in: 1000,
+            bytes_out: 5000,
+        };
+        stats.restore(&BTreeMap::from([
+            ("lobby.example.com".to_string(), restored),
+            ("survival.example.com".to_string(), restored),
+        ]));
+        lobby.join();
+        lobby.bytes_out().fetch_add(20, Ordering::Relaxed);
+
+        let cumulative = stats.cumulative_totals();
+        assert_eq!(
+            cumulative["lobby.example.com"],
+            Totals {
+                joins: 11,
+                bytes_in: 1000,
+                bytes_out: 5020,
+            }
+        );
+        assert_eq!(cumulative["survival.example.com"], restored);
+        // the rates only see what was counted
+        assert_eq!(stats.totals()["lobby.example.com"].joins, 2);
+
+        // restoring again replaces the totals rather than adding to them
+        stats.restore(&BTreeMap::new());
+        lobby.join();
+        assert_eq!(stats.cumulative_totals()["lobby.example.com"].joins, 1);
+        assert_eq!(
+            stats.cumulative_totals()["survival.example.com"],
+            Totals::default()
+        );
+    }
+
+    #[test]
+    fn it_scores_the_health_of_the_backends() {
+        let stats = Stats::default();
+        let lobby = stats.counters("lobby.example.com");
+        assert_eq!(
+            stats.health("lobby.example.com").state,
+            HealthState::Healthy
+        );
+        assert_eq!(stats.health("survival.example.com").score, 1.0);
+
+        // a failure lowers the score, the slow connects too
+        lobby.connected(Duration::from_millis(20), 3);
+        lobby.failed(3);
+        lobby.failed(3);
+        let health = stats.health("lobby.example.com");
+        assert_eq!(health.state, HealthState::Degraded);
+        assert!((health.score - 0.64).abs() < 1e-9);
+        assert_eq!(health.consecutive_failures, 2);
+
+        lobby.failed(3);
+        let health = stats.health("lobby.example.com");
+        assert_eq!(health.state, HealthState::Down);
+        assert_eq!(health.score, 0.0);
+
+        let slow = stats.counters("slow.example.com");
+        slow.connected(Duration::from_millis(1000), 3);
+        let health = stats.health("slow.example.com");
+        assert_eq!(health.state, HealthState::Degraded);
+        assert_eq!(health.score, 0.25);
+        assert_eq!(health.connect_ms, 1000.0);
+    }
+}
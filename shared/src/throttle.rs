@@ -0,0 +1,72 @@
+use std::{
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::expiring::ExpiringMap;
+
+/// The most addresses tracked, the least recently attempting ones are forgotten past it
+const MAX_TRACKED: usize = 65_536;
+
+/// The last login attempt of the clients, throttling the reconnect spam
+///
+/// Every attempt restarts the delay of its address, so a macro reconnecting in a loop is kept out
+/// until it stops, while a player waiting a moment gets in.
+#[derive(Debug)]
+pub struct Throttle {
+    last: Mutex<ExpiringMap<IpAddr, Instant>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self {
+            last: Mutex::new(ExpiringMap::new(MAX_TRACKED)),
+        }
+    }
+}
+
+impl Throttle {
+    /// It records the login attempt of a client, and tells whether it came after the delay
+    ///
+    /// Arguments:
+    ///
+    /// * `ip`: The address of the client.
+    /// * `min_delay`: The minimum delay between two attempts of an address.
+    ///
+    /// Returns:
+    ///
+    /// A bool, false when the previous attempt of the address is too recent
+    pub fn attempt(&self, ip: IpAddr, min_delay: Duration) -> bool {
+        self.attempt_at(ip, min_delay, Instant::now())
+    }
+
+    fn attempt_at(&self, ip: IpAddr, min_delay: Duration, now: Instant) -> bool {
+        let mut last = self.last.lock().unwrap();
+        // an attempt is forgotten once past the delay, the next one is let in anyway
+        match last.insert(ip, now, now + min_delay, now) {
+            Some(attempted) => now.duration_since(attempted) >= min_delay,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_throttles_the_attempts_within_the_delay() {
+        let throttle = Throttle::default();
+        let min_delay = Duration::from_secs(4);
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(throttle.attempt_at(ip, min_delay, start));
+        assert!(throttle.attempt_at("203.0.113.8".parse().unwrap(), min_delay, start));
+        // the throttled attempt restarts the delay
+        assert!(!throttle.attempt_at(ip, min_delay, start + Duration::from_secs(3)));
+        assert!(!throttle.attempt_at(ip, min_delay, start + Duration::from_secs(6)));
+        assert!(throttle.attempt_at(ip, min_delay, start + Duration::from_secs(10)));
+    }
+}
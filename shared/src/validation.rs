@@ -0,0 +1,22 @@
+/// The findings of the dry run of a backend change, which leaves the storage untouched
+///
+/// Properties:
+///
+/// * `errors`: What would reject the change, or leave the route broken.
+/// * `warnings`: What is likely a mistake, e.g. a fallback on a backend that isn't stored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validation {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl Validation {
+    /// It returns whether the change can be applied
+    ///
+    /// Returns:
+    ///
+    /// true without errors, whatever the warnings
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
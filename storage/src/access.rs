@@ -0,0 +1,126 @@
+use std::{net::IpAddr, sync::Arc};
+
+use arc_swap::ArcSwap;
+use shared::models::access_rule::{AccessKind, AccessRule, AccessSubject};
+
+/// A handle on the latest access list published by the storage
+///
+/// Loading it never blocks, so the connections check it right after the accept.
+pub type AccessHandle = Arc<ArcSwap<AccessList>>;
+
+/// An immutable view of the access rules, published by the storage after every change
+///
+/// The expired rules are ignored, the storage drops them at its next change.
+#[derive(Debug, Default)]
+pub struct AccessList {
+    bans: Vec<AccessRule>,
+    allows: Vec<AccessRule>,
+}
+
+impl AccessList {
+    /// Creates a new access list from the access rules
+    ///
+    /// Arguments:
+    ///
+    /// * `rules` - The rules, bans and allows
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new<'a>(rules: impl IntoIterator<Item = &'a AccessRule>) -> Self {
+        let (allows, bans) = rules
+            .into_iter()
+            .cloned()
+            .partition(|rule| rule.kind == AccessKind::Allow);
+
+        Self { bans, allows }
+    }
+
+    /// It returns the ban of a client, unless the client is allowed
+    ///
+    /// Arguments:
+    ///
+    /// * `ip` - The address of the client
+    /// * `username` - The username of the login start, none to only check the address
+    /// * `now_ms` - The current time, in milliseconds since the Unix epoch
+    ///
+    /// Returns:
+    ///
+    /// The rule banning the client, if any
+    pub fn ban(&self, ip: &IpAddr, username: Option<&str>, now_ms: u64) -> Option<&AccessRule> {
+        if self.is_allowed(ip, username, now_ms) {
+            return None;
+        }
+
+        self.bans
+            .iter()
+            .find(|rule| !rule.is_expired(now_ms) && rule.subject.matches(ip, username))
+    }
+
+    /// It tells whether a client is allowed, so it is never banned
+    ///
+    /// Arguments:
+    ///
+    /// * `ip` - The address of the client
+    /// * `username` - The username of the login start, none to only check the address
+    /// * `now_ms` - The current time, in milliseconds since the Unix epoch
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn is_allowed(&self, ip: &IpAddr, username: Option<&str>, now_ms: u64) -> bool {
+        self.allows
+            .iter()
+            .any(|rule| !rule.is_expired(now_ms) && rule.subject.matches(ip, username))
+    }
+
+    /// It tells whether a rule applies to the usernames, so the login starts must be read
+    ///
+    /// Returns:
+    ///
+    /// A bool
+    pub fn has_username_bans(&self) -> bool {
+        self.bans
+            .iter()
+            .any(|rule| matches!(rule.subject, AccessSubject::Username(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: AccessKind, subject: &str, expires_at_ms: Option<u64>) -> AccessRule {
+        AccessRule {
+            kind,
+            subject: subject.parse().unwrap(),
+            reason: String::new(),
+            created_at_ms: 0,
+            expires_at_ms,
+        }
+    }
+
+    #[test]
+    fn it_bans_the_clients_unless_allowed() {
+        let list = AccessList::new(&[
+            rule(AccessKind::Ban, "10.0.0.0/8", None),
+            rule(AccessKind::Ban, "Griefer", Some(1_000)),
+            rule(AccessKind::Allow, "10.0.0.1", None),
+        ]);
+        let banned: IpAddr = "10.1.2.3".parse().unwrap();
+        let allowed: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "192.168.1.1".parse().unwrap();
+
+        assert!(list.ban(&banned, None, 0).is_some());
+        assert!(list.ban(&allowed, None, 0).is_none());
+        assert!(list.is_allowed(&allowed, None, 0));
+        assert_eq!(
+            list.ban(&other, Some("griefer"), 999)
+                .map(|rule| &rule.subject),
+            Some(&AccessSubject::Username("Griefer".to_string()))
+        );
+        // the ban of the username expired
+        assert!(list.ban(&other, Some("griefer"), 1_000).is_none());
+        assert!(list.has_username_bans());
+    }
+}
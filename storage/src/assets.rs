@@ -0,0 +1,48 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arc_swap::ArcSwap;
+use shared::models::status_asset::StatusAsset;
+
+/// A handle on the latest status assets published by the storage
+///
+/// Loading it never blocks, so the connections read it when the proxy answers a status itself.
+pub type AssetsHandle = Arc<ArcSwap<StatusAssets>>;
+
+/// An immutable view of the status assets, published by the storage after every change
+#[derive(Debug, Default)]
+pub struct StatusAssets {
+    assets: HashMap<String, StatusAsset>,
+}
+
+impl StatusAssets {
+    /// Creates a new view of the status assets
+    ///
+    /// Arguments:
+    ///
+    /// * `assets` - The assets, one per hostname
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new<'a>(assets: impl IntoIterator<Item = &'a StatusAsset>) -> Self {
+        Self {
+            assets: assets
+                .into_iter()
+                .map(|asset| (asset.hostname.clone(), asset.clone()))
+                .collect(),
+        }
+    }
+
+    /// It returns the asset of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname` - The hostname of the backend
+    ///
+    /// Returns:
+    ///
+    /// The asset, if one was uploaded
+    pub fn get(&self, hostname: &str) -> Option<&StatusAsset> {
+        self.assets.get(hostname)
+    }
+}
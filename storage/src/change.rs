@@ -0,0 +1,26 @@
+use shared::models::backend::Backend;
+
+/// A change applied to the storage, broadcast to every subscriber
+///
+/// Properties:
+///
+/// * `Put`: A backend has been created or updated, with its new version.
+/// * `Delete`: A backend has been removed, with its last stored version.
+#[derive(Debug, Clone)]
+pub enum BackendChange {
+    Put(Backend),
+    Delete(Backend),
+}
+
+impl BackendChange {
+    /// It returns the backend concerned by the change
+    ///
+    /// Returns:
+    ///
+    /// A reference to the backend
+    pub fn backend(&self) -> &Backend {
+        match self {
+            Self::Put(backend) | Self::Delete(backend) => backend,
+        }
+    }
+}
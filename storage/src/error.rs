@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+/// Errors returned by the storage when a change can't be applied
+///
+/// Properties:
+///
+/// * `NotFound`: No backend is stored for the given hostname.
+/// * `AlreadyExists`: A backend is already stored for the given hostname.
+/// * `QuotaExceeded`: The change would exceed a backend quota.
+/// * `VersionConflict`: The version sent by the caller doesn't match the stored one.
+/// * `ReadOnly`: The backend is a static route of the configuration file.
+/// * `BatchRejected`: A change of a batch was rejected, with the error of the change.
+/// * `AccessRuleNotFound`: No access rule is stored for the given kind and subject.
+/// * `StatusAssetNotFound`: No status asset is stored for the given hostname.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StorageError {
+    #[error("backend {0} not found")]
+    NotFound(String),
+    #[error("backend {0} already exists")]
+    AlreadyExists(String),
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(String),
+    #[error("version conflict on backend {hostname}: expected version {expected}, got {actual}")]
+    VersionConflict {
+        hostname: String,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("backend {0} is a static route of the configuration and is read-only")]
+    ReadOnly(String),
+    #[error("change #{index} of the batch rejected")]
+    BatchRejected {
+        index: usize,
+        #[source]
+        source: Box<StorageError>,
+    },
+    #[error("access rule {0} not found")]
+    AccessRuleNotFound(String),
+    #[error("status asset of {0} not found")]
+    StatusAssetNotFound(String),
+}
+
+impl StorageError {
+    /// It returns the error of the change itself, the one of the rejected change for a batch
+    ///
+    /// Returns:
+    ///
+    /// A reference to the StorageError
+    pub fn root_cause(&self) -> &StorageError {
+        match self {
+            Self::BatchRejected { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+}
@@ -1,12 +1,81 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use shared::models::backend::Backend;
+use arc_swap::ArcSwap;
+use config::LimitsConfig;
+use metrics::storage::StorageMetrics;
+use shared::models::{
+    access_rule::{now_ms, AccessKind, AccessRule, AccessSubject},
+    backend::Backend,
+    selector::LabelSelector,
+    status_asset::StatusAsset,
+};
+use shared::stats::Totals;
+use tokio::sync::broadcast;
+
+pub use crate::{
+    access::{AccessHandle, AccessList},
+    assets::{AssetsHandle, StatusAssets},
+    change::BackendChange,
+    error::StorageError,
+    quota::Quotas,
+    routing::{RoutingHandle, RoutingTable},
+    snapshot::Snapshot,
+    store::BackendStore,
+    tombstone::Tombstone,
+};
+
+pub mod access;
+pub mod assets;
+pub mod change;
+pub mod error;
+pub mod quota;
+pub mod routing;
+pub mod snapshot;
+pub mod store;
+pub mod tombstone;
+
+/// The number of changes a subscriber can lag behind before missing some
+const CHANGES_CAPACITY: usize = 64;
 
 /// The storage is responsible for storing the backends
-#[derive(Debug, Default)]
+///
+/// Every change bumps a global revision which is used as the new version of the
+/// changed backend, so versions are monotonically increasing across the whole storage.
+/// Changes are also broadcast to the subscribers, see `Storage::subscribe`, and
+/// published as a new routing table, see `Storage::routing_table`.
+///
+/// When a tombstone retention is set, deleted backends are kept as tombstones for
+/// that long and can be brought back with `Storage::restore_backend`.
+///
+/// The bans and the allows set through the API are stored alongside the backends, published
+/// as a new access list, see `Storage::access_list`, and carried by the snapshots. So are the
+/// MOTDs and the favicons of the hostnames, see `Storage::status_assets_handle`, and the
+/// cumulative totals of the backends the proxy checkpoints, see `Storage::checkpoint_totals`.
+#[derive(Debug)]
 pub struct Storage {
     backends: BTreeMap<String, Backend>,
+    tombstones: BTreeMap<String, Tombstone>,
+    tombstone_retention: Option<Duration>,
+    quotas: Quotas,
+    revision: u64,
+    changes: broadcast::Sender<BackendChange>,
+    routes: RoutingHandle,
+    access_rules: BTreeMap<String, AccessRule>,
+    access: AccessHandle,
+    status_assets: BTreeMap<String, StatusAsset>,
+    assets: AssetsHandle,
+    backend_totals: BTreeMap<String, Totals>,
+    metrics: StorageMetrics,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::with_metrics(StorageMetrics::default())
+    }
 }
 
 impl Storage {
@@ -19,7 +88,318 @@ impl Storage {
         Self::default()
     }
 
-    /// It adds a new backend to the storage
+    /// Creates a new instance of the `Storage` struct recording its operations into metrics
+    ///
+    /// Arguments:
+    ///
+    /// * `metrics` - The metrics to record the operations into
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn with_metrics(metrics: StorageMetrics) -> Self {
+        Self::with_capacity(CHANGES_CAPACITY, metrics)
+    }
+
+    /// Creates a new instance of the `Storage` struct with the given capacity of change
+    /// notifications, recording its operations into metrics
+    ///
+    /// Arguments:
+    ///
+    /// * `changes` - The number of changes a subscriber can lag behind before missing some
+    /// * `metrics` - The metrics to record the operations into
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn with_capacity(changes: usize, metrics: StorageMetrics) -> Self {
+        let (changes, _) = broadcast::channel(changes);
+        let routes = RoutingTable::new(std::iter::empty(), metrics.clone());
+
+        Self {
+            backends: BTreeMap::new(),
+            tombstones: BTreeMap::new(),
+            tombstone_retention: None,
+            quotas: Quotas::default(),
+            revision: 0,
+            changes,
+            routes: Arc::new(ArcSwap::from_pointee(routes)),
+            access_rules: BTreeMap::new(),
+            access: AccessHandle::default(),
+            status_assets: BTreeMap::new(),
+            assets: AssetsHandle::default(),
+            backend_totals: BTreeMap::new(),
+            metrics,
+        }
+    }
+
+    /// It applies the limits of the configuration, the tombstone retention and the quotas
+    ///
+    /// Arguments:
+    ///
+    /// * `limits` - The limits section of the proxy configuration
+    pub fn set_limits(&mut self, limits: &LimitsConfig) {
+        self.set_tombstone_retention(limits.tombstone_retention_secs.map(Duration::from_secs));
+        self.set_quotas(Quotas::from(limits));
+    }
+
+    /// It replaces the static routes declared in the configuration file
+    ///
+    /// Static routes are read-only: they replace any backend registered through the API with
+    /// the same hostname, and only a new call can change or remove them. They don't count
+    /// against the quotas. Unchanged routes keep their version.
+    ///
+    /// Arguments:
+    ///
+    /// * `backends` - The backends of the static routes
+    pub fn set_static_routes(&mut self, backends: Vec<Backend>) {
+        let hostnames: BTreeSet<String> = backends
+            .iter()
+            .map(|backend| backend.hostname().to_string())
+            .collect();
+
+        let removed: Vec<String> = self
+            .backends
+            .values()
+            .filter(|backend| backend.read_only() && !hostnames.contains(backend.hostname()))
+            .map(|backend| backend.hostname().to_string())
+            .collect();
+        for hostname in removed {
+            if let Some(backend) = self.backends.remove(&hostname) {
+                self.revision += 1;
+                self.notify(BackendChange::Delete(backend));
+            }
+        }
+
+        for mut backend in backends {
+            backend.read_only = true;
+            backend.version = self.stored_version(backend.hostname());
+            if self.backends.get(backend.hostname()) == Some(&backend) {
+                continue;
+            }
+
+            self.revision += 1;
+            backend.version = self.revision;
+
+            self.tombstones.remove(backend.hostname());
+            self.backends
+                .insert(backend.hostname().to_string(), backend.clone());
+            self.notify(BackendChange::Put(backend));
+        }
+
+        self.publish();
+    }
+
+    /// It sets how long deleted backends are kept as tombstones
+    ///
+    /// Arguments:
+    ///
+    /// * `retention` - The retention window, `None` to delete backends right away
+    pub fn set_tombstone_retention(&mut self, retention: Option<Duration>) {
+        self.tombstone_retention = retention;
+        if retention.is_none() {
+            self.tombstones.clear();
+        }
+    }
+
+    /// It sets the limits on the number of backends
+    ///
+    /// Arguments:
+    ///
+    /// * `quotas` - The quotas checked by every change adding backends
+    pub fn set_quotas(&mut self, quotas: Quotas) {
+        self.quotas = quotas;
+    }
+
+    /// It subscribes to the changes applied to the storage
+    ///
+    /// Only the changes applied after the subscription are received, callers that
+    /// need the current state should read it while holding the same lock.
+    ///
+    /// Returns:
+    ///
+    /// A broadcast::Receiver<BackendChange>
+    pub fn subscribe(&self) -> broadcast::Receiver<BackendChange> {
+        self.changes.subscribe()
+    }
+
+    /// It returns a handle on the routing table, updated after every change
+    ///
+    /// Returns:
+    ///
+    /// A RoutingHandle
+    pub fn routing_table(&self) -> RoutingHandle {
+        self.routes.clone()
+    }
+
+    /// It returns a handle on the access list, updated after every change of the access rules
+    ///
+    /// Returns:
+    ///
+    /// An AccessHandle
+    pub fn access_list(&self) -> AccessHandle {
+        self.access.clone()
+    }
+
+    /// It returns the access rules which didn't expire
+    ///
+    /// Returns:
+    ///
+    /// The rules, ordered by kind then subject
+    pub fn access_rules(&self) -> Vec<AccessRule> {
+        let now_ms = now_ms();
+        self.observe("list_access_rules", Instant::now(), true);
+        self.access_rules
+            .values()
+            .filter(|rule| !rule.is_expired(now_ms))
+            .cloned()
+            .collect()
+    }
+
+    /// It sets an access rule, replacing the one of the same kind and subject
+    ///
+    /// Arguments:
+    ///
+    /// * `rule` - The ban or the allow to set
+    ///
+    /// Returns:
+    ///
+    /// The stored rule
+    pub fn put_access_rule(&mut self, rule: AccessRule) -> AccessRule {
+        let start = Instant::now();
+
+        self.access_rules.insert(rule.key(), rule.clone());
+        self.publish_access();
+
+        self.observe("put_access_rule", start, true);
+        rule
+    }
+
+    /// It removes an access rule
+    ///
+    /// Arguments:
+    ///
+    /// * `kind` - Whether the rule bans or allows its subject
+    /// * `subject` - The clients the rule applies to
+    ///
+    /// Returns:
+    ///
+    /// A Result<AccessRule> with the removed rule
+    pub fn remove_access_rule(
+        &mut self,
+        kind: AccessKind,
+        subject: &AccessSubject,
+    ) -> Result<AccessRule, StorageError> {
+        let start = Instant::now();
+
+        let key = AccessRule::key_of(kind, subject);
+        let now_ms = now_ms();
+        let result = match self.access_rules.remove(&key) {
+            Some(rule) if !rule.is_expired(now_ms) => Ok(rule),
+            _ => Err(StorageError::AccessRuleNotFound(key)),
+        };
+        self.publish_access();
+
+        self.observe("delete_access_rule", start, result.is_ok());
+        result
+    }
+
+    /// It returns a handle on the status assets, updated after every change of them
+    ///
+    /// Returns:
+    ///
+    /// An AssetsHandle
+    pub fn status_assets_handle(&self) -> AssetsHandle {
+        self.assets.clone()
+    }
+
+    /// It returns the status assets
+    ///
+    /// Returns:
+    ///
+    /// The assets, ordered by hostname
+    pub fn status_assets(&self) -> Vec<StatusAsset> {
+        self.observe("list_status_assets", Instant::now(), true);
+        self.status_assets.values().cloned().collect()
+    }
+
+    /// It sets the status asset of a hostname, replacing the previous one
+    ///
+    /// The backend of the hostname doesn't need to exist yet, e.g. when the asset is uploaded
+    /// before the backend is registered.
+    ///
+    /// Arguments:
+    ///
+    /// * `asset` - The MOTD and the favicon of the hostname
+    ///
+    /// Returns:
+    ///
+    /// The stored asset
+    pub fn put_status_asset(&mut self, asset: StatusAsset) -> StatusAsset {
+        let start = Instant::now();
+
+        self.status_assets
+            .insert(asset.hostname.clone(), asset.clone());
+        self.publish_assets();
+
+        self.observe("put_status_asset", start, true);
+        asset
+    }
+
+    /// It removes the status asset of a hostname
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname` - The hostname of the backend
+    ///
+    /// Returns:
+    ///
+    /// A Result<StatusAsset> with the removed asset
+    pub fn remove_status_asset(&mut self, hostname: &str) -> Result<StatusAsset, StorageError> {
+        let start = Instant::now();
+
+        let result = self
+            .status_assets
+            .remove(hostname)
+            .ok_or_else(|| StorageError::StatusAssetNotFound(hostname.to_string()));
+        if result.is_ok() {
+            self.publish_assets();
+        }
+
+        self.observe("delete_status_asset", start, result.is_ok());
+        result
+    }
+
+    /// It returns the cumulative totals of the backends, as of the latest checkpoint
+    ///
+    /// Returns:
+    ///
+    /// The totals, by hostname
+    pub fn backend_totals(&self) -> BTreeMap<String, Totals> {
+        self.backend_totals.clone()
+    }
+
+    /// It replaces the cumulative totals of the backends with the ones counted by the proxy
+    ///
+    /// The totals of the deleted backends are kept for the usage reports.
+    ///
+    /// Arguments:
+    ///
+    /// * `totals` - The cumulative totals, by hostname
+    pub fn checkpoint_totals(&mut self, totals: BTreeMap<String, Totals>) {
+        let start = Instant::now();
+
+        self.backend_totals = totals;
+
+        self.observe("checkpoint_totals", start, true);
+    }
+
+    /// It adds a new backend to the storage, or updates an existing one
+    ///
+    /// The version of the backend must match the stored version (`0` when the
+    /// backend doesn't exist yet), otherwise a `StorageError::VersionConflict`
+    /// is returned and the storage is left untouched. The same goes with a
+    /// `StorageError::QuotaExceeded` if the backend would exceed a quota.
     ///
     /// Arguments:
     ///
@@ -27,11 +407,40 @@ impl Storage {
     ///
     /// Returns:
     ///
-    /// A Result<()>
-    pub fn add_backend(&mut self, backend: Backend) -> Result<()> {
-        self.backends
-            .insert(backend.hostname().to_string(), backend);
-        Ok(())
+    /// A Result<Backend> with the stored backend and its new version
+    pub fn add_backend(&mut self, backend: Backend) -> Result<Backend, StorageError> {
+        let start = Instant::now();
+
+        let result = self
+            .check_quotas(&BTreeMap::from([(backend.hostname(), Some(&backend))]))
+            .and_then(|_| self.insert(backend));
+        if result.is_ok() {
+            self.publish();
+        }
+
+        self.observe("put", start, result.is_ok());
+        result
+    }
+
+    /// It checks that a backend could be put, without storing it, e.g. for a dry run
+    ///
+    /// Arguments:
+    ///
+    /// * `backend` - The backend to check
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>, the error `add_backend` would return
+    pub fn check_backend(&self, backend: &Backend) -> Result<(), StorageError> {
+        if self.is_read_only(backend.hostname()) {
+            return Err(StorageError::ReadOnly(backend.hostname().to_string()));
+        }
+        Self::check_version(
+            backend.hostname(),
+            self.stored_version(backend.hostname()),
+            backend.version(),
+        )?;
+        self.check_quotas(&BTreeMap::from([(backend.hostname(), Some(backend))]))
     }
 
     /// It removes a backend from the storage
@@ -39,13 +448,224 @@ impl Storage {
     /// Arguments:
     ///
     /// * `host` - The host of the backend to remove
+    /// * `version` - The version of the backend the caller expects to remove
     ///
     /// Returns:
     ///
     /// A Result<()>
-    pub fn remove_backend(&mut self, host: &str) -> Result<()> {
-        self.backends.remove(host);
-        Ok(())
+    pub fn remove_backend(&mut self, host: &str, version: u64) -> Result<(), StorageError> {
+        let start = Instant::now();
+
+        let result = self.delete(host, version);
+        if result.is_ok() {
+            self.publish();
+        }
+
+        self.observe("delete", start, result.is_ok());
+        result.map(|_| ())
+    }
+
+    /// It restores a backend deleted during the tombstone retention window
+    ///
+    /// The backend is stored again with a new version, as if it was put.
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the deleted backend
+    ///
+    /// Returns:
+    ///
+    /// A Result<Backend> with the restored backend and its new version
+    pub fn restore_backend(&mut self, host: &str) -> Result<Backend, StorageError> {
+        let start = Instant::now();
+
+        let result = self.undelete(host);
+        if result.is_ok() {
+            self.publish();
+        }
+
+        self.observe("restore_backend", start, result.is_ok());
+        result
+    }
+
+    /// It applies a batch of puts and deletes atomically
+    ///
+    /// The whole batch is validated before anything is applied: if any change is
+    /// rejected, the storage is left untouched. Changes are applied in order, so a
+    /// change on a hostname already changed by the batch must use the version assigned
+    /// by the previous change.
+    ///
+    /// Arguments:
+    ///
+    /// * `batch` - The changes to apply, with the versions they are based on
+    ///
+    /// Returns:
+    ///
+    /// A Result<Vec<BackendChange>> with the applied changes and their new versions
+    pub fn apply(&mut self, batch: Vec<BackendChange>) -> Result<Vec<BackendChange>, StorageError> {
+        let start = Instant::now();
+
+        let result = self.apply_batch(batch);
+
+        self.observe("apply", start, result.is_ok());
+        result
+    }
+
+    /// It validates then applies a batch of changes, see `Storage::apply`
+    ///
+    /// Arguments:
+    ///
+    /// * `batch` - The changes to apply, with the versions they are based on
+    ///
+    /// Returns:
+    ///
+    /// A Result<Vec<BackendChange>> with the applied changes and their new versions
+    fn apply_batch(
+        &mut self,
+        batch: Vec<BackendChange>,
+    ) -> Result<Vec<BackendChange>, StorageError> {
+        let mut staged: BTreeMap<&str, u64> = BTreeMap::new();
+        let mut staged_backends: BTreeMap<&str, Option<&Backend>> = BTreeMap::new();
+
+        for (index, change) in batch.iter().enumerate() {
+            let backend = change.backend();
+            let current = staged
+                .get(backend.hostname())
+                .copied()
+                .unwrap_or_else(|| self.stored_version(backend.hostname()));
+
+            let result = match change {
+                _ if self.is_read_only(backend.hostname()) => {
+                    Err(StorageError::ReadOnly(backend.hostname().to_string()))
+                }
+                BackendChange::Delete(_) if current == 0 => {
+                    Err(StorageError::NotFound(backend.hostname().to_string()))
+                }
+                _ => Self::check_version(backend.hostname(), current, backend.version()),
+            };
+            result.map_err(|e| StorageError::BatchRejected {
+                index,
+                source: Box::new(e),
+            })?;
+
+            let (version, staged_backend) = match change {
+                BackendChange::Put(_) => (self.revision + index as u64 + 1, Some(backend)),
+                BackendChange::Delete(_) => (0, None),
+            };
+            staged.insert(backend.hostname(), version);
+            staged_backends.insert(backend.hostname(), staged_backend);
+        }
+
+        // quotas only apply to the state at the end of the batch
+        self.check_quotas(&staged_backends)?;
+
+        let mut applied = Vec::with_capacity(batch.len());
+        for change in batch {
+            applied.push(match change {
+                BackendChange::Put(backend) => BackendChange::Put(self.insert(backend)?),
+                BackendChange::Delete(backend) => {
+                    BackendChange::Delete(self.delete(backend.hostname(), backend.version())?)
+                }
+            });
+        }
+        self.publish();
+
+        Ok(applied)
+    }
+
+    /// It takes a snapshot of the whole state of the storage
+    ///
+    /// The static routes are left out, they come from the configuration file.
+    ///
+    /// Returns:
+    ///
+    /// A Snapshot
+    pub fn snapshot(&self) -> Snapshot {
+        let start = Instant::now();
+
+        let snapshot = Snapshot {
+            revision: self.revision,
+            backends: self
+                .backends
+                .values()
+                .filter(|backend| !backend.read_only())
+                .cloned()
+                .collect(),
+            access_rules: self.access_rules(),
+            status_assets: self.status_assets.values().cloned().collect(),
+            backend_totals: self.backend_totals.clone(),
+        };
+
+        self.observe("snapshot", start, true);
+        snapshot
+    }
+
+    /// It replaces the whole state of the storage with a snapshot
+    ///
+    /// The versions of the restored backends are kept as is, and the revision never goes
+    /// backward so versions assigned afterwards are still greater than any known version.
+    /// Subscribers receive a delete for every dropped backend and a put for every restored one.
+    /// The static routes are kept, and take precedence over the snapshot. The access rules, the
+    /// status assets and the totals of the backends are replaced by the ones of the snapshot.
+    ///
+    /// Arguments:
+    ///
+    /// * `snapshot` - The snapshot to restore
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        let start = Instant::now();
+        let mut backends: BTreeMap<String, Backend> = snapshot
+            .backends
+            .into_iter()
+            .filter(|backend| !self.is_read_only(backend.hostname()))
+            .map(|backend| {
+                (
+                    backend.hostname().to_string(),
+                    Backend {
+                        read_only: false,
+                        ..backend
+                    },
+                )
+            })
+            .collect();
+        backends.extend(
+            self.backends
+                .values()
+                .filter(|backend| backend.read_only())
+                .map(|backend| (backend.hostname().to_string(), backend.clone())),
+        );
+
+        let max_version = backends.values().map(|b| b.version()).max().unwrap_or(0);
+        self.revision = self.revision.max(snapshot.revision).max(max_version);
+
+        self.tombstones.clear();
+        let previous = std::mem::replace(&mut self.backends, backends);
+        for (hostname, backend) in previous {
+            if !self.backends.contains_key(&hostname) {
+                self.notify(BackendChange::Delete(backend));
+            }
+        }
+        for backend in self.backends.values() {
+            self.notify(BackendChange::Put(backend.clone()));
+        }
+        self.publish();
+
+        self.access_rules = snapshot
+            .access_rules
+            .into_iter()
+            .map(|rule| (rule.key(), rule))
+            .collect();
+        self.publish_access();
+
+        self.status_assets = snapshot
+            .status_assets
+            .into_iter()
+            .map(|asset| (asset.hostname.clone(), asset))
+            .collect();
+        self.publish_assets();
+
+        self.backend_totals = snapshot.backend_totals;
+
+        self.observe("restore", start, true);
     }
 
     /// It returns the backend with the specified host
@@ -58,7 +678,13 @@ impl Storage {
     ///
     /// The backend with the specified host
     pub fn get_backend(&self, host: &str) -> Option<&Backend> {
-        self.backends.get(host)
+        let start = Instant::now();
+
+        let backend = self.backends.get(host);
+
+        self.observe("get", start, true);
+        self.metrics.lookup(backend.is_some());
+        backend
     }
 
     /// It returns all the backends
@@ -67,6 +693,631 @@ impl Storage {
     ///
     /// All the backends
     pub fn get_backends(&self) -> &BTreeMap<String, Backend> {
+        self.observe("list", Instant::now(), true);
         &self.backends
     }
+
+    /// It returns the backends whose labels match a selector
+    ///
+    /// Arguments:
+    ///
+    /// * `selector` - The label selector, an empty one selects every backend
+    ///
+    /// Returns:
+    ///
+    /// The selected backends, ordered by hostname
+    pub fn select_backends(&self, selector: &LabelSelector) -> Vec<Backend> {
+        self.observe("select", Instant::now(), true);
+        self.backends
+            .values()
+            .filter(|backend| selector.matches(backend.labels()))
+            .cloned()
+            .collect()
+    }
+
+    /// It checks the version of a backend, then stores it with a new version
+    ///
+    /// The routing table is not published, see `Storage::publish`.
+    ///
+    /// Arguments:
+    ///
+    /// * `backend` - The backend to store
+    ///
+    /// Returns:
+    ///
+    /// A Result<Backend> with the stored backend and its new version
+    fn insert(&mut self, mut backend: Backend) -> Result<Backend, StorageError> {
+        if self.is_read_only(backend.hostname()) {
+            return Err(StorageError::ReadOnly(backend.hostname().to_string()));
+        }
+        backend.read_only = false;
+
+        Self::check_version(
+            backend.hostname(),
+            self.stored_version(backend.hostname()),
+            backend.version(),
+        )?;
+
+        self.revision += 1;
+        backend.version = self.revision;
+
+        self.tombstones.remove(backend.hostname());
+        self.backends
+            .insert(backend.hostname().to_string(), backend.clone());
+        self.notify(BackendChange::Put(backend.clone()));
+        Ok(backend)
+    }
+
+    /// It checks the version of a backend, then removes it
+    ///
+    /// The routing table is not published, see `Storage::publish`.
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the backend to remove
+    /// * `version` - The version of the backend the caller expects to remove
+    ///
+    /// Returns:
+    ///
+    /// A Result<Backend> with the removed backend
+    fn delete(&mut self, host: &str, version: u64) -> Result<Backend, StorageError> {
+        let backend = self
+            .backends
+            .get(host)
+            .ok_or_else(|| StorageError::NotFound(host.to_string()))?;
+        if backend.read_only() {
+            return Err(StorageError::ReadOnly(host.to_string()));
+        }
+        Self::check_version(host, backend.version(), version)?;
+
+        self.revision += 1;
+        let backend = self
+            .backends
+            .remove(host)
+            .ok_or_else(|| StorageError::NotFound(host.to_string()))?;
+        self.notify(BackendChange::Delete(backend.clone()));
+
+        self.purge_tombstones();
+        if self.tombstone_retention.is_some() {
+            self.tombstones
+                .insert(host.to_string(), Tombstone::new(backend.clone()));
+        }
+        Ok(backend)
+    }
+
+    /// It stores back the backend of a tombstone with a new version
+    ///
+    /// The routing table is not published, see `Storage::publish`.
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the deleted backend
+    ///
+    /// Returns:
+    ///
+    /// A Result<Backend> with the restored backend and its new version
+    fn undelete(&mut self, host: &str) -> Result<Backend, StorageError> {
+        self.purge_tombstones();
+
+        if self.backends.contains_key(host) {
+            return Err(StorageError::AlreadyExists(host.to_string()));
+        }
+
+        let tombstone = self
+            .tombstones
+            .get(host)
+            .ok_or_else(|| StorageError::NotFound(host.to_string()))?;
+        self.check_quotas(&BTreeMap::from([(host, Some(&tombstone.backend))]))?;
+
+        let mut backend = self
+            .tombstones
+            .remove(host)
+            .ok_or_else(|| StorageError::NotFound(host.to_string()))?
+            .backend;
+
+        // the backend doesn't exist anymore, so it's put back from version 0
+        backend.version = 0;
+        self.insert(backend)
+    }
+
+    /// It checks that replacing some backends doesn't exceed the quotas
+    ///
+    /// Arguments:
+    ///
+    /// * `changes` - The new backends by hostname, none for the deleted ones
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    fn check_quotas(&self, changes: &BTreeMap<&str, Option<&Backend>>) -> Result<(), StorageError> {
+        if self.quotas.is_unlimited() {
+            return Ok(());
+        }
+
+        // the static routes don't count against the quotas
+        let before = self
+            .backends
+            .values()
+            .filter(|backend| !backend.read_only());
+        let unchanged = before
+            .clone()
+            .filter(|backend| !changes.contains_key(backend.hostname()));
+        let after = unchanged.chain(changes.values().flatten().copied());
+
+        self.quotas.check(before, after)
+    }
+
+    /// It drops the tombstones that outlived the retention window
+    fn purge_tombstones(&mut self) {
+        match self.tombstone_retention {
+            Some(retention) => self
+                .tombstones
+                .retain(|_, tombstone| !tombstone.is_expired(retention)),
+            None => self.tombstones.clear(),
+        }
+    }
+
+    /// It builds a new routing table from the backends and publishes it
+    fn publish(&self) {
+        self.routes.store(Arc::new(RoutingTable::new(
+            self.backends.values(),
+            self.metrics.clone(),
+        )));
+    }
+
+    /// It drops the expired access rules, then publishes a new access list
+    fn publish_access(&mut self) {
+        let now_ms = now_ms();
+        self.access_rules.retain(|_, rule| !rule.is_expired(now_ms));
+        self.access
+            .store(Arc::new(AccessList::new(self.access_rules.values())));
+    }
+
+    /// It publishes a new view of the status assets
+    fn publish_assets(&self) {
+        self.assets
+            .store(Arc::new(StatusAssets::new(self.status_assets.values())));
+    }
+
+    /// It records an operation into the metrics
+    ///
+    /// Arguments:
+    ///
+    /// * `operation` - The name of the operation
+    /// * `start` - When the operation started
+    /// * `ok` - Whether the operation succeeded
+    fn observe(&self, operation: &str, start: Instant, ok: bool) {
+        self.metrics
+            .observe(operation, start.elapsed().as_secs_f64(), ok);
+    }
+
+    /// It broadcasts a change to the subscribers
+    ///
+    /// Arguments:
+    ///
+    /// * `change` - The change to broadcast
+    fn notify(&self, change: BackendChange) {
+        // an error only means that nobody is subscribed
+        let _ = self.changes.send(change);
+    }
+
+    /// It tells whether a backend is a static route of the configuration file
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the backend
+    ///
+    /// Returns:
+    ///
+    /// true if the backend is read-only
+    fn is_read_only(&self, host: &str) -> bool {
+        self.backends.get(host).is_some_and(|b| b.read_only())
+    }
+
+    /// It returns the stored version of a backend, `0` if it doesn't exist
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the backend
+    ///
+    /// Returns:
+    ///
+    /// The stored version
+    fn stored_version(&self, host: &str) -> u64 {
+        self.backends.get(host).map_or(0, |b| b.version())
+    }
+
+    /// It checks that the given version matches the expected version of a backend
+    ///
+    /// Arguments:
+    ///
+    /// * `host` - The host of the backend
+    /// * `expected` - The version currently stored
+    /// * `version` - The version sent by the caller
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    fn check_version(host: &str, expected: u64, version: u64) -> Result<(), StorageError> {
+        if expected != version {
+            return Err(StorageError::VersionConflict {
+                hostname: host.to_string(),
+                expected,
+                actual: version,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(version: u64) -> Backend {
+        let mut backend = Backend::new(
+            "game.example.com".to_string(),
+            "192.168.1.10".to_string(),
+            25565,
+        );
+        backend.version = version;
+        backend
+    }
+
+    #[test]
+    fn test_add_backend_assigns_increasing_versions() {
+        let mut storage = Storage::new();
+
+        let created = storage.add_backend(backend(0)).unwrap();
+        let updated = storage.add_backend(backend(created.version())).unwrap();
+
+        assert_eq!(created.version(), 1);
+        assert_eq!(updated.version(), 2);
+    }
+
+    #[test]
+    fn test_add_backend_stale_version_err() {
+        let mut storage = Storage::new();
+        storage.add_backend(backend(0)).unwrap();
+
+        let err = storage.add_backend(backend(0)).unwrap_err();
+
+        assert_eq!(
+            err,
+            StorageError::VersionConflict {
+                hostname: "game.example.com".to_string(),
+                expected: 1,
+                actual: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_backend_leaves_the_storage_untouched() {
+        let mut storage = Storage::new();
+        assert_eq!(storage.check_backend(&backend(0)), Ok(()));
+        assert!(storage.get_backends().is_empty());
+
+        storage.add_backend(backend(0)).unwrap();
+        assert!(matches!(
+            storage.check_backend(&backend(0)),
+            Err(StorageError::VersionConflict { .. })
+        ));
+        assert_eq!(storage.check_backend(&backend(1)), Ok(()));
+        assert_eq!(
+            storage.get_backend("game.example.com").unwrap().version(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_remove_backend_requires_version() {
+        let mut storage = Storage::new();
+        let created = storage.add_backend(backend(0)).unwrap();
+
+        assert!(storage.remove_backend("game.example.com", 0).is_err());
+        assert!(storage
+            .remove_backend("game.example.com", created.version())
+            .is_ok());
+        assert!(storage.get_backend("game.example.com").is_none());
+    }
+
+    #[test]
+    fn test_subscribe_receives_changes() {
+        let mut storage = Storage::new();
+        let mut changes = storage.subscribe();
+
+        let created = storage.add_backend(backend(0)).unwrap();
+        storage
+            .remove_backend("game.example.com", created.version())
+            .unwrap();
+
+        assert!(matches!(changes.try_recv(), Ok(BackendChange::Put(b)) if b.version() == 1));
+        assert!(matches!(changes.try_recv(), Ok(BackendChange::Delete(b)) if b.version() == 1));
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_apply_is_atomic() {
+        let mut storage = Storage::new();
+        storage.add_backend(backend(0)).unwrap();
+
+        let mut other = backend(0);
+        other.hostname = "other.example.com".to_string();
+
+        // the second change is based on a stale version, so nothing must be applied
+        let err = storage
+            .apply(vec![
+                BackendChange::Put(other.clone()),
+                BackendChange::Put(backend(0)),
+            ])
+            .unwrap_err();
+
+        assert_eq!(
+            err.root_cause(),
+            &StorageError::VersionConflict {
+                hostname: "game.example.com".to_string(),
+                expected: 1,
+                actual: 0,
+            }
+        );
+        assert!(storage.get_backend("other.example.com").is_none());
+
+        let applied = storage
+            .apply(vec![
+                BackendChange::Put(other),
+                BackendChange::Delete(backend(1)),
+            ])
+            .unwrap();
+
+        assert_eq!(applied.len(), 2);
+        assert_eq!(applied[0].backend().version(), 2);
+        assert!(storage.get_backend("game.example.com").is_none());
+    }
+
+    #[test]
+    fn test_restore_keeps_revision_monotonic() {
+        let mut source = Storage::new();
+        source.add_backend(backend(0)).unwrap();
+        let snapshot = source.snapshot();
+
+        let mut target = Storage::new();
+        target.restore(snapshot);
+
+        assert_eq!(
+            target.get_backend("game.example.com").map(|b| b.version()),
+            Some(1)
+        );
+        assert_eq!(target.add_backend(backend(1)).unwrap().version(), 2);
+    }
+
+    #[test]
+    fn test_restore_backend_from_tombstone() {
+        let mut storage = Storage::new();
+        storage.set_tombstone_retention(Some(Duration::from_secs(60)));
+
+        let created = storage.add_backend(backend(0)).unwrap();
+        storage
+            .remove_backend("game.example.com", created.version())
+            .unwrap();
+
+        let restored = storage.restore_backend("game.example.com").unwrap();
+
+        assert_eq!(restored.version(), 3);
+        assert!(storage.get_backend("game.example.com").is_some());
+        assert!(storage.restore_backend("game.example.com").is_err());
+    }
+
+    #[test]
+    fn test_restore_backend_expired_tombstone_err() {
+        let mut storage = Storage::new();
+        storage.set_tombstone_retention(Some(Duration::ZERO));
+
+        let created = storage.add_backend(backend(0)).unwrap();
+        storage
+            .remove_backend("game.example.com", created.version())
+            .unwrap();
+
+        let err = storage.restore_backend("game.example.com").unwrap_err();
+
+        assert_eq!(err, StorageError::NotFound("game.example.com".to_string()));
+    }
+
+    #[test]
+    fn test_add_backend_quota_exceeded_err() {
+        let mut storage = Storage::new();
+        storage.set_quotas(Quotas {
+            max_backends: None,
+            max_backends_per_label: BTreeMap::from([("tenant".to_string(), 1)]),
+        });
+
+        let mut first = backend(0);
+        first.labels.insert("tenant".to_string(), "a".to_string());
+        let mut second = first.clone();
+        second.hostname = "other.example.com".to_string();
+
+        let created = storage.add_backend(first).unwrap();
+        let err = storage.add_backend(second.clone()).unwrap_err();
+
+        assert!(matches!(err, StorageError::QuotaExceeded(_)));
+
+        // a batch freeing the quota first is accepted
+        assert!(storage
+            .apply(vec![
+                BackendChange::Delete(created),
+                BackendChange::Put(second)
+            ])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_routing_table_follows_changes() {
+        let mut storage = Storage::new();
+        let routes = storage.routing_table();
+
+        let created = storage.add_backend(backend(0)).unwrap();
+        assert!(routes.load().get_backend("game.example.com").is_some());
+
+        storage
+            .remove_backend("game.example.com", created.version())
+            .unwrap();
+        assert!(routes.load().get_backend("game.example.com").is_none());
+    }
+
+    #[test]
+    fn test_routing_table_prefers_the_port_of_the_handshake() {
+        let mut storage = Storage::new();
+        let routes = storage.routing_table();
+
+        let mut survival = backend(0);
+        survival.hostname = "game.example.com:25566".to_string();
+        survival.redirect_ip = "192.168.1.11".to_string();
+        storage.add_backend(backend(0)).unwrap();
+        storage.add_backend(survival).unwrap();
+
+        let routes = routes.load();
+        let routed = |port| routes.get_backend_on_port("game.example.com", port);
+        assert_eq!(routed(25566).unwrap().redirect_ip(), "192.168.1.11");
+        assert_eq!(routed(25565).unwrap().redirect_ip(), "192.168.1.10");
+        assert!(routes
+            .get_backend_on_port("other.example.com", 25566)
+            .is_none());
+    }
+
+    #[test]
+    fn test_static_routes_are_read_only() {
+        let mut storage = Storage::new();
+        let dynamic = storage.add_backend(backend(0)).unwrap();
+
+        storage.set_static_routes(vec![backend(0)]);
+        let stored = storage.get_backend("game.example.com").unwrap().clone();
+        assert!(stored.read_only());
+        assert!(stored.version() > dynamic.version());
+
+        let err = storage.add_backend(backend(stored.version())).unwrap_err();
+        assert_eq!(err, StorageError::ReadOnly("game.example.com".to_string()));
+        assert!(storage
+            .remove_backend("game.example.com", stored.version())
+            .is_err());
+        assert!(storage.snapshot().backends.is_empty());
+
+        // setting the same routes again keeps their version
+        storage.set_static_routes(vec![backend(0)]);
+        assert_eq!(
+            storage.get_backend("game.example.com").unwrap().version(),
+            stored.version()
+        );
+
+        storage.set_static_routes(Vec::new());
+        assert!(storage.get_backend("game.example.com").is_none());
+    }
+
+    #[test]
+    fn test_access_rules_are_published_and_restored() {
+        let mut storage = Storage::new();
+        let access = storage.access_list();
+        let ban = AccessRule {
+            kind: AccessKind::Ban,
+            subject: "Griefer".parse().unwrap(),
+            reason: "griefing".to_string(),
+            created_at_ms: now_ms(),
+            expires_at_ms: None,
+        };
+        storage.put_access_rule(ban.clone());
+        // an expired rule is dropped at the next change
+        storage.put_access_rule(AccessRule {
+            subject: "10.0.0.1".parse().unwrap(),
+            expires_at_ms: Some(1),
+            ..ban.clone()
+        });
+
+        let ip = "10.0.0.1".parse().unwrap();
+        assert_eq!(
+            access.load().ban(&ip, Some("griefer"), now_ms()),
+            Some(&ban)
+        );
+        assert!(access.load().ban(&ip, None, now_ms()).is_none());
+        assert_eq!(storage.access_rules(), vec![ban.clone()]);
+
+        let mut target = Storage::new();
+        target.restore(storage.snapshot());
+        assert_eq!(target.access_rules(), vec![ban.clone()]);
+
+        let subject = AccessSubject::Username("GRIEFER".to_string());
+        assert_eq!(
+            storage.remove_access_rule(AccessKind::Ban, &subject),
+            Ok(ban)
+        );
+        assert!(access.load().ban(&ip, Some("griefer"), now_ms()).is_none());
+        assert!(matches!(
+            storage.remove_access_rule(AccessKind::Ban, &subject),
+            Err(StorageError::AccessRuleNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_status_assets_are_published_and_restored() {
+        let mut storage = Storage::new();
+        let assets = storage.status_assets_handle();
+        let asset = StatusAsset {
+            hostname: "lobby.example.com".to_string(),
+            motd: Some("{message}".to_string()),
+            favicon: None,
+        };
+        storage.put_status_asset(asset.clone());
+        assert_eq!(assets.load().get("lobby.example.com"), Some(&asset));
+
+        let mut target = Storage::new();
+        target.restore(storage.snapshot());
+        assert_eq!(target.status_assets(), vec![asset.clone()]);
+
+        assert_eq!(storage.remove_status_asset("lobby.example.com"), Ok(asset));
+        assert!(assets.load().get("lobby.example.com").is_none());
+        assert_eq!(
+            storage.remove_status_asset("lobby.example.com"),
+            Err(StorageError::StatusAssetNotFound(
+                "lobby.example.com".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_backend_totals_are_checkpointed_and_restored() {
+        let mut storage = Storage::new();
+        let totals = BTreeMap::from([(
+            "lobby.example.com".to_string(),
+            Totals {
+                joins: 3,
+                bytes_in: 100,
+                bytes_out: 2000,
+            },
+        )]);
+        storage.checkpoint_totals(totals.clone());
+        assert_eq!(storage.backend_totals(), totals);
+
+        let mut target = Storage::new();
+        target.restore(storage.snapshot());
+        assert_eq!(target.backend_totals(), totals);
+
+        target.restore(Snapshot::default());
+        assert!(target.backend_totals().is_empty());
+    }
+
+    #[test]
+    fn test_select_backends_by_labels() {
+        let mut storage = Storage::new();
+        for (hostname, env) in [("a.example.com", "prod"), ("b.example.com", "dev")] {
+            let mut backend = Backend::new(hostname.to_string(), "127.0.0.1".to_string(), 25565);
+            backend.labels.insert("env".to_string(), env.to_string());
+            storage.add_backend(backend).unwrap();
+        }
+
+        let selected = storage.select_backends(&"env=prod".parse().unwrap());
+        assert_eq!(
+            selected.iter().map(Backend::hostname).collect::<Vec<_>>(),
+            vec!["a.example.com"]
+        );
+        assert_eq!(storage.select_backends(&LabelSelector::default()).len(), 2);
+    }
 }
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use config::LimitsConfig;
+use shared::models::backend::Backend;
+
+use crate::StorageError;
+
+/// Limits on the number of backends the storage accepts
+///
+/// Properties:
+///
+/// * `max_backends`: The maximum number of backends, none for unlimited.
+/// * `max_backends_per_label`: The maximum number of backends sharing the same value of a label,
+///   by label key (e.g. `tenant` limits the backends of every tenant).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quotas {
+    pub max_backends: Option<usize>,
+    pub max_backends_per_label: BTreeMap<String, usize>,
+}
+
+impl From<&LimitsConfig> for Quotas {
+    fn from(limits: &LimitsConfig) -> Self {
+        Self {
+            max_backends: limits.max_backends,
+            max_backends_per_label: limits.max_backends_per_label.clone(),
+        }
+    }
+}
+
+impl Quotas {
+    /// It checks whether any quota is set
+    ///
+    /// Returns:
+    ///
+    /// true if no quota is set
+    pub fn is_unlimited(&self) -> bool {
+        self.max_backends.is_none() && self.max_backends_per_label.is_empty()
+    }
+
+    /// It checks that a change of the backends doesn't exceed the quotas
+    ///
+    /// Only the counts growing with the change are checked, so lowering a quota below
+    /// the current count doesn't prevent updating or deleting the existing backends.
+    ///
+    /// Arguments:
+    ///
+    /// * `before` - The backends before the change
+    /// * `after` - The backends after the change
+    ///
+    /// Returns:
+    ///
+    /// A Result<(), StorageError>
+    pub fn check<'a>(
+        &'a self,
+        before: impl IntoIterator<Item = &'a Backend>,
+        after: impl IntoIterator<Item = &'a Backend>,
+    ) -> Result<(), StorageError> {
+        let before = self.count(before);
+        let after = self.count(after);
+
+        for (group, count) in &after {
+            let limit = match group {
+                None => self.max_backends,
+                Some((key, _)) => self.max_backends_per_label.get(*key).copied(),
+            };
+            let previous = before.get(group).copied().unwrap_or(0);
+
+            match limit {
+                Some(limit) if *count > limit && *count > previous => {
+                    return Err(StorageError::QuotaExceeded(match group {
+                        None => format!("at most {} backends", limit),
+                        Some((key, value)) => {
+                            format!("at most {} backends with label {}={}", limit, key, value)
+                        }
+                    }))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// It counts the backends in total (`None`) and by value of the limited labels
+    ///
+    /// Arguments:
+    ///
+    /// * `backends` - The backends to count
+    ///
+    /// Returns:
+    ///
+    /// The counts by group
+    fn count<'a>(
+        &'a self,
+        backends: impl IntoIterator<Item = &'a Backend>,
+    ) -> BTreeMap<Option<(&'a str, &'a str)>, usize> {
+        let mut counts = BTreeMap::new();
+
+        for backend in backends {
+            *counts.entry(None).or_insert(0) += 1;
+
+            for (key, value) in backend.labels() {
+                if self.max_backends_per_label.contains_key(key) {
+                    *counts
+                        .entry(Some((key.as_str(), value.as_str())))
+                        .or_insert(0) += 1;
+                }
+            }
+        }
+
+        counts
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arc_swap::ArcSwap;
+use metrics::storage::StorageMetrics;
+use shared::models::backend::Backend;
+
+/// A handle on the latest routing table published by the storage
+///
+/// Loading it never blocks, even while the storage is being changed. The connection handlers
+/// only ever route through it, never through the storage, so another source of the backends must
+/// publish its own tables here rather than be read by the connections.
+pub type RoutingHandle = Arc<ArcSwap<RoutingTable>>;
+
+/// An immutable view of the backends, indexed by hostname, used to route connections
+///
+/// A new table is built and published by the storage after every change, the
+/// connection handlers only ever read it.
+#[derive(Debug)]
+pub struct RoutingTable {
+    backends: HashMap<String, Backend>,
+    metrics: StorageMetrics,
+}
+
+impl RoutingTable {
+    /// Creates a new routing table from a list of backends
+    ///
+    /// Arguments:
+    ///
+    /// * `backends` - The backends to route to
+    /// * `metrics` - The metrics to record the lookups into
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new<'a>(
+        backends: impl IntoIterator<Item = &'a Backend>,
+        metrics: StorageMetrics,
+    ) -> Self {
+        Self {
+            backends: backends
+                .into_iter()
+                .map(|backend| (backend.hostname().to_string(), backend.clone()))
+                .collect(),
+            metrics,
+        }
+    }
+
+    /// It returns the backend to route a hostname to
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname` - The hostname requested by the client
+    ///
+    /// Returns:
+    ///
+    /// The backend with the specified hostname
+    pub fn get_backend(&self, hostname: &str) -> Option<&Backend> {
+        let backend = self.backends.get(hostname);
+
+        self.metrics.lookup(backend.is_some());
+        backend
+    }
+
+    /// It returns the backend to route a hostname to, the one declared for the port of the
+    /// handshake as `hostname:port` first
+    ///
+    /// Arguments:
+    ///
+    /// * `hostname` - The hostname requested by the client
+    /// * `port` - The port of the handshake of the client
+    ///
+    /// Returns:
+    ///
+    /// The backend of the hostname and the port, or else of the hostname
+    pub fn get_backend_on_port(&self, hostname: &str, port: u16) -> Option<&Backend> {
+        let backend = self
+            .backends
+            .get(&format!("{}:{}", hostname, port))
+            .or_else(|| self.backends.get(hostname));
+
+        self.metrics.lookup(backend.is_some());
+        backend
+    }
+}
@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+
+use shared::{
+    models::{access_rule::AccessRule, backend::Backend, status_asset::StatusAsset},
+    stats::Totals,
+};
+
+/// A point-in-time copy of the whole state of the storage
+///
+/// Properties:
+///
+/// * `revision`: The revision of the storage when the snapshot was taken.
+/// * `backends`: All the backends, with their versions.
+/// * `access_rules`: The bans and the allows which didn't expire.
+/// * `status_assets`: The MOTDs and the favicons of the hostnames.
+/// * `backend_totals`: The cumulative totals of the backends, by hostname, as of the latest
+///   checkpoint.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub revision: u64,
+    pub backends: Vec<Backend>,
+    pub access_rules: Vec<AccessRule>,
+    pub status_assets: Vec<StatusAsset>,
+    pub backend_totals: BTreeMap<String, Totals>,
+}
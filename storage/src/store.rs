@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use config::LimitsConfig;
+use shared::{
+    models::{
+        access_rule::{AccessKind, AccessRule, AccessSubject},
+        backend::Backend,
+        selector::LabelSelector,
+        status_asset::StatusAsset,
+    },
+    stats::Totals,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    AccessHandle, AssetsHandle, BackendChange, RoutingHandle, Snapshot, Storage, StorageError,
+};
+
+/// Where the proxy stores its backends, its access rules and its status assets
+///
+/// The events of the control plane, the reloads of the configuration and the checkpoints of the
+/// totals go through it, behind a lock. The connections never do: they route through the handles
+/// it publishes, e.g. `BackendStore::routing_table`, which it must swap after every change.
+///
+/// `Storage`, in memory, is the store of the proxy unless the builder is given another one.
+pub trait BackendStore: Debug + Send + Sync {
+    /// It applies the limits of the configuration, the tombstone retention and the quotas
+    fn set_limits(&mut self, limits: &LimitsConfig);
+
+    /// It replaces the read-only backends of the static routes of the configuration
+    fn set_static_routes(&mut self, backends: Vec<Backend>);
+
+    /// It subscribes to the changes of the backends, made after the subscription
+    fn subscribe(&self) -> broadcast::Receiver<BackendChange>;
+
+    /// It returns the handle on the latest routing table, which the connections route through
+    fn routing_table(&self) -> RoutingHandle;
+
+    /// It returns the handle on the latest access list, which the connections are checked against
+    fn access_list(&self) -> AccessHandle;
+
+    /// It returns the handle on the latest status assets, which the kicks and the MOTDs use
+    fn status_assets_handle(&self) -> AssetsHandle;
+
+    /// It returns the access rules which didn't expire
+    fn access_rules(&self) -> Vec<AccessRule>;
+
+    /// It adds an access rule, or replaces the one of its kind and subject
+    fn put_access_rule(&mut self, rule: AccessRule) -> AccessRule;
+
+    /// It removes the access rule of a kind and a subject
+    fn remove_access_rule(
+        &mut self,
+        kind: AccessKind,
+        subject: &AccessSubject,
+    ) -> Result<AccessRule, StorageError>;
+
+    /// It returns the status assets of every hostname
+    fn status_assets(&self) -> Vec<StatusAsset>;
+
+    /// It adds the status asset of a hostname, or replaces it
+    fn put_status_asset(&mut self, asset: StatusAsset) -> StatusAsset;
+
+    /// It removes the status asset of a hostname
+    fn remove_status_asset(&mut self, hostname: &str) -> Result<StatusAsset, StorageError>;
+
+    /// It returns the cumulative totals of the backends, as of the latest checkpoint
+    fn backend_totals(&self) -> BTreeMap<String, Totals>;
+
+    /// It replaces the cumulative totals of the backends with the latest ones of the proxy
+    fn checkpoint_totals(&mut self, totals: BTreeMap<String, Totals>);
+
+    /// It adds a backend, or updates the one of its hostname, and returns it with its new version
+    fn add_backend(&mut self, backend: Backend) -> Result<Backend, StorageError>;
+
+    /// It tells whether a backend would be added, without adding it
+    fn check_backend(&self, backend: &Backend) -> Result<(), StorageError>;
+
+    /// It removes the backend of a hostname, at a version unless it is 0
+    fn remove_backend(&mut self, host: &str, version: u64) -> Result<(), StorageError>;
+
+    /// It brings back the deleted backend of a hostname, from its tombstone
+    fn restore_backend(&mut self, host: &str) -> Result<Backend, StorageError>;
+
+    /// It applies a batch of changes atomically, all or none of them
+    fn apply(&mut self, batch: Vec<BackendChange>) -> Result<Vec<BackendChange>, StorageError>;
+
+    /// It returns a copy of the whole state
+    fn snapshot(&self) -> Snapshot;
+
+    /// It replaces the whole state with a snapshot, the static routes excepted
+    fn restore(&mut self, snapshot: Snapshot);
+
+    /// It returns the backend of a hostname
+    fn get_backend(&self, host: &str) -> Option<&Backend>;
+
+    /// It returns every backend, by hostname
+    fn get_backends(&self) -> &BTreeMap<String, Backend>;
+
+    /// It returns the backends whose labels match a selector
+    fn select_backends(&self, selector: &LabelSelector) -> Vec<Backend>;
+}
+
+impl BackendStore for Storage {
+    fn set_limits(&mut self, limits: &LimitsConfig) {
+        Storage::set_limits(self, limits)
+    }
+
+    fn set_static_routes(&mut self, backends: Vec<Backend>) {
+        Storage::set_static_routes(self, backends)
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<BackendChange> {
+        Storage::subscribe(self)
+    }
+
+    fn routing_table(&self) -> RoutingHandle {
+        Storage::routing_table(self)
+    }
+
+    fn access_list(&self) -> AccessHandle {
+        Storage::access_list(self)
+    }
+
+    fn status_assets_handle(&self) -> AssetsHandle {
+        Storage::status_assets_handle(self)
+    }
+
+    fn access_rules(&self) -> Vec<AccessRule> {
+        Storage::access_rules(self)
+    }
+
+    fn put_access_rule(&mut self, rule: AccessRule) -> AccessRule {
+        Storage::put_access_rule(self, rule)
+    }
+
+    fn remove_access_rule(
+        &mut self,
+        kind: AccessKind,
+        subject: &AccessSubject,
+    ) -> Result<AccessRule, StorageError> {
+        Storage::remove_access_rule(self, kind, subject)
+    }
+
+    fn status_assets(&self) -> Vec<StatusAsset> {
+        Storage::status_assets(self)
+    }
+
+    fn put_status_asset(&mut self, asset: StatusAsset) -> StatusAsset {
+        Storage::put_status_asset(self, asset)
+    }
+
+    fn remove_status_asset(&mut self, hostname: &str) -> Result<StatusAsset, StorageError> {
+        Storage::remove_status_asset(self, hostname)
+    }
+
+    fn backend_totals(&self) -> BTreeMap<String, Totals> {
+        Storage::backend_totals(self)
+    }
+
+    fn checkpoint_totals(&mut self, totals: BTreeMap<String, Totals>) {
+        Storage::checkpoint_totals(self, totals)
+    }
+
+    fn add_backend(&mut self, backend: Backend) -> Result<Backend, StorageError> {
+        Storage::add_backend(self, backend)
+    }
+
+    fn check_backend(&self, backend: &Backend) -> Result<(), StorageError> {
+        Storage::check_backend(self, backend)
+    }
+
+    fn remove_backend(&mut self, host: &str, version: u64) -> Result<(), StorageError> {
+        Storage::remove_backend(self, host, version)
+    }
+
+    fn restore_backend(&mut self, host: &str) -> Result<Backend, StorageError> {
+        Storage::restore_backend(self, host)
+    }
+
+    fn apply(&mut self, batch: Vec<BackendChange>) -> Result<Vec<BackendChange>, StorageError> {
+        Storage::apply(self, batch)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Storage::snapshot(self)
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        Storage::restore(self, snapshot)
+    }
+
+    fn get_backend(&self, host: &str) -> Option<&Backend> {
+        Storage::get_backend(self, host)
+    }
+
+    fn get_backends(&self) -> &BTreeMap<String, Backend> {
+        Storage::get_backends(self)
+    }
+
+    fn select_backends(&self, selector: &LabelSelector) -> Vec<Backend> {
+        Storage::select_backends(self, selector)
+    }
+}
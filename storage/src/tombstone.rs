@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use shared::models::backend::Backend;
+
+/// A deleted backend kept around so the deletion can be undone
+///
+/// Properties:
+///
+/// * `backend`: The backend as it was when it was deleted.
+/// * `deleted_at`: When the backend was deleted.
+#[derive(Debug, Clone)]
+pub struct Tombstone {
+    pub backend: Backend,
+    pub deleted_at: Instant,
+}
+
+impl Tombstone {
+    /// Creates a new tombstone for a backend deleted now
+    ///
+    /// Arguments:
+    ///
+    /// * `backend` - The deleted backend
+    ///
+    /// Returns:
+    ///
+    /// A new instance of the struct.
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            deleted_at: Instant::now(),
+        }
+    }
+
+    /// It checks whether the tombstone outlived the retention window
+    ///
+    /// Arguments:
+    ///
+    /// * `retention` - How long tombstones are kept
+    ///
+    /// Returns:
+    ///
+    /// true if the backend can't be restored anymore
+    pub fn is_expired(&self, retention: Duration) -> bool {
+        self.deleted_at.elapsed() >= retention
+    }
+}
@@ -0,0 +1,61 @@
+use std::net::SocketAddr;
+
+use config::ProxyConfig;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use protocol::packets::serverbound::handshake::NextState;
+use testkit::{route, FakeClient, FakeServer, TestProxy};
+
+/// The bytes relayed by every iteration
+const RELAYED: usize = 1024 * 1024;
+
+/// The bytes sent before their echo is read, below the socket buffers so nothing blocks
+const CHUNK: usize = 64 * 1024;
+
+/// It sends the relayed bytes in chunks, reading the echo of each of them
+///
+/// Arguments:
+///
+/// * `addr`: The address of the proxy, or of the server for the baseline.
+async fn relay(addr: SocketAddr) {
+    let mut client = FakeClient::connect(addr, "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let chunk = vec![42u8; CHUNK];
+    for _ in 0..RELAYED / CHUNK {
+        client.send(&chunk).await.unwrap();
+        client.receive(CHUNK).await.unwrap();
+    }
+}
+
+fn loopback(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (server, proxy) = runtime.block_on(async {
+        let server = FakeServer::start("").await.unwrap();
+        let proxy = TestProxy::start(ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        (server, proxy)
+    });
+
+    let mut group = c.benchmark_group("relay");
+    group.throughput(Throughput::Bytes(RELAYED as u64 * 2));
+    // the echo server alone, what the proxy adds is the difference
+    group.bench_function("direct", |b| {
+        b.to_async(&runtime).iter(|| relay(server.addr()))
+    });
+    group.bench_function("proxy", |b| {
+        b.to_async(&runtime).iter(|| relay(proxy.addr()))
+    });
+    group.finish();
+
+    runtime.block_on(async move {
+        drop(proxy);
+        drop(server);
+    });
+}
+
+criterion_group!(benches, loopback);
+criterion_main!(benches);
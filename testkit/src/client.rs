@@ -0,0 +1,160 @@
+use std::net::SocketAddr;
+
+use anyhow::{anyhow, Result};
+use protocol::{
+    packets::{
+        clientbound::pong::Pong,
+        serverbound::{handshake::NextState, ping::Ping},
+    },
+    read_packet, read_string, read_var_int, write_packet, write_string, write_var_int,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::PROTOCOL_VERSION;
+
+/// The maximum length of the packets read by the fake client
+const MAX_PACKET_SIZE: usize = 32 * 1024;
+
+/// A Minecraft client connecting to the proxy
+///
+/// Properties:
+///
+/// * `stream`: The connection to the proxy, right after the handshake.
+#[derive(Debug)]
+pub struct FakeClient {
+    stream: TcpStream,
+}
+
+impl FakeClient {
+    /// It connects to the proxy and sends the handshake
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address of the proxy.
+    /// * `hostname`: The hostname the client asks for.
+    /// * `next_state`: `Status` to ping the server list, `Login` to join, `Transfer` to join after a transfer.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn connect(addr: SocketAddr, hostname: &str, next_state: NextState) -> Result<Self> {
+        Self::connect_with_version(addr, hostname, next_state, PROTOCOL_VERSION).await
+    }
+
+    /// It connects to the proxy and sends the handshake of another protocol version
+    ///
+    /// Arguments:
+    ///
+    /// * `addr`: The address of the proxy.
+    /// * `hostname`: The hostname the client asks for.
+    /// * `next_state`: What the client connects for, see `FakeClient::connect`.
+    /// * `version`: The protocol version of the client, e.g. 766 to be transferred.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn connect_with_version(
+        addr: SocketAddr,
+        hostname: &str,
+        next_state: NextState,
+        version: i32,
+    ) -> Result<Self> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+
+        let mut data = Vec::new();
+        write_var_int(&mut data, 0).await?;
+        write_var_int(&mut data, version).await?;
+        write_string(&mut data, hostname).await?;
+        data.extend_from_slice(&addr.port().to_be_bytes());
+        let next_state = match next_state {
+            NextState::Status => 1,
+            NextState::Login => 2,
+            NextState::Transfer => 3,
+        };
+        write_var_int(&mut data, next_state).await?;
+        write_packet(&mut stream, &data).await?;
+
+        Ok(Self { stream })
+    }
+
+    /// It asks for the status of the server, after a `Status` handshake
+    ///
+    /// Returns:
+    ///
+    /// A Result with the JSON of the status
+    pub async fn status(&mut self) -> Result<String> {
+        write_packet(&mut self.stream, &[0]).await?;
+        self.read_string_packet().await
+    }
+
+    /// It pings the server, after its status
+    ///
+    /// Arguments:
+    ///
+    /// * `payload`: The payload of the ping.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the payload of the pong
+    pub async fn ping(&mut self, payload: i64) -> Result<i64> {
+        Ping::new(payload).write(&mut self.stream).await?;
+        Ok(Pong::read(&mut self.stream).await?.payload())
+    }
+
+    /// It reads the reason the player was kicked, after a `Login` handshake
+    ///
+    /// Returns:
+    ///
+    /// A Result with the JSON of the reason
+    pub async fn kick_reason(&mut self) -> Result<String> {
+        self.read_string_packet().await
+    }
+
+    /// It sends raw bytes to the proxy
+    ///
+    /// Arguments:
+    ///
+    /// * `data`: The bytes to send.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    pub async fn send(&mut self, data: &[u8]) -> Result<()> {
+        self.stream.write_all(data).await?;
+        Ok(())
+    }
+
+    /// It reads raw bytes from the proxy
+    ///
+    /// Arguments:
+    ///
+    /// * `length`: The number of bytes to read.
+    ///
+    /// Returns:
+    ///
+    /// A Result with the bytes
+    pub async fn receive(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; length];
+        self.stream.read_exact(&mut data).await?;
+        Ok(data)
+    }
+
+    /// It reads a packet whose only field is a string, as the status and the disconnect are
+    ///
+    /// Returns:
+    ///
+    /// A Result with the string
+    async fn read_string_packet(&mut self) -> Result<String> {
+        let mut packet = read_packet(&mut self.stream, MAX_PACKET_SIZE).await?;
+        let id = read_var_int(&mut packet).await?;
+        if id != 0 {
+            return Err(anyhow!("unexpected packet id: {}", id));
+        }
+
+        Ok(read_string(&mut packet, MAX_PACKET_SIZE).await?)
+    }
+}
@@ -0,0 +1,17 @@
+//! The harness of the end-to-end tests of the proxy
+//!
+//! It boots a `Proxy` on ephemeral ports in front of fake Minecraft servers, and connects fake
+//! clients to it, so the routing, the kicks and the forwarding are tested over real sockets.
+
+pub mod client;
+pub mod proxy;
+pub mod server;
+
+pub use crate::{
+    client::FakeClient,
+    proxy::{route, TestProxy},
+    server::{FakeServer, ReceivedHandshake},
+};
+
+/// The protocol version of the handshakes sent by the fake clients, 1.20.4
+pub const PROTOCOL_VERSION: i32 = 765;
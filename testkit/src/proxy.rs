@@ -0,0 +1,274 @@
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use config::{ProxyConfig, StaticRoute};
+use proxy::{builder::ProxyBuilder, handle::ProxyHandle, tap::TapEvent, Proxy};
+use shared::{
+    capture::Captures,
+    ddos::DdosMode,
+    models::forwarding::ForwardingMode,
+    sessions::{Sessions, Transfer},
+    stats::Stats,
+};
+use storage::BackendStore;
+use tokio::{
+    sync::{broadcast, RwLock},
+    time::timeout,
+};
+
+/// How long the proxy is waited for until it is ready
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A proxy running in the background of a test, on ephemeral ports of the loopback
+///
+/// Properties:
+///
+/// * `config`: The configuration the proxy was started with, its bound ports included.
+/// * `storage`: The storage of the backends of the proxy.
+/// * `taps`: A subscription to the packets of the tapped backends, taken before the start.
+/// * `captures`: The hostnames whose connections the proxy captures.
+/// * `sessions`: The players the proxy relays to every version of the backends.
+/// * `transfers`: The sender of the transfers of the players of a backend.
+/// * `stats`: The counters of every backend relayed by the proxy.
+/// * `ddos`: The DDoS mode of the proxy.
+/// * `handle`: The proxy, aborted when it is dropped.
+#[derive(Debug)]
+pub struct TestProxy {
+    config: ProxyConfig,
+    storage: Arc<RwLock<dyn BackendStore>>,
+    taps: broadcast::Receiver<TapEvent>,
+    captures: Arc<Captures>,
+    sessions: Arc<Sessions>,
+    transfers: broadcast::Sender<Transfer>,
+    stats: Arc<Stats>,
+    ddos: Arc<DdosMode>,
+    handle: Option<ProxyHandle>,
+}
+
+impl TestProxy {
+    /// It starts a proxy and waits until it accepts the connections
+    ///
+    /// Every server of the proxy listens on a free port of the loopback, whatever the
+    /// configuration says.
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration of the proxy, its routes and its messages.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn start(config: ProxyConfig) -> Result<Self> {
+        Self::start_with(config, |builder| builder).await
+    }
+
+    /// It starts a proxy built with more than its configuration, e.g. with an injected storage or
+    /// a shutdown signal, and waits until it is ready
+    ///
+    /// Arguments:
+    ///
+    /// * `config`: The configuration of the proxy, its routes and its messages.
+    /// * `build`: It adds to the builder of the proxy, whose addresses are set already.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn start_with<F>(mut config: ProxyConfig, build: F) -> Result<Self>
+    where
+        F: FnOnce(ProxyBuilder) -> ProxyBuilder,
+    {
+        config.validate()?;
+
+        // the builder binds port 0, which the validation of the configuration refuses
+        let ephemeral = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut bound = config.clone();
+        if bound.health.ping_port.is_some() {
+            bound.health.ping_port = Some(ephemeral.port());
+        }
+        let builder = Proxy::builder(bound)
+            .proxy_addr(ephemeral)
+            .listener_addr(ephemeral)
+            .metrics_addr(ephemeral)
+            .health_addr(ephemeral);
+        let proxy = build(builder).build()?;
+        let storage = proxy.storage();
+        let taps = proxy.taps();
+        let captures = proxy.captures();
+        let sessions = proxy.sessions();
+        let transfers = proxy.transfers();
+        let stats = proxy.stats();
+        let ddos = proxy.ddos_mode();
+        let handle = proxy.start().await?;
+        if timeout(START_TIMEOUT, handle.wait_ready()).await.is_err() {
+            handle.abort();
+            return Err(anyhow!("the proxy wasn't ready in time"));
+        }
+
+        let addrs = handle.addrs();
+        let loopback = ephemeral.ip().to_string();
+        config.proxy.host = loopback.clone();
+        config.listener.host = loopback.clone();
+        config.metrics.host = loopback.clone();
+        config.health.host = loopback;
+        config.proxy.port = addrs.proxy.port();
+        if let Some(listener) = addrs.listener {
+            config.listener.port = listener.port();
+        }
+        if let Some(metrics) = addrs.metrics {
+            config.metrics.port = metrics.port();
+        }
+        config.health.port = addrs.health.port();
+        config.health.ping_port = addrs.ping.map(|ping| ping.port());
+
+        Ok(Self {
+            config,
+            storage,
+            taps,
+            captures,
+            sessions,
+            transfers,
+            stats,
+            ddos,
+            handle: Some(handle),
+        })
+    }
+
+    /// It returns the address accepting the Minecraft clients
+    ///
+    /// Returns:
+    ///
+    /// A SocketAddr
+    pub fn addr(&self) -> SocketAddr {
+        self.handle().addrs().proxy
+    }
+
+    /// It returns the configuration the proxy was started with, its bound ports included
+    ///
+    /// Returns:
+    ///
+    /// A reference to the ProxyConfig
+    pub fn config(&self) -> &ProxyConfig {
+        &self.config
+    }
+
+    /// It returns the storage of the backends of the proxy
+    ///
+    /// Returns:
+    ///
+    /// An Arc<RwLock<dyn BackendStore>>
+    pub fn storage(&self) -> Arc<RwLock<dyn BackendStore>> {
+        self.storage.clone()
+    }
+
+    /// It subscribes to the packets of the tapped backends, from the start of the proxy
+    ///
+    /// Returns:
+    ///
+    /// A broadcast::Receiver<TapEvent>
+    pub fn taps(&self) -> broadcast::Receiver<TapEvent> {
+        self.taps.resubscribe()
+    }
+
+    /// It returns the hostnames whose connections the proxy captures
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Captures>
+    pub fn captures(&self) -> Arc<Captures> {
+        self.captures.clone()
+    }
+
+    /// It returns the players the proxy relays to every version of the backends
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Sessions>
+    pub fn sessions(&self) -> Arc<Sessions> {
+        self.sessions.clone()
+    }
+
+    /// It returns the sender of the transfers of the players of a backend
+    ///
+    /// Returns:
+    ///
+    /// A broadcast::Sender<Transfer>
+    pub fn transfers(&self) -> broadcast::Sender<Transfer> {
+        self.transfers.clone()
+    }
+
+    /// It returns the counters of every backend relayed by the proxy
+    ///
+    /// Returns:
+    ///
+    /// An Arc<Stats>
+    pub fn stats(&self) -> Arc<Stats> {
+        self.stats.clone()
+    }
+
+    /// It returns the DDoS mode of the proxy, so a test can flip it
+    ///
+    /// Returns:
+    ///
+    /// An Arc<DdosMode>
+    pub fn ddos_mode(&self) -> Arc<DdosMode> {
+        self.ddos.clone()
+    }
+
+    /// It shuts the proxy down, draining its connections, and waits until it stops
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>, an error when the proxy failed
+    pub async fn shutdown(mut self) -> Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle.shutdown().await,
+            None => Ok(()),
+        }
+    }
+
+    /// It returns the handle of the running proxy
+    ///
+    /// Returns:
+    ///
+    /// A reference to the ProxyHandle
+    fn handle(&self) -> &ProxyHandle {
+        self.handle.as_ref().expect("the proxy is running")
+    }
+}
+
+impl Drop for TestProxy {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.handle {
+            handle.abort();
+        }
+    }
+}
+
+/// It returns a static route from a hostname to a server, e.g. a `FakeServer`
+///
+/// Arguments:
+///
+/// * `hostname`: The hostname the players connect to.
+/// * `addr`: The address of the server.
+///
+/// Returns:
+///
+/// A StaticRoute
+pub fn route(hostname: &str, addr: SocketAddr) -> StaticRoute {
+    StaticRoute {
+        hostname: hostname.to_string(),
+        redirect_ip: addr.ip().to_string(),
+        redirect_port: addr.port(),
+        weight: 1,
+        max_connections: 0,
+        forwarding_mode: ForwardingMode::None,
+        motd: None,
+        labels: BTreeMap::new(),
+        preserve_hostname: false,
+        tap: false,
+        min_protocol_version: 0,
+        max_protocol_version: 0,
+        username_routes: BTreeMap::new(),
+        schedules: Vec::new(),
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use protocol::{
+    packets::serverbound::handshake::{Handshake, NextState},
+    read_packet, read_var_int, write_packet, write_string, write_var_int,
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// The maximum length of the packets read by the fake server after the handshake
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// A handshake received by the fake server, as forwarded by the proxy
+///
+/// Properties:
+///
+/// * `version`: The protocol version of the handshake.
+/// * `hostname`: The hostname, rewritten by the proxy unless the route preserves it.
+/// * `port`: The port of the handshake.
+/// * `next_state`: `Status`, `Login` or `Transfer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedHandshake {
+    pub version: i32,
+    pub hostname: String,
+    pub port: u16,
+    pub next_state: NextState,
+}
+
+/// An in-process Minecraft server standing in for a backend
+///
+/// It answers the status pings with its MOTD and the pings with their pong, and echoes what the
+/// players logging in send once their handshake is read.
+///
+/// Properties:
+///
+/// * `addr`: The address the server listens on.
+/// * `handshakes`: The handshakes received so far, the oldest first.
+/// * `task`: The accept loop, aborted when the server is dropped.
+#[derive(Debug)]
+pub struct FakeServer {
+    addr: SocketAddr,
+    handshakes: Arc<Mutex<Vec<ReceivedHandshake>>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeServer {
+    /// It starts a fake server on an ephemeral port of the loopback
+    ///
+    /// Arguments:
+    ///
+    /// * `motd`: The description answered to the status pings.
+    ///
+    /// Returns:
+    ///
+    /// A Result<Self>
+    pub async fn start(motd: &str) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let handshakes = Arc::new(Mutex::new(Vec::new()));
+        let status = format!(
+            "{{\"version\":{{\"name\":\"1.20.4\",\"protocol\":{}}},\"players\":{{\"max\":20,\"online\":0}},\"description\":{{\"text\":\"{}\"}}}}",
+            crate::PROTOCOL_VERSION,
+            motd
+        );
+
+        let received = handshakes.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let received = received.clone();
+                let status = status.clone();
+                tokio::spawn(async move {
+                    let _ = Self::serve(socket, received, &status).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            handshakes,
+            task,
+        })
+    }
+
+    /// It returns the address the server listens on
+    ///
+    /// Returns:
+    ///
+    /// A SocketAddr
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// It returns the handshakes received so far, the oldest first
+    ///
+    /// Returns:
+    ///
+    /// A Vec<ReceivedHandshake>
+    pub fn handshakes(&self) -> Vec<ReceivedHandshake> {
+        self.handshakes.lock().unwrap().clone()
+    }
+
+    /// It serves a connection, from its handshake until it is closed
+    ///
+    /// Arguments:
+    ///
+    /// * `socket`: The connection opened by the proxy.
+    /// * `received`: The handshakes received so far.
+    /// * `status`: The JSON answered to the status requests.
+    ///
+    /// Returns:
+    ///
+    /// A Result<()>
+    async fn serve(
+        mut socket: TcpStream,
+        received: Arc<Mutex<Vec<ReceivedHandshake>>>,
+        status: &str,
+    ) -> Result<()> {
+        socket.set_nodelay(true)?;
+        let handshake = Handshake::read(&mut socket).await?;
+        let next_state = handshake.next_state();
+        received.lock().unwrap().push(ReceivedHandshake {
+            version: handshake.version(),
+            hostname: handshake.hostname(),
+            port: handshake.port(),
+            next_state,
+        });
+
+        match next_state {
+            NextState::Status => loop {
+                let mut packet = read_packet(&mut socket, MAX_PACKET_SIZE).await?;
+                match read_var_int(&mut packet).await? {
+                    0 => {
+                        let mut data = Vec::new();
+                        write_var_int(&mut data, 0).await?;
+                        write_string(&mut data, status).await?;
+                        write_packet(&mut socket, &data).await?;
+                    }
+                    // the pong carries the payload of the ping back
+                    1 => {
+                        write_packet(&mut socket, packet.get_ref()).await?;
+                        return Ok(());
+                    }
+                    id => return Err(anyhow!("unexpected status packet id: {}", id)),
+                }
+            },
+            NextState::Login | NextState::Transfer => {
+                let (mut reader, mut writer) = socket.split();
+                tokio::io::copy(&mut reader, &mut writer).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
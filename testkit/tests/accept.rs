@@ -0,0 +1,87 @@
+//! The accepts failing for a lack of file descriptors, in a test binary of their own since the
+//! limit of file descriptors is the one of the whole process
+
+use std::{fs::File, time::Duration};
+
+use config::ProxyConfig;
+use protocol::packets::serverbound::handshake::NextState;
+use testkit::{route, FakeClient, FakeServer, TestProxy};
+use tokio::time::{sleep, timeout};
+
+/// It returns the limit of file descriptors of the process
+///
+/// Returns:
+///
+/// A libc::rlimit
+fn file_limit() -> libc::rlimit {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    assert_eq!(
+        unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) },
+        0
+    );
+    limit
+}
+
+/// It sets the limit of file descriptors of the process
+///
+/// Arguments:
+///
+/// * `limit`: The soft and the hard limits.
+fn set_file_limit(limit: &libc::rlimit) {
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, limit) }, 0);
+}
+
+#[tokio::test]
+async fn it_keeps_accepting_once_the_file_descriptors_are_back() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // the process runs out of file descriptors, but for the one of the client
+    let limit = file_limit();
+    let open = std::fs::read_dir("/proc/self/fd").unwrap().count() as u64;
+    set_file_limit(&libc::rlimit {
+        rlim_cur: open + 16,
+        ..limit
+    });
+    let mut files = Vec::new();
+    loop {
+        match File::open("/dev/null") {
+            Ok(file) => files.push(file),
+            Err(e) if e.raw_os_error() == Some(libc::EMFILE) => break,
+            Err(e) => panic!("failed to open a file: {}", e),
+        }
+    }
+    files.pop();
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert_eq!(
+        File::open("/dev/null").unwrap_err().raw_os_error(),
+        Some(libc::EMFILE)
+    );
+
+    // the proxy fails to accept the client for a while
+    sleep(Duration::from_millis(300)).await;
+    drop(files);
+    set_file_limit(&limit);
+
+    let status = timeout(Duration::from_secs(5), client.status())
+        .await
+        .expect("the proxy stopped accepting after the failed accepts")
+        .unwrap();
+    assert!(status.contains("hello from the lobby"));
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+}
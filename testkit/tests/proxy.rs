@@ -0,0 +1,1264 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use config::{route::StaticSchedule, DirectIpPolicy, OverloadPolicy, ProxyConfig};
+use listener::event::Event;
+use protocol::packets::serverbound::handshake::NextState;
+use proxy::{
+    fingerprint::{Classifier, Fingerprint, Verdict},
+    frames::{Direction, Frame, FrameObserver},
+};
+use shared::{
+    capture::{CaptureSettings, DEFAULT_CAPTURE_BYTES},
+    models::{
+        access_rule::{AccessKind, AccessRule},
+        backend::Backend,
+        status_asset::StatusAsset,
+    },
+    sessions::Transfer,
+    stats::Totals,
+};
+use storage::Storage;
+use testkit::{route, FakeClient, FakeServer, TestProxy};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    runtime::Builder,
+    sync::{mpsc, oneshot},
+    time::{sleep, timeout},
+};
+use ulid::Ulid;
+
+#[tokio::test]
+async fn it_forwards_the_status_of_the_backend() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+
+    // the hostname is rewritten to the address of the backend
+    let handshakes = server.handshakes();
+    assert_eq!(handshakes.len(), 1);
+    assert_eq!(handshakes[0].hostname, "127.0.0.1");
+    assert_eq!(handshakes[0].next_state, NextState::Status);
+}
+
+#[tokio::test]
+async fn it_relays_through_the_sockets_tuned_per_direction() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    };
+    // expedited forwarding to the clients, bigger buffers to the backends
+    config.sockets.client.dscp = Some(46);
+    config.sockets.client.send_buffer_bytes = Some(64 * 1024);
+    config.sockets.backend.dscp = Some(10);
+    config.sockets.backend.recv_buffer_bytes = Some(256 * 1024);
+    let proxy = TestProxy::start(config).await.unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+}
+
+#[tokio::test]
+async fn it_kicks_the_players_of_unknown_hostnames() {
+    let proxy = TestProxy::start(ProxyConfig::default()).await.unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "unknown.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains(&proxy.config().messages.backend_not_found));
+
+    let mut client = FakeClient::connect(proxy.addr(), "unknown.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.kick_reason().await.unwrap();
+    assert!(status.contains("\"description\""));
+
+    // the proxy answers the ping of its own status, so the server list shows a latency
+    let mut client = FakeClient::connect(proxy.addr(), "unknown.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client.status().await.unwrap().contains("\"description\""));
+    assert_eq!(
+        client.ping(0x018f_4a6b_2c10).await.unwrap(),
+        0x018f_4a6b_2c10
+    );
+}
+
+#[tokio::test]
+async fn it_relays_the_traffic_of_the_players() {
+    let server = FakeServer::start("").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let data = b"some packets of the player".repeat(1000);
+    client.send(&data).await.unwrap();
+    assert_eq!(client.receive(data.len()).await.unwrap(), data);
+    assert_eq!(server.handshakes()[0].next_state, NextState::Login);
+}
+
+#[tokio::test]
+async fn it_relays_the_players_transferred_by_a_server() {
+    let server = FakeServer::start("").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Transfer)
+        .await
+        .unwrap();
+    client.send(b"a transferred player").await.unwrap();
+    assert_eq!(client.receive(20).await.unwrap(), b"a transferred player");
+    assert_eq!(server.handshakes()[0].next_state, NextState::Transfer);
+}
+
+#[tokio::test]
+async fn it_hands_the_packets_to_the_observer_after_the_compression() {
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(Direction, Option<i32>, bool)>>);
+
+    impl FrameObserver for Recorder {
+        fn observe(&self, _: Ulid, frame: &Frame<'_>) {
+            let mut frames = self.0.lock().unwrap();
+            frames.push((frame.direction, frame.id, frame.compressed));
+        }
+    }
+
+    let server = FakeServer::start("").await.unwrap();
+    let recorder = Arc::new(Recorder::default());
+    let observer = recorder.clone();
+    let proxy = TestProxy::start_with(
+        ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        },
+        |builder| builder.frame_observer(observer),
+    )
+    .await
+    .unwrap();
+
+    // the server echoes, so it sets the compression with the packet the client sends
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let set_compression = b"\x03\x03\x80\x02";
+    client.send(set_compression).await.unwrap();
+    assert_eq!(client.receive(4).await.unwrap(), set_compression);
+    let packets = b"\x03\x00\x05\x01\x04\x90\x03\x78\x9c";
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+
+    let expected = [(Some(3), false), (Some(5), false), (None, true)];
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let frames = recorder.0.lock().unwrap().clone();
+        let of = |direction| {
+            frames
+                .iter()
+                .filter(|frame| frame.0 == direction)
+                .map(|frame| (frame.1, frame.2))
+                .collect::<Vec<_>>()
+        };
+        if of(Direction::Clientbound) == expected || Instant::now() > deadline {
+            assert_eq!(of(Direction::Clientbound), expected);
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn it_enforces_the_access_rules_of_the_storage() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let storage = proxy.storage();
+    let rule = |kind, subject: &str, reason: &str| AccessRule {
+        kind,
+        subject: subject.parse().unwrap(),
+        reason: reason.to_string(),
+        created_at_ms: 0,
+        expires_at_ms: None,
+    };
+
+    // a banned username is kicked with the reason of its ban
+    storage
+        .write()
+        .await
+        .put_access_rule(rule(AccessKind::Ban, "steve", "griefing"));
+    let login_start = b"\x07\x00\x05Steve";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(login_start).await.unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains("You are banned from this server"));
+    assert!(reason.contains("griefing"));
+    assert!(server.handshakes().is_empty());
+
+    // an allow wins over the ban
+    storage
+        .write()
+        .await
+        .put_access_rule(rule(AccessKind::Allow, "Steve", ""));
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(login_start).await.unwrap();
+    assert_eq!(
+        client.receive(login_start.len()).await.unwrap(),
+        login_start
+    );
+
+    // the connections of a banned address are dropped right after the accept
+    storage
+        .write()
+        .await
+        .put_access_rule(rule(AccessKind::Ban, "127.0.0.0/8", ""));
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client.status().await.is_err());
+
+    storage
+        .write()
+        .await
+        .remove_access_rule(AccessKind::Ban, &"127.0.0.0/8".parse().unwrap())
+        .unwrap();
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+}
+
+#[tokio::test]
+async fn it_rejects_the_connections_the_classifier_judges_as_bots() {
+    // the login starts lying about their version are rejected, the status pings flagged
+    #[derive(Default)]
+    struct LyingVersions(Mutex<Vec<(Option<String>, bool)>>);
+
+    impl Classifier for LyingVersions {
+        fn classify(&self, fingerprint: &Fingerprint<'_>) -> Verdict {
+            let mut fingerprints = self.0.lock().unwrap();
+            fingerprints.push((
+                fingerprint.username.map(str::to_string),
+                fingerprint.login_delay.is_some(),
+            ));
+            match fingerprint.fits_version {
+                Some(false) => Verdict::Reject("Please use a real client".to_string()),
+                Some(true) => Verdict::Allow,
+                None => Verdict::Flag("status".to_string()),
+            }
+        }
+    }
+
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let classifier = Arc::new(LyingVersions::default());
+    let judge = classifier.clone();
+    let proxy = TestProxy::start_with(
+        ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        },
+        |builder| builder.classifier(judge),
+    )
+    .await
+    .unwrap();
+
+    // a 1.8 login start, the username alone, from a client declaring 1.20.3
+    let login_start = b"\x07\x00\x05Steve";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(login_start).await.unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains("Please use a real client"));
+    assert!(server.handshakes().is_empty());
+
+    // the same login start from a 1.8 client is forwarded to the backend
+    let mut client =
+        FakeClient::connect_with_version(proxy.addr(), "lobby.example.com", NextState::Login, 47)
+            .await
+            .unwrap();
+    client.send(login_start).await.unwrap();
+    assert_eq!(
+        client.receive(login_start.len()).await.unwrap(),
+        login_start
+    );
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+
+    assert_eq!(
+        *classifier.0.lock().unwrap(),
+        vec![
+            (Some("Steve".to_string()), true),
+            (Some("Steve".to_string()), true),
+            (None, false),
+        ]
+    );
+    let metrics = scrape(&proxy).await;
+    assert!(metrics.contains("classified_connections_total{verdict=\"reject\"} 1"));
+    assert!(metrics.contains("classified_connections_total{verdict=\"flag\"} 1"));
+}
+
+#[tokio::test]
+async fn it_taps_the_packets_of_the_tapped_backends() {
+    let server = FakeServer::start("").await.unwrap();
+    let mut tapped = route("debug.example.com", server.addr());
+    tapped.tap = true;
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr()), tapped],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    let mut taps = proxy.taps();
+
+    // the packets of the other backends are copied without being looked at
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+
+    let mut client = FakeClient::connect(proxy.addr(), "debug.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+
+    let mut events = Vec::new();
+    while events.len() < 2 {
+        let event = timeout(Duration::from_secs(5), taps.recv()).await;
+        events.push(event.unwrap().unwrap());
+    }
+    events.sort_by_key(|event| event.direction == Direction::Clientbound);
+    assert_eq!(events[0].direction, Direction::Serverbound);
+    assert_eq!(events[1].direction, Direction::Clientbound);
+    for event in events {
+        assert_eq!(&*event.backend, "debug.example.com");
+        assert_eq!((event.id, event.size, event.login), (Some(0), 3, true));
+    }
+    assert!(taps.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn it_captures_the_connections_of_a_hostname() {
+    let server = FakeServer::start("").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("debug.example.com", server.addr())],
+        ..Default::default()
+    };
+    let directory = std::env::temp_dir().join(format!("kubecraft-captures-{}", Ulid::new()));
+    config.capture.directory = directory.clone();
+    let proxy = TestProxy::start(config).await.unwrap();
+    proxy.captures().start(
+        "debug.example.com",
+        CaptureSettings {
+            max_bytes: DEFAULT_CAPTURE_BYTES,
+            remaining: Some(1),
+        },
+    );
+
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(proxy.addr(), "debug.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+    drop(client);
+    // the capture stops after its only connection
+    assert!(proxy.captures().list().is_empty());
+
+    // the file is written once the connection is closed
+    let started = Instant::now();
+    let file = loop {
+        let files = std::fs::read_dir(&directory)
+            .map(|files| files.flatten().map(|file| file.path()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if let [file] = files.as_slice() {
+            break file.clone();
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "no capture was written"
+        );
+        sleep(Duration::from_millis(20)).await;
+    };
+    let name = file.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(name.contains("-debug.example.com-") && name.ends_with(".kccap"));
+
+    let data = std::fs::read(&file).unwrap();
+    assert!(data.starts_with(b"KCCAP01\n"));
+    assert!(data.windows(17).any(|bytes| bytes == b"debug.example.com"));
+    // both directions of the packets relayed are recorded
+    assert!(
+        data.windows(packets.len())
+            .filter(|bytes| bytes == packets)
+            .count()
+            >= 2
+    );
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[tokio::test]
+async fn it_kicks_the_players_of_unsupported_versions() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let mut newer = route("newer.example.com", server.addr());
+    newer.min_protocol_version = 766;
+    let mut current = route("lobby.example.com", server.addr());
+    current.min_protocol_version = 763;
+    current.max_protocol_version = 765;
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![newer, current],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // the fake clients speak 1.20.4
+    let mut client = FakeClient::connect(proxy.addr(), "newer.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains("This server requires 1.20.5 or newer"));
+
+    // the server list still shows the backend, with its own version
+    let mut client = FakeClient::connect(proxy.addr(), "newer.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client
+        .status()
+        .await
+        .unwrap()
+        .contains("hello from the lobby"));
+
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+}
+
+#[tokio::test]
+async fn it_routes_the_players_of_some_usernames_to_another_backend() {
+    let lobby = FakeServer::start("hello from the lobby").await.unwrap();
+    let staging = FakeServer::start("hello from the staging").await.unwrap();
+    let mut public = route("lobby.example.com", lobby.addr());
+    public
+        .username_routes
+        .insert("Staff".to_string(), "staging.example.com".to_string());
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![public, route("staging.example.com", staging.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // the login start of the player, its username matched regardless of its case
+    let login_start = b"\x07\x00\x05staff";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(login_start).await.unwrap();
+    assert_eq!(
+        client.receive(login_start.len()).await.unwrap(),
+        login_start
+    );
+    assert_eq!(staging.handshakes().len(), 1);
+    assert!(lobby.handshakes().is_empty());
+
+    let login_start = b"\x07\x00\x05Steve";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(login_start).await.unwrap();
+    assert_eq!(
+        client.receive(login_start.len()).await.unwrap(),
+        login_start
+    );
+    assert_eq!(lobby.handshakes().len(), 1);
+
+    // the server list shows the backend of the hostname
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client
+        .status()
+        .await
+        .unwrap()
+        .contains("hello from the lobby"));
+}
+
+#[tokio::test]
+async fn it_applies_the_maintenance_windows_of_the_backends() {
+    let lobby = FakeServer::start("hello from the lobby").await.unwrap();
+    let limbo = FakeServer::start("hello from the limbo").await.unwrap();
+    // a window opening every minute is always open
+    let window = |fallback: Option<&str>| StaticSchedule {
+        cron: "* * * * *".parse().unwrap(),
+        duration_mins: 1,
+        fallback: fallback.map(str::to_string),
+        motd: Some("Back at 5am".to_string()),
+    };
+    let mut restarting = route("lobby.example.com", lobby.addr());
+    restarting.schedules = vec![window(Some("limbo.example.com"))];
+    let mut closed = route("closed.example.com", lobby.addr());
+    closed.schedules = vec![window(None)];
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![restarting, closed, route("limbo.example.com", limbo.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+    assert_eq!(limbo.handshakes().len(), 1);
+    assert!(lobby.handshakes().is_empty());
+
+    let mut client = FakeClient::connect(proxy.addr(), "closed.example.com", NextState::Login)
+        .await
+        .unwrap();
+    assert!(client.kick_reason().await.unwrap().contains("Back at 5am"));
+    let mut client = FakeClient::connect(proxy.addr(), "closed.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client.status().await.unwrap().contains("Back at 5am"));
+    assert!(lobby.handshakes().is_empty());
+}
+
+#[tokio::test]
+async fn it_applies_the_direct_ip_policy_to_the_unknown_hostnames() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = |policy| {
+        let mut config = ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        };
+        config.direct_ip.policy = policy;
+        config.direct_ip.backend = "lobby.example.com".to_string();
+        TestProxy::start(config)
+    };
+
+    // the direct joins reach the default backend
+    let routing = proxy(DirectIpPolicy::Route).await.unwrap();
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(routing.addr(), "127.0.0.1", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+
+    let hinting = proxy(DirectIpPolicy::Hint).await.unwrap();
+    let mut client = FakeClient::connect(hinting.addr(), "127.0.0.1", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.kick_reason().await.unwrap();
+    assert!(status.contains(&hinting.config().messages.direct_ip_hint));
+
+    // the dropped connections are closed without an answer
+    let dropping = proxy(DirectIpPolicy::Drop).await.unwrap();
+    let mut client = FakeClient::connect(dropping.addr(), "127.0.0.1", NextState::Login)
+        .await
+        .unwrap();
+    assert!(client.receive(1).await.is_err());
+}
+
+#[tokio::test]
+async fn it_shields_the_backends_in_ddos_mode() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    };
+    config.direct_ip.policy = DirectIpPolicy::Route;
+    config.direct_ip.backend = "lobby.example.com".to_string();
+
+    // a login without a status ping first is kicked
+    config.ddos_mode.enabled = true;
+    let gated = TestProxy::start(config.clone()).await.unwrap();
+    let mut client = FakeClient::connect(gated.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains(&gated.config().messages.ping_required));
+
+    config.ddos_mode.enabled = false;
+    let proxy = TestProxy::start(config).await.unwrap();
+    proxy.ddos_mode().set(true);
+
+    // the status ping is answered by the proxy, the backend isn't dialed
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains(&proxy.config().messages.ddos_mode));
+    assert!(server.handshakes().is_empty());
+
+    // the unknown hostnames don't reach the default backend
+    let mut client = FakeClient::connect(proxy.addr(), "127.0.0.1", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains(&proxy.config().messages.backend_not_found));
+
+    // the player which pinged logs in, its next attempt right after is throttled
+    let packets = b"\x03\x00\x05\x01";
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(packets).await.unwrap();
+    assert_eq!(client.receive(packets.len()).await.unwrap(), packets);
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains(&proxy.config().messages.throttled));
+
+    // the backend answers its status pings again once the mode is off
+    proxy.ddos_mode().set(false);
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+}
+
+#[tokio::test]
+async fn it_answers_the_status_itself_with_the_assets_of_the_hostname() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+    proxy.storage().write().await.put_status_asset(StatusAsset {
+        hostname: "lobby.example.com".to_string(),
+        motd: Some("Lobby: {message}".to_string()),
+        favicon: Some("data:image/png;base64,iVBORw0KGgo=".to_string()),
+    });
+    proxy.ddos_mode().set(true);
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    let motd = format!("Lobby: {}", proxy.config().messages.ddos_mode);
+    assert!(status.contains(&motd));
+    assert!(status.contains("\"favicon\": \"data:image/png;base64,iVBORw0KGgo=\""));
+    assert!(server.handshakes().is_empty());
+
+    // the status pings answered by the backend keep its own MOTD
+    proxy.ddos_mode().set(false);
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.status().await.unwrap();
+    assert!(status.contains("hello from the lobby"));
+    assert!(!status.contains("favicon"));
+}
+
+#[tokio::test]
+async fn it_answers_the_unknown_hostnames_with_the_catch_all() {
+    let mut config = ProxyConfig::default();
+    config.catch_all.motd = Some("No server here".to_string());
+    config.catch_all.favicon = Some("data:image/png;base64,iVBORw0KGgo=".to_string());
+    config.catch_all.kick = Some("Unknown server\nCheck the address".to_string());
+    config.catch_all.hostnames = vec!["play.example.com".to_string()];
+    let proxy = TestProxy::start(config).await.unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "typo.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = client.kick_reason().await.unwrap();
+    assert!(status.contains("No server here"));
+    assert!(status.contains("\"favicon\": \"data:image/png;base64,iVBORw0KGgo=\""));
+
+    let mut client = FakeClient::connect(proxy.addr(), "typo.example.com", NextState::Login)
+        .await
+        .unwrap();
+    let reason = client.kick_reason().await.unwrap();
+    assert!(reason.contains(r"Unknown server\nCheck the address\n\nplay.example.com"));
+
+    let metrics = scrape(&proxy).await;
+    assert!(metrics.contains("unknown_hostnames_total{hostname=\"typo.example.com\"} 2"));
+}
+
+#[tokio::test]
+async fn it_answers_the_load_balancers_on_the_ping_port() {
+    let mut config = ProxyConfig::default();
+    config.health.ping_port = Some(25566);
+    let proxy = TestProxy::start(config).await.unwrap();
+    let ping_addr = SocketAddr::from(([127, 0, 0, 1], proxy.config().health.ping_port.unwrap()));
+
+    let mut client = FakeClient::connect(ping_addr, "lb.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client.status().await.unwrap().contains("kubecraft-proxy"));
+    assert_eq!(client.ping(42).await.unwrap(), 42);
+
+    // the checks are not the traffic of the players
+    let metrics = scrape(&proxy).await;
+    assert!(!metrics.contains("\nconnections_total{"));
+    assert!(!metrics.contains("\nhandshakes_total{"));
+    assert!(!metrics.contains("unknown_hostnames_total{"));
+}
+
+#[tokio::test]
+async fn it_routes_the_connections_while_the_storage_is_locked() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    // a control plane writing the storage doesn't hold the connections back
+    let storage = proxy.storage();
+    let _writing = storage.write().await;
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    let status = tokio::time::timeout(Duration::from_secs(5), client.status())
+        .await
+        .unwrap();
+    assert!(status.unwrap().contains("hello from the lobby"));
+}
+
+#[tokio::test]
+async fn it_keeps_the_players_on_the_backend_they_joined_until_they_leave() {
+    let previous = FakeServer::start("hello from the previous lobby")
+        .await
+        .unwrap();
+    let next = FakeServer::start("hello from the next lobby")
+        .await
+        .unwrap();
+    let proxy = TestProxy::start(ProxyConfig::default()).await.unwrap();
+    let storage = proxy.storage();
+    let lobby = |server: &FakeServer, version| Backend {
+        version,
+        ..Backend::new(
+            "lobby.example.com".to_string(),
+            server.addr().ip().to_string(),
+            server.addr().port(),
+        )
+    };
+    let stored = storage
+        .write()
+        .await
+        .add_backend(lobby(&previous, 0))
+        .unwrap();
+
+    let mut staying = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    staying.send(b"joined").await.unwrap();
+    assert_eq!(staying.receive(6).await.unwrap(), b"joined");
+
+    let updated = storage
+        .write()
+        .await
+        .add_backend(lobby(&next, stored.version()))
+        .unwrap();
+    let mut joining = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    joining.send(b"joined").await.unwrap();
+    assert_eq!(joining.receive(6).await.unwrap(), b"joined");
+
+    // the update only moved the next player
+    staying.send(b"still playing").await.unwrap();
+    assert_eq!(staying.receive(13).await.unwrap(), b"still playing");
+    assert_eq!(previous.handshakes().len(), 1);
+    assert_eq!(next.handshakes().len(), 1);
+    let versions: Vec<_> = proxy
+        .sessions()
+        .list()
+        .into_iter()
+        .map(|sessions| (sessions.version, sessions.backend, sessions.active))
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            (stored.version(), previous.addr().to_string(), 1),
+            (updated.version(), next.addr().to_string(), 1),
+        ]
+    );
+
+    drop(staying);
+    timeout(Duration::from_secs(5), async {
+        while proxy.sessions().list().len() > 1 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn it_transfers_the_players_of_a_backend_between_two_packets() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut moving =
+        FakeClient::connect_with_version(proxy.addr(), "lobby.example.com", NextState::Login, 766)
+            .await
+            .unwrap();
+    // the login acknowledged of the client, echoed back as the finish configuration
+    moving.send(b"\x01\x03").await.unwrap();
+    assert_eq!(moving.receive(2).await.unwrap(), b"\x01\x03");
+    let mut staying = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    staying.send(b"joined").await.unwrap();
+    assert_eq!(staying.receive(6).await.unwrap(), b"joined");
+
+    let relays = proxy
+        .transfers()
+        .send(Transfer {
+            backend: "lobby.example.com".to_string(),
+            hostname: "play.example.com".to_string(),
+            port: 25565,
+        })
+        .unwrap();
+    assert_eq!(relays, 1);
+
+    // the transfer of the play state of 1.20.5, then the connection is closed
+    assert_eq!(
+        moving.receive(22).await.unwrap(),
+        b"\x15\x73\x10play.example.com\xdd\xc7\x01"
+    );
+    assert!(moving.receive(1).await.is_err());
+    // the clients older than 1.20.5 stay
+    staying.send(b"still playing").await.unwrap();
+    assert_eq!(staying.receive(13).await.unwrap(), b"still playing");
+}
+
+#[tokio::test]
+async fn it_counts_the_joins_and_the_bytes_of_every_backend() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let proxy = TestProxy::start(ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(b"joined").await.unwrap();
+    assert_eq!(client.receive(6).await.unwrap(), b"joined");
+    let mut other = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    other.send(b"again").await.unwrap();
+    assert_eq!(other.receive(5).await.unwrap(), b"again");
+
+    let totals = proxy.stats().totals()["lobby.example.com"];
+    assert_eq!(totals.joins, 2);
+    // the handshake is written to the backend before the relay
+    assert_eq!((totals.bytes_in, totals.bytes_out), (11, 11));
+}
+
+#[tokio::test]
+async fn it_carries_on_from_the_totals_of_the_storage() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let mut storage = Storage::new();
+    let restored = Totals {
+        joins: 40,
+        bytes_in: 1000,
+        bytes_out: 9000,
+    };
+    storage.checkpoint_totals(BTreeMap::from([
+        ("lobby.example.com".to_string(), restored),
+        ("survival.example.com".to_string(), restored),
+    ]));
+    let proxy = TestProxy::start_with(
+        ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        },
+        |builder| builder.storage(storage),
+    )
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(b"joined").await.unwrap();
+    assert_eq!(client.receive(6).await.unwrap(), b"joined");
+
+    let cumulative = proxy.stats().cumulative_totals();
+    assert_eq!(
+        cumulative["lobby.example.com"],
+        Totals {
+            joins: 41,
+            bytes_in: 1006,
+            bytes_out: 9006,
+        }
+    );
+    assert_eq!(cumulative["survival.example.com"], restored);
+    // the rates only see what this proxy relayed
+    assert_eq!(proxy.stats().totals()["lobby.example.com"].joins, 1);
+}
+
+#[tokio::test]
+async fn it_serves_an_injected_storage_until_the_shutdown_signal() {
+    let server = FakeServer::start("hello from the storage").await.unwrap();
+    let mut storage = Storage::new();
+    storage
+        .add_backend(Backend::new(
+            "lobby.example.com".to_string(),
+            server.addr().ip().to_string(),
+            server.addr().port(),
+        ))
+        .unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let proxy = TestProxy::start_with(ProxyConfig::default(), |builder| {
+        builder.storage(storage).shutdown(async {
+            let _ = stopped.await;
+        })
+    })
+    .await
+    .unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client
+        .status()
+        .await
+        .unwrap()
+        .contains("hello from the storage"));
+
+    // once its sessions are closed, the proxy stops accepting
+    drop(client);
+    stop.send(()).unwrap();
+    for _ in 0..100 {
+        if TcpStream::connect(proxy.addr()).await.is_err() {
+            return;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the proxy still accepts the connections after its shutdown");
+}
+
+#[tokio::test]
+async fn it_stops_accepting_once_the_handle_shuts_it_down() {
+    let proxy = TestProxy::start(ProxyConfig::default()).await.unwrap();
+    let addr = proxy.addr();
+    assert_ne!(addr.port(), 0);
+    assert_ne!(proxy.config().health.port, 0);
+
+    proxy.shutdown().await.unwrap();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn it_sheds_the_connections_over_the_limit() {
+    let server = FakeServer::start("hello from the lobby").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    };
+    config.proxy.max_connections = Some(1);
+    config.proxy.overload = OverloadPolicy::Kick;
+    let proxy = TestProxy::start(config).await.unwrap();
+
+    let mut player = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    player.send(b"playing").await.unwrap();
+    assert_eq!(player.receive(7).await.unwrap(), b"playing");
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    assert!(client
+        .kick_reason()
+        .await
+        .unwrap()
+        .contains(&proxy.config().messages.overloaded));
+
+    // the ticket of the player is handed to the next client once it leaves
+    drop(player);
+    for _ in 0..100 {
+        let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+            .await
+            .unwrap();
+        if client
+            .status()
+            .await
+            .unwrap()
+            .contains("hello from the lobby")
+        {
+            return;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the proxy still sheds the connections once the player left");
+}
+
+#[tokio::test]
+async fn it_closes_the_connections_left_after_the_drain() {
+    let server = FakeServer::start("").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    };
+    config.health.drain_timeout_secs = 0;
+    let proxy = TestProxy::start(config).await.unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(b"still playing").await.unwrap();
+    assert_eq!(client.receive(13).await.unwrap(), b"still playing");
+
+    proxy.shutdown().await.unwrap();
+    let closed = tokio::time::timeout(Duration::from_secs(5), client.receive(1))
+        .await
+        .unwrap();
+    assert!(closed.is_err());
+}
+
+#[tokio::test]
+async fn it_keeps_relaying_the_sessions_while_it_drains() {
+    let server = FakeServer::start("").await.unwrap();
+    let mut config = ProxyConfig {
+        routes: vec![route("lobby.example.com", server.addr())],
+        ..Default::default()
+    };
+    config.health.drain_timeout_secs = 30;
+    let proxy = TestProxy::start(config).await.unwrap();
+    let addr = proxy.addr();
+
+    let mut client = FakeClient::connect(addr, "lobby.example.com", NextState::Login)
+        .await
+        .unwrap();
+    client.send(b"still playing").await.unwrap();
+    assert_eq!(client.receive(13).await.unwrap(), b"still playing");
+
+    let shutdown = tokio::spawn(proxy.shutdown());
+    sleep(Duration::from_millis(100)).await;
+    assert!(!shutdown.is_finished());
+
+    // the session is relayed until its player leaves, the new players are refused
+    client.send(b"still playing").await.unwrap();
+    assert_eq!(client.receive(13).await.unwrap(), b"still playing");
+    assert!(
+        FakeClient::connect(addr, "lobby.example.com", NextState::Status)
+            .await
+            .is_err()
+    );
+
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), shutdown)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn it_relays_on_the_runtime_of_the_data_plane() {
+    let data_plane = Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+        .unwrap();
+    let server = data_plane
+        .spawn(FakeServer::start("hello from the data plane"))
+        .await
+        .unwrap()
+        .unwrap();
+    let proxy = TestProxy::start_with(
+        ProxyConfig {
+            routes: vec![route("lobby.example.com", server.addr())],
+            ..Default::default()
+        },
+        |builder| builder.data_plane(data_plane.handle().clone()),
+    )
+    .await
+    .unwrap();
+
+    let addr = proxy.addr();
+    let status = || {
+        data_plane.spawn(async move {
+            let mut client =
+                FakeClient::connect(addr, "lobby.example.com", NextState::Status).await?;
+            client.status().await
+        })
+    };
+    // the accept loop is spawned once the control plane runs the proxy
+    status().await.unwrap().unwrap();
+
+    // the runtime of the control plane is stuck, the players are relayed anyway
+    let status = status();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !status.is_finished() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(status.is_finished());
+    assert!(status
+        .await
+        .unwrap()
+        .unwrap()
+        .contains("hello from the data plane"));
+
+    // the accept loop of the data plane is stopped on its own runtime, shortly after
+    proxy.shutdown().await.unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while TcpStream::connect(addr).await.is_ok() && Instant::now() < deadline {
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert!(TcpStream::connect(addr).await.is_err());
+    data_plane.shutdown_background();
+}
+
+#[tokio::test]
+async fn it_handles_the_events_of_another_control_plane() {
+    let server = FakeServer::start("hello from the events").await.unwrap();
+    let (tx, rx) = mpsc::channel(8);
+    let proxy = TestProxy::start_with(ProxyConfig::default(), |builder| {
+        builder.without_listener().event_channel(tx.clone(), rx)
+    })
+    .await
+    .unwrap();
+
+    let backend = Backend::new(
+        "lobby.example.com".to_string(),
+        server.addr().ip().to_string(),
+        server.addr().port(),
+    );
+    let (reply, replied) = oneshot::channel();
+    tx.send(Event::PutBackend(backend, reply)).await.unwrap();
+    replied.await.unwrap().unwrap();
+
+    let mut client = FakeClient::connect(proxy.addr(), "lobby.example.com", NextState::Status)
+        .await
+        .unwrap();
+    assert!(client
+        .status()
+        .await
+        .unwrap()
+        .contains("hello from the events"));
+}
+
+#[tokio::test]
+async fn it_runs_two_independent_proxies_in_one_process() {
+    let public = FakeServer::start("hello from the public lobby")
+        .await
+        .unwrap();
+    let staging = FakeServer::start("hello from the staging lobby")
+        .await
+        .unwrap();
+    let start = |hostname: &str, server: &FakeServer, entrypoint: &str| {
+        let config = ProxyConfig {
+            routes: vec![route(hostname, server.addr())],
+            ..Default::default()
+        };
+        let labels = HashMap::from([("entrypoint".to_string(), entrypoint.to_string())]);
+        TestProxy::start_with(config, |builder| builder.metrics_labels(labels))
+    };
+    let public_proxy = start("play.example.com", &public, "public").await.unwrap();
+    let staging_proxy = start("staging.example.com", &staging, "staging")
+        .await
+        .unwrap();
+
+    let mut client = FakeClient::connect(
+        staging_proxy.addr(),
+        "staging.example.com",
+        NextState::Status,
+    )
+    .await
+    .unwrap();
+    assert!(client
+        .status()
+        .await
+        .unwrap()
+        .contains("hello from the staging lobby"));
+
+    // the routes of one proxy are unknown to the other
+    let mut client =
+        FakeClient::connect(public_proxy.addr(), "staging.example.com", NextState::Login)
+            .await
+            .unwrap();
+    assert!(client
+        .kick_reason()
+        .await
+        .unwrap()
+        .contains(&public_proxy.config().messages.backend_not_found));
+
+    let metrics = scrape(&staging_proxy).await;
+    assert!(metrics.contains("entrypoint=\"staging\""));
+    assert!(!metrics.contains("entrypoint=\"public\""));
+}
+
+/// It reads the metrics a test proxy exposes
+async fn scrape(proxy: &TestProxy) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", proxy.config().metrics.port))
+        .await
+        .unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.0\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}